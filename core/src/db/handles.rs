@@ -0,0 +1,1807 @@
+use argon2::{
+    password_hash::{rand_core::OsRng, SaltString},
+    Argon2, PasswordHasher,
+};
+
+use rand::{distributions::Alphanumeric, Rng};
+use sqlx::{sqlite::SqliteQueryResult, Pool, Row, Sqlite};
+use tokio::task;
+
+use super::models::{AdvancedConfiguration, Configuration};
+use crate::db::models::{
+    AdBreak, AuditEntry, Channel, Companion, DownloadJob, GlobalSettings, HandoverNote, Node,
+    NodeChannel, OutputTarget, Permission, PlaylistVersion, Region, Role, RolePermissions, Script,
+    SpotPlay, TextPreset, TranscodeJob, TrimSuggestion, User,
+};
+use crate::utils::{
+    advanced_config::AdvancedConfig, config::PlayoutConfig, errors::ServiceError,
+    is_running_in_container, local_utc_offset, password_policy::PasswordPolicy,
+    time_machine::time_now,
+};
+
+pub async fn db_migrate(conn: &Pool<Sqlite>) -> Result<(), Box<dyn std::error::Error>> {
+    sqlx::migrate!("../migrations").run(conn).await?;
+
+    if select_global(conn).await.is_err() {
+        let secret: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(80)
+            .map(char::from)
+            .collect();
+        let shared = is_running_in_container().await;
+
+        let query = "CREATE TRIGGER global_row_count
+        BEFORE INSERT ON global
+        WHEN (SELECT COUNT(*) FROM global) >= 1
+        BEGIN
+            SELECT RAISE(FAIL, 'Database is already initialized!');
+        END;
+        INSERT INTO global(secret, shared) VALUES($1, $2);";
+
+        sqlx::query(query)
+            .bind(secret)
+            .bind(shared)
+            .execute(conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Newest migration, which this engine knows.
+pub fn latest_migration() -> i64 {
+    sqlx::migrate!("../migrations")
+        .iter()
+        .map(|m| m.version)
+        .max()
+        .unwrap_or_default()
+}
+
+/// Newest migration in the database.
+pub async fn select_migration(conn: &Pool<Sqlite>) -> Result<i64, sqlx::Error> {
+    let query = "SELECT COALESCE(MAX(version), 0) FROM _sqlx_migrations WHERE success = 1";
+
+    sqlx::query_scalar(query).fetch_one(conn).await
+}
+
+/// Consistent copy of the database into a new file.
+pub async fn vacuum_into(conn: &Pool<Sqlite>, path: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("VACUUM INTO $1")
+        .bind(path)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn integrity_check(conn: &Pool<Sqlite>) -> Result<String, sqlx::Error> {
+    sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_one(conn)
+        .await
+}
+
+/// Write the WAL into the database file and truncate it.
+pub async fn checkpoint(conn: &Pool<Sqlite>) -> Result<(), sqlx::Error> {
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Database answers a simple query.
+pub async fn check_connection(conn: &Pool<Sqlite>) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT 1").execute(conn).await?;
+
+    Ok(())
+}
+
+pub async fn select_global(conn: &Pool<Sqlite>) -> Result<GlobalSettings, sqlx::Error> {
+    let query =
+        "SELECT id, secret, logs, playlists, public, storage, shared, mail_smtp, mail_user, mail_password, mail_starttls FROM global WHERE id = 1";
+
+    sqlx::query_as(query).fetch_one(conn).await
+}
+
+pub async fn update_global(
+    conn: &Pool<Sqlite>,
+    global: GlobalSettings,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "UPDATE global SET logs = $2, playlists = $3, public = $4, storage = $5,
+            mail_smtp = $6, mail_user = $7, mail_password = $8, mail_starttls = $9  WHERE id = 1";
+
+    sqlx::query(query)
+        .bind(global.id)
+        .bind(global.logs)
+        .bind(global.playlists)
+        .bind(global.public)
+        .bind(global.storage)
+        .bind(global.mail_smtp)
+        .bind(global.mail_user)
+        .bind(global.mail_password)
+        .bind(global.mail_starttls)
+        .execute(conn)
+        .await
+}
+
+pub async fn select_channel(conn: &Pool<Sqlite>, id: &i32) -> Result<Channel, sqlx::Error> {
+    let query = "SELECT * FROM channels WHERE id = $1";
+    let mut result: Channel = sqlx::query_as(query).bind(id).fetch_one(conn).await?;
+
+    result.utc_offset = local_utc_offset();
+
+    Ok(result)
+}
+
+pub async fn select_related_channels(
+    conn: &Pool<Sqlite>,
+    user_id: Option<i32>,
+) -> Result<Vec<Channel>, sqlx::Error> {
+    let query = match user_id {
+        Some(id) => format!(
+            "SELECT c.id, c.name, c.preview_url, c.extra_extensions, c.active, c.public, c.playlists, c.storage, c.last_date, c.time_shift, c.timezone, c.public_embed, c.public_headers, c.storage_quota, c.public_nowplaying, c.nowplaying_token, c.maintenance, c.start_priority, c.cpu_affinity, c.nice, c.memory_limit, c.gpu FROM channels c
+                left join user_channels uc on uc.channel_id = c.id
+                left join user u on u.id = uc.user_id
+             WHERE u.id = {id} ORDER BY c.id ASC;"
+        ),
+        None => "SELECT * FROM channels ORDER BY id ASC;".to_string(),
+    };
+
+    let mut results: Vec<Channel> = sqlx::query_as(&query).fetch_all(conn).await?;
+
+    for result in &mut results {
+        result.utc_offset = local_utc_offset();
+    }
+
+    Ok(results)
+}
+
+pub async fn delete_user_channel(
+    conn: &Pool<Sqlite>,
+    user_id: i32,
+    channel_id: i32,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "DELETE FROM user_channels WHERE user_id = $1 AND channel_id = $2";
+
+    sqlx::query(query)
+        .bind(user_id)
+        .bind(channel_id)
+        .execute(conn)
+        .await
+}
+
+pub async fn update_channel(
+    conn: &Pool<Sqlite>,
+    id: i32,
+    channel: Channel,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query =
+        "UPDATE channels SET name = $2, preview_url = $3, extra_extensions = $4, public = $5, playlists = $6, storage = $7, public_embed = COALESCE($8, public_embed), public_headers = $9, storage_quota = $10, public_nowplaying = $11, nowplaying_token = $12, maintenance = $13, start_priority = $14, cpu_affinity = $15, nice = $16, memory_limit = $17, gpu = $18 WHERE id = $1";
+
+    sqlx::query(query)
+        .bind(id)
+        .bind(channel.name)
+        .bind(channel.preview_url)
+        .bind(channel.extra_extensions)
+        .bind(channel.public)
+        .bind(channel.playlists)
+        .bind(channel.storage)
+        .bind(channel.public_embed)
+        .bind(channel.public_headers)
+        .bind(channel.storage_quota)
+        .bind(channel.public_nowplaying)
+        .bind(channel.nowplaying_token)
+        .bind(channel.maintenance)
+        .bind(channel.start_priority)
+        .bind(channel.cpu_affinity)
+        .bind(channel.nice)
+        .bind(channel.memory_limit)
+        .bind(channel.gpu)
+        .execute(conn)
+        .await
+}
+
+pub async fn update_stat(
+    conn: &Pool<Sqlite>,
+    id: i32,
+    last_date: Option<String>,
+    time_shift: f64,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = match last_date {
+        Some(_) => "UPDATE channels SET last_date = $2, time_shift = $3 WHERE id = $1",
+        None => "UPDATE channels SET time_shift = $2 WHERE id = $1",
+    };
+
+    let mut q = sqlx::query(query).bind(id);
+
+    if last_date.is_some() {
+        q = q.bind(last_date);
+    }
+
+    q.bind(time_shift).execute(conn).await
+}
+
+pub async fn update_player(
+    conn: &Pool<Sqlite>,
+    id: i32,
+    active: bool,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "UPDATE channels SET active = $2 WHERE id = $1";
+
+    sqlx::query(query).bind(id).bind(active).execute(conn).await
+}
+
+pub async fn insert_channel(conn: &Pool<Sqlite>, channel: Channel) -> Result<Channel, sqlx::Error> {
+    let query = "INSERT INTO channels (name, preview_url, extra_extensions, public, playlists, storage, storage_quota) VALUES($1, $2, $3, $4, $5, $6, $7)";
+    let result = sqlx::query(query)
+        .bind(channel.name)
+        .bind(channel.preview_url)
+        .bind(channel.extra_extensions)
+        .bind(channel.public)
+        .bind(channel.playlists)
+        .bind(channel.storage)
+        .bind(channel.storage_quota)
+        .execute(conn)
+        .await?;
+
+    sqlx::query_as("SELECT * FROM channels WHERE id = $1")
+        .bind(result.last_insert_rowid())
+        .fetch_one(conn)
+        .await
+}
+
+pub async fn delete_channel(
+    conn: &Pool<Sqlite>,
+    id: &i32,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "DELETE FROM channels WHERE id = $1";
+
+    sqlx::query(query).bind(id).execute(conn).await
+}
+
+pub async fn select_last_channel(conn: &Pool<Sqlite>) -> Result<i32, sqlx::Error> {
+    let query = "select seq from sqlite_sequence WHERE name = 'channel';";
+
+    sqlx::query_scalar(query).fetch_one(conn).await
+}
+
+pub async fn select_configuration(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+) -> Result<Configuration, sqlx::Error> {
+    let query = "SELECT * FROM configurations WHERE channel_id = $1";
+
+    sqlx::query_as(query).bind(channel).fetch_one(conn).await
+}
+
+pub async fn insert_configuration(
+    conn: &Pool<Sqlite>,
+    channel_id: i32,
+    output_param: String,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "INSERT INTO configurations (channel_id, output_param) VALUES($1, $2)";
+
+    sqlx::query(query)
+        .bind(channel_id)
+        .bind(output_param)
+        .execute(conn)
+        .await
+}
+
+pub async fn update_configuration(
+    conn: &Pool<Sqlite>,
+    id: i32,
+    config: PlayoutConfig,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "UPDATE configurations SET general_stop_threshold = $2, mail_subject = $3, mail_recipient = $4, mail_level = $5, mail_interval = $6, logging_ffmpeg_level = $7, logging_ingest_level = $8, logging_detect_silence = $9, logging_ignore = $10, processing_mode = $11, processing_audio_only = $12, processing_copy_audio = $13, processing_copy_video = $14, processing_width = $15, processing_height = $16, processing_aspect = $17, processing_fps = $18, processing_add_logo = $19, processing_logo = $20, processing_logo_scale = $21, processing_logo_opacity = $22, processing_logo_position = $23, processing_audio_tracks = $24, processing_audio_track_index = $25, processing_audio_channels = $26, processing_volume = $27, processing_filter = $28, processing_vtt_enable = $29, processing_vtt_dummy = $30, ingest_enable = $31, ingest_param = $32, ingest_filter = $33, playlist_day_start = $34, playlist_length = $35, playlist_infinit = $36, storage_filler = $37, storage_extensions = $38, storage_shuffle = $39, text_add = $40, text_from_filename = $41, text_font = $42, text_style = $43, text_regex = $44, task_enable = $45, task_path = $46, output_mode = $47, output_param = $48, processing_transition = $49, processing_transition_frames = $50, output_s3_enable = $51, output_s3_endpoint = $52, output_s3_region = $53, output_s3_bucket = $54, output_s3_prefix = $55, output_s3_access_key = $56, output_s3_secret_key = $57, output_s3_concurrency = $58, processing_logos = $59, storage_artwork_path = $60, storage_artwork_api = $61, storage_artwork_field = $62, storage_trash_retention = $63, storage_filler_max_repeat = $64, storage_filler_fit = $65, storage_filler_exclude = $66, playlist_check_before = $67, playlist_fallback = $68, processing_dead_air_duration = $69, processing_dead_air_fallback = $70, processing_dead_air_audio = $71, processing_transition_audio_frames = $72, output_scte35 = $73, output_rating = $74, processing_subtitle_burn = $75, output_backup_param = $76, general_air_hours = $77, general_scripting = $78, storage_watch_folder = $79, processing_house_format = $80, mail_notifiers = $81, mail_repeat_limit = $82, processing_passthrough = $83, storage_object_store = $84, storage_pull_ingest = $85, text_widgets = $86, text_overlay = $87, output_radio = $88, processing_audio_track_map = $89, output_decklink = $90, ingest_auth = $91, ingest_preview = $92, ingest_whip = $93, storage_pre_cache = $94, output_delayed = $95, processing_aspect_policy = $96, output_broadcast = $97, processing_audio_downmix = $98, output_offline = $99, logging_level = $100, logging_retention = $101, storage_file_inline = $102, output_preview = $103, processing_audio_meter = $104, output_alarms = $105, output_item_metadata = $106 WHERE id = $1";
+
+    sqlx::query(query)
+        .bind(id)
+        .bind(config.general.stop_threshold)
+        .bind(config.mail.subject)
+        .bind(config.mail.recipient)
+        .bind(config.mail.mail_level.as_str())
+        .bind(config.mail.interval)
+        .bind(config.logging.ffmpeg_level)
+        .bind(config.logging.ingest_level)
+        .bind(config.logging.detect_silence)
+        .bind(config.logging.ignore_lines.join(";"))
+        .bind(config.processing.mode.to_string())
+        .bind(config.processing.audio_only)
+        .bind(config.processing.copy_audio)
+        .bind(config.processing.copy_video)
+        .bind(config.processing.width)
+        .bind(config.processing.height)
+        .bind(config.processing.aspect)
+        .bind(config.processing.fps)
+        .bind(config.processing.add_logo)
+        .bind(config.processing.logo)
+        .bind(config.processing.logo_scale)
+        .bind(config.processing.logo_opacity)
+        .bind(config.processing.logo_position)
+        .bind(config.processing.audio_tracks)
+        .bind(config.processing.audio_track_index)
+        .bind(config.processing.audio_channels)
+        .bind(config.processing.volume)
+        .bind(config.processing.custom_filter)
+        .bind(config.processing.vtt_enable)
+        .bind(config.processing.vtt_dummy)
+        .bind(config.ingest.enable)
+        .bind(config.ingest.input_param)
+        .bind(config.ingest.custom_filter)
+        .bind(config.playlist.day_start)
+        .bind(config.playlist.length)
+        .bind(config.playlist.infinit)
+        .bind(config.storage.filler)
+        .bind(config.storage.extensions.join(";"))
+        .bind(config.storage.shuffle)
+        .bind(config.text.add_text)
+        .bind(config.text.text_from_filename)
+        .bind(config.text.font)
+        .bind(config.text.style)
+        .bind(config.text.regex)
+        .bind(config.task.enable)
+        .bind(config.task.path.to_string_lossy().to_string())
+        .bind(config.output.mode.to_string())
+        .bind(config.output.output_param)
+        .bind(config.processing.transition.to_string())
+        .bind(config.processing.transition_frames)
+        .bind(config.output.s3_enable)
+        .bind(config.output.s3_endpoint)
+        .bind(config.output.s3_region)
+        .bind(config.output.s3_bucket)
+        .bind(config.output.s3_prefix)
+        .bind(config.output.s3_access_key)
+        .bind(config.output.s3_secret_key)
+        .bind(config.output.s3_concurrency)
+        .bind(serde_json::to_string(&config.processing.logos).unwrap_or_default())
+        .bind(config.storage.artwork_path)
+        .bind(config.storage.artwork_api)
+        .bind(config.storage.artwork_field)
+        .bind(config.storage.trash_retention)
+        .bind(config.storage.filler_max_repeat)
+        .bind(config.storage.filler_fit)
+        .bind(config.storage.filler_exclude)
+        .bind(config.playlist.check_before)
+        .bind(config.playlist.fallback.to_string())
+        .bind(config.processing.dead_air_duration)
+        .bind(config.processing.dead_air_fallback)
+        .bind(config.processing.dead_air_audio)
+        .bind(config.processing.transition_audio_frames)
+        .bind(config.output.scte35)
+        .bind(serde_json::to_string(&config.output.rating).unwrap_or_default())
+        .bind(config.processing.subtitle_burn)
+        .bind(config.output.backup_param)
+        .bind(serde_json::to_string(&config.general.air_hours).unwrap_or_default())
+        .bind(config.general.scripting)
+        .bind(serde_json::to_string(&config.storage.watch_folder).unwrap_or_default())
+        .bind(serde_json::to_string(&config.processing.house_format).unwrap_or_default())
+        .bind(serde_json::to_string(&config.mail.notifiers).unwrap_or_default())
+        .bind(config.mail.repeat_limit)
+        .bind(config.processing.passthrough)
+        .bind(serde_json::to_string(&config.storage.object_store).unwrap_or_default())
+        .bind(serde_json::to_string(&config.storage.pull_ingest).unwrap_or_default())
+        .bind(serde_json::to_string(&config.text.widgets).unwrap_or_default())
+        .bind(serde_json::to_string(&config.text.overlay).unwrap_or_default())
+        .bind(serde_json::to_string(&config.output.radio).unwrap_or_default())
+        .bind(serde_json::to_string(&config.processing.audio_track_map).unwrap_or_default())
+        .bind(serde_json::to_string(&config.output.decklink).unwrap_or_default())
+        .bind(serde_json::to_string(&config.ingest.auth).unwrap_or_default())
+        .bind(serde_json::to_string(&config.ingest.preview).unwrap_or_default())
+        .bind(serde_json::to_string(&config.ingest.whip).unwrap_or_default())
+        .bind(serde_json::to_string(&config.storage.pre_cache).unwrap_or_default())
+        .bind(serde_json::to_string(&config.output.delayed).unwrap_or_default())
+        .bind(config.processing.aspect_policy.to_string())
+        .bind(serde_json::to_string(&config.output.broadcast).unwrap_or_default())
+        .bind(config.processing.audio_downmix.to_string())
+        .bind(serde_json::to_string(&config.output.offline).unwrap_or_default())
+        .bind(config.logging.level)
+        .bind(serde_json::to_string(&config.logging.retention).unwrap_or_default())
+        .bind(config.storage.file_inline)
+        .bind(serde_json::to_string(&config.output.preview).unwrap_or_default())
+        .bind(config.processing.audio_meter)
+        .bind(serde_json::to_string(&config.output.alarms).unwrap_or_default())
+        .bind(config.output.item_metadata)
+        .execute(conn)
+        .await
+}
+
+pub async fn insert_advanced_configuration(
+    conn: &Pool<Sqlite>,
+    channel_id: i32,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "INSERT INTO advanced_configurations (channel_id) VALUES($1)";
+
+    sqlx::query(query).bind(channel_id).execute(conn).await
+}
+
+pub async fn update_advanced_configuration(
+    conn: &Pool<Sqlite>,
+    channel_id: i32,
+    config: AdvancedConfig,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "UPDATE advanced_configurations SET decoder_input_param = $2, decoder_output_param = $3, encoder_input_param = $4, ingest_input_param = $5, filter_deinterlace = $6, filter_pad_scale_w = $7, filter_pad_scale_h = $8, filter_pad_video = $9, filter_fps = $10, filter_scale = $11, filter_set_dar = $12, filter_fade_in = $13, filter_fade_out = $14, filter_logo = $15, filter_overlay_logo_scale = $16, filter_overlay_logo_fade_in = $17, filter_overlay_logo_fade_out = $18, filter_overlay_logo = $19, filter_tpad = $20, filter_drawtext_from_file = $21, filter_drawtext_from_zmq = $22, filter_aevalsrc = $23, filter_afade_in = $24, filter_afade_out = $25, filter_apad = $26, filter_volume = $27, filter_split = $28 WHERE channel_id = $1";
+
+    sqlx::query(query)
+        .bind(channel_id)
+        .bind(config.decoder.input_param)
+        .bind(config.decoder.output_param)
+        .bind(config.encoder.input_param)
+        .bind(config.ingest.input_param)
+        .bind(config.filter.deinterlace)
+        .bind(config.filter.pad_scale_w)
+        .bind(config.filter.pad_scale_h)
+        .bind(config.filter.pad_video)
+        .bind(config.filter.fps)
+        .bind(config.filter.scale)
+        .bind(config.filter.set_dar)
+        .bind(config.filter.fade_in)
+        .bind(config.filter.fade_out)
+        .bind(config.filter.logo)
+        .bind(config.filter.overlay_logo_scale)
+        .bind(config.filter.overlay_logo_fade_in)
+        .bind(config.filter.overlay_logo_fade_out)
+        .bind(config.filter.overlay_logo)
+        .bind(config.filter.tpad)
+        .bind(config.filter.drawtext_from_file)
+        .bind(config.filter.drawtext_from_zmq)
+        .bind(config.filter.aevalsrc)
+        .bind(config.filter.afade_in)
+        .bind(config.filter.afade_out)
+        .bind(config.filter.apad)
+        .bind(config.filter.volume)
+        .bind(config.filter.split)
+        .execute(conn)
+        .await
+}
+
+pub async fn select_advanced_configuration(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+) -> Result<AdvancedConfiguration, sqlx::Error> {
+    let query = "SELECT * FROM advanced_configurations WHERE channel_id = $1";
+
+    sqlx::query_as(query).bind(channel).fetch_one(conn).await
+}
+
+pub async fn select_role(conn: &Pool<Sqlite>, id: &i32) -> Result<Role, sqlx::Error> {
+    let query = "SELECT name FROM roles WHERE id = $1";
+    let result: Role = sqlx::query_as(query).bind(id).fetch_one(conn).await?;
+
+    Ok(result)
+}
+
+pub async fn select_role_permissions(
+    conn: &Pool<Sqlite>,
+) -> Result<Vec<RolePermissions>, sqlx::Error> {
+    let query = "SELECT r.id AS role_id, r.name, group_concat(p.permission, ',') AS permissions FROM roles r
+        LEFT JOIN role_permissions p ON p.role_id = r.id
+    GROUP BY r.id ORDER BY r.id";
+
+    sqlx::query_as(query).fetch_all(conn).await
+}
+
+pub async fn update_role_permissions(
+    conn: &Pool<Sqlite>,
+    role_id: i32,
+    permissions: &[Permission],
+) -> Result<(), sqlx::Error> {
+    let mut tx = conn.begin().await?;
+
+    sqlx::query("DELETE FROM role_permissions WHERE role_id = $1")
+        .bind(role_id)
+        .execute(&mut *tx)
+        .await?;
+
+    for permission in permissions {
+        sqlx::query("INSERT OR IGNORE INTO role_permissions (role_id, permission) VALUES ($1, $2)")
+            .bind(role_id)
+            .bind(permission.to_string())
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await
+}
+
+/// Permissions, which are used for the login token: global admins can do everything,
+/// otherwise the permissions from the user are used, or the defaults from the role.
+pub async fn select_user_permissions(
+    conn: &Pool<Sqlite>,
+    user: &User,
+    role: &Role,
+) -> Result<Vec<Permission>, sqlx::Error> {
+    if role == &Role::GlobalAdmin {
+        return Ok(Permission::ALL.to_vec());
+    }
+
+    if let Some(permissions) = &user.permissions {
+        return Ok(permissions.clone());
+    }
+
+    let query = "SELECT permission FROM role_permissions WHERE role_id = $1";
+    let rows = sqlx::query(query)
+        .bind(user.role_id.unwrap_or_default())
+        .fetch_all(conn)
+        .await?;
+
+    Ok(rows
+        .iter()
+        .filter_map(|r| r.get::<String, &str>("permission").parse().ok())
+        .collect())
+}
+
+pub async fn select_login(conn: &Pool<Sqlite>, user: &str) -> Result<User, sqlx::Error> {
+    let query =
+        "SELECT u.id, u.mail, u.username, u.password, u.role_id, u.permissions, u.must_change, u.password_changed, group_concat(uc.channel_id, ',') as channel_ids FROM user u
+        left join user_channels uc on uc.user_id = u.id
+    WHERE u.username = $1";
+
+    sqlx::query_as(query).bind(user).fetch_one(conn).await
+}
+
+pub async fn select_user(conn: &Pool<Sqlite>, id: i32) -> Result<User, sqlx::Error> {
+    let query = "SELECT u.id, u.mail, u.username, u.role_id, u.permissions, u.must_change, group_concat(uc.channel_id, ',') as channel_ids FROM user u
+        left join user_channels uc on uc.user_id = u.id
+    WHERE u.id = $1";
+
+    sqlx::query_as(query).bind(id).fetch_one(conn).await
+}
+
+pub async fn select_global_admins(conn: &Pool<Sqlite>) -> Result<Vec<User>, sqlx::Error> {
+    let query = "SELECT u.id, u.mail, u.username, u.role_id, group_concat(uc.channel_id, ',') as channel_ids FROM user u
+        left join user_channels uc on uc.user_id = u.id
+    WHERE u.role_id = 1";
+
+    sqlx::query_as(query).fetch_all(conn).await
+}
+
+pub async fn select_users(conn: &Pool<Sqlite>) -> Result<Vec<User>, sqlx::Error> {
+    let query = "SELECT id, username FROM user";
+
+    sqlx::query_as(query).fetch_all(conn).await
+}
+
+pub async fn insert_user(conn: &Pool<Sqlite>, user: User) -> Result<(), ServiceError> {
+    let password_hash = task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = Argon2::default()
+            .hash_password(user.password.as_bytes(), &salt)
+            .unwrap();
+
+        hash.to_string()
+    })
+    .await?;
+
+    let query = "INSERT INTO user (mail, username, password, role_id, permissions, must_change, password_changed) VALUES($1, $2, $3, $4, $5, $6, $7) RETURNING id";
+
+    let user_id: i32 = sqlx::query(query)
+        .bind(user.mail)
+        .bind(user.username)
+        .bind(password_hash)
+        .bind(user.role_id)
+        .bind(user.permissions.as_deref().map(Permission::join))
+        .bind(user.must_change_password)
+        .bind(time_now().timestamp())
+        .fetch_one(conn)
+        .await?
+        .get("id");
+
+    if let Some(channel_ids) = user.channel_ids {
+        insert_user_channel(conn, user_id, channel_ids).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn insert_or_update_user(conn: &Pool<Sqlite>, user: User) -> Result<(), ServiceError> {
+    let password_hash = task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = Argon2::default()
+            .hash_password(user.password.as_bytes(), &salt)
+            .unwrap();
+
+        hash.to_string()
+    })
+    .await?;
+
+    let query = "INSERT INTO user (mail, username, password, role_id, password_changed) VALUES($1, $2, $3, $4, $5)
+            ON CONFLICT(username) DO UPDATE SET
+                mail = excluded.mail, username = excluded.username, password = excluded.password, role_id = excluded.role_id,
+                password_changed = excluded.password_changed
+        RETURNING id";
+
+    let user_id: i32 = sqlx::query(query)
+        .bind(user.mail)
+        .bind(user.username)
+        .bind(password_hash)
+        .bind(user.role_id)
+        .bind(time_now().timestamp())
+        .fetch_one(conn)
+        .await?
+        .get("id");
+
+    if let Some(channel_ids) = user.channel_ids {
+        insert_user_channel(conn, user_id, channel_ids).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn update_user(
+    conn: &Pool<Sqlite>,
+    id: i32,
+    fields: String,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = format!("UPDATE user SET {fields} WHERE id = $1");
+
+    sqlx::query(&query).bind(id).execute(conn).await
+}
+
+pub async fn insert_user_channel(
+    conn: &Pool<Sqlite>,
+    user_id: i32,
+    channel_ids: Vec<i32>,
+) -> Result<(), sqlx::Error> {
+    for channel in &channel_ids {
+        let query = "INSERT OR IGNORE INTO user_channels (channel_id, user_id) VALUES ($1, $2);";
+
+        sqlx::query(query)
+            .bind(channel)
+            .bind(user_id)
+            .execute(conn)
+            .await?;
+    }
+
+    Ok(())
+}
+
+pub async fn delete_user(conn: &Pool<Sqlite>, id: i32) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "DELETE FROM user WHERE id = $1;";
+
+    sqlx::query(query).bind(id).execute(conn).await
+}
+
+pub async fn select_login_hash(conn: &Pool<Sqlite>, id: i32) -> Result<String, sqlx::Error> {
+    let query = "SELECT password FROM user WHERE id = $1";
+
+    sqlx::query_scalar(query).bind(id).fetch_one(conn).await
+}
+
+pub async fn update_user_password(
+    conn: &Pool<Sqlite>,
+    id: i32,
+    hash: &str,
+    changed: i64,
+    must_change: bool,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query =
+        "UPDATE user SET password = $2, password_changed = $3, must_change = $4 WHERE id = $1";
+
+    sqlx::query(query)
+        .bind(id)
+        .bind(hash)
+        .bind(changed)
+        .bind(must_change)
+        .execute(conn)
+        .await
+}
+
+pub async fn select_password_policy(conn: &Pool<Sqlite>) -> Result<PasswordPolicy, sqlx::Error> {
+    let query = "SELECT password_policy FROM global WHERE id = 1";
+    let policy: String = sqlx::query_scalar(query).fetch_one(conn).await?;
+
+    Ok(serde_json::from_str(&policy).unwrap_or_default())
+}
+
+pub async fn update_password_policy(
+    conn: &Pool<Sqlite>,
+    policy: &PasswordPolicy,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "UPDATE global SET password_policy = $1 WHERE id = 1";
+
+    sqlx::query(query)
+        .bind(serde_json::to_string(policy).unwrap_or_default())
+        .execute(conn)
+        .await
+}
+
+/// Last password hashes of the user, newest first.
+pub async fn select_password_history(
+    conn: &Pool<Sqlite>,
+    user_id: i32,
+    limit: usize,
+) -> Result<Vec<String>, sqlx::Error> {
+    let query =
+        "SELECT password FROM password_history WHERE user_id = $1 ORDER BY id DESC LIMIT $2";
+
+    sqlx::query_scalar(query)
+        .bind(user_id)
+        .bind(limit as i64)
+        .fetch_all(conn)
+        .await
+}
+
+/// Add a password hash to the history, only the last `keep` hashes stay.
+pub async fn insert_password_history(
+    conn: &Pool<Sqlite>,
+    user_id: i32,
+    hash: &str,
+    keep: usize,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "INSERT INTO password_history (user_id, password) VALUES($1, $2)";
+
+    sqlx::query(query)
+        .bind(user_id)
+        .bind(hash)
+        .execute(conn)
+        .await?;
+
+    let query = "DELETE FROM password_history WHERE user_id = $1 AND id NOT IN
+        (SELECT id FROM password_history WHERE user_id = $1 ORDER BY id DESC LIMIT $2)";
+
+    sqlx::query(query)
+        .bind(user_id)
+        .bind(keep as i64)
+        .execute(conn)
+        .await
+}
+
+/// Users with the name or the mail address, for the password reset.
+pub async fn select_reset_users(conn: &Pool<Sqlite>, name: &str) -> Result<Vec<User>, sqlx::Error> {
+    let query = "SELECT id, mail, username FROM user WHERE username = $1 OR mail = $1";
+
+    sqlx::query_as(query).bind(name).fetch_all(conn).await
+}
+
+/// Save the hash of a reset token, older tokens of the user are removed.
+pub async fn insert_password_reset(
+    conn: &Pool<Sqlite>,
+    user_id: i32,
+    token_hash: &str,
+    expires: i64,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    delete_password_resets(conn, user_id).await?;
+
+    let query = "INSERT INTO password_resets (user_id, token_hash, expires) VALUES($1, $2, $3)";
+
+    sqlx::query(query)
+        .bind(user_id)
+        .bind(token_hash)
+        .bind(expires)
+        .execute(conn)
+        .await
+}
+
+pub async fn select_password_reset(
+    conn: &Pool<Sqlite>,
+    user_id: i32,
+    token_hash: &str,
+    now: i64,
+) -> Result<bool, sqlx::Error> {
+    let query =
+        "SELECT id FROM password_resets WHERE user_id = $1 AND token_hash = $2 AND expires >= $3";
+
+    let row = sqlx::query(query)
+        .bind(user_id)
+        .bind(token_hash)
+        .bind(now)
+        .fetch_optional(conn)
+        .await?;
+
+    Ok(row.is_some())
+}
+
+pub async fn delete_password_resets(
+    conn: &Pool<Sqlite>,
+    user_id: i32,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "DELETE FROM password_resets WHERE user_id = $1";
+
+    sqlx::query(query).bind(user_id).execute(conn).await
+}
+
+pub async fn select_presets(conn: &Pool<Sqlite>, id: i32) -> Result<Vec<TextPreset>, sqlx::Error> {
+    let query = "SELECT * FROM presets WHERE channel_id = $1";
+
+    sqlx::query_as(query).bind(id).fetch_all(conn).await
+}
+
+pub async fn select_preset(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    id: i32,
+) -> Result<TextPreset, sqlx::Error> {
+    let query = "SELECT * FROM presets WHERE channel_id = $1 AND id = $2";
+
+    sqlx::query_as(query)
+        .bind(channel)
+        .bind(id)
+        .fetch_one(conn)
+        .await
+}
+
+pub async fn update_preset(
+    conn: &Pool<Sqlite>,
+    id: &i32,
+    preset: TextPreset,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query =
+        "UPDATE presets SET name = $1, text = $2, x = $3, y = $4, fontsize = $5, line_spacing = $6,
+        fontcolor = $7, alpha = $8, box = $9, boxcolor = $10, boxborderw = $11 WHERE id = $12";
+
+    sqlx::query(query)
+        .bind(preset.name)
+        .bind(preset.text)
+        .bind(preset.x)
+        .bind(preset.y)
+        .bind(preset.fontsize)
+        .bind(preset.line_spacing)
+        .bind(preset.fontcolor)
+        .bind(preset.alpha)
+        .bind(preset.r#box)
+        .bind(preset.boxcolor)
+        .bind(preset.boxborderw)
+        .bind(id)
+        .execute(conn)
+        .await
+}
+
+pub async fn insert_preset(
+    conn: &Pool<Sqlite>,
+    preset: TextPreset,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query =
+        "INSERT INTO presets (channel_id, name, text, x, y, fontsize, line_spacing, fontcolor, alpha, box, boxcolor, boxborderw)
+            VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)";
+
+    sqlx::query(query)
+        .bind(preset.channel_id)
+        .bind(preset.name)
+        .bind(preset.text)
+        .bind(preset.x)
+        .bind(preset.y)
+        .bind(preset.fontsize)
+        .bind(preset.line_spacing)
+        .bind(preset.fontcolor)
+        .bind(preset.alpha)
+        .bind(preset.r#box)
+        .bind(preset.boxcolor)
+        .bind(preset.boxborderw)
+        .execute(conn)
+        .await
+}
+
+pub async fn new_channel_presets(
+    conn: &Pool<Sqlite>,
+    channel_id: i32,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "INSERT INTO presets (name, text, x, y, fontsize, line_spacing, fontcolor, box, boxcolor, boxborderw, alpha, channel_id)
+        VALUES ('Default', 'Welcome to ffplayout messenger!', '(w-text_w)/2', '(h-text_h)/2', '24', '4', '#ffffff@0xff', '0', '#000000@0x80', '4', '1.0', $1),
+        ('Empty Text', '', '0', '0', '24', '4', '#000000', '0', '#000000', '0', '0', $1),
+        ('Bottom Text fade in', 'The upcoming event will be delayed by a few minutes.', '(w-text_w)/2', '(h-line_h)*0.9', '24', '4', '#ffffff', '1', '#000000@0x80', '4', 'ifnot(ld(1),st(1,t));if(lt(t,ld(1)+1),0,if(lt(t,ld(1)+2),(t-(ld(1)+1))/1,if(lt(t,ld(1)+8),1,if(lt(t,ld(1)+9),(1-(t-(ld(1)+8)))/1,0))))', $1),
+        ('Scrolling Text', 'We have a very important announcement to make.', 'ifnot(ld(1),st(1,t));if(lt(t,ld(1)+1),w+4,w-w/12*mod(t-ld(1),12*(w+tw)/w))', '(h-line_h)*0.9', '24', '4', '#ffffff', '1', '#000000@0x80', '4', '1.0', $1);";
+
+    sqlx::query(query).bind(channel_id).execute(conn).await
+}
+
+pub async fn delete_preset(
+    conn: &Pool<Sqlite>,
+    id: &i32,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "DELETE FROM presets WHERE id = $1;";
+
+    sqlx::query(query).bind(id).execute(conn).await
+}
+
+pub async fn insert_audit(
+    conn: &Pool<Sqlite>,
+    entry: AuditEntry,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query =
+        "INSERT INTO audit_log (channel_id, user_id, ip, method, route, payload, status, timestamp)
+        VALUES($1, $2, $3, $4, $5, $6, $7, $8)";
+
+    sqlx::query(query)
+        .bind(entry.channel_id)
+        .bind(entry.user_id)
+        .bind(entry.ip)
+        .bind(entry.method)
+        .bind(entry.route)
+        .bind(entry.payload)
+        .bind(entry.status)
+        .bind(entry.timestamp)
+        .execute(conn)
+        .await
+}
+
+pub async fn select_audit(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    user_id: Option<i32>,
+    from: &str,
+    to: &str,
+) -> Result<Vec<AuditEntry>, sqlx::Error> {
+    let query = match user_id {
+        Some(_) => "SELECT * FROM audit_log WHERE channel_id = $1 AND timestamp >= $2 AND timestamp <= $3 AND user_id = $4 ORDER BY timestamp DESC",
+        None => "SELECT * FROM audit_log WHERE channel_id = $1 AND timestamp >= $2 AND timestamp <= $3 ORDER BY timestamp DESC",
+    };
+
+    let mut q = sqlx::query_as(query).bind(channel).bind(from).bind(to);
+
+    if let Some(id) = user_id {
+        q = q.bind(id);
+    }
+
+    q.fetch_all(conn).await
+}
+
+/// Entries without channel, like from user and channel management.
+pub async fn select_audit_global(
+    conn: &Pool<Sqlite>,
+    from: &str,
+    to: &str,
+) -> Result<Vec<AuditEntry>, sqlx::Error> {
+    let query = "SELECT * FROM audit_log WHERE channel_id IS NULL AND timestamp >= $1 AND timestamp <= $2 ORDER BY timestamp DESC";
+
+    sqlx::query_as(query)
+        .bind(from)
+        .bind(to)
+        .fetch_all(conn)
+        .await
+}
+
+pub async fn insert_handover(
+    conn: &Pool<Sqlite>,
+    note: HandoverNote,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "INSERT INTO handover_notes (channel_id, user_id, note, event, timestamp)
+        VALUES($1, $2, $3, $4, $5)";
+
+    sqlx::query(query)
+        .bind(note.channel_id)
+        .bind(note.user_id)
+        .bind(note.note)
+        .bind(note.event)
+        .bind(note.timestamp)
+        .execute(conn)
+        .await
+}
+
+pub async fn select_handover(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    from: &str,
+    to: &str,
+) -> Result<Vec<HandoverNote>, sqlx::Error> {
+    let query = "SELECT h.*, IFNULL(u.username, '') AS username FROM handover_notes h
+        LEFT JOIN user u ON u.id = h.user_id
+        WHERE h.channel_id = $1 AND h.timestamp >= $2 AND h.timestamp <= $3 ORDER BY h.timestamp";
+
+    sqlx::query_as(query)
+        .bind(channel)
+        .bind(from)
+        .bind(to)
+        .fetch_all(conn)
+        .await
+}
+
+pub async fn insert_playlist_version(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    date: &str,
+    user_id: i32,
+    playlist: &str,
+    timestamp: &str,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "INSERT INTO playlist_versions (channel_id, date, user_id, playlist, timestamp)
+        VALUES($1, $2, $3, $4, $5)";
+
+    sqlx::query(query)
+        .bind(channel)
+        .bind(date)
+        .bind(user_id)
+        .bind(playlist)
+        .bind(timestamp)
+        .execute(conn)
+        .await
+}
+
+/// All versions from one playlist, newest first, without the playlist content.
+pub async fn select_playlist_versions(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    date: &str,
+) -> Result<Vec<PlaylistVersion>, sqlx::Error> {
+    let query = "SELECT v.id, v.channel_id, v.date, v.user_id, IFNULL(u.username, '') AS username,
+        '' AS playlist, IFNULL(json_array_length(v.playlist, '$.program'), 0) AS items, v.timestamp
+        FROM playlist_versions v LEFT JOIN user u ON u.id = v.user_id
+        WHERE v.channel_id = $1 AND v.date = $2 ORDER BY v.id DESC";
+
+    sqlx::query_as(query)
+        .bind(channel)
+        .bind(date)
+        .fetch_all(conn)
+        .await
+}
+
+pub async fn select_playlist_version(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    id: i32,
+) -> Result<PlaylistVersion, sqlx::Error> {
+    let query = "SELECT v.*, IFNULL(u.username, '') AS username FROM playlist_versions v
+        LEFT JOIN user u ON u.id = v.user_id WHERE v.channel_id = $1 AND v.id = $2";
+
+    sqlx::query_as(query)
+        .bind(channel)
+        .bind(id)
+        .fetch_one(conn)
+        .await
+}
+
+/// Keep only the newest versions from one playlist.
+pub async fn delete_old_playlist_versions(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    date: &str,
+    keep: i64,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "DELETE FROM playlist_versions WHERE channel_id = $1 AND date = $2 AND id NOT IN
+        (SELECT id FROM playlist_versions WHERE channel_id = $1 AND date = $2 ORDER BY id DESC LIMIT $3)";
+
+    sqlx::query(query)
+        .bind(channel)
+        .bind(date)
+        .bind(keep)
+        .execute(conn)
+        .await
+}
+
+pub async fn select_artwork(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    title: &str,
+) -> Result<Option<(String, String)>, sqlx::Error> {
+    let query = "SELECT url, updated FROM artwork_cache WHERE channel_id = $1 AND title = $2";
+
+    sqlx::query_as(query)
+        .bind(channel)
+        .bind(title)
+        .fetch_optional(conn)
+        .await
+}
+
+pub async fn upsert_artwork(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    title: &str,
+    url: &str,
+    updated: &str,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "INSERT INTO artwork_cache (channel_id, title, url, updated) VALUES($1, $2, $3, $4)
+        ON CONFLICT(channel_id, title) DO UPDATE SET url = $3, updated = $4";
+
+    sqlx::query(query)
+        .bind(channel)
+        .bind(title)
+        .bind(url)
+        .bind(updated)
+        .execute(conn)
+        .await
+}
+
+pub async fn upsert_trim_suggestion(
+    conn: &Pool<Sqlite>,
+    trim: &TrimSuggestion,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "INSERT INTO trim_suggestions (channel_id, source, seek, out, duration, updated)
+        VALUES($1, $2, $3, $4, $5, $6)
+        ON CONFLICT(channel_id, source) DO UPDATE SET seek = $3, out = $4, duration = $5, updated = $6";
+
+    sqlx::query(query)
+        .bind(trim.channel_id)
+        .bind(&trim.source)
+        .bind(trim.seek)
+        .bind(trim.out)
+        .bind(trim.duration)
+        .bind(&trim.updated)
+        .execute(conn)
+        .await
+}
+
+/// Trim suggestions from all files, which path starts with the given folder.
+pub async fn select_trim_suggestions(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    folder: &str,
+) -> Result<Vec<TrimSuggestion>, sqlx::Error> {
+    let query = "SELECT * FROM trim_suggestions WHERE channel_id = $1 AND substr(source, 1, length($2)) = $2
+        ORDER BY source";
+
+    sqlx::query_as(query)
+        .bind(channel)
+        .bind(folder)
+        .fetch_all(conn)
+        .await
+}
+
+pub async fn select_scripts(conn: &Pool<Sqlite>, channel: i32) -> Result<Vec<Script>, sqlx::Error> {
+    let query = "SELECT * FROM scripts WHERE channel_id = $1 ORDER BY name";
+
+    sqlx::query_as(query).bind(channel).fetch_all(conn).await
+}
+
+pub async fn insert_script(
+    conn: &Pool<Sqlite>,
+    script: &Script,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "INSERT INTO scripts (channel_id, name, code, enabled, updated)
+        VALUES($1, $2, $3, $4, $5)";
+
+    sqlx::query(query)
+        .bind(script.channel_id)
+        .bind(&script.name)
+        .bind(&script.code)
+        .bind(script.enabled)
+        .bind(&script.updated)
+        .execute(conn)
+        .await
+}
+
+pub async fn update_script(
+    conn: &Pool<Sqlite>,
+    script: &Script,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "UPDATE scripts SET name = $3, code = $4, enabled = $5, updated = $6
+        WHERE id = $1 AND channel_id = $2";
+
+    sqlx::query(query)
+        .bind(script.id)
+        .bind(script.channel_id)
+        .bind(&script.name)
+        .bind(&script.code)
+        .bind(script.enabled)
+        .bind(&script.updated)
+        .execute(conn)
+        .await
+}
+
+pub async fn delete_script(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    id: i32,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "DELETE FROM scripts WHERE id = $1 AND channel_id = $2";
+
+    sqlx::query(query)
+        .bind(id)
+        .bind(channel)
+        .execute(conn)
+        .await
+}
+
+pub async fn select_companions(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+) -> Result<Vec<Companion>, sqlx::Error> {
+    let query = "SELECT * FROM companions WHERE channel_id = $1 ORDER BY name";
+
+    sqlx::query_as(query).bind(channel).fetch_all(conn).await
+}
+
+pub async fn select_companion(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    name: &str,
+) -> Result<Companion, sqlx::Error> {
+    let query = "SELECT * FROM companions WHERE channel_id = $1 AND name = $2";
+
+    sqlx::query_as(query)
+        .bind(channel)
+        .bind(name)
+        .fetch_one(conn)
+        .await
+}
+
+pub async fn insert_companion(
+    conn: &Pool<Sqlite>,
+    companion: &Companion,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query =
+        "INSERT INTO companions (channel_id, name, command, args, restart, max_restarts, autostart)
+        VALUES($1, $2, $3, $4, $5, $6, $7)";
+
+    sqlx::query(query)
+        .bind(companion.channel_id)
+        .bind(&companion.name)
+        .bind(&companion.command)
+        .bind(&companion.args)
+        .bind(&companion.restart)
+        .bind(companion.max_restarts)
+        .bind(companion.autostart)
+        .execute(conn)
+        .await
+}
+
+pub async fn update_companion(
+    conn: &Pool<Sqlite>,
+    companion: &Companion,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "UPDATE companions SET name = $3, command = $4, args = $5, restart = $6, max_restarts = $7, autostart = $8
+        WHERE id = $1 AND channel_id = $2";
+
+    sqlx::query(query)
+        .bind(companion.id)
+        .bind(companion.channel_id)
+        .bind(&companion.name)
+        .bind(&companion.command)
+        .bind(&companion.args)
+        .bind(&companion.restart)
+        .bind(companion.max_restarts)
+        .bind(companion.autostart)
+        .execute(conn)
+        .await
+}
+
+pub async fn delete_companion(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    id: i32,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "DELETE FROM companions WHERE id = $1 AND channel_id = $2";
+
+    sqlx::query(query)
+        .bind(id)
+        .bind(channel)
+        .execute(conn)
+        .await
+}
+
+pub async fn select_ad_breaks(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+) -> Result<Vec<AdBreak>, sqlx::Error> {
+    let query = "SELECT * FROM ad_breaks WHERE channel_id = $1 ORDER BY name";
+
+    sqlx::query_as(query).bind(channel).fetch_all(conn).await
+}
+
+pub async fn insert_ad_break(
+    conn: &Pool<Sqlite>,
+    ad_break: &AdBreak,
+) -> Result<AdBreak, sqlx::Error> {
+    let query = "INSERT INTO ad_breaks (channel_id, name, duration, spots, max_plays, pad)
+        VALUES($1, $2, $3, $4, $5, $6) RETURNING *";
+
+    sqlx::query_as(query)
+        .bind(ad_break.channel_id)
+        .bind(&ad_break.name)
+        .bind(ad_break.duration)
+        .bind(&ad_break.spots)
+        .bind(ad_break.max_plays)
+        .bind(ad_break.pad)
+        .fetch_one(conn)
+        .await
+}
+
+pub async fn update_ad_break(
+    conn: &Pool<Sqlite>,
+    ad_break: &AdBreak,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "UPDATE ad_breaks SET name = $3, duration = $4, spots = $5, max_plays = $6,
+        pad = $7 WHERE id = $1 AND channel_id = $2";
+
+    sqlx::query(query)
+        .bind(ad_break.id)
+        .bind(ad_break.channel_id)
+        .bind(&ad_break.name)
+        .bind(ad_break.duration)
+        .bind(&ad_break.spots)
+        .bind(ad_break.max_plays)
+        .bind(ad_break.pad)
+        .execute(conn)
+        .await
+}
+
+pub async fn delete_ad_break(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    id: i32,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "DELETE FROM ad_breaks WHERE id = $1 AND channel_id = $2";
+
+    sqlx::query(query)
+        .bind(id)
+        .bind(channel)
+        .execute(conn)
+        .await
+}
+
+pub async fn select_spot_plays(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    date: &str,
+) -> Result<Vec<SpotPlay>, sqlx::Error> {
+    let query =
+        "SELECT source, date, plays FROM spot_plays WHERE channel_id = $1 AND date = $2 ORDER BY source";
+
+    sqlx::query_as(query)
+        .bind(channel)
+        .bind(date)
+        .fetch_all(conn)
+        .await
+}
+
+pub async fn add_spot_plays(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    date: &str,
+    sources: &[String],
+) -> Result<(), sqlx::Error> {
+    let query = "INSERT INTO spot_plays (channel_id, source, date, plays) VALUES($1, $2, $3, 1)
+        ON CONFLICT(channel_id, source, date) DO UPDATE SET plays = plays + 1";
+    let mut tx = conn.begin().await?;
+
+    for source in sources {
+        sqlx::query(query)
+            .bind(channel)
+            .bind(source)
+            .bind(date)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    // keep one week for the statistics
+    sqlx::query("DELETE FROM spot_plays WHERE channel_id = $1 AND date < date($2, '-7 days')")
+        .bind(channel)
+        .bind(date)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await
+}
+
+pub async fn insert_transcode_job(
+    conn: &Pool<Sqlite>,
+    job: &TranscodeJob,
+) -> Result<TranscodeJob, sqlx::Error> {
+    let query = "INSERT INTO transcode_jobs (channel_id, source, target, status, seek, out, created, updated)
+        VALUES($1, $2, $3, $4, $5, $6, $7, $7) RETURNING *";
+
+    sqlx::query_as(query)
+        .bind(job.channel_id)
+        .bind(&job.source)
+        .bind(&job.target)
+        .bind(&job.status)
+        .bind(job.seek)
+        .bind(job.out)
+        .bind(&job.created)
+        .fetch_one(conn)
+        .await
+}
+
+/// Jobs from a channel, newest first. Without status all jobs are selected.
+pub async fn select_transcode_jobs(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    status: Option<&str>,
+) -> Result<Vec<TranscodeJob>, sqlx::Error> {
+    let query = "SELECT * FROM transcode_jobs WHERE channel_id = $1 AND ($2 IS NULL OR status = $2)
+        ORDER BY id DESC";
+
+    sqlx::query_as(query)
+        .bind(channel)
+        .bind(status)
+        .fetch_all(conn)
+        .await
+}
+
+pub async fn select_transcode_job(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    id: i32,
+) -> Result<TranscodeJob, sqlx::Error> {
+    let query = "SELECT * FROM transcode_jobs WHERE id = $1 AND channel_id = $2";
+
+    sqlx::query_as(query)
+        .bind(id)
+        .bind(channel)
+        .fetch_one(conn)
+        .await
+}
+
+/// Oldest queued job from all channels.
+pub async fn select_next_transcode_job(
+    conn: &Pool<Sqlite>,
+) -> Result<Option<TranscodeJob>, sqlx::Error> {
+    let query = "SELECT * FROM transcode_jobs WHERE status = 'queued' ORDER BY id LIMIT 1";
+
+    sqlx::query_as(query).fetch_optional(conn).await
+}
+
+/// Change the status, but only when the job still has the expected status.
+pub async fn switch_transcode_status(
+    conn: &Pool<Sqlite>,
+    id: i32,
+    from: &str,
+    to: &str,
+    updated: &str,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "UPDATE transcode_jobs SET status = $3, updated = $4 WHERE id = $1 AND status = $2";
+
+    sqlx::query(query)
+        .bind(id)
+        .bind(from)
+        .bind(to)
+        .bind(updated)
+        .execute(conn)
+        .await
+}
+
+pub async fn update_transcode_job(
+    conn: &Pool<Sqlite>,
+    job: &TranscodeJob,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query =
+        "UPDATE transcode_jobs SET status = $2, progress = $3, message = $4, updated = $5 WHERE id = $1";
+
+    sqlx::query(query)
+        .bind(job.id)
+        .bind(&job.status)
+        .bind(job.progress)
+        .bind(&job.message)
+        .bind(&job.updated)
+        .execute(conn)
+        .await
+}
+
+/// Jobs, which were running when the engine stopped, are queued again.
+pub async fn requeue_transcode_jobs(conn: &Pool<Sqlite>) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query =
+        "UPDATE transcode_jobs SET status = 'queued', progress = 0 WHERE status = 'running'";
+
+    sqlx::query(query).execute(conn).await
+}
+
+pub async fn delete_transcode_job(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    id: i32,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "DELETE FROM transcode_jobs WHERE id = $1 AND channel_id = $2";
+
+    sqlx::query(query)
+        .bind(id)
+        .bind(channel)
+        .execute(conn)
+        .await
+}
+
+pub async fn insert_download_job(
+    conn: &Pool<Sqlite>,
+    job: &DownloadJob,
+) -> Result<DownloadJob, sqlx::Error> {
+    let query = "INSERT INTO download_jobs (channel_id, url, folder, format, playlist_date, status, created, updated)
+        VALUES($1, $2, $3, $4, $5, $6, $7, $7) RETURNING *";
+
+    sqlx::query_as(query)
+        .bind(job.channel_id)
+        .bind(&job.url)
+        .bind(&job.folder)
+        .bind(&job.format)
+        .bind(&job.playlist_date)
+        .bind(&job.status)
+        .bind(&job.created)
+        .fetch_one(conn)
+        .await
+}
+
+/// Downloads from a channel, newest first. Without status all jobs are selected.
+pub async fn select_download_jobs(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    status: Option<&str>,
+) -> Result<Vec<DownloadJob>, sqlx::Error> {
+    let query = "SELECT * FROM download_jobs WHERE channel_id = $1 AND ($2 IS NULL OR status = $2)
+        ORDER BY id DESC";
+
+    sqlx::query_as(query)
+        .bind(channel)
+        .bind(status)
+        .fetch_all(conn)
+        .await
+}
+
+pub async fn select_download_job(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    id: i32,
+) -> Result<DownloadJob, sqlx::Error> {
+    let query = "SELECT * FROM download_jobs WHERE id = $1 AND channel_id = $2";
+
+    sqlx::query_as(query)
+        .bind(id)
+        .bind(channel)
+        .fetch_one(conn)
+        .await
+}
+
+/// Oldest queued download from all channels.
+pub async fn select_next_download_job(
+    conn: &Pool<Sqlite>,
+) -> Result<Option<DownloadJob>, sqlx::Error> {
+    let query = "SELECT * FROM download_jobs WHERE status = 'queued' ORDER BY id LIMIT 1";
+
+    sqlx::query_as(query).fetch_optional(conn).await
+}
+
+/// Change the status, but only when the download still has the expected status.
+pub async fn switch_download_status(
+    conn: &Pool<Sqlite>,
+    id: i32,
+    from: &str,
+    to: &str,
+    updated: &str,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "UPDATE download_jobs SET status = $3, updated = $4 WHERE id = $1 AND status = $2";
+
+    sqlx::query(query)
+        .bind(id)
+        .bind(from)
+        .bind(to)
+        .bind(updated)
+        .execute(conn)
+        .await
+}
+
+pub async fn update_download_job(
+    conn: &Pool<Sqlite>,
+    job: &DownloadJob,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "UPDATE download_jobs SET target = $2, status = $3, progress = $4, message = $5, updated = $6
+        WHERE id = $1";
+
+    sqlx::query(query)
+        .bind(job.id)
+        .bind(&job.target)
+        .bind(&job.status)
+        .bind(job.progress)
+        .bind(&job.message)
+        .bind(&job.updated)
+        .execute(conn)
+        .await
+}
+
+/// Downloads, which were running when the engine stopped, are queued again.
+pub async fn requeue_download_jobs(conn: &Pool<Sqlite>) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "UPDATE download_jobs SET status = 'queued', progress = 0 WHERE status = 'running'";
+
+    sqlx::query(query).execute(conn).await
+}
+
+pub async fn delete_download_job(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    id: i32,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "DELETE FROM download_jobs WHERE id = $1 AND channel_id = $2";
+
+    sqlx::query(query)
+        .bind(id)
+        .bind(channel)
+        .execute(conn)
+        .await
+}
+
+pub async fn select_regions(conn: &Pool<Sqlite>, channel: i32) -> Result<Vec<Region>, sqlx::Error> {
+    let query = "SELECT * FROM regions WHERE channel_id = $1 ORDER BY name";
+
+    sqlx::query_as(query).bind(channel).fetch_all(conn).await
+}
+
+pub async fn insert_region(conn: &Pool<Sqlite>, region: &Region) -> Result<Region, sqlx::Error> {
+    let query = "INSERT INTO regions (channel_id, name, output_param, enable)
+        VALUES($1, $2, $3, $4) RETURNING *";
+
+    sqlx::query_as(query)
+        .bind(region.channel_id)
+        .bind(&region.name)
+        .bind(&region.output_param)
+        .bind(region.enable)
+        .fetch_one(conn)
+        .await
+}
+
+pub async fn update_region(
+    conn: &Pool<Sqlite>,
+    region: &Region,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "UPDATE regions SET name = $3, output_param = $4, enable = $5
+        WHERE id = $1 AND channel_id = $2";
+
+    sqlx::query(query)
+        .bind(region.id)
+        .bind(region.channel_id)
+        .bind(&region.name)
+        .bind(&region.output_param)
+        .bind(region.enable)
+        .execute(conn)
+        .await
+}
+
+pub async fn delete_region(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    id: i32,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "DELETE FROM regions WHERE id = $1 AND channel_id = $2";
+
+    sqlx::query(query)
+        .bind(id)
+        .bind(channel)
+        .execute(conn)
+        .await
+}
+
+pub async fn select_output_targets(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+) -> Result<Vec<OutputTarget>, sqlx::Error> {
+    let query = "SELECT * FROM output_targets WHERE channel_id = $1 ORDER BY id";
+
+    sqlx::query_as(query).bind(channel).fetch_all(conn).await
+}
+
+pub async fn select_output_target(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    id: i32,
+) -> Result<OutputTarget, sqlx::Error> {
+    let query = "SELECT * FROM output_targets WHERE id = $1 AND channel_id = $2";
+
+    sqlx::query_as(query)
+        .bind(id)
+        .bind(channel)
+        .fetch_one(conn)
+        .await
+}
+
+pub async fn insert_output_target(
+    conn: &Pool<Sqlite>,
+    target: &OutputTarget,
+) -> Result<OutputTarget, sqlx::Error> {
+    let query = "INSERT INTO output_targets (channel_id, name, url, format, stream_key, username, password, enable)
+        VALUES($1, $2, $3, $4, $5, $6, $7, $8) RETURNING *";
+
+    sqlx::query_as(query)
+        .bind(target.channel_id)
+        .bind(&target.name)
+        .bind(&target.url)
+        .bind(&target.format)
+        .bind(&target.stream_key)
+        .bind(&target.username)
+        .bind(&target.password)
+        .bind(target.enable)
+        .fetch_one(conn)
+        .await
+}
+
+pub async fn update_output_target(
+    conn: &Pool<Sqlite>,
+    target: &OutputTarget,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "UPDATE output_targets SET name = $3, url = $4, format = $5, stream_key = $6, username = $7, password = $8, enable = $9
+        WHERE id = $1 AND channel_id = $2";
+
+    sqlx::query(query)
+        .bind(target.id)
+        .bind(target.channel_id)
+        .bind(&target.name)
+        .bind(&target.url)
+        .bind(&target.format)
+        .bind(&target.stream_key)
+        .bind(&target.username)
+        .bind(&target.password)
+        .bind(target.enable)
+        .execute(conn)
+        .await
+}
+
+pub async fn enable_output_target(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    id: i32,
+    enable: bool,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "UPDATE output_targets SET enable = $3 WHERE id = $1 AND channel_id = $2";
+
+    sqlx::query(query)
+        .bind(id)
+        .bind(channel)
+        .bind(enable)
+        .execute(conn)
+        .await
+}
+
+pub async fn delete_output_target(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    id: i32,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "DELETE FROM output_targets WHERE id = $1 AND channel_id = $2";
+
+    sqlx::query(query)
+        .bind(id)
+        .bind(channel)
+        .execute(conn)
+        .await
+}
+
+pub async fn select_nodes(conn: &Pool<Sqlite>) -> Result<Vec<Node>, sqlx::Error> {
+    let query = "SELECT * FROM nodes ORDER BY id";
+
+    sqlx::query_as(query).fetch_all(conn).await
+}
+
+pub async fn insert_node(conn: &Pool<Sqlite>, node: &Node) -> Result<Node, sqlx::Error> {
+    let query = "INSERT INTO nodes (name, url, drained) VALUES($1, $2, $3) RETURNING *";
+
+    sqlx::query_as(query)
+        .bind(&node.name)
+        .bind(&node.url)
+        .bind(node.drained)
+        .fetch_one(conn)
+        .await
+}
+
+pub async fn update_node(
+    conn: &Pool<Sqlite>,
+    node: &Node,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "UPDATE nodes SET name = $2, url = $3, drained = $4 WHERE id = $1";
+
+    sqlx::query(query)
+        .bind(node.id)
+        .bind(&node.name)
+        .bind(&node.url)
+        .bind(node.drained)
+        .execute(conn)
+        .await
+}
+
+pub async fn delete_node(conn: &Pool<Sqlite>, id: i32) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "DELETE FROM nodes WHERE id = $1";
+
+    sqlx::query(query).bind(id).execute(conn).await
+}
+
+pub async fn select_node_channels(conn: &Pool<Sqlite>) -> Result<Vec<NodeChannel>, sqlx::Error> {
+    let query = "SELECT * FROM node_channels ORDER BY channel_id";
+
+    sqlx::query_as(query).fetch_all(conn).await
+}
+
+pub async fn upsert_node_channel(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    node: i32,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "INSERT INTO node_channels (channel_id, node_id) VALUES($1, $2)
+        ON CONFLICT(channel_id) DO UPDATE SET node_id = $2";
+
+    sqlx::query(query)
+        .bind(channel)
+        .bind(node)
+        .execute(conn)
+        .await
+}
+
+pub async fn delete_node_channel(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+) -> Result<SqliteQueryResult, sqlx::Error> {
+    let query = "DELETE FROM node_channels WHERE channel_id = $1";
+
+    sqlx::query(query).bind(channel).execute(conn).await
+}
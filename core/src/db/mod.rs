@@ -12,6 +12,7 @@ use sqlx::{migrate::MigrateDatabase, Pool, Sqlite, SqlitePool};
 pub mod handles;
 pub mod models;
 
+use crate::utils::backup::apply_staged_restore;
 use crate::ARGS;
 use models::GlobalSettings;
 
@@ -64,6 +65,11 @@ pub static DB_PATH: LazyLock<Result<Cow<'static, Path>, io::Error>> = LazyLock::
 pub static GLOBAL_SETTINGS: OnceLock<GlobalSettings> = OnceLock::new();
 pub async fn db_pool() -> Result<Pool<Sqlite>, Box<dyn std::error::Error + Send + Sync>> {
     let db_path = DB_PATH.as_ref()?;
+
+    if apply_staged_restore(db_path)? {
+        info!("Database restored from backup");
+    }
+
     let db_path = db_path.to_string_lossy();
 
     if !Sqlite::database_exists(&db_path).await? {
@@ -0,0 +1,1035 @@
+use std::{error::Error, fmt, str::FromStr};
+
+use regex::Regex;
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Serialize,
+};
+// use serde_with::{formats::CommaSeparator, serde_as, StringWithSeparator};
+use sqlx::{sqlite::SqliteRow, FromRow, Pool, Row, Sqlite};
+
+use crate::db::handles;
+use crate::utils::config::PlayoutConfig;
+
+#[derive(Clone, Default, Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct GlobalSettings {
+    pub id: i32,
+    pub secret: Option<String>,
+    pub logs: String,
+    pub playlists: String,
+    pub public: String,
+    pub storage: String,
+    pub shared: bool,
+    pub mail_smtp: String,
+    pub mail_user: String,
+    pub mail_password: String,
+    pub mail_starttls: bool,
+}
+
+impl GlobalSettings {
+    pub async fn new(conn: &Pool<Sqlite>) -> Self {
+        let global_settings = handles::select_global(conn);
+
+        match global_settings.await {
+            Ok(g) => g,
+            Err(_) => Self {
+                id: 0,
+                secret: None,
+                logs: String::new(),
+                playlists: String::new(),
+                public: String::new(),
+                storage: String::new(),
+                shared: false,
+                mail_smtp: String::new(),
+                mail_user: String::new(),
+                mail_password: String::new(),
+                mail_starttls: false,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Channel {
+    #[serde(default = "default_id", skip_deserializing)]
+    pub id: i32,
+    pub name: String,
+    pub preview_url: String,
+    pub extra_extensions: String,
+    pub active: bool,
+    pub public: String,
+    pub playlists: String,
+    pub storage: String,
+    pub last_date: Option<String>,
+    pub time_shift: f64,
+    // not in use currently
+    #[sqlx(default)]
+    #[serde(default, skip_serializing)]
+    pub timezone: Option<String>,
+
+    #[sqlx(default)]
+    #[serde(default)]
+    pub utc_offset: i32,
+
+    /// Player page without login, `None` keeps the stored value on update.
+    #[sqlx(default)]
+    #[serde(default)]
+    pub public_embed: Option<bool>,
+
+    /// Extra response headers for public routes, one `Name: value` per line.
+    #[sqlx(default)]
+    #[serde(default)]
+    pub public_headers: String,
+
+    /// Maximal size of the storage folder in bytes, 0 means no limit.
+    #[sqlx(default)]
+    #[serde(default)]
+    pub storage_quota: i64,
+
+    /// Now playing route without login, for website widgets.
+    #[sqlx(default)]
+    #[serde(default)]
+    pub public_nowplaying: bool,
+
+    /// Token for the public now playing route, empty means no token is needed.
+    #[sqlx(default)]
+    #[serde(default)]
+    pub nowplaying_token: String,
+
+    /// Planned work: no alerts and no automatic starts.
+    #[sqlx(default)]
+    #[serde(default)]
+    pub maintenance: bool,
+
+    /// Order of the channel start at boot, a higher priority starts first.
+    #[sqlx(default)]
+    #[serde(default)]
+    pub start_priority: i32,
+
+    /// CPUs for the ffmpeg processes, like `0-3,8`, empty for all.
+    #[sqlx(default)]
+    #[serde(default)]
+    pub cpu_affinity: String,
+
+    /// Nice level of the ffmpeg processes, from -20 to 19.
+    #[sqlx(default)]
+    #[serde(default)]
+    pub nice: i32,
+
+    /// Memory limit in bytes for all ffmpeg processes, 0 for no limit.
+    #[sqlx(default)]
+    #[serde(default)]
+    pub memory_limit: i64,
+
+    /// GPU for hardware encoders: empty for none, `auto` or the GPU index.
+    #[sqlx(default)]
+    #[serde(default)]
+    pub gpu: String,
+
+    #[sqlx(skip)]
+    #[serde(default, skip_deserializing)]
+    pub storage_used: i64,
+}
+
+fn default_id() -> i32 {
+    1
+}
+
+// #[serde_as]
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct User {
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mail: Option<String>,
+    pub username: String,
+    #[serde(skip_serializing, default = "String::new")]
+    pub password: String,
+    pub role_id: Option<i32>,
+    // #[serde_as(as = "StringWithSeparator::<CommaSeparator, i32>")]
+    pub channel_ids: Option<Vec<i32>>,
+    /// Permissions of this user, instead of the defaults from the role.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permissions: Option<Vec<Permission>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Ask for a new password after login.
+    #[serde(default)]
+    pub must_change_password: bool,
+    /// Timestamp of the last password change.
+    #[serde(skip)]
+    pub password_changed: i64,
+}
+
+impl FromRow<'_, SqliteRow> for User {
+    fn from_row(row: &SqliteRow) -> sqlx::Result<Self> {
+        Ok(Self {
+            id: row.try_get("id").unwrap_or_default(),
+            mail: row.try_get("mail").unwrap_or_default(),
+            username: row.try_get("username").unwrap_or_default(),
+            password: row.try_get("password").unwrap_or_default(),
+            role_id: row.try_get("role_id").unwrap_or_default(),
+            channel_ids: Some(
+                row.try_get::<String, &str>("channel_ids")
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(|i| i.parse::<i32>().unwrap_or_default())
+                    .collect(),
+            ),
+            permissions: row
+                .try_get::<Option<String>, &str>("permissions")
+                .unwrap_or_default()
+                .map(|p| Permission::parse_list(&p)),
+            token: None,
+            must_change_password: row.try_get("must_change").unwrap_or_default(),
+            password_changed: row.try_get("password_changed").unwrap_or_default(),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct UserMeta {
+    pub id: i32,
+    pub channels: Vec<i32>,
+    pub permissions: Vec<Permission>,
+}
+
+impl UserMeta {
+    pub fn new(id: i32, channels: Vec<i32>, permissions: Vec<Permission>) -> Self {
+        Self {
+            id,
+            channels,
+            permissions,
+        }
+    }
+
+    pub fn can(&self, permission: Permission) -> bool {
+        self.permissions.contains(&permission)
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub enum Role {
+    GlobalAdmin,
+    ChannelAdmin,
+    User,
+    Guest,
+}
+
+impl Role {
+    pub fn set_role(role: &str) -> Self {
+        role.parse().unwrap_or(Self::Guest)
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "global_admin" => Ok(Self::GlobalAdmin),
+            "channel_admin" => Ok(Self::ChannelAdmin),
+            "user" => Ok(Self::User),
+            _ => Ok(Self::Guest),
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::GlobalAdmin => write!(f, "global_admin"),
+            Self::ChannelAdmin => write!(f, "channel_admin"),
+            Self::User => write!(f, "user"),
+            Self::Guest => write!(f, "guest"),
+        }
+    }
+}
+
+impl<'r> sqlx::decode::Decode<'r, ::sqlx::Sqlite> for Role
+where
+    &'r str: sqlx::decode::Decode<'r, sqlx::Sqlite>,
+{
+    fn decode(
+        value: sqlx::sqlite::SqliteValueRef<'r>,
+    ) -> Result<Self, Box<dyn Error + 'static + Send + Sync>> {
+        let value = <&str as sqlx::decode::Decode<sqlx::Sqlite>>::decode(value)?;
+
+        Ok(value.parse()?)
+    }
+}
+
+impl FromRow<'_, SqliteRow> for Role {
+    fn from_row(row: &SqliteRow) -> sqlx::Result<Self> {
+        match row.get("name") {
+            "global_admin" => Ok(Self::GlobalAdmin),
+            "channel_admin" => Ok(Self::ChannelAdmin),
+            "user" => Ok(Self::User),
+            _ => Ok(Self::Guest),
+        }
+    }
+}
+
+/// Feature, which a user is allowed to use in the channels.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+pub enum Permission {
+    #[serde(rename = "channel.edit")]
+    ChannelEdit,
+    #[serde(rename = "config.edit")]
+    ConfigEdit,
+    #[serde(rename = "preset.edit")]
+    PresetEdit,
+    #[serde(rename = "text.send")]
+    TextSend,
+    #[serde(rename = "process.control")]
+    ProcessControl,
+    #[serde(rename = "playlist.edit")]
+    PlaylistEdit,
+    #[serde(rename = "file.edit")]
+    FileEdit,
+    #[serde(rename = "file.delete")]
+    FileDelete,
+}
+
+impl Permission {
+    pub const ALL: [Self; 8] = [
+        Self::ChannelEdit,
+        Self::ConfigEdit,
+        Self::PresetEdit,
+        Self::TextSend,
+        Self::ProcessControl,
+        Self::PlaylistEdit,
+        Self::FileEdit,
+        Self::FileDelete,
+    ];
+
+    /// Parse a comma separated list, unknown permissions are ignored.
+    pub fn parse_list(input: &str) -> Vec<Self> {
+        input
+            .split(',')
+            .filter_map(|p| p.trim().parse().ok())
+            .collect()
+    }
+
+    pub fn join(permissions: &[Self]) -> String {
+        permissions
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<String>>()
+            .join(",")
+    }
+}
+
+impl FromStr for Permission {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "channel.edit" => Ok(Self::ChannelEdit),
+            "config.edit" => Ok(Self::ConfigEdit),
+            "preset.edit" => Ok(Self::PresetEdit),
+            "text.send" => Ok(Self::TextSend),
+            "process.control" => Ok(Self::ProcessControl),
+            "playlist.edit" => Ok(Self::PlaylistEdit),
+            "file.edit" => Ok(Self::FileEdit),
+            "file.delete" => Ok(Self::FileDelete),
+            _ => Err(format!("Unknown permission: {input}")),
+        }
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::ChannelEdit => write!(f, "channel.edit"),
+            Self::ConfigEdit => write!(f, "config.edit"),
+            Self::PresetEdit => write!(f, "preset.edit"),
+            Self::TextSend => write!(f, "text.send"),
+            Self::ProcessControl => write!(f, "process.control"),
+            Self::PlaylistEdit => write!(f, "playlist.edit"),
+            Self::FileEdit => write!(f, "file.edit"),
+            Self::FileDelete => write!(f, "file.delete"),
+        }
+    }
+}
+
+/// Permissions of a role, one row from the permission matrix.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RolePermissions {
+    pub role_id: i32,
+    pub role: String,
+    pub permissions: Vec<Permission>,
+}
+
+impl FromRow<'_, SqliteRow> for RolePermissions {
+    fn from_row(row: &SqliteRow) -> sqlx::Result<Self> {
+        Ok(Self {
+            role_id: row.try_get("role_id")?,
+            role: row.try_get("name")?,
+            permissions: Permission::parse_list(
+                &row.try_get::<Option<String>, &str>("permissions")?
+                    .unwrap_or_default(),
+            ),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, sqlx::FromRow)]
+pub struct TextPreset {
+    #[sqlx(default)]
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    pub channel_id: i32,
+    pub name: String,
+    pub text: String,
+    pub x: String,
+    pub y: String,
+    #[serde(deserialize_with = "deserialize_number_or_string")]
+    pub fontsize: String,
+    #[serde(deserialize_with = "deserialize_number_or_string")]
+    pub line_spacing: String,
+    pub fontcolor: String,
+    pub r#box: String,
+    pub boxcolor: String,
+    #[serde(deserialize_with = "deserialize_number_or_string")]
+    pub boxborderw: String,
+    #[serde(deserialize_with = "deserialize_number_or_string")]
+    pub alpha: String,
+}
+
+/// Deserialize number or string
+pub fn deserialize_number_or_string<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    struct StringOrNumberVisitor;
+
+    impl Visitor<'_> for StringOrNumberVisitor {
+        type Value = String;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a string or a number")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+            let re = Regex::new(r"0,([0-9]+)").unwrap();
+            let clean_string = re.replace_all(value, "0.$1").to_string();
+            Ok(clean_string)
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+            Ok(value.to_string())
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+            Ok(value.to_string())
+        }
+
+        fn visit_f64<E: de::Error>(self, value: f64) -> Result<Self::Value, E> {
+            Ok(value.to_string())
+        }
+    }
+
+    deserializer.deserialize_any(StringOrNumberVisitor)
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Configuration {
+    pub id: i32,
+    pub channel_id: i32,
+    pub general_stop_threshold: f64,
+
+    pub mail_subject: String,
+    pub mail_recipient: String,
+    pub mail_level: String,
+    pub mail_interval: i64,
+
+    pub logging_ffmpeg_level: String,
+    pub logging_ingest_level: String,
+    pub logging_detect_silence: bool,
+    #[serde(default)]
+    pub logging_ignore: String,
+
+    pub processing_mode: String,
+    pub processing_audio_only: bool,
+    pub processing_copy_audio: bool,
+    pub processing_copy_video: bool,
+    #[serde(default)]
+    pub processing_passthrough: bool,
+    pub processing_width: i64,
+    pub processing_height: i64,
+    pub processing_aspect: f64,
+    pub processing_fps: f64,
+    pub processing_add_logo: bool,
+    pub processing_logo: String,
+    pub processing_logo_scale: String,
+    pub processing_logo_opacity: f64,
+    pub processing_logo_position: String,
+    #[serde(default = "default_tracks")]
+    pub processing_audio_tracks: i32,
+    #[serde(default = "default_track_index")]
+    pub processing_audio_track_index: i32,
+    #[serde(default = "default_channels")]
+    pub processing_audio_channels: u8,
+    pub processing_volume: f64,
+    #[serde(default)]
+    pub processing_filter: String,
+    #[serde(default)]
+    pub processing_vtt_enable: bool,
+    #[serde(default)]
+    pub processing_vtt_dummy: Option<String>,
+    #[serde(default)]
+    pub processing_transition: String,
+    #[serde(default)]
+    pub processing_transition_frames: i32,
+    #[serde(default)]
+    pub processing_transition_audio_frames: i32,
+    #[serde(default)]
+    pub processing_logos: String,
+    #[serde(default)]
+    pub processing_dead_air_duration: f64,
+    #[serde(default)]
+    pub processing_dead_air_fallback: String,
+    #[serde(default)]
+    pub processing_dead_air_audio: String,
+
+    pub ingest_enable: bool,
+    pub ingest_param: String,
+    #[serde(default)]
+    pub ingest_filter: String,
+
+    pub playlist_day_start: String,
+    pub playlist_length: String,
+    pub playlist_infinit: bool,
+    #[serde(default)]
+    pub playlist_check_before: i64,
+    #[serde(default)]
+    pub playlist_fallback: String,
+
+    pub storage_filler: String,
+    #[serde(default)]
+    pub storage_filler_max_repeat: i64,
+    #[serde(default)]
+    pub storage_filler_fit: bool,
+    #[serde(default)]
+    pub storage_filler_exclude: String,
+    pub storage_extensions: String,
+    pub storage_shuffle: bool,
+    #[serde(default)]
+    pub storage_artwork_path: String,
+    #[serde(default)]
+    pub storage_artwork_api: String,
+    #[serde(default)]
+    pub storage_artwork_field: String,
+    #[serde(default)]
+    pub storage_trash_retention: i64,
+
+    pub text_add: bool,
+    pub text_from_filename: bool,
+    pub text_font: String,
+    pub text_style: String,
+    pub text_regex: String,
+
+    pub task_enable: bool,
+    pub task_path: String,
+
+    pub output_mode: String,
+    pub output_param: String,
+    #[serde(default)]
+    pub output_s3_enable: bool,
+    #[serde(default)]
+    pub output_s3_endpoint: String,
+    #[serde(default)]
+    pub output_s3_region: String,
+    #[serde(default)]
+    pub output_s3_bucket: String,
+    #[serde(default)]
+    pub output_s3_prefix: String,
+    #[serde(default)]
+    pub output_s3_access_key: String,
+    #[serde(default)]
+    pub output_s3_secret_key: String,
+    #[serde(default)]
+    pub output_s3_concurrency: i32,
+    #[serde(default)]
+    pub output_scte35: bool,
+    #[serde(default)]
+    pub output_rating: String,
+    #[serde(default)]
+    pub processing_subtitle_burn: bool,
+    #[serde(default)]
+    pub output_backup_param: String,
+    #[serde(default)]
+    pub general_air_hours: String,
+    #[serde(default)]
+    pub general_scripting: bool,
+    #[serde(default)]
+    pub storage_watch_folder: String,
+    #[serde(default)]
+    pub processing_house_format: String,
+    #[serde(default)]
+    pub mail_notifiers: String,
+    #[serde(default)]
+    pub mail_repeat_limit: i64,
+    #[serde(default)]
+    pub storage_object_store: String,
+    #[serde(default)]
+    pub storage_pull_ingest: String,
+    #[serde(default)]
+    pub text_widgets: String,
+    #[serde(default)]
+    pub text_overlay: String,
+    #[serde(default)]
+    pub output_radio: String,
+    #[serde(default)]
+    pub processing_audio_track_map: String,
+    #[serde(default)]
+    pub output_decklink: String,
+    #[serde(default)]
+    pub ingest_auth: String,
+    #[serde(default)]
+    pub ingest_preview: String,
+    #[serde(default)]
+    pub ingest_whip: String,
+    #[serde(default)]
+    pub storage_pre_cache: String,
+    #[serde(default)]
+    pub output_delayed: String,
+    #[serde(default)]
+    pub processing_aspect_policy: String,
+    #[serde(default)]
+    pub output_broadcast: String,
+    #[serde(default)]
+    pub processing_audio_downmix: String,
+    #[serde(default)]
+    pub output_offline: String,
+    #[serde(default)]
+    pub logging_level: String,
+    #[serde(default)]
+    pub logging_retention: String,
+    #[serde(default)]
+    pub storage_file_inline: bool,
+    #[serde(default)]
+    pub output_preview: String,
+    #[serde(default)]
+    pub processing_audio_meter: bool,
+    #[serde(default)]
+    pub output_alarms: String,
+    #[serde(default)]
+    pub output_item_metadata: bool,
+}
+
+impl Configuration {
+    pub fn from(id: i32, channel_id: i32, config: PlayoutConfig) -> Self {
+        Self {
+            id,
+            channel_id,
+            general_stop_threshold: config.general.stop_threshold,
+            mail_subject: config.mail.subject,
+            mail_recipient: config.mail.recipient,
+            mail_level: config.mail.mail_level.to_string(),
+            mail_interval: config.mail.interval,
+            logging_ffmpeg_level: config.logging.ffmpeg_level,
+            logging_ingest_level: config.logging.ingest_level,
+            logging_detect_silence: config.logging.detect_silence,
+            logging_ignore: config.logging.ignore_lines.join(";"),
+            processing_mode: config.processing.mode.to_string(),
+            processing_audio_only: config.processing.audio_only,
+            processing_audio_track_index: config.processing.audio_track_index,
+            processing_copy_audio: config.processing.copy_audio,
+            processing_copy_video: config.processing.copy_video,
+            processing_passthrough: config.processing.passthrough,
+            processing_width: config.processing.width,
+            processing_height: config.processing.height,
+            processing_aspect: config.processing.aspect,
+            processing_fps: config.processing.fps,
+            processing_add_logo: config.processing.add_logo,
+            processing_logo: config.processing.logo,
+            processing_logo_scale: config.processing.logo_scale,
+            processing_logo_opacity: config.processing.logo_opacity,
+            processing_logo_position: config.processing.logo_position,
+            processing_audio_tracks: config.processing.audio_tracks,
+            processing_audio_channels: config.processing.audio_channels,
+            processing_volume: config.processing.volume,
+            processing_filter: config.processing.custom_filter,
+            processing_vtt_enable: config.processing.vtt_enable,
+            processing_vtt_dummy: config.processing.vtt_dummy,
+            processing_transition: config.processing.transition.to_string(),
+            processing_transition_frames: config.processing.transition_frames,
+            processing_transition_audio_frames: config.processing.transition_audio_frames,
+            processing_logos: serde_json::to_string(&config.processing.logos).unwrap_or_default(),
+            processing_dead_air_duration: config.processing.dead_air_duration,
+            processing_dead_air_fallback: config.processing.dead_air_fallback,
+            processing_dead_air_audio: config.processing.dead_air_audio,
+            ingest_enable: config.ingest.enable,
+            ingest_param: config.ingest.input_param,
+            ingest_filter: config.ingest.custom_filter,
+            playlist_day_start: config.playlist.day_start,
+            playlist_length: config.playlist.length,
+            playlist_infinit: config.playlist.infinit,
+            playlist_check_before: config.playlist.check_before,
+            playlist_fallback: config.playlist.fallback.to_string(),
+            storage_filler: config.storage.filler,
+            storage_filler_max_repeat: config.storage.filler_max_repeat,
+            storage_filler_fit: config.storage.filler_fit,
+            storage_filler_exclude: config.storage.filler_exclude,
+            storage_extensions: config.storage.extensions.join(";"),
+            storage_shuffle: config.storage.shuffle,
+            storage_artwork_path: config.storage.artwork_path,
+            storage_artwork_api: config.storage.artwork_api,
+            storage_artwork_field: config.storage.artwork_field,
+            storage_trash_retention: config.storage.trash_retention,
+            text_add: config.text.add_text,
+            text_font: config.text.font,
+            text_from_filename: config.text.text_from_filename,
+            text_style: config.text.style,
+            text_regex: config.text.regex,
+            task_enable: config.task.enable,
+            task_path: config.task.path.to_string_lossy().to_string(),
+            output_mode: config.output.mode.to_string(),
+            output_param: config.output.output_param,
+            output_s3_enable: config.output.s3_enable,
+            output_s3_endpoint: config.output.s3_endpoint,
+            output_s3_region: config.output.s3_region,
+            output_s3_bucket: config.output.s3_bucket,
+            output_s3_prefix: config.output.s3_prefix,
+            output_s3_access_key: config.output.s3_access_key,
+            output_s3_secret_key: config.output.s3_secret_key,
+            output_s3_concurrency: config.output.s3_concurrency,
+            output_scte35: config.output.scte35,
+            output_rating: serde_json::to_string(&config.output.rating).unwrap_or_default(),
+            processing_subtitle_burn: config.processing.subtitle_burn,
+            output_backup_param: config.output.backup_param,
+            general_air_hours: serde_json::to_string(&config.general.air_hours).unwrap_or_default(),
+            general_scripting: config.general.scripting,
+            storage_watch_folder: serde_json::to_string(&config.storage.watch_folder)
+                .unwrap_or_default(),
+            processing_house_format: serde_json::to_string(&config.processing.house_format)
+                .unwrap_or_default(),
+            mail_notifiers: serde_json::to_string(&config.mail.notifiers).unwrap_or_default(),
+            mail_repeat_limit: config.mail.repeat_limit,
+            storage_object_store: serde_json::to_string(&config.storage.object_store)
+                .unwrap_or_default(),
+            storage_pull_ingest: serde_json::to_string(&config.storage.pull_ingest)
+                .unwrap_or_default(),
+            text_widgets: serde_json::to_string(&config.text.widgets).unwrap_or_default(),
+            text_overlay: serde_json::to_string(&config.text.overlay).unwrap_or_default(),
+            output_radio: serde_json::to_string(&config.output.radio).unwrap_or_default(),
+            processing_audio_track_map: serde_json::to_string(&config.processing.audio_track_map)
+                .unwrap_or_default(),
+            output_decklink: serde_json::to_string(&config.output.decklink).unwrap_or_default(),
+            ingest_auth: serde_json::to_string(&config.ingest.auth).unwrap_or_default(),
+            ingest_preview: serde_json::to_string(&config.ingest.preview).unwrap_or_default(),
+            ingest_whip: serde_json::to_string(&config.ingest.whip).unwrap_or_default(),
+            storage_pre_cache: serde_json::to_string(&config.storage.pre_cache).unwrap_or_default(),
+            output_delayed: serde_json::to_string(&config.output.delayed).unwrap_or_default(),
+            processing_aspect_policy: config.processing.aspect_policy.to_string(),
+            output_broadcast: serde_json::to_string(&config.output.broadcast).unwrap_or_default(),
+            processing_audio_downmix: config.processing.audio_downmix.to_string(),
+            output_offline: serde_json::to_string(&config.output.offline).unwrap_or_default(),
+            logging_level: config.logging.level,
+            logging_retention: serde_json::to_string(&config.logging.retention).unwrap_or_default(),
+            storage_file_inline: config.storage.file_inline,
+            output_preview: serde_json::to_string(&config.output.preview).unwrap_or_default(),
+            processing_audio_meter: config.processing.audio_meter,
+            output_alarms: serde_json::to_string(&config.output.alarms).unwrap_or_default(),
+            output_item_metadata: config.output.item_metadata,
+        }
+    }
+}
+
+fn default_track_index() -> i32 {
+    -1
+}
+
+fn default_tracks() -> i32 {
+    1
+}
+
+fn default_channels() -> u8 {
+    2
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, sqlx::FromRow)]
+pub struct AdvancedConfiguration {
+    pub id: i32,
+    pub channel_id: i32,
+    pub decoder_input_param: Option<String>,
+    pub decoder_output_param: Option<String>,
+    pub encoder_input_param: Option<String>,
+    pub ingest_input_param: Option<String>,
+    pub filter_deinterlace: Option<String>,
+    pub filter_pad_scale_w: Option<String>,
+    pub filter_pad_scale_h: Option<String>,
+    pub filter_pad_video: Option<String>,
+    pub filter_fps: Option<String>,
+    pub filter_scale: Option<String>,
+    pub filter_set_dar: Option<String>,
+    pub filter_fade_in: Option<String>,
+    pub filter_fade_out: Option<String>,
+    pub filter_logo: Option<String>,
+    pub filter_overlay_logo_scale: Option<String>,
+    pub filter_overlay_logo_fade_in: Option<String>,
+    pub filter_overlay_logo_fade_out: Option<String>,
+    pub filter_overlay_logo: Option<String>,
+    pub filter_tpad: Option<String>,
+    pub filter_drawtext_from_file: Option<String>,
+    pub filter_drawtext_from_zmq: Option<String>,
+    pub filter_aevalsrc: Option<String>,
+    pub filter_afade_in: Option<String>,
+    pub filter_afade_out: Option<String>,
+    pub filter_apad: Option<String>,
+    pub filter_volume: Option<String>,
+    pub filter_split: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct AuditEntry {
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    pub channel_id: Option<i32>,
+    pub user_id: i32,
+    pub ip: String,
+    pub method: String,
+    pub route: String,
+    pub payload: String,
+    pub status: i32,
+    pub timestamp: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct HandoverNote {
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    #[serde(skip_deserializing)]
+    pub channel_id: i32,
+    #[serde(skip_deserializing)]
+    pub user_id: i32,
+    #[sqlx(default)]
+    #[serde(skip_deserializing)]
+    pub username: String,
+    pub note: String,
+    /// As-run event, the clip which was playing when the note was written.
+    #[serde(default)]
+    pub event: String,
+    #[serde(default)]
+    pub timestamp: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct PlaylistVersion {
+    pub id: i32,
+    pub channel_id: i32,
+    pub date: String,
+    pub user_id: i32,
+    #[sqlx(default)]
+    pub username: String,
+    /// Playlist JSON, only filled when a single version is requested.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub playlist: String,
+    #[sqlx(default)]
+    pub items: i32,
+    pub timestamp: String,
+}
+
+/// Suggested in/out points, from leading and trailing black or silence.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct TrimSuggestion {
+    #[serde(skip_serializing)]
+    pub id: i32,
+    #[serde(skip_serializing)]
+    pub channel_id: i32,
+    pub source: String,
+    #[serde(rename = "in")]
+    pub seek: f64,
+    pub out: f64,
+    pub duration: f64,
+    pub updated: String,
+}
+
+/// Lua script with scheduling rules of a channel.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Script {
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    #[serde(skip_deserializing)]
+    pub channel_id: i32,
+    pub name: String,
+    pub code: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(skip_deserializing)]
+    pub updated: String,
+}
+
+/// Helper process of a channel, like a bot or a relay, which runs next to the playout.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Companion {
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    #[serde(skip_deserializing)]
+    pub channel_id: i32,
+    pub name: String,
+    /// Path to the binary.
+    pub command: String,
+    /// Arguments, with variables like `{channel_id}`.
+    #[serde(default)]
+    pub args: String,
+    /// Restart policy: never, on_failure, always.
+    #[serde(default)]
+    pub restart: String,
+    /// Restarts in a row, until the process stays stopped, 0 is unlimited.
+    #[serde(default = "default_max_restarts")]
+    pub max_restarts: i64,
+    /// Start and stop together with the playout of the channel.
+    #[serde(default)]
+    pub autostart: bool,
+}
+
+/// Background job, which transcodes a file to the house format of the channel.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct TranscodeJob {
+    pub id: i32,
+    #[serde(skip_serializing)]
+    pub channel_id: i32,
+    pub source: String,
+    pub target: String,
+    /// `queued`, `running`, `done`, `failed` or `canceled`
+    pub status: String,
+    /// Percent from 0 to 100.
+    pub progress: f64,
+    pub message: String,
+    /// In point of a trimmed copy.
+    pub seek: f64,
+    /// Out point of a trimmed copy, 0 is the whole file.
+    pub out: f64,
+    pub created: String,
+    pub updated: String,
+}
+
+/// Download of a remote url with yt-dlp into the storage of a channel.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct DownloadJob {
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    #[serde(skip)]
+    pub channel_id: i32,
+    pub url: String,
+    /// Target folder, relative to the storage.
+    #[serde(default)]
+    pub folder: String,
+    /// yt-dlp format selector, empty matches the house format.
+    #[serde(default)]
+    pub format: String,
+    /// Append the file to the playlist from this date.
+    #[serde(default)]
+    pub playlist_date: Option<String>,
+    /// Path of the file, when the download is done.
+    #[serde(skip_deserializing)]
+    pub target: String,
+    /// `queued`, `running`, `done`, `failed` or `canceled`
+    #[serde(skip_deserializing)]
+    pub status: String,
+    /// Percent from 0 to 100.
+    #[serde(skip_deserializing)]
+    pub progress: f64,
+    #[serde(skip_deserializing)]
+    pub message: String,
+    #[serde(skip_deserializing)]
+    pub created: String,
+    #[serde(skip_deserializing)]
+    pub updated: String,
+}
+
+/// Commercial break, which replaces the break markers in the playlists with spots.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct AdBreak {
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    #[serde(skip_deserializing)]
+    pub channel_id: i32,
+    pub name: String,
+    /// Target length in seconds.
+    pub duration: f64,
+    /// Folder with the spots, relative to the channel storage.
+    pub spots: String,
+    /// How often one spot can play per day, 0 is unlimited.
+    #[serde(default)]
+    pub max_plays: i64,
+    /// Fill the rest of the break with fillers, otherwise the break gets shorter.
+    #[serde(default = "default_enabled")]
+    pub pad: bool,
+}
+
+/// Plays from one spot on one day.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct SpotPlay {
+    pub source: String,
+    pub date: String,
+    pub plays: i64,
+}
+
+/// Regional output of a channel, it replaces break windows with regional content.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Region {
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    #[serde(skip_deserializing)]
+    pub channel_id: i32,
+    pub name: String,
+    /// Output parameters of the regional encoder, like the output parameters of the channel.
+    pub output_param: String,
+    #[serde(default = "default_enabled")]
+    pub enable: bool,
+}
+
+/// Destination of the stream output, like a RTMP server or a SRT endpoint.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct OutputTarget {
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    #[serde(skip_deserializing)]
+    pub channel_id: i32,
+    pub name: String,
+    pub url: String,
+    /// ffmpeg muxer, empty takes it from the url scheme.
+    #[serde(default)]
+    pub format: String,
+    #[serde(default)]
+    pub stream_key: String,
+    #[serde(default)]
+    pub username: String,
+    /// Password, or passphrase for SRT.
+    #[serde(default)]
+    pub password: String,
+    #[serde(default = "default_enabled")]
+    pub enable: bool,
+}
+
+/// Playout node in a cluster, it runs channels for the API tier.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Node {
+    #[serde(skip_deserializing)]
+    pub id: i32,
+    pub name: String,
+    /// Address of the node API, like `http://10.0.0.2:8787`.
+    pub url: String,
+    /// The node gets no channels, its channels move to the other nodes.
+    #[serde(default)]
+    pub drained: bool,
+}
+
+/// Channel, which runs on a node.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, sqlx::FromRow)]
+pub struct NodeChannel {
+    pub channel_id: i32,
+    pub node_id: i32,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_max_restarts() -> i64 {
+    5
+}
@@ -0,0 +1,140 @@
+use std::sync::{atomic::Ordering, Arc, Mutex};
+
+use serde_json::{Map, Value};
+use sqlx::{Pool, Sqlite};
+
+use crate::db::{db_pool, handles, init_globales, GLOBAL_SETTINGS};
+use crate::player::{
+    controller::{ChannelController, ChannelManager},
+    utils::get_data_map,
+};
+use crate::set_args;
+use crate::utils::{
+    args_parse::Args,
+    config::get_config,
+    control::control_state,
+    errors::ServiceError,
+    logging::MailQueue,
+    startup::{load_channels, start_channels},
+};
+
+/// Playout engine with all channels from the database.
+#[derive(Clone)]
+pub struct Engine {
+    pool: Pool<Sqlite>,
+    controllers: Arc<Mutex<ChannelController>>,
+    mail_queues: Arc<Mutex<Vec<Arc<Mutex<MailQueue>>>>>,
+}
+
+impl Engine {
+    /// Open the database from the arguments, migrate it and load all channels.
+    ///
+    /// The core reads all arguments from here, so they have to be set before anything else from the engine runs.
+    pub async fn new(args: Args) -> Result<Self, ServiceError> {
+        set_args(args).map_err(|_| ServiceError::Conflict("Arguments are already set".into()))?;
+
+        let pool = db_pool()
+            .await
+            .map_err(|e| ServiceError::Conflict(e.to_string()))?;
+
+        Self::with_pool(pool).await
+    }
+
+    /// Use an existing database pool, for example an in-memory database.
+    pub async fn with_pool(pool: Pool<Sqlite>) -> Result<Self, ServiceError> {
+        handles::db_migrate(&pool)
+            .await
+            .map_err(|e| ServiceError::Conflict(e.to_string()))?;
+
+        if GLOBAL_SETTINGS.get().is_none() {
+            init_globales(&pool)
+                .await
+                .map_err(|e| ServiceError::Conflict(e.to_string()))?;
+        }
+
+        let controllers = Arc::new(Mutex::new(ChannelController::new()));
+        let mail_queues = Arc::new(Mutex::new(vec![]));
+
+        load_channels(&pool, &controllers, &mail_queues).await?;
+
+        Ok(Self {
+            pool,
+            controllers,
+            mail_queues,
+        })
+    }
+
+    pub fn pool(&self) -> &Pool<Sqlite> {
+        &self.pool
+    }
+
+    /// Channel controller, which can be shared with the REST API (`web::Data::from`).
+    pub fn controllers(&self) -> Arc<Mutex<ChannelController>> {
+        self.controllers.clone()
+    }
+
+    /// Mail queues of the channels, for the logger (`init_logging`).
+    pub fn mail_queues(&self) -> Arc<Mutex<Vec<Arc<Mutex<MailQueue>>>>> {
+        self.mail_queues.clone()
+    }
+
+    pub fn channel(&self, id: i32) -> Result<ChannelManager, ServiceError> {
+        self.controllers
+            .lock()
+            .unwrap()
+            .get(id)
+            .ok_or_else(|| ServiceError::BadRequest(format!("Channel {id} not found")))
+    }
+
+    /// Start all channels, which are active in the database, and the background workers,
+    /// like the ffplayout server does. Call it only once.
+    pub async fn start_active(&self) {
+        start_channels(&self.pool, &self.controllers, |_| false);
+    }
+
+    pub async fn start(&self, id: i32) -> Result<(), ServiceError> {
+        let manager = self.channel(id)?;
+
+        if !manager.is_alive.load(Ordering::SeqCst) {
+            manager.channel.lock().unwrap().active = true;
+            manager.async_start().await;
+        }
+
+        Ok(())
+    }
+
+    pub async fn stop(&self, id: i32) -> Result<(), ServiceError> {
+        let manager = self.channel(id)?;
+
+        manager.channel.lock().unwrap().active = false;
+        manager.async_stop().await
+    }
+
+    /// Playout control: `back`, `next` or `reset`.
+    pub async fn control(
+        &self,
+        id: i32,
+        command: &str,
+    ) -> Result<Map<String, Value>, ServiceError> {
+        let manager = self.channel(id)?;
+
+        control_state(&self.pool, &manager, command).await
+    }
+
+    /// Current clip and state, the same data as from `/api/control/{id}/media/current`.
+    pub fn status(&self, id: i32) -> Result<Map<String, Value>, ServiceError> {
+        let manager = self.channel(id)?;
+
+        Ok(get_data_map(&manager))
+    }
+
+    /// Reload the config of a channel from the database, after it was changed.
+    pub async fn reload_config(&self, id: i32) -> Result<(), ServiceError> {
+        let manager = self.channel(id)?;
+        let config = get_config(&self.pool, id).await?;
+
+        manager.update_config(config);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+//! Player core from ffplayout, for embedding the playout in other Rust applications.
+//!
+//! The engine runs the playlist resolution, builds the ffmpeg pipelines and controls the channels,
+//! the REST API from ffplayout is only one consumer of it. With this crate other applications
+//! can build their own control plane, without the HTTP layer:
+//!
+//! ```no_run
+//! use ffplayout_core::{Args, Engine};
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), ffplayout_core::ServiceError> {
+//!     let args = Args {
+//!         db: Some("/var/lib/my-app/ffplayout.db".into()),
+//!         ..Default::default()
+//!     };
+//!     let engine = Engine::new(args).await?;
+//!
+//!     engine.start(1).await?;
+//!     println!("{:?}", engine.status(1)?);
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Channels, configs and playlists are stored in the ffplayout database, so the frontend
+//! and the API can work on the same data.
+
+use std::sync::{Arc, LazyLock, Mutex, OnceLock};
+
+use sysinfo::{Disks, Networks, System};
+
+pub mod db;
+pub mod engine;
+pub mod macros;
+pub mod player;
+pub mod utils;
+
+use utils::advanced_config::AdvancedConfig;
+
+pub use db::models::Channel;
+pub use engine::Engine;
+pub use player::{
+    controller::{ChannelController, ChannelManager, ProcessUnit},
+    utils::{JsonPlaylist, Media},
+};
+pub use utils::{
+    air_hours::air_scheduler,
+    args_parse::Args,
+    config::PlayoutConfig,
+    errors::{ProcessError, ServiceError},
+    pull_ingest::pull_ingest,
+    transcode::transcode_worker,
+    watch_folder::watch_folders,
+};
+
+static EMBEDDED_ARGS: OnceLock<Args> = OnceLock::new();
+
+/// Arguments from [`set_args`], the defaults when nothing was set.
+///
+/// The core never parses the command line, the server and applications which embed it
+/// give their arguments.
+pub static ARGS: LazyLock<Args> =
+    LazyLock::new(|| EMBEDDED_ARGS.get().cloned().unwrap_or_default());
+pub static DISKS: LazyLock<Arc<Mutex<Disks>>> =
+    LazyLock::new(|| Arc::new(Mutex::new(Disks::new_with_refreshed_list())));
+pub static NETWORKS: LazyLock<Arc<Mutex<Networks>>> =
+    LazyLock::new(|| Arc::new(Mutex::new(Networks::new_with_refreshed_list())));
+pub static SYS: LazyLock<Arc<Mutex<System>>> =
+    LazyLock::new(|| Arc::new(Mutex::new(System::new_all())));
+
+/// Set the arguments for the core.
+///
+/// This has to happen before the core reads any argument, otherwise the given arguments are returned.
+pub fn set_args(args: Args) -> Result<(), Box<Args>> {
+    EMBEDDED_ARGS.set(args).map_err(Box::new)
+}
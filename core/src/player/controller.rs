@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     fmt, fs,
     io::{self, Read},
     path::Path,
@@ -11,25 +12,56 @@ use std::{
     time::Duration,
 };
 
-use actix_web::web;
 use log::*;
 use m3u8_rs::Playlist;
 use serde::{Deserialize, Serialize};
 use sqlx::{Pool, Sqlite};
+use tokio::task;
 use walkdir::WalkDir;
 
 use crate::player::{
-    output::{player, write_hls},
-    utils::{folder::fill_filler_list, Media},
+    input::{
+        air_watch, dead_air::DeadAir, emergency::OverrideState, playlist_watchdog,
+        switch::InputSwitch,
+    },
+    output::{
+        dash::drain_dash_path,
+        delayed::DelayedStatus,
+        offline::{start_offline, stop_offline},
+        output_changed, player,
+        redundant::EncoderStatus,
+        scte35::CueQueue,
+        write_hls,
+    },
+    utils::{
+        audio_meter::AudioLevels, ffmpeg_events::FfmpegEvents, filler::FillerHistory,
+        folder::fill_filler_list, now_playing::NowPlaying, stream_stats::StreamStats, Media,
+    },
 };
 use crate::utils::{
+    ad_breaks::AdBreaks,
+    companion,
     config::{OutputMode::*, PlayoutConfig},
     errors::{ProcessError, ServiceError},
+    gpu::{assign_gpu, release_gpu},
+    maintenance::{in_maintenance, set_maintenance},
+    object_store::prefetcher,
+    overlay::{reset_overlay, OverlayState},
+    pre_cache::pre_cache_worker,
+    pull_ingest::PullStatus,
+    scripting::Scripts,
+    shutdown::terminate,
+    time_machine::clock_sleep,
+    watch_folder::WatchStatus,
+    widgets::{init_files, widget_updater},
 };
 use crate::ARGS;
 use crate::{
-    db::{handles, models::Channel},
-    utils::logging::Target,
+    db::{
+        handles,
+        models::{Channel, Region},
+    },
+    utils::logging::{set_channel_logging, Target},
 };
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -62,24 +94,67 @@ pub struct ChannelManager {
     pub channel: Arc<Mutex<Channel>>,
     pub decoder: Arc<Mutex<Option<Child>>>,
     pub encoder: Arc<Mutex<Option<Child>>>,
+    pub backup_encoder: Arc<Mutex<Option<Child>>>,
+    pub encoder_status: Arc<Mutex<EncoderStatus>>,
+    /// Time shifted copy of the HLS output.
+    pub delayed_status: Arc<Mutex<DelayedStatus>>,
+    /// Slate in the HLS output, while the channel is stopped.
+    pub offline: Arc<Mutex<Option<Child>>>,
+    /// Output settings changed, the encoder starts again before the next clip.
+    pub reload_output: Arc<AtomicBool>,
     pub ingest: Arc<Mutex<Option<Child>>>,
     pub ingest_is_running: Arc<AtomicBool>,
     pub is_terminated: Arc<AtomicBool>,
+    /// Channel is stopped by the air hours.
+    pub off_air: Arc<AtomicBool>,
+    /// Channel waits for the takeover, on the failover backup.
+    pub standby: Arc<AtomicBool>,
     pub is_alive: Arc<AtomicBool>,
     pub is_processing: Arc<AtomicBool>,
     pub filter_chain: Option<Arc<Mutex<Vec<String>>>>,
     pub current_date: Arc<Mutex<String>>,
     pub list_init: Arc<AtomicBool>,
     pub current_media: Arc<Mutex<Option<Media>>>,
+    /// Delta between playlist and playout clock at the start of the current clip.
+    pub playlist_delta: Arc<Mutex<Option<f64>>>,
     pub current_list: Arc<Mutex<Vec<Media>>>,
     pub filler_list: Arc<Mutex<Vec<Media>>>,
     pub current_index: Arc<AtomicUsize>,
     pub filler_index: Arc<AtomicUsize>,
+    pub filler_history: Arc<Mutex<FillerHistory>>,
+    pub dead_air: Arc<Mutex<DeadAir>>,
+    pub input_switch: Arc<Mutex<InputSwitch>>,
+    /// Emergency override, which plays instead of the schedule.
+    pub emergency: Arc<Mutex<OverrideState>>,
+    pub cues: Arc<Mutex<CueQueue>>,
+    /// Lua scripts with scheduling rules.
+    pub scripts: Arc<Mutex<Option<Scripts>>>,
+    /// Commercial breaks, which are filled when a playlist is loaded.
+    pub ad_breaks: Arc<Mutex<AdBreaks>>,
+    /// Regional outputs, which can replace the break windows.
+    pub regions: Arc<Mutex<Vec<Region>>>,
+    /// Regions changed, the regional encoders start again before the next clip.
+    pub reload_regions: Arc<AtomicBool>,
+    pub watch_status: Arc<Mutex<WatchStatus>>,
+    pub pull_status: Arc<Mutex<PullStatus>>,
+    /// Image or HTML overlay from the control API.
+    pub overlay: Arc<Mutex<OverlayState>>,
+    /// Warnings and errors from the ffmpeg log, since the start of the channel.
+    pub ffmpeg_events: Arc<Mutex<FfmpegEvents>>,
+    /// Health of the ingest and the outputs, by name.
+    pub stream_stats: Arc<Mutex<BTreeMap<String, StreamStats>>>,
+    /// Audio levels of the program, for VU meters.
+    pub audio_levels: Arc<Mutex<AudioLevels>>,
+    /// Current and next item, for the public now playing route.
+    pub now_playing: Arc<Mutex<NowPlaying>>,
     pub run_count: Arc<AtomicUsize>,
 }
 
 impl ChannelManager {
     pub fn new(db_pool: Option<Pool<Sqlite>>, channel: Channel, config: PlayoutConfig) -> Self {
+        set_channel_logging(channel.id, &config.logging);
+        set_maintenance(channel.id, channel.maintenance);
+
         Self {
             db_pool,
             is_alive: Arc::new(AtomicBool::new(false)),
@@ -110,11 +185,37 @@ impl ChannelManager {
 
     pub fn update_config(&self, new_config: PlayoutConfig) {
         let mut config = self.config.lock().unwrap();
+        let id = config.general.channel_id;
+
+        if self.is_alive.load(Ordering::SeqCst) && output_changed(&config, &new_config) {
+            if config.output.mode == new_config.output.mode {
+                info!(target: Target::file_mail(), channel = id; "Output settings changed, reload them with the next clip");
+
+                self.reload_output.store(true, Ordering::SeqCst);
+            } else {
+                warn!(target: Target::file_mail(), channel = id; "Output mode changed, restart the channel to apply it");
+            }
+        }
+
+        if config.logging != new_config.logging {
+            set_channel_logging(id, &new_config.logging);
+        }
+
         *config = new_config;
     }
 
     pub async fn async_start(&self) {
+        self.off_air.store(false, Ordering::SeqCst);
+
         if !self.is_alive.load(Ordering::SeqCst) {
+            let channel_id = self.channel.lock().unwrap().id;
+            let gpu = self.config.lock().unwrap().channel.limits.gpu;
+
+            if let Err(e) = assign_gpu(channel_id, gpu) {
+                error!(target: Target::file_mail(), channel = channel_id; "Channel is not started: {e}");
+                return;
+            }
+
             self.run_count.fetch_add(1, Ordering::SeqCst);
             self.is_alive.store(true, Ordering::SeqCst);
             self.is_terminated.store(false, Ordering::SeqCst);
@@ -122,12 +223,13 @@ impl ChannelManager {
 
             let pool_clone = self.db_pool.clone().unwrap();
             let self_clone = self.clone();
-            let channel_id = self.channel.lock().unwrap().id;
 
             if let Err(e) = handles::update_player(&pool_clone, channel_id, true).await {
                 error!(target: Target::all(), channel = channel_id; "Unable write to player status: {e}");
             };
 
+            companion::start_auto(&pool_clone, self).await;
+
             thread::spawn(move || {
                 loop {
                     let run_count = self_clone.run_count.clone();
@@ -139,6 +241,16 @@ impl ChannelManager {
 
                     let active = self_clone.channel.lock().unwrap().active;
                     if !active {
+                        start_offline(&self_clone);
+                        break;
+                    }
+
+                    if in_maintenance(channel_id) {
+                        warn!(target: Target::file(), channel = channel_id; "Channel is in maintenance, playout is not started again");
+
+                        self_clone.is_alive.store(false, Ordering::SeqCst);
+                        release_gpu(channel_id);
+                        start_offline(&self_clone);
                         break;
                     }
 
@@ -147,7 +259,7 @@ impl ChannelManager {
                     self_clone.is_terminated.store(false, Ordering::SeqCst);
                     self_clone.list_init.store(true, Ordering::SeqCst);
 
-                    thread::sleep(Duration::from_millis(250));
+                    clock_sleep(Duration::from_millis(250));
                 }
 
                 trace!("Async start done");
@@ -194,6 +306,12 @@ impl ChannelManager {
         }
     }
 
+    /// Live ingest can be played, the input switch allows it and no override is running.
+    pub fn accepts_ingest(&self) -> bool {
+        self.input_switch.lock().unwrap().source.accepts_ingest()
+            && !self.emergency.lock().unwrap().active
+    }
+
     pub fn stop(&self, unit: ProcessUnit) -> Result<(), ProcessError> {
         match unit {
             Decoder => {
@@ -203,6 +321,10 @@ impl ChannelManager {
                 }
             }
             Encoder => {
+                if let Some(proc) = self.backup_encoder.lock()?.as_mut() {
+                    let _ = proc.kill();
+                }
+
                 if let Some(proc) = self.encoder.lock()?.as_mut() {
                     proc.kill()
                         .map_err(|e| ProcessError::Custom(format!("Encoder: {e}")))?;
@@ -230,7 +352,7 @@ impl ChannelManager {
             loop {
                 match proc.try_wait() {
                     Ok(Some(_)) => break,
-                    Ok(None) => thread::sleep(Duration::from_millis(10)),
+                    Ok(None) => clock_sleep(Duration::from_millis(10)),
                     Err(e) => return Err(ProcessError::Custom(format!("{unit}: {e}"))),
                 }
             }
@@ -244,11 +366,14 @@ impl ChannelManager {
     pub fn wait(&self, unit: ProcessUnit) -> Result<(), ProcessError> {
         match unit {
             Decoder => self.run_wait(unit, &self.decoder)?,
-            Encoder => self.run_wait(unit, &self.encoder)?,
+            Encoder => {
+                self.run_wait(unit, &self.backup_encoder)?;
+                self.run_wait(unit, &self.encoder)?;
+            }
             Ingest => self.run_wait(unit, &self.ingest)?,
         }
 
-        thread::sleep(Duration::from_millis(50));
+        clock_sleep(Duration::from_millis(50));
 
         Ok(())
     }
@@ -260,6 +385,7 @@ impl ChannelManager {
             debug!(target: Target::all(), channel = channel_id; "Deactivate playout and stop all child processes from channel: <yellow>{channel_id}</>");
         }
 
+        self.off_air.store(false, Ordering::SeqCst);
         self.is_terminated.store(true, Ordering::SeqCst);
         self.is_alive.store(false, Ordering::SeqCst);
         self.ingest_is_running.store(false, Ordering::SeqCst);
@@ -270,19 +396,56 @@ impl ChannelManager {
             error!(target: Target::all(), channel = channel_id; "Unable write to player status: {e}");
         };
 
+        companion::stop_auto(channel_id).await;
+
         for unit in [Decoder, Encoder, Ingest] {
             let self_clone = self.clone();
 
-            if let Err(e) = web::block(move || self_clone.stop(unit)).await? {
+            if let Err(e) = task::spawn_blocking(move || self_clone.stop(unit)).await? {
                 if !e.to_string().contains("exited process") {
                     error!(target: Target::all(), channel = channel_id; "{e}");
                 }
             }
         }
 
+        release_gpu(channel_id);
+
         Ok(())
     }
 
+    /// Stop the channel for a shutdown: the ffmpeg processes get SIGTERM, to close their outputs,
+    /// and are killed after the timeout.
+    pub fn graceful_stop(&self, timeout: Duration) {
+        let channel_id = self.channel.lock().unwrap().id;
+
+        self.is_terminated.store(true, Ordering::SeqCst);
+        self.ingest_is_running.store(false, Ordering::SeqCst);
+
+        if self.is_alive.swap(false, Ordering::SeqCst) {
+            debug!(target: Target::all(), channel = channel_id; "Stop all child processes from channel: <yellow>{channel_id}</>");
+
+            self.run_count.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        // the encoder goes first, so it gets no broken input
+        let children = [
+            &self.encoder,
+            &self.backup_encoder,
+            &self.ingest,
+            &self.decoder,
+            &self.offline,
+        ]
+        .iter()
+        .filter_map(|slot| slot.lock().unwrap().take())
+        .collect();
+
+        let killed = terminate(children, timeout);
+
+        if killed > 0 {
+            warn!(target: Target::all(), channel = channel_id; "Killed <yellow>{killed}</> ffmpeg processes after the shutdown timeout");
+        }
+    }
+
     /// No matter what is running, terminate them all.
     pub fn stop_all(&self) {
         let channel_id = self.channel.lock().unwrap().id;
@@ -350,9 +513,26 @@ pub fn start_channel(manager: ChannelManager) -> Result<(), ProcessError> {
     let mode = config.output.mode.clone();
     let filler_list = manager.filler_list.clone();
     let channel_id = config.general.channel_id;
+    let prefetch = config.storage.object_store.enable;
+    let pre_cache = config.storage.pre_cache.enable;
+    let widgets = config.text.widgets.enabled();
+    let overlay = config.text.overlay.enable;
+
+    // the config is fresh, nothing to reload
+    manager.reload_output.store(false, Ordering::SeqCst);
+
+    stop_offline(&manager);
+    *manager.ffmpeg_events.lock()? = FfmpegEvents::default();
+    manager.stream_stats.lock()?.clear();
+    *manager.audio_levels.lock()? = AudioLevels::default();
+    *manager.now_playing.lock()? = NowPlaying::default();
 
     drain_hls_path(&config.channel.public)?;
 
+    if mode == Dash {
+        drain_dash_path(&config.channel.public)?;
+    }
+
     debug!(target: Target::all(), channel = channel_id; "Start ffplayout v{VERSION}, channel: <yellow>{channel_id}</>");
 
     // Fill filler list, can also be a single file.
@@ -360,6 +540,37 @@ pub fn start_channel(manager: ChannelManager) -> Result<(), ProcessError> {
         fill_filler_list(&config, Some(filler_list));
     });
 
+    let manager_watchdog = manager.clone();
+
+    thread::spawn(move || playlist_watchdog(manager_watchdog));
+
+    let manager_air = manager.clone();
+
+    thread::spawn(move || air_watch(manager_air));
+
+    if prefetch {
+        let manager_prefetch = manager.clone();
+
+        thread::spawn(move || prefetcher(manager_prefetch));
+    }
+
+    if pre_cache {
+        let manager_cache = manager.clone();
+
+        thread::spawn(move || pre_cache_worker(manager_cache));
+    }
+
+    if widgets {
+        let manager_widgets = manager.clone();
+
+        init_files(channel_id);
+        thread::spawn(move || widget_updater(manager_widgets));
+    }
+
+    if overlay {
+        reset_overlay(&manager);
+    }
+
     match mode {
         // write files/playlist to HLS m3u8 playlist
         HLS => write_hls(manager),
@@ -394,7 +605,7 @@ fn find_m3u8_files(path: &Path) -> io::Result<Vec<String>> {
     for entry in WalkDir::new(path)
         .into_iter()
         .filter_map(Result::ok)
-        .filter(|e| e.path().is_file() && e.path().extension().map_or(false, |ext| ext == "m3u8"))
+        .filter(|e| e.path().is_file() && e.path().extension().is_some_and(|ext| ext == "m3u8"))
     {
         m3u8_files.push(entry.path().to_string_lossy().to_string());
     }
@@ -414,7 +625,7 @@ fn delete_old_segments<P: AsRef<Path> + Clone + std::fmt::Debug>(
             e.path().is_file()
                 && e.path()
                     .extension()
-                    .map_or(false, |ext| ext == "ts" || ext == "vtt")
+                    .is_some_and(|ext| ext == "ts" || ext == "vtt")
         })
     {
         let filename = entry.file_name().to_string_lossy().to_string();
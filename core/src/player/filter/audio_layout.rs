@@ -0,0 +1,129 @@
+/*
+Channel layout of the audio tracks.
+
+The output has the channel count from `processing.audio_channels`: 1 (mono), 2 (stereo),
+6 (5.1) or 8 (7.1). A source track with another channel count gets an explicit conversion:
+
+- a playlist item with `channel_map` gets a `pan` filter with this map, like `c0=c1|c1=c1`
+- a 5.1 source for stereo output gets the downmix from `processing.audio_downmix`
+- otherwise `aformat` converts the layout, with the default mix from ffmpeg
+
+Tracks with the output channel count are not touched. Missing tracks are filled
+with silence in the output layout.
+
+*/
+
+use regex::Regex;
+
+use crate::utils::config::Downmix;
+
+/// Layout name for the channel count of the output.
+pub fn output_layout(channels: u8) -> Option<&'static str> {
+    match channels {
+        1 => Some("mono"),
+        2 => Some("stereo"),
+        6 => Some("5.1"),
+        8 => Some("7.1"),
+        _ => None,
+    }
+}
+
+/// Check the channel map of a playlist item, like `c0=c1|c1=c1` or `c0<c0+c2`.
+pub fn validate_channel_map(map: &str) -> Result<(), String> {
+    let re = Regex::new(r"^c\d+\s*[=<]\s*[\w.*+\- ]+$").unwrap();
+
+    if let Some(part) = map.split('|').find(|p| !re.is_match(p.trim())) {
+        return Err(format!("Invalid channel map: {part}"));
+    }
+
+    Ok(())
+}
+
+/// Filter for a source track with `source_channels`, `None` when the layout fits.
+pub fn layout_filter(
+    channels: u8,
+    source_channels: Option<i64>,
+    downmix: Downmix,
+    channel_map: Option<&str>,
+) -> Option<String> {
+    let layout = output_layout(channels)?;
+
+    if let Some(map) = channel_map.filter(|m| !m.trim().is_empty()) {
+        return Some(format!("pan={layout}|{}", map.trim()));
+    }
+
+    let source_channels = source_channels.filter(|c| *c > 0)?;
+
+    if source_channels == i64::from(channels) {
+        return None;
+    }
+
+    if source_channels == 6 && channels == 2 {
+        // 5.1: c0 FL, c1 FR, c2 FC, c3 LFE, c4 BL/SL, c5 BR/SR
+        match downmix {
+            Downmix::Itu => {
+                return Some(
+                    "pan=stereo|c0<c0+0.707*c2+0.707*c4|c1<c1+0.707*c2+0.707*c5".to_string(),
+                )
+            }
+            Downmix::Front => return Some("pan=stereo|c0<c0+0.707*c2|c1<c1+0.707*c2".to_string()),
+            Downmix::Auto => {}
+        }
+    }
+
+    Some(format!("aformat=channel_layouts={layout}"))
+}
+
+/// Layout of the silence for missing tracks.
+pub fn silence_layout(channels: u8) -> &'static str {
+    output_layout(channels).unwrap_or("stereo")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::utils::Media;
+
+    #[test]
+    fn audio_layouts() {
+        assert_eq!(output_layout(6), Some("5.1"));
+        assert_eq!(output_layout(4), None);
+        assert_eq!(silence_layout(6), "5.1");
+        assert_eq!(silence_layout(4), "stereo");
+
+        assert_eq!(layout_filter(2, Some(2), Downmix::Itu, None), None);
+        assert_eq!(layout_filter(2, None, Downmix::Itu, None), None);
+        assert_eq!(
+            layout_filter(2, Some(6), Downmix::Auto, None).unwrap(),
+            "aformat=channel_layouts=stereo"
+        );
+        assert_eq!(
+            layout_filter(2, Some(6), Downmix::Itu, None).unwrap(),
+            "pan=stereo|c0<c0+0.707*c2+0.707*c4|c1<c1+0.707*c2+0.707*c5"
+        );
+        assert_eq!(
+            layout_filter(2, Some(6), Downmix::Front, None).unwrap(),
+            "pan=stereo|c0<c0+0.707*c2|c1<c1+0.707*c2"
+        );
+        assert_eq!(
+            layout_filter(6, Some(2), Downmix::Itu, None).unwrap(),
+            "aformat=channel_layouts=5.1"
+        );
+        assert_eq!(
+            layout_filter(2, Some(2), Downmix::Auto, Some("c0=c0|c1=c0")).unwrap(),
+            "pan=stereo|c0=c0|c1=c0"
+        );
+
+        assert!(validate_channel_map("c0=c0|c1=c0").is_ok());
+        assert!(validate_channel_map("c0<c0+0.5*c2|c1<c1+0.5*c2").is_ok());
+        assert!(validate_channel_map("c0=c1,volume=2").is_err());
+        assert!(validate_channel_map("c0=c0|[x]").is_err());
+
+        let item: Media = serde_json::from_str(
+        r#"{"in": 0, "out": 10, "duration": 10, "source": "a.mp4", "channel_map": "c0=c1|c1=c1"}"#,
+    )
+    .unwrap();
+
+        assert_eq!(item.channel_map.as_deref(), Some("c0=c1|c1=c1"));
+    }
+}
@@ -0,0 +1,205 @@
+use std::{
+    process::{Command, Stdio},
+    sync::OnceLock,
+};
+
+use log::*;
+use regex::Regex;
+
+use crate::utils::logging::Target;
+
+/// Apply custom filters
+pub fn filter_node(id: i32, filter: &str) -> (String, String) {
+    let re = Regex::new(r"^;?(\[[0-9]:[^\[]+\])?|\[[^\[]+\]$").unwrap(); // match start/end link
+    let mut video_filter = String::new();
+    let mut audio_filter = String::new();
+
+    // match chain with audio and video filter
+    if filter.contains("[c_v_out]") && filter.contains("[c_a_out]") {
+        let v_pos = filter.find("[c_v_out]").unwrap();
+        let a_pos = filter.find("[c_a_out]").unwrap();
+        let mut delimiter = "[c_v_out]";
+
+        // split delimiter should be first filter output link
+        if v_pos > a_pos {
+            delimiter = "[c_a_out]";
+        }
+
+        if let Some((f_1, f_2)) = filter.split_once(delimiter) {
+            if f_2.contains("[c_a_out]") {
+                video_filter = re.replace_all(f_1, "").to_string();
+                audio_filter = re.replace_all(f_2, "").to_string();
+            } else {
+                video_filter = re.replace_all(f_2, "").to_string();
+                audio_filter = re.replace_all(f_1, "").to_string();
+            }
+        }
+    } else if filter.contains("[c_v_out]") {
+        video_filter = re.replace_all(filter, "").to_string();
+    } else if filter.contains("[c_a_out]") {
+        audio_filter = re.replace_all(filter, "").to_string();
+    } else if !filter.is_empty() && filter != "~" {
+        error!(target: Target::file_mail(), channel = id; "Custom filter is not well formatted, use correct out link names (\"[c_v_out]\" and/or \"[c_a_out]\"). Filter skipped!");
+    }
+
+    (video_filter, audio_filter)
+}
+
+/// Split a filter graph into its filters, at `,` and `;` which are not escaped or quoted.
+fn split_graph(filter: &str) -> Result<Vec<String>, String> {
+    let mut parts = vec![];
+    let mut part = String::new();
+    let mut chars = filter.chars();
+    let mut quoted = false;
+    let mut depth = 0;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                part.push(c);
+                part.extend(chars.next());
+                continue;
+            }
+            '\'' => quoted = !quoted,
+            '[' if !quoted => depth += 1,
+            ']' if !quoted => {
+                if depth == 0 {
+                    return Err("Unmatched ] in custom filter".to_string());
+                }
+
+                depth -= 1;
+            }
+            ',' | ';' if !quoted && depth == 0 => {
+                parts.push(part.trim().to_string());
+                part.clear();
+                continue;
+            }
+            _ => {}
+        }
+
+        part.push(c);
+    }
+
+    if quoted {
+        return Err("Unclosed quote in custom filter".to_string());
+    }
+
+    if depth > 0 {
+        return Err("Unclosed [ in custom filter".to_string());
+    }
+
+    parts.push(part.trim().to_string());
+
+    Ok(parts)
+}
+
+/// Names of the filters in a filter graph, without link labels, options and instance names.
+pub fn filter_names(filter: &str) -> Result<Vec<String>, String> {
+    let re = Regex::new(r"^(\[[^\]]*\]\s*)*").unwrap();
+    let mut names = vec![];
+
+    for part in split_graph(filter)? {
+        let part = re.replace(&part, "");
+        let name: String = part
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+            .collect();
+
+        // a link label alone, like `[v_in];` at the start
+        if part.is_empty() {
+            continue;
+        }
+
+        if name.is_empty() {
+            return Err(format!("Invalid filter in custom filter: {part}"));
+        }
+
+        names.push(name);
+    }
+
+    Ok(names)
+}
+
+/// Filters from the installed ffmpeg, empty when ffmpeg is not found.
+pub fn ffmpeg_filters() -> &'static [String] {
+    static FILTERS: OnceLock<Vec<String>> = OnceLock::new();
+
+    FILTERS.get_or_init(|| {
+        let Ok(output) = Command::new("ffmpeg")
+            .args(["-hide_banner", "-filters"])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+        else {
+            return vec![];
+        };
+
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|l| l.contains('>'))
+            .filter_map(|l| {
+                let line = l.split_whitespace().collect::<Vec<_>>();
+
+                (line.len() > 2).then(|| line[1].to_string())
+            })
+            .collect()
+    })
+}
+
+/// Check a custom filter from the config or from a playlist item.
+///
+/// The filter needs the out link `[c_v_out]` and/or `[c_a_out]`, each only once. When the
+/// filters from ffmpeg are known, all filter names must be in the list.
+pub fn validate_filter(filter: &str, ffmpeg_filters: &[String]) -> Result<(), String> {
+    if filter.is_empty() || filter == "~" {
+        return Ok(());
+    }
+
+    let v_out = filter.matches("[c_v_out]").count();
+    let a_out = filter.matches("[c_a_out]").count();
+
+    if v_out + a_out == 0 {
+        return Err(
+            "Custom filter needs the out link name \"[c_v_out]\" and/or \"[c_a_out]\"".to_string(),
+        );
+    }
+
+    if v_out > 1 || a_out > 1 {
+        return Err("Out link names in custom filter are used more than once".to_string());
+    }
+
+    for name in filter_names(filter)? {
+        if !ffmpeg_filters.is_empty() && !ffmpeg_filters.contains(&name) {
+            return Err(format!("Unknown filter in custom filter: {name}"));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec_strings;
+
+    #[test]
+    fn custom_filter_check() {
+        let known = vec_strings!["gblur", "volume", "movie", "scale", "overlay", "drawtext"];
+
+        assert_eq!(
+            filter_names("[v_in];movie=a.png,scale=1024:576[l];[v_in][l]overlay=0:0[c_v_out]")
+                .unwrap(),
+            vec!["movie", "scale", "overlay"]
+        );
+        assert!(validate_filter("", &known).is_ok());
+        assert!(validate_filter("~", &known).is_ok());
+        assert!(validate_filter("gblur=5[c_v_out];volume=0.5[c_a_out]", &known).is_ok());
+        assert!(validate_filter("drawtext=text='a, b; c':x=10[c_v_out]", &known).is_ok());
+        assert!(validate_filter("gblur=5", &known).is_err());
+        assert!(validate_filter("gblur=5[c_v_out];gblur=2[c_v_out]", &known).is_err());
+        assert!(validate_filter("drawtext=text='open[c_v_out]", &known).is_err());
+        assert!(validate_filter("gblur=5[c_v_out", &known).is_err());
+        assert!(validate_filter("edgedetect[c_v_out]", &known).is_err());
+        assert!(validate_filter("edgedetect[c_v_out]", &[]).is_ok());
+    }
+}
@@ -1,22 +1,39 @@
 use std::{
+    collections::HashMap,
     fmt,
     path::Path,
     sync::{Arc, Mutex},
 };
 
+use chrono::Datelike;
 use log::*;
 use regex::Regex;
 
-mod custom;
+pub mod audio_layout;
+pub mod custom;
 pub mod v_drawtext;
 
 use crate::player::{
     controller::ProcessUnit::*,
-    utils::{custom_format, fps_calc, is_close, Media},
+    filter::audio_layout::{layout_filter, silence_layout},
+    input::dead_air::detect_filter,
+    output::{broadcast::broadcast_filter, decklink::audio_filter},
+    utils::{
+        audio_meter::meter_filter,
+        audio_tracks::audio_sources,
+        custom_format, fps_calc, is_close,
+        subtitles::{burn_filter, find_subtitle},
+        time_in_seconds, time_to_sec, Media,
+    },
 };
 use crate::utils::{
-    config::{OutputMode::*, PlayoutConfig},
+    config::{
+        logo_exists, AspectPolicy, LogoRule, OutputMode::*, PlayoutConfig, TransitionKind,
+        IMAGE_FORMAT,
+    },
     logging::Target,
+    overlay::canvas_file,
+    time_machine::time_now,
 };
 use crate::vec_strings;
 
@@ -52,6 +69,7 @@ pub struct Filters {
     video_position: i32,
     audio_last: i32,
     video_last: i32,
+    audio_sources: HashMap<i32, i32>,
 }
 
 impl Filters {
@@ -69,23 +87,31 @@ impl Filters {
             audio_position,
             video_position: 0,
             audio_last: -1,
+            audio_sources: HashMap::new(),
             video_last: -1,
         }
     }
 
+    /// Read the output audio track from another source track.
+    pub fn set_audio_source(&mut self, track_nr: i32, source: i32) {
+        self.audio_sources.insert(track_nr, source);
+    }
+
     pub fn add_filter(&mut self, filter: &str, track_nr: i32, filter_type: FilterType) {
-        let (map, chain, position, last) = match filter_type {
+        let (map, chain, position, last, source_nr) = match filter_type {
             Audio => (
                 &mut self.audio_map,
                 &mut self.audio_chain,
                 self.audio_position,
                 &mut self.audio_last,
+                *self.audio_sources.get(&track_nr).unwrap_or(&track_nr),
             ),
             Video => (
                 &mut self.video_map,
                 &mut self.video_chain,
                 self.video_position,
                 &mut self.video_last,
+                track_nr,
             ),
         };
 
@@ -108,7 +134,7 @@ impl Filters {
             } else {
                 chain.push_str(&format!(
                     // build audio/video selector like [0:a:0]
-                    "{sep}[{position}:{filter_type}:{track_nr}]{filter}",
+                    "{sep}[{position}:{filter_type}:{source_nr}]{filter}",
                 ));
             }
 
@@ -242,6 +268,39 @@ fn pad(aspect: f64, chain: &mut Filters, v_stream: &ffprobe::Stream, config: &Pl
     }
 }
 
+/// Crop or blur filter for the aspect policy, pad and stretch have their own handling.
+pub fn aspect_filter(policy: AspectPolicy, width: i64, height: i64) -> Option<String> {
+    match policy {
+        AspectPolicy::Crop => Some(format!(
+            "crop=min(iw\\,ih*({width}/{height})):min(ih\\,iw/({width}/{height}))"
+        )),
+        AspectPolicy::Blur => Some(format!(
+            "split[ap_bg][ap_fg];[ap_bg]scale={width}:{height}:force_original_aspect_ratio=increase,crop={width}:{height},boxblur=20:2[ap_blur];\
+            [ap_fg]scale={width}:{height}:force_original_aspect_ratio=decrease[ap_pic];[ap_blur][ap_pic]overlay=(W-w)/2:(H-h)/2"
+        )),
+        AspectPolicy::Pad | AspectPolicy::Stretch => None,
+    }
+}
+
+/// Fit a clip with another aspect into the channel, the stretch happens in the scale filter.
+fn fit_aspect(
+    aspect: f64,
+    policy: AspectPolicy,
+    chain: &mut Filters,
+    v_stream: &ffprobe::Stream,
+    config: &PlayoutConfig,
+) {
+    if policy == AspectPolicy::Pad {
+        pad(aspect, chain, v_stream, config);
+    } else if !is_close(aspect, config.processing.aspect, 0.03) {
+        if let Some(filter) =
+            aspect_filter(policy, config.processing.width, config.processing.height)
+        {
+            chain.add_filter(&filter, 0, Video);
+        }
+    }
+}
+
 fn fps(fps: f64, chain: &mut Filters, config: &PlayoutConfig) {
     if fps != config.processing.fps {
         let fps_filter = match config.advanced.filter.fps.clone() {
@@ -309,6 +368,45 @@ fn scale(
     }
 }
 
+/// Get the length of the fade to black in seconds, from playlist item or channel defaults.
+/// Crossfades are not faded here, see [`crossfade_duration`].
+fn transition_duration(
+    node: &Media,
+    filter_type: FilterType,
+    config: &PlayoutConfig,
+) -> Option<f64> {
+    if node.unit != Decoder {
+        return None;
+    }
+
+    let transition = node.transition.unwrap_or_default();
+    let kind = transition.kind.unwrap_or(config.processing.transition);
+    let frames = if filter_type == Audio {
+        let channel_frames = match config.processing.transition_audio_frames {
+            0 => config.processing.transition_frames,
+            f => f,
+        };
+
+        transition
+            .audio_frames
+            .or(transition.frames)
+            .unwrap_or(channel_frames)
+    } else {
+        transition
+            .frames
+            .unwrap_or(config.processing.transition_frames)
+    };
+
+    match kind {
+        TransitionKind::Fade | TransitionKind::Audio
+            if frames > 0 && (kind == TransitionKind::Fade || filter_type == Audio) =>
+        {
+            Some(frames as f64 / config.processing.fps)
+        }
+        _ => None,
+    }
+}
+
 fn fade(
     node: &mut Media,
     chain: &mut Filters,
@@ -327,7 +425,31 @@ fn fade(
         }
     }
 
-    if node.seek > 0.0 || node.unit == Ingest {
+    if let Some(duration) = node.fade_in {
+        chain.add_filter(&format!("{t}fade=in:st=0:d={duration}"), nr, filter_type);
+    }
+
+    if let Some(duration) = transition_duration(node, filter_type, config) {
+        let length = node.out - node.seek;
+
+        if length > duration * 2.0 {
+            if !node.crossfaded && node.fade_in.is_none() {
+                chain.add_filter(&format!("{t}fade=in:st=0:d={duration}"), nr, filter_type);
+            }
+
+            if node.crossfade.is_none() {
+                chain.add_filter(
+                    &format!("{t}fade=out:st={}:d={duration}", length - duration),
+                    nr,
+                    filter_type,
+                );
+            }
+
+            return;
+        }
+    }
+
+    if ((node.seek > 0.0 && !node.crossfaded) || node.unit == Ingest) && node.fade_in.is_none() {
         let mut fade_in = format!("{t}fade=in:st=0:d=0.5");
 
         if t == "a" {
@@ -341,7 +463,9 @@ fn fade(
         chain.add_filter(&fade_in, nr, filter_type);
     }
 
-    if (node.out != node.duration && node.out - node.seek > 1.0) || fade_audio {
+    if ((node.out != node.duration && node.out - node.seek > 1.0) || fade_audio)
+        && node.crossfade.is_none()
+    {
         let mut fade_out = format!("{t}fade=out:st={}:d=1.0", (node.out - node.seek - 1.0));
 
         if t == "a" {
@@ -356,25 +480,98 @@ fn fade(
     }
 }
 
+/// Crossfade length in seconds, when the clip should blend into the next clip.
+pub fn crossfade_duration(node: &Media, next: &Media, config: &PlayoutConfig) -> Option<f64> {
+    let transition = node.transition.unwrap_or_default();
+    let kind = transition.kind.unwrap_or(config.processing.transition);
+    let frames = transition
+        .frames
+        .unwrap_or(config.processing.transition_frames);
+    let duration = frames as f64 / config.processing.fps;
+
+    if kind != TransitionKind::Crossfade
+        || frames <= 0
+        || config.processing.copy_audio
+        || config.processing.copy_video
+        || !node.audio.is_empty()
+        || !next.audio.is_empty()
+        || node.out - node.seek <= duration * 2.0
+        || next.out - next.seek <= duration * 2.0
+    {
+        return None;
+    }
+
+    Some(duration)
+}
+
+/// Blend the end of the clip with the begin of the next clip,
+/// the next clip is the last input, cut to the crossfade length.
+fn crossfade(
+    node: &Media,
+    chain: &mut Filters,
+    nr: i32,
+    filter_type: FilterType,
+    config: &PlayoutConfig,
+) {
+    let Some(crossfade) = &node.crossfade else {
+        return;
+    };
+    let input = crossfade.input;
+    let duration = crossfade.duration;
+
+    if filter_type == Video {
+        let w = config.processing.width;
+        let h = config.processing.height;
+        let fps = config.processing.fps;
+        let offset = node.out - node.seek - duration;
+        // xfade needs the same size, aspect and frame rate on both inputs
+        let next = format!("[{input}:v:0]scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={fps}[xf_v_next]");
+
+        chain.add_filter(
+            &format!("setsar=1,fps={fps}[xf_v_in];{next};[xf_v_in][xf_v_next]xfade=transition=fade:duration={duration}:offset={offset}"),
+            nr,
+            Video,
+        );
+    } else if crossfade.has_audio {
+        chain.add_filter(
+            &format!("anull[xf_a_in];[xf_a_in][{input}:a:0]acrossfade=d={duration}"),
+            nr,
+            Audio,
+        );
+    }
+}
+
+/// Movie source for logos, image sequences need the image2 demuxer.
+fn logo_movie(path: &str) -> String {
+    let escaped = path.replace('\\', "/").replace(':', "\\\\:");
+
+    if path.contains('%') {
+        format!("movie={escaped}:f=image2:loop=0")
+    } else {
+        format!("movie={escaped}:loop=0")
+    }
+}
+
 fn overlay(node: &mut Media, chain: &mut Filters, config: &PlayoutConfig) {
     if config.processing.add_logo
-        && Path::new(&config.processing.logo_path).is_file()
+        && logo_exists(Path::new(&config.processing.logo_path))
         && &node.category != "advertisement"
     {
         let mut logo_chain = match config.advanced.filter.logo.clone() {
-            Some(logo) => custom_format(&logo, &[config
-                .processing
-                .logo_path
-                .replace('\\', "/")
-                .replace(':', "\\\\:"),
-            config.processing.logo_opacity.to_string()]),
+            Some(logo) => custom_format(
+                &logo,
+                &[
+                    config
+                        .processing
+                        .logo_path
+                        .replace('\\', "/")
+                        .replace(':', "\\\\:"),
+                    config.processing.logo_opacity.to_string(),
+                ],
+            ),
             None => format!(
-                "null[v];movie={}:loop=0,setpts=N/(FRAME_RATE*TB),format=rgba,colorchannelmixer=aa={}",
-                config
-                    .processing
-                    .logo_path
-                    .replace('\\', "/")
-                    .replace(':', "\\\\:"),
+                "null[v];{},setpts=N/(FRAME_RATE*TB),format=rgba,colorchannelmixer=aa={}",
+                logo_movie(&config.processing.logo_path),
                 config.processing.logo_opacity,
             ),
         };
@@ -428,6 +625,106 @@ fn overlay(node: &mut Media, chain: &mut Filters, config: &PlayoutConfig) {
     }
 }
 
+/// Time ranges, relative to the clip start, in which a logo rule is active.
+///
+/// `begin` is the clip start in seconds of the day, `weekday` goes from 1 (monday) to 7 (sunday).
+pub fn logo_intervals(rule: &LogoRule, begin: f64, length: f64, weekday: u32) -> Vec<(f64, f64)> {
+    if !rule.days.is_empty() && !rule.days.contains(&weekday) {
+        return vec![];
+    }
+
+    let start = if rule.start.is_empty() {
+        0.0
+    } else {
+        time_to_sec(&rule.start)
+    };
+    let end = if rule.end.is_empty() {
+        86400.0
+    } else {
+        time_to_sec(&rule.end)
+    };
+    let windows = if end > start {
+        vec![(start, end)]
+    } else {
+        vec![(start, 86400.0), (0.0, end)]
+    };
+    let mut intervals = vec![];
+
+    for (w_start, w_end) in windows {
+        // check also the next day, for clips which runs over midnight
+        for offset in [0.0, 86400.0] {
+            let i_start = (w_start + offset - begin).max(0.0);
+            let i_end = (w_end + offset - begin).min(length);
+
+            if i_end > i_start {
+                intervals.push((i_start, i_end));
+            }
+        }
+    }
+
+    intervals
+}
+
+/// Overlay scheduled logos. Logos switch inside a running clip with the overlay `enable` option,
+/// so no ffmpeg restart is needed.
+fn overlay_logos(node: &mut Media, chain: &mut Filters, config: &PlayoutConfig) {
+    if &node.category == "advertisement" {
+        return;
+    }
+
+    let now = time_now();
+    let begin = time_in_seconds();
+    let length = if node.unit == Ingest {
+        86400.0
+    } else {
+        node.out - node.seek
+    };
+
+    for (i, rule) in config.processing.logos.iter().enumerate() {
+        if (rule.ingest && node.unit != Ingest) || !logo_exists(Path::new(&rule.abs_path)) {
+            continue;
+        }
+
+        let intervals = logo_intervals(rule, begin, length, now.weekday().number_from_monday());
+
+        if intervals.is_empty() {
+            continue;
+        }
+
+        let enable = if intervals.len() == 1 && intervals[0] == (0.0, length) {
+            String::new()
+        } else {
+            let between: Vec<String> = intervals
+                .iter()
+                .map(|(s, e)| format!("between(t,{s},{e})"))
+                .collect();
+
+            format!(":enable='{}'", between.join("+"))
+        };
+        let position = if rule.position.is_empty() {
+            &config.processing.logo_position
+        } else {
+            &rule.position
+        };
+
+        let mut logo_chain = format!(
+            "null[vl{i}];{},setpts=N/(FRAME_RATE*TB),format=rgba,colorchannelmixer=aa={}",
+            logo_movie(&rule.abs_path),
+            rule.opacity
+        );
+
+        if !rule.scale.is_empty() {
+            logo_chain.push_str(&format!(",scale={}", rule.scale));
+        }
+
+        logo_chain.push_str(&format!(
+            "[ll{i}];[vl{i}][ll{i}]overlay={position}:shortest=1{enable}"
+        ));
+
+        chain.add_filter(&logo_chain, 0, Video);
+    }
+}
+
 fn extend_video(node: &mut Media, chain: &mut Filters, config: &PlayoutConfig) {
     if let Some(video_duration) = node
         .probe
@@ -449,6 +746,15 @@ fn extend_video(node: &mut Media, chain: &mut Filters, config: &PlayoutConfig) {
     }
 }
 
+/// Render the subtitle into the video.
+fn burn_subtitles(node: &Media, chain: &mut Filters, config: &PlayoutConfig) {
+    if config.processing.subtitle_burn && node.unit == Decoder {
+        if let Some(subtitle) = find_subtitle(config, node) {
+            chain.add_filter(&burn_filter(node, &subtitle), 0, Video);
+        }
+    }
+}
+
 /// add drawtext filter for lower thirds messages
 fn add_text(
     node: &mut Media,
@@ -465,11 +771,43 @@ fn add_text(
     }
 }
 
+/// add drawtext filters for clock, countdown and ticker
+fn add_widgets(node: &Media, chain: &mut Filters, config: &PlayoutConfig) {
+    if config.output.mode == HLS || node.unit == Encoder {
+        for filter in v_drawtext::widget_nodes(config) {
+            chain.add_filter(&filter, 0, Video);
+        }
+    }
+}
+
+/// add the canvas from the overlay API
+fn add_overlay(node: &Media, chain: &mut Filters, config: &PlayoutConfig) {
+    if config.text.overlay.enable && (config.output.mode == HLS || node.unit == Encoder) {
+        let canvas = canvas_file(config.general.channel_id);
+
+        chain.add_filter(
+            &format!(
+                "null[v];{},setpts=N/(FRAME_RATE*TB),format=rgba[o];[v][o]overlay=shortest=1",
+                logo_movie(&canvas.to_string_lossy())
+            ),
+            0,
+            Video,
+        );
+    }
+}
+
+fn add_broadcast(chain: &mut Filters, config: &PlayoutConfig) {
+    if let Some(filter) = broadcast_filter(&config.output.broadcast, config.processing.fps) {
+        chain.add_filter(&filter, 0, Video);
+    }
+}
+
 fn add_audio(node: &Media, chain: &mut Filters, nr: i32, config: &PlayoutConfig) {
     let audio = match config.advanced.filter.aevalsrc.clone() {
         Some(aevalsrc) => custom_format(&aevalsrc, &[node.out - node.seek]),
         None => format!(
-            "aevalsrc=0:channel_layout=stereo:duration={}:sample_rate=48000",
+            "aevalsrc=0:channel_layout={}:duration={}:sample_rate=48000",
+            silence_layout(config.processing.audio_channels),
             node.out - node.seek
         ),
     };
@@ -477,6 +815,26 @@ fn add_audio(node: &Media, chain: &mut Filters, nr: i32, config: &PlayoutConfig)
     chain.add_filter(&audio, nr, Audio);
 }
 
+fn channel_layout(node: &Media, chain: &mut Filters, nr: i32, source: i32, config: &PlayoutConfig) {
+    let source_channels = if Path::new(&node.audio).is_file() {
+        None
+    } else {
+        node.probe
+            .as_ref()
+            .and_then(|p| p.audio_streams.get(source as usize))
+            .and_then(|s| s.channels)
+    };
+
+    if let Some(filter) = layout_filter(
+        config.processing.audio_channels,
+        source_channels,
+        config.processing.audio_downmix,
+        node.channel_map.as_deref(),
+    ) {
+        chain.add_filter(&filter, nr, Audio);
+    }
+}
+
 fn extend_audio(node: &mut Media, chain: &mut Filters, nr: i32, config: &PlayoutConfig) {
     if !Path::new(&node.audio).is_file() {
         if let Some(audio_duration) = node
@@ -510,6 +868,14 @@ fn audio_volume(chain: &mut Filters, config: &PlayoutConfig, nr: i32) {
     }
 }
 
+/// Frozen picture and silence are detected only in real clips, not in placeholders.
+fn detect_dead_air(node: &Media, config: &PlayoutConfig) -> bool {
+    config.processing.dead_air_duration > 0.0
+        && node.unit == Decoder
+        && !node.source.is_empty()
+        && !node.source.contains("color=c=")
+}
+
 fn aspect_calc(aspect_string: &Option<String>, config: &PlayoutConfig) -> f64 {
     let mut source_aspect = config.processing.aspect;
 
@@ -610,6 +976,30 @@ pub fn filter_chains(
     if node.unit == Encoder {
         if !config.processing.audio_only {
             add_text(node, &mut filters, config, filter_chain);
+            add_widgets(node, &mut filters, config);
+            add_overlay(node, &mut filters, config);
+
+            if config.output.mode != HLS && !config.processing.copy_video {
+                add_broadcast(&mut filters, config);
+            }
+        }
+
+        if config.output.mode == Decklink && !config.processing.copy_audio {
+            let tracks = if config.processing.audio_track_index == -1 {
+                config.processing.audio_tracks
+            } else {
+                1
+            };
+
+            filters.add_filter(
+                &audio_filter(
+                    &config.output.decklink,
+                    tracks,
+                    config.processing.audio_channels,
+                ),
+                0,
+                Audio,
+            );
         }
 
         if let Some(f) = config.output.output_filter.clone() {
@@ -630,9 +1020,12 @@ pub fn filter_chains(
             if let Some(v_stream) = &probe.video_streams.first() {
                 let aspect = aspect_calc(&v_stream.display_aspect_ratio, config);
                 let frame_per_sec = fps_calc(&v_stream.r_frame_rate, 1.0);
+                let policy = node
+                    .aspect_policy
+                    .unwrap_or(config.processing.aspect_policy);
 
                 deinterlace(&v_stream.field_order, &mut filters, config);
-                pad(aspect, &mut filters, v_stream, config);
+                fit_aspect(aspect, policy, &mut filters, v_stream, config);
                 fps(frame_per_sec, &mut filters, config);
                 scale(
                     v_stream.width,
@@ -649,9 +1042,13 @@ pub fn filter_chains(
             scale(None, None, 1.0, &mut filters, config);
         }
 
+        burn_subtitles(node, &mut filters, config);
         add_text(node, &mut filters, config, filter_chain);
+        add_widgets(node, &mut filters, config);
         fade(node, &mut filters, 0, Video, config);
         overlay(node, &mut filters, config);
+        overlay_logos(node, &mut filters, config);
+        add_overlay(node, &mut filters, config);
     }
 
     let (proc_vf, proc_af) = if node.unit == Ingest {
@@ -665,28 +1062,30 @@ pub fn filter_chains(
     if !config.processing.copy_video {
         custom(&proc_vf, &mut filters, 0, Video);
         custom(&list_vf, &mut filters, 0, Video);
-    }
 
-    let mut audio_indexes = vec![];
+        let is_image = node
+            .source
+            .rsplit_once('.')
+            .is_some_and(|(_, e)| IMAGE_FORMAT.contains(&e.to_lowercase().as_str()));
 
-    if config.processing.audio_track_index == -1 {
-        for i in 0..config.processing.audio_tracks {
-            audio_indexes.push(i);
+        if detect_dead_air(node, config) && !config.processing.audio_only && !is_image {
+            filters.add_filter(&detect_filter(config, true), 0, Video);
+        }
+
+        if !config.processing.audio_only {
+            crossfade(node, &mut filters, 0, Video, config);
         }
-    } else {
-        audio_indexes.push(config.processing.audio_track_index);
     }
 
     if !config.processing.copy_audio {
-        for i in audio_indexes {
-            if node
-                .probe
-                .as_ref()
-                .and_then(|p| p.audio_streams.get(i as usize))
-                .is_some()
-                || Path::new(&node.audio).is_file()
-            {
+        let sources = audio_sources(config, node);
+        let first_track = sources.first().map(|(i, _)| *i);
+
+        for (i, source) in sources {
+            if let Some(source) = source {
+                filters.set_audio_source(i, source);
                 extend_audio(node, &mut filters, i, config);
+                channel_layout(node, &mut filters, i, source, config);
             } else if node.unit == Decoder && !node.source.contains("color=c=") {
                 warn!(target: Target::file_mail(), channel = config.general.channel_id;
                     "Missing audio track (id {i}) from <b><magenta>{}</></b>",
@@ -705,12 +1104,29 @@ pub fn filter_chains(
 
             custom(&proc_af, &mut filters, i, Audio);
             custom(&list_af, &mut filters, i, Audio);
+
+            // silence is checked only on the first track
+            if detect_dead_air(node, config) && Some(i) == first_track {
+                filters.add_filter(&detect_filter(config, false), i, Audio);
+            }
+
+            if config.processing.audio_meter && Some(i) == first_track {
+                filters.add_filter(&meter_filter(), i, Audio);
+            }
+
+            if Some(i) == first_track {
+                crossfade(node, &mut filters, i, Audio, config);
+            }
         }
     } else if config.processing.audio_track_index > -1 {
         error!(target: Target::file_mail(), channel = config.general.channel_id; "Setting 'audio_track_index' other than '-1' is not allowed in audio copy mode!");
     }
 
     if config.output.mode == HLS {
+        if !config.processing.audio_only && !config.processing.copy_video {
+            add_broadcast(&mut filters, config);
+        }
+
         if let Some(f) = config.output.output_filter.clone() {
             process_output_filters(config, &mut filters, &f);
         }
@@ -718,3 +1134,62 @@ pub fn filter_chains(
 
     filters
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::config::AspectPolicy;
+    use crate::utils::config::LogoRule;
+
+    #[test]
+    fn aspect_policies() {
+        assert_eq!(aspect_filter(AspectPolicy::Pad, 1024, 576), None);
+        assert_eq!(aspect_filter(AspectPolicy::Stretch, 1024, 576), None);
+        assert_eq!(
+            aspect_filter(AspectPolicy::Crop, 1024, 576).unwrap(),
+            "crop=min(iw\\,ih*(1024/576)):min(ih\\,iw/(1024/576))"
+        );
+
+        let blur = aspect_filter(AspectPolicy::Blur, 1920, 1080).unwrap();
+
+        assert!(blur.starts_with("split[ap_bg][ap_fg];"));
+        assert!(blur.contains("scale=1920:1080:force_original_aspect_ratio=decrease[ap_pic]"));
+        assert!(blur.ends_with("overlay=(W-w)/2:(H-h)/2"));
+
+        let item: Media = serde_json::from_str(
+            r#"{"in": 0, "out": 10, "duration": 10, "source": "a.mp4", "aspect_policy": "crop"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(item.aspect_policy, Some(AspectPolicy::Crop));
+    }
+
+    #[test]
+    fn logo_rule_intervals() {
+        let rule = LogoRule {
+            path: "logo.png".to_string(),
+            days: vec![6, 7],
+            start: "20:00:00".to_string(),
+            end: "02:00:00".to_string(),
+            ..Default::default()
+        };
+
+        // not on weekdays
+        assert!(logo_intervals(&rule, 72000.0, 600.0, 1).is_empty());
+
+        // clip starts before window
+        assert_eq!(
+            logo_intervals(&rule, 71700.0, 600.0, 6),
+            vec![(300.0, 600.0)]
+        );
+
+        // clip runs over midnight
+        assert_eq!(
+            logo_intervals(&rule, 86100.0, 600.0, 7),
+            vec![(0.0, 300.0), (300.0, 600.0)]
+        );
+
+        // outside of window
+        assert!(logo_intervals(&rule, 36000.0, 600.0, 7).is_empty());
+    }
+}
@@ -10,7 +10,7 @@ use crate::player::{
     controller::ProcessUnit::*,
     utils::{custom_format, Media},
 };
-use crate::utils::config::PlayoutConfig;
+use crate::utils::{config::PlayoutConfig, widgets::text_file};
 
 pub fn filter_node(
     config: &PlayoutConfig,
@@ -72,3 +72,43 @@ pub fn filter_node(
 
     filter
 }
+
+/// Drawtext filters for the clock, countdown and ticker widgets.
+pub fn widget_nodes(config: &PlayoutConfig) -> Vec<String> {
+    let widgets = &config.text.widgets;
+    let id = config.general.channel_id;
+    let font = if Path::new(&config.text.font_path).is_file() {
+        format!(":fontfile='{}'", config.text.font_path)
+    } else {
+        String::new()
+    };
+    let node = |name: &str, position: &str, style: &str| {
+        let style = if style.is_empty() {
+            String::new()
+        } else {
+            format!(":{style}")
+        };
+
+        format!(
+            "drawtext=textfile='{}':reload=1:expansion=none{font}{position}{style}",
+            text_file(id, name).display()
+        )
+    };
+    let mut nodes = vec![];
+
+    if widgets.clock {
+        nodes.push(node("clock", "", &widgets.clock_style));
+    }
+
+    if widgets.countdown {
+        nodes.push(node("countdown", "", &widgets.countdown_style));
+    }
+
+    if widgets.ticker && !widgets.ticker_url.is_empty() {
+        let position = format!(":x='w-mod(t*{},w+tw)'", widgets.ticker_speed);
+
+        nodes.push(node("ticker", &position, &widgets.ticker_style));
+    }
+
+    nodes
+}
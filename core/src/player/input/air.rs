@@ -0,0 +1,151 @@
+/*
+Broadcast hours.
+
+Outside of the air hours the channel plays a slate, or it is stopped and started again
+by the air scheduler (see `utils::air_hours`).
+
+In slate mode the running clip is cut when the channel goes off air,
+the slate plays until the next start and the program continues then in sync.
+
+*/
+
+use std::{sync::atomic::Ordering, time::Duration};
+
+use log::*;
+use serde::Serialize;
+
+use crate::player::{
+    controller::{ChannelManager, ProcessUnit::*},
+    input::{
+        dead_air::{fallback_source, source_path},
+        playlist::gen_source,
+    },
+    utils::{sec_to_time, time_in_seconds, Media},
+};
+use crate::utils::{
+    config::{AirHours, OffAir, PlayoutConfig},
+    logging::Target,
+    time_machine::clock_sleep,
+};
+
+/// Air state from one channel, for the status API.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AirStatus {
+    pub enable: bool,
+    pub on_air: bool,
+    pub off_air: OffAir,
+    /// Time of the day, when the channel goes on or off air.
+    pub next_change: Option<String>,
+}
+
+impl AirStatus {
+    pub fn new(air_hours: &AirHours, sec: f64) -> Self {
+        Self {
+            enable: air_hours.enable,
+            on_air: air_hours.on_air(sec),
+            off_air: air_hours.off_air,
+            next_change: air_hours
+                .next_change(sec)
+                .map(|d| sec_to_time((sec + d) % 86400.0)),
+        }
+    }
+}
+
+/// Slate is played instead of the program.
+fn plays_slate(config: &PlayoutConfig) -> bool {
+    let air_hours = &config.general.air_hours;
+
+    air_hours.off_air == OffAir::Slate && !air_hours.on_air(time_in_seconds())
+}
+
+/// Media node from the slate, until the channel goes on air.
+pub fn slate_source(manager: &ChannelManager, config: &PlayoutConfig) -> Media {
+    let air_hours = &config.general.air_hours;
+    let duration = air_hours
+        .next_change(time_in_seconds())
+        .unwrap_or_default()
+        .max(1.0);
+
+    if air_hours.slate.is_empty() {
+        return fallback_source(manager, config, duration);
+    }
+
+    let index = manager.current_list.lock().unwrap().len();
+    let mut node = Media::new(index, &source_path(config, &air_hours.slate), false);
+
+    node.begin = Some(time_in_seconds());
+    node.duration = duration;
+    node.out = duration;
+
+    gen_source(config, node, manager, index)
+}
+
+/// Source iterator, which plays the slate outside of the air hours.
+pub struct AirHoursSource {
+    manager: ChannelManager,
+    source: Box<dyn Iterator<Item = Media>>,
+    slate: bool,
+}
+
+impl AirHoursSource {
+    pub fn new(manager: ChannelManager, source: Box<dyn Iterator<Item = Media>>) -> Self {
+        Self {
+            manager,
+            source,
+            slate: false,
+        }
+    }
+}
+
+impl Iterator for AirHoursSource {
+    type Item = Media;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let config = self.manager.config.lock().unwrap().clone();
+
+        if plays_slate(&config) {
+            if !self.slate {
+                info!(target: Target::file_mail(), channel = config.general.channel_id;
+                    "Off air, play slate until <yellow>{}</>", config.general.air_hours.start
+                );
+            }
+
+            self.slate = true;
+
+            return Some(slate_source(&self.manager, &config));
+        }
+
+        if self.slate {
+            self.slate = false;
+
+            info!(target: Target::file_mail(), channel = config.general.channel_id; "On air, back to program");
+
+            // get in sync with the playlist again
+            self.manager.list_init.store(true, Ordering::SeqCst);
+        }
+
+        self.source.next()
+    }
+}
+
+/// Cut the running clip, when the channel goes off air in slate mode.
+pub fn air_watch(manager: ChannelManager) {
+    let mut off_air = plays_slate(&manager.config.lock().unwrap());
+
+    while !manager.is_terminated.load(Ordering::SeqCst) {
+        let config = manager.config.lock().unwrap().clone();
+        let slate = plays_slate(&config);
+
+        if slate && !off_air {
+            debug!(target: Target::file_mail(), channel = config.general.channel_id; "Air hours end, stop current clip");
+
+            if let Err(e) = manager.stop(Decoder) {
+                error!(target: Target::file_mail(), channel = config.general.channel_id; "{e}");
+            }
+        }
+
+        off_air = slate;
+
+        clock_sleep(Duration::from_secs(1));
+    }
+}
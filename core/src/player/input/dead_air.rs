@@ -0,0 +1,221 @@
+/*
+Dead-air detection with automatic fallback.
+
+When a dead-air duration is set, the decoder gets a freezedetect and silencedetect filter.
+ffmpeg reports frozen pictures and silence, which are longer then this duration, on stderr.
+Then the current clip is stopped and the fallback source plays for the rest of the clip:
+
+- empty fallback: filler from storage
+- image: looped, with optional audio (slate with music)
+- video or stream url, for example a backup SRT feed
+
+After the fallback the playlist continues in sync.
+
+*/
+
+use std::{fmt, path::Path, sync::atomic::Ordering};
+
+use log::*;
+use serde::Serialize;
+
+use crate::player::{
+    controller::{ChannelManager, ProcessUnit::*},
+    input::playlist::gen_source,
+    utils::{is_remote, time_in_seconds, Media},
+};
+use crate::utils::{config::PlayoutConfig, logging::Target, time_machine::time_now};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeadAirKind {
+    Freeze,
+    Silence,
+}
+
+impl fmt::Display for DeadAirKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Freeze => write!(f, "frozen picture"),
+            Self::Silence => write!(f, "silence"),
+        }
+    }
+}
+
+/// Dead-air state from one channel, for the status API.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeadAir {
+    /// Fallback is playing.
+    pub active: bool,
+    pub kind: Option<DeadAirKind>,
+    /// Clip with dead air.
+    pub source: String,
+    pub since: Option<String>,
+    /// Fallback length, which is not handed out yet.
+    #[serde(skip)]
+    pending: Option<f64>,
+}
+
+/// Freezedetect/silencedetect filter for the decoder.
+pub fn detect_filter(config: &PlayoutConfig, freeze: bool) -> String {
+    let duration = config.processing.dead_air_duration;
+
+    if freeze {
+        format!("freezedetect=d={duration}")
+    } else {
+        format!("silencedetect=n=-60dB:d={duration}")
+    }
+}
+
+/// ffmpeg log levels, which show info messages.
+pub fn shows_info(level: &str) -> bool {
+    !matches!(
+        level.to_lowercase().as_str(),
+        "quiet" | "panic" | "fatal" | "error" | "warning"
+    )
+}
+
+/// Log format for the decoder, the detection filters report on info level.
+pub fn decoder_log_format(config: &PlayoutConfig) -> String {
+    let level = config.logging.ffmpeg_level.to_lowercase();
+
+    if config.processing.dead_air_duration > 0.0 && !shows_info(&level) {
+        "level+info".to_string()
+    } else {
+        format!("level+{level}")
+    }
+}
+
+/// Find the start from a frozen picture or silence in a ffmpeg log line.
+/// Returns the kind and the start time in seconds, relative to the clip.
+pub fn parse_dead_air(line: &str) -> Option<(DeadAirKind, f64)> {
+    let (kind, value) =
+        if let Some((_, value)) = line.split_once("lavfi.freezedetect.freeze_start:") {
+            (DeadAirKind::Freeze, value)
+        } else if let Some((_, value)) = line.split_once("silence_start:") {
+            (DeadAirKind::Silence, value)
+        } else {
+            return None;
+        };
+
+    value
+        .split_whitespace()
+        .next()
+        .and_then(|v| v.parse().ok())
+        .map(|start| (kind, start))
+}
+
+/// Stop the current clip, the fallback plays then for the rest of it.
+pub fn switch_to_fallback(manager: &ChannelManager, kind: DeadAirKind, start: f64) {
+    let config = manager.config.lock().unwrap().clone();
+    let id = config.general.channel_id;
+    let Some(node) = manager.current_media.lock().unwrap().clone() else {
+        return;
+    };
+    let mut state = manager.dead_air.lock().unwrap();
+
+    if state.active || manager.emergency.lock().unwrap().active {
+        // the fallback or override has dead air by itself, for example a slate image
+        return;
+    }
+
+    let remaining = node.out - node.seek - start - config.processing.dead_air_duration;
+
+    error!(target: Target::file_mail(), channel = id;
+        "Dead air, {kind} in <b><magenta>{}</></b> since <yellow>{start:.2}</> seconds",
+        node.source
+    );
+
+    if remaining < 1.0 {
+        return;
+    }
+
+    warn!(target: Target::file_mail(), channel = id;
+        "Switch to dead-air fallback for <yellow>{remaining:.2}</> seconds"
+    );
+
+    *state = DeadAir {
+        active: true,
+        kind: Some(kind),
+        source: node.source,
+        since: Some(time_now().format("%Y-%m-%d %H:%M:%S").to_string()),
+        pending: Some(remaining),
+    };
+
+    drop(state);
+
+    if let Err(e) = manager.stop(Decoder) {
+        error!(target: Target::file_mail(), channel = id; "{e}");
+    }
+}
+
+/// Relative sources are in the channel storage.
+pub fn source_path(config: &PlayoutConfig, source: &str) -> String {
+    if source.is_empty() || is_remote(source) || Path::new(source).is_absolute() {
+        source.to_string()
+    } else {
+        config
+            .channel
+            .storage
+            .join(source)
+            .to_string_lossy()
+            .to_string()
+    }
+}
+
+/// Media node from the fallback source, with the given length.
+pub fn fallback_source(manager: &ChannelManager, config: &PlayoutConfig, duration: f64) -> Media {
+    let index = manager.current_list.lock().unwrap().len();
+    let source = source_path(config, &config.processing.dead_air_fallback);
+    let mut node = Media::new(index, &source, false);
+
+    node.audio = source_path(config, &config.processing.dead_air_audio);
+    node.begin = Some(time_in_seconds());
+    node.duration = duration;
+    node.out = duration;
+
+    gen_source(config, node, manager, index)
+}
+
+/// Source iterator, which plays the fallback after dead air was detected.
+pub struct DeadAirSource {
+    manager: ChannelManager,
+    source: Box<dyn Iterator<Item = Media>>,
+}
+
+impl DeadAirSource {
+    pub fn new(manager: ChannelManager, source: Box<dyn Iterator<Item = Media>>) -> Self {
+        *manager.dead_air.lock().unwrap() = DeadAir::default();
+
+        Self { manager, source }
+    }
+}
+
+impl Iterator for DeadAirSource {
+    type Item = Media;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut state = self.manager.dead_air.lock().unwrap();
+
+        if let Some(duration) = state.pending.take() {
+            drop(state);
+
+            let config = self.manager.config.lock().unwrap().clone();
+
+            return Some(fallback_source(&self.manager, &config, duration));
+        }
+
+        if state.active {
+            *state = DeadAir::default();
+
+            let id = self.manager.channel.lock().unwrap().id;
+            info!(target: Target::file_mail(), channel = id; "Dead-air fallback ends, back to program");
+
+            // get in sync with the playlist again
+            self.manager.list_init.store(true, Ordering::SeqCst);
+        }
+
+        drop(state);
+
+        self.source.next()
+    }
+}
@@ -0,0 +1,267 @@
+/*
+Emergency override, like a breaking news slate.
+
+An override plays instead of the schedule, it has priority over the playlist, the live ingest,
+the air hours and the dead-air fallback:
+
+- live: a live input (RTMP/SRT/UDP url)
+- slate: image with an optional text, without image the dead-air fallback
+- clip: looped clip from the storage
+
+The override ends with a release, or by itself at the end of the item, which was running when it started.
+Then the program continues in sync with the schedule.
+
+*/
+
+use std::{
+    env, fmt, fs,
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+    time::Instant,
+};
+
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::player::{
+    controller::ChannelManager,
+    input::{dead_air::source_path, playlist::gen_source},
+    utils::{time_in_seconds, Media},
+};
+use crate::utils::{config::PlayoutConfig, logging::Target};
+
+/// Length from one override node, without an end it continues with the next one.
+const OVERRIDE_LENGTH: f64 = 3600.0;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverrideKind {
+    Live,
+    #[default]
+    Slate,
+    Clip,
+    Release,
+}
+
+impl fmt::Display for OverrideKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Live => write!(f, "live"),
+            Self::Slate => write!(f, "slate"),
+            Self::Clip => write!(f, "clip"),
+            Self::Release => write!(f, "release"),
+        }
+    }
+}
+
+/// When the override returns to the schedule.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverrideEnd {
+    /// With the release over the API.
+    #[default]
+    Release,
+    /// At the end of the running item.
+    Next,
+}
+
+/// Override request from the API.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct OverrideParams {
+    pub source: OverrideKind,
+    /// Url from the live input.
+    pub url: String,
+    /// Image or clip in the storage.
+    pub path: String,
+    /// Text on the slate.
+    pub text: String,
+    pub until: OverrideEnd,
+}
+
+/// Override from one channel, for the status API.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OverrideState {
+    pub active: bool,
+    pub source: Option<OverrideKind>,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub url: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub path: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub text: String,
+    pub until: OverrideEnd,
+    pub since: Option<String>,
+    /// Time, when it returns to the schedule by itself.
+    pub ends: Option<String>,
+    #[serde(skip)]
+    pub deadline: Option<Instant>,
+}
+
+/// Text file from the slate.
+pub fn text_file(id: i32) -> PathBuf {
+    env::temp_dir().join(format!("ffplayout_{id}_override.txt"))
+}
+
+/// Drawtext filter, which shows the text from the slate in the center.
+pub fn slate_filter(config: &PlayoutConfig) -> String {
+    let font = if Path::new(&config.text.font_path).is_file() {
+        format!(":fontfile='{}'", config.text.font_path)
+    } else {
+        String::new()
+    };
+
+    format!(
+        "drawtext=textfile='{}':expansion=none{font}:fontsize=h/14:fontcolor=white:box=1:boxcolor=black@0.6:boxborderw=20:x=(w-tw)/2:y=(h-th)/2[c_v_out]",
+        text_file(config.general.channel_id).display()
+    )
+}
+
+/// Seconds until the running item ends.
+pub fn remaining_time(node: &Media, now: f64, shift: f64) -> f64 {
+    let begin = node.begin.unwrap_or(now) - shift;
+    let mut elapsed = now - begin;
+
+    // the item started before midnight
+    if elapsed < 0.0 {
+        elapsed += 86400.0;
+    }
+
+    (node.out - node.seek - elapsed).max(0.0)
+}
+
+/// Media node from the override, with the given length.
+fn override_node(
+    manager: &ChannelManager,
+    config: &PlayoutConfig,
+    state: &OverrideState,
+    duration: f64,
+) -> Media {
+    let index = manager.current_list.lock().unwrap().len();
+    let mut node = match state.source {
+        Some(OverrideKind::Clip) => Media::new(index, &source_path(config, &state.path), true),
+        Some(OverrideKind::Live) => Media::new(index, &state.url, false),
+        _ if state.path.is_empty() => {
+            let source = source_path(config, &config.processing.dead_air_fallback);
+            let mut node = Media::new(index, &source, false);
+
+            node.audio = source_path(config, &config.processing.dead_air_audio);
+            node
+        }
+        _ => Media::new(index, &source_path(config, &state.path), false),
+    };
+
+    // clips keep their length, they loop until the end
+    if state.source != Some(OverrideKind::Clip) {
+        node.duration = duration;
+    }
+
+    node.out = duration;
+
+    if state.source == Some(OverrideKind::Slate) && !state.text.is_empty() {
+        node.custom_filter = slate_filter(config);
+    }
+
+    node.begin = Some(time_in_seconds());
+    node.category = "override".to_string();
+
+    gen_source(config, node, manager, index)
+}
+
+/// Source iterator, which plays the override instead of the schedule.
+pub struct EmergencySource {
+    manager: ChannelManager,
+    source: Box<dyn Iterator<Item = Media>>,
+}
+
+impl EmergencySource {
+    pub fn new(manager: ChannelManager, source: Box<dyn Iterator<Item = Media>>) -> Self {
+        *manager.emergency.lock().unwrap() = OverrideState::default();
+
+        Self { manager, source }
+    }
+}
+
+impl Iterator for EmergencySource {
+    type Item = Media;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut state = self.manager.emergency.lock().unwrap();
+        let remaining = state
+            .deadline
+            .map(|d| d.saturating_duration_since(Instant::now()).as_secs_f64());
+
+        if state.active && remaining.is_none_or(|r| r >= 1.0) {
+            let current = state.clone();
+            drop(state);
+
+            let config = self.manager.config.lock().unwrap().clone();
+
+            return Some(override_node(
+                &self.manager,
+                &config,
+                &current,
+                remaining.unwrap_or(OVERRIDE_LENGTH),
+            ));
+        }
+
+        if state.active {
+            *state = OverrideState::default();
+
+            let id = self.manager.channel.lock().unwrap().id;
+            info!(target: Target::file_mail(), channel = id; "Override ends, back to schedule");
+
+            let _ = fs::remove_file(text_file(id));
+
+            // get in sync with the playlist again
+            self.manager.list_init.store(true, Ordering::SeqCst);
+        }
+
+        drop(state);
+
+        self.source.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::config::PlayoutConfig;
+
+    #[test]
+    fn emergency_override() {
+        let params: OverrideParams = serde_json::from_str(r#"{"source": "release"}"#).unwrap();
+
+        assert_eq!(params.source, OverrideKind::Release);
+        assert_eq!(params.until, OverrideEnd::Release);
+
+        let params: OverrideParams =
+            serde_json::from_str(r#"{"source": "clip", "path": "loop.mp4", "until": "next"}"#)
+                .unwrap();
+
+        assert_eq!(params.source, OverrideKind::Clip);
+        assert_eq!(params.until, OverrideEnd::Next);
+        assert!(params.text.is_empty());
+
+        let mut node = Media::new(0, "", false);
+        node.begin = Some(3600.0);
+        node.seek = 10.0;
+        node.out = 130.0;
+
+        assert_eq!(remaining_time(&node, 3700.0, 0.0), 20.0);
+        assert_eq!(remaining_time(&node, 3700.0, 50.0), 0.0);
+
+        // item started before midnight
+        node.begin = Some(86380.0);
+        node.seek = 0.0;
+        node.out = 60.0;
+
+        assert_eq!(remaining_time(&node, 10.0, 0.0), 30.0);
+
+        let filter = slate_filter(&PlayoutConfig::default());
+
+        assert!(filter.starts_with("drawtext=textfile='"));
+        assert!(filter.contains("ffplayout_0_override.txt"));
+        assert!(filter.ends_with("[c_v_out]"));
+    }
+}
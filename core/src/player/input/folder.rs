@@ -5,7 +5,6 @@ use std::{
         mpsc::channel,
         {Arc, Mutex},
     },
-    thread::sleep,
     time::Duration,
 };
 
@@ -18,7 +17,7 @@ use notify::{
 use notify_debouncer_full::new_debouncer;
 
 use crate::player::utils::{include_file_extension, Media};
-use crate::utils::{config::PlayoutConfig, logging::Target};
+use crate::utils::{config::PlayoutConfig, logging::Target, time_machine::clock_sleep};
 
 /// Create a watcher, which monitor file changes.
 /// When a change is register, update the current file list.
@@ -95,6 +94,6 @@ pub fn watchman(
             }
         }
 
-        sleep(Duration::from_secs(3));
+        clock_sleep(Duration::from_secs(3));
     }
 }
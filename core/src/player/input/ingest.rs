@@ -1,4 +1,5 @@
 use std::{
+    fs,
     io::{BufRead, BufReader, Read},
     process::{ChildStderr, Command, Stdio},
     sync::{atomic::Ordering, mpsc::SyncSender},
@@ -10,12 +11,21 @@ use log::*;
 use crate::utils::{
     config::{PlayoutConfig, FFMPEG_IGNORE_ERRORS, FFMPEG_UNRECOVERABLE_ERRORS},
     logging::{log_line, Target},
+    process_limits::LimitProcess,
+    stream_keys::{is_auth_stream, is_grace_stream},
 };
 use crate::vec_strings;
 use crate::{
     player::{
         controller::{ChannelManager, ProcessUnit::*},
-        utils::{is_free_tcp_port, valid_stream, Media},
+        output::preview::{clear_preview, preview_args, preview_dir},
+        utils::{
+            audio_meter::{ingest_on_air, record_meter},
+            ffmpeg_events::record_event,
+            is_free_tcp_port,
+            stream_stats::{progress_args, record_progress, start_stream, stop_stream, INGEST},
+            valid_stream, Media,
+        },
     },
     utils::errors::ProcessError,
 };
@@ -30,13 +40,26 @@ fn server_monitor(
     for line in buffer.lines() {
         let line = line?;
 
+        if record_progress(&channel_mgr, INGEST, &line)
+            || record_meter(&channel_mgr, &line, ingest_on_air(&channel_mgr))
+        {
+            continue;
+        }
+
+        record_event(&channel_mgr, Ingest, &line);
+
         if !FFMPEG_IGNORE_ERRORS.iter().any(|i| line.contains(*i))
             && !ignore.iter().any(|i| line.contains(i))
         {
             log_line(&line, level);
         }
 
-        if line.contains("rtmp") && line.contains("Unexpected stream") && !valid_stream(&line) {
+        if line.contains("rtmp")
+            && line.contains("Unexpected stream")
+            && !valid_stream(&line)
+            && !is_grace_stream(id, &line)
+            && !is_auth_stream(&channel_mgr.config.lock().unwrap().ingest, &line)
+        {
             warn!(target: Target::file_mail(), channel = id; "Unexpected ingest stream: {line}");
 
             if let Err(e) = channel_mgr.stop(Ingest) {
@@ -68,7 +91,7 @@ pub fn ingest_server(
     let id = config.general.channel_id;
     let mut buffer: [u8; 65088] = [0; 65088];
     let mut server_cmd = vec_strings!["-hide_banner", "-nostats", "-v", "level+info"];
-    let stream_input = config.ingest.input_cmd.clone().unwrap();
+    let mut stream_input = config.ingest.input_cmd.clone().unwrap();
     let mut dummy_media = Media::new(0, "Live Stream", false);
     dummy_media.unit = Ingest;
     dummy_media.add_filter(&config, &None);
@@ -79,13 +102,16 @@ pub fn ingest_server(
         .storage
         .join(config.processing.vtt_dummy.clone().unwrap_or_default());
 
+    server_cmd.append(&mut progress_args());
+
     if let Some(ingest_input_cmd) = config.advanced.ingest.input_cmd {
         server_cmd.append(&mut ingest_input_cmd.clone());
     }
 
+    let input_pos = server_cmd.len();
     server_cmd.append(&mut stream_input.clone());
 
-    if config.processing.vtt_enable && vtt_dummy.is_file() {
+    if config.processing.subtitle_passthrough() && vtt_dummy.is_file() {
         server_cmd.append(&mut vec_strings!["-i", vtt_dummy.to_string_lossy()]);
     }
 
@@ -94,7 +120,7 @@ pub fn ingest_server(
         server_cmd.append(&mut filter.map());
     }
 
-    if config.processing.vtt_enable && vtt_dummy.is_file() {
+    if config.processing.subtitle_passthrough() && vtt_dummy.is_file() {
         server_cmd.append(&mut vec_strings!("-map", "1:s"));
     }
 
@@ -102,6 +128,16 @@ pub fn ingest_server(
         server_cmd.append(&mut cmd);
     }
 
+    let preview = preview_dir(&config.channel.public);
+
+    if config.ingest.preview.enable {
+        if let Err(e) = fs::create_dir_all(&preview) {
+            error!(target: Target::file_mail(), channel = id; "Ingest preview folder: {e}");
+        }
+
+        server_cmd.append(&mut preview_args(&config.ingest.preview, &preview));
+    }
+
     let mut is_running;
 
     debug!(target: Target::file_mail(), channel = id;
@@ -119,11 +155,23 @@ pub fn ingest_server(
     };
 
     while !is_terminated.load(Ordering::SeqCst) {
+        // Input can change while running, for example after a stream key rotation.
+        // The listener is idle at this point, so it is safe to use the new one.
+        let new_input = channel_mgr.config.lock().unwrap().ingest.input_cmd.clone();
+
+        if let Some(new_input) = new_input.filter(|i| *i != stream_input) {
+            server_cmd.splice(input_pos..input_pos + stream_input.len(), new_input.clone());
+            stream_input = new_input;
+
+            info!(target: Target::file_mail(), channel = id; "Ingest input changed, restart listener");
+        }
+
         let proc_ctl = channel_mgr.clone();
         let level = config.logging.ingest_level.clone();
         let ignore = config.logging.ignore_lines.clone();
         let mut server_proc = match Command::new("ffmpeg")
             .args(server_cmd.clone())
+            .limits(&config.channel.limits)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
@@ -140,6 +188,7 @@ pub fn ingest_server(
             thread::spawn(move || server_monitor(id, &level, ignore, server_err, proc_ctl));
 
         *channel_mgr.ingest.lock().unwrap() = Some(server_proc);
+        start_stream(&channel_mgr, INGEST);
         is_running = false;
 
         loop {
@@ -170,11 +219,16 @@ pub fn ingest_server(
 
         drop(ingest_reader);
         ingest_is_running.store(false, Ordering::SeqCst);
+        stop_stream(&channel_mgr, INGEST);
 
         if let Err(e) = channel_mgr.wait(Ingest) {
             error!(target: Target::file_mail(), channel = id; "{e}");
         }
 
+        if config.ingest.preview.enable {
+            clear_preview(&preview);
+        }
+
         if let Err(e) = error_reader_thread.join() {
             error!(target: Target::file_mail(), channel = id; "{e:?}");
         };
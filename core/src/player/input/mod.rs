@@ -2,13 +2,23 @@ use std::thread;
 
 use log::*;
 
+pub mod air;
+pub mod dead_air;
+pub mod emergency;
 pub mod folder;
 pub mod ingest;
 pub mod playlist;
+pub mod switch;
+pub mod watchdog;
 
+pub use air::{air_watch, AirHoursSource};
+pub use dead_air::DeadAirSource;
+pub use emergency::EmergencySource;
 pub use folder::watchman;
 pub use ingest::ingest_server;
 pub use playlist::CurrentProgram;
+pub use switch::InputSwitchSource;
+pub use watchdog::playlist_watchdog;
 
 use crate::player::{
     controller::ChannelManager,
@@ -22,8 +32,9 @@ pub fn source_generator(manager: ChannelManager) -> Box<dyn Iterator<Item = Medi
     let id = config.general.channel_id;
     let is_terminated = manager.is_terminated.clone();
     let current_list = manager.current_list.clone();
+    let manager_clone = manager.clone();
 
-    match config.processing.mode {
+    let source = match config.processing.mode {
         Folder => {
             info!(target: Target::file_mail(), channel = id; "Playout in folder mode");
             debug!(target: Target::file_mail(), channel = id;
@@ -46,5 +57,12 @@ pub fn source_generator(manager: ChannelManager) -> Box<dyn Iterator<Item = Medi
 
             Box::new(program) as Box<dyn Iterator<Item = Media>>
         }
-    }
+    };
+
+    let source = Box::new(InputSwitchSource::new(manager_clone.clone(), source));
+    let source = Box::new(AirHoursSource::new(manager_clone.clone(), source));
+
+    let source = Box::new(DeadAirSource::new(manager_clone.clone(), source));
+
+    Box::new(EmergencySource::new(manager_clone, source))
 }
@@ -11,16 +11,23 @@ use log::*;
 use crate::db::handles;
 use crate::player::{
     controller::ChannelManager,
+    filter::crossfade_duration,
     utils::{
+        filler::FillerRules,
         gen_dummy, get_delta, is_close, is_remote,
         json_serializer::{read_json, set_defaults},
-        loop_filler, loop_image, modified_time, seek_and_length, time_in_seconds, JsonPlaylist,
-        Media, MediaProbe,
+        loop_filler, loop_image, modified_time, seek_and_length, time_in_seconds, Crossfade,
+        JsonPlaylist, Media, MediaProbe,
     },
 };
 use crate::utils::{
+    ad_breaks::fill_breaks,
     config::{PlayoutConfig, IMAGE_FORMAT},
     logging::Target,
+    object_store::fetch,
+    pre_cache::{cached_source, count_hit},
+    scripting::{on_filler, on_next},
+    transcode::preferred_source,
 };
 
 /// Struct for current playlist.
@@ -92,6 +99,8 @@ impl CurrentProgram {
                 false,
             );
 
+            fill_breaks(&self.manager, &mut self.json_playlist);
+
             if !reload {
                 if let Some(file) = &self.json_playlist.path {
                     info!(target: Target::file_mail(), channel = self.id; "Read playlist: <b><magenta>{file}</></b>");
@@ -179,6 +188,8 @@ impl CurrentProgram {
                 true,
             );
 
+            fill_breaks(&self.manager, &mut self.json_playlist);
+
             if let Some(file) = &self.json_playlist.path {
                 info!(target: Target::file_mail(), channel = self.id; "Read next playlist: <b><magenta>{file}</></b>");
             }
@@ -437,6 +448,7 @@ impl Iterator for CurrentProgram {
             let index = self.manager.current_index.load(Ordering::SeqCst);
             let node_list = self.manager.current_list.lock().unwrap();
             let mut node = node_list[index].clone();
+            let next_node = node_list.get(index + 1).cloned();
             let last_index = node_list.len() - 1;
 
             drop(node_list);
@@ -446,10 +458,21 @@ impl Iterator for CurrentProgram {
             }
 
             self.last_next_ad(&mut node);
+            on_next(&self.manager, &mut node);
+
+            if let Some(next) = next_node {
+                set_crossfade(&self.config, &mut node, next);
+            }
 
             self.current_node =
                 timed_source(node, &self.config, is_last, &self.manager, last_index);
 
+            if self.current_node.process == Some(true) {
+                if let Some(crossfade) = &self.current_node.crossfade {
+                    shift_after_crossfade(&self.manager, index, crossfade.duration);
+                }
+            }
+
             self.manager.current_index.fetch_add(1, Ordering::SeqCst);
         } else {
             let (_, total_delta) = get_delta(&self.config, &self.start_sec);
@@ -480,6 +503,7 @@ impl Iterator for CurrentProgram {
             self.manager.current_index.store(0, Ordering::SeqCst);
             self.last_next_ad(&mut first_node);
             first_node.last_ad = self.last_node_ad;
+            on_next(&self.manager, &mut first_node);
 
             self.current_node = gen_source(&self.config, first_node, &self.manager, 0);
 
@@ -528,6 +552,8 @@ fn timed_source(
             debug!(target: Target::file_mail(), channel = id; "Delta: <yellow>{shifted_delta:.3}</>");
         }
 
+        *manager.playlist_delta.lock().unwrap() = Some(shifted_delta);
+
         if config.general.stop_threshold > 0.0
             && shifted_delta.abs() > config.general.stop_threshold
         {
@@ -581,6 +607,55 @@ fn timed_source(
     new_node
 }
 
+/// Prepare the crossfade into the next clip, which must be a local video file.
+fn set_crossfade(config: &PlayoutConfig, node: &mut Media, mut next: Media) {
+    let Some(duration) = crossfade_duration(node, &next, config) else {
+        return;
+    };
+
+    if is_remote(&next.source)
+        || !Path::new(&next.source).is_file()
+        || next
+            .source
+            .rsplit_once('.')
+            .is_some_and(|(_, e)| IMAGE_FORMAT.contains(&e.to_lowercase().as_str()))
+        || next.add_probe(false).is_err()
+    {
+        return;
+    }
+
+    let Some(probe) = &next.probe else {
+        return;
+    };
+
+    if probe.video_streams.is_empty() && !config.processing.audio_only {
+        return;
+    }
+
+    node.crossfade = Some(Crossfade {
+        source: next.source.clone(),
+        seek: next.seek,
+        duration,
+        has_audio: !probe.audio_streams.is_empty(),
+        input: 0,
+    });
+}
+
+/// The next clip starts inside the crossfade,
+/// so it gets shorter and all following clips begin earlier.
+fn shift_after_crossfade(manager: &ChannelManager, index: usize, duration: f64) {
+    let mut list = manager.current_list.lock().unwrap();
+
+    if let Some(next) = list.get_mut(index + 1) {
+        next.seek += duration;
+        next.crossfaded = true;
+    }
+
+    for item in list.iter_mut().skip(index + 2) {
+        item.begin = item.begin.map(|b| b - duration);
+    }
+}
+
 fn duplicate_for_seek_and_loop(node: &mut Media, current_list: &Arc<Mutex<Vec<Media>>>) {
     let mut nodes = current_list.lock().unwrap();
     let index = node.index.unwrap_or_default();
@@ -647,6 +722,33 @@ pub fn gen_source(
 
     trace!("Clip new length: {duration}, duration: {}", node.duration);
 
+    if config.storage.object_store.enable
+        && Path::new(&node.source).starts_with(&config.channel.storage)
+    {
+        // normally the prefetcher has loaded it already
+        match fetch(config, &node.source) {
+            Ok(true) => {
+                count_hit(config.general.channel_id, false);
+                warn!(target: Target::file_mail(), channel = config.general.channel_id; "Clip was not prefetched: <b><magenta>{}</></b>", node.source);
+            }
+            Ok(false) => count_hit(config.general.channel_id, true),
+            Err(e) => {
+                error!(target: Target::file_mail(), channel = config.general.channel_id; "{e}");
+            }
+        }
+    }
+
+    if let Some(proxy) = preferred_source(config, &node.source) {
+        trace!("Play proxy: {proxy}");
+
+        node.source = proxy;
+        node.probe = None;
+    }
+
+    if config.storage.pre_cache.enable {
+        node.source = cached_source(config, &node.source);
+    }
+
     if node.probe.is_none() && !node.source.is_empty() {
         if let Err(e) = node.add_probe(true) {
             trace!("{e:?}");
@@ -664,6 +766,7 @@ pub fn gen_source(
             .filter(|c| IMAGE_FORMAT.contains(&c.as_str()))
             .is_some()
         {
+            node.crossfade = None;
             node.cmd = Some(loop_image(config, &node));
         } else {
             if node.seek > 0.0 && node.out > node.duration {
@@ -676,6 +779,8 @@ pub fn gen_source(
     } else {
         trace!("clip index: {node_index} | last index: {last_index}");
 
+        node.crossfade = None;
+
         // Last index is the index from the last item from the node list.
         if node_index < last_index {
             error!(target: Target::file_mail(), channel = config.general.channel_id; "Source not found: <b><magenta>{}</></b>", node.source);
@@ -693,16 +798,38 @@ pub fn gen_source(
         // Set list_init to true, to stay in sync.
         manager.list_init.store(true, Ordering::SeqCst);
 
-        if config.storage.filler_path.is_dir() && !fillers.is_empty() {
-            let index = manager.filler_index.fetch_add(1, Ordering::SeqCst);
-            let mut filler_media = fillers[index].clone();
+        let scripted = on_filler(manager, duration, &fillers);
+
+        let selected = if scripted.is_some() {
+            None
+        } else if config.storage.filler_path.is_dir() && !fillers.is_empty() {
+            let date = manager.current_date.lock().unwrap().clone();
+            let mut history = manager.filler_history.lock().unwrap();
+
+            history.set_date(&date);
+            history.select(
+                &fillers,
+                &FillerRules::new(&config.storage),
+                manager.filler_index.load(Ordering::SeqCst),
+                duration,
+            )
+        } else {
+            None
+        };
 
-            trace!("take filler: {}", filler_media.source);
+        let filler = scripted.or_else(|| {
+            selected.map(|index| {
+                // continue with the next clip in the next round
+                manager
+                    .filler_index
+                    .store((index + 1) % fillers.len(), Ordering::SeqCst);
 
-            if index == fillers.len() - 1 {
-                // reset index for next round
-                manager.filler_index.store(0, Ordering::SeqCst);
-            }
+                fillers[index].clone()
+            })
+        });
+
+        if let Some(mut filler_media) = filler {
+            trace!("take filler: {}", filler_media.source);
 
             if filler_media.probe.is_none() {
                 if let Err(e) = filler_media.add_probe(false) {
@@ -720,6 +847,14 @@ pub fn gen_source(
             node.duration = filler_media.duration;
             node.cmd = Some(loop_filler(config, &node));
             node.probe = filler_media.probe;
+        } else if config.storage.filler_path.is_dir() && !fillers.is_empty() {
+            // all clips are excluded by the filler rules
+            let (source, cmd) = gen_dummy(config, duration);
+            node.seek = 0.0;
+            node.out = duration;
+            node.duration = duration;
+            node.source = source;
+            node.cmd = Some(cmd);
         } else {
             match MediaProbe::new(&config.storage.filler_path.to_string_lossy()) {
                 Ok(probe) => {
@@ -0,0 +1,152 @@
+/*
+Manual switching between the program sources.
+
+By default a live ingest preempts the playlist, as soon as it connects.
+Operators can switch the output explicit:
+
+- auto: default behavior, live ingest preempts playlist/folder
+- playlist: play playlist/folder, a connected ingest is ignored
+- live: play the connected live ingest
+- external: play a external input (RTMP/SRT/UDP url), until the next switch
+
+The new source can fade in, the live ingest fades in always.
+
+*/
+
+use std::{fmt, sync::atomic::Ordering};
+
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::player::{
+    controller::ChannelManager,
+    input::playlist::gen_source,
+    utils::{time_in_seconds, Media},
+};
+use crate::utils::{config::PlayoutConfig, logging::Target};
+
+/// Length from one external source node, after that it gets reconnected.
+const EXTERNAL_LENGTH: f64 = 3600.0;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputSource {
+    #[default]
+    Auto,
+    Playlist,
+    Live,
+    External,
+}
+
+impl InputSource {
+    /// Live ingest can be played.
+    pub fn accepts_ingest(&self) -> bool {
+        matches!(self, Self::Auto | Self::Live)
+    }
+}
+
+impl fmt::Display for InputSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Auto => write!(f, "auto"),
+            Self::Playlist => write!(f, "playlist"),
+            Self::Live => write!(f, "live"),
+            Self::External => write!(f, "external"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SwitchTransition {
+    #[default]
+    Cut,
+    Fade,
+}
+
+/// Switch request from the API.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SwitchParams {
+    pub source: InputSource,
+    /// Url from the external input.
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub transition: SwitchTransition,
+    /// Fade length in seconds.
+    #[serde(default)]
+    pub duration: Option<f64>,
+}
+
+/// Selected source from one channel, for the status API.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct InputSwitch {
+    pub source: InputSource,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub url: String,
+    pub since: Option<String>,
+    /// Fade in for the next node.
+    #[serde(skip)]
+    pub fade: Option<f64>,
+}
+
+/// Media node from the external input.
+pub fn external_source(manager: &ChannelManager, config: &PlayoutConfig, url: &str) -> Media {
+    let index = manager.current_list.lock().unwrap().len();
+    let mut node = Media::new(index, url, false);
+
+    node.begin = Some(time_in_seconds());
+    node.duration = EXTERNAL_LENGTH;
+    node.out = EXTERNAL_LENGTH;
+
+    gen_source(config, node, manager, index)
+}
+
+/// Source iterator, which follows the manual source switches.
+pub struct InputSwitchSource {
+    manager: ChannelManager,
+    source: Box<dyn Iterator<Item = Media>>,
+}
+
+impl InputSwitchSource {
+    pub fn new(manager: ChannelManager, source: Box<dyn Iterator<Item = Media>>) -> Self {
+        *manager.input_switch.lock().unwrap() = InputSwitch::default();
+
+        Self { manager, source }
+    }
+}
+
+impl Iterator for InputSwitchSource {
+    type Item = Media;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut state = self.manager.input_switch.lock().unwrap();
+
+        if state.source == InputSource::Live
+            && !self.manager.ingest_is_running.load(Ordering::SeqCst)
+        {
+            let id = self.manager.channel.lock().unwrap().id;
+            info!(target: Target::file_mail(), channel = id; "Live ingest ends, input switch is back on <yellow>auto</>");
+
+            *state = InputSwitch::default();
+        }
+
+        let fade = state.fade.take();
+        let external = (state.source == InputSource::External).then(|| state.url.clone());
+
+        drop(state);
+
+        let config = self.manager.config.lock().unwrap().clone();
+        let mut node = match external {
+            Some(url) => external_source(&self.manager, &config, &url),
+            None => self.source.next()?,
+        };
+
+        if fade.is_some() && node.cmd.is_some() {
+            node.fade_in = fade;
+            node.add_filter(&config, &self.manager.filter_chain);
+        }
+
+        Some(node)
+    }
+}
@@ -0,0 +1,213 @@
+/*
+Watchdog for the playlist of the next day.
+
+Some time before the day rollover (playlist day_start), the playlist from the next day is checked.
+When it is missing or invalid, a alert is send and the fallback policy is applied:
+
+- none: nothing happens, the engine plays filler clips
+- copy: the playlist from the current day is copied to the next day
+- generate: a playlist is generated from storage, or from the template
+
+*/
+
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+    time::Duration,
+};
+
+use chrono::TimeDelta;
+use log::*;
+
+use crate::player::{
+    controller::ChannelManager,
+    utils::{is_remote, json_reader, json_writer, sum_durations, time_in_seconds},
+};
+use crate::utils::{
+    config::{PlaylistFallback, PlayoutConfig, ProcessMode::Playlist},
+    generator::playlist_generator,
+    logging::Target,
+    playlist::copy_playlist,
+    time_machine::{clock_sleep, time_now},
+};
+
+/// Seconds until the next playlist starts.
+pub fn rollover_in(now: f64, start: f64) -> f64 {
+    if now < start {
+        start - now
+    } else {
+        86400.0 - now + start
+    }
+}
+
+fn playlist_path(config: &PlayoutConfig, date: &str) -> PathBuf {
+    let d: Vec<&str> = date.split('-').collect();
+
+    config
+        .channel
+        .playlists
+        .join(d[0])
+        .join(d[1])
+        .join(date)
+        .with_extension("json")
+}
+
+/// Check if the playlist exists, can be read, is long enough and all local sources exists.
+/// Returns a list of problems.
+pub fn check_playlist(config: &PlayoutConfig, path: &Path) -> Vec<String> {
+    if !path.is_file() {
+        return vec![format!(
+            "Playlist <b><magenta>{}</></b> not exists",
+            path.display()
+        )];
+    }
+
+    let playlist = match json_reader(&path.to_path_buf()) {
+        Ok(p) => p,
+        Err(e) => return vec![format!("Playlist is not readable: {e}")],
+    };
+
+    if playlist.program.is_empty() {
+        return vec!["Playlist is empty".to_string()];
+    }
+
+    let mut problems = vec![];
+    let length = config.playlist.length_sec.unwrap_or(86400.0);
+    let total = sum_durations(&playlist.program);
+
+    if total + config.general.stop_threshold.max(1.0) < length {
+        problems.push(format!(
+            "Playlist is too short: <yellow>{total:.2}</> of <yellow>{length:.2}</> seconds"
+        ));
+    }
+
+    let missing: Vec<&str> = playlist
+        .program
+        .iter()
+        .filter(|m| !is_remote(&m.source) && !Path::new(&m.source).is_file())
+        .map(|m| m.source.as_str())
+        .collect();
+
+    if !missing.is_empty() {
+        problems.push(format!(
+            "<yellow>{}</> sources not found, first: <b><magenta>{}</></b>",
+            missing.len(),
+            missing[0]
+        ));
+    }
+
+    problems
+}
+
+fn apply_fallback(
+    manager: &ChannelManager,
+    config: &PlayoutConfig,
+    date: &str,
+) -> Result<(), String> {
+    let path = playlist_path(config, date);
+
+    if path.is_file() {
+        // never overwrite a existing playlist, even when it has problems
+        return Err("Playlist exists, fallback is skipped".to_string());
+    }
+
+    match config.playlist.fallback {
+        PlaylistFallback::None => Ok(()),
+        PlaylistFallback::Copy => {
+            let current_date = manager.current_date.lock().unwrap().clone();
+            let current = json_reader(&playlist_path(config, &current_date))
+                .map_err(|e| format!("Read playlist from {current_date} failed: {e}"))?;
+            let channel = manager.channel.lock().unwrap().name.clone();
+            let storage = &config.channel.storage;
+            let copy = copy_playlist(&current, &channel, date, storage, storage);
+
+            if let Some(p) = path.parent() {
+                std::fs::create_dir_all(p).map_err(|e| e.to_string())?;
+            }
+
+            json_writer(&path, copy).map_err(|e| e.to_string())
+        }
+        PlaylistFallback::Generate => {
+            let mut gen_config = config.clone();
+            gen_config.general.generate = Some(vec![date.to_string()]);
+
+            let channel = manager.channel.lock().unwrap().clone();
+            let gen_manager = ChannelManager::new(None, channel, gen_config);
+
+            playlist_generator(&gen_manager)
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        }
+    }
+}
+
+fn run_check(manager: &ChannelManager, config: &PlayoutConfig, date: &str) {
+    let id = config.general.channel_id;
+    let path = playlist_path(config, date);
+    let problems = check_playlist(config, &path);
+
+    if problems.is_empty() {
+        debug!(target: Target::file_mail(), channel = id; "Playlist from <yellow>{date}</> is ready");
+        return;
+    }
+
+    error!(target: Target::file_mail(), channel = id;
+        "Playlist from <yellow>{date}</> is not ready: {}", problems.join(", ")
+    );
+
+    if config.playlist.fallback == PlaylistFallback::None {
+        return;
+    }
+
+    match apply_fallback(manager, config, date) {
+        Ok(_) => {
+            let problems = check_playlist(config, &path);
+
+            if problems.is_empty() {
+                warn!(target: Target::file_mail(), channel = id;
+                    "Fallback <yellow>{}</> created playlist from <yellow>{date}</>", config.playlist.fallback
+                );
+            } else {
+                error!(target: Target::file_mail(), channel = id;
+                    "Fallback playlist from <yellow>{date}</> has problems: {}", problems.join(", ")
+                );
+            }
+        }
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "Playlist fallback failed: {e}");
+        }
+    }
+}
+
+/// Check the next playlist, when the day rollover comes closer then `check_before` minutes.
+pub fn playlist_watchdog(manager: ChannelManager) {
+    let mut checked = String::new();
+
+    while !manager.is_terminated.load(Ordering::SeqCst) {
+        let config = manager.config.lock().unwrap().clone();
+        let playlists = &config.channel.playlists;
+
+        if config.playlist.check_before > 0
+            && config.processing.mode == Playlist
+            && !config.playlist.infinit
+            && !is_remote(&playlists.to_string_lossy())
+            && playlists.is_dir()
+        {
+            let start = config.playlist.start_sec.unwrap_or(0.0);
+            let remaining = rollover_in(time_in_seconds(), start);
+
+            if remaining <= config.playlist.check_before as f64 * 60.0 {
+                let date = (time_now() + TimeDelta::seconds(remaining.ceil() as i64))
+                    .format("%Y-%m-%d")
+                    .to_string();
+
+                if date != checked {
+                    run_check(&manager, &config, &date);
+                    checked = date;
+                }
+            }
+        }
+
+        clock_sleep(Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,415 @@
+/*
+Alarms for silence and black picture in the program output.
+
+A small ffmpeg process gets the same stream as the channel encoder and runs silencedetect
+and blackdetect on it. The start and the end of silence and black picture are printed to stderr
+as metadata, the progress of ffmpeg gives a tick every second. When the silence or the black
+picture lasts longer than its threshold, an alarm goes to the log and the mail notifiers.
+
+After an alarm, the next alarm of the same kind waits for the cooldown, one incident
+which comes and goes does not fill the mailbox. Alarms in the cooldown are only logged.
+
+*/
+
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use log::*;
+
+use crate::player::{
+    controller::ChannelManager,
+    output::tap::ProgramTap,
+    utils::stream_stats::{parse_progress, progress_args},
+};
+use crate::utils::{
+    config::{OutputAlarms, PlayoutConfig},
+    logging::Target,
+};
+use crate::vec_strings;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlarmKind {
+    Silence,
+    Black,
+}
+
+impl fmt::Display for AlarmKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Silence => write!(f, "Silence"),
+            Self::Black => write!(f, "Black picture"),
+        }
+    }
+}
+
+/// Line from the alarm process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlarmEvent {
+    Start(AlarmKind),
+    End(AlarmKind),
+    /// Progress of ffmpeg, every second.
+    Tick,
+    /// Other metadata or progress lines.
+    Skip,
+}
+
+/// Event from a line of the alarm process, `None` for log lines.
+pub fn parse_alarm(line: &str) -> Option<AlarmEvent> {
+    let line = line.trim();
+
+    if line.starts_with("frame:") && line.contains("pts_time:") {
+        return Some(AlarmEvent::Skip);
+    }
+
+    if let Some(metadata) = line.strip_prefix("lavfi.") {
+        let (key, _) = metadata.split_once('=')?;
+
+        return Some(match key {
+            "silence_start" => AlarmEvent::Start(AlarmKind::Silence),
+            "silence_end" => AlarmEvent::End(AlarmKind::Silence),
+            "black_start" => AlarmEvent::Start(AlarmKind::Black),
+            "black_end" => AlarmEvent::End(AlarmKind::Black),
+            _ => AlarmEvent::Skip,
+        });
+    }
+
+    match parse_progress(line)? {
+        ("progress", _) => Some(AlarmEvent::Tick),
+        _ => Some(AlarmEvent::Skip),
+    }
+}
+
+/// ffmpeg parameters for the alarms, it reads the program stream from stdin.
+pub fn alarm_args(alarms: &OutputAlarms) -> Vec<String> {
+    let mut args = vec_strings!["-hide_banner", "-nostats", "-v", "level+error"];
+
+    args.append(&mut progress_args());
+    args.append(&mut vec_strings!["-f", "mpegts", "-i", "pipe:0"]);
+
+    if alarms.black > 0.0 {
+        args.append(&mut vec_strings![
+            "-map",
+            "0:v:0?",
+            "-vf",
+            format!(
+                "scale=320:-2,blackdetect=d=0:pix_th={},metadata=mode=print:file=pipe\\\\:2",
+                alarms.black_level.clamp(0.0, 1.0)
+            )
+        ]);
+    } else {
+        args.push("-vn".to_string());
+    }
+
+    if alarms.silence > 0.0 {
+        args.append(&mut vec_strings![
+            "-map",
+            "0:a:0?",
+            "-af",
+            format!(
+                "silencedetect=n={}dB:d=0.5,ametadata=mode=print:file=pipe\\\\:2",
+                alarms.silence_level
+            )
+        ]);
+    } else {
+        args.push("-an".to_string());
+    }
+
+    args.append(&mut vec_strings!["-f", "null", "-"]);
+
+    args
+}
+
+/// Result from the check of a detector.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Alarm {
+    /// Send the alarm, with the length of the incident in seconds.
+    Send(f64),
+    /// Alarm in the cooldown, it is only logged.
+    Muted(f64),
+}
+
+/// State of one kind of incident.
+#[derive(Debug, Clone, Default)]
+pub struct Detector {
+    since: Option<Instant>,
+    /// Threshold is reached, the incident is handled.
+    reached: bool,
+    /// Alarm was sent for the running incident.
+    sent: bool,
+    last_alarm: Option<Instant>,
+}
+
+impl Detector {
+    pub fn start(&mut self, now: Instant) {
+        if self.since.is_none() {
+            self.since = Some(now);
+        }
+    }
+
+    /// Incident ends, returns its length, when an alarm was sent for it.
+    pub fn end(&mut self, now: Instant) -> Option<f64> {
+        let since = self.since.take()?;
+        let sent = self.sent;
+
+        self.reached = false;
+        self.sent = false;
+
+        sent.then(|| now.saturating_duration_since(since).as_secs_f64())
+    }
+
+    /// Check the running incident against the threshold, only once per incident.
+    pub fn check(&mut self, now: Instant, threshold: f64, cooldown: Duration) -> Option<Alarm> {
+        let length = now.saturating_duration_since(self.since?).as_secs_f64();
+
+        if self.reached || length < threshold {
+            return None;
+        }
+
+        self.reached = true;
+
+        if self
+            .last_alarm
+            .is_some_and(|t| now.saturating_duration_since(t) < cooldown)
+        {
+            return Some(Alarm::Muted(length));
+        }
+
+        self.sent = true;
+        self.last_alarm = Some(now);
+
+        Some(Alarm::Send(length))
+    }
+}
+
+fn current_source(manager: &ChannelManager) -> String {
+    manager
+        .current_media
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|m| m.source.clone())
+        .unwrap_or_default()
+}
+
+/// Silence and black picture detectors, fed by the lines of the alarm process.
+struct Alarms {
+    id: i32,
+    manager: ChannelManager,
+    settings: OutputAlarms,
+    silence: Detector,
+    black: Detector,
+}
+
+impl Alarms {
+    fn detector(&mut self, kind: AlarmKind) -> &mut Detector {
+        match kind {
+            AlarmKind::Silence => &mut self.silence,
+            AlarmKind::Black => &mut self.black,
+        }
+    }
+
+    fn check(&mut self, kind: AlarmKind, now: Instant) {
+        let id = self.id;
+        let threshold = match kind {
+            AlarmKind::Silence => self.settings.silence,
+            AlarmKind::Black => self.settings.black,
+        };
+        let cooldown = Duration::from_secs(self.settings.cooldown.max(0) as u64);
+
+        if threshold <= 0.0 {
+            return;
+        }
+
+        match self.detector(kind).check(now, threshold, cooldown) {
+            Some(Alarm::Send(length)) => {
+                error!(target: Target::file_mail(), channel = id;
+                    "{kind} in the program output for <yellow>{length:.0}</> seconds, clip: <b><magenta>{}</></b>",
+                    current_source(&self.manager)
+                );
+            }
+            Some(Alarm::Muted(length)) => {
+                warn!(target: Target::file(), channel = id;
+                    "{kind} in the program output for <yellow>{length:.0}</> seconds, alarm is in the cooldown"
+                );
+            }
+            None => {}
+        }
+    }
+
+    fn line(&mut self, line: &str) {
+        let id = self.id;
+        let now = Instant::now();
+
+        match parse_alarm(line) {
+            Some(AlarmEvent::Start(kind)) => self.detector(kind).start(now),
+            Some(AlarmEvent::End(kind)) => {
+                if let Some(length) = self.detector(kind).end(now) {
+                    info!(target: Target::file_mail(), channel = id;
+                        "{kind} in the program output ended after <yellow>{length:.0}</> seconds"
+                    );
+                }
+            }
+            Some(AlarmEvent::Tick) => {
+                self.check(AlarmKind::Silence, now);
+                self.check(AlarmKind::Black, now);
+            }
+            Some(AlarmEvent::Skip) => {}
+            None => {
+                warn!(target: Target::file(), channel = id;
+                    "<bright black>[Alarms]</> {}",
+                    line.replace("[error] ", "").replace("[fatal] ", "")
+                );
+            }
+        }
+    }
+}
+
+/// Process with the silence and black picture detection of the program.
+pub struct ProgramAlarms {
+    manager: ChannelManager,
+    settings: OutputAlarms,
+    tap: Option<ProgramTap>,
+}
+
+impl ProgramAlarms {
+    pub fn new(manager: ChannelManager, config: &PlayoutConfig) -> Self {
+        let mut alarms = Self {
+            manager,
+            settings: config.output.alarms.clone(),
+            tap: None,
+        };
+
+        if alarms.settings.enabled() {
+            alarms.start(config.general.channel_id);
+        }
+
+        alarms
+    }
+
+    fn start(&mut self, id: i32) {
+        let mut alarms = Alarms {
+            id,
+            manager: self.manager.clone(),
+            settings: self.settings.clone(),
+            silence: Detector::default(),
+            black: Detector::default(),
+        };
+        let limits = self.manager.config.lock().unwrap().channel.limits.clone();
+
+        match ProgramTap::spawn(
+            id,
+            "alarms",
+            alarm_args(&self.settings),
+            &limits,
+            move |line| alarms.line(line),
+        ) {
+            Ok(tap) => self.tap = Some(tap),
+            Err(e) => {
+                error!(target: Target::file_mail(), channel = id; "Program alarms failed: {e}");
+            }
+        }
+    }
+
+    /// Start the detection again with changed settings, call it only between two clips.
+    pub fn update(&mut self, config: &PlayoutConfig) {
+        if config.output.alarms == self.settings {
+            return;
+        }
+
+        self.stop();
+        self.settings = config.output.alarms.clone();
+
+        if self.settings.enabled() {
+            self.start(config.general.channel_id);
+        }
+    }
+
+    /// Give the package to the detection, without waiting for it.
+    pub fn write(&mut self, buf: &[u8]) {
+        if self.tap.as_mut().is_some_and(|tap| !tap.write(buf)) {
+            self.stop();
+        }
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(mut tap) = self.tap.take() {
+            tap.stop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::config::OutputAlarms;
+
+    #[test]
+    fn output_alarm_detection() {
+        assert_eq!(
+            parse_alarm("lavfi.silence_start=12.5"),
+            Some(AlarmEvent::Start(AlarmKind::Silence))
+        );
+        assert_eq!(
+            parse_alarm("lavfi.black_end=20.04"),
+            Some(AlarmEvent::End(AlarmKind::Black))
+        );
+        assert_eq!(parse_alarm("progress=continue"), Some(AlarmEvent::Tick));
+        assert_eq!(parse_alarm("out_time=00:00:01.000"), Some(AlarmEvent::Skip));
+        assert_eq!(
+            parse_alarm("frame:250  pts:10     pts_time:10"),
+            Some(AlarmEvent::Skip)
+        );
+        assert_eq!(parse_alarm("[error] Invalid data found"), None);
+
+        let mut alarms = OutputAlarms {
+            silence: 10.0,
+            ..Default::default()
+        };
+        let args = alarm_args(&alarms);
+
+        assert!(args.contains(&"-vn".to_string()));
+        assert!(args
+            .iter()
+            .any(|a| a.starts_with("silencedetect=n=-50dB:d=0.5,ametadata")));
+
+        alarms.black = 5.0;
+        assert!(alarm_args(&alarms)
+            .iter()
+            .any(|a| a.contains("blackdetect=d=0:pix_th=0.1,metadata")));
+
+        let start = std::time::Instant::now();
+        let at = |secs| start + std::time::Duration::from_secs(secs);
+        let cooldown = std::time::Duration::from_secs(900);
+        let mut detector = Detector::default();
+
+        assert_eq!(detector.check(at(5), 10.0, cooldown), None);
+
+        detector.start(at(0));
+        detector.start(at(3));
+
+        assert_eq!(detector.check(at(5), 10.0, cooldown), None);
+        assert_eq!(
+            detector.check(at(11), 10.0, cooldown),
+            Some(Alarm::Send(11.0))
+        );
+        // one alarm per incident
+        assert_eq!(detector.check(at(20), 10.0, cooldown), None);
+        assert_eq!(detector.end(at(30)), Some(30.0));
+
+        // the next incident is in the cooldown
+        detector.start(at(100));
+        assert_eq!(
+            detector.check(at(110), 10.0, cooldown),
+            Some(Alarm::Muted(10.0))
+        );
+        assert_eq!(detector.end(at(120)), None);
+
+        detector.start(at(1000));
+        assert_eq!(
+            detector.check(at(1010), 10.0, cooldown),
+            Some(Alarm::Send(10.0))
+        );
+    }
+}
@@ -0,0 +1,173 @@
+/*
+Output for traditional broadcast chains.
+
+The filters go at the end of the video chain, after text and logo, so the legalizer also catches
+overlays. In HLS mode they are in the decoder, in the other modes in the encoder.
+The encoder flags go in front of every output from the output parameters.
+
+*/
+
+use crate::utils::config::{Broadcast, FieldOrder};
+use crate::vec_strings;
+
+/// Video filters for interlacing, colors and legal levels.
+pub fn broadcast_filter(settings: &Broadcast, fps: f64) -> Option<String> {
+    let mut filters = vec![];
+
+    if settings.bt709 {
+        filters.push(
+            "scale=out_color_matrix=bt709:out_range=tv,setparams=color_primaries=bt709:color_trc=bt709:colorspace=bt709:range=tv"
+                .to_string(),
+        );
+    }
+
+    if settings.legalizer {
+        filters.push(
+            "lutyuv=y=clip(val\\,16\\,235):u=clip(val\\,16\\,240):v=clip(val\\,16\\,240)"
+                .to_string(),
+        );
+    }
+
+    let field = match settings.field_order {
+        FieldOrder::Progressive => None,
+        FieldOrder::Tff => Some("tff"),
+        FieldOrder::Bff => Some("bff"),
+    };
+
+    if let Some(field) = field {
+        if fps > 30.0 {
+            // two frames become the two fields of one frame
+            filters.push(format!("interlace=scan={field}:lowpass=complex"));
+        } else {
+            filters.push(format!("setfield=mode={field}"));
+        }
+    }
+
+    (!filters.is_empty()).then(|| filters.join(","))
+}
+
+/// Encoder flags for interlacing and colors.
+pub fn broadcast_args(settings: &Broadcast) -> Vec<String> {
+    let mut args = vec![];
+
+    match settings.field_order {
+        FieldOrder::Progressive => {}
+        FieldOrder::Tff => args.append(&mut vec_strings!["-flags:v", "+ilme+ildct", "-top", "1"]),
+        FieldOrder::Bff => args.append(&mut vec_strings!["-flags:v", "+ilme+ildct", "-top", "0"]),
+    }
+
+    if settings.bt709 {
+        args.append(&mut vec_strings![
+            "-color_primaries:v",
+            "bt709",
+            "-color_trc:v",
+            "bt709",
+            "-colorspace:v",
+            "bt709",
+            "-color_range:v",
+            "tv"
+        ]);
+    }
+
+    args
+}
+
+/// Add the encoder flags in front of every output format, or in front of the output.
+pub fn broadcast_flags(cmd: &mut Vec<String>, settings: &Broadcast) {
+    let args = broadcast_args(settings);
+
+    if args.is_empty() || cmd.is_empty() {
+        return;
+    }
+
+    let mut positions: Vec<usize> = cmd
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| *c == "-f")
+        .map(|(i, _)| i)
+        .collect();
+
+    if positions.is_empty() {
+        positions.push(cmd.len() - 1);
+    }
+
+    for i in positions.into_iter().rev() {
+        cmd.splice(i..i, args.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::config::Broadcast;
+    use crate::utils::config::FieldOrder;
+    use crate::vec_strings;
+
+    #[test]
+    fn broadcast_output() {
+        let mut settings = Broadcast::default();
+
+        assert_eq!(broadcast_filter(&settings, 25.0), None);
+        assert!(broadcast_args(&settings).is_empty());
+
+        settings.field_order = FieldOrder::Tff;
+
+        assert_eq!(
+            broadcast_filter(&settings, 25.0).unwrap(),
+            "setfield=mode=tff"
+        );
+        assert_eq!(
+            broadcast_filter(&settings, 50.0).unwrap(),
+            "interlace=scan=tff:lowpass=complex"
+        );
+
+        settings.bt709 = true;
+        settings.legalizer = true;
+
+        let filter = broadcast_filter(&settings, 50.0).unwrap();
+
+        assert!(filter.starts_with("scale=out_color_matrix=bt709:out_range=tv,"));
+        assert!(filter.contains(",lutyuv=y=clip(val\\,16\\,235)"));
+        assert!(filter.ends_with(",interlace=scan=tff:lowpass=complex"));
+
+        let mut cmd = vec_strings![
+            "-c:v",
+            "libx264",
+            "-f",
+            "flv",
+            "rtmp://a/live",
+            "-c:v",
+            "libx264",
+            "-f",
+            "mpegts",
+            "udp://b"
+        ];
+        broadcast_flags(&mut cmd, &settings);
+
+        assert_eq!(cmd.iter().filter(|c| *c == "+ilme+ildct").count(), 2);
+        assert_eq!(cmd.iter().filter(|c| *c == "-color_range:v").count(), 2);
+        assert_eq!(
+            cmd[2..6],
+            vec_strings!["-flags:v", "+ilme+ildct", "-top", "1"]
+        );
+        assert_eq!(cmd.last().unwrap(), "udp://b");
+
+        let mut cmd = vec_strings!["-c:v", "libx264", "out.ts"];
+        settings.bt709 = false;
+        settings.field_order = FieldOrder::Bff;
+        broadcast_flags(&mut cmd, &settings);
+
+        assert_eq!(
+            cmd,
+            vec_strings![
+                "-c:v",
+                "libx264",
+                "-flags:v",
+                "+ilme+ildct",
+                "-top",
+                "0",
+                "out.ts"
+            ]
+        );
+    }
+}
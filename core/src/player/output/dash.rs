@@ -0,0 +1,96 @@
+/*
+DASH output.
+
+The encoder works like in stream mode, it reads the decoded clips from a pipe.
+The dash muxer writes the MPD with segment templates and the segments into the public folder,
+segments which fall out of the window are removed by the muxer.
+
+*/
+
+use std::{fs, io, path::Path};
+
+use walkdir::WalkDir;
+
+use crate::vec_strings;
+
+/// Muxer options, which are set when the output parameters don't have them.
+const DASH_DEFAULTS: [(&str, &str); 4] = [
+    ("-use_template", "1"),
+    ("-use_timeline", "1"),
+    ("-window_size", "10"),
+    ("-extra_window_size", "5"),
+];
+
+/// Add the default dash muxer options in front of `-f dash`.
+pub fn dash_flags(cmd: &mut Vec<String>) {
+    let Some(i) = cmd.windows(2).position(|w| w[0] == "-f" && w[1] == "dash") else {
+        return;
+    };
+
+    let mut flags = vec![];
+
+    for (key, value) in DASH_DEFAULTS {
+        if !cmd.iter().any(|c| c == key) {
+            flags.append(&mut vec_strings![key, value]);
+        }
+    }
+
+    cmd.splice(i..i, flags);
+}
+
+/// Remove segments from the last run, the muxer starts again with the first segment.
+pub fn drain_dash_path(path: &Path) -> io::Result<()> {
+    for entry in WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.path().is_file() && e.path().extension().is_some_and(|ext| ext == "m4s"))
+    {
+        fs::remove_file(entry.path())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec_strings;
+
+    #[test]
+    fn dash_muxer_flags() {
+        let mut cmd = vec_strings![
+            "-c:v",
+            "libx264",
+            "-window_size",
+            "5",
+            "-f",
+            "dash",
+            "/public/live/stream.mpd"
+        ];
+        dash_flags(&mut cmd);
+
+        assert_eq!(
+            cmd,
+            vec_strings![
+                "-c:v",
+                "libx264",
+                "-window_size",
+                "5",
+                "-use_template",
+                "1",
+                "-use_timeline",
+                "1",
+                "-extra_window_size",
+                "5",
+                "-f",
+                "dash",
+                "/public/live/stream.mpd"
+            ]
+        );
+
+        let mut cmd = vec_strings!["-f", "flv", "rtmp://localhost/live/stream"];
+        dash_flags(&mut cmd);
+
+        assert_eq!(cmd.len(), 3);
+    }
+}
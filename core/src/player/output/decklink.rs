@@ -0,0 +1,315 @@
+/*
+Blackmagic Decklink SDI output.
+
+In decklink mode, the encoder gets its output parameters from the decklink settings:
+
+- the processing resolution and frame rate follow the video format, like `1080i50`
+- video goes uncompressed (8 bit uyvy422 or 10 bit v210) to the card
+- the audio tracks are merged and mapped to 2, 8 or 16 embedded SDI channels
+
+The installed cards are detected with ffmpeg and listed in the system endpoint.
+
+*/
+
+use std::{
+    process::Command,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use regex::Regex;
+
+use crate::utils::config::Decklink;
+use crate::vec_strings;
+
+/// Video formats, which the settings offer.
+pub const FORMATS: [&str; 16] = [
+    "486i59.94",
+    "576i50",
+    "720p50",
+    "720p59.94",
+    "720p60",
+    "1080i50",
+    "1080i59.94",
+    "1080i60",
+    "1080p23.98",
+    "1080p24",
+    "1080p25",
+    "1080p29.97",
+    "1080p30",
+    "1080p50",
+    "1080p59.94",
+    "2160p50",
+];
+
+const DEVICE_CACHE: Duration = Duration::from_secs(60);
+
+static DEVICES: Mutex<Option<(Instant, Vec<String>)>> = Mutex::new(None);
+
+/// Video format from the card.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VideoFormat {
+    pub width: i64,
+    pub height: i64,
+    /// Frames per second, interlaced formats have two fields per frame.
+    pub fps: f64,
+    pub aspect: f64,
+    /// Field order of interlaced formats: `tt` for HD, `bb` for SD.
+    pub field_order: Option<&'static str>,
+}
+
+fn exact_rate(rate: &str) -> Option<f64> {
+    match rate {
+        "23.98" => Some(24000.0 / 1001.0),
+        "29.97" => Some(30000.0 / 1001.0),
+        "59.94" => Some(60000.0 / 1001.0),
+        "24" | "25" | "30" | "50" | "60" => rate.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Video format from a name like `1080i50` (field rate) or `720p59.94` (frame rate).
+pub fn video_format(name: &str) -> Option<VideoFormat> {
+    let re = Regex::new(r"^(\d{3,4})([ip])([\d.]+)$").unwrap();
+    let caps = re.captures(name.trim())?;
+    let height: i64 = caps[1].parse().ok()?;
+    let interlaced = &caps[2] == "i";
+    let rate = exact_rate(&caps[3])?;
+
+    let (width, aspect) = match height {
+        486 | 576 => (720, 4.0 / 3.0),
+        720 => (1280, 16.0 / 9.0),
+        1080 => (1920, 16.0 / 9.0),
+        2160 => (3840, 16.0 / 9.0),
+        _ => return None,
+    };
+
+    // SD is only interlaced, 720 and 2160 only progressive
+    if interlaced != (width == 720) && height != 1080 {
+        return None;
+    }
+
+    if interlaced && rate < 50.0 {
+        return None;
+    }
+
+    // PAL and NTSC have a fixed rate
+    if (height == 576 && &caps[3] != "50") || (height == 486 && &caps[3] != "59.94") {
+        return None;
+    }
+
+    Some(VideoFormat {
+        width,
+        height,
+        fps: if interlaced { rate / 2.0 } else { rate },
+        aspect,
+        field_order: interlaced.then_some(if width == 720 { "bb" } else { "tt" }),
+    })
+}
+
+/// Encoder output parameters for the card.
+pub fn decklink_args(decklink: &Decklink) -> Vec<String> {
+    let mut args = if decklink.ten_bit {
+        vec_strings!["-c:v", "v210"]
+    } else {
+        vec_strings!["-pix_fmt", "uyvy422", "-c:v", "rawvideo"]
+    };
+
+    if let Some(order) = video_format(&decklink.format).and_then(|f| f.field_order) {
+        args.append(&mut vec_strings!["-field_order", order]);
+    }
+
+    args.append(&mut vec_strings![
+        "-c:a",
+        "pcm_s16le",
+        "-ar",
+        "48000",
+        "-f",
+        "decklink"
+    ]);
+
+    if let Some(offset) = decklink.timing_offset {
+        args.append(&mut vec_strings!["-timing_offset", offset]);
+    }
+
+    for (key, value) in [
+        ("-duplex_mode", &decklink.duplex_mode),
+        ("-link", &decklink.link),
+    ] {
+        if !value.is_empty() && value != "unset" {
+            args.append(&mut vec_strings![key, value]);
+        }
+    }
+
+    args.append(&mut vec_strings![
+        "-preroll",
+        decklink.preroll.max(0.0),
+        decklink.device.trim()
+    ]);
+
+    args
+}
+
+/// Audio filter for the SDI channels, after the first audio track.
+/// More tracks are merged first, their channels follow each other.
+pub fn audio_filter(decklink: &Decklink, tracks: i32, channels: u8) -> String {
+    let outputs = match decklink.audio_channels {
+        0..=2 => 2,
+        3..=8 => 8,
+        _ => 16,
+    };
+    let tracks = tracks.max(1) as usize;
+    let channels = channels.max(1) as usize;
+    let mut filter = String::new();
+
+    if tracks > 1 {
+        for t in 1..tracks {
+            filter.push_str(&format!("[0:a:{t}]"));
+        }
+
+        filter.push_str(&format!("amerge=inputs={tracks},"));
+    }
+
+    let mut pan = format!("pan={outputs}c");
+
+    for i in 0..outputs {
+        let source = if decklink.audio_map.is_empty() {
+            Some(i).filter(|i| *i < tracks * channels)
+        } else {
+            decklink
+                .audio_map
+                .get(i)
+                .and_then(|m| m.trim().split_once('.'))
+                .and_then(|(t, c)| Some((t.parse::<usize>().ok()?, c.parse::<usize>().ok()?)))
+                .filter(|(t, c)| *t < tracks && *c < channels)
+                .map(|(t, c)| t * channels + c)
+        };
+
+        if let Some(source) = source {
+            pan.push_str(&format!("|c{i}=c{source}"));
+        }
+    }
+
+    if !pan.contains('|') {
+        // only silence
+        pan.push_str("|c0=0*c0");
+    }
+
+    filter.push_str(&pan);
+
+    filter
+}
+
+/// Card names from the `ffmpeg -sinks decklink` output.
+pub fn parse_devices(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter(|l| l.starts_with(' ') || l.starts_with('*'))
+        .map(|l| {
+            let line = l.trim_start_matches('*').trim();
+
+            line.rfind(" [")
+                .map_or(line, |i| &line[..i])
+                .trim()
+                .to_string()
+        })
+        .filter(|l| !l.is_empty())
+        .collect()
+}
+
+/// Installed cards, ffmpeg is asked again after one minute.
+pub fn devices() -> Vec<String> {
+    let mut cache = DEVICES.lock().unwrap();
+
+    if let Some((time, list)) = cache.as_ref() {
+        if time.elapsed() < DEVICE_CACHE {
+            return list.clone();
+        }
+    }
+
+    let list = Command::new("ffmpeg")
+        .args(["-hide_banner", "-sinks", "decklink"])
+        .output()
+        .map(|o| parse_devices(&String::from_utf8_lossy(&o.stdout)))
+        .unwrap_or_default();
+
+    *cache = Some((Instant::now(), list.clone()));
+
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::config::Decklink;
+    use crate::vec_strings;
+
+    #[test]
+    fn decklink_output() {
+        let format = video_format("1080i50").unwrap();
+
+        assert_eq!((format.width, format.height), (1920, 1080));
+        assert_eq!(format.fps, 25.0);
+        assert_eq!(format.field_order, Some("tt"));
+        assert_eq!(video_format("576i50").unwrap().field_order, Some("bb"));
+        assert!((video_format("720p59.94").unwrap().fps - 59.94).abs() < 0.01);
+        assert_eq!(video_format("1080p25").unwrap().field_order, None);
+        assert!(video_format("720i50").is_none());
+        assert!(video_format("576i59.94").is_none());
+        assert!(video_format("1080i25").is_none());
+        assert!(video_format("hd").is_none());
+
+        let mut decklink = Decklink {
+            device: "DeckLink Duo (1)".to_string(),
+            ..Default::default()
+        };
+
+        let args = decklink_args(&decklink);
+
+        assert_eq!(&args[..4], ["-pix_fmt", "uyvy422", "-c:v", "rawvideo"]);
+        assert!(args.windows(2).any(|w| w == ["-field_order", "tt"]));
+        assert!(args.windows(2).any(|w| w == ["-f", "decklink"]));
+        assert!(!args.contains(&"-duplex_mode".to_string()));
+        assert_eq!(args.last().unwrap(), "DeckLink Duo (1)");
+
+        decklink.ten_bit = true;
+        decklink.format = "720p50".to_string();
+        decklink.timing_offset = Some(-12);
+        decklink.link = "dual".to_string();
+
+        let args = decklink_args(&decklink);
+
+        assert_eq!(&args[..2], ["-c:v", "v210"]);
+        assert!(!args.contains(&"-field_order".to_string()));
+        assert!(args.windows(2).any(|w| w == ["-timing_offset", "-12"]));
+        assert!(args.windows(2).any(|w| w == ["-link", "dual"]));
+
+        assert_eq!(audio_filter(&decklink, 1, 2), "pan=2c|c0=c0|c1=c1");
+
+        decklink.audio_channels = 8;
+
+        assert_eq!(
+            audio_filter(&decklink, 2, 2),
+            "[0:a:1]amerge=inputs=2,pan=8c|c0=c0|c1=c1|c2=c2|c3=c3"
+        );
+
+        decklink.audio_map = vec_strings!["1.0", "1.1", "", "0.0", "3.0"];
+
+        assert_eq!(
+            audio_filter(&decklink, 2, 2),
+            "[0:a:1]amerge=inputs=2,pan=8c|c0=c2|c1=c3|c3=c0"
+        );
+
+        decklink.audio_map = vec_strings!["4.0"];
+
+        assert_eq!(audio_filter(&decklink, 1, 2), "pan=8c|c0=0*c0");
+
+        let sinks = "Auto-detected sinks for decklink:\n  DeckLink Duo (1) [DeckLink Duo (1)]\n* DeckLink Mini Monitor [DeckLink Mini Monitor]\n";
+
+        assert_eq!(
+            parse_devices(sinks),
+            ["DeckLink Duo (1)", "DeckLink Mini Monitor"]
+        );
+        assert!(parse_devices("Cannot list sinks: Function not implemented").is_empty());
+    }
+}
@@ -0,0 +1,430 @@
+/*
+Delayed output, for time shifted channels like +1h.
+
+The recorder follows the HLS playlists from the channel output. Every new segment is copied to the
+`delayed` folder in the public folder and goes into the delayed playlist, when it is older than
+`delay`. So the delayed stream is served at `/{id}/delayed/...`, with the same playlist names
+as the live stream.
+
+With `output_param`, ffmpeg reads the delayed playlist and pushes it as second output of the channel.
+
+The buffer is on disk, for one hour of a 5 Mbit stream that are about 2.2 GB. It starts empty
+with every start of the channel, so the delayed output is on air after the first `delay` seconds.
+
+*/
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::atomic::Ordering,
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::*;
+use serde::Serialize;
+use shlex::split;
+use walkdir::{DirEntry, WalkDir};
+
+use crate::player::{controller::ChannelManager, output::preview::preview_dir};
+use crate::utils::{logging::Target, process_limits::LimitProcess, time_machine::clock_sleep};
+use crate::vec_strings;
+
+/// Folder from the delayed output, in the public folder of the channel.
+pub const DELAYED_DIR: &str = "delayed";
+
+/// Segments in the delayed playlists.
+const LIST_SIZE: usize = 6;
+
+/// Time between restarts of a failed push.
+const RETRY_DELAY: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct DelayedStatus {
+    pub enable: bool,
+    pub delay: f64,
+    /// Seconds in the buffer, which are not played yet.
+    pub buffered: f64,
+    /// The buffer reached the delay, the delayed output is on air.
+    pub ready: bool,
+    /// ffmpeg pushes the delayed stream to `output_param`.
+    pub pushing: bool,
+    pub error: Option<String>,
+}
+
+/// Segment from a HLS media playlist, with its length in seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub uri: String,
+    pub duration: f64,
+}
+
+/// Segments of a media playlist, master playlists have none.
+pub fn parse_segments(content: &str) -> Vec<Segment> {
+    let mut segments = vec![];
+    let mut duration = None;
+
+    for line in content.lines().map(str::trim) {
+        if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+            duration = extinf.split(',').next().and_then(|d| d.trim().parse().ok());
+        } else if !line.is_empty() && !line.starts_with('#') {
+            if let Some(duration) = duration.take() {
+                segments.push(Segment {
+                    uri: line.to_string(),
+                    duration,
+                });
+            }
+        }
+    }
+
+    segments
+}
+
+/// Media playlist from the delayed segments, `sequence` is the number of the first segment.
+pub fn delayed_playlist(segments: &[Segment], sequence: u64) -> String {
+    let target = segments
+        .iter()
+        .map(|s| s.duration.ceil() as u64)
+        .max()
+        .unwrap_or(1);
+    let mut playlist = format!(
+        "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:{target}\n#EXT-X-MEDIA-SEQUENCE:{sequence}\n"
+    );
+
+    for segment in segments {
+        playlist.push_str(&format!(
+            "#EXTINF:{:.6},\n{}\n",
+            segment.duration, segment.uri
+        ));
+    }
+
+    playlist
+}
+
+#[derive(Debug)]
+struct Buffered {
+    segment: Segment,
+    arrived: Instant,
+}
+
+/// Delayed copy of one media playlist.
+#[derive(Debug, Default)]
+struct Track {
+    /// Segments from the last version of the live playlist.
+    seen: HashSet<String>,
+    buffer: VecDeque<Buffered>,
+    /// Segments, which are removed from the buffer.
+    removed: u64,
+    count: u64,
+    content: String,
+}
+
+impl Track {
+    /// Copy the new segments from the live playlist into the buffer.
+    fn record(&mut self, live: &Path, target: &Path, content: &str) -> Result<(), String> {
+        let segments = parse_segments(content);
+        let stem = live
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        // at the first look only the newest segment, the older ones are already played
+        let skip = if self.seen.is_empty() {
+            segments.len().saturating_sub(1)
+        } else {
+            0
+        };
+
+        for segment in segments
+            .iter()
+            .skip(skip)
+            .filter(|s| !self.seen.contains(&s.uri))
+        {
+            let source = live.with_file_name(&segment.uri);
+            let extension = Path::new(&segment.uri)
+                .extension()
+                .map(|e| e.to_string_lossy().to_string())
+                .unwrap_or_else(|| "ts".to_string());
+            // new names, ffmpeg starts again with the first number after a restart
+            let name = format!("{stem}_delayed-{}.{extension}", self.count);
+
+            fs::copy(&source, target.with_file_name(&name))
+                .map_err(|e| format!("Copy of {} failed: {e}", source.display()))?;
+
+            self.count += 1;
+            self.buffer.push_back(Buffered {
+                segment: Segment {
+                    uri: name,
+                    duration: segment.duration,
+                },
+                arrived: Instant::now(),
+            });
+        }
+
+        self.seen = segments.into_iter().map(|s| s.uri).collect();
+
+        Ok(())
+    }
+
+    /// Write the delayed playlist and remove the played segments.
+    fn publish(&mut self, target: &Path, delay: Duration) -> Result<(), String> {
+        let due = self
+            .buffer
+            .iter()
+            .take_while(|b| b.arrived.elapsed() >= delay)
+            .count();
+
+        if due == 0 {
+            return Ok(());
+        }
+
+        // players can still load the segments, which just left the playlist
+        let played = due.saturating_sub(LIST_SIZE * 2);
+
+        for old in self.buffer.drain(..played) {
+            let _ = fs::remove_file(target.with_file_name(&old.segment.uri));
+        }
+
+        self.removed += played as u64;
+
+        let due = due - played;
+        let start = due.saturating_sub(LIST_SIZE);
+        let window: Vec<Segment> = self
+            .buffer
+            .iter()
+            .skip(start)
+            .take(due - start)
+            .map(|b| b.segment.clone())
+            .collect();
+        let content = delayed_playlist(&window, self.removed + start as u64);
+
+        if content != self.content {
+            let tmp = target.with_extension("m3u8.tmp");
+
+            fs::write(&tmp, &content)
+                .and_then(|_| fs::rename(&tmp, target))
+                .map_err(|e| format!("Write delayed playlist failed: {e}"))?;
+
+            self.content = content;
+        }
+
+        Ok(())
+    }
+
+    /// Seconds in the buffer, which are not in the delayed playlist yet.
+    fn waiting(&self, delay: Duration) -> f64 {
+        self.buffer
+            .iter()
+            .filter(|b| b.arrived.elapsed() < delay)
+            .map(|b| b.segment.duration)
+            .sum()
+    }
+}
+
+/// HLS playlists from the live output, without the preview and the delayed output.
+fn live_playlists(public: &Path, delayed: &Path) -> Vec<PathBuf> {
+    let preview = preview_dir(public);
+    let mut playlists: Vec<PathBuf> = WalkDir::new(public)
+        .into_iter()
+        .filter_entry(|e| e.path() != delayed && e.path() != preview)
+        .flatten()
+        .filter(|e| {
+            e.file_type().is_file() && e.path().extension().is_some_and(|ext| ext == "m3u8")
+        })
+        .map(DirEntry::into_path)
+        .collect();
+
+    playlists.sort();
+    playlists
+}
+
+fn start_push(manager: &ChannelManager, input: &Path, output_param: &str) -> Result<Child, String> {
+    let id = manager.channel.lock().unwrap().id;
+    let limits = manager.config.lock().unwrap().channel.limits.clone();
+    let mut cmd = vec_strings![
+        "-hide_banner",
+        "-nostats",
+        "-v",
+        "level+error",
+        "-i",
+        input.to_string_lossy(),
+        "-c",
+        "copy"
+    ];
+
+    cmd.append(&mut split(output_param).ok_or("Invalid output parameters")?);
+
+    let mut child = Command::new("ffmpeg")
+        .args(&cmd)
+        .limits(&limits)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Start delayed output failed: {e}"))?;
+
+    if let Some(stderr) = child.stderr.take() {
+        thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                error!(target: Target::file_mail(), channel = id; "<bright black>[Delayed]</> {line}");
+            }
+        });
+    }
+
+    info!(target: Target::file_mail(), channel = id; "Push delayed output from <b><magenta>{}</></b>", input.display());
+
+    Ok(child)
+}
+
+/// Record the live HLS output and play it out again after the delay, until the channel stops.
+pub fn delay_recorder(manager: ChannelManager) {
+    let config = manager.config.lock().unwrap().clone();
+    let id = config.general.channel_id;
+    let settings = config.output.delayed.clone();
+    let delay = Duration::from_secs_f64(settings.delay.max(0.0));
+    let public = config.channel.public.clone();
+    let delayed = public.join(DELAYED_DIR);
+    let is_terminated = manager.is_terminated.clone();
+    let mut tracks: HashMap<PathBuf, Track> = HashMap::new();
+    let mut push: Option<Child> = None;
+    let mut last_push: Option<Instant> = None;
+
+    // the buffer from the last run is not complete
+    let _ = fs::remove_dir_all(&delayed);
+
+    info!(target: Target::file_mail(), channel = id; "Delayed output with <yellow>{}</> seconds", settings.delay);
+
+    while !is_terminated.load(Ordering::SeqCst) {
+        let mut error = None;
+        let mut main = None;
+
+        for live in live_playlists(&public, &delayed) {
+            let Ok(relative) = live.strip_prefix(&public) else {
+                continue;
+            };
+            let target = delayed.join(relative);
+            let Ok(content) = fs::read_to_string(&live) else {
+                continue;
+            };
+
+            if let Err(e) = target.parent().map_or(Ok(()), fs::create_dir_all) {
+                error = Some(format!("Unable to create delayed folder: {e}"));
+                continue;
+            }
+
+            if !content.contains("#EXTINF") {
+                // master playlist, the variants have the same names
+                if fs::read_to_string(&target).ok().as_ref() != Some(&content) {
+                    let _ = fs::write(&target, &content);
+                }
+
+                main = Some(target);
+                continue;
+            }
+
+            let track = tracks.entry(target.clone()).or_default();
+            let result = track
+                .record(&live, &target, &content)
+                .and_then(|_| track.publish(&target, delay));
+
+            if let Err(e) = result {
+                error = Some(e);
+            }
+
+            if main.is_none() && target.is_file() {
+                main = Some(target);
+            }
+        }
+
+        let ready = tracks.values().any(|t| !t.content.is_empty());
+
+        if let Some(child) = push.as_mut() {
+            if let Ok(Some(status)) = child.try_wait() {
+                warn!(target: Target::file_mail(), channel = id; "Delayed output stopped: {status}");
+                push = None;
+            }
+        }
+
+        if push.is_none()
+            && ready
+            && !settings.output_param.trim().is_empty()
+            && last_push.is_none_or(|l| l.elapsed() >= RETRY_DELAY)
+        {
+            if let Some(input) = &main {
+                last_push = Some(Instant::now());
+
+                match start_push(&manager, input, &settings.output_param) {
+                    Ok(child) => push = Some(child),
+                    Err(e) => error = Some(e),
+                }
+            }
+        }
+
+        let mut status = manager.delayed_status.lock().unwrap();
+
+        if let Some(e) = error.as_ref().filter(|e| status.error.as_ref() != Some(*e)) {
+            error!(target: Target::file_mail(), channel = id; "{e}");
+        }
+
+        *status = DelayedStatus {
+            enable: true,
+            delay: settings.delay,
+            buffered: tracks
+                .values()
+                .map(|t| t.waiting(delay))
+                .fold(0.0, f64::max),
+            ready,
+            pushing: push.is_some(),
+            error,
+        };
+
+        drop(status);
+        clock_sleep(Duration::from_millis(500));
+    }
+
+    if let Some(mut child) = push {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+
+    *manager.delayed_status.lock().unwrap() = DelayedStatus::default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delayed_hls_playlist() {
+        let live = "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:6\n#EXT-X-MEDIA-SEQUENCE:41\n\
+        #EXT-X-PROGRAM-DATE-TIME:2024-05-01T12:00:00.000+0200\n#EXTINF:6.000000,\nstream-41.ts\n\
+        #EXT-X-CUE-OUT:30.000\n#EXTINF:5.960000,\nstream-42.ts\n";
+        let segments = parse_segments(live);
+
+        assert_eq!(
+            segments,
+            vec![
+                Segment {
+                    uri: "stream-41.ts".to_string(),
+                    duration: 6.0
+                },
+                Segment {
+                    uri: "stream-42.ts".to_string(),
+                    duration: 5.96
+                }
+            ]
+        );
+        assert!(
+            parse_segments("#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1280000\nstream_720p.m3u8\n")
+                .is_empty()
+        );
+
+        let delayed = delayed_playlist(&segments, 7);
+
+        assert!(delayed.contains("#EXT-X-TARGETDURATION:6\n#EXT-X-MEDIA-SEQUENCE:7\n"));
+        assert!(delayed.ends_with("#EXTINF:5.960000,\nstream-42.ts\n"));
+        assert_eq!(parse_segments(&delayed), segments);
+    }
+}
@@ -3,7 +3,7 @@ use std::process::{self, Command, Stdio};
 use log::*;
 
 use crate::player::filter::v_drawtext;
-use crate::utils::{config::PlayoutConfig, logging::Target};
+use crate::utils::{config::PlayoutConfig, logging::Target, process_limits::LimitProcess};
 use crate::vec_strings;
 
 /// Desktop Output
@@ -71,6 +71,7 @@ pub fn output(config: &PlayoutConfig, log_format: &str) -> process::Child {
 
     let enc_proc = match Command::new("ffplay")
         .args(enc_cmd)
+        .limits(&config.channel.limits)
         .stdin(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()
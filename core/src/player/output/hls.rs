@@ -21,20 +21,34 @@ use std::{
     io::{BufRead, BufReader},
     process::{Command, Stdio},
     sync::atomic::Ordering,
-    thread::{self, sleep},
+    thread,
     time::{Duration, SystemTime},
 };
 
 use log::*;
 
-use crate::utils::{logging::log_line, task_runner};
+use crate::utils::{
+    logging::log_line, process_limits::LimitProcess, task_runner, time_machine::clock_sleep,
+};
 use crate::vec_strings;
 use crate::{
     player::{
         controller::{ChannelManager, ProcessUnit::*},
-        input::source_generator,
+        input::{dead_air::decoder_log_format, source_generator},
+        output::{
+            delayed::delay_recorder,
+            rating::node_rating,
+            s3::s3_pusher,
+            scte35::{cue_tagger, node_cue},
+            timed_metadata::node_item,
+        },
         utils::{
-            get_delta, is_free_tcp_port, prepare_output_cmd, sec_to_time, stderr_reader,
+            audio_meter::{ingest_on_air, record_meter},
+            ffmpeg_events::record_event,
+            get_delta, is_free_tcp_port,
+            now_playing::update_now_playing,
+            prepare_output_cmd, sec_to_time, stderr_reader,
+            stream_stats::{progress_args, record_progress, start_stream, stop_stream, INGEST},
             valid_stream, Media,
         },
     },
@@ -50,6 +64,7 @@ fn ingest_to_hls_server(manager: ChannelManager) -> Result<(), ProcessError> {
     let mut error_count = 0;
 
     let mut server_prefix = vec_strings!["-hide_banner", "-nostats", "-v", "level+info"];
+    server_prefix.append(&mut progress_args());
     let stream_input = config.ingest.input_cmd.clone().unwrap();
     let mut dummy_media = Media::new(0, "Live Stream", false);
     dummy_media.unit = Ingest;
@@ -63,7 +78,7 @@ fn ingest_to_hls_server(manager: ChannelManager) -> Result<(), ProcessError> {
 
     server_prefix.append(&mut stream_input.clone());
 
-    if config.processing.vtt_enable {
+    if config.processing.subtitle_passthrough() {
         let vtt_dummy = config
             .channel
             .storage
@@ -101,6 +116,7 @@ fn ingest_to_hls_server(manager: ChannelManager) -> Result<(), ProcessError> {
         let proc_ctl = manager.clone();
         let mut server_proc = match Command::new("ffmpeg")
             .args(server_cmd.clone())
+            .limits(&config.channel.limits)
             .stderr(Stdio::piped())
             .spawn()
         {
@@ -113,11 +129,20 @@ fn ingest_to_hls_server(manager: ChannelManager) -> Result<(), ProcessError> {
 
         let server_err = BufReader::new(server_proc.stderr.take().unwrap());
         *manager.ingest.lock().unwrap() = Some(server_proc);
+        start_stream(&manager, INGEST);
         is_running = false;
 
         for line in server_err.lines() {
             let line = line?;
 
+            if record_progress(&manager, INGEST, &line)
+                || record_meter(&manager, &line, ingest_on_air(&manager))
+            {
+                continue;
+            }
+
+            record_event(&manager, Ingest, &line);
+
             if line.contains("rtmp") && line.contains("Unexpected stream") && !valid_stream(&line) {
                 warn!(target: Target::file_mail(), channel = id; "Unexpected ingest stream: {line}");
 
@@ -126,7 +151,13 @@ fn ingest_to_hls_server(manager: ChannelManager) -> Result<(), ProcessError> {
                 };
             }
 
-            if !is_running && line.contains("Input #0") {
+            if !is_running && line.contains("Input #0") && !manager.accepts_ingest() {
+                warn!(target: Target::file_mail(), channel = id; "Input is switched to {}, ignore live ingest", manager.input_switch.lock().unwrap().source);
+
+                if let Err(e) = proc_ctl.stop(Ingest) {
+                    error!(target: Target::file_mail(), channel = id; "{e}");
+                };
+            } else if !is_running && line.contains("Input #0") {
                 ingest_is_running.store(true, Ordering::SeqCst);
                 playlist_init.store(true, Ordering::SeqCst);
                 is_running = true;
@@ -150,6 +181,7 @@ fn ingest_to_hls_server(manager: ChannelManager) -> Result<(), ProcessError> {
         }
 
         ingest_is_running.store(false, Ordering::SeqCst);
+        stop_stream(&manager, INGEST);
 
         if let Err(e) = manager.wait(Ingest) {
             error!(target: Target::file_mail(), channel = id; "{e}");
@@ -182,12 +214,12 @@ fn ingest_to_hls_server(manager: ChannelManager) -> Result<(), ProcessError> {
 ///
 /// Write with single ffmpeg instance directly to a HLS playlist.
 pub fn write_hls(manager: ChannelManager) -> Result<(), ProcessError> {
-    let config = manager.config.lock()?.clone();
+    let mut config = manager.config.lock()?.clone();
     let id = config.general.channel_id;
     let current_media = manager.current_media.clone();
     let is_terminated = manager.is_terminated.clone();
 
-    let ff_log_format = format!("level+{}", config.logging.ffmpeg_level.to_lowercase());
+    let mut ff_log_format = decoder_log_format(&config);
 
     let channel_mgr_2 = manager.clone();
     let ingest_is_running = manager.ingest_is_running.clone();
@@ -199,10 +231,30 @@ pub fn write_hls(manager: ChannelManager) -> Result<(), ProcessError> {
         thread::spawn(move || ingest_to_hls_server(channel_mgr_2));
     }
 
+    if config.output.s3_enable {
+        let channel_mgr_s3 = manager.clone();
+        thread::spawn(move || s3_pusher(channel_mgr_s3));
+    }
+
+    if config.output.delayed.enable {
+        let channel_mgr_delay = manager.clone();
+        thread::spawn(move || delay_recorder(channel_mgr_delay));
+    }
+
+    if config.output.scte35
+        || config.output.rating.enable
+        || config.output.item_metadata
+        || !config.processing.audio_track_map.is_empty()
+    {
+        let channel_mgr_cue = manager.clone();
+        thread::spawn(move || cue_tagger(channel_mgr_cue));
+    }
+
     let mut error_count = 0;
 
     for node in get_source {
         *current_media.lock().unwrap() = Some(node.clone());
+        update_now_playing(&manager, &node);
         let ignore = config.logging.ignore_lines.clone();
         let timer = SystemTime::now();
 
@@ -219,6 +271,18 @@ pub fn write_hls(manager: ChannelManager) -> Result<(), ProcessError> {
             continue;
         }
 
+        // every clip has its own writer, so new settings start with the next clip
+        if manager.reload_output.swap(false, Ordering::SeqCst) {
+            info!(target: Target::file_mail(), channel = id; "Apply new output settings");
+
+            config = manager.config.lock()?.clone();
+            ff_log_format = decoder_log_format(&config);
+        }
+
+        node_cue(&manager, &node);
+        node_rating(&manager, &node);
+        node_item(&manager, &node);
+
         info!(target: Target::file_mail(), channel = id;
             "Play for <yellow>{}</>: <b><magenta>{}</></b>",
             sec_to_time(node.out - node.seek),
@@ -272,6 +336,7 @@ pub fn write_hls(manager: ChannelManager) -> Result<(), ProcessError> {
 
         let mut dec_proc = match Command::new("ffmpeg")
             .args(dec_cmd)
+            .limits(&config.channel.limits)
             .stderr(Stdio::piped())
             .spawn()
         {
@@ -285,7 +350,7 @@ pub fn write_hls(manager: ChannelManager) -> Result<(), ProcessError> {
         let dec_err = BufReader::new(dec_proc.stderr.take().unwrap());
         *manager.decoder.lock().unwrap() = Some(dec_proc);
 
-        if let Err(e) = stderr_reader(dec_err, ignore, Decoder, manager.clone()) {
+        if let Err(e) = stderr_reader(dec_err, ignore, Decoder, None, manager.clone()) {
             error!(target: Target::file_mail(), channel = id; "{e:?}");
         };
 
@@ -294,7 +359,7 @@ pub fn write_hls(manager: ChannelManager) -> Result<(), ProcessError> {
         }
 
         while ingest_is_running.load(Ordering::SeqCst) {
-            sleep(Duration::from_secs(1));
+            clock_sleep(Duration::from_secs(1));
         }
 
         if let Ok(elapsed) = timer.elapsed() {
@@ -311,7 +376,7 @@ pub fn write_hls(manager: ChannelManager) -> Result<(), ProcessError> {
         }
     }
 
-    sleep(Duration::from_secs(1));
+    clock_sleep(Duration::from_secs(1));
 
     manager.stop_all();
 
@@ -1,28 +1,80 @@
 use std::{
-    io::{prelude::*, BufReader, BufWriter, Read},
+    io::{prelude::*, BufReader, Read},
     process::{Command, Stdio},
     sync::{atomic::Ordering, mpsc::sync_channel},
-    thread::{self, sleep},
+    thread,
     time::{Duration, SystemTime},
 };
 
 use log::*;
-
+use serde::Serialize;
+use serde_json::Value;
+
+pub mod alarms;
+pub mod broadcast;
+pub mod dash;
+pub mod decklink;
+pub mod delayed;
 mod desktop;
 mod hls;
 mod null;
+pub mod offline;
+pub mod preview;
+pub mod radio;
+pub mod rating;
+pub mod redundant;
+pub mod regions;
+pub mod s3;
+pub mod scte35;
 mod stream;
+pub mod tap;
+pub mod targets;
+pub mod timed_metadata;
 
 pub use hls::write_hls;
 
 use crate::player::{
     controller::{ChannelManager, ProcessUnit::*},
-    input::{ingest_server, source_generator},
-    utils::{sec_to_time, stderr_reader},
+    input::{dead_air::decoder_log_format, ingest_server, source_generator},
+    output::{
+        alarms::ProgramAlarms,
+        preview::ProgramOutput,
+        radio::node_metadata,
+        rating::node_rating,
+        redundant::EncoderOutput,
+        regions::RegionOutputs,
+        scte35::{cue_tagger, node_cue},
+        timed_metadata::node_item,
+    },
+    utils::{
+        now_playing::update_now_playing,
+        passthrough::{copy_args, transcode_reason},
+        sec_to_time, stderr_reader,
+        subtitles::subtitle_map,
+    },
+};
+use crate::utils::{
+    config::PlayoutConfig, errors::ProcessError, logging::Target, process_limits::LimitProcess,
+    task_runner, time_machine::clock_sleep,
 };
-use crate::utils::{config::OutputMode::*, errors::ProcessError, logging::Target, task_runner};
 use crate::vec_strings;
 
+fn json_value<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or_default()
+}
+
+/// Settings from the encoder changed: the output commands, or the sections which build the encoder filters.
+pub fn output_changed(old: &PlayoutConfig, new: &PlayoutConfig) -> bool {
+    old.output.mode != new.output.mode
+        || old.output.output_cmd != new.output.output_cmd
+        || old.output.backup_cmd != new.output.backup_cmd
+        || old.output.output_filter != new.output.output_filter
+        || old.output.decklink != new.output.decklink
+        || json_value(&old.processing) != json_value(&new.processing)
+        || json_value(&old.text) != json_value(&new.text)
+        || json_value(&old.advanced) != json_value(&new.advanced)
+}
+
 /// Player
 ///
 /// Here we create the input file loop, from playlist, or folder source.
@@ -37,7 +89,6 @@ pub fn player(manager: ChannelManager) -> Result<(), ProcessError> {
     let id = config.general.channel_id;
     let config_clone = config.clone();
     let ff_log_format = format!("level+{}", config.logging.ffmpeg_level.to_lowercase());
-    let ignore_enc = config.logging.ignore_lines.clone();
     let mut buffer = [0; 65088];
     let mut live_on = false;
     let playlist_init = manager.list_init.clone();
@@ -48,23 +99,26 @@ pub fn player(manager: ChannelManager) -> Result<(), ProcessError> {
     // get source iterator
     let node_sources = source_generator(manager.clone());
 
-    // get ffmpeg output instance
-    let mut enc_proc = match config.output.mode {
-        Desktop => desktop::output(&config, &ff_log_format),
-        Null => null::output(&config, &ff_log_format),
-        Stream => stream::output(&config, &ff_log_format),
-        _ => panic!("Output mode doesn't exists!"),
-    };
+    // get ffmpeg output instance, with the backup encoder when it is configured
+    let mut enc_writer = EncoderOutput::new(manager.clone(), &config, &ff_log_format);
 
-    let mut enc_writer = BufWriter::new(enc_proc.stdin.take().unwrap());
-    let enc_err = BufReader::new(enc_proc.stderr.take().unwrap());
+    // regional encoders get the same stream, without the replaced break windows
+    let mut regions = RegionOutputs::new(manager.clone(), &ff_log_format);
 
-    *manager.encoder.lock().unwrap() = Some(enc_proc);
-    let enc_p_ctl = manager.clone();
+    // low bitrate mirror of the program for the web interface
+    let mut preview = ProgramOutput::new(manager.clone(), &config);
 
-    // spawn a thread to log ffmpeg output error messages
-    let error_encoder_thread =
-        thread::spawn(move || stderr_reader(enc_err, ignore_enc, Encoder, enc_p_ctl));
+    // silence and black picture detection on the program
+    let mut alarms = ProgramAlarms::new(manager.clone(), &config);
+
+    if config.output.scte35
+        || config.output.rating.enable
+        || config.output.item_metadata
+        || !config.processing.audio_track_map.is_empty()
+    {
+        let channel_mgr_cue = manager.clone();
+        thread::spawn(move || cue_tagger(channel_mgr_cue));
+    }
 
     let channel_mgr_2 = manager.clone();
     let mut ingest_receiver = None;
@@ -84,6 +138,7 @@ pub fn player(manager: ChannelManager) -> Result<(), ProcessError> {
         let config = manager.config.lock()?.clone();
 
         *manager.current_media.lock().unwrap() = Some(node.clone());
+        update_now_playing(&manager, &node);
         let ignore_dec = config.logging.ignore_lines.clone();
         let timer = SystemTime::now();
 
@@ -106,6 +161,28 @@ pub fn player(manager: ChannelManager) -> Result<(), ProcessError> {
             continue;
         }
 
+        // new output settings are applied between two clips
+        if manager.reload_output.swap(false, Ordering::SeqCst) {
+            info!(target: Target::file_mail(), channel = id; "Restart encoder with new output settings");
+
+            enc_writer = enc_writer.reload(&config);
+        }
+
+        if manager.reload_regions.swap(false, Ordering::SeqCst) {
+            info!(target: Target::file_mail(), channel = id; "Restart regional outputs with new settings");
+
+            regions = regions.reload();
+        }
+
+        preview.update(&config);
+        alarms.update(&config);
+
+        node_cue(&manager, &node);
+        node_rating(&manager, &node);
+        node_item(&manager, &node);
+        node_metadata(&manager, &node);
+        regions.start_node(&node);
+
         let c_index = if cfg!(debug_assertions) {
             format!(
                 " ({}/{})",
@@ -136,7 +213,8 @@ pub fn player(manager: ChannelManager) -> Result<(), ProcessError> {
             }
         }
 
-        let mut dec_cmd = vec_strings!["-hide_banner", "-nostats", "-v", &ff_log_format];
+        let dec_log_format = decoder_log_format(&config);
+        let mut dec_cmd = vec_strings!["-hide_banner", "-nostats", "-v", &dec_log_format];
 
         if let Some(decoder_input_cmd) = &config.advanced.decoder.input_cmd {
             dec_cmd.append(&mut decoder_input_cmd.clone());
@@ -144,23 +222,32 @@ pub fn player(manager: ChannelManager) -> Result<(), ProcessError> {
 
         dec_cmd.append(&mut cmd);
 
-        if let Some(mut filter) = node.filter {
-            dec_cmd.append(&mut filter.cmd());
-            dec_cmd.append(&mut filter.map());
-        }
+        let transcode = config
+            .processing
+            .passthrough
+            .then(|| transcode_reason(&config, &node));
 
-        if config.processing.vtt_enable && dec_cmd.iter().any(|s| s.ends_with(".vtt")) {
-            let i = dec_cmd
-                .iter()
-                .filter(|&n| n == "-i")
-                .count()
-                .saturating_sub(1);
+        if let Some(None) = transcode {
+            debug!(target: Target::file_mail(), channel = id; "Passthrough, copy clip in house format");
 
-            dec_cmd.append(&mut vec_strings!("-map", format!("{i}:s"), "-c:s", "copy"));
-        }
+            dec_cmd.append(&mut copy_args(&config.processing));
+        } else {
+            if let Some(Some(reason)) = &transcode {
+                debug!(target: Target::file_mail(), channel = id; "Passthrough, encode clip: {reason}");
+            }
+
+            if let Some(mut filter) = node.filter {
+                dec_cmd.append(&mut filter.cmd());
+                dec_cmd.append(&mut filter.map());
+            }
 
-        if let Some(mut cmd) = config.processing.cmd.clone() {
-            dec_cmd.append(&mut cmd);
+            if config.processing.subtitle_passthrough() {
+                dec_cmd.append(&mut subtitle_map(&dec_cmd));
+            }
+
+            if let Some(mut cmd) = config.processing.cmd.clone() {
+                dec_cmd.append(&mut cmd);
+            }
         }
 
         debug!(target: Target::file_mail(), channel = id;
@@ -171,6 +258,7 @@ pub fn player(manager: ChannelManager) -> Result<(), ProcessError> {
         // create ffmpeg decoder instance, for reading the input files
         let mut dec_proc = match Command::new("ffmpeg")
             .args(dec_cmd)
+            .limits(&config.channel.limits)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
@@ -189,11 +277,13 @@ pub fn player(manager: ChannelManager) -> Result<(), ProcessError> {
         let channel_mgr_c = manager.clone();
 
         let error_decoder_thread =
-            thread::spawn(move || stderr_reader(dec_err, ignore_dec, Decoder, channel_mgr_c));
+            thread::spawn(move || stderr_reader(dec_err, ignore_dec, Decoder, None, channel_mgr_c));
 
         loop {
-            // when server is running, read from it
-            if ingest_is_running.load(Ordering::SeqCst) {
+            let ingest_on = ingest_is_running.load(Ordering::SeqCst);
+
+            // when server is running and the input switch allows it, read from it
+            if ingest_on && manager.accepts_ingest() {
                 if !live_on {
                     info!(target: Target::file_mail(), channel = id; "Switch from {} to live ingest", config.processing.mode);
 
@@ -211,6 +301,10 @@ pub fn player(manager: ChannelManager) -> Result<(), ProcessError> {
 
                         break 'source_iter;
                     };
+
+                    regions.write(&rx.1[..rx.0]);
+                    preview.write(&rx.1[..rx.0]);
+                    alarms.write(&rx.1[..rx.0]);
                 }
             // read from decoder instance
             } else {
@@ -221,6 +315,11 @@ pub fn player(manager: ChannelManager) -> Result<(), ProcessError> {
                     break;
                 }
 
+                if ingest_on {
+                    // ingest is switched off, drop its packages
+                    ingest_receiver.as_ref().unwrap().try_iter().for_each(drop);
+                }
+
                 let dec_bytes_len = match dec_reader.read(&mut buffer[..]) {
                     Ok(length) => length,
                     Err(e) => {
@@ -236,6 +335,10 @@ pub fn player(manager: ChannelManager) -> Result<(), ProcessError> {
 
                         break 'source_iter;
                     };
+
+                    regions.write(&buffer[..dec_bytes_len]);
+                    preview.write(&buffer[..dec_bytes_len]);
+                    alarms.write(&buffer[..dec_bytes_len]);
                 } else {
                     break;
                 }
@@ -266,13 +369,14 @@ pub fn player(manager: ChannelManager) -> Result<(), ProcessError> {
 
     trace!("Out of source loop");
 
-    sleep(Duration::from_secs(1));
+    clock_sleep(Duration::from_secs(1));
 
     manager.stop_all();
 
-    if let Err(e) = error_encoder_thread.join() {
-        error!(target: Target::file_mail(), channel = id; "{e:?}");
-    };
+    regions.stop();
+    preview.stop();
+    alarms.stop();
+    enc_writer.join();
 
     Ok(())
 }
@@ -6,7 +6,7 @@ use crate::player::{
     controller::ProcessUnit::*,
     utils::{prepare_output_cmd, Media},
 };
-use crate::utils::{config::PlayoutConfig, logging::Target};
+use crate::utils::{config::PlayoutConfig, logging::Target, process_limits::LimitProcess};
 use crate::vec_strings;
 
 /// Desktop Output
@@ -35,6 +35,7 @@ pub fn output(config: &PlayoutConfig, log_format: &str) -> process::Child {
 
     let enc_proc = match Command::new("ffmpeg")
         .args(enc_cmd)
+        .limits(&config.channel.limits)
         .stdin(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()
@@ -0,0 +1,312 @@
+/*
+Offline slate in HLS mode.
+
+When the channel stops, by the control API or by the air hours, a small ffmpeg process writes a
+slate into the HLS output of the channel: an image or black, with an optional text and an audio
+loop or silence. So viewers see that the channel is offline, instead of the last segment.
+
+The slate uses the playlist, the segment names and the variant streams from the output
+parameters, and appends to the running playlist. It stops, when the channel starts again,
+and it is not started at a server shutdown.
+
+*/
+
+use std::{
+    env, fs,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+};
+
+use log::*;
+
+use crate::player::{controller::ChannelManager, input::dead_air::source_path};
+use crate::utils::{
+    config::{OutputMode::HLS, PlayoutConfig},
+    logging::Target,
+    process_limits::LimitProcess,
+    shutdown::is_shutting_down,
+};
+use crate::vec_strings;
+
+/// Text file from the slate.
+pub fn offline_text_file(id: i32) -> PathBuf {
+    env::temp_dir().join(format!("ffplayout_{id}_offline.txt"))
+}
+
+fn param<'a>(cmd: &'a [String], key: &str) -> Option<&'a str> {
+    cmd.iter()
+        .position(|c| c == key)
+        .and_then(|i| cmd.get(i + 1))
+        .map(String::as_str)
+}
+
+/// Variant streams from `-var_stream_map`, with their names.
+fn variants(cmd: &[String]) -> Vec<Option<String>> {
+    match param(cmd, "-var_stream_map") {
+        Some(map) => map
+            .split_whitespace()
+            .map(|v| {
+                v.split(',')
+                    .find_map(|p| p.strip_prefix("name:"))
+                    .map(ToString::to_string)
+            })
+            .collect(),
+        None => vec![None],
+    }
+}
+
+/// ffmpeg parameters for the slate, `None` without HLS output.
+pub fn offline_args(config: &PlayoutConfig) -> Option<Vec<String>> {
+    let cmd = config.output.output_cmd.as_ref()?;
+    let playlist = cmd
+        .iter()
+        .rev()
+        .find(|c| c.ends_with(".m3u8") && !c.starts_with('-'))?;
+    let hls_time = param(cmd, "-hls_time").unwrap_or("6");
+    let settings = &config.output.offline;
+    let processing = &config.processing;
+    let fps = processing.fps;
+    let mut args = vec_strings!["-hide_banner", "-nostats", "-v", "level+error"];
+
+    if settings.image.is_empty() {
+        args.append(&mut vec_strings![
+            "-f",
+            "lavfi",
+            "-i",
+            format!(
+                "color=c=black:s={}x{}:r={fps}",
+                processing.width, processing.height
+            )
+        ]);
+    } else {
+        args.append(&mut vec_strings![
+            "-re",
+            "-loop",
+            "1",
+            "-framerate",
+            fps,
+            "-i",
+            source_path(config, &settings.image)
+        ]);
+    }
+
+    if settings.audio.is_empty() {
+        args.append(&mut vec_strings![
+            "-f",
+            "lavfi",
+            "-i",
+            "anullsrc=r=48000:cl=stereo"
+        ]);
+    } else {
+        args.append(&mut vec_strings![
+            "-re",
+            "-stream_loop",
+            "-1",
+            "-i",
+            source_path(config, &settings.audio)
+        ]);
+    }
+
+    let mut filter = format!(
+        "scale={0}:{1}:force_original_aspect_ratio=decrease,pad={0}:{1}:(ow-iw)/2:(oh-ih)/2,setsar=1,format=yuv420p",
+        processing.width, processing.height
+    );
+
+    if !settings.text.is_empty() {
+        let font = if Path::new(&config.text.font_path).is_file() {
+            format!(":fontfile='{}'", config.text.font_path)
+        } else {
+            String::new()
+        };
+
+        filter.push_str(&format!(
+            ",drawtext=textfile='{}':expansion=none{font}:fontsize=h/14:fontcolor=white:x=(w-tw)/2:y=(h-th)/2",
+            offline_text_file(config.general.channel_id).display()
+        ));
+    }
+
+    let variants = variants(cmd);
+
+    for _ in &variants {
+        args.append(&mut vec_strings!["-map", "0:v", "-map", "1:a"]);
+    }
+
+    let gop = (fps * hls_time.parse::<f64>().unwrap_or(6.0))
+        .round()
+        .max(1.0);
+
+    args.append(&mut vec_strings![
+        "-vf",
+        filter,
+        "-c:v",
+        "libx264",
+        "-preset",
+        "veryfast",
+        "-tune",
+        "stillimage",
+        "-r",
+        fps,
+        "-g",
+        gop,
+        "-keyint_min",
+        gop,
+        "-sc_threshold",
+        "0",
+        "-b:v",
+        "500k",
+        "-c:a",
+        "aac",
+        "-b:a",
+        "64k",
+        "-ar",
+        "48000",
+        "-ac",
+        "2",
+        "-f",
+        "hls",
+        "-hls_time",
+        hls_time,
+        "-hls_list_size",
+        "6",
+        "-hls_flags",
+        "append_list+delete_segments+omit_endlist+discont_start"
+    ]);
+
+    if variants.len() > 1 || variants[0].is_some() {
+        let map = variants
+            .iter()
+            .enumerate()
+            .map(|(i, name)| match name {
+                Some(name) => format!("v:{i},a:{i},name:{name}"),
+                None => format!("v:{i},a:{i}"),
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        args.append(&mut vec_strings!["-var_stream_map", map]);
+    }
+
+    if let Some(master) = param(cmd, "-master_pl_name") {
+        args.append(&mut vec_strings!["-master_pl_name", master]);
+    }
+
+    if let Some(segment) = param(cmd, "-hls_segment_filename") {
+        args.append(&mut vec_strings!["-hls_segment_filename", segment]);
+    }
+
+    args.push(playlist.clone());
+
+    Some(args)
+}
+
+/// Start the slate, when it is enabled and the channel runs in HLS mode.
+pub fn start_offline(manager: &ChannelManager) {
+    let config = manager.config.lock().unwrap().clone();
+    let id = config.general.channel_id;
+
+    if !config.output.offline.enable || config.output.mode != HLS || is_shutting_down() {
+        return;
+    }
+
+    stop_offline(manager);
+
+    let Some(args) = offline_args(&config) else {
+        warn!(target: Target::file_mail(), channel = id; "No HLS playlist in the output parameters, offline slate is not started");
+        return;
+    };
+
+    if let Err(e) = fs::write(offline_text_file(id), &config.output.offline.text) {
+        error!(target: Target::file_mail(), channel = id; "Unable to write offline text: {e}");
+    }
+
+    let mut child = match Command::new("ffmpeg")
+        .args(&args)
+        .limits(&config.channel.limits)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "Start offline slate failed: {e}");
+            return;
+        }
+    };
+
+    if let Some(stderr) = child.stderr.take() {
+        thread::spawn(move || {
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                error!(target: Target::file_mail(), channel = id; "<bright black>[Offline]</> {line}");
+            }
+        });
+    }
+
+    info!(target: Target::file_mail(), channel = id; "Channel stopped, play offline slate");
+
+    *manager.offline.lock().unwrap() = Some(child);
+}
+
+/// Stop the slate, before the channel writes to the HLS output again.
+pub fn stop_offline(manager: &ChannelManager) {
+    if let Some(mut child) = manager.offline.lock().unwrap().take() {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::config::PlayoutConfig;
+    use crate::vec_strings;
+
+    #[test]
+    fn offline_slate() {
+        let mut config = PlayoutConfig::default();
+
+        assert_eq!(offline_args(&config), None);
+
+        config.output.offline.image = "/opt/slate.png".to_string();
+        config.output.output_cmd = Some(vec_strings![
+            "-c:v",
+            "libx264",
+            "-f",
+            "hls",
+            "-hls_time",
+            "4",
+            "-hls_segment_filename",
+            "/live/stream_%v-%d.ts",
+            "-master_pl_name",
+            "master.m3u8",
+            "-var_stream_map",
+            "v:0,a:0,name:720p v:1,a:1,name:288p",
+            "/live/stream_%v.m3u8"
+        ]);
+
+        let args = offline_args(&config).unwrap();
+        let value = |key: &str| {
+            args.iter()
+                .position(|a| a == key)
+                .map(|i| args[i + 1].clone())
+        };
+
+        assert_eq!(args.last().unwrap(), "/live/stream_%v.m3u8");
+        assert_eq!(value("-loop").unwrap(), "1");
+        assert_eq!(value("-hls_time").unwrap(), "4");
+        assert_eq!(
+            value("-hls_segment_filename").unwrap(),
+            "/live/stream_%v-%d.ts"
+        );
+        assert_eq!(value("-master_pl_name").unwrap(), "master.m3u8");
+        assert_eq!(
+            value("-var_stream_map").unwrap(),
+            "v:0,a:0,name:720p v:1,a:1,name:288p"
+        );
+        assert_eq!(args.iter().filter(|a| *a == "0:v").count(), 2);
+        assert!(value("-vf").unwrap().contains(",drawtext=textfile="));
+        assert!(args.contains(&"anullsrc=r=48000:cl=stereo".to_string()));
+    }
+}
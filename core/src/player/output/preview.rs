@@ -0,0 +1,345 @@
+/*
+Previews of the live ingest and the program output.
+
+With the ingest preview enabled, the ingest server gets a second output: a low latency HLS stream
+from the incoming feed, before the processing filters. It is written to `preview/ingest.m3u8`
+in the public folder and served at `/{id}/preview/ingest.m3u8`. So master control can check
+the remote feed, also when it is not on air, for example with the playlist switch.
+
+The program preview is an own small encoder, which gets the same stream as the channel encoder
+and writes it to `preview/program.m3u8`. With a gateway it is also pushed to a WebRTC gateway.
+Like all program taps, the preview never holds up the channel output.
+Changed settings apply with the next clip.
+
+The files are removed, when the publisher disconnects or the preview stops.
+
+*/
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use log::*;
+
+use crate::player::{
+    controller::ChannelManager,
+    output::tap::ProgramTap,
+    utils::stream_stats::{progress_args, record_progress, start_stream, stop_stream},
+};
+use crate::utils::{
+    config::{IngestPreview, PlayoutConfig, ProgramPreview},
+    logging::Target,
+};
+use crate::vec_strings;
+
+/// Playlist from the preview, in the preview folder.
+pub const PREVIEW_PLAYLIST: &str = "ingest.m3u8";
+
+/// Playlist from the program preview, in the preview folder.
+pub const PROGRAM_PLAYLIST: &str = "program.m3u8";
+
+/// Name of the program preview in the stream stats.
+const PROGRAM_STATS: &str = "preview";
+
+/// Folder from the preview, in the public folder of the channel.
+pub fn preview_dir(public: &Path) -> PathBuf {
+    public.join("preview")
+}
+
+fn encoder_args(height: i64, bitrate: i64) -> Vec<String> {
+    let height = height.clamp(144, 1080);
+    let bitrate = bitrate.clamp(100, 8000);
+
+    vec_strings![
+        "-map",
+        "0:v:0?",
+        "-map",
+        "0:a:0?",
+        "-vf",
+        format!("scale=-2:{height}"),
+        "-c:v",
+        "libx264",
+        "-preset",
+        "ultrafast",
+        "-tune",
+        "zerolatency",
+        "-pix_fmt",
+        "yuv420p",
+        "-b:v",
+        format!("{bitrate}k"),
+        "-maxrate",
+        format!("{bitrate}k"),
+        "-bufsize",
+        format!("{}k", bitrate * 2),
+        "-force_key_frames",
+        "expr:gte(t,n_forced*1)",
+        "-c:a",
+        "aac",
+        "-b:a",
+        "96k",
+        "-ac",
+        "2"
+    ]
+}
+
+fn hls_options(dir: &Path, name: &str) -> [(&'static str, String); 4] {
+    [
+        ("hls_time", "1".to_string()),
+        ("hls_list_size", "4".to_string()),
+        (
+            "hls_flags",
+            "delete_segments+omit_endlist+independent_segments".to_string(),
+        ),
+        (
+            "hls_segment_filename",
+            dir.join(format!("{name}-%d.ts"))
+                .to_string_lossy()
+                .to_string(),
+        ),
+    ]
+}
+
+/// Output parameters for the preview, video and audio from the first tracks of the ingest.
+pub fn preview_args(preview: &IngestPreview, dir: &Path) -> Vec<String> {
+    let mut args = encoder_args(preview.height, preview.bitrate);
+
+    args.append(&mut vec_strings!["-f", "hls"]);
+
+    for (key, value) in hls_options(dir, "ingest") {
+        args.append(&mut vec_strings![format!("-{key}"), value]);
+    }
+
+    args.push(dir.join(PREVIEW_PLAYLIST).to_string_lossy().to_string());
+
+    args
+}
+
+/// Muxer for the gateway url.
+fn gateway_format(url: &str) -> &'static str {
+    if url.starts_with("rtsp") {
+        "rtsp"
+    } else {
+        "flv"
+    }
+}
+
+/// ffmpeg parameters for the program preview, it reads the program stream from stdin.
+pub fn program_preview_args(preview: &ProgramPreview, dir: &Path) -> Vec<String> {
+    let mut args = vec_strings!["-hide_banner", "-nostats", "-v", "level+error"];
+    let playlist = dir.join(PROGRAM_PLAYLIST).to_string_lossy().to_string();
+    let gateway = preview.gateway.trim();
+
+    args.append(&mut progress_args());
+    args.append(&mut vec_strings!["-f", "mpegts", "-i", "pipe:0"]);
+    args.append(&mut encoder_args(preview.height, preview.bitrate));
+
+    if gateway.is_empty() {
+        args.append(&mut vec_strings!["-f", "hls"]);
+
+        for (key, value) in hls_options(dir, "program") {
+            args.append(&mut vec_strings![format!("-{key}"), value]);
+        }
+
+        args.push(playlist);
+    } else {
+        // the gateway can fail, without stopping the HLS preview
+        let hls = hls_options(dir, "program")
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect::<Vec<_>>()
+            .join(":");
+
+        args.append(&mut vec_strings![
+            "-flags",
+            "+global_header",
+            "-f",
+            "tee",
+            format!(
+                "[f=hls:{hls}]{playlist}|[f={}:onfail=ignore]{gateway}",
+                gateway_format(gateway)
+            )
+        ]);
+    }
+
+    args
+}
+
+fn clear_files(dir: &Path, prefix: &str) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if name.starts_with(prefix) && (name.ends_with(".ts") || name.ends_with(".m3u8")) {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// Remove playlist and segments from the preview, an old playlist should not look like a live feed.
+pub fn clear_preview(dir: &Path) {
+    clear_files(dir, "ingest");
+}
+
+/// Remove playlist and segments from the program preview.
+pub fn clear_program_preview(dir: &Path) {
+    clear_files(dir, "program");
+}
+
+/// Encoder of the program preview.
+pub struct ProgramOutput {
+    manager: ChannelManager,
+    settings: ProgramPreview,
+    dir: PathBuf,
+    tap: Option<ProgramTap>,
+}
+
+impl ProgramOutput {
+    pub fn new(manager: ChannelManager, config: &PlayoutConfig) -> Self {
+        let mut output = Self {
+            manager,
+            settings: config.output.preview.clone(),
+            dir: preview_dir(&config.channel.public),
+            tap: None,
+        };
+
+        if output.settings.enable {
+            output.start(config.general.channel_id);
+        }
+
+        output
+    }
+
+    fn start(&mut self, id: i32) {
+        if let Err(e) = fs::create_dir_all(&self.dir) {
+            error!(target: Target::file_mail(), channel = id; "Program preview folder: {e}");
+            return;
+        }
+
+        let manager = self.manager.clone();
+        let cmd = program_preview_args(&self.settings, &self.dir);
+        let limits = manager.config.lock().unwrap().channel.limits.clone();
+
+        start_stream(&manager, PROGRAM_STATS);
+
+        let tap = ProgramTap::spawn(id, "preview", cmd, &limits, move |line| {
+            if !record_progress(&manager, PROGRAM_STATS, line) {
+                warn!(target: Target::file(), channel = id;
+                    "<bright black>[Preview]</> {}",
+                    line.replace("[error] ", "").replace("[fatal] ", "")
+                );
+            }
+        });
+
+        match tap {
+            Ok(tap) => {
+                info!(target: Target::file_mail(), channel = id; "Start program preview");
+
+                self.tap = Some(tap);
+            }
+            Err(e) => {
+                error!(target: Target::file_mail(), channel = id; "Program preview failed: {e}");
+            }
+        }
+    }
+
+    /// Start the preview again with changed settings, call it only between two clips.
+    pub fn update(&mut self, config: &PlayoutConfig) {
+        if config.output.preview == self.settings {
+            return;
+        }
+
+        self.stop();
+        self.settings = config.output.preview.clone();
+
+        if self.settings.enable {
+            self.start(config.general.channel_id);
+        }
+    }
+
+    /// Give the package to the preview encoder, without waiting for it.
+    pub fn write(&mut self, buf: &[u8]) {
+        if self.tap.as_mut().is_some_and(|tap| !tap.write(buf)) {
+            self.stop();
+        }
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(mut tap) = self.tap.take() {
+            tap.stop();
+            stop_stream(&self.manager, PROGRAM_STATS);
+        }
+
+        clear_program_preview(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::utils::config::IngestPreview;
+    use crate::utils::config::ProgramPreview;
+
+    #[test]
+    fn ingest_preview_output() {
+        let dir = Path::new("/tmp/ffplayout-preview");
+        let args = preview_args(
+            &IngestPreview {
+                enable: true,
+                height: 4000,
+                bitrate: 600,
+            },
+            dir,
+        );
+
+        assert!(args.contains(&"scale=-2:1080".to_string()));
+        assert!(args.contains(&"600k".to_string()));
+        assert_eq!(args.last().unwrap(), "/tmp/ffplayout-preview/ingest.m3u8");
+        assert_eq!(
+            preview_dir(Path::new("/var/lib/public")),
+            Path::new("/var/lib/public/preview")
+        );
+
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join("ingest.m3u8"), "#EXTM3U").unwrap();
+        std::fs::write(dir.join("ingest-1.ts"), "").unwrap();
+        std::fs::write(dir.join("other.txt"), "").unwrap();
+
+        clear_preview(dir);
+
+        assert!(!dir.join("ingest.m3u8").exists());
+        assert!(!dir.join("ingest-1.ts").exists());
+        assert!(dir.join("other.txt").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn program_preview_output() {
+        let dir = Path::new("/tmp/ffplayout-program");
+        let mut preview = ProgramPreview::default();
+        let args = program_preview_args(&preview, dir);
+
+        assert!(args.windows(2).any(|a| a == ["-i", "pipe:0"]));
+        assert!(args.contains(&"scale=-2:360".to_string()));
+        assert!(args.contains(&"-progress".to_string()));
+        assert_eq!(args.last().unwrap(), "/tmp/ffplayout-program/program.m3u8");
+
+        preview.gateway = "rtsp://127.0.0.1:8554/preview".to_string();
+        let args = program_preview_args(&preview, dir);
+
+        assert!(args.windows(2).any(|a| a == ["-f", "tee"]));
+        assert!(args
+            .last()
+            .unwrap()
+            .starts_with("[f=hls:hls_time=1:hls_list_size=4:"));
+        assert!(args.last().unwrap().ends_with(
+        "/tmp/ffplayout-program/program.m3u8|[f=rtsp:onfail=ignore]rtsp://127.0.0.1:8554/preview"
+    ));
+    }
+}
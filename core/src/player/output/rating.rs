@@ -0,0 +1,118 @@
+/*
+Parental rating signaling.
+
+The rating from the running item is mapped to the rating system of the channel
+and written by the cue tagger to the HLS playlists:
+
+- media playlists get a `#EXT-X-DATERANGE` with `X-RATING`, before the first segment from the item
+- the master playlist gets the current rating as `#EXT-X-SESSION-DATA`
+
+MPEG-TS outputs can not carry it, ffmpeg writes no EIT. For them the rating is in the XMLTV export,
+which EIT generators can read.
+
+*/
+
+use chrono::{DateTime, Local};
+use log::*;
+
+use crate::player::{controller::ChannelManager, utils::Media};
+use crate::utils::logging::Target;
+
+pub const SESSION_DATA_ID: &str = "com.ffplayout.rating";
+
+/// Value for a quoted string attribute, which can not have quotes and line breaks.
+pub fn quote(value: &str) -> String {
+    value.replace(['"', '\n', '\r'], "")
+}
+
+/// Set the rating from the item, which starts now.
+pub fn node_rating(manager: &ChannelManager, node: &Media) {
+    let config = manager.config.lock().unwrap();
+
+    if !config.output.rating.enable {
+        return;
+    }
+
+    let id = config.general.channel_id;
+    let rating = config.output.rating.map(node.rating.as_deref());
+    drop(config);
+
+    if manager.cues.lock().unwrap().set_rating(rating.clone()) {
+        info!(target: Target::file_mail(), channel = id; "Parental rating: <yellow>{}</>", rating.as_deref().unwrap_or("none"));
+    }
+}
+
+/// Media playlist tag for a rating change, an empty rating ends the last one.
+pub fn rating_tags(system: &str, rating: Option<&str>, time: &DateTime<Local>) -> Vec<String> {
+    vec![format!(
+        "#EXT-X-DATERANGE:ID=\"rating-{}\",START-DATE=\"{}\",X-RATING-SYSTEM=\"{}\",X-RATING=\"{}\"",
+        time.timestamp_millis(),
+        time.format("%Y-%m-%dT%H:%M:%S%.3f%:z"),
+        quote(system),
+        quote(rating.unwrap_or_default())
+    )]
+}
+
+/// Replace the rating session data in the master playlist.
+pub fn session_data(content: &str, system: &str, rating: Option<&str>) -> String {
+    let prefix = format!("#EXT-X-SESSION-DATA:DATA-ID=\"{SESSION_DATA_ID}");
+    let mut lines: Vec<String> = content
+        .lines()
+        .filter(|l| !l.starts_with(&prefix))
+        .map(String::from)
+        .collect();
+
+    if let Some(rating) = rating {
+        // after #EXTM3U and #EXT-X-VERSION
+        let pos = lines
+            .iter()
+            .take_while(|l| l.starts_with("#EXTM3U") || l.starts_with("#EXT-X-VERSION"))
+            .count();
+
+        let mut data = vec![format!("{prefix}\",VALUE=\"{}\"", quote(rating))];
+
+        if !system.is_empty() {
+            data.push(format!("{prefix}-system\",VALUE=\"{}\"", quote(system)));
+        }
+
+        lines.splice(pos..pos, data);
+    }
+
+    let mut playlist = lines.join("\n");
+    playlist.push('\n');
+
+    playlist
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::utils::config::ParentalRating;
+
+    #[test]
+    fn parental_rating() {
+        let rating = ParentalRating {
+            enable: true,
+            system: "FSK".to_string(),
+            default: "0".to_string(),
+            mapping: HashMap::from([("PG-13".to_string(), "12".to_string())]),
+        };
+
+        assert_eq!(rating.map(Some("PG-13")), Some("12".to_string()));
+        assert_eq!(rating.map(Some("16")), Some("16".to_string()));
+        assert_eq!(rating.map(None), Some("0".to_string()));
+        assert_eq!(ParentalRating::default().map(Some(" ")), None);
+
+        let master = "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-STREAM-INF:BANDWIDTH=140800\nstream.m3u8\n";
+        let tagged = session_data(master, "FSK", Some("12"));
+
+        assert_eq!(
+        tagged,
+        format!("#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-SESSION-DATA:DATA-ID=\"{SESSION_DATA_ID}\",VALUE=\"12\"\n#EXT-X-SESSION-DATA:DATA-ID=\"{SESSION_DATA_ID}-system\",VALUE=\"FSK\"\n#EXT-X-STREAM-INF:BANDWIDTH=140800\nstream.m3u8\n")
+    );
+        assert_eq!(session_data(&tagged, "FSK", Some("12")), tagged);
+        assert_eq!(session_data(&tagged, "FSK", None), master);
+    }
+}
@@ -0,0 +1,417 @@
+/*
+Redundant encoder output.
+
+With `backup_param` a second encoder runs in parallel and gets the same decoded stream,
+for example to the backup ingest of a CDN or on a other GPU.
+Both encoders are checked on every write and every second:
+when the active encoder fails, the backup gets promoted and the failed encoder starts again as new backup.
+The decoder keeps running, so the playback position is not lost.
+
+When the output settings change, the encoders are restarted between two clips:
+the new encoders start first and the writer switches to them, only then the old encoders
+get the end of their input, write the buffered data and exit. So the output has no gap.
+
+*/
+
+use std::{
+    io::{self, BufReader, BufWriter, Write},
+    mem,
+    process::{Child, ChildStdin},
+    sync::{atomic::Ordering, Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use log::*;
+use serde::Serialize;
+
+use super::{desktop, null, stream};
+use crate::player::{
+    controller::{ChannelManager, ProcessUnit::*},
+    utils::{stderr_reader, stream_stats::start_stream},
+};
+use crate::utils::{
+    config::{OutputMode::*, PlayoutConfig},
+    errors::ProcessError,
+    logging::Target,
+    time_machine::{clock_sleep, time_now},
+};
+
+/// Time between restarts of a failed backup encoder.
+const RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// Time for an encoder to write the rest of its input, before it gets killed.
+const FINISH_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EncoderRole {
+    #[default]
+    Main,
+    Backup,
+}
+
+impl EncoderRole {
+    fn other(self) -> Self {
+        match self {
+            Self::Main => Self::Backup,
+            Self::Backup => Self::Main,
+        }
+    }
+
+    /// Name of the encoder in the stream stats.
+    fn stats_name(self) -> &'static str {
+        match self {
+            Self::Main => "output",
+            Self::Backup => "backup",
+        }
+    }
+}
+
+/// Encoder state for the channel status.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct EncoderStatus {
+    pub redundant: bool,
+    /// Encoder, which is the active output.
+    pub active: EncoderRole,
+    /// Backup encoder is running.
+    pub standby: bool,
+    pub promotions: u32,
+    pub last_promotion: Option<String>,
+}
+
+/// Start the encoder for the output mode, the backup uses its own output parameters.
+fn spawn_encoder(config: &PlayoutConfig, role: EncoderRole, log_format: &str) -> Child {
+    let mut config = config.clone();
+
+    if role == EncoderRole::Backup {
+        config.output.output_cmd = config.output.backup_cmd.clone();
+    }
+
+    match config.output.mode {
+        Desktop => desktop::output(&config, log_format),
+        Null => null::output(&config, log_format),
+        Stream | Dash | Decklink => stream::output(&config, log_format),
+        _ => panic!("Output mode doesn't exists!"),
+    }
+}
+
+struct EncoderPipe {
+    role: EncoderRole,
+    writer: BufWriter<ChildStdin>,
+}
+
+/// Writer to the active encoder, which promotes the backup when the active encoder fails.
+pub struct EncoderOutput {
+    manager: ChannelManager,
+    config: PlayoutConfig,
+    log_format: String,
+    active: EncoderPipe,
+    standby: Option<EncoderPipe>,
+    retry: Option<Instant>,
+    last_check: Instant,
+    threads: Vec<JoinHandle<Result<(), ProcessError>>>,
+}
+
+impl EncoderOutput {
+    pub fn new(manager: ChannelManager, config: &PlayoutConfig, log_format: &str) -> Self {
+        let redundant = config.output.backup_cmd.is_some();
+
+        *manager.encoder_status.lock().unwrap() = EncoderStatus {
+            redundant,
+            ..Default::default()
+        };
+
+        let mut threads = vec![];
+        let active = start(
+            &manager,
+            config,
+            EncoderRole::Main,
+            log_format,
+            &manager.encoder,
+            &mut threads,
+        );
+        let mut output = Self {
+            manager,
+            config: config.clone(),
+            log_format: log_format.to_string(),
+            active,
+            standby: None,
+            retry: None,
+            last_check: Instant::now(),
+            threads,
+        };
+
+        if redundant {
+            output.start_standby(EncoderRole::Backup);
+        }
+
+        output
+    }
+
+    fn channel_id(&self) -> i32 {
+        self.config.general.channel_id
+    }
+
+    fn start_standby(&mut self, role: EncoderRole) {
+        let pipe = start(
+            &self.manager,
+            &self.config,
+            role,
+            &self.log_format,
+            &self.manager.backup_encoder,
+            &mut self.threads,
+        );
+
+        self.standby = Some(pipe);
+        self.retry = None;
+        self.manager.encoder_status.lock().unwrap().standby = true;
+    }
+
+    fn stop_standby(&mut self) {
+        self.standby = None;
+        self.retry = Some(Instant::now() + RETRY_DELAY);
+        self.manager.encoder_status.lock().unwrap().standby = false;
+
+        if let Some(proc) = self.manager.backup_encoder.lock().unwrap().as_mut() {
+            let _ = proc.kill();
+            let _ = proc.wait();
+        }
+    }
+
+    /// Make the backup the active encoder, the failed encoder becomes the new backup.
+    fn promote(&mut self, reason: &str) -> bool {
+        if self.manager.is_terminated.load(Ordering::SeqCst) {
+            return false;
+        }
+
+        let Some(standby) = self.standby.take() else {
+            return false;
+        };
+
+        let failed = mem::replace(&mut self.active, standby);
+
+        mem::swap(
+            &mut *self.manager.encoder.lock().unwrap(),
+            &mut *self.manager.backup_encoder.lock().unwrap(),
+        );
+
+        error!(target: Target::file_mail(), channel = self.channel_id();
+            "{:?} encoder failed ({reason}), promote <yellow>{:?}</> encoder",
+            failed.role, self.active.role
+        );
+
+        {
+            let mut status = self.manager.encoder_status.lock().unwrap();
+            status.active = self.active.role;
+            status.promotions += 1;
+            status.last_promotion = Some(time_now().format("%Y-%m-%d %H:%M:%S%.3f").to_string());
+        }
+
+        drop(failed);
+        self.stop_standby();
+        self.retry = Some(Instant::now());
+
+        true
+    }
+
+    /// Check if the encoder processes are still running.
+    fn health_check(&mut self) {
+        if self.last_check.elapsed() < Duration::from_secs(1) {
+            return;
+        }
+
+        self.last_check = Instant::now();
+
+        let active_exited = exited(&self.manager.encoder);
+
+        if active_exited && self.promote("process exited") {
+            return;
+        }
+
+        if self.standby.is_some() && exited(&self.manager.backup_encoder) {
+            warn!(target: Target::file_mail(), channel = self.channel_id();
+                "Backup encoder exited, restart in {} seconds", RETRY_DELAY.as_secs()
+            );
+
+            self.stop_standby();
+        }
+
+        if self.standby.is_none()
+            && self.retry.is_some_and(|r| r <= Instant::now())
+            && !self.manager.is_terminated.load(Ordering::SeqCst)
+        {
+            let role = self.active.role.other();
+
+            info!(target: Target::file_mail(), channel = self.channel_id(); "Start {role:?} encoder as backup");
+
+            self.start_standby(role);
+        }
+    }
+
+    /// Restart the encoders with new settings, call it only between two clips.
+    pub fn reload(self, config: &PlayoutConfig) -> Self {
+        let Self {
+            manager,
+            log_format,
+            mut active,
+            standby,
+            threads,
+            ..
+        } = self;
+        let status = manager.encoder_status.lock().unwrap().clone();
+
+        let _ = active.writer.flush();
+
+        // the old encoders keep running, until the new ones took over
+        let old_main = manager.encoder.lock().unwrap().take();
+        let old_backup = manager.backup_encoder.lock().unwrap().take();
+
+        let mut output = Self::new(manager, config, &log_format);
+
+        // the writer is switched, close the old inputs, so the old encoders can finish
+        drop(standby);
+        drop(active);
+
+        finish(old_main);
+        finish(old_backup);
+
+        output.threads.extend(threads);
+
+        {
+            let mut new_status = output.manager.encoder_status.lock().unwrap();
+            new_status.promotions = status.promotions;
+            new_status.last_promotion = status.last_promotion;
+        }
+
+        output
+    }
+
+    /// Wait for the encoder log threads.
+    pub fn join(self) {
+        let id = self.channel_id();
+        drop(self.standby);
+        drop(self.active);
+
+        for thread in self.threads {
+            if let Err(e) = thread.join() {
+                error!(target: Target::file_mail(), channel = id; "{e:?}");
+            }
+        }
+    }
+}
+
+impl Write for EncoderOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.health_check();
+
+        if let Err(e) = self.active.writer.write_all(buf) {
+            if !self.promote(&e.kind().to_string()) {
+                return Err(e);
+            }
+
+            self.active.writer.write_all(buf)?;
+        }
+
+        if let Some(standby) = self.standby.as_mut() {
+            if let Err(e) = standby.writer.write_all(buf) {
+                warn!(target: Target::file_mail(), channel = self.channel_id();
+                    "Backup encoder write error: {}, restart in {} seconds", e.kind(), RETRY_DELAY.as_secs()
+                );
+
+                self.stop_standby();
+            }
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.active.writer.flush()
+    }
+}
+
+fn exited(child: &Arc<Mutex<Option<Child>>>) -> bool {
+    child
+        .lock()
+        .unwrap()
+        .as_mut()
+        .is_some_and(|p| !matches!(p.try_wait(), Ok(None)))
+}
+
+/// Wait until the encoder exits after the end of its input, or kill it after a timeout.
+fn finish(proc: Option<Child>) {
+    let Some(mut proc) = proc else {
+        return;
+    };
+    let deadline = Instant::now() + FINISH_TIMEOUT;
+
+    while matches!(proc.try_wait(), Ok(None)) {
+        if Instant::now() >= deadline {
+            let _ = proc.kill();
+            break;
+        }
+
+        clock_sleep(Duration::from_millis(50));
+    }
+
+    let _ = proc.wait();
+}
+
+/// Spawn one encoder, with its log reader thread.
+fn start(
+    manager: &ChannelManager,
+    config: &PlayoutConfig,
+    role: EncoderRole,
+    log_format: &str,
+    slot: &Arc<Mutex<Option<Child>>>,
+    threads: &mut Vec<JoinHandle<Result<(), ProcessError>>>,
+) -> EncoderPipe {
+    let mut proc = spawn_encoder(config, role, log_format);
+    let writer = BufWriter::new(proc.stdin.take().unwrap());
+    let enc_err = BufReader::new(proc.stderr.take().unwrap());
+    let ignore = config.logging.ignore_lines.clone();
+    let manager = manager.clone();
+
+    // only the stream outputs write their progress
+    let stats = matches!(config.output.mode, Stream | Dash | Decklink).then(|| {
+        start_stream(&manager, role.stats_name());
+        role.stats_name().to_string()
+    });
+
+    *slot.lock().unwrap() = Some(proc);
+
+    threads.push(thread::spawn(move || {
+        stderr_reader(enc_err, ignore, Encoder, stats, manager)
+    }));
+
+    EncoderPipe { role, writer }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::controller::ChannelManager;
+    use crate::player::utils::get_data_map;
+
+    #[test]
+    fn encoder_status() {
+        let manager = ChannelManager::default();
+
+        assert_eq!(get_data_map(&manager)["encoder"]["redundant"], false);
+        assert_eq!(get_data_map(&manager)["encoder"]["active"], "main");
+
+        {
+            let mut status = manager.encoder_status.lock().unwrap();
+            status.redundant = true;
+            status.active = EncoderRole::Backup;
+            status.promotions += 1;
+        }
+
+        let data = get_data_map(&manager);
+
+        assert_eq!(data["encoder"]["active"], "backup");
+        assert_eq!(data["encoder"]["promotions"], 1);
+        assert_eq!(data["encoder"]["standby"], false);
+    }
+}
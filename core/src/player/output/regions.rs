@@ -0,0 +1,499 @@
+/*
+Regional outputs, with opt-out of break windows.
+
+Every enabled region of a channel gets its own encoder, with its own output parameters.
+The region encoders get the same decoded stream as the channel encoder, so they share the
+main schedule. A break window is a break marker in the playlist, or the spots which filled it.
+
+When the sub-playlist of a region has items for the break of the window, the region plays them
+instead of the window, the last item is cut at the end of the window:
+
+    { "in": 0, "out": 30, "duration": 30, "source": "/regional/north/promo.mp4", "break": "prime" }
+
+The sub-playlists are stored in the playlist folder, under `regions/{region_id}/YYYY/MM/YYYY-MM-DD.json`.
+
+Regional outputs work with the output modes, which run one encoder: stream, desktop, null and decklink.
+
+*/
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufReader, BufWriter, Read, Write},
+    path::PathBuf,
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, LazyLock, Mutex,
+    },
+    thread,
+};
+
+use log::*;
+use serde::Serialize;
+use shlex::split;
+use sqlx::{Pool, Sqlite};
+
+use crate::db::{handles, models::Region};
+use crate::player::{
+    controller::{ChannelManager, ProcessUnit::*},
+    utils::{
+        json_reader,
+        json_serializer::set_defaults,
+        json_writer, loop_image, seek_and_length, stderr_reader,
+        stream_stats::{progress_args, start_stream},
+        JsonPlaylist, Media,
+    },
+};
+use crate::utils::{
+    config::{PlayoutConfig, IMAGE_FORMAT},
+    errors::ServiceError,
+    logging::Target,
+    process_limits::LimitProcess,
+};
+use crate::vec_strings;
+
+/// Folder of the sub-playlists, in the playlist folder of the channel.
+pub const REGIONS_DIR: &str = "regions";
+
+static STATUS: LazyLock<Mutex<HashMap<i32, RegionStatus>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// State of a regional output.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct RegionStatus {
+    pub running: bool,
+    /// Break window, which plays regional content right now.
+    pub window: Option<String>,
+    /// Break windows with regional content, since the channel started.
+    pub replaced: u64,
+    pub error: Option<String>,
+}
+
+pub fn region_status(id: i32) -> RegionStatus {
+    STATUS.lock().unwrap().get(&id).cloned().unwrap_or_default()
+}
+
+fn update_status(id: i32, f: impl FnOnce(&mut RegionStatus)) {
+    f(STATUS.lock().unwrap().entry(id).or_default());
+}
+
+/// Load the regions of the channel from the database, call it again after a region changed.
+pub async fn load_regions(conn: &Pool<Sqlite>, manager: &ChannelManager) {
+    let id = manager.channel.lock().unwrap().id;
+
+    match handles::select_regions(conn, id).await {
+        Ok(regions) => {
+            *manager.regions.lock().unwrap() = regions;
+            manager.reload_regions.store(true, Ordering::SeqCst);
+        }
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "Unable to read regions: {e}");
+        }
+    }
+}
+
+/// Path of the sub-playlist from a region.
+pub fn region_playlist_path(config: &PlayoutConfig, region: i32, date: &str) -> PathBuf {
+    let d: Vec<&str> = date.split('-').collect();
+
+    config
+        .channel
+        .playlists
+        .join(REGIONS_DIR)
+        .join(region.to_string())
+        .join(d[0])
+        .join(d.get(1).unwrap_or(&""))
+        .join(date)
+        .with_extension("json")
+}
+
+pub fn read_region_playlist(
+    config: &PlayoutConfig,
+    region: i32,
+    date: &str,
+) -> Result<JsonPlaylist, ServiceError> {
+    json_reader(&region_playlist_path(config, region, date))
+        .map_err(|e| ServiceError::NoContent(e.to_string()))
+}
+
+pub fn write_region_playlist(
+    config: &PlayoutConfig,
+    region: i32,
+    playlist: JsonPlaylist,
+) -> Result<(), ServiceError> {
+    if playlist.program.iter().any(|m| m.break_name.is_none()) {
+        return Err(ServiceError::BadRequest(
+            "Every item needs the break, which it replaces".into(),
+        ));
+    }
+
+    let path = region_playlist_path(config, region, &playlist.date);
+
+    if let Some(p) = path.parent() {
+        fs::create_dir_all(p)?;
+    }
+
+    json_writer(&path, playlist).map_err(|e| ServiceError::Conflict(e.to_string()))
+}
+
+/// Break window of a clip: the break marker itself, or the break which the spot fills.
+pub fn break_window(node: &Media) -> Option<String> {
+    node.window.clone().or_else(|| node.break_name.clone())
+}
+
+/// Length of the break window, which starts at `index`: all following clips in the same window.
+pub fn window_length(list: &[Media], index: usize) -> f64 {
+    let Some(window) = list.get(index).and_then(break_window) else {
+        return 0.0;
+    };
+
+    list.iter()
+        .skip(index)
+        .take_while(|m| break_window(m).as_ref() == Some(&window))
+        .map(|m| m.out - m.seek)
+        .sum()
+}
+
+/// Items of the sub-playlist for the break window, the last one is cut at the end of the window.
+pub fn window_items(playlist: &JsonPlaylist, window: &str, length: f64) -> Vec<Media> {
+    let mut items = vec![];
+    let mut rest = length;
+
+    for item in playlist
+        .program
+        .iter()
+        .filter(|m| m.break_name.as_deref() == Some(window))
+    {
+        if rest < 1.0 {
+            break;
+        }
+
+        let mut item = item.clone();
+
+        if item.out - item.seek > rest {
+            item.out = item.seek + rest;
+        }
+
+        rest -= item.out - item.seek;
+        item.break_name = None;
+        items.push(item);
+    }
+
+    items
+}
+
+/// Encoder of one region.
+struct RegionOutput {
+    region: Region,
+    child: Child,
+    writer: Arc<Mutex<Option<BufWriter<ChildStdin>>>>,
+    /// Regional content plays, the main stream is not written.
+    in_window: Arc<AtomicBool>,
+}
+
+/// Decoder command for regional content.
+fn decoder_cmd(config: &PlayoutConfig, manager: &ChannelManager, node: &mut Media) -> Vec<String> {
+    let mut cmd = vec_strings!["-hide_banner", "-nostats", "-v", "level+error"];
+
+    if let Err(e) = node.add_probe(false) {
+        trace!("{e:?}");
+    }
+
+    let is_image = node
+        .source
+        .rsplit_once('.')
+        .is_some_and(|(_, e)| IMAGE_FORMAT.contains(&e.to_lowercase().as_str()));
+
+    if is_image {
+        cmd.append(&mut loop_image(config, node));
+    } else {
+        cmd.append(&mut seek_and_length(config, node));
+    }
+
+    node.add_filter(config, &manager.filter_chain);
+
+    if let Some(mut filter) = node.filter.clone() {
+        cmd.append(&mut filter.cmd());
+        cmd.append(&mut filter.map());
+    }
+
+    if let Some(mut processing) = config.processing.cmd.clone() {
+        cmd.append(&mut processing);
+    }
+
+    cmd
+}
+
+/// Play the regional content of a break window into the region encoder.
+fn play_window(
+    manager: ChannelManager,
+    config: PlayoutConfig,
+    region: i32,
+    items: Vec<Media>,
+    writer: Arc<Mutex<Option<BufWriter<ChildStdin>>>>,
+    in_window: Arc<AtomicBool>,
+) {
+    let id = config.general.channel_id;
+    let mut buffer = [0; 65088];
+
+    for mut node in items {
+        if manager.is_terminated.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let cmd = decoder_cmd(&config, &manager, &mut node);
+
+        debug!(target: Target::file_mail(), channel = id;
+            "Region <yellow>{region}</> decoder CMD: <bright-blue>\"ffmpeg {}\"</>",
+            cmd.join(" ")
+        );
+
+        let mut proc = match Command::new("ffmpeg")
+            .args(cmd)
+            .limits(&config.channel.limits)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(proc) => proc,
+            Err(e) => {
+                error!(target: Target::file_mail(), channel = id; "Region <yellow>{region}</> decoder failed: {e}");
+                update_status(region, |s| s.error = Some(e.to_string()));
+                break;
+            }
+        };
+        let mut reader = BufReader::new(proc.stdout.take().unwrap());
+
+        loop {
+            let length = match reader.read(&mut buffer[..]) {
+                Ok(length) if length > 0 => length,
+                _ => break,
+            };
+            let mut writer = writer.lock().unwrap();
+
+            if let Some(Err(e)) = writer.as_mut().map(|w| w.write_all(&buffer[..length])) {
+                error!(target: Target::file_mail(), channel = id; "Region <yellow>{region}</> encoder write error: {}", e.kind());
+
+                *writer = None;
+                update_status(region, |s| s.error = Some(e.to_string()));
+            }
+
+            if writer.is_none() {
+                break;
+            }
+        }
+
+        let _ = proc.kill();
+        let _ = proc.wait();
+    }
+
+    update_status(region, |s| s.window = None);
+    in_window.store(false, Ordering::SeqCst);
+}
+
+fn start_encoder(
+    manager: &ChannelManager,
+    region: Region,
+    log_format: &str,
+) -> Option<RegionOutput> {
+    let config = manager.config.lock().unwrap().clone();
+    let id = config.general.channel_id;
+    let mut cmd = vec_strings!["-hide_banner", "-nostats", "-v", log_format];
+    cmd.append(&mut progress_args());
+    cmd.append(&mut vec_strings!["-re", "-i", "pipe:0"]);
+
+    let Some(mut output) = split(&region.output_param).filter(|o| !o.is_empty()) else {
+        error!(target: Target::file_mail(), channel = id; "Region <b><magenta>{}</></b> has invalid output parameters", region.name);
+        update_status(region.id, |s| {
+            s.error = Some("Invalid output parameters".to_string());
+        });
+
+        return None;
+    };
+
+    cmd.append(&mut output);
+
+    debug!(target: Target::file_mail(), channel = id;
+        "Region <b><magenta>{}</></b> encoder CMD: <bright-blue>\"ffmpeg {}\"</>",
+        region.name,
+        cmd.join(" ")
+    );
+
+    let mut child = match Command::new("ffmpeg")
+        .args(cmd)
+        .limits(&config.channel.limits)
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "Region <b><magenta>{}</></b> encoder failed: {e}", region.name);
+            update_status(region.id, |s| s.error = Some(e.to_string()));
+
+            return None;
+        }
+    };
+
+    let writer = BufWriter::new(child.stdin.take().unwrap());
+    let enc_err = BufReader::new(child.stderr.take().unwrap());
+    let ignore = config.logging.ignore_lines.clone();
+    let manager = manager.clone();
+    let stats = format!("region_{}", region.name);
+
+    start_stream(&manager, &stats);
+
+    thread::spawn(move || stderr_reader(enc_err, ignore, Encoder, Some(stats), manager));
+
+    info!(target: Target::file_mail(), channel = id; "Start regional output <b><magenta>{}</></b>", region.name);
+
+    update_status(region.id, |s| {
+        s.running = true;
+        s.error = None;
+    });
+
+    Some(RegionOutput {
+        region,
+        child,
+        writer: Arc::new(Mutex::new(Some(writer))),
+        in_window: Arc::new(AtomicBool::new(false)),
+    })
+}
+
+/// Encoders of all enabled regions from a channel.
+pub struct RegionOutputs {
+    manager: ChannelManager,
+    log_format: String,
+    outputs: Vec<RegionOutput>,
+    /// Window of the last clip, a new window starts with another one.
+    window: Option<String>,
+}
+
+impl RegionOutputs {
+    pub fn new(manager: ChannelManager, log_format: &str) -> Self {
+        manager.reload_regions.store(false, Ordering::SeqCst);
+
+        let regions = manager.regions.lock().unwrap().clone();
+        let outputs = regions
+            .into_iter()
+            .filter(|r| r.enable)
+            .filter_map(|r| start_encoder(&manager, r, log_format))
+            .collect();
+
+        Self {
+            manager,
+            log_format: log_format.to_string(),
+            outputs,
+            window: None,
+        }
+    }
+
+    /// Start the encoders again with the changed regions, call it only between two clips.
+    pub fn reload(self) -> Self {
+        let manager = self.manager.clone();
+        let log_format = self.log_format.clone();
+
+        self.stop();
+
+        Self::new(manager, &log_format)
+    }
+
+    /// Check if the clip starts a break window, and start the regional content for it.
+    pub fn start_node(&mut self, node: &Media) {
+        let window = break_window(node);
+
+        if window == self.window {
+            return;
+        }
+
+        self.window.clone_from(&window);
+
+        let (Some(window), Some(index)) = (window, node.index) else {
+            return;
+        };
+
+        let config = self.manager.config.lock().unwrap().clone();
+        let date = self.manager.current_date.lock().unwrap().clone();
+        let length = window_length(&self.manager.current_list.lock().unwrap(), index);
+
+        for output in &self.outputs {
+            if output.in_window.load(Ordering::SeqCst) || output.writer.lock().unwrap().is_none() {
+                continue;
+            }
+
+            let Ok(mut playlist) = read_region_playlist(&config, output.region.id, &date) else {
+                continue;
+            };
+
+            playlist.start_sec = Some(0.0);
+            set_defaults(&mut playlist);
+
+            let items = window_items(&playlist, &window, length);
+
+            if items.is_empty() {
+                continue;
+            }
+
+            info!(target: Target::file_mail(), channel = config.general.channel_id;
+                "Region <b><magenta>{}</></b> replaces break <b><magenta>{window}</></b> with {} items",
+                output.region.name,
+                items.len()
+            );
+
+            update_status(output.region.id, |s| {
+                s.window = Some(window.clone());
+                s.replaced += 1;
+            });
+
+            output.in_window.store(true, Ordering::SeqCst);
+
+            let manager = self.manager.clone();
+            let config = config.clone();
+            let region = output.region.id;
+            let writer = output.writer.clone();
+            let in_window = output.in_window.clone();
+
+            thread::spawn(move || play_window(manager, config, region, items, writer, in_window));
+        }
+    }
+
+    /// Write the main stream to the regions, which play no regional content.
+    pub fn write(&mut self, buf: &[u8]) {
+        let id = self.manager.channel.lock().unwrap().id;
+
+        for output in &self.outputs {
+            if output.in_window.load(Ordering::SeqCst) {
+                continue;
+            }
+
+            let mut writer = output.writer.lock().unwrap();
+
+            if let Some(Err(e)) = writer.as_mut().map(|w| w.write_all(buf)) {
+                error!(target: Target::file_mail(), channel = id; "Region <b><magenta>{}</></b> encoder write error: {}", output.region.name, e.kind());
+
+                *writer = None;
+                update_status(output.region.id, |s| {
+                    s.running = false;
+                    s.error = Some(e.to_string());
+                });
+            }
+        }
+    }
+
+    /// Stop all region encoders.
+    pub fn stop(self) {
+        for mut output in self.outputs {
+            output.in_window.store(false, Ordering::SeqCst);
+            *output.writer.lock().unwrap() = None;
+
+            let _ = output.child.kill();
+            let _ = output.child.wait();
+
+            update_status(output.region.id, |s| {
+                s.running = false;
+                s.window = None;
+            });
+        }
+    }
+}
@@ -0,0 +1,584 @@
+/*
+Push HLS output to S3 compatible storage. The client is used for the media storage too,
+see `utils::object_store`.
+
+ffmpeg still writes segments and playlists to the channel public folder,
+this module watches that folder and uploads new or changed files to the bucket.
+Segments which are removed locally (hls_flags delete_segments) are deleted from the bucket too.
+
+Requests are signed with AWS Signature Version 4 and use path style addressing:
+{endpoint}/{bucket}/{prefix}{file}
+
+*/
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{atomic::Ordering, LazyLock},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use log::*;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use crate::player::controller::ChannelManager;
+use crate::utils::{config::Output, logging::Target, time_machine::clock_sleep};
+
+const HLS_EXTENSIONS: [&str; 6] = ["m3u8", "ts", "m4s", "mp4", "vtt", "aac"];
+
+/// Media files can be large, the default timeout is for small files.
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(3600);
+
+/// SHA256 of an empty body.
+const EMPTY_HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+static LIST_PREFIX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<CommonPrefixes>\s*<Prefix>(.*?)</Prefix>").unwrap());
+static LIST_CONTENT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)<Contents>(.*?)</Contents>").unwrap());
+static LIST_KEY: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<Key>(.*?)</Key>").unwrap());
+static LIST_SIZE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<Size>(\d+)</Size>").unwrap());
+static LIST_TOKEN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<NextContinuationToken>(.*?)</NextContinuationToken>").unwrap());
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone)]
+pub struct S3Client {
+    client: reqwest::blocking::Client,
+    endpoint: String,
+    host: String,
+    region: String,
+    bucket: String,
+    access_key: String,
+    secret_key: String,
+}
+
+/// Objects and sub folders under a prefix.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjectList {
+    pub folders: Vec<String>,
+    /// Keys with size in bytes.
+    pub objects: Vec<(String, u64)>,
+}
+
+impl S3Client {
+    pub fn new(output: &Output) -> Result<Self, String> {
+        Self::connect(
+            &output.s3_endpoint,
+            &output.s3_region,
+            &output.s3_bucket,
+            &output.s3_access_key,
+            &output.s3_secret_key,
+        )
+    }
+
+    pub fn connect(
+        endpoint: &str,
+        region: &str,
+        bucket: &str,
+        access_key: &str,
+        secret_key: &str,
+    ) -> Result<Self, String> {
+        let url = url::Url::parse(endpoint).map_err(|e| e.to_string())?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{host}:{port}"),
+            (Some(host), None) => host.to_string(),
+            _ => return Err(format!("No host in S3 endpoint: {endpoint}")),
+        };
+        let region = if region.is_empty() {
+            "us-east-1".to_string()
+        } else {
+            region.to_string()
+        };
+
+        Ok(Self {
+            client: reqwest::blocking::Client::builder()
+                .timeout(Duration::from_secs(30))
+                .build()
+                .map_err(|e| e.to_string())?,
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            host,
+            region,
+            bucket: bucket.to_string(),
+            access_key: access_key.to_string(),
+            secret_key: secret_key.to_string(),
+        })
+    }
+
+    fn path(&self, key: &str) -> String {
+        if key.is_empty() {
+            uri_encode(&format!("/{}", self.bucket))
+        } else {
+            uri_encode(&format!("/{}/{key}", self.bucket))
+        }
+    }
+
+    fn signing_key(&self, date: &str) -> Vec<u8> {
+        let k_date = hmac(format!("AWS4{}", self.secret_key).as_bytes(), date);
+        let k_region = hmac(&k_date, &self.region);
+        let k_service = hmac(&k_region, "s3");
+
+        hmac(&k_service, "aws4_request")
+    }
+
+    /// Signed request, without body.
+    fn signed(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        query: &[(&str, &str)],
+        payload_hash: &str,
+    ) -> reqwest::blocking::RequestBuilder {
+        let path = self.path(key);
+        let query = canonical_query(query);
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+
+        let canonical_request = format!(
+            "{method}\n{path}\n{query}\nhost:{}\nx-amz-content-sha256:{payload_hash}\nx-amz-date:{amz_date}\n\nhost;x-amz-content-sha256;x-amz-date\n{payload_hash}",
+            self.host
+        );
+        let scope = format!("{date}/{}/s3/aws4_request", self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let signature = hex::encode(hmac(&self.signing_key(&date), &string_to_sign));
+
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={signature}",
+            self.access_key
+        );
+
+        let url = if query.is_empty() {
+            format!("{}{path}", self.endpoint)
+        } else {
+            format!("{}{path}?{query}", self.endpoint)
+        };
+
+        self.client
+            .request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("Authorization", authorization)
+    }
+
+    fn execute(
+        req: reqwest::blocking::RequestBuilder,
+    ) -> Result<reqwest::blocking::Response, String> {
+        let resp = req.send().map_err(|e| e.to_string())?;
+
+        if resp.status().is_success() {
+            Ok(resp)
+        } else {
+            Err(format!(
+                "{} {}",
+                resp.status(),
+                resp.text().unwrap_or_default()
+            ))
+        }
+    }
+
+    fn send(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        body: Vec<u8>,
+        content_type: Option<&str>,
+    ) -> Result<(), String> {
+        let payload_hash = hex::encode(Sha256::digest(&body));
+        let mut req = self.signed(method, key, &[], &payload_hash);
+
+        if let Some(c_type) = content_type {
+            req = req.header("Content-Type", c_type);
+        }
+
+        Self::execute(req.body(body)).map(|_| ())
+    }
+
+    pub fn put(&self, key: &str, body: Vec<u8>, content_type: &str) -> Result<(), String> {
+        self.send(reqwest::Method::PUT, key, body, Some(content_type))
+    }
+
+    /// Upload a file, it is streamed from disk and not loaded into memory.
+    pub fn put_file(&self, key: &str, path: &Path) -> Result<(), String> {
+        let file = fs::File::open(path).map_err(|e| e.to_string())?;
+        let length = file.metadata().map_err(|e| e.to_string())?.len();
+        let req = self
+            .signed(reqwest::Method::PUT, key, &[], "UNSIGNED-PAYLOAD")
+            .header("Content-Type", content_type(path))
+            .timeout(TRANSFER_TIMEOUT)
+            .body(reqwest::blocking::Body::sized(file, length));
+
+        Self::execute(req).map(|_| ())
+    }
+
+    /// Download an object into a file.
+    pub fn get_file(&self, key: &str, path: &Path) -> Result<u64, String> {
+        let req = self
+            .signed(reqwest::Method::GET, key, &[], EMPTY_HASH)
+            .timeout(TRANSFER_TIMEOUT);
+        let mut resp = Self::execute(req)?;
+        let mut file = fs::File::create(path).map_err(|e| e.to_string())?;
+
+        resp.copy_to(&mut file).map_err(|e| e.to_string())
+    }
+
+    pub fn delete(&self, key: &str) -> Result<(), String> {
+        self.send(reqwest::Method::DELETE, key, vec![], None)
+    }
+
+    /// List objects and sub folders under the prefix, which should end with a slash.
+    pub fn list(&self, prefix: &str) -> Result<ObjectList, String> {
+        let mut list = ObjectList::default();
+        let mut token = None;
+
+        loop {
+            let mut query = vec![("delimiter", "/"), ("list-type", "2"), ("prefix", prefix)];
+
+            if let Some(t) = token.as_deref() {
+                query.push(("continuation-token", t));
+            }
+
+            let resp = Self::execute(self.signed(reqwest::Method::GET, "", &query, EMPTY_HASH))?;
+            let page = parse_list(&resp.text().map_err(|e| e.to_string())?);
+
+            list.folders.extend(page.0.folders);
+            list.objects.extend(page.0.objects);
+
+            match page.1 {
+                Some(next) => token = Some(next),
+                None => break,
+            }
+        }
+
+        Ok(list)
+    }
+
+    /// Url with the signature in the query, which can be read without credentials until it expires.
+    pub fn presign(&self, key: &str, expires: Duration) -> String {
+        let path = self.path(key);
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let scope = format!("{date}/{}/s3/aws4_request", self.region);
+        let credential = format!("{}/{scope}", self.access_key);
+        let seconds = expires.as_secs().to_string();
+        let query = canonical_query(&[
+            ("X-Amz-Algorithm", "AWS4-HMAC-SHA256"),
+            ("X-Amz-Credential", &credential),
+            ("X-Amz-Date", &amz_date),
+            ("X-Amz-Expires", &seconds),
+            ("X-Amz-SignedHeaders", "host"),
+        ]);
+
+        let canonical_request = format!(
+            "GET\n{path}\n{query}\nhost:{}\n\nhost\nUNSIGNED-PAYLOAD",
+            self.host
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let signature = hex::encode(hmac(&self.signing_key(&date), &string_to_sign));
+
+        format!(
+            "{}{path}?{query}&X-Amz-Signature={signature}",
+            self.endpoint
+        )
+    }
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC can take key of any size");
+    mac.update(data.as_bytes());
+
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Encode path for S3, slashes are kept.
+pub fn uri_encode(path: &str) -> String {
+    encode(path, true)
+}
+
+fn encode(input: &str, keep_slash: bool) -> String {
+    let mut encoded = String::new();
+
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char);
+            }
+            b'/' if keep_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+
+    encoded
+}
+
+/// Query string sorted by name, with encoded names and values.
+pub fn canonical_query(query: &[(&str, &str)]) -> String {
+    let mut pairs: Vec<(String, String)> = query
+        .iter()
+        .map(|(k, v)| (encode(k, false), encode(v, false)))
+        .collect();
+
+    pairs.sort();
+
+    pairs
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn xml_text(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Entries and continuation token from a ListObjectsV2 response.
+pub fn parse_list(xml: &str) -> (ObjectList, Option<String>) {
+    let mut list = ObjectList::default();
+    let mut token = None;
+
+    for prefix in LIST_PREFIX.captures_iter(xml) {
+        list.folders.push(xml_text(&prefix[1]));
+    }
+
+    for content in LIST_CONTENT.captures_iter(xml) {
+        let entry = &content[1];
+
+        if let Some(key) = LIST_KEY.captures(entry) {
+            let size = LIST_SIZE
+                .captures(entry)
+                .and_then(|s| s[1].parse().ok())
+                .unwrap_or_default();
+
+            list.objects.push((xml_text(&key[1]), size));
+        }
+    }
+
+    if xml.contains("<IsTruncated>true</IsTruncated>") {
+        token = LIST_TOKEN.captures(xml).map(|t| xml_text(&t[1]));
+    }
+
+    (list, token)
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+    {
+        "m3u8" => "application/vnd.apple.mpegurl",
+        "ts" => "video/mp2t",
+        "m4s" | "mp4" => "video/mp4",
+        "vtt" => "text/vtt",
+        "aac" => "audio/aac",
+        _ => "application/octet-stream",
+    }
+}
+
+fn is_hls_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| HLS_EXTENSIONS.contains(&e))
+}
+
+/// Object key for a local file, relative to the public folder.
+fn s3_key(public: &Path, prefix: &str, path: &Path) -> String {
+    let relative = path
+        .strip_prefix(public)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string();
+
+    if prefix.is_empty() {
+        relative
+    } else {
+        format!("{prefix}/{relative}")
+    }
+}
+
+/// Upload files in parallel, with at most `concurrency` requests at once.
+fn upload_files(
+    client: &S3Client,
+    files: &[(PathBuf, String)],
+    concurrency: usize,
+    id: i32,
+) -> Vec<PathBuf> {
+    let mut uploaded = vec![];
+
+    for chunk in files.chunks(concurrency.max(1)) {
+        thread::scope(|s| {
+            let handles: Vec<_> = chunk
+                .iter()
+                .map(|(path, key)| {
+                    s.spawn(move || {
+                        let body = fs::read(path).map_err(|e| e.to_string())?;
+                        client.put(key, body, content_type(path))?;
+
+                        Ok::<PathBuf, String>(path.clone())
+                    })
+                })
+                .collect();
+
+            for handle in handles {
+                match handle.join() {
+                    Ok(Ok(path)) => uploaded.push(path),
+                    Ok(Err(e)) => {
+                        error!(target: Target::file_mail(), channel = id; "S3 upload failed: {e}");
+                    }
+                    Err(_) => {
+                        error!(target: Target::file_mail(), channel = id; "S3 upload thread panicked");
+                    }
+                }
+            }
+        });
+    }
+
+    uploaded
+}
+
+/// Watch the public folder and push changed HLS files to S3, until the channel stops.
+pub fn s3_pusher(manager: ChannelManager) {
+    let config = manager.config.lock().unwrap().clone();
+    let id = config.general.channel_id;
+    let public = config.channel.public.clone();
+    let prefix = config.output.s3_prefix.trim_matches('/').to_string();
+    let concurrency = config.output.s3_concurrency.max(1) as usize;
+    let is_terminated = manager.is_terminated.clone();
+
+    let client = match S3Client::new(&config.output) {
+        Ok(c) => c,
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "S3 push disabled: {e}");
+            return;
+        }
+    };
+
+    info!(target: Target::file_mail(), channel = id; "Push HLS output to S3 bucket <b><magenta>{}</></b>", config.output.s3_bucket);
+
+    let mut known: HashMap<PathBuf, SystemTime> = HashMap::new();
+
+    while !is_terminated.load(Ordering::SeqCst) {
+        let mut segments = vec![];
+        let mut playlists = vec![];
+        let mut existing = vec![];
+
+        for entry in WalkDir::new(&public)
+            .into_iter()
+            .flatten()
+            .filter(|e| e.file_type().is_file() && is_hls_file(e.path()))
+        {
+            let path = entry.path().to_path_buf();
+            let modified = entry
+                .metadata()
+                .ok()
+                .and_then(|m| m.modified().ok())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+
+            existing.push(path.clone());
+
+            if known.get(&path) == Some(&modified) {
+                continue;
+            }
+
+            let key = s3_key(&public, &prefix, &path);
+
+            if path.extension().is_some_and(|e| e == "m3u8") {
+                playlists.push((path, key));
+            } else {
+                segments.push((path, key));
+            }
+        }
+
+        // segments first, playlists should never point to missing files
+        for files in [segments, playlists] {
+            for path in upload_files(&client, &files, concurrency, id) {
+                if let Ok(modified) = fs::metadata(&path).and_then(|m| m.modified()) {
+                    known.insert(path, modified);
+                }
+            }
+        }
+
+        let expired: Vec<PathBuf> = known
+            .keys()
+            .filter(|p| !existing.contains(p))
+            .cloned()
+            .collect();
+
+        for path in expired {
+            let key = s3_key(&public, &prefix, &path);
+
+            if let Err(e) = client.delete(&key) {
+                warn!(target: Target::file_mail(), channel = id; "S3 delete of <b><magenta>{key}</></b> failed: {e}");
+            }
+
+            known.remove(&path);
+        }
+
+        clock_sleep(Duration::from_millis(500));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn s3_uri_encode() {
+        assert_eq!(
+            uri_encode("/bucket/live/stream-1.ts"),
+            "/bucket/live/stream-1.ts"
+        );
+        assert_eq!(
+            uri_encode("/bucket/my channel/stream+1.ts"),
+            "/bucket/my%20channel/stream%2B1.ts"
+        );
+    }
+
+    #[test]
+    fn s3_object_list() {
+        assert_eq!(
+            canonical_query(&[("prefix", "media/my show/"), ("list-type", "2")]),
+            "list-type=2&prefix=media%2Fmy%20show%2F"
+        );
+
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult>
+  <IsTruncated>true</IsTruncated>
+  <Contents><Key>media/</Key><Size>0</Size></Contents>
+  <Contents><Key>media/a &amp; b.mp4</Key><LastModified>2024-10-27T10:00:00.000Z</LastModified><Size>1048576</Size></Contents>
+  <CommonPrefixes><Prefix>media/shows/</Prefix></CommonPrefixes>
+  <NextContinuationToken>1ueGcxLPRx1Tr</NextContinuationToken>
+</ListBucketResult>"#;
+
+        let (list, token) = parse_list(xml);
+
+        assert_eq!(list.folders, vec!["media/shows/"]);
+        assert_eq!(
+            list.objects,
+            vec![
+                ("media/".to_string(), 0),
+                ("media/a & b.mp4".to_string(), 1048576)
+            ]
+        );
+        assert_eq!(token, Some("1ueGcxLPRx1Tr".to_string()));
+        assert_eq!(parse_list("<IsTruncated>false</IsTruncated>").1, None);
+    }
+}
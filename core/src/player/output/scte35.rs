@@ -0,0 +1,503 @@
+/*
+SCTE-35 ad markers for HLS output.
+
+Playlist items with `ad_break` (length in seconds) start a break, when they begin to play.
+A break can also be started or ended over the API.
+
+ffmpeg can not create SCTE-35 sections by itself, so the markers are written to the HLS playlists:
+the cue tagger watches the playlists in the public folder and adds before the first segment after a cue
+
+- `#EXT-X-DATERANGE` with the binary splice_insert() section (`SCTE35-OUT`/`SCTE35-IN`)
+- `#EXT-X-CUE-OUT`/`#EXT-X-CUE-IN` for players, which only know these tags
+
+The same tagger writes the parental rating, see the rating module, the metadata of the playing item,
+see the timed_metadata module, and the names and languages of the audio tracks to the master playlist.
+
+*/
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, TimeDelta};
+use log::*;
+use serde::{Deserialize, Serialize};
+use walkdir::{DirEntry, WalkDir};
+
+use crate::player::{
+    controller::ChannelManager,
+    output::{
+        rating::{rating_tags, session_data},
+        timed_metadata::{item_tags, ItemMetadata},
+    },
+    utils::{audio_tracks::master_labels, Media},
+};
+use crate::utils::{
+    logging::Target,
+    time_machine::{clock_sleep, time_now},
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CueKind {
+    /// Leave the program for a break.
+    #[default]
+    Out,
+    /// Return to the program.
+    In,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    pub id: u32,
+    pub kind: CueKind,
+    pub duration: f64,
+    pub time: DateTime<Local>,
+}
+
+/// Cues, rating changes and started items, which are not written to the HLS playlists yet.
+#[derive(Debug, Default, Clone)]
+pub struct CueQueue {
+    next_id: u32,
+    cues: Vec<Cue>,
+    rating: Option<String>,
+    ratings: Vec<(DateTime<Local>, Option<String>)>,
+    items: Vec<ItemMetadata>,
+}
+
+impl CueQueue {
+    /// Start a break now, the return follows after the duration.
+    pub fn cue_out(&mut self, duration: f64) -> Cue {
+        let now = time_now();
+
+        self.next_id += 1;
+        // a running break ends with the new one
+        self.cues.retain(|c| c.kind != CueKind::In);
+
+        let cue = Cue {
+            id: self.next_id,
+            kind: CueKind::Out,
+            duration,
+            time: now,
+        };
+
+        self.cues.push(cue.clone());
+        self.cues.push(Cue {
+            kind: CueKind::In,
+            duration: 0.0,
+            time: now + TimeDelta::milliseconds((duration * 1000.0) as i64),
+            ..cue.clone()
+        });
+
+        cue
+    }
+
+    /// Return to the program now, before the planned end of the break.
+    pub fn cue_in(&mut self) -> Option<Cue> {
+        let index = self.cues.iter().position(|c| c.kind == CueKind::In)?;
+        let mut cue = self.cues.remove(index);
+
+        cue.time = time_now();
+        self.cues.push(cue.clone());
+
+        Some(cue)
+    }
+
+    /// Remove and return all cues, which are due.
+    pub fn take_due(&mut self, now: DateTime<Local>) -> Vec<Cue> {
+        let (due, pending) = self.cues.drain(..).partition(|c| c.time <= now);
+        self.cues = pending;
+
+        due
+    }
+
+    /// Set the parental rating from the running item, returns true when it changed.
+    pub fn set_rating(&mut self, rating: Option<String>) -> bool {
+        if self.rating == rating {
+            return false;
+        }
+
+        self.rating.clone_from(&rating);
+        self.ratings.push((time_now(), rating));
+
+        true
+    }
+
+    pub fn rating(&self) -> Option<&str> {
+        self.rating.as_deref()
+    }
+
+    /// Remove and return all rating changes.
+    pub fn take_ratings(&mut self) -> Vec<(DateTime<Local>, Option<String>)> {
+        self.ratings.drain(..).collect()
+    }
+
+    /// Metadata from the item, which starts now.
+    pub fn push_item(&mut self, item: ItemMetadata) {
+        self.items.push(item);
+    }
+
+    /// Remove and return the metadata from the started items.
+    pub fn take_items(&mut self) -> Vec<ItemMetadata> {
+        self.items.drain(..).collect()
+    }
+}
+
+/// CRC-32/MPEG-2, which is used for MPEG-TS sections.
+pub fn crc32_mpeg(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFF_u32;
+
+    for byte in data {
+        crc ^= (*byte as u32) << 24;
+
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04C1_1DB7
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc
+}
+
+/// splice_info_section with a immediate splice_insert() command.
+pub fn splice_insert(event_id: u32, kind: CueKind, duration: f64) -> Vec<u8> {
+    let with_duration = kind == CueKind::Out && duration > 0.0;
+    let mut command = event_id.to_be_bytes().to_vec();
+
+    // splice_event_cancel_indicator 0, reserved
+    command.push(0x7F);
+    // out_of_network_indicator, program_splice_flag, duration_flag, splice_immediate_flag, reserved
+    command
+        .push((u8::from(kind == CueKind::Out) << 7) | 0x40 | (u8::from(with_duration) << 5) | 0x1F);
+
+    if with_duration {
+        // auto_return, reserved, duration in 90 kHz ticks
+        let ticks = ((duration * 90000.0).round() as u64) & 0x1_FFFF_FFFF;
+        let value = (1_u64 << 39) | (0x3F << 33) | ticks;
+
+        command.extend_from_slice(&value.to_be_bytes()[3..]);
+    }
+
+    // unique_program_id, avail_num, avails_expected
+    command.extend_from_slice(&[0x00, 0x01, 0x00, 0x00]);
+
+    let command_length = command.len();
+    let section_length = 17 + command_length;
+    let mut section = vec![
+        0xFC,
+        0x30 | ((section_length >> 8) as u8 & 0x0F),
+        section_length as u8,
+        // protocol_version
+        0x00,
+        // encrypted_packet, encryption_algorithm, pts_adjustment
+        0x00,
+        0x00,
+        0x00,
+        0x00,
+        0x00,
+        // cw_index
+        0x00,
+        // tier, splice_command_length
+        0xFF,
+        0xF0 | ((command_length >> 8) as u8 & 0x0F),
+        command_length as u8,
+        // splice_command_type: splice_insert
+        0x05,
+    ];
+
+    section.append(&mut command);
+    // descriptor_loop_length
+    section.extend_from_slice(&[0x00, 0x00]);
+
+    let crc = crc32_mpeg(&section);
+    section.extend_from_slice(&crc.to_be_bytes());
+
+    section
+}
+
+/// HLS tags for one cue.
+pub fn cue_tags(cue: &Cue) -> Vec<String> {
+    let section = hex::encode_upper(splice_insert(cue.id, cue.kind, cue.duration));
+    let start = cue.time.format("%Y-%m-%dT%H:%M:%S%.3f%:z");
+
+    match cue.kind {
+        CueKind::Out => vec![
+            format!(
+                "#EXT-X-DATERANGE:ID=\"splice-{}\",START-DATE=\"{start}\",PLANNED-DURATION={:.3},SCTE35-OUT=0x{section}",
+                cue.id, cue.duration
+            ),
+            format!("#EXT-X-CUE-OUT:DURATION={:.3}", cue.duration),
+        ],
+        CueKind::In => vec![
+            format!(
+                "#EXT-X-DATERANGE:ID=\"splice-{}\",START-DATE=\"{start}\",SCTE35-IN=0x{section}",
+                cue.id
+            ),
+            "#EXT-X-CUE-IN".to_string(),
+        ],
+    }
+}
+
+/// Segment uris from a media playlist.
+fn segments(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+fn is_cue_tag(line: &str) -> bool {
+    line.starts_with("#EXT-X-CUE-")
+        || line.starts_with("#EXT-X-DATERANGE:ID=\"splice-")
+        || line.starts_with("#EXT-X-DATERANGE:ID=\"rating-")
+        || line.starts_with("#EXT-X-DATERANGE:ID=\"item-")
+}
+
+/// Insert the tags before the `#EXTINF` from their segments, old cue tags are replaced.
+pub fn tag_playlist(content: &str, tags: &HashMap<String, Vec<String>>) -> String {
+    let mut lines: Vec<String> = content
+        .lines()
+        .filter(|l| !is_cue_tag(l))
+        .map(String::from)
+        .collect();
+    let mut i = lines.len();
+
+    while i > 0 {
+        i -= 1;
+
+        if lines[i].starts_with('#') {
+            continue;
+        }
+
+        if let Some(t) = tags.get(lines[i].trim()) {
+            let pos = lines[..i]
+                .iter()
+                .rposition(|l| l.starts_with("#EXTINF"))
+                .unwrap_or(i);
+
+            lines.splice(pos..pos, t.iter().cloned());
+        }
+    }
+
+    let mut playlist = lines.join("\n");
+    playlist.push('\n');
+
+    playlist
+}
+
+/// Start a break, when the playlist item has one.
+pub fn node_cue(manager: &ChannelManager, node: &Media) {
+    let config = manager.config.lock().unwrap();
+
+    if let Some(duration) = node.ad_break.filter(|d| *d > 0.0 && config.output.scte35) {
+        let id = config.general.channel_id;
+        drop(config);
+
+        let cue = manager.cues.lock().unwrap().cue_out(duration);
+
+        info!(target: Target::file_mail(), channel = id; "SCTE-35 ad break <yellow>{}</> for <yellow>{duration:.2}</> seconds", cue.id);
+    }
+}
+
+#[derive(Debug, Default)]
+struct PlaylistState {
+    content: String,
+    segments: HashSet<String>,
+    tags: HashMap<String, Vec<String>>,
+    /// Tags, which wait for the next segment.
+    waiting: Vec<String>,
+}
+
+fn media_playlists(public: &Path) -> Vec<PathBuf> {
+    WalkDir::new(public)
+        .into_iter()
+        .flatten()
+        .filter(|e| {
+            e.file_type().is_file() && e.path().extension().is_some_and(|ext| ext == "m3u8")
+        })
+        .map(DirEntry::into_path)
+        .collect()
+}
+
+/// Write the due cues and rating changes to all HLS playlists in the public folder.
+pub fn cue_tagger(manager: ChannelManager) {
+    let config = manager.config.lock().unwrap().clone();
+    let id = config.general.channel_id;
+    let public = config.channel.public.clone();
+    let rating_config = config.output.rating.clone();
+    let track_map = config.processing.audio_track_map.clone();
+    let is_terminated = manager.is_terminated.clone();
+    let mut states: HashMap<PathBuf, PlaylistState> = HashMap::new();
+
+    info!(target: Target::file_mail(), channel = id; "Write markers to HLS playlists");
+
+    while !is_terminated.load(Ordering::SeqCst) {
+        let (mut due, ratings, items, rating) = {
+            let mut queue = manager.cues.lock().unwrap();
+            let due: Vec<String> = queue
+                .take_due(time_now())
+                .iter()
+                .flat_map(cue_tags)
+                .collect();
+            let ratings = queue.take_ratings();
+            let items = queue.take_items();
+
+            (due, ratings, items, queue.rating().map(String::from))
+        };
+
+        for (time, r) in ratings {
+            due.append(&mut rating_tags(&rating_config.system, r.as_deref(), &time));
+        }
+
+        for item in items {
+            due.append(&mut item_tags(&item));
+        }
+
+        for path in media_playlists(&public) {
+            let state = states.entry(path.clone()).or_default();
+            state.waiting.extend(due.iter().cloned());
+
+            let Ok(content) = fs::read_to_string(&path) else {
+                continue;
+            };
+
+            let tagged = if content.contains("#EXTINF") {
+                if content == state.content {
+                    continue;
+                }
+
+                let current = segments(&content);
+
+                // the first segment, which was not in the last version of the playlist
+                if let Some(segment) = current
+                    .iter()
+                    .find(|s| !state.segments.is_empty() && !state.segments.contains(*s))
+                    .filter(|_| !state.waiting.is_empty())
+                {
+                    let tags = state.tags.entry(segment.clone()).or_default();
+                    tags.append(&mut state.waiting);
+                }
+
+                state.tags.retain(|s, _| current.contains(s));
+                state.segments = current.into_iter().collect();
+
+                tag_playlist(&content, &state.tags)
+            } else {
+                // master playlist
+                state.waiting.clear();
+
+                if !rating_config.enable && track_map.is_empty() {
+                    continue;
+                }
+
+                let mut master = content.clone();
+
+                if rating_config.enable {
+                    master = session_data(&master, &rating_config.system, rating.as_deref());
+                }
+
+                master_labels(&master, &track_map)
+            };
+
+            if tagged == content {
+                state.content = tagged;
+                continue;
+            }
+
+            let tmp = path.with_extension("m3u8.tmp");
+
+            if let Err(e) = fs::write(&tmp, &tagged).and_then(|_| fs::rename(&tmp, &path)) {
+                error!(target: Target::file_mail(), channel = id; "Write HLS markers failed: {e}");
+                continue;
+            }
+
+            state.content = tagged;
+        }
+
+        states.retain(|p, _| p.is_file());
+
+        clock_sleep(Duration::from_millis(250));
+    }
+}
+
+/// Add `program_date_time` to the hls_flags, `EXT-X-DATERANGE` needs it.
+pub fn hls_cue_flags(cmd: &mut Vec<String>) {
+    if let Some(i) = cmd.iter().position(|c| c == "-hls_flags") {
+        if let Some(flags) = cmd.get_mut(i + 1) {
+            if !flags.contains("program_date_time") {
+                flags.push_str("+program_date_time");
+            }
+        }
+    } else if let Some(i) = cmd.windows(2).position(|w| w[0] == "-f" && w[1] == "hls") {
+        cmd.splice(
+            i..i,
+            ["-hls_flags".to_string(), "program_date_time".to_string()],
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::vec_strings;
+
+    #[test]
+    fn scte35_markers() {
+        let section = splice_insert(7, CueKind::Out, 120.0);
+
+        assert_eq!(section[0], 0xFC);
+        assert_eq!(
+            section.len(),
+            3 + (((section[1] as usize) & 0x0F) << 8 | section[2] as usize)
+        );
+        assert_eq!(&section[14..18], &7_u32.to_be_bytes());
+        // out of network, with duration, immediate
+        assert_eq!(section[19], 0xFF);
+        // CRC over the whole section with CRC is zero
+        assert_eq!(crc32_mpeg(&section), 0);
+        assert_eq!(splice_insert(7, CueKind::In, 0.0).len(), section.len() - 5);
+
+        let playlist =
+        "#EXTM3U\n#EXT-X-TARGETDURATION:6\n#EXTINF:6.0,\nstream-1.ts\n#EXTINF:6.0,\nstream-2.ts\n";
+        let tags = HashMap::from([(
+            "stream-2.ts".to_string(),
+            vec!["#EXT-X-CUE-OUT:DURATION=120.000".to_string()],
+        )]);
+        let tagged = tag_playlist(playlist, &tags);
+
+        assert_eq!(
+        tagged,
+        "#EXTM3U\n#EXT-X-TARGETDURATION:6\n#EXTINF:6.0,\nstream-1.ts\n#EXT-X-CUE-OUT:DURATION=120.000\n#EXTINF:6.0,\nstream-2.ts\n"
+    );
+        // tagging again keeps the playlist stable
+        assert_eq!(tag_playlist(&tagged, &tags), tagged);
+
+        let mut cmd = vec_strings!["-hls_flags", "delete_segments", "-f", "hls", "stream.m3u8"];
+        hls_cue_flags(&mut cmd);
+        assert_eq!(cmd[1], "delete_segments+program_date_time");
+
+        let mut cmd = vec_strings!["-f", "hls", "stream.m3u8"];
+        hls_cue_flags(&mut cmd);
+        assert_eq!(
+            cmd,
+            vec_strings![
+                "-hls_flags",
+                "program_date_time",
+                "-f",
+                "hls",
+                "stream.m3u8"
+            ]
+        );
+    }
+}
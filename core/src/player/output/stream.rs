@@ -4,9 +4,9 @@ use log::*;
 
 use crate::player::{
     controller::ProcessUnit::*,
-    utils::{prepare_output_cmd, Media},
+    utils::{prepare_output_cmd, stream_stats::progress_args, Media},
 };
-use crate::utils::{config::PlayoutConfig, logging::Target};
+use crate::utils::{config::PlayoutConfig, logging::Target, process_limits::LimitProcess};
 use crate::vec_strings;
 
 /// Streaming Output
@@ -19,6 +19,7 @@ pub fn output(config: &PlayoutConfig, log_format: &str) -> process::Child {
     media.add_filter(config, &None);
 
     let mut enc_prefix = vec_strings!["-hide_banner", "-nostats", "-v", log_format];
+    enc_prefix.append(&mut progress_args());
 
     if let Some(input_cmd) = &config.advanced.encoder.input_cmd {
         enc_prefix.append(&mut input_cmd.clone());
@@ -35,6 +36,7 @@ pub fn output(config: &PlayoutConfig, log_format: &str) -> process::Child {
 
     let enc_proc = match Command::new("ffmpeg")
         .args(enc_cmd)
+        .limits(&config.channel.limits)
         .stdin(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()
@@ -0,0 +1,146 @@
+/*
+Copies of the program stream for extra ffmpeg processes, like the program preview and the alarms.
+
+The process gets the same stream as the channel encoder on stdin. The stream is given to it
+without waiting: when the process is too slow, packages are dropped and the channel output
+keeps running. Lines from stderr go to a handler, they never stop the channel.
+
+*/
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    process::{Child, Command, Stdio},
+    sync::mpsc::{sync_channel, SyncSender, TrySendError},
+    thread::{self, JoinHandle},
+};
+
+use log::*;
+
+use crate::utils::{
+    logging::Target,
+    process_limits::{LimitProcess, ProcessLimits},
+};
+
+/// Packages, which wait for the process.
+const QUEUE_SIZE: usize = 64;
+
+/// ffmpeg process, which reads the program stream from stdin.
+pub struct ProgramTap {
+    id: i32,
+    name: &'static str,
+    sender: Option<SyncSender<Vec<u8>>>,
+    child: Option<Child>,
+    threads: Vec<JoinHandle<()>>,
+    /// Packages, which were dropped since the last warning.
+    dropped: usize,
+}
+
+impl ProgramTap {
+    /// Start ffmpeg with the arguments, every line from stderr goes to `on_line`.
+    pub fn spawn<F>(
+        id: i32,
+        name: &'static str,
+        args: Vec<String>,
+        limits: &ProcessLimits,
+        mut on_line: F,
+    ) -> io::Result<Self>
+    where
+        F: FnMut(&str) + Send + 'static,
+    {
+        debug!(target: Target::file_mail(), channel = id;
+            "Program {name} CMD: <bright-blue>\"ffmpeg {}\"</>",
+            args.join(" ")
+        );
+
+        let mut child = Command::new("ffmpeg")
+            .args(args)
+            .limits(limits)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let mut stdin = child.stdin.take().unwrap();
+        let stderr = BufReader::new(child.stderr.take().unwrap());
+        let (sender, receiver) = sync_channel::<Vec<u8>>(QUEUE_SIZE);
+        let mut threads = vec![];
+
+        threads.push(thread::spawn(move || {
+            for package in receiver {
+                if stdin.write_all(&package).is_err() {
+                    break;
+                }
+            }
+        }));
+
+        threads.push(thread::spawn(move || {
+            for line in stderr.lines().map_while(Result::ok) {
+                on_line(&line);
+            }
+        }));
+
+        Ok(Self {
+            id,
+            name,
+            sender: Some(sender),
+            child: Some(child),
+            threads,
+            dropped: 0,
+        })
+    }
+
+    /// Give the package to the process, without waiting for it.
+    /// Returns `false`, when the process has stopped.
+    pub fn write(&mut self, buf: &[u8]) -> bool {
+        let Some(sender) = &self.sender else {
+            return false;
+        };
+
+        match sender.try_send(buf.to_vec()) {
+            Ok(()) => {
+                if self.dropped > 0 {
+                    warn!(target: Target::file(), channel = self.id;
+                        "Program {} is too slow, dropped <yellow>{}</> packages",
+                        self.name,
+                        self.dropped
+                    );
+
+                    self.dropped = 0;
+                }
+
+                true
+            }
+            Err(TrySendError::Full(_)) => {
+                self.dropped += 1;
+
+                true
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                error!(target: Target::file_mail(), channel = self.id; "Program {} has stopped", self.name);
+
+                self.stop();
+
+                false
+            }
+        }
+    }
+
+    pub fn stop(&mut self) {
+        // ffmpeg ends, when its input is closed
+        self.sender = None;
+
+        if let Some(mut child) = self.child.take() {
+            if matches!(child.try_wait(), Ok(None)) {
+                let _ = child.kill();
+            }
+
+            let _ = child.wait();
+        }
+
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+
+        self.dropped = 0;
+    }
+}
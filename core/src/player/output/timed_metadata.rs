@@ -0,0 +1,113 @@
+/*
+Timed metadata of the playing item in the HLS playlists.
+
+When an item starts, the cue tagger writes a `#EXT-X-DATERANGE` with the class `com.ffplayout.item`
+before the first segment of the item, with title and category as client attributes:
+
+    #EXT-X-DATERANGE:ID="item-1718280000000",CLASS="com.ffplayout.item",START-DATE="2024-06-13T14:00:00.000+02:00",PLANNED-DURATION=1800.000,X-TITLE="News",X-CATEGORY="news"
+
+The segments get `#EXT-X-PROGRAM-DATE-TIME`, so players and analytics can map the item to the timeline.
+The metadata is not written as ID3 into the segments, ffmpeg can not mux it from outside the stream.
+
+*/
+
+use chrono::{DateTime, Local};
+
+use crate::player::{
+    controller::ChannelManager,
+    output::{radio::song_text, rating::quote},
+    utils::Media,
+};
+use crate::utils::time_machine::time_now;
+
+/// Class of the item date ranges.
+pub const ITEM_CLASS: &str = "com.ffplayout.item";
+
+/// Item, which is written to the playlists.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemMetadata {
+    pub title: String,
+    pub category: String,
+    pub duration: f64,
+    pub time: DateTime<Local>,
+}
+
+impl ItemMetadata {
+    pub fn new(node: &Media, time: DateTime<Local>) -> Self {
+        Self {
+            title: song_text("{title}", node),
+            category: node.category.clone(),
+            duration: node.out - node.seek,
+            time,
+        }
+    }
+}
+
+/// Media playlist tag for the start of an item.
+pub fn item_tags(item: &ItemMetadata) -> Vec<String> {
+    let mut tag = format!(
+        "#EXT-X-DATERANGE:ID=\"item-{}\",CLASS=\"{ITEM_CLASS}\",START-DATE=\"{}\"",
+        item.time.timestamp_millis(),
+        item.time.format("%Y-%m-%dT%H:%M:%S%.3f%:z")
+    );
+
+    if item.duration > 0.0 {
+        tag.push_str(&format!(",PLANNED-DURATION={:.3}", item.duration));
+    }
+
+    tag.push_str(&format!(",X-TITLE=\"{}\"", quote(&item.title)));
+
+    if !item.category.is_empty() {
+        tag.push_str(&format!(",X-CATEGORY=\"{}\"", quote(&item.category)));
+    }
+
+    vec![tag]
+}
+
+/// Queue the metadata from the item, which starts now.
+pub fn node_item(manager: &ChannelManager, node: &Media) {
+    if !manager.config.lock().unwrap().output.item_metadata {
+        return;
+    }
+
+    let item = ItemMetadata::new(node, time_now());
+
+    manager.cues.lock().unwrap().push_item(item);
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn item_metadata_tags() {
+        let time = Local.with_ymd_and_hms(2024, 6, 13, 14, 0, 0).unwrap();
+        let mut node = Media::new(0, "/media/news/evening.mp4", false);
+        node.out = 1800.0;
+        node.title = Some("News \"live\"".to_string());
+        node.category = "news".to_string();
+
+        let item = ItemMetadata::new(&node, time);
+
+        assert_eq!(item.title, "News \"live\"");
+        assert_eq!(item.duration, 1800.0);
+        assert_eq!(
+        item_tags(&item),
+        vec![format!(
+            "#EXT-X-DATERANGE:ID=\"item-{}\",CLASS=\"{ITEM_CLASS}\",START-DATE=\"{}\",PLANNED-DURATION=1800.000,X-TITLE=\"News live\",X-CATEGORY=\"news\"",
+            time.timestamp_millis(),
+            time.format("%Y-%m-%dT%H:%M:%S%.3f%:z")
+        )]
+    );
+
+        node.title = None;
+        node.category = String::new();
+
+        let tag = item_tags(&ItemMetadata::new(&node, time)).remove(0);
+
+        assert!(tag.ends_with(",X-TITLE=\"evening\""));
+        assert!(!tag.contains("X-CATEGORY"));
+    }
+}
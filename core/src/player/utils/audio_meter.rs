@@ -0,0 +1,197 @@
+/*
+Audio levels of the program, for VU meters.
+
+With the audio meter enabled, the first audio track of the decoder and the ingest gets an ebur128
+and an astats filter. ebur128 cuts the audio into frames of 100ms and measures the loudness,
+astats measures peak and RMS level per channel in every frame. ametadata prints the values
+to stderr, between the log lines:
+
+    frame:12   pts:57600   pts_time:1.2
+    lavfi.r128.M=-22.817
+    lavfi.astats.1.Peak_level=-6.021
+
+The levels come from the ingest, while it is on air, otherwise from the decoder.
+Levels, which are older then a second, are not reported, so a stopped decoder looks like no audio.
+
+*/
+
+use std::{
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::player::controller::ChannelManager;
+
+/// Lowest level, silence is reported with it and not as minus infinity.
+pub const LEVEL_FLOOR: f64 = -120.0;
+
+/// Time after which the levels are stale.
+const STALE: Duration = Duration::from_secs(1);
+
+/// Filters for the first audio track, which print the levels to stderr.
+pub fn meter_filter() -> String {
+    [
+        "ebur128=metadata=1",
+        "astats=metadata=1:reset=1:measure_perchannel=Peak_level+RMS_level:measure_overall=none",
+        "ametadata=mode=print:file=pipe\\\\:2:direct=1",
+    ]
+    .join(",")
+}
+
+/// Levels in dBFS and loudness in LUFS.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AudioLevels {
+    /// Momentary loudness, over 400ms.
+    pub momentary: f64,
+    /// Short-term loudness, over 3s.
+    pub short_term: f64,
+    /// Integrated loudness, since the start of the clip or ingest.
+    pub integrated: f64,
+    /// Loudness range in LU.
+    pub range: f64,
+    /// Sample peak per channel.
+    pub peak: Vec<f64>,
+    /// RMS level per channel.
+    pub rms: Vec<f64>,
+    #[serde(skip)]
+    updated: Option<Instant>,
+}
+
+impl AudioLevels {
+    /// Take a value from the printed metadata.
+    pub fn update(&mut self, key: &str, value: &str, now: Instant) {
+        let Ok(value) = value.trim().parse::<f64>() else {
+            return;
+        };
+        let level = value.max(LEVEL_FLOOR);
+
+        match key {
+            "r128.M" => self.momentary = level,
+            "r128.S" => self.short_term = level,
+            "r128.I" => self.integrated = level,
+            "r128.LRA" => self.range = value.max(0.0),
+            _ => {
+                let Some((channel, measure)) =
+                    key.strip_prefix("astats.").and_then(|k| k.split_once('.'))
+                else {
+                    return;
+                };
+                let Some(index) = channel.parse::<usize>().ok().and_then(|c| c.checked_sub(1))
+                else {
+                    return;
+                };
+                let levels = match measure {
+                    "Peak_level" => &mut self.peak,
+                    "RMS_level" => &mut self.rms,
+                    _ => return,
+                };
+
+                if levels.len() <= index {
+                    levels.resize(index + 1, LEVEL_FLOOR);
+                }
+
+                levels[index] = level;
+            }
+        }
+
+        self.updated = Some(now);
+    }
+
+    /// Current levels, `None` when no audio was measured in the last second.
+    pub fn current(&self, now: Instant) -> Option<Self> {
+        self.updated
+            .filter(|t| now.saturating_duration_since(*t) < STALE)
+            .map(|_| self.clone())
+    }
+}
+
+/// Key and value from a line of the audio meter, the frame lines have no value.
+pub fn parse_meter(line: &str) -> Option<(&str, Option<&str>)> {
+    let line = line.trim();
+
+    if line.starts_with("frame:") && line.contains("pts_time:") {
+        return Some(("frame", None));
+    }
+
+    let (key, value) = line.strip_prefix("lavfi.")?.split_once('=')?;
+
+    if !(key.starts_with("r128.") || key.starts_with("astats.")) {
+        return None;
+    }
+
+    Some((key, Some(value)))
+}
+
+/// The live ingest is on air.
+pub fn ingest_on_air(manager: &ChannelManager) -> bool {
+    manager.ingest_is_running.load(Ordering::SeqCst) && manager.accepts_ingest()
+}
+
+/// Take the line into the audio levels, returns `false` for other lines.
+pub fn record_meter(manager: &ChannelManager, line: &str, on_air: bool) -> bool {
+    let Some((key, value)) = parse_meter(line) else {
+        return false;
+    };
+
+    if let Some(value) = value.filter(|_| on_air) {
+        manager
+            .audio_levels
+            .lock()
+            .unwrap()
+            .update(key, value, Instant::now());
+    }
+
+    true
+}
+
+/// Audio levels of the program.
+pub fn audio_levels(manager: &ChannelManager) -> Option<AudioLevels> {
+    manager.audio_levels.lock().unwrap().current(Instant::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audio_meter_levels() {
+        assert!(meter_filter().contains(r"file=pipe\\:2"));
+        assert_eq!(
+            parse_meter("frame:12   pts:57600   pts_time:1.2"),
+            Some(("frame", None))
+        );
+        assert_eq!(
+            parse_meter("lavfi.r128.M=-22.817"),
+            Some(("r128.M", Some("-22.817")))
+        );
+        assert_eq!(parse_meter("lavfi.freezedetect.freeze_start: 2.5"), None);
+        assert_eq!(parse_meter("[info] frame=  250 fps=25"), None);
+
+        let start = std::time::Instant::now();
+        let mut levels = AudioLevels::default();
+
+        assert!(levels.current(start).is_none());
+
+        for (key, value) in [
+            ("r128.M", "-22.817"),
+            ("r128.S", "-inf"),
+            ("astats.2.Peak_level", "-6.5"),
+            ("astats.1.RMS_level", "-24.0"),
+            ("astats.0.Peak_level", "-1.0"),
+        ] {
+            levels.update(key, value, start);
+        }
+
+        let current = levels.current(start).unwrap();
+
+        assert_eq!(current.momentary, -22.817);
+        assert_eq!(current.short_term, LEVEL_FLOOR);
+        assert_eq!(current.peak, vec![LEVEL_FLOOR, -6.5]);
+        assert_eq!(current.rms, vec![-24.0]);
+        assert!(levels
+            .current(start + std::time::Duration::from_secs(2))
+            .is_none());
+    }
+}
@@ -0,0 +1,133 @@
+/*
+Multiple audio tracks.
+
+Output tracks get their source track from:
+
+- the playlist item (`audio_tracks`), a list with the source track for each output track
+- the track map from the config, by language or by the track index from the map
+- otherwise the output track has the same index as the source track
+
+A missing source track is filled with silence. In HLS mode the audio renditions
+from the master playlist get the names and languages from the track map.
+
+*/
+
+use std::path::Path;
+
+use regex::Regex;
+
+use crate::player::utils::Media;
+use crate::utils::config::{AudioTrack, PlayoutConfig};
+
+/// Output tracks with their source track, `None` when the source has no such track.
+pub fn audio_sources(config: &PlayoutConfig, node: &Media) -> Vec<(i32, Option<i32>)> {
+    let processing = &config.processing;
+    let outputs: Vec<i32> = if processing.audio_track_index == -1 {
+        (0..processing.audio_tracks).collect()
+    } else {
+        vec![processing.audio_track_index]
+    };
+
+    if Path::new(&node.audio).is_file() {
+        // separate audio file, it has no track selection
+        return outputs.into_iter().map(|i| (i, Some(i))).collect();
+    }
+
+    let streams = node
+        .probe
+        .as_ref()
+        .map(|p| p.audio_streams.as_slice())
+        .unwrap_or_default();
+    let exists = |i: i32| i >= 0 && (i as usize) < streams.len();
+
+    outputs
+        .into_iter()
+        .map(|i| {
+            let source = if let Some(tracks) = &node.audio_tracks {
+                tracks.get(i as usize).copied().unwrap_or(-1)
+            } else if let Some(track) = processing.audio_track_map.get(i as usize) {
+                streams
+                    .iter()
+                    .position(|s| {
+                        !track.language.is_empty()
+                            && s.tags
+                                .as_ref()
+                                .and_then(|t| t.language.as_deref())
+                                .is_some_and(|l| l.eq_ignore_ascii_case(&track.language))
+                    })
+                    .map(|p| p as i32)
+                    .unwrap_or(track.source)
+            } else {
+                i
+            };
+
+            (i, exists(source).then_some(source))
+        })
+        .collect()
+}
+
+fn set_attribute(line: &str, key: &str, value: &str) -> String {
+    let re = Regex::new(&format!(r#"([:,]){key}="[^"]*""#)).unwrap();
+    let value = value.replace(['"', '\n', '\r'], "");
+
+    if re.is_match(line) {
+        re.replace(
+            line,
+            format!("${{1}}{key}=\"{}\"", value.replace('$', "$$")),
+        )
+        .to_string()
+    } else {
+        format!("{line},{key}=\"{value}\"")
+    }
+}
+
+/// Names and languages from the track map for the audio renditions in the master playlist.
+/// Renditions are counted per group, the first one in a group is the first track from the map.
+pub fn master_labels(content: &str, tracks: &[AudioTrack]) -> String {
+    let group = Regex::new(r#"GROUP-ID="([^"]*)""#).unwrap();
+    let mut counts: Vec<(String, usize)> = vec![];
+    let mut lines = vec![];
+
+    for line in content.lines() {
+        if !line.starts_with("#EXT-X-MEDIA:") || !line.contains("TYPE=AUDIO") {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        let id = group
+            .captures(line)
+            .map(|c| c[1].to_string())
+            .unwrap_or_default();
+        let index = match counts.iter_mut().find(|(g, _)| *g == id) {
+            Some((_, count)) => {
+                *count += 1;
+                *count - 1
+            }
+            None => {
+                counts.push((id, 1));
+                0
+            }
+        };
+        let mut line = line.to_string();
+
+        if let Some(track) = tracks.get(index) {
+            if !track.name.is_empty() {
+                line = set_attribute(&line, "NAME", &track.name);
+            }
+
+            if !track.language.is_empty() {
+                line = set_attribute(&line, "LANGUAGE", &track.language);
+            }
+        }
+
+        lines.push(line);
+    }
+
+    let mut labeled = lines.join("\n");
+
+    if content.ends_with('\n') {
+        labeled.push('\n');
+    }
+
+    labeled
+}
@@ -0,0 +1,161 @@
+/*
+Chapter-aware playback of long files.
+
+The chapters from the file metadata are turned into playlist items,
+each item plays the range of one chapter with seek and out.
+
+*/
+
+use std::{path::Path, process::Command};
+
+use log::*;
+use serde::Deserialize;
+
+use crate::player::utils::Media;
+use crate::utils::errors::ProcessError;
+
+#[derive(Debug, Default, Deserialize)]
+struct ChapterTags {
+    #[serde(default)]
+    title: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Chapter {
+    #[serde(rename = "start_time", deserialize_with = "string_to_f64")]
+    pub start: f64,
+    #[serde(rename = "end_time", deserialize_with = "string_to_f64")]
+    pub end: f64,
+    #[serde(default)]
+    tags: ChapterTags,
+}
+
+impl Chapter {
+    pub fn new(start: f64, end: f64, title: Option<&str>) -> Self {
+        Self {
+            start,
+            end,
+            tags: ChapterTags {
+                title: title.map(String::from),
+            },
+        }
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.tags.title.as_deref().filter(|t| !t.trim().is_empty())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChapterProbe {
+    #[serde(default)]
+    chapters: Vec<Chapter>,
+}
+
+fn string_to_f64<'de, D>(d: D) -> Result<f64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value: String = Deserialize::deserialize(d)?;
+
+    value.parse().map_err(serde::de::Error::custom)
+}
+
+/// Read chapter metadata from file.
+pub fn probe_chapters(source: &str) -> Result<Vec<Chapter>, ProcessError> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-show_chapters", "-of", "json", source])
+        .output()
+        .map_err(ProcessError::CommandSpawn)?;
+
+    if !output.status.success() {
+        return Err(ProcessError::Custom(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    let probe: ChapterProbe = serde_json::from_slice(&output.stdout)?;
+
+    Ok(probe.chapters)
+}
+
+/// One playlist item for every chapter, which is at least one second long.
+///
+/// Items without chapter title get the file name with the chapter number.
+pub fn chapter_items(media: &Media, chapters: &[Chapter]) -> Vec<Media> {
+    let name = Path::new(&media.source)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    chapters
+        .iter()
+        .filter(|c| c.end.min(media.duration) - c.start >= 1.0)
+        .enumerate()
+        .map(|(i, chapter)| {
+            let mut item = media.clone();
+            item.seek = chapter.start;
+            item.out = chapter.end.min(media.duration);
+            item.title = Some(
+                chapter
+                    .title()
+                    .map(String::from)
+                    .unwrap_or_else(|| format!("{name} - {}", i + 1)),
+            );
+
+            item
+        })
+        .collect()
+}
+
+/// Playlist items from the chapters of a file, or the whole file when it has no chapters.
+pub fn split_by_chapters(media: Media) -> Vec<Media> {
+    match probe_chapters(&media.source) {
+        Ok(chapters) if !chapters.is_empty() => {
+            let items = chapter_items(&media, &chapters);
+
+            if items.is_empty() {
+                vec![media]
+            } else {
+                items
+            }
+        }
+        Ok(_) => vec![media],
+        Err(e) => {
+            warn!(
+                "Read chapters from <b><magenta>{}</></b> failed: {e}",
+                media.source
+            );
+
+            vec![media]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chapter_items_split() {
+        let mut media = Media::new(0, "/media/show.mkv", false);
+        media.duration = 100.0;
+
+        let chapters = vec![
+            Chapter::new(0.0, 40.0, Some("Intro")),
+            Chapter::new(40.0, 40.5, None),
+            Chapter::new(40.5, 120.0, None),
+        ];
+
+        let items = chapter_items(&media, &chapters);
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].title, Some("Intro".to_string()));
+        assert_eq!(items[0].seek, 0.0);
+        assert_eq!(items[0].out, 40.0);
+        assert_eq!(items[1].title, Some("show - 2".to_string()));
+        assert_eq!(items[1].seek, 40.5);
+        assert_eq!(items[1].out, 100.0);
+    }
+}
@@ -0,0 +1,275 @@
+/*
+Events from the ffmpeg log.
+
+Warnings and errors from decoder, encoder and ingest are sorted into kinds:
+
+- `decode_error`: broken or missing frames and packets in the source
+- `buffer_underrun`: buffers, which run empty or overflow, and blocking queues
+- `disconnect`: network connections, which are refused, reset or time out, like RTMP
+- `timestamp`: timestamp discontinuities and non monotonic DTS
+- `error`: other errors
+
+Every channel counts the events, with the last events and the clip which was playing.
+Also lines from the ignore lists are counted, they are only not logged. When one kind has
+`ALERT_COUNT` events in `ALERT_WINDOW`, an alert goes to the log and the mail notifiers.
+
+*/
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt,
+    time::{Duration, Instant},
+};
+
+use log::*;
+use serde::Serialize;
+
+use crate::player::controller::{ChannelManager, ProcessUnit};
+use crate::utils::{logging::Target, time_machine::time_now};
+
+/// Events in the history.
+const HISTORY: usize = 50;
+
+/// Events of one kind in the window, which raise an alert.
+pub const ALERT_COUNT: usize = 10;
+pub const ALERT_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    DecodeError,
+    BufferUnderrun,
+    Disconnect,
+    Timestamp,
+    Error,
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::DecodeError => write!(f, "decode error"),
+            Self::BufferUnderrun => write!(f, "buffer underrun"),
+            Self::Disconnect => write!(f, "disconnect"),
+            Self::Timestamp => write!(f, "timestamp"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+const DISCONNECT: [&str; 9] = [
+    "Connection refused",
+    "Connection reset",
+    "Connection timed out",
+    "Broken pipe",
+    "RTMP_ReadPacket",
+    "RTMP_Connect",
+    "Network is unreachable",
+    "Server returned 5",
+    "Connection to tcp://",
+];
+
+const BUFFER: [&str; 7] = [
+    "buffer underflow",
+    "underrun",
+    "VBV underflow",
+    "Circular buffer overrun",
+    "buffer overrun",
+    "Thread message queue blocking",
+    "max delay reached",
+];
+
+const TIMESTAMP: [&str; 8] = [
+    "timestamp discontinuity",
+    "Non-monotonous DTS",
+    "non monotonically increasing dts",
+    "Non-monotonic DTS",
+    "Invalid timestamps",
+    "Timestamps are unset",
+    "DTS discontinuity",
+    "Past duration",
+];
+
+const DECODE: [&str; 11] = [
+    "error while decoding",
+    "Error while decoding",
+    "decode_slice_header error",
+    "Invalid data found when processing input",
+    "concealing",
+    "corrupt decoded frame",
+    "corrupt input packet",
+    "Packet corrupt",
+    "missing picture",
+    "ac-tex damaged",
+    "Invalid mb type",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FfmpegEvent {
+    pub kind: EventKind,
+    pub unit: String,
+    pub message: String,
+    /// Clip, which was playing.
+    pub source: Option<String>,
+    pub timestamp: String,
+}
+
+/// Event counters from one channel, for the status API.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FfmpegEvents {
+    pub counts: BTreeMap<EventKind, u64>,
+    /// Last events, newest first.
+    pub history: VecDeque<FfmpegEvent>,
+    #[serde(skip)]
+    window: HashMap<EventKind, VecDeque<Instant>>,
+}
+
+impl FfmpegEvents {
+    /// Count the event, returns `true` when the kind reaches the alert count.
+    pub fn add(&mut self, event: FfmpegEvent, now: Instant) -> bool {
+        let kind = event.kind;
+        let window = self.window.entry(kind).or_default();
+
+        while window
+            .front()
+            .is_some_and(|t| now.duration_since(*t) > ALERT_WINDOW)
+        {
+            window.pop_front();
+        }
+
+        window.push_back(now);
+
+        let alert = window.len() == ALERT_COUNT;
+
+        if alert {
+            // the next alert needs a full window again
+            window.clear();
+        }
+
+        *self.counts.entry(kind).or_default() += 1;
+        self.history.push_front(event);
+        self.history.truncate(HISTORY);
+
+        alert
+    }
+}
+
+/// Kind of a warning or error line from ffmpeg, `None` for other lines.
+pub fn classify(line: &str) -> Option<EventKind> {
+    let is_error = line.contains("[error]") || line.contains("[fatal]");
+
+    if !is_error && !line.contains("[warning]") {
+        return None;
+    }
+
+    let matches = |patterns: &[&str]| patterns.iter().any(|p| line.contains(p));
+
+    if matches(&DISCONNECT) {
+        Some(EventKind::Disconnect)
+    } else if matches(&BUFFER) {
+        Some(EventKind::BufferUnderrun)
+    } else if matches(&TIMESTAMP) {
+        Some(EventKind::Timestamp)
+    } else if matches(&DECODE) {
+        Some(EventKind::DecodeError)
+    } else if is_error {
+        Some(EventKind::Error)
+    } else {
+        None
+    }
+}
+
+/// Count a line from the ffmpeg log as event and send an alert, when there are too many.
+pub fn record_event(manager: &ChannelManager, unit: ProcessUnit, line: &str) {
+    let Some(kind) = classify(line) else {
+        return;
+    };
+
+    let id = manager.channel.lock().unwrap().id;
+    let source = manager
+        .current_media
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|m| m.source.clone());
+    let message = line
+        .replace("[warning] ", "")
+        .replace("[error] ", "")
+        .replace("[fatal] ", "");
+    let event = FfmpegEvent {
+        kind,
+        unit: unit.to_string(),
+        message,
+        source: source.clone(),
+        timestamp: time_now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    };
+
+    let alert = manager
+        .ffmpeg_events
+        .lock()
+        .unwrap()
+        .add(event, Instant::now());
+
+    if alert {
+        error!(target: Target::file_mail(), channel = id;
+            "<yellow>{ALERT_COUNT}</> {kind} events from {unit} in <yellow>{}</> seconds, clip: <b><magenta>{}</></b>",
+            ALERT_WINDOW.as_secs(),
+            source.unwrap_or_default()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ffmpeg_event_kinds() {
+        assert_eq!(
+            classify("[h264 @ 0x55] [error] error while decoding MB 12 30"),
+            Some(EventKind::DecodeError)
+        );
+        assert_eq!(
+            classify("[rtmp @ 0x55] [error] RTMP_ReadPacket, failed to read RTMP packet header"),
+            Some(EventKind::Disconnect)
+        );
+        assert_eq!(
+            classify("[mpegts @ 0x55] [warning] Non-monotonous DTS in output stream 0:1"),
+            Some(EventKind::Timestamp)
+        );
+        assert_eq!(
+            classify("[udp @ 0x55] [error] Circular buffer overrun"),
+            Some(EventKind::BufferUnderrun)
+        );
+        assert_eq!(
+            classify("[out#0 @ 0x55] [error] Unknown encoder 'foo'"),
+            Some(EventKind::Error)
+        );
+        assert_eq!(classify("[mp4 @ 0x55] [warning] Unknown cover type"), None);
+        assert_eq!(classify("[info] Stream mapping:"), None);
+
+        let event = FfmpegEvent {
+            kind: EventKind::DecodeError,
+            unit: "Decoder".to_string(),
+            message: "error while decoding".to_string(),
+            source: Some("clip.mp4".to_string()),
+            timestamp: String::new(),
+        };
+        let mut events = FfmpegEvents::default();
+        let start = std::time::Instant::now();
+
+        for i in 1..ALERT_COUNT {
+            assert!(!events.add(event.clone(), start));
+            assert_eq!(events.counts[&EventKind::DecodeError], i as u64);
+        }
+
+        // old events are out of the window
+        assert!(!events.add(event.clone(), start + ALERT_WINDOW * 2));
+
+        for _ in 2..ALERT_COUNT {
+            assert!(!events.add(event.clone(), start + ALERT_WINDOW * 2));
+        }
+
+        assert!(events.add(event.clone(), start + ALERT_WINDOW * 2));
+        assert_eq!(events.history.len(), ALERT_COUNT * 2 - 1);
+    }
+}
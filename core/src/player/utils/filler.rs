@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use crate::player::utils::Media;
+use crate::utils::config::Storage;
+
+/// Rules for choosing clips from the filler folder.
+///
+/// Clips in sub folders of the filler folder get the folder name as category.
+#[derive(Debug, Clone, Default)]
+pub struct FillerRules {
+    /// How often one clip can play per day, 0 is unlimited.
+    pub max_repeat: usize,
+    /// Prefer clips which fit completely into the gap.
+    pub fit_duration: bool,
+    /// Categories which are never used as filler.
+    pub exclude: Vec<String>,
+}
+
+impl FillerRules {
+    pub fn new(storage: &Storage) -> Self {
+        Self {
+            max_repeat: storage.filler_max_repeat.max(0) as usize,
+            fit_duration: storage.filler_fit,
+            exclude: storage
+                .filler_exclude
+                .split([';', ','])
+                .map(|c| c.trim().to_lowercase())
+                .filter(|c| !c.is_empty())
+                .collect(),
+        }
+    }
+
+    fn is_excluded(&self, media: &Media) -> bool {
+        !media.category.is_empty() && self.exclude.contains(&media.category.to_lowercase())
+    }
+}
+
+/// Played filler clips from one day.
+#[derive(Debug, Clone, Default)]
+pub struct FillerHistory {
+    date: String,
+    plays: HashMap<String, usize>,
+    last: Option<String>,
+}
+
+impl FillerHistory {
+    pub fn new(date: &str) -> Self {
+        Self {
+            date: date.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Start counting again, when the day changes.
+    pub fn set_date(&mut self, date: &str) {
+        if self.date != date {
+            self.date = date.to_string();
+            self.plays.clear();
+        }
+    }
+
+    pub fn plays(&self, source: &str) -> usize {
+        self.plays.get(source).copied().unwrap_or_default()
+    }
+
+    /// Choose the next filler for a gap with the given length and count the play.
+    ///
+    /// Clips are checked in list order, beginning with `start`. When all clips reached
+    /// the repeat limit, the least played ones are used again.
+    pub fn select(
+        &mut self,
+        fillers: &[Media],
+        rules: &FillerRules,
+        start: usize,
+        gap: f64,
+    ) -> Option<usize> {
+        let len = fillers.len();
+        let allowed: Vec<usize> = (0..len)
+            .map(|i| (start + i) % len)
+            .filter(|i| !rules.is_excluded(&fillers[*i]))
+            .collect();
+
+        let mut candidates: Vec<usize> = allowed
+            .iter()
+            .copied()
+            .filter(|i| rules.max_repeat == 0 || self.plays(&fillers[*i].source) < rules.max_repeat)
+            .collect();
+
+        if candidates.is_empty() {
+            let min = allowed
+                .iter()
+                .map(|i| self.plays(&fillers[*i].source))
+                .min()?;
+
+            candidates = allowed
+                .into_iter()
+                .filter(|i| self.plays(&fillers[*i].source) == min)
+                .collect();
+        }
+
+        if candidates
+            .iter()
+            .any(|i| Some(&fillers[*i].source) != self.last.as_ref())
+        {
+            candidates.retain(|i| Some(&fillers[*i].source) != self.last.as_ref());
+        }
+
+        let index = if rules.fit_duration {
+            candidates
+                .iter()
+                .find(|i| fillers[**i].duration > 0.0 && fillers[**i].duration <= gap)
+                .or(candidates.first())
+        } else {
+            candidates.first()
+        }
+        .copied()?;
+
+        let source = fillers[index].source.clone();
+
+        *self.plays.entry(source.clone()).or_default() += 1;
+        self.last = Some(source);
+
+        Some(index)
+    }
+}
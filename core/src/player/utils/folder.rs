@@ -185,7 +185,19 @@ pub fn fill_filler_list(
         {
             let mut media = Media::new(index, &entry.path().to_string_lossy(), false);
 
-            if fillers.is_none() {
+            // clips in sub folders get the folder name as category, for the filler rules
+            if let Some(folder) = entry
+                .path()
+                .strip_prefix(filler_path)
+                .ok()
+                .and_then(|p| p.parent())
+                .and_then(|p| p.components().next())
+            {
+                media.category = folder.as_os_str().to_string_lossy().to_string();
+            }
+
+            // durations are needed for fitting fillers into gaps
+            if fillers.is_none() || config.storage.filler_fit {
                 if let Err(e) = media.add_probe(false) {
                     error!(target: Target::file_mail(), channel = id; "{e:?}");
                 };
@@ -0,0 +1,279 @@
+/// Import text/m3u or csv file and create a playlist out of it
+use std::{
+    //error::Error,
+    fs::{create_dir_all, File},
+    io::{BufRead, BufReader, Error, ErrorKind},
+    path::Path,
+};
+
+use crate::player::utils::{
+    chapters::split_by_chapters, file_extension, is_remote, json_reader,
+    json_serializer::JsonPlaylist, json_writer, time_to_sec, Media, PlayoutConfig,
+};
+
+/// Split a csv line, fields can be quoted and have `""` for a quote.
+pub fn split_csv(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+
+    fields.push(field);
+
+    fields.into_iter().map(|f| f.trim().to_string()).collect()
+}
+
+/// Seconds from `hh:mm:ss.ms` or a plain number.
+fn csv_seconds(value: &str) -> Option<f64> {
+    if value.contains(':') {
+        Some(time_to_sec(value))
+    } else {
+        value.parse().ok()
+    }
+}
+
+/// Relative sources are in the `base` folder.
+fn resolve_source(base: &Path, source: &str) -> String {
+    if Path::new(source).is_absolute() || is_remote(source) {
+        source.to_string()
+    } else {
+        base.join(source).to_string_lossy().to_string()
+    }
+}
+
+/// Items from a csv file, with a header line. The column `source` (or `file`, `path`) is needed,
+/// `title`, `category`, `in`, `out` and `duration` are optional.
+fn read_csv(reader: BufReader<File>, base: &Path) -> Result<Vec<Media>, Error> {
+    let mut lines = reader.lines();
+    let header = lines.next().transpose()?.unwrap_or_default();
+    let delimiter = if header.contains(';') { ';' } else { ',' };
+    let columns: Vec<String> = split_csv(header.trim_start_matches('\u{feff}'), delimiter)
+        .iter()
+        .map(|c| c.to_lowercase())
+        .collect();
+    let column = |names: &[&str]| columns.iter().position(|c| names.contains(&c.as_str()));
+    let source_col = column(&["source", "file", "path"]).ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidData,
+            "CSV needs a source, file or path column",
+        )
+    })?;
+    let title_col = column(&["title"]);
+    let category_col = column(&["category"]);
+    let in_col = column(&["in", "seek"]);
+    let out_col = column(&["out"]);
+    let duration_col = column(&["duration"]);
+    let mut items = vec![];
+
+    for line in lines {
+        let line = line?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let fields = split_csv(&line, delimiter);
+        let field = |col: Option<usize>| {
+            col.and_then(|c| fields.get(c))
+                .filter(|f| !f.is_empty())
+                .cloned()
+        };
+        let Some(source) = field(Some(source_col)) else {
+            continue;
+        };
+        let mut item = Media::new(items.len(), &resolve_source(base, &source), true);
+
+        if item.duration <= 0.0 {
+            item.duration = field(duration_col)
+                .and_then(|d| csv_seconds(&d))
+                .unwrap_or_default();
+            item.out = item.duration;
+        }
+
+        item.title = field(title_col);
+        item.category = field(category_col).unwrap_or_default();
+
+        if let Some(seek) = field(in_col).and_then(|s| csv_seconds(&s)) {
+            item.seek = seek;
+        }
+
+        if let Some(out) = field(out_col).and_then(|o| csv_seconds(&o)) {
+            item.out = out;
+        }
+
+        if item.duration > 0.0 {
+            items.push(item);
+        }
+    }
+
+    Ok(items)
+}
+
+/// Items from a text/m3u or csv file, relative paths are in the `base` folder.
+/// Lines with leading "#" are ignored, files which can not be read are skipped.
+pub fn read_items(path: &Path, base: &Path) -> Result<Vec<Media>, Error> {
+    let file = File::open(path)?;
+    let reader = BufReader::new(file);
+
+    if file_extension(path).is_some_and(|e| e.eq_ignore_ascii_case("csv")) {
+        return read_csv(reader, base);
+    }
+
+    let mut items = vec![];
+
+    for line in reader.lines() {
+        let line = line?;
+
+        if !line.starts_with('#') && !line.trim().is_empty() {
+            let item = Media::new(0, &resolve_source(base, line.trim()), true);
+
+            if item.duration > 0.0 {
+                items.push(item);
+            }
+        }
+    }
+
+    Ok(items)
+}
+
+/// Write the items to the playlist of the date. Without `replace`, they are added
+/// to an existing playlist.
+pub fn write_items(
+    config: &PlayoutConfig,
+    date: &str,
+    channel_name: Option<String>,
+    mut items: Vec<Media>,
+    replace: bool,
+) -> Result<String, Error> {
+    let mut playlist = JsonPlaylist {
+        channel: channel_name.unwrap_or_else(|| "Channel 1".to_string()),
+        date: date.to_string(),
+        path: None,
+        start_sec: None,
+        length: None,
+        modified: None,
+        program: vec![],
+    };
+
+    let playlist_root = &config.channel.playlists;
+    if !playlist_root.is_dir() {
+        return Err(Error::other(format!(
+            "Playlist folder <b><magenta>{:?}</></b> not exists!",
+            config.channel.playlists,
+        )));
+    }
+
+    let d: Vec<&str> = date.split('-').collect();
+
+    if d.len() != 3 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Invalid date: {date}"),
+        ));
+    }
+
+    let year = d[0];
+    let month = d[1];
+    let playlist_path = playlist_root.join(year).join(month);
+    let playlist_file = &playlist_path.join(format!("{date}.json"));
+
+    create_dir_all(playlist_path)?;
+
+    let file_exists = playlist_file.is_file();
+
+    if file_exists && !replace {
+        let mut existing_data = json_reader(playlist_file)?;
+        existing_data.program.append(&mut items);
+
+        items = existing_data.program;
+    }
+
+    playlist.program = items;
+
+    let msg = if file_exists {
+        format!("Update playlist from {date} success!")
+    } else {
+        format!("Write playlist from {date} success!")
+    };
+
+    match json_writer(playlist_file, playlist) {
+        Ok(_) => Ok(msg),
+        Err(e) => Err(Error::other(e)),
+    }
+}
+
+/// With `chapters`, files are split by their chapter metadata into multiple items.
+pub fn import_file(
+    config: &PlayoutConfig,
+    date: &str,
+    channel_name: Option<String>,
+    path: &Path,
+    chapters: bool,
+) -> Result<String, Error> {
+    let mut items = vec![];
+
+    for item in read_items(path, &config.channel.storage)? {
+        if chapters {
+            items.append(&mut split_by_chapters(item));
+        } else {
+            items.push(item);
+        }
+    }
+
+    write_items(config, date, channel_name, items, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn csv_playlist_import() {
+        assert_eq!(
+            split_csv(r#"clip.mp4,"News, at 8","say ""hi""""#, ','),
+            vec!["clip.mp4", "News, at 8", r#"say "hi""#]
+        );
+
+        let dir = std::env::temp_dir().join("ffplayout_csv_import");
+        let file = dir.join("2024-10-27.csv");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            &file,
+            "Title;Source;Duration;In\n\
+        Spot;spots/a.mp4;30;\n\
+        Movie;/opt/movie.mp4;01:30:00.0;00:00:10\n\
+        \n\
+        Missing;b.mp4;;\n",
+        )
+        .unwrap();
+
+        let items = read_items(&file, Path::new("/tv-media/traffic")).unwrap();
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].source, "/tv-media/traffic/spots/a.mp4");
+        assert_eq!(items[0].title, Some("Spot".to_string()));
+        assert_eq!(items[0].out, 30.0);
+        assert_eq!(items[1].source, "/opt/movie.mp4");
+        assert_eq!(items[1].duration, 5400.0);
+        assert_eq!(items[1].seek, 10.0);
+
+        std::fs::write(&file, "name;title\n").unwrap();
+        assert!(read_items(&file, &dir).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
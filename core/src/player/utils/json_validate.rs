@@ -11,7 +11,11 @@ use std::{
 use log::*;
 use regex::Regex;
 
-use crate::player::filter::FilterType::Audio;
+use crate::player::filter::{
+    audio_layout::validate_channel_map,
+    custom::{ffmpeg_filters, validate_filter},
+    FilterType::Audio,
+};
 use crate::player::utils::{
     is_close, is_remote, loop_image, sec_to_time, seek_and_length, JsonPlaylist, Media,
 };
@@ -183,6 +187,23 @@ pub fn validate_playlist(
 
         let pos = index + 1;
 
+        // break markers are filled with spots, when the playlist is loaded
+        if item.break_name.is_some() {
+            begin += item.out - item.seek;
+            continue;
+        }
+
+        if let Err(e) = validate_filter(&item.custom_filter, ffmpeg_filters()).and_then(|_| {
+            item.channel_map
+                .as_deref()
+                .map_or(Ok(()), validate_channel_map)
+        }) {
+            error!(target: Target::file_mail(), channel = id;
+                "[Validation] Error on position <yellow>{pos:0>3}</> <yellow>{}</>: {e}",
+                sec_to_time(begin)
+            );
+        }
+
         if !is_remote(&item.source) {
             if item.audio.is_empty() {
                 if let Err(e) = item.add_probe(false) {
@@ -19,10 +19,19 @@ use reqwest::header;
 use serde::{de::Deserializer, Deserialize, Serialize};
 use serde_json::{json, Map, Value};
 
+pub mod audio_meter;
+pub mod audio_tracks;
+pub mod chapters;
+pub mod ffmpeg_events;
+pub mod filler;
 pub mod folder;
 pub mod import;
 pub mod json_serializer;
 pub mod json_validate;
+pub mod now_playing;
+pub mod passthrough;
+pub mod stream_stats;
+pub mod subtitles;
 
 use crate::player::{
     controller::{
@@ -30,14 +39,26 @@ use crate::player::{
         ProcessUnit::{self, *},
     },
     filter::{filter_chains, Filters},
+    input::{
+        air::AirStatus,
+        dead_air::{parse_dead_air, shows_info, switch_to_fallback},
+    },
 };
 use crate::utils::{
-    config::{OutputMode::*, PlayoutConfig, FFMPEG_IGNORE_ERRORS, FFMPEG_UNRECOVERABLE_ERRORS},
+    config::{
+        AspectPolicy, OutputMode::*, PlayoutConfig, Transition, FFMPEG_IGNORE_ERRORS,
+        FFMPEG_UNRECOVERABLE_ERRORS,
+    },
     errors::ProcessError,
     logging::Target,
+    maintenance::in_maintenance,
     time_machine::time_now,
 };
+use audio_meter::{ingest_on_air, record_meter};
+use ffmpeg_events::record_event;
 pub use json_serializer::{read_json, JsonPlaylist};
+use stream_stats::{record_progress, stop_stream};
+use subtitles::subtitle_file;
 
 use crate::vec_strings;
 
@@ -124,7 +145,7 @@ pub fn prepare_output_cmd(
         }
     }
 
-    if config.processing.vtt_enable && vtt_dummy.is_file() {
+    if config.processing.subtitle_passthrough() && vtt_dummy.is_file() {
         let i = cmd.iter().filter(|&n| n == "-i").count().saturating_sub(1);
 
         cmd.append(&mut vec_strings!("-map", format!("{i}:s?")));
@@ -165,9 +186,36 @@ pub fn get_media_map(media: Media) -> Value {
         );
     }
 
+    if let Some(artwork) = media.artwork {
+        obj.as_object_mut()
+            .unwrap()
+            .insert("artwork".to_string(), Value::String(artwork));
+    }
+
+    if let Some(rating) = media.rating {
+        obj.as_object_mut()
+            .unwrap()
+            .insert("rating".to_string(), Value::String(rating));
+    }
+
     obj
 }
 
+/// Convert markdown description to plain text, for targets which can not render markdown.
+pub fn markdown_to_text(text: &str) -> String {
+    let image = Regex::new(r"!\[([^\]]*)\]\([^)]*\)").unwrap();
+    let link = Regex::new(r"\[([^\]]*)\]\([^)]*\)").unwrap();
+    let block = Regex::new(r"(?m)^[ \t]{0,3}(#{1,6}[ \t]+|>[ \t]?|[-*+][ \t]+)").unwrap();
+    let emphasis = Regex::new(r"(\*\*|__|\*|~~|`)").unwrap();
+
+    let text = image.replace_all(text, "$1");
+    let text = link.replace_all(&text, "$1");
+    let text = block.replace_all(&text, "");
+    let text = emphasis.replace_all(&text, "");
+
+    text.trim().to_string()
+}
+
 /// prepare json object for response
 pub fn get_data_map(manager: &ChannelManager) -> Map<String, Value> {
     let media = manager
@@ -179,15 +227,29 @@ pub fn get_data_map(manager: &ChannelManager) -> Map<String, Value> {
     let channel = manager.channel.lock().unwrap().clone();
     let config = manager.config.lock().unwrap().processing.clone();
     let ingest_is_running = manager.ingest_is_running.load(Ordering::SeqCst);
+    let dead_air = manager.dead_air.lock().unwrap().clone();
+    let input_switch = manager.input_switch.lock().unwrap().clone();
+    let emergency = manager.emergency.lock().unwrap().clone();
+    let encoder = manager.encoder_status.lock().unwrap().clone();
+    let delayed = manager.delayed_status.lock().unwrap().clone();
+    let air_hours = manager.config.lock().unwrap().general.air_hours.clone();
 
     let mut data_map = Map::new();
     let current_time = time_in_seconds();
     let shift = channel.time_shift;
     let begin = media.begin.unwrap_or(0.0) - shift;
     let played_time = current_time - begin;
+    let air = AirStatus::new(&air_hours, current_time);
 
     data_map.insert("index".to_string(), json!(media.index));
     data_map.insert("ingest".to_string(), json!(ingest_is_running));
+    data_map.insert("dead_air".to_string(), json!(dead_air));
+    data_map.insert("input".to_string(), json!(input_switch));
+    data_map.insert("override".to_string(), json!(emergency));
+    data_map.insert("encoder".to_string(), json!(encoder));
+    data_map.insert("delayed".to_string(), json!(delayed));
+    data_map.insert("air".to_string(), json!(air));
+    data_map.insert("maintenance".to_string(), json!(in_maintenance(channel.id)));
     data_map.insert("mode".to_string(), json!(config.mode));
     data_map.insert(
         "shift".to_string(),
@@ -203,6 +265,18 @@ pub fn get_data_map(manager: &ChannelManager) -> Map<String, Value> {
     data_map
 }
 
+/// Crossfade from a clip into the next one.
+#[derive(Debug, Clone)]
+pub struct Crossfade {
+    pub source: String,
+    pub seek: f64,
+    /// Length in seconds.
+    pub duration: f64,
+    pub has_audio: bool,
+    /// Input index in the decoder command.
+    pub input: usize,
+}
+
 /// Video clip struct to hold some important states and comments for current media.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Media {
@@ -231,6 +305,21 @@ pub struct Media {
     pub description: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub enable_description: Option<bool>,
+    /// Image path, relative to the channel storage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artwork: Option<String>,
+    /// Parental rating, like `PG-13` or `16`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<String>,
+    /// Subtitle file, relative to the channel storage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtitle: Option<String>,
+    /// Source audio tracks for the output tracks, -1 is silence.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_tracks: Option<Vec<i32>>,
+    /// Channel map for the audio tracks, like `c0=c1|c1=c1`, instead of the downmix from the config.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_map: Option<String>,
     #[serde(deserialize_with = "null_string")]
     pub source: String,
 
@@ -250,6 +339,36 @@ pub struct Media {
     #[serde(default, skip_serializing_if = "is_empty_string")]
     pub custom_filter: String,
 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transition: Option<Transition>,
+
+    /// Aspect policy for this item, instead of the one from the config.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aspect_policy: Option<AspectPolicy>,
+
+    /// Length of the SCTE-35 ad break, which starts with this item.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ad_break: Option<f64>,
+
+    /// Name of a commercial break, the item is replaced with spots when the playlist is loaded.
+    #[serde(default, rename = "break", skip_serializing_if = "Option::is_none")]
+    pub break_name: Option<String>,
+
+    /// Break, which the spot fills, for the regional outputs.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub window: Option<String>,
+
+    #[serde(skip_serializing, skip_deserializing)]
+    pub crossfade: Option<Crossfade>,
+
+    /// Clip starts inside the crossfade from the previous clip.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub crossfaded: bool,
+
+    /// Fade in length, after a manual source switch.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub fade_in: Option<f64>,
+
     #[serde(skip_serializing, skip_deserializing)]
     pub probe: Option<MediaProbe>,
 
@@ -298,11 +417,24 @@ impl Media {
             category: String::new(),
             description: None,
             enable_description: None,
+            artwork: None,
+            rating: None,
+            subtitle: None,
+            audio_tracks: None,
+            channel_map: None,
             source: src.to_string(),
             audio: String::new(),
             cmd: Some(vec_strings!["-i", src]),
             filter: None,
             custom_filter: String::new(),
+            transition: None,
+            aspect_policy: None,
+            ad_break: None,
+            break_name: None,
+            window: None,
+            crossfade: None,
+            crossfaded: false,
+            fade_in: None,
             probe,
             probe_audio: None,
             last_ad: false,
@@ -381,8 +513,16 @@ impl PartialEq for Media {
             && self.category == other.category
             && self.description == other.description
             && self.enable_description == other.enable_description
+            && self.artwork == other.artwork
+            && self.rating == other.rating
+            && self.subtitle == other.subtitle
             && self.audio == other.audio
+            && self.channel_map == other.channel_map
             && self.custom_filter == other.custom_filter
+            && self.transition == other.transition
+            && self.aspect_policy == other.aspect_policy
+            && self.ad_break == other.ad_break
+            && self.break_name == other.break_name
     }
 }
 
@@ -406,6 +546,7 @@ pub struct MediaProbe {
     pub format: ffprobe::Format,
     pub audio_streams: Vec<FFStream>,
     pub video_streams: Vec<FFStream>,
+    pub subtitle_streams: Vec<FFStream>,
 }
 
 impl MediaProbe {
@@ -413,6 +554,7 @@ impl MediaProbe {
         let probe = ffprobe(input);
         let mut a_stream = vec![];
         let mut v_stream = vec![];
+        let mut s_stream = vec![];
 
         match probe {
             Ok(obj) => {
@@ -423,6 +565,7 @@ impl MediaProbe {
                         match c_type.as_str() {
                             "audio" => a_stream.push(stream),
                             "video" => v_stream.push(stream),
+                            "subtitle" => s_stream.push(stream),
                             _ => {}
                         }
                     } else {
@@ -434,6 +577,7 @@ impl MediaProbe {
                     format: obj.format,
                     audio_streams: a_stream,
                     video_streams: v_stream,
+                    subtitle_streams: s_stream,
                 })
             }
             Err(e) => {
@@ -642,8 +786,8 @@ pub fn loop_image(config: &PlayoutConfig, node: &Media) -> Vec<String> {
 
     source_cmd.append(&mut vec_strings!["-t", duration]);
 
-    if config.processing.vtt_enable {
-        let vtt_file = Path::new(&node.source).with_extension("vtt");
+    if config.processing.subtitle_passthrough() {
+        let vtt_file = subtitle_file(config, node);
         let vtt_dummy = config
             .channel
             .storage
@@ -653,7 +797,7 @@ pub fn loop_image(config: &PlayoutConfig, node: &Media) -> Vec<String> {
             source_cmd.append(&mut vec_strings!["-ss", node.seek]);
         }
 
-        if vtt_file.is_file() {
+        if let Some(vtt_file) = vtt_file {
             source_cmd.append(&mut vec_strings![
                 "-i",
                 vtt_file.to_string_lossy(),
@@ -683,14 +827,14 @@ pub fn loop_filler(config: &PlayoutConfig, node: &Media) -> Vec<String> {
 
     source_cmd.append(&mut vec_strings!["-i", node.source, "-t", node.out]);
 
-    if config.processing.vtt_enable {
-        let vtt_file = Path::new(&node.source).with_extension("vtt");
+    if config.processing.subtitle_passthrough() {
+        let vtt_file = subtitle_file(config, node);
         let vtt_dummy = config
             .channel
             .storage
             .join(config.processing.vtt_dummy.clone().unwrap_or_default());
 
-        if vtt_file.is_file() {
+        if let Some(vtt_file) = vtt_file {
             if loop_count > 1 {
                 source_cmd.append(&mut vec_strings!["-stream_loop", loop_count]);
             }
@@ -757,8 +901,23 @@ pub fn seek_and_length(config: &PlayoutConfig, node: &mut Media) -> Vec<String>
         }
     }
 
-    if config.processing.vtt_enable {
-        let vtt_file = Path::new(&node.source).with_extension("vtt");
+    if let Some(crossfade) = node.crossfade.as_mut() {
+        crossfade.input = source_cmd.iter().filter(|a| *a == "-i").count();
+
+        if crossfade.seek > 0.0 {
+            source_cmd.append(&mut vec_strings!["-ss", crossfade.seek]);
+        }
+
+        source_cmd.append(&mut vec_strings![
+            "-t",
+            crossfade.duration,
+            "-i",
+            crossfade.source.clone()
+        ]);
+    }
+
+    if config.processing.subtitle_passthrough() {
+        let vtt_file = subtitle_file(config, node);
         let vtt_dummy = config
             .channel
             .storage
@@ -768,7 +927,7 @@ pub fn seek_and_length(config: &PlayoutConfig, node: &mut Media) -> Vec<String>
             source_cmd.append(&mut vec_strings!["-ss", node.seek]);
         }
 
-        if vtt_file.is_file() {
+        if let Some(vtt_file) = vtt_file {
             if loop_count > 1 {
                 source_cmd.append(&mut vec_strings!["-stream_loop", loop_count]);
             }
@@ -809,7 +968,7 @@ pub fn gen_dummy(config: &PlayoutConfig, duration: f64) -> (String, Vec<String>)
         format!("anoisesrc=d={duration}:c=pink:r=48000:a=0.3")
     ];
 
-    if config.processing.vtt_enable {
+    if config.processing.subtitle_passthrough() {
         let vtt_dummy = config
             .channel
             .storage
@@ -898,16 +1057,41 @@ pub fn include_file_extension(config: &PlayoutConfig, file_path: &Path) -> bool
 
 /// Read ffmpeg stderr decoder and encoder instance
 /// and log the output.
+///
+/// With a `stats` name the progress lines go to the stream stats.
 pub fn stderr_reader(
     buffer: BufReader<ChildStderr>,
     ignore: Vec<String>,
     suffix: ProcessUnit,
+    stats: Option<String>,
     manager: ChannelManager,
 ) -> Result<(), ProcessError> {
     let id = manager.channel.lock().unwrap().id;
+    let log_info = shows_info(&manager.config.lock().unwrap().logging.ffmpeg_level);
+
     for line in buffer.lines() {
         let line = line?;
 
+        if stats
+            .as_ref()
+            .is_some_and(|name| record_progress(&manager, name, &line))
+        {
+            continue;
+        }
+
+        if suffix == Decoder && record_meter(&manager, &line, !ingest_on_air(&manager)) {
+            continue;
+        }
+
+        record_event(&manager, suffix, &line);
+
+        if suffix == Decoder {
+            if let Some((kind, start)) = parse_dead_air(&line) {
+                switch_to_fallback(&manager, kind, start);
+                continue;
+            }
+        }
+
         if FFMPEG_IGNORE_ERRORS.iter().any(|i| line.contains(*i))
             || ignore.iter().any(|i| line.contains(i))
         {
@@ -915,10 +1099,13 @@ pub fn stderr_reader(
         }
 
         if line.contains("[info]") {
-            info!(target: Target::file_mail(), channel = id;
-                "<bright black>[{suffix}]</> {}",
-                line.replace("[info] ", "")
-            );
+            // decoder can run on info level, for the dead-air detection
+            if log_info {
+                info!(target: Target::file_mail(), channel = id;
+                    "<bright black>[{suffix}]</> {}",
+                    line.replace("[info] ", "")
+                );
+            }
         } else if line.contains("[warning]") {
             warn!(target: Target::file_mail(), channel = id;
                 "<bright black>[{suffix}]</> {}",
@@ -936,6 +1123,11 @@ pub fn stderr_reader(
                 || (line.contains("No such file or directory")
                     && !line.contains("failed to delete old segment"))
             {
+                // with a backup encoder the output switches over, instead of stopping the channel
+                if suffix == Encoder && manager.encoder_status.lock().unwrap().redundant {
+                    continue;
+                }
+
                 error!(target: Target::file_mail(), channel = id; "Hit unrecoverable error!");
                 manager.channel.lock().unwrap().active = false;
                 manager.stop_all();
@@ -943,6 +1135,10 @@ pub fn stderr_reader(
         }
     }
 
+    if let Some(name) = stats {
+        stop_stream(&manager, &name);
+    }
+
     Ok(())
 }
 
@@ -1205,3 +1401,18 @@ pub fn custom_format<T: fmt::Display>(template: &str, args: &[T]) -> String {
 
     filled_template
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_description_to_text() {
+        let text = "# Episode 1\n\nThe **first** part, see [our site](https://example.org).\n\n- one\n- `two`\n\n![poster](poster.jpg)";
+
+        assert_eq!(
+            markdown_to_text(text),
+            "Episode 1\n\nThe first part, see our site.\n\none\ntwo\n\nposter"
+        );
+    }
+}
@@ -0,0 +1,188 @@
+/*
+Current and next item of the channel, for website widgets.
+
+The player fills the cache, when a clip starts. The public now playing route reads only the cache,
+no playlist and no database, so it can take a high request volume. Elapsed and remaining time
+are computed from the start of the clip, at the time of the request.
+
+The route is open, when the channel has the public now playing enabled. With a token
+in the channel settings the request needs it, as `?token=` or as bearer token.
+
+*/
+
+use std::sync::atomic::Ordering;
+
+use chrono::{DateTime, Local, TimeDelta};
+use serde::Serialize;
+
+use crate::player::{controller::ChannelManager, output::radio::song_text, utils::Media};
+use crate::utils::{errors::ServiceError, time_machine::time_now};
+
+/// Public infos from a playlist item.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NowItem {
+    pub title: String,
+    pub category: String,
+    pub description: Option<String>,
+    pub artwork: Option<String>,
+    pub start: DateTime<Local>,
+    /// Length in seconds, from the start.
+    pub duration: f64,
+}
+
+impl NowItem {
+    pub fn new(node: &Media, start: DateTime<Local>) -> Self {
+        Self {
+            title: song_text("{title}", node),
+            category: node.category.clone(),
+            description: node.description.clone(),
+            artwork: node.artwork.clone(),
+            start,
+            duration: (node.out - node.seek).max(0.0),
+        }
+    }
+
+    pub fn end(&self) -> DateTime<Local> {
+        self.start + TimeDelta::milliseconds((self.duration * 1000.0) as i64)
+    }
+}
+
+/// Response of the now playing route.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct NowNext {
+    pub current: Option<NowItem>,
+    pub next: Option<NowItem>,
+    /// Seconds since the start of the current item.
+    pub elapsed: f64,
+    /// Seconds until the end of the current item.
+    pub remaining: f64,
+}
+
+/// Cache with the current and the next item.
+#[derive(Debug, Clone, Default)]
+pub struct NowPlaying {
+    current: Option<NowItem>,
+    next: Option<NowItem>,
+}
+
+impl NowPlaying {
+    /// Take the item, which starts now, and the item after it.
+    pub fn update(&mut self, node: &Media, next: Option<&Media>, now: DateTime<Local>) {
+        let current = NowItem::new(node, now);
+
+        self.next = next.map(|n| NowItem::new(n, current.end()));
+        self.current = Some(current);
+    }
+
+    /// Current and next item, with the times at `now`.
+    pub fn at(&self, now: DateTime<Local>) -> NowNext {
+        let Some(current) = &self.current else {
+            return NowNext::default();
+        };
+        let elapsed = (now - current.start).num_milliseconds() as f64 / 1000.0;
+
+        NowNext {
+            current: Some(current.clone()),
+            next: self.next.clone(),
+            elapsed: elapsed.clamp(0.0, current.duration),
+            remaining: (current.duration - elapsed).clamp(0.0, current.duration),
+        }
+    }
+}
+
+/// Fill the cache with the item, which starts now.
+pub fn update_now_playing(manager: &ChannelManager, node: &Media) {
+    let next = node
+        .index
+        .and_then(|i| manager.current_list.lock().unwrap().get(i + 1).cloned());
+
+    manager
+        .now_playing
+        .lock()
+        .unwrap()
+        .update(node, next.as_ref(), time_now());
+}
+
+/// Current and next item, nothing when the channel is not running.
+pub fn now_next(manager: &ChannelManager) -> NowNext {
+    if !manager.is_alive.load(Ordering::SeqCst) {
+        return NowNext::default();
+    }
+
+    manager.now_playing.lock().unwrap().at(time_now())
+}
+
+/// Check the access to the now playing route, `given` is the token from the request.
+pub fn check_access(enable: bool, token: &str, given: Option<&str>) -> Result<(), ServiceError> {
+    if !enable {
+        return Err(ServiceError::Forbidden(
+            "Now playing is not public".to_string(),
+        ));
+    }
+
+    let token = token.trim();
+
+    if !token.is_empty() && given.is_none_or(|t| t.trim() != token) {
+        return Err(ServiceError::Unauthorized("Invalid token".to_string()));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeDelta, TimeZone};
+
+    use super::*;
+    use crate::utils::errors::ServiceError;
+
+    #[test]
+    fn now_playing_cache() {
+        let start = Local.with_ymd_and_hms(2024, 6, 13, 14, 0, 0).unwrap();
+        let mut current = Media::new(0, "/media/show.mp4", false);
+        current.seek = 100.0;
+        current.out = 1900.0;
+        current.title = Some("Show".to_string());
+        let mut next = Media::new(1, "/media/news.mp4", false);
+        next.out = 600.0;
+        next.category = "news".to_string();
+
+        let mut cache = NowPlaying::default();
+
+        assert!(cache.at(start).current.is_none());
+
+        cache.update(&current, Some(&next), start);
+
+        let data = cache.at(start + TimeDelta::seconds(300));
+        let item = data.current.unwrap();
+        let next_item = data.next.unwrap();
+
+        assert_eq!(item.title, "Show");
+        assert_eq!(item.duration, 1800.0);
+        assert_eq!(data.elapsed, 300.0);
+        assert_eq!(data.remaining, 1500.0);
+        assert_eq!(next_item.title, "news");
+        assert_eq!(next_item.category, "news");
+        assert_eq!(next_item.start, start + TimeDelta::seconds(1800));
+
+        let late = cache.at(start + TimeDelta::seconds(2000));
+
+        assert_eq!(late.elapsed, 1800.0);
+        assert_eq!(late.remaining, 0.0);
+
+        assert!(check_access(true, "", None).is_ok());
+        assert!(check_access(true, "secret", Some("secret")).is_ok());
+        assert!(matches!(
+            check_access(true, "secret", Some("other")),
+            Err(ServiceError::Unauthorized(_))
+        ));
+        assert!(matches!(
+            check_access(true, "secret", None),
+            Err(ServiceError::Unauthorized(_))
+        ));
+        assert!(matches!(
+            check_access(false, "", None),
+            Err(ServiceError::Forbidden(_))
+        ));
+    }
+}
@@ -0,0 +1,264 @@
+/*
+Passthrough of clips in the house format.
+
+With `passthrough` the decoder remuxes clips, which have the house format, without encoding.
+All other clips are encoded to the house format, so the encoder gets the same format from
+every clip and can copy the streams too.
+
+The format of a file is probed only once, as long as the file is not changed.
+
+*/
+
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{LazyLock, Mutex},
+};
+
+use crate::player::{
+    controller::ProcessUnit::Decoder,
+    utils::{fps_calc, is_close, Media, MediaProbe},
+};
+use crate::utils::{
+    config::{PlayoutConfig, Processing, TransitionKind},
+    thumbnails::modified,
+};
+use crate::vec_strings;
+
+/// Probed formats by file, with the modification time.
+static FORMATS: LazyLock<Mutex<HashMap<String, (u64, ClipFormat)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Format of the first video and audio stream from a clip.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClipFormat {
+    pub video_codec: Option<String>,
+    pub width: i64,
+    pub height: i64,
+    pub fps: f64,
+    pub pix_fmt: String,
+    pub audio_codec: Option<String>,
+    pub sample_rate: i64,
+    pub channels: i64,
+}
+
+impl ClipFormat {
+    pub fn new(probe: &MediaProbe) -> Self {
+        let mut format = Self::default();
+
+        if let Some(video) = probe.video_streams.first() {
+            format.video_codec.clone_from(&video.codec_name);
+            format.width = video.width.unwrap_or_default();
+            format.height = video.height.unwrap_or_default();
+            format.fps = fps_calc(&video.r_frame_rate, 0.0);
+            format.pix_fmt = video.pix_fmt.clone().unwrap_or_default();
+        }
+
+        if let Some(audio) = probe.audio_streams.first() {
+            format.audio_codec.clone_from(&audio.codec_name);
+            format.sample_rate = audio
+                .sample_rate
+                .as_deref()
+                .and_then(|r| r.parse().ok())
+                .unwrap_or_default();
+            format.channels = audio.channels.unwrap_or_default();
+        }
+
+        format
+    }
+}
+
+/// Codec name from ffprobe for an ffmpeg encoder.
+pub fn codec_name(encoder: &str) -> &str {
+    match encoder {
+        e if e.starts_with("libx264") || e.starts_with("h264") => "h264",
+        e if e.starts_with("libx265") || e.starts_with("hevc") => "hevc",
+        e if e.starts_with("libvpx-vp9") || e.starts_with("vp9") => "vp9",
+        e if e.starts_with("libaom-av1") || e.starts_with("libsvtav1") || e.starts_with("av1") => {
+            "av1"
+        }
+        "libfdk_aac" => "aac",
+        "libopus" => "opus",
+        "libmp3lame" => "mp3",
+        e => e,
+    }
+}
+
+/// Differences between the clip format and the house format, empty when the clip can be copied.
+pub fn format_mismatch(config: &PlayoutConfig, format: &ClipFormat) -> Vec<String> {
+    let processing = &config.processing;
+    let house = &processing.house_format;
+    let mut mismatch = vec![];
+
+    if !processing.audio_only {
+        let video_codec = codec_name(&house.video_codec);
+
+        match &format.video_codec {
+            Some(codec) if codec == video_codec => {}
+            Some(codec) => mismatch.push(format!("video codec {codec}")),
+            None => mismatch.push("no video".to_string()),
+        }
+
+        if format.width != processing.width || format.height != processing.height {
+            mismatch.push(format!("size {}x{}", format.width, format.height));
+        }
+
+        if !is_close(format.fps, processing.fps, 0.01) {
+            mismatch.push(format!("frame rate {:.3}", format.fps));
+        }
+
+        if format.pix_fmt != "yuv420p" {
+            mismatch.push(format!("pixel format {}", format.pix_fmt));
+        }
+    }
+
+    let audio_codec = codec_name(&house.audio_codec);
+
+    match &format.audio_codec {
+        Some(codec) if codec == audio_codec => {}
+        Some(codec) => mismatch.push(format!("audio codec {codec}")),
+        None => mismatch.push("no audio".to_string()),
+    }
+
+    if format.audio_codec.is_some() && format.sample_rate != 48000 {
+        mismatch.push(format!("sample rate {}", format.sample_rate));
+    }
+
+    if format.audio_codec.is_some() && format.channels != i64::from(processing.audio_channels) {
+        mismatch.push(format!("{} audio channels", format.channels));
+    }
+
+    mismatch
+}
+
+/// Settings, which need filters on every clip and make copying impossible.
+pub fn global_filters(config: &PlayoutConfig) -> bool {
+    let processing = &config.processing;
+
+    processing.add_logo
+        || !processing.logos.is_empty()
+        || processing.subtitle_burn
+        || processing.transition != TransitionKind::None
+        || processing.audio_tracks > 1
+        || processing.volume != 1.0
+        || !processing.custom_filter.is_empty()
+        || config.text.add_text
+}
+
+/// The clip is played without changes: no seek, separate audio, filter or fade.
+fn unchanged(node: &Media) -> bool {
+    node.unit == Decoder
+        && node.seek == 0.0
+        && node.audio.is_empty()
+        && node.custom_filter.is_empty()
+        && node.transition.is_none()
+        && node.crossfade.is_none()
+        && !node.crossfaded
+        && node.fade_in.is_none()
+        && node.subtitle.is_none()
+}
+
+/// Format of the clip, from the cache or probed.
+pub fn clip_format(node: &Media) -> Option<ClipFormat> {
+    let path = Path::new(&node.source);
+
+    if !path.is_file() {
+        return None;
+    }
+
+    let time = modified(path);
+    let mut formats = FORMATS.lock().unwrap();
+
+    if let Some((_, format)) = formats.get(&node.source).filter(|(t, _)| *t == time) {
+        return Some(format.clone());
+    }
+
+    let format = match &node.probe {
+        Some(probe) => ClipFormat::new(probe),
+        None => ClipFormat::new(&MediaProbe::new(&node.source).ok()?),
+    };
+
+    formats.insert(node.source.clone(), (time, format.clone()));
+
+    Some(format)
+}
+
+/// Why the clip has to be encoded, `None` when it can be copied.
+pub fn transcode_reason(config: &PlayoutConfig, node: &Media) -> Option<String> {
+    if global_filters(config) {
+        return Some("filters in processing settings".to_string());
+    }
+
+    if !unchanged(node) {
+        return Some("clip needs filters".to_string());
+    }
+
+    let Some(format) = clip_format(node) else {
+        return Some("no local file".to_string());
+    };
+
+    let mismatch = format_mismatch(config, &format);
+
+    if mismatch.is_empty() {
+        None
+    } else {
+        Some(mismatch.join(", "))
+    }
+}
+
+/// Decoder output to copy the streams of a clip.
+pub fn copy_args(processing: &Processing) -> Vec<String> {
+    if processing.audio_only {
+        vec_strings!["-map", "0:a:0", "-vn", "-c:a", "copy", "-f", "mpegts", "-"]
+    } else {
+        vec_strings![
+            "-map",
+            "0:v:0",
+            "-map",
+            "0:a:0",
+            "-c",
+            "copy",
+            "-mpegts_flags",
+            "initial_discontinuity",
+            "-f",
+            "mpegts",
+            "-"
+        ]
+    }
+}
+
+/// Video encoder for clips, which are not in the house format.
+pub fn house_video_args(processing: &Processing) -> Vec<String> {
+    let house = &processing.house_format;
+
+    vec_strings![
+        "-pix_fmt",
+        "yuv420p",
+        "-r",
+        processing.fps,
+        "-c:v",
+        house.video_codec,
+        "-preset",
+        house.preset,
+        "-crf",
+        house.crf,
+        "-mpegts_flags",
+        "initial_discontinuity"
+    ]
+}
+
+/// Audio encoder for clips, which are not in the house format.
+pub fn house_audio_args(processing: &Processing) -> Vec<String> {
+    let house = &processing.house_format;
+
+    vec_strings![
+        "-c:a",
+        house.audio_codec,
+        "-b:a",
+        house.audio_bitrate,
+        "-ar",
+        "48000",
+        "-ac",
+        processing.audio_channels
+    ]
+}
@@ -0,0 +1,273 @@
+/*
+Health of the ingest and the outputs.
+
+The ffmpeg instances from the ingest, the encoders and the regional outputs write their progress
+with `-progress pipe:2` to stderr, between the log lines. The progress lines are `key=value` pairs,
+log lines have the level in front, so they can not be mixed up.
+
+From the progress every connection gets the current bitrate and fps, the dropped frames and the
+time of the last packet. A reconnect is counted, when data flows again after a connection was lost.
+
+*/
+
+use std::{collections::BTreeMap, time::Instant};
+
+use serde::Serialize;
+
+use crate::player::controller::ChannelManager;
+use crate::utils::time_machine::time_now;
+use crate::vec_strings;
+
+/// Name of the ingest in the stats.
+pub const INGEST: &str = "ingest";
+
+/// Interval in seconds in which ffmpeg writes the progress.
+const PROGRESS_PERIOD: &str = "1";
+
+/// Arguments, which let ffmpeg write its progress to stderr.
+pub fn progress_args() -> Vec<String> {
+    vec_strings!["-progress", "pipe:2", "-stats_period", PROGRESS_PERIOD]
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StreamStats {
+    /// Data is flowing.
+    pub connected: bool,
+    /// Current bitrate in kbit/s.
+    pub bitrate: Option<f64>,
+    pub fps: Option<f64>,
+    pub speed: Option<f64>,
+    pub frames: u64,
+    pub total_size: u64,
+    /// Dropped frames, since the start of the channel.
+    pub dropped_frames: u64,
+    /// Duplicated frames, since the start of the channel.
+    pub duplicated_frames: u64,
+    pub reconnects: u32,
+    pub last_packet: Option<String>,
+    /// Seconds since the last packet.
+    pub since_last_packet: Option<f64>,
+    #[serde(skip)]
+    packet_at: Option<Instant>,
+    /// Dropped and duplicated frames from the previous processes.
+    #[serde(skip)]
+    base: (u64, u64),
+    #[serde(skip)]
+    was_connected: bool,
+}
+
+impl StreamStats {
+    /// New ffmpeg process, its counters start again by zero.
+    pub fn start(&mut self) {
+        self.base = (self.dropped_frames, self.duplicated_frames);
+        self.frames = 0;
+        self.total_size = 0;
+    }
+
+    /// Connection is lost, or the process has ended.
+    pub fn stop(&mut self) {
+        self.connected = false;
+        self.bitrate = None;
+        self.fps = None;
+        self.speed = None;
+    }
+
+    fn packet(&mut self, now: Instant) {
+        if !self.connected {
+            if self.was_connected {
+                self.reconnects += 1;
+            }
+
+            self.connected = true;
+            self.was_connected = true;
+        }
+
+        self.packet_at = Some(now);
+        self.last_packet = Some(time_now().format("%Y-%m-%d %H:%M:%S").to_string());
+    }
+
+    /// Take a value from the progress.
+    pub fn update(&mut self, key: &str, value: &str, now: Instant) {
+        let value = value.trim();
+
+        match key {
+            "bitrate" => {
+                self.bitrate = value
+                    .strip_suffix("kbits/s")
+                    .and_then(|v| v.trim().parse().ok());
+            }
+            "fps" => self.fps = value.parse().ok(),
+            "speed" => {
+                self.speed = value.strip_suffix('x').and_then(|v| v.trim().parse().ok());
+            }
+            "frame" => {
+                if let Ok(frames) = value.parse() {
+                    if frames > self.frames {
+                        self.packet(now);
+                    }
+
+                    self.frames = frames;
+                }
+            }
+            "total_size" => {
+                if let Ok(size) = value.parse() {
+                    if size > self.total_size {
+                        self.packet(now);
+                    }
+
+                    self.total_size = size;
+                }
+            }
+            "drop_frames" => {
+                if let Ok(count) = value.parse::<u64>() {
+                    self.dropped_frames = self.base.0 + count;
+                }
+            }
+            "dup_frames" => {
+                if let Ok(count) = value.parse::<u64>() {
+                    self.duplicated_frames = self.base.1 + count;
+                }
+            }
+            "progress" if value == "end" => self.stop(),
+            _ => {}
+        }
+    }
+
+    /// Copy with the time since the last packet.
+    pub fn snapshot(&self, now: Instant) -> Self {
+        let mut stats = self.clone();
+
+        stats.since_last_packet = self.packet_at.map(|t| {
+            let elapsed = now.saturating_duration_since(t);
+
+            (elapsed.as_secs_f64() * 10.0).round() / 10.0
+        });
+
+        stats
+    }
+}
+
+/// Key and value from a progress line of ffmpeg, `None` for log lines.
+pub fn parse_progress(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.trim().split_once('=')?;
+
+    if key.is_empty()
+        || !key
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    {
+        return None;
+    }
+
+    Some((key, value))
+}
+
+/// Take the progress line into the stats from the stream, returns `false` for log lines.
+pub fn record_progress(manager: &ChannelManager, name: &str, line: &str) -> bool {
+    let Some((key, value)) = parse_progress(line) else {
+        return false;
+    };
+
+    manager
+        .stream_stats
+        .lock()
+        .unwrap()
+        .entry(name.to_string())
+        .or_default()
+        .update(key, value, Instant::now());
+
+    true
+}
+
+/// The ffmpeg process from the stream started.
+pub fn start_stream(manager: &ChannelManager, name: &str) {
+    manager
+        .stream_stats
+        .lock()
+        .unwrap()
+        .entry(name.to_string())
+        .or_default()
+        .start();
+}
+
+/// The ffmpeg process from the stream has ended.
+pub fn stop_stream(manager: &ChannelManager, name: &str) {
+    if let Some(stats) = manager.stream_stats.lock().unwrap().get_mut(name) {
+        stats.stop();
+    }
+}
+
+/// Stats from all streams of the channel.
+pub fn stream_stats(manager: &ChannelManager) -> BTreeMap<String, StreamStats> {
+    let now = Instant::now();
+
+    manager
+        .stream_stats
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, stats)| (name.clone(), stats.snapshot(now)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stream_health() {
+        assert_eq!(
+            parse_progress("bitrate=4521.3kbits/s"),
+            Some(("bitrate", "4521.3kbits/s"))
+        );
+        assert_eq!(
+            parse_progress("[info] Input #0, flv, from 'rtmp://...':"),
+            None
+        );
+        assert_eq!(parse_progress("[rtmp @ 0x55] [warning] key=value"), None);
+
+        let start = std::time::Instant::now();
+        let mut stats = StreamStats::default();
+
+        stats.start();
+
+        for (key, value) in [
+            ("frame", "250"),
+            ("fps", "25.00"),
+            ("bitrate", "4521.3kbits/s"),
+            ("total_size", "1048576"),
+            ("drop_frames", "3"),
+            ("speed", "1.01x"),
+        ] {
+            stats.update(key, value, start);
+        }
+
+        assert!(stats.connected);
+        assert_eq!(stats.fps, Some(25.0));
+        assert_eq!(stats.bitrate, Some(4521.3));
+        assert_eq!(stats.speed, Some(1.01));
+        assert_eq!(stats.dropped_frames, 3);
+        assert_eq!(stats.reconnects, 0);
+
+        let later = stats.snapshot(start + std::time::Duration::from_secs(5));
+        assert_eq!(later.since_last_packet, Some(5.0));
+
+        stats.update("bitrate", "N/A", start);
+        assert_eq!(stats.bitrate, None);
+
+        // connection lost, the new process counts from zero
+        stats.update("progress", "end", start);
+        assert!(!stats.connected);
+
+        stats.start();
+        stats.update("frame", "0", start);
+        assert!(!stats.connected);
+
+        stats.update("frame", "25", start);
+        stats.update("drop_frames", "1", start);
+
+        assert!(stats.connected);
+        assert_eq!(stats.reconnects, 1);
+        assert_eq!(stats.dropped_frames, 4);
+    }
+}
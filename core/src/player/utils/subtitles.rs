@@ -0,0 +1,144 @@
+/*
+Subtitles for playlist items.
+
+A subtitle comes from the playlist item (`subtitle`), from a sidecar `.vtt`/`.srt` file
+with the same name as the clip, or from the first embedded subtitle track.
+
+With `vtt_enable` the subtitle is passed through as WebVTT for HLS,
+with `subtitle_burn` it is rendered into the video by the subtitles filter.
+
+*/
+
+use std::path::{Path, PathBuf};
+
+use log::*;
+
+use crate::player::utils::Media;
+use crate::utils::{config::PlayoutConfig, logging::Target};
+use crate::vec_strings;
+
+pub const SIDECAR_EXTENSIONS: [&str; 2] = ["vtt", "srt"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Subtitle {
+    File(PathBuf),
+    /// Index of the subtitle track in the source.
+    Embedded(usize),
+}
+
+/// Subtitle path from the playlist item, relative paths are in the channel storage.
+pub fn item_subtitle(config: &PlayoutConfig, node: &Media) -> Option<PathBuf> {
+    let subtitle = node.subtitle.as_deref().filter(|s| !s.trim().is_empty())?;
+    let path = Path::new(subtitle);
+
+    if path.is_absolute() {
+        Some(path.to_path_buf())
+    } else {
+        Some(config.channel.storage.join(path))
+    }
+}
+
+/// Find the subtitle for the node.
+pub fn find_subtitle(config: &PlayoutConfig, node: &Media) -> Option<Subtitle> {
+    if let Some(path) = item_subtitle(config, node) {
+        if path.is_file() {
+            return Some(Subtitle::File(path));
+        }
+
+        warn!(target: Target::file_mail(), channel = config.general.channel_id;
+            "Subtitle <b><magenta>{}</></b> not found", path.display()
+        );
+    }
+
+    for ext in SIDECAR_EXTENSIONS {
+        let path = Path::new(&node.source).with_extension(ext);
+
+        if path.is_file() {
+            return Some(Subtitle::File(path));
+        }
+    }
+
+    node.probe
+        .as_ref()
+        .filter(|p| !p.subtitle_streams.is_empty() && Path::new(&node.source).is_file())
+        .map(|_| Subtitle::Embedded(0))
+}
+
+/// Input file for the WebVTT passthrough, embedded subtitles are read from the source.
+pub fn subtitle_file(config: &PlayoutConfig, node: &Media) -> Option<PathBuf> {
+    match find_subtitle(config, node)? {
+        Subtitle::File(path) => Some(path),
+        Subtitle::Embedded(_) => Some(PathBuf::from(&node.source)),
+    }
+}
+
+/// Map the subtitle from the last input for the WebVTT passthrough, `.vtt` files are copied.
+pub fn subtitle_map(cmd: &[String]) -> Vec<String> {
+    let inputs: Vec<&String> = cmd
+        .windows(2)
+        .filter(|w| w[0] == "-i")
+        .map(|w| &w[1])
+        .collect();
+    let index = inputs.len().saturating_sub(1);
+    let codec = if inputs.last().is_some_and(|i| i.ends_with(".vtt")) {
+        "copy"
+    } else {
+        "webvtt"
+    };
+
+    vec_strings!["-map", format!("{index}:s:0?"), "-c:s", codec]
+}
+
+/// Subtitles filter, which burns the subtitle into the video.
+///
+/// The subtitles filter reads the file by itself, so the timestamps are shifted by the seek value.
+pub fn burn_filter(node: &Media, subtitle: &Subtitle) -> String {
+    let (file, index) = match subtitle {
+        Subtitle::File(path) => (path.to_string_lossy().to_string(), None),
+        Subtitle::Embedded(i) => (node.source.clone(), Some(*i)),
+    };
+    let escaped = file
+        .replace('\\', "/")
+        .replace(':', "\\\\:")
+        .replace('\'', "\\\\\\'");
+    let mut filter = format!("subtitles=filename={escaped}");
+
+    if let Some(i) = index {
+        filter.push_str(&format!(":si={i}"));
+    }
+
+    if node.seek > 0.5 {
+        filter = format!("setpts=PTS+{}/TB,{filter},setpts=PTS-STARTPTS", node.seek);
+    }
+
+    filter
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec_strings;
+
+    #[test]
+    fn subtitle_mapping() {
+        let cmd = vec_strings!["-ss", 10, "-i", "clip.mp4", "-i", "clip.srt"];
+        assert_eq!(
+            subtitle_map(&cmd),
+            vec_strings!["-map", "1:s:0?", "-c:s", "webvtt"]
+        );
+
+        let cmd = vec_strings!["-i", "clip.mp4", "-i", "dummy.vtt"];
+        assert_eq!(
+            subtitle_map(&cmd),
+            vec_strings!["-map", "1:s:0?", "-c:s", "copy"]
+        );
+
+        let mut node = Media::new(0, "/media/clip.mkv", false);
+        node.seek = 10.0;
+
+        assert_eq!(
+            burn_filter(&node, &Subtitle::Embedded(1)),
+            "setpts=PTS+10/TB,subtitles=filename=/media/clip.mkv:si=1,setpts=PTS-STARTPTS"
+        );
+    }
+}
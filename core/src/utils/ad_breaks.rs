@@ -0,0 +1,265 @@
+/*
+Commercial breaks as sub-playlists.
+
+A playlist item with a break name is a marker, it holds the place of the break:
+
+    { "in": 0, "out": 180, "duration": 180, "source": "", "break": "prime" }
+
+When the playlist is loaded, the marker is replaced with spots from the pool folder of the
+break definition, until the target length is reached. Spots which played less often this day
+come first, one spot plays only once per break and not more then `max_plays` per day.
+The rest of the break is filled with fillers, when the break is padded.
+
+*/
+
+use std::collections::HashMap;
+
+use log::*;
+use rand::{seq::SliceRandom, thread_rng};
+use sqlx::{Pool, Sqlite};
+use walkdir::WalkDir;
+
+use crate::db::{
+    handles,
+    models::{AdBreak, SpotPlay},
+};
+use crate::player::{
+    controller::ChannelManager,
+    utils::{include_file_extension, json_serializer::set_defaults, JsonPlaylist, Media},
+};
+use crate::utils::{config::PlayoutConfig, generator::filler_list, logging::Target};
+
+/// Break definitions from one channel, with the spot pools and the plays from the current day.
+#[derive(Debug, Clone, Default)]
+pub struct AdBreaks {
+    pub breaks: Vec<AdBreak>,
+    pools: HashMap<String, Vec<Media>>,
+    date: String,
+    plays: HashMap<String, usize>,
+}
+
+impl AdBreaks {
+    pub fn new(breaks: Vec<AdBreak>) -> Self {
+        Self {
+            breaks,
+            ..Default::default()
+        }
+    }
+
+    pub fn find(&self, name: &str) -> Option<&AdBreak> {
+        self.breaks.iter().find(|b| b.name == name)
+    }
+
+    /// Plays are from this day.
+    pub fn is_date(&self, date: &str) -> bool {
+        self.date == date
+    }
+
+    /// Start counting with the plays of the day.
+    pub fn set_plays(&mut self, date: &str, plays: Vec<SpotPlay>) {
+        self.date = date.to_string();
+        self.plays = plays
+            .into_iter()
+            .map(|p| (p.source, p.plays.max(0) as usize))
+            .collect();
+    }
+
+    pub fn plays(&self, source: &str) -> usize {
+        self.plays.get(source).copied().unwrap_or_default()
+    }
+
+    /// Spots from the pool folder, they are probed only ones.
+    pub fn pool(&mut self, config: &PlayoutConfig, folder: &str) -> Vec<Media> {
+        self.pools
+            .entry(folder.to_string())
+            .or_insert_with(|| {
+                let path = config.channel.storage.join(folder.trim_start_matches('/'));
+
+                WalkDir::new(path)
+                    .into_iter()
+                    .filter_map(Result::ok)
+                    .filter(|f| f.path().is_file())
+                    .filter(|f| include_file_extension(config, f.path()))
+                    .map(|f| {
+                        let mut media = Media::new(0, &f.path().to_string_lossy(), true);
+                        media.category = "advertisement".to_string();
+                        media
+                    })
+                    .filter(|m| m.duration > 0.0)
+                    .collect()
+            })
+            .clone()
+    }
+
+    /// Choose spots for the break, the least played first.
+    pub fn select(&mut self, ad_break: &AdBreak, pool: &[Media]) -> Vec<Media> {
+        let mut candidates = pool.to_vec();
+        let mut spots = vec![];
+        let mut length = 0.0;
+
+        // random order for spots with the same plays
+        candidates.shuffle(&mut thread_rng());
+        candidates.sort_by_key(|m| self.plays(&m.source));
+
+        for spot in candidates {
+            if length + spot.duration > ad_break.duration
+                || (ad_break.max_plays > 0
+                    && self.plays(&spot.source) >= ad_break.max_plays as usize)
+            {
+                continue;
+            }
+
+            length += spot.duration;
+            *self.plays.entry(spot.source.clone()).or_default() += 1;
+            spots.push(spot);
+        }
+
+        spots
+    }
+}
+
+/// Load the break definitions of the channel from the database, call it again after a break changed.
+pub async fn load_ad_breaks(conn: &Pool<Sqlite>, manager: &ChannelManager) {
+    let id = manager.channel.lock().unwrap().id;
+
+    match handles::select_ad_breaks(conn, id).await {
+        Ok(breaks) => *manager.ad_breaks.lock().unwrap() = AdBreaks::new(breaks),
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "Unable to read ad breaks: {e}");
+        }
+    }
+}
+
+/// Replace the break markers in the playlist with spots.
+pub fn fill_breaks(manager: &ChannelManager, playlist: &mut JsonPlaylist) {
+    if !playlist.program.iter().any(|m| m.break_name.is_some()) {
+        return;
+    }
+
+    let config = manager.config.lock().unwrap().clone();
+    let id = config.general.channel_id;
+    let pool = manager.db_pool.clone();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let mut ad_breaks = manager.ad_breaks.lock().unwrap();
+    let mut program = vec![];
+    let mut played = vec![];
+
+    if !ad_breaks.is_date(&playlist.date) {
+        let plays = match &pool {
+            Some(pool) => runtime
+                .block_on(handles::select_spot_plays(pool, id, &playlist.date))
+                .unwrap_or_default(),
+            None => vec![],
+        };
+
+        ad_breaks.set_plays(&playlist.date, plays);
+    }
+
+    for item in playlist.program.drain(..) {
+        let Some(name) = item.break_name.clone() else {
+            program.push(item);
+            continue;
+        };
+
+        let Some(ad_break) = ad_breaks.find(&name).cloned() else {
+            error!(target: Target::file_mail(), channel = id; "Ad break <b><magenta>{name}</></b> not exists!");
+
+            program.push(item);
+            continue;
+        };
+
+        let start = program.len();
+        let spot_pool = ad_breaks.pool(&config, &ad_break.spots);
+        let mut spots = ad_breaks.select(&ad_break, &spot_pool);
+        let length: f64 = spots.iter().map(|s| s.duration).sum();
+
+        if spots.is_empty() {
+            warn!(target: Target::file_mail(), channel = id; "No spots for ad break <b><magenta>{name}</></b>");
+        } else {
+            spots[0].ad_break = Some(length);
+        }
+
+        debug!(target: Target::file(), channel = id; "Ad break <b><magenta>{name}</></b> with {} spots, <yellow>{length:.2}</> seconds", spots.len());
+
+        played.extend(spots.iter().map(|s| s.source.clone()));
+        program.append(&mut spots);
+
+        if ad_break.pad && ad_break.duration - length >= 1.0 {
+            let mut history = manager.filler_history.lock().unwrap();
+            let mut fillers = filler_list(&config, ad_break.duration - length, &mut history);
+
+            program.append(&mut fillers);
+        }
+
+        for item in program.iter_mut().skip(start) {
+            item.window = Some(name.clone());
+        }
+    }
+
+    playlist.program = program;
+    set_defaults(playlist);
+
+    if let Some(pool) = pool.filter(|_| !played.is_empty()) {
+        if let Err(e) =
+            runtime.block_on(handles::add_spot_plays(&pool, id, &playlist.date, &played))
+        {
+            error!(target: Target::file_mail(), channel = id; "Unable to write spot plays: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::models::AdBreak;
+    use crate::db::models::SpotPlay;
+
+    #[test]
+    fn ad_break_rotation() {
+        let spot = |src: &str, duration: f64| {
+            let mut media = Media::new(0, src, false);
+            media.duration = duration;
+            media.out = duration;
+            media
+        };
+        let pool = vec![
+            spot("spot_a.mp4", 30.0),
+            spot("spot_b.mp4", 30.0),
+            spot("spot_c.mp4", 20.0),
+            spot("spot_d.mp4", 60.0),
+        ];
+        let ad_break = AdBreak {
+            name: "prime".to_string(),
+            duration: 60.0,
+            max_plays: 2,
+            ..Default::default()
+        };
+        let mut breaks = AdBreaks::new(vec![ad_break.clone()]);
+
+        breaks.set_plays(
+            "2024-11-04",
+            vec![SpotPlay {
+                source: "spot_d.mp4".to_string(),
+                date: "2024-11-04".to_string(),
+                plays: 2,
+            }],
+        );
+
+        assert!(breaks.find("prime").is_some());
+        assert!(breaks.is_date("2024-11-04"));
+
+        // spot_d reached the limit
+        let spots = breaks.select(&ad_break, &pool);
+        let length: f64 = spots.iter().map(|s| s.duration).sum();
+
+        assert!((50.0..=60.0).contains(&length));
+        assert!(spots.iter().all(|s| s.source != "spot_d.mp4"));
+
+        // the spots from the first break played more often
+        let first: Vec<String> = spots.iter().map(|s| s.source.clone()).collect();
+        let spots = breaks.select(&ad_break, &pool);
+
+        assert!(spots.iter().any(|s| !first.contains(&s.source)));
+        assert!(pool.iter().all(|s| breaks.plays(&s.source) <= 2));
+    }
+}
@@ -0,0 +1,80 @@
+/*
+Air scheduler for channels, which are switched off outside of their broadcast hours.
+
+When the air hours end, a running channel is stopped and marked as off air,
+when they begin, a channel which was stopped by the scheduler is started again.
+Only these changes trigger an action, a manual start or stop in between is kept.
+The player status in the database is not touched, so the channel starts again after a restart of the engine.
+
+*/
+
+use std::{
+    collections::HashMap,
+    sync::{atomic::Ordering, Arc, Mutex},
+    time::Duration,
+};
+
+use log::*;
+use tokio::{task, time::interval};
+
+use crate::player::{controller::ChannelController, utils::time_in_seconds};
+use crate::utils::{
+    config::{OffAir, PlayoutConfig},
+    logging::Target,
+    maintenance::in_maintenance,
+};
+
+/// Channel has to be stopped right now.
+pub fn is_switched_off(config: &PlayoutConfig) -> bool {
+    let air_hours = &config.general.air_hours;
+
+    air_hours.off_air == OffAir::Off && !air_hours.on_air(time_in_seconds())
+}
+
+/// Check the air hours from all channels every few seconds.
+pub fn air_scheduler(controllers: Arc<Mutex<ChannelController>>) {
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(5));
+        let mut last_state = HashMap::new();
+
+        loop {
+            interval.tick().await;
+
+            let channels = controllers.lock().unwrap().channels.clone();
+
+            for manager in channels {
+                let config = manager.config.lock().unwrap().clone();
+                let id = config.general.channel_id;
+                let off = is_switched_off(&config);
+
+                if last_state.insert(id, off) == Some(off) {
+                    continue;
+                }
+
+                if off && manager.is_alive.load(Ordering::SeqCst) {
+                    info!(target: Target::file_mail(), channel = id;
+                        "Air hours end, switch channel off until <yellow>{}</>", config.general.air_hours.start
+                    );
+
+                    manager.channel.lock().unwrap().active = false;
+                    manager.off_air.store(true, Ordering::SeqCst);
+
+                    let manager_clone = manager.clone();
+
+                    if let Err(e) = task::spawn_blocking(move || manager_clone.stop_all()).await {
+                        error!(target: Target::file_mail(), channel = id; "{e}");
+                    }
+                } else if !off
+                    && manager.off_air.load(Ordering::SeqCst)
+                    && !manager.is_alive.load(Ordering::SeqCst)
+                    && !in_maintenance(id)
+                {
+                    info!(target: Target::file_mail(), channel = id; "Air hours begin, switch channel on");
+
+                    manager.channel.lock().unwrap().active = true;
+                    manager.async_start().await;
+                }
+            }
+        }
+    });
+}
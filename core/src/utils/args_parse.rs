@@ -21,6 +21,7 @@ use crate::utils::{
     advanced_config::AdvancedConfig,
     config::{OutputMode, PlayoutConfig},
     copy_assets,
+    failover::FailoverRole,
 };
 use crate::ARGS;
 
@@ -113,9 +114,33 @@ pub struct Args {
     #[clap(long, help_heading = Some("General"), help = "List available channel ids")]
     pub list_channels: bool,
 
-    #[clap(short, env, long, help_heading = Some("General"), help = "Listen on IP:PORT, like: 127.0.0.1:8787")]
+    #[clap(short, env, long, help_heading = Some("General"), help = "Listen on IP:PORT, unix:PATH or systemd, like: 127.0.0.1:8787")]
     pub listen: Option<String>,
 
+    #[clap(
+        long,
+        env,
+        help_heading = Some("General"),
+        help = "Seconds for running requests, like uploads, to finish on shutdown [default: 60]"
+    )]
+    pub shutdown_timeout: Option<u64>,
+
+    #[clap(
+        long,
+        env,
+        help_heading = Some("General"),
+        help = "Public URL of the web frontend, for links in mails, like: https://play.example.org"
+    )]
+    pub public_url: Option<String>,
+
+    #[clap(
+        long = "set",
+        help_heading = Some("General"),
+        help = "Override a config field, like: output.mode=hls, or 2:output.mode=hls for channel 2",
+        name = "KEY=VALUE"
+    )]
+    pub config_set: Option<Vec<String>>,
+
     #[clap(
         long,
         env,
@@ -127,6 +152,14 @@ pub struct Args {
     #[clap(long, env, help_heading = Some("General"), help = "Log to console")]
     pub log_to_console: bool,
 
+    #[clap(
+        long,
+        env,
+        help_heading = Some("General"),
+        help = "Format of log lines: plain, json"
+    )]
+    pub log_format: Option<String>,
+
     #[clap(
         short,
         long,
@@ -140,6 +173,13 @@ pub struct Args {
     #[clap(long, hide = true, help = "set fake time (for debugging)")]
     pub fake_time: Option<String>,
 
+    #[clap(
+        long,
+        hide = true,
+        help = "Run the clock faster (or slower) than real time, for simulation"
+    )]
+    pub time_speed: Option<f64>,
+
     #[clap(
         short,
         long,
@@ -179,7 +219,7 @@ pub struct Args {
     #[clap(long, env, help_heading = Some("Playout"), help = "Add timestamp to log line")]
     pub log_timestamp: bool,
 
-    #[clap(short, long, help_heading = Some("Playout"), help = "Set output mode: desktop, hls, null, stream")]
+    #[clap(short, long, help_heading = Some("Playout"), help = "Set output mode: dash, decklink, desktop, hls, null, stream")]
     pub output: Option<OutputMode>,
 
     #[clap(short, long, help_heading = Some("Playout"), help = "Set audio volume")]
@@ -187,6 +227,124 @@ pub struct Args {
 
     #[clap(long, help_heading = Some("Playout"), help = "Skip validation process")]
     pub skip_validation: bool,
+
+    #[clap(
+        long,
+        env,
+        help_heading = Some("Playout"),
+        help = "Reference for the playout clock: ntp://HOST[:PORT], ptp:///dev/ptp0 or ltc:FPS:COMMAND"
+    )]
+    pub clock_reference: Option<String>,
+
+    #[clap(
+        long,
+        env,
+        help_heading = Some("Playout"),
+        help = "Only report the offset to the reference clock, the playout follows the system time"
+    )]
+    pub clock_monitor_only: bool,
+
+    #[clap(
+        long,
+        env,
+        help_heading = Some("Playout"),
+        help = "Channels, which start at the same time at boot, 0 for no limit"
+    )]
+    pub start_concurrency: Option<usize>,
+
+    #[clap(
+        long,
+        env,
+        help_heading = Some("Playout"),
+        help = "Seconds to wait after a channel start at boot, before the next channel starts"
+    )]
+    pub start_delay: Option<u64>,
+
+    #[clap(
+        long,
+        env,
+        help_heading = Some("Playout"),
+        help = "Cgroup v2 folder for the memory limits of the channels, default: /sys/fs/cgroup/ffplayout"
+    )]
+    pub cgroup_root: Option<PathBuf>,
+
+    #[clap(
+        long,
+        env,
+        help_heading = Some("Playout"),
+        help = "Channels, which can run on one GPU, 0 for no limit"
+    )]
+    pub gpu_channels: Option<usize>,
+
+    #[clap(
+        long,
+        env,
+        help_heading = Some("Failover"),
+        help = "Role of this instance in an active/passive pair: primary, backup"
+    )]
+    pub failover_role: Option<FailoverRole>,
+
+    #[clap(
+        long,
+        env,
+        help_heading = Some("Failover"),
+        help = "API address of the other instance, like http://10.0.0.2:8787"
+    )]
+    pub failover_peer: Option<String>,
+
+    #[clap(
+        long,
+        env,
+        help_heading = Some("Failover"),
+        help = "Shared token for the heartbeat between the instances"
+    )]
+    pub failover_token: Option<String>,
+
+    #[clap(
+        long,
+        env,
+        help_heading = Some("Failover"),
+        help = "Seconds without heartbeat from the primary, until the backup takes over [default: 5]"
+    )]
+    pub failover_timeout: Option<u64>,
+
+    #[clap(long, env, help_heading = Some("TLS"), help = "Certificate chain in PEM format, for HTTPS")]
+    pub tls_cert: Option<PathBuf>,
+
+    #[clap(long, env, help_heading = Some("TLS"), help = "Private key in PEM format, for HTTPS")]
+    pub tls_key: Option<PathBuf>,
+
+    #[clap(
+        long,
+        env,
+        num_args = 1..,
+        value_delimiter = ',',
+        help_heading = Some("TLS"),
+        help = "Get certificate with ACME for domains, like: example.org,www.example.org"
+    )]
+    pub acme_domain: Option<Vec<String>>,
+
+    #[clap(long, env, help_heading = Some("TLS"), help = "Contact mail for the ACME account")]
+    pub acme_email: Option<String>,
+
+    #[clap(
+        long,
+        env,
+        help_heading = Some("TLS"),
+        help = "ACME directory URL [default: Let's Encrypt]"
+    )]
+    pub acme_url: Option<String>,
+
+    #[clap(long, env, help_heading = Some("TLS"), help = "Use the Let's Encrypt staging server")]
+    pub acme_staging: bool,
+
+    #[clap(
+        long,
+        env,
+        help_heading = Some("TLS"),
+        help = "Directory for ACME account and certificates [default: acme next to the database]"
+    )]
+    pub acme_dir: Option<PathBuf>,
 }
 
 fn global_user(args: &mut Args) {
@@ -447,7 +605,10 @@ pub async fn run_args(pool: &Pool<Sqlite>) -> Result<(), i32> {
             password: args.password.unwrap(),
             role_id: Some(1),
             channel_ids: Some(chl.clone()),
+            permissions: None,
             token: None,
+            must_change_password: false,
+            password_changed: 0,
         };
 
         if let Err(e) = handles::insert_or_update_user(pool, ff_user).await {
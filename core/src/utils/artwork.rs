@@ -0,0 +1,174 @@
+/*
+Artwork lookup for program items.
+
+Items without own artwork get a poster by title, first from a local folder in the channel storage,
+then from an external API. The API url contains a `{title}` placeholder and must return JSON,
+the image url is read with a JSON pointer (for example: `/results/0/poster`).
+
+Results, also missing ones, are cached in the database, to not hit the API on every program request.
+
+*/
+
+use std::{path::Path, time::Duration};
+
+use chrono::{NaiveDateTime, TimeDelta};
+use log::*;
+use serde_json::Value;
+use sqlx::{Pool, Sqlite};
+use walkdir::{DirEntry, WalkDir};
+
+use crate::db::handles;
+use crate::utils::{
+    config::PlayoutConfig, files::norm_abs_path, logging::Target, time_machine::time_now,
+};
+
+const IMAGE_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "webp"];
+/// Days until a cached lookup is checked again.
+const CACHE_DAYS: i64 = 7;
+
+/// Lowercase title with only letters and digits, for comparing titles with file names.
+pub fn normalize_title(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Find a image in the artwork folder, which file name matches the title.
+/// Returns the path relative to the storage.
+pub fn local_artwork(storage: &Path, folder: &str, title: &str) -> Option<String> {
+    let needle = normalize_title(title);
+
+    if needle.is_empty() {
+        return None;
+    }
+
+    let (dir, _, _) = norm_abs_path(storage, folder).ok()?;
+
+    WalkDir::new(dir)
+        .max_depth(2)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .map(DirEntry::into_path)
+        .filter(|p| {
+            p.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| IMAGE_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        })
+        .find(|p| {
+            p.file_stem()
+                .is_some_and(|s| normalize_title(&s.to_string_lossy()) == needle)
+        })
+        .and_then(|p| {
+            p.strip_prefix(storage)
+                .ok()
+                .map(|r| r.to_string_lossy().to_string())
+        })
+}
+
+async fn api_artwork(api: &str, field: &str, title: &str) -> Result<Option<String>, String> {
+    let title: String = url::form_urlencoded::byte_serialize(title.as_bytes()).collect();
+    let url = api.replace("{title}", &title);
+    let pointer = if field.is_empty() { "/poster" } else { field };
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let resp = client.get(url).send().await.map_err(|e| e.to_string())?;
+    let data: Value = resp.json().await.map_err(|e| e.to_string())?;
+
+    Ok(data
+        .pointer(pointer)
+        .and_then(|v| v.as_str())
+        .filter(|v| !v.is_empty())
+        .map(String::from))
+}
+
+/// Artwork for a title, from cache, local folder or API.
+/// Local images are relative to the storage, API results are full urls.
+pub async fn lookup_artwork(
+    pool: &Pool<Sqlite>,
+    config: &PlayoutConfig,
+    title: &str,
+) -> Option<String> {
+    let storage = &config.storage;
+    let id = config.general.channel_id;
+
+    if (storage.artwork_path.is_empty() && storage.artwork_api.is_empty()) || title.is_empty() {
+        return None;
+    }
+
+    let now = time_now().naive_local();
+
+    if let Ok(Some((url, updated))) = handles::select_artwork(pool, id, title).await {
+        if NaiveDateTime::parse_from_str(&updated, "%Y-%m-%d %H:%M:%S")
+            .is_ok_and(|t| now - t < TimeDelta::days(CACHE_DAYS))
+        {
+            return Some(url).filter(|u| !u.is_empty());
+        }
+    }
+
+    let mut artwork = None;
+
+    if !storage.artwork_path.is_empty() {
+        let root = config.channel.storage.clone();
+        let folder = storage.artwork_path.clone();
+        let t = title.to_string();
+
+        artwork = tokio::task::spawn_blocking(move || local_artwork(&root, &folder, &t))
+            .await
+            .unwrap_or_default();
+    }
+
+    if artwork.is_none() && !storage.artwork_api.is_empty() {
+        match api_artwork(&storage.artwork_api, &storage.artwork_field, title).await {
+            Ok(url) => artwork = url,
+            Err(e) => {
+                // no caching, try again next time
+                warn!(target: Target::file_mail(), channel = id; "Artwork lookup for <b><magenta>{title}</></b> failed: {e}");
+                return None;
+            }
+        }
+    }
+
+    if let Err(e) = handles::upsert_artwork(
+        pool,
+        id,
+        title,
+        artwork.as_deref().unwrap_or_default(),
+        &now.format("%Y-%m-%d %H:%M:%S").to_string(),
+    )
+    .await
+    {
+        error!(target: Target::file_mail(), channel = id; "Unable to cache artwork: {e}");
+    }
+
+    artwork
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn artwork_local_lookup() {
+        let storage = std::env::temp_dir().join("ffplayout_artwork");
+
+        std::fs::create_dir_all(storage.join("artwork")).unwrap();
+        std::fs::write(storage.join("artwork/The_Big-Show.JPG"), b"").unwrap();
+        std::fs::write(storage.join("artwork/notes.txt"), b"").unwrap();
+
+        assert_eq!(normalize_title("The Big Show!"), "thebigshow");
+        assert_eq!(
+            local_artwork(&storage, "artwork", "The Big Show"),
+            Some("artwork/The_Big-Show.JPG".to_string())
+        );
+        assert_eq!(local_artwork(&storage, "artwork", "Notes"), None);
+        assert_eq!(local_artwork(&storage, "artwork", "Other Show"), None);
+
+        std::fs::remove_dir_all(storage).unwrap();
+    }
+}
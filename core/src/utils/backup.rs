@@ -0,0 +1,463 @@
+/*
+Backup and restore of the database and the channel settings.
+
+The backup is a tar archive with:
+
+- `manifest.json`: engine version, newest migration and the channels
+- `ffplayout.db`: a consistent copy of the SQLite database
+- `channels/<id>/config.json`, `advanced.json` and `presets.json`: the settings of every
+  channel in readable form, the restore takes them from the database
+
+A restore checks the archive and the database in it. A database from a newer engine is rejected,
+an older one is migrated. The database is staged next to the running one and replaces it with
+the next start, so the switch is atomic and the running engine has no half restored state.
+
+Temporary copies of the database have the password hashes, so they go in a private folder
+next to the database, only readable by the engine, and are removed also when a step fails.
+After staging the restore, the server shuts down and starts again by its service manager.
+
+The archive is written to such a private file and streamed from there, so a big database
+is not held in memory. Uploaded archives are limited to `MAX_BACKUP_SIZE`.
+
+*/
+
+use std::{
+    fs::{self, DirBuilder, File, OpenOptions},
+    io::{self, BufWriter, Read, Seek, SeekFrom, Write},
+    os::unix::fs::{DirBuilderExt, OpenOptionsExt},
+    path::{Path, PathBuf},
+};
+
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite, SqlitePool};
+
+use crate::db::{handles, DB_PATH};
+use crate::utils::{errors::ServiceError, time_machine::time_now};
+
+/// Format version of the archive.
+pub const BACKUP_FORMAT: u32 = 1;
+
+pub const MANIFEST_NAME: &str = "manifest.json";
+pub const DB_NAME: &str = "ffplayout.db";
+const ARCHIVE_NAME: &str = "backup.tar";
+
+/// Maximal size of an uploaded backup.
+pub const MAX_BACKUP_SIZE: usize = 1024 * 1024 * 1024;
+
+const BLOCK: usize = 512;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub format: u32,
+    /// Engine version, which created the backup.
+    pub version: String,
+    /// Newest migration of the database.
+    pub migration: i64,
+    pub created: String,
+    pub channels: Vec<i32>,
+}
+
+fn octal(field: &mut [u8], value: u64) {
+    let end = field.len() - 1;
+    let digits = format!("{value:0end$o}");
+
+    field[..end].copy_from_slice(digits.as_bytes());
+    field[end] = 0;
+}
+
+/// Header of a file in the tar archive.
+fn tar_header(name: &str, size: u64) -> Result<[u8; BLOCK], String> {
+    if name.len() >= 100 {
+        return Err(format!("Name too long for archive: {name}"));
+    }
+
+    let mut header = [0u8; BLOCK];
+
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    octal(&mut header[100..108], 0o644);
+    octal(&mut header[108..116], 0);
+    octal(&mut header[116..124], 0);
+    octal(&mut header[124..136], size);
+    octal(&mut header[136..148], time_now().timestamp().max(0) as u64);
+    header[148..156].fill(b' ');
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    let checksum: u32 = header.iter().map(|b| *b as u32).sum();
+    header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+
+    Ok(header)
+}
+
+/// Append a file to the tar archive.
+pub fn tar_append(archive: &mut impl Write, name: &str, data: &[u8]) -> Result<(), String> {
+    tar_append_reader(archive, name, data.len() as u64, &mut &data[..])
+}
+
+/// Append a file with the given size from a reader, without loading it in memory.
+fn tar_append_reader(
+    archive: &mut impl Write,
+    name: &str,
+    size: u64,
+    reader: &mut impl Read,
+) -> Result<(), String> {
+    let header = tar_header(name, size)?;
+    let padding = (size as usize).div_ceil(BLOCK) * BLOCK - size as usize;
+
+    archive.write_all(&header).map_err(|e| e.to_string())?;
+
+    let copied = io::copy(&mut reader.take(size), archive).map_err(|e| e.to_string())?;
+
+    if copied != size {
+        return Err(format!("{name} changed while writing the archive"));
+    }
+
+    archive
+        .write_all(&[0; BLOCK][..padding])
+        .map_err(|e| e.to_string())
+}
+
+/// End of the tar archive: two empty blocks.
+pub fn tar_finish(archive: &mut impl Write) -> Result<(), String> {
+    archive
+        .write_all(&[0; BLOCK * 2])
+        .map_err(|e| e.to_string())
+}
+
+fn parse_octal(field: &[u8]) -> Result<u64, String> {
+    let text = String::from_utf8_lossy(field);
+    let text = text.trim_matches(|c: char| c == '\0' || c == ' ');
+
+    if text.is_empty() {
+        return Ok(0);
+    }
+
+    u64::from_str_radix(text, 8).map_err(|_| "Invalid number in archive header".to_string())
+}
+
+/// Files from a tar archive, other entries like folders are skipped.
+pub fn tar_read(archive: &[u8]) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let mut files = vec![];
+    let mut pos = 0;
+
+    while pos + BLOCK <= archive.len() {
+        let header = &archive[pos..pos + BLOCK];
+
+        if header.iter().all(|b| *b == 0) {
+            return Ok(files);
+        }
+
+        let expected = parse_octal(&header[148..156])?;
+        let checksum: u64 = header
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                if (148..156).contains(&i) {
+                    32
+                } else {
+                    *b as u64
+                }
+            })
+            .sum();
+
+        if checksum != expected {
+            return Err("Invalid checksum in archive header".to_string());
+        }
+
+        let name_end = header[..100].iter().position(|b| *b == 0).unwrap_or(100);
+        let name = String::from_utf8_lossy(&header[..name_end]).to_string();
+        let size = parse_octal(&header[124..136])? as usize;
+        let start = pos + BLOCK;
+        let end = start
+            .checked_add(size)
+            .filter(|e| *e <= archive.len())
+            .ok_or("Archive is truncated")?;
+
+        if matches!(header[156], b'0' | 0) {
+            files.push((name, archive[start..end].to_vec()));
+        }
+
+        pos = start + size.div_ceil(BLOCK) * BLOCK;
+    }
+
+    Err("Archive has no end".to_string())
+}
+
+fn db_path() -> Result<PathBuf, ServiceError> {
+    DB_PATH
+        .as_ref()
+        .map(|p| p.to_path_buf())
+        .map_err(|e| ServiceError::Conflict(e.to_string()))
+}
+
+/// Path of a staged restore, next to the database.
+pub fn staged_path(db: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.restore", db.display()))
+}
+
+/// Private folder for temporary database copies, it is removed on drop.
+struct Staging {
+    dir: PathBuf,
+}
+
+impl Staging {
+    /// New folder next to the database, with a random name and only accessible by the owner.
+    fn new(db: &Path) -> Result<Self, ServiceError> {
+        let nonce: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(16)
+            .map(char::from)
+            .collect();
+        let dir = db
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join(format!(".ffplayout_staging_{nonce}"));
+
+        // fails when the path exists already, so nothing else can be placed there before
+        DirBuilder::new().mode(0o700).create(&dir)?;
+
+        Ok(Self { dir })
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    /// Create a new file, only readable by the owner.
+    fn create(&self, name: &str) -> Result<File, ServiceError> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(self.path(name))?;
+
+        Ok(file)
+    }
+
+    /// Write a new file, only readable by the owner.
+    fn write(&self, name: &str, data: &[u8]) -> Result<PathBuf, ServiceError> {
+        self.create(name)?.write_all(data)?;
+
+        Ok(self.path(name))
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn json<T: Serialize>(value: &T) -> Result<Vec<u8>, ServiceError> {
+    Ok(serde_json::to_vec_pretty(value)?)
+}
+
+/// Create the backup archive.
+///
+/// The archive is a private temporary file, which is removed again before it is returned.
+/// So only the returned file handle can read it, from the start.
+pub async fn create_backup(pool: &Pool<Sqlite>) -> Result<File, ServiceError> {
+    let staging = Staging::new(&db_path()?)?;
+    let snapshot = staging.path(DB_NAME);
+
+    handles::vacuum_into(pool, &snapshot.to_string_lossy()).await?;
+
+    let mut db = File::open(&snapshot)?;
+    let db_size = db.metadata()?.len();
+    let channels = handles::select_related_channels(pool, None).await?;
+    let manifest = BackupManifest {
+        format: BACKUP_FORMAT,
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        migration: handles::select_migration(pool).await?,
+        created: time_now().to_rfc3339(),
+        channels: channels.iter().map(|c| c.id).collect(),
+    };
+    let mut archive = BufWriter::new(staging.create(ARCHIVE_NAME)?);
+
+    tar_append(&mut archive, MANIFEST_NAME, &json(&manifest)?)?;
+    tar_append_reader(&mut archive, DB_NAME, db_size, &mut db)?;
+
+    for channel in &channels {
+        let config = handles::select_configuration(pool, channel.id).await?;
+        let advanced = handles::select_advanced_configuration(pool, channel.id).await?;
+        let presets = handles::select_presets(pool, channel.id).await?;
+
+        tar_append(
+            &mut archive,
+            &format!("channels/{}/config.json", channel.id),
+            &json(&config)?,
+        )?;
+        tar_append(
+            &mut archive,
+            &format!("channels/{}/advanced.json", channel.id),
+            &json(&advanced)?,
+        )?;
+        tar_append(
+            &mut archive,
+            &format!("channels/{}/presets.json", channel.id),
+            &json(&presets)?,
+        )?;
+    }
+
+    tar_finish(&mut archive)?;
+
+    let mut file = archive.into_inner().map_err(io::IntoInnerError::into_error)?;
+    file.seek(SeekFrom::Start(0))?;
+
+    Ok(file)
+}
+
+/// Check the manifest from a backup against this engine.
+pub fn check_manifest(manifest: &BackupManifest, latest: i64) -> Result<(), String> {
+    if manifest.format != BACKUP_FORMAT {
+        return Err(format!("Unknown backup format: {}", manifest.format));
+    }
+
+    if manifest.migration > latest {
+        return Err(format!(
+            "Backup is from a newer version ({}), update the engine first",
+            manifest.version
+        ));
+    }
+
+    Ok(())
+}
+
+/// Check the database from the backup and migrate it.
+async fn prepare_database(path: &Path, latest: i64) -> Result<(), ServiceError> {
+    let pool = SqlitePool::connect(&path.to_string_lossy()).await?;
+    let result = async {
+        let check = handles::integrity_check(&pool).await?;
+
+        if check != "ok" {
+            return Err(ServiceError::BadRequest(format!(
+                "Database in backup is damaged: {check}"
+            )));
+        }
+
+        if handles::select_migration(&pool).await? > latest {
+            return Err(ServiceError::BadRequest(
+                "Database in backup is from a newer version".into(),
+            ));
+        }
+
+        handles::db_migrate(&pool)
+            .await
+            .map_err(|e| ServiceError::BadRequest(format!("Migration failed: {e}")))?;
+
+        // no WAL file next to the staged database
+        handles::checkpoint(&pool).await?;
+
+        Ok(())
+    }
+    .await;
+
+    pool.close().await;
+
+    result
+}
+
+/// Check the backup and stage its database, it replaces the running one with the next start.
+pub async fn restore_backup(archive: &[u8]) -> Result<BackupManifest, ServiceError> {
+    let files = tar_read(archive).map_err(ServiceError::BadRequest)?;
+    let file = |name: &str| {
+        files
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, d)| d.clone())
+            .ok_or_else(|| ServiceError::BadRequest(format!("{name} missing in backup")))
+    };
+    let manifest: BackupManifest = serde_json::from_slice(&file(MANIFEST_NAME)?)
+        .map_err(|e| ServiceError::BadRequest(format!("Invalid manifest: {e}")))?;
+    let latest = handles::latest_migration();
+
+    check_manifest(&manifest, latest).map_err(ServiceError::BadRequest)?;
+
+    let db = db_path()?;
+    let staging = Staging::new(&db)?;
+    let temp = staging.write(DB_NAME, &file(DB_NAME)?)?;
+
+    prepare_database(&temp, latest).await?;
+
+    // same file system as the database, so the rename at start is atomic
+    let staged = staged_path(&db);
+    let _ = fs::remove_file(&staged);
+
+    if let Err(e) = fs::copy(&temp, &staged) {
+        let _ = fs::remove_file(&staged);
+
+        return Err(e.into());
+    }
+
+    Ok(manifest)
+}
+
+/// Replace the database with a staged restore, before it is opened.
+/// The old database is kept with the `.bak` extension.
+pub fn apply_staged_restore(db: &Path) -> std::io::Result<bool> {
+    let staged = staged_path(db);
+
+    if !staged.is_file() {
+        return Ok(false);
+    }
+
+    for suffix in ["", "-wal", "-shm"] {
+        let current = PathBuf::from(format!("{}{suffix}", db.display()));
+
+        if current.is_file() {
+            fs::rename(&current, format!("{}.bak{suffix}", db.display()))?;
+        }
+    }
+
+    fs::rename(&staged, db)?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backup_archive() {
+        let mut archive = vec![];
+
+        tar_append(&mut archive, "manifest.json", b"{}").unwrap();
+        tar_append(&mut archive, "channels/1/config.json", &[7; 700]).unwrap();
+        tar_finish(&mut archive).unwrap();
+
+        assert_eq!(archive.len() % 512, 0);
+
+        let files = tar_read(&archive).unwrap();
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0], ("manifest.json".to_string(), b"{}".to_vec()));
+        assert_eq!(files[1].0, "channels/1/config.json");
+        assert_eq!(files[1].1, vec![7; 700]);
+        assert!(tar_append(&mut vec![], &"x".repeat(100), b"").is_err());
+
+        let mut broken = archive.clone();
+        broken[10] = b'x';
+
+        assert!(tar_read(&broken).is_err());
+        assert!(tar_read(&archive[..1024]).is_err());
+
+        let mut manifest = BackupManifest {
+            format: BACKUP_FORMAT,
+            version: "0.24.4".to_string(),
+            migration: 20240101000000,
+            created: "2024-11-04T12:00:00+01:00".to_string(),
+            channels: vec![1],
+        };
+
+        assert!(check_manifest(&manifest, 20240101000000).is_ok());
+        assert!(check_manifest(&manifest, 20230101000000).is_err());
+
+        manifest.format = 2;
+
+        assert!(check_manifest(&manifest, 20240101000000).is_err());
+    }
+}
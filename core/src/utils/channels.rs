@@ -9,8 +9,13 @@ use sqlx::{Pool, Sqlite};
 
 use super::logging::MailQueue;
 use crate::db::{handles, models::Channel};
-use crate::player::controller::{ChannelController, ChannelManager};
-use crate::utils::{config::get_config, copy_assets, errors::ServiceError};
+use crate::player::{
+    controller::{ChannelController, ChannelManager},
+    output::offline::stop_offline,
+};
+use crate::utils::{
+    companion, config::get_config, copy_assets, errors::ServiceError, maintenance::set_maintenance,
+};
 
 async fn map_global_admins(conn: &Pool<Sqlite>) -> Result<(), ServiceError> {
     let channels = handles::select_related_channels(conn, None).await?;
@@ -54,7 +59,7 @@ pub async fn create_channel(
 
     controllers
         .lock()
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?
+        .map_err(|e| io::Error::other(e.to_string()))?
         .add(manager);
 
     if let Ok(mut mqs) = queue.lock() {
@@ -75,9 +80,21 @@ pub async fn delete_channel(
     let channel = handles::select_channel(conn, &id).await?;
     handles::delete_channel(conn, &channel.id).await?;
 
+    let manager = controllers
+        .lock()
+        .map_err(|e| io::Error::other(e.to_string()))?
+        .get(id);
+
+    if let Some(manager) = manager {
+        stop_offline(&manager);
+    }
+
+    companion::stop_channel(id).await;
+    set_maintenance(id, false);
+
     controllers
         .lock()
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?
+        .map_err(|e| io::Error::other(e.to_string()))?
         .remove(id);
 
     if let Ok(mut mqs) = queue.lock() {
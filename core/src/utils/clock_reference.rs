@@ -0,0 +1,398 @@
+/*
+External reference for the playout clock.
+
+The offset between the system time and the reference is measured in background:
+
+- `ntp://HOST[:PORT]`: SNTP request every 16 seconds
+- `ptp:///dev/ptp0`: PTP hardware clock (TAI), read every second
+- `ltc:FPS:COMMAND`: command which prints the decoded LTC timecode, like `ltcdump`
+
+The playout clock follows the reference, with `--clock-monitor-only` the offset is only reported.
+
+*/
+
+use std::{
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader},
+    net::UdpSocket,
+    os::fd::AsRawFd,
+    process::{Command, Stdio},
+    str::FromStr,
+    sync::{LazyLock, Mutex},
+    thread::{self, sleep},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{Local, Timelike};
+use log::*;
+use nix::time::{clock_gettime, ClockId};
+use serde::Serialize;
+
+use crate::utils::time_machine::{set_clock, time_now, ReferenceClock};
+use ClockReference::*;
+
+/// Seconds between 1900 (NTP era) and 1970 (Unix epoch).
+const NTP_UNIX_OFFSET: f64 = 2_208_988_800.0;
+const NTP_INTERVAL: Duration = Duration::from_secs(16);
+const PTP_INTERVAL: Duration = Duration::from_secs(1);
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// Measurements with a longer round trip are not trusted.
+const MAX_DELAY: f64 = 1.0;
+/// TAI is ahead of UTC by the leap seconds.
+const TAI_OFFSET: f64 = 37.0;
+/// Offset in seconds, from which a warning is logged.
+pub const MAX_OFFSET: f64 = 0.5;
+
+static STATUS: LazyLock<Mutex<ClockStatus>> = LazyLock::new(|| Mutex::new(ClockStatus::default()));
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClockReference {
+    Ntp(String),
+    Ptp(String),
+    Ltc(f64, String),
+}
+
+impl FromStr for ClockReference {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        if let Some(host) = input.strip_prefix("ntp://") {
+            let host = host.trim_end_matches('/');
+
+            if host.is_empty() {
+                return Err("NTP reference needs a server".to_string());
+            }
+
+            if host.contains(':') {
+                return Ok(Self::Ntp(host.to_string()));
+            }
+
+            return Ok(Self::Ntp(format!("{host}:123")));
+        }
+
+        if let Some(device) = input.strip_prefix("ptp://") {
+            if !device.starts_with('/') {
+                return Err("PTP reference needs a device, like ptp:///dev/ptp0".to_string());
+            }
+
+            return Ok(Self::Ptp(device.to_string()));
+        }
+
+        if let Some(ltc) = input.strip_prefix("ltc:") {
+            let (fps, command) = ltc.split_once(':').ok_or(
+                "LTC reference needs frame rate and command, like ltc:25:ltcdump -".to_string(),
+            )?;
+            let fps: f64 = fps
+                .parse()
+                .map_err(|_| format!("Invalid frame rate: {fps}"))?;
+
+            if fps <= 0.0 || command.trim().is_empty() {
+                return Err("LTC reference needs frame rate and command".to_string());
+            }
+
+            return Ok(Self::Ltc(fps, command.trim().to_string()));
+        }
+
+        Err(format!("Unknown clock reference: {input}"))
+    }
+}
+
+impl fmt::Display for ClockReference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Ntp(host) => write!(f, "ntp://{host}"),
+            Self::Ptp(device) => write!(f, "ptp://{device}"),
+            Self::Ltc(fps, command) => write!(f, "ltc:{fps}:{command}"),
+        }
+    }
+}
+
+/// State from the reference clock, for the clock API.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ClockStatus {
+    /// Reference, empty when the playout runs on the system time.
+    pub source: String,
+    /// Playout clock follows the reference.
+    pub follow: bool,
+    pub synced: bool,
+    /// Seconds, which the reference is ahead of the system time.
+    pub offset: f64,
+    /// Round trip of the last NTP request.
+    pub delay: Option<f64>,
+    /// Change of the offset per hour.
+    pub drift: Option<f64>,
+    pub last_sync: Option<String>,
+    pub error: Option<String>,
+    #[serde(skip)]
+    first_sync: Option<(f64, f64)>,
+}
+
+pub fn clock_status() -> ClockStatus {
+    STATUS.lock().unwrap().clone()
+}
+
+fn unix_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or_default()
+}
+
+/// Unix time from a 64 bit NTP timestamp.
+pub fn ntp_timestamp(bytes: &[u8]) -> f64 {
+    let secs = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64;
+    let frac = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as f64;
+
+    secs - NTP_UNIX_OFFSET + frac / 4_294_967_296.0
+}
+
+/// Offset and round trip from the four NTP times: request sent, received by the server,
+/// answer sent by the server, answer received.
+pub fn ntp_offset(t1: f64, t2: f64, t3: f64, t4: f64) -> (f64, f64) {
+    (((t2 - t1) + (t3 - t4)) / 2.0, (t4 - t1) - (t3 - t2))
+}
+
+/// Ask the NTP server, returns offset and round trip.
+fn query_ntp(server: &str) -> Result<(f64, f64), String> {
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| e.to_string())?;
+    let mut request = [0_u8; 48];
+    let mut answer = [0_u8; 48];
+
+    // no leap indicator, version 4, client mode
+    request[0] = 0x23;
+
+    socket
+        .set_read_timeout(Some(TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    socket.connect(server).map_err(|e| e.to_string())?;
+
+    let t1 = unix_now();
+
+    socket.send(&request).map_err(|e| e.to_string())?;
+
+    let length = socket.recv(&mut answer).map_err(|e| e.to_string())?;
+    let t4 = unix_now();
+
+    // server mode and a stratum, stratum 0 is a kiss-o'-death packet
+    if length < 48 || answer[0] & 0x07 != 4 || answer[1] == 0 {
+        return Err(format!("Invalid answer from {server}"));
+    }
+
+    Ok(ntp_offset(
+        t1,
+        ntp_timestamp(&answer[32..40]),
+        ntp_timestamp(&answer[40..48]),
+        t4,
+    ))
+}
+
+/// Offset from the PTP hardware clock, which runs in TAI.
+fn read_ptp(device: &str) -> Result<f64, String> {
+    let file = File::open(device).map_err(|e| format!("{device}: {e}"))?;
+    // dynamic clock id from the file descriptor
+    let clock = ClockId::from_raw(((!file.as_raw_fd()) << 3) | 3);
+    let time = clock_gettime(clock).map_err(|e| format!("{device}: {e}"))?;
+    let system = unix_now();
+    let ptp = time.tv_sec() as f64 + time.tv_nsec() as f64 / 1_000_000_000.0;
+
+    Ok(ptp - TAI_OFFSET - system)
+}
+
+/// Seconds of the day from the first timecode in a line, like `10:12:04:20` or `10:12:04;20`.
+pub fn parse_ltc(line: &str, fps: f64) -> Option<f64> {
+    line.split_whitespace().find_map(|token| {
+        let parts: Vec<f64> = token
+            .split([':', ';', '.'])
+            .map(|p| p.parse().ok())
+            .collect::<Option<_>>()?;
+
+        match parts[..] {
+            [h, m, s, f] if h < 24.0 && m < 60.0 && s < 60.0 && f < fps.ceil() => {
+                Some(h * 3600.0 + m * 60.0 + s + f / fps)
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Offset between a time of the day and the system time of the day, the nearest way over midnight.
+pub fn day_offset(reference: f64, system: f64) -> f64 {
+    let offset = reference - system;
+
+    if offset > 43200.0 {
+        offset - 86400.0
+    } else if offset < -43200.0 {
+        offset + 86400.0
+    } else {
+        offset
+    }
+}
+
+fn update(clock: &ReferenceClock, result: Result<(f64, Option<f64>), String>) {
+    let mut status = STATUS.lock().unwrap();
+
+    match result {
+        Ok((offset, delay)) => {
+            if !status.synced {
+                info!(
+                    "Clock reference <b><magenta>{}</></b> synced, offset: <yellow>{offset:.3}</> seconds",
+                    status.source
+                );
+            }
+
+            if offset.abs() > MAX_OFFSET && status.offset.abs() <= MAX_OFFSET {
+                warn!("System clock is <yellow>{offset:.3}</> seconds away from the reference");
+            }
+
+            let now = unix_now();
+            let (first_time, first_offset) = *status.first_sync.get_or_insert((now, offset));
+
+            if now - first_time >= 60.0 {
+                status.drift = Some((offset - first_offset) / (now - first_time) * 3600.0);
+            }
+
+            status.synced = true;
+            status.offset = offset;
+            status.delay = delay;
+            status.error = None;
+            status.last_sync = Some(time_now().format("%Y-%m-%d %H:%M:%S%.3f").to_string());
+
+            if status.follow {
+                clock.set_offset(offset);
+            }
+        }
+        Err(e) => {
+            if status.synced || status.error.is_none() {
+                error!("Clock reference <b><magenta>{}</></b>: {e}", status.source);
+            }
+
+            // the last offset stays, until the reference is back
+            status.synced = false;
+            status.error = Some(e);
+        }
+    }
+}
+
+/// Read the timecodes from the command, it is started again when it ends.
+fn ltc_reader(clock: &ReferenceClock, fps: f64, command: &str) {
+    loop {
+        let child = Command::new("sh")
+            .args(["-c", command])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+
+        match child {
+            Ok(mut proc) => {
+                let reader = BufReader::new(proc.stdout.take().unwrap());
+
+                for line in reader.lines().map_while(Result::ok) {
+                    if let Some(sec) = parse_ltc(&line, fps) {
+                        // compare with the system time, the playout clock has the offset already
+                        let now = Local::now();
+                        let system = now.num_seconds_from_midnight() as f64
+                            + now.nanosecond() as f64 / 1_000_000_000.0;
+
+                        update(clock, Ok((day_offset(sec, system), None)));
+                    }
+                }
+
+                let _ = proc.wait();
+
+                update(clock, Err("LTC reader ended".to_string()));
+            }
+            Err(e) => update(clock, Err(format!("LTC reader: {e}"))),
+        }
+
+        sleep(TIMEOUT);
+    }
+}
+
+/// Measure the offset to the reference in background. When `follow` is set,
+/// the playout clock runs with the time of the reference.
+pub fn start_reference_clock(reference: ClockReference, follow: bool) {
+    let clock = ReferenceClock::new();
+
+    {
+        let mut status = STATUS.lock().unwrap();
+        status.source = reference.to_string();
+        status.follow = follow;
+    }
+
+    if follow {
+        set_clock(clock.clone());
+    }
+
+    thread::spawn(move || match reference {
+        Ntp(server) => loop {
+            let result = query_ntp(&server).and_then(|(offset, delay)| {
+                if delay > MAX_DELAY {
+                    return Err(format!("Round trip too long: {delay:.3} seconds"));
+                }
+
+                Ok((offset, Some(delay)))
+            });
+
+            update(&clock, result);
+            sleep(NTP_INTERVAL);
+        },
+        Ptp(device) => loop {
+            update(&clock, read_ptp(&device).map(|offset| (offset, None)));
+            sleep(PTP_INTERVAL);
+        },
+        Ltc(fps, command) => ltc_reader(&clock, fps, &command),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_reference_sources() {
+        assert_eq!(
+            "ntp://pool.ntp.org".parse::<ClockReference>(),
+            Ok(ClockReference::Ntp("pool.ntp.org:123".to_string()))
+        );
+        assert_eq!(
+            "ntp://10.0.0.1:1123".parse::<ClockReference>(),
+            Ok(ClockReference::Ntp("10.0.0.1:1123".to_string()))
+        );
+        assert_eq!(
+            "ptp:///dev/ptp0".parse::<ClockReference>(),
+            Ok(ClockReference::Ptp("/dev/ptp0".to_string()))
+        );
+        assert_eq!(
+            "ltc:25:ltcdump -f -".parse::<ClockReference>(),
+            Ok(ClockReference::Ltc(25.0, "ltcdump -f -".to_string()))
+        );
+        assert!("ntp://".parse::<ClockReference>().is_err());
+        assert!("ptp://ptp0".parse::<ClockReference>().is_err());
+        assert!("ltc:abc:ltcdump".parse::<ClockReference>().is_err());
+        assert!("gps:/dev/ttyS0".parse::<ClockReference>().is_err());
+
+        // 2024-01-01 00:00:00 UTC, with a half second
+        let bytes = [0xe9, 0x3c, 0x7f, 0x00, 0x80, 0x00, 0x00, 0x00];
+
+        assert_eq!(ntp_timestamp(&bytes), 1_704_067_200.5);
+
+        // server is 2 seconds ahead, 0.2 seconds on the way and 0.1 seconds for the answer
+        let (offset, delay) = ntp_offset(100.0, 102.1, 102.2, 100.3);
+
+        assert!((offset - 2.0).abs() < 1e-9);
+        assert!((delay - 0.2).abs() < 1e-9);
+
+        let ltc = parse_ltc("10:12:04:20", 25.0).unwrap();
+        let drop_frame = parse_ltc("frame 1234 10:12:04;15 +", 30.0).unwrap();
+
+        assert!((ltc - 36724.8).abs() < 1e-9);
+        assert!((drop_frame - 36724.5).abs() < 1e-9);
+        assert_eq!(parse_ltc("10:12:04:25", 25.0), None);
+        assert_eq!(parse_ltc("no timecode", 25.0), None);
+
+        assert!((day_offset(36724.8, 36724.0) - 0.8).abs() < 1e-9);
+        assert_eq!(day_offset(10.0, 86390.0), 20.0);
+        assert_eq!(day_offset(86390.0, 10.0), -20.0);
+    }
+}
@@ -0,0 +1,827 @@
+/*
+Companion processes of a channel.
+
+Helper commands, like bots, relays or a pipe from streamlink into the ingest, which run next to
+the playout. They are stored per channel: binary, arguments, restart policy and autostart.
+Variables in the arguments are replaced at start, they are also in the environment as
+`FFPLAYOUT_<NAME>`, which is the safe way to use them in a shell command:
+
+- `{channel_id}`, `{channel_name}`, `{storage}`, `{public}`, `{playlists}`
+- `{ingest_target}`: port and path from the ingest settings, like `:1936/live/stream`
+- `{ingest_url}`: local RTMP url of the ingest, like `rtmp://127.0.0.1:1936/live/stream`
+- own variables from the start request, like `{url}`
+
+Restart policies are `never`, `on_failure` (exit code is not 0) and `always`, with a growing
+delay. After `max_restarts` restarts in a row the process stays stopped, a run longer than
+`STABLE_RUN` resets the count. Every process gets its own process group, so stop reaches also
+its children. The last `LOG_LINES` lines of stdout and stderr are kept for the log endpoint.
+
+Relays report their health with the ffmpeg progress output (`-progress pipe:1`): bitrate and the
+time of the last data. A relay, which sends no data for `STALL_TIMEOUT`, is killed and restarted,
+like a failed process. When the restarts are used up, an error goes to mail and notifiers.
+
+Companions with autostart start and stop together with the playout. All companions of a channel
+stop, when the channel is deleted or the server stops.
+
+`ytbot` and `livestream` are built in, when the channel has no companion with this name.
+
+*/
+
+use std::{
+    collections::{HashMap, VecDeque},
+    env, fmt,
+    path::Path,
+    process::Stdio,
+    str::FromStr,
+    sync::{Arc, LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use dirs::home_dir;
+use log::*;
+use nix::{
+    sys::signal::{killpg, Signal},
+    unistd::Pid,
+};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process::{Child, Command},
+    sync::Notify,
+    task::JoinHandle,
+    time::{interval, sleep, timeout},
+};
+
+use crate::db::{handles, models::Companion};
+use crate::player::controller::ChannelManager;
+use crate::utils::{
+    errors::ServiceError, logging::Target, maintenance::in_maintenance, time_machine::time_now,
+};
+
+/// Lines from stdout and stderr, which are kept for each companion.
+pub const LOG_LINES: usize = 200;
+
+/// Time after SIGTERM, until the process group is killed.
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A run longer than this resets the restart count.
+const STABLE_RUN: Duration = Duration::from_secs(60);
+
+/// Time without new data, until a relay counts as broken.
+pub const STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Restarts of the built-in relay, before it gives up.
+const RELAY_RETRIES: i64 = 10;
+
+/// Keys from the ffmpeg progress output.
+const PROGRESS_KEYS: [&str; 11] = [
+    "frame",
+    "fps",
+    "bitrate",
+    "total_size",
+    "out_time_us",
+    "out_time_ms",
+    "out_time",
+    "dup_frames",
+    "drop_frames",
+    "speed",
+    "progress",
+];
+
+static RUNNERS: LazyLock<Mutex<HashMap<(i32, String), Runner>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+static VARIABLE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{([a-z][a-z0-9_]*)\}").unwrap());
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestartPolicy {
+    #[default]
+    Never,
+    OnFailure,
+    Always,
+}
+
+impl FromStr for RestartPolicy {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "" | "never" => Ok(Self::Never),
+            "on_failure" => Ok(Self::OnFailure),
+            "always" => Ok(Self::Always),
+            _ => Err("Use 'never', 'on_failure' or 'always'".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for RestartPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Never => write!(f, "never"),
+            Self::OnFailure => write!(f, "on_failure"),
+            Self::Always => write!(f, "always"),
+        }
+    }
+}
+
+/// Process should start again, after it exited.
+pub fn should_restart(
+    policy: RestartPolicy,
+    success: bool,
+    restarts: usize,
+    max_restarts: i64,
+) -> bool {
+    let wanted = match policy {
+        RestartPolicy::Never => false,
+        RestartPolicy::OnFailure => !success,
+        RestartPolicy::Always => true,
+    };
+
+    wanted && (max_restarts <= 0 || (restarts as i64) < max_restarts)
+}
+
+/// Delay before the restart: 2, 4, 8 ... seconds, at most one minute.
+pub fn restart_delay(restarts: usize) -> Duration {
+    Duration::from_secs((1_u64 << restarts.clamp(1, 6)).min(60))
+}
+
+/// Line from the ffmpeg progress output.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Progress {
+    /// Bitrate in kbit/s, `None` when ffmpeg does not know it yet.
+    Bitrate(Option<f64>),
+    /// Bytes written so far.
+    TotalSize(u64),
+    Other,
+}
+
+/// Parse a progress line, `None` for normal log lines.
+pub fn parse_progress(line: &str) -> Option<Progress> {
+    let (key, value) = line.trim().split_once('=')?;
+
+    if !PROGRESS_KEYS.contains(&key) && !key.starts_with("stream_") {
+        return None;
+    }
+
+    let value = value.trim();
+
+    Some(match key {
+        "bitrate" => Progress::Bitrate(value.trim_end_matches("kbits/s").parse().ok()),
+        "total_size" => Progress::TotalSize(value.parse().ok()?),
+        _ => Progress::Other,
+    })
+}
+
+/// The relay sent data before, but nothing new since the timeout.
+pub fn is_stalled(last_data: Option<Instant>, now: Instant, timeout: Duration) -> bool {
+    last_data.is_some_and(|last| now.saturating_duration_since(last) >= timeout)
+}
+
+/// Port and path of the ingest, from its input parameters.
+pub fn ingest_target(input_param: &str) -> Option<String> {
+    let re = Regex::new(r":(\d{1,5})(\S*)").ok()?;
+    let caps = re.captures(input_param)?;
+    let port = caps.get(1)?.as_str().parse::<u16>().ok()?;
+
+    Some(format!(":{port}{}", caps.get(2).map_or("", |m| m.as_str())))
+}
+
+/// Arguments from the template, split like a shell and with the variables replaced.
+pub fn render_args(
+    args: &str,
+    vars: &HashMap<String, String>,
+) -> Result<Vec<String>, ServiceError> {
+    let parts = shlex::split(args)
+        .ok_or_else(|| ServiceError::BadRequest("Arguments have unclosed quotes".to_string()))?;
+    let mut rendered = vec![];
+
+    for part in parts {
+        let mut missing = None;
+        let arg = VARIABLE.replace_all(&part, |caps: &Captures| match vars.get(&caps[1]) {
+            Some(value) => value.clone(),
+            None => {
+                missing = Some(caps[1].to_string());
+                String::new()
+            }
+        });
+
+        if let Some(name) = missing {
+            return Err(ServiceError::BadRequest(format!(
+                "Unknown variable: {{{name}}}"
+            )));
+        }
+
+        rendered.push(arg.to_string());
+    }
+
+    Ok(rendered)
+}
+
+/// Variables from the channel.
+pub fn channel_vars(manager: &ChannelManager) -> HashMap<String, String> {
+    let channel = manager.channel.lock().unwrap().clone();
+    let config = manager.config.lock().unwrap();
+    let mut vars = HashMap::from([
+        ("channel_id".to_string(), channel.id.to_string()),
+        ("channel_name".to_string(), channel.name),
+        (
+            "storage".to_string(),
+            config.channel.storage.to_string_lossy().to_string(),
+        ),
+        (
+            "public".to_string(),
+            config.channel.public.to_string_lossy().to_string(),
+        ),
+        (
+            "playlists".to_string(),
+            config.channel.playlists.to_string_lossy().to_string(),
+        ),
+    ]);
+
+    if let Some(target) = ingest_target(&config.ingest.input_param) {
+        vars.insert(
+            "ingest_url".to_string(),
+            format!("rtmp://127.0.0.1{target}"),
+        );
+        vars.insert("ingest_target".to_string(), target);
+    }
+
+    vars
+}
+
+fn valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars.next().is_some_and(|c| c.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Check the settings, before they are saved.
+pub fn check_companion(companion: &Companion) -> Result<(), ServiceError> {
+    if !valid_name(&companion.name) {
+        return Err(ServiceError::BadRequest(
+            "Name needs lowercase letters, digits and underscores".to_string(),
+        ));
+    }
+
+    if companion.command.trim().is_empty() {
+        return Err(ServiceError::BadRequest("Command is needed".to_string()));
+    }
+
+    companion
+        .restart
+        .parse::<RestartPolicy>()
+        .map_err(ServiceError::BadRequest)?;
+
+    shlex::split(&companion.args)
+        .ok_or_else(|| ServiceError::BadRequest("Arguments have unclosed quotes".to_string()))?;
+
+    Ok(())
+}
+
+fn find_binary<P: AsRef<Path>>(var: &str, paths: &[P]) -> Option<String> {
+    if let Ok(path) = env::var(var) {
+        if Path::new(&path).is_file() {
+            return Some(path);
+        }
+    }
+
+    paths
+        .iter()
+        .find(|p| p.as_ref().is_file())
+        .map(|p| p.as_ref().to_string_lossy().to_string())
+}
+
+/// Built-in companions, they were separate modules before.
+pub fn builtin(channel: i32, name: &str) -> Option<Companion> {
+    let mut companion = Companion {
+        channel_id: channel,
+        name: name.to_string(),
+        restart: RestartPolicy::Never.to_string(),
+        ..Default::default()
+    };
+
+    match name {
+        "ytbot" => {
+            companion.command = find_binary(
+                "YTBOT_PATH",
+                &[
+                    Path::new("/usr/local/bin/ytbot.sh"),
+                    Path::new("/usr/local/bin/ytbot.py"),
+                ],
+            )?;
+            companion.args = "--monitor_channel={channel_id} --channel_name={channel_name} --rtmp_details={ingest_target}".to_string();
+        }
+        "livestream" => {
+            let venv = home_dir().map(|h| h.join("livebot/venv/bin/streamlink"));
+            let streamlink = find_binary("STREAMLINK_PATH", venv.as_slice())
+                .unwrap_or_else(|| "streamlink".to_string());
+            let ffmpeg = find_binary(
+                "FFMPEG_PATH",
+                &[
+                    Path::new("/usr/bin/ffmpeg"),
+                    Path::new("/usr/local/bin/ffmpeg"),
+                ],
+            )
+            .unwrap_or_else(|| "ffmpeg".to_string());
+            let pipe = format!(
+                "{} --hls-live-edge 6 --ringbuffer-size 128M -4 --stream-sorting-excludes '>720p' \
+                --default-stream best --url \"$FFPLAYOUT_URL\" -o - | {} -re -hide_banner -nostats \
+                -v level+error -progress pipe:1 -stats_period 5 -i pipe:0 -c copy -f flv \
+                \"$FFPLAYOUT_INGEST_URL\"",
+                shlex::try_quote(&streamlink).ok()?,
+                shlex::try_quote(&ffmpeg).ok()?,
+            );
+
+            companion.command = "sh".to_string();
+            companion.args = shlex::try_join(["-c", pipe.as_str()]).ok()?;
+            // the stream ends also with exit code 0, when the source drops
+            companion.restart = RestartPolicy::Always.to_string();
+            companion.max_restarts = RELAY_RETRIES;
+        }
+        _ => return None,
+    }
+
+    Some(companion)
+}
+
+/// Companion from the database, or the built-in one with this name.
+pub async fn find(
+    conn: &Pool<Sqlite>,
+    channel: i32,
+    name: &str,
+) -> Result<Companion, ServiceError> {
+    match handles::select_companion(conn, channel, name).await {
+        Ok(companion) => Ok(companion),
+        Err(sqlx::Error::RowNotFound) => builtin(channel, name)
+            .ok_or_else(|| ServiceError::NoContent(format!("Companion {name} not found"))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CompanionStatus {
+    pub name: String,
+    pub running: bool,
+    pub pid: Option<u32>,
+    /// Start time of the current, or last run.
+    pub started: Option<String>,
+    /// Restarts in a row.
+    pub restarts: usize,
+    pub exit_code: Option<i32>,
+    /// Waits for the next start.
+    pub restarting: bool,
+    /// All restarts are used up.
+    pub gave_up: bool,
+    /// Bitrate in kbit/s, from the ffmpeg progress.
+    pub bitrate: Option<f64>,
+    /// Time of the last data, from the ffmpeg progress.
+    pub last_data: Option<String>,
+    #[serde(skip)]
+    data_at: Option<Instant>,
+    #[serde(skip)]
+    total_size: u64,
+    #[serde(skip)]
+    logs: VecDeque<String>,
+}
+
+struct Runner {
+    autostart: bool,
+    stop: Arc<Notify>,
+    state: Arc<Mutex<CompanionStatus>>,
+    task: JoinHandle<()>,
+}
+
+fn push_log(state: &Mutex<CompanionStatus>, line: String) {
+    let mut state = state.lock().unwrap();
+
+    if state.logs.len() >= LOG_LINES {
+        state.logs.pop_front();
+    }
+
+    state.logs.push_back(line);
+}
+
+async fn read_lines<R: AsyncRead + Unpin>(
+    reader: R,
+    state: Arc<Mutex<CompanionStatus>>,
+    channel: i32,
+    name: String,
+) {
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        match parse_progress(&line) {
+            Some(Progress::Bitrate(bitrate)) => state.lock().unwrap().bitrate = bitrate,
+            Some(Progress::TotalSize(size)) => {
+                let mut state = state.lock().unwrap();
+
+                if size > state.total_size {
+                    state.total_size = size;
+                    state.data_at = Some(Instant::now());
+                    state.last_data = Some(time_now().format("%Y-%m-%d %H:%M:%S").to_string());
+                }
+            }
+            Some(Progress::Other) => {}
+            None => {
+                debug!(target: Target::file(), channel = channel; "[{name}] {line}");
+
+                push_log(&state, line);
+            }
+        }
+    }
+}
+
+fn signal_group(pid: Option<u32>, signal: Signal) {
+    if let Some(pid) = pid {
+        let _ = killpg(Pid::from_raw(pid as i32), signal);
+    }
+}
+
+/// SIGTERM to the process group, kill it after the timeout.
+async fn terminate(child: &mut Child, pid: Option<u32>) {
+    signal_group(pid, Signal::SIGTERM);
+
+    if timeout(STOP_TIMEOUT, child.wait()).await.is_err() {
+        signal_group(pid, Signal::SIGKILL);
+        let _ = child.kill().await;
+    }
+}
+
+async fn supervise(
+    companion: Companion,
+    args: Vec<String>,
+    envs: Vec<(String, String)>,
+    policy: RestartPolicy,
+    stop: Arc<Notify>,
+    state: Arc<Mutex<CompanionStatus>>,
+) {
+    let id = companion.channel_id;
+    let name = companion.name.clone();
+    let mut restarts = 0;
+
+    loop {
+        let mut child = match Command::new(&companion.command)
+            .args(&args)
+            .envs(envs.iter().cloned())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0)
+            .kill_on_drop(true)
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => {
+                error!(target: Target::file_mail(), channel = id; "Companion <b><magenta>{name}</></b> can not start: {e}");
+
+                push_log(&state, format!("Start failed: {e}"));
+                break;
+            }
+        };
+
+        let pid = child.id();
+
+        {
+            let mut state = state.lock().unwrap();
+            state.running = true;
+            state.pid = pid;
+            state.started = Some(time_now().format("%Y-%m-%d %H:%M:%S").to_string());
+            state.restarts = restarts;
+            state.exit_code = None;
+            state.restarting = false;
+            state.bitrate = None;
+            state.data_at = None;
+            state.total_size = 0;
+        }
+
+        info!(target: Target::file(), channel = id; "Start companion <b><magenta>{name}</></b>, pid: <yellow>{}</>", pid.unwrap_or_default());
+
+        if let Some(stdout) = child.stdout.take() {
+            tokio::spawn(read_lines(stdout, state.clone(), id, name.clone()));
+        }
+
+        if let Some(stderr) = child.stderr.take() {
+            tokio::spawn(read_lines(stderr, state.clone(), id, name.clone()));
+        }
+
+        let started = Instant::now();
+        let mut check = interval(Duration::from_secs(5));
+        let status = loop {
+            tokio::select! {
+                status = child.wait() => break status.ok(),
+                () = stop.notified() => {
+                    terminate(&mut child, pid).await;
+
+                    let mut state = state.lock().unwrap();
+                    state.running = false;
+                    state.pid = None;
+
+                    info!(target: Target::file(), channel = id; "Companion <b><magenta>{name}</></b> stopped");
+
+                    return;
+                }
+                _ = check.tick() => {
+                    let last_data = state.lock().unwrap().data_at;
+
+                    if is_stalled(last_data, Instant::now(), STALL_TIMEOUT) {
+                        warn!(target: Target::file(), channel = id; "Companion <b><magenta>{name}</></b> sends no data since <yellow>{}</> seconds", STALL_TIMEOUT.as_secs());
+
+                        terminate(&mut child, pid).await;
+                        break None;
+                    }
+                }
+            }
+        };
+
+        // leftovers, like the first command of a pipe
+        signal_group(pid, Signal::SIGTERM);
+
+        let success = status.is_some_and(|s| s.success());
+        let code = status.and_then(|s| s.code());
+
+        {
+            let mut state = state.lock().unwrap();
+            state.running = false;
+            state.pid = None;
+            state.exit_code = code;
+        }
+
+        if started.elapsed() >= STABLE_RUN {
+            restarts = 0;
+        }
+
+        if !should_restart(policy, success, restarts, companion.max_restarts) {
+            if should_restart(policy, success, 0, 0) {
+                state.lock().unwrap().gave_up = true;
+
+                error!(target: Target::file_mail(), channel = id; "Companion <b><magenta>{name}</></b> gave up after <yellow>{restarts}</> restarts");
+            } else if !success {
+                warn!(target: Target::file_mail(), channel = id; "Companion <b><magenta>{name}</></b> exited with code: <yellow>{}</>", code.unwrap_or(-1));
+            }
+
+            break;
+        }
+
+        if in_maintenance(id) {
+            info!(target: Target::file(), channel = id; "Companion <b><magenta>{name}</></b> exited, no restart in maintenance mode");
+
+            break;
+        }
+
+        restarts += 1;
+        let delay = restart_delay(restarts);
+
+        state.lock().unwrap().restarting = true;
+
+        warn!(target: Target::file(), channel = id; "Companion <b><magenta>{name}</></b> exited, restart in <yellow>{}</> seconds", delay.as_secs());
+
+        tokio::select! {
+            () = sleep(delay) => {}
+            () = stop.notified() => {
+                state.lock().unwrap().restarting = false;
+                return;
+            }
+        }
+    }
+}
+
+/// Start the companion, `vars` are the variables from the request.
+pub fn start(
+    manager: &ChannelManager,
+    companion: Companion,
+    vars: HashMap<String, String>,
+) -> Result<(), ServiceError> {
+    let policy = companion
+        .restart
+        .parse::<RestartPolicy>()
+        .map_err(ServiceError::BadRequest)?;
+    let mut all_vars = channel_vars(manager);
+
+    for (key, value) in vars {
+        if !valid_name(&key) || all_vars.contains_key(&key) {
+            return Err(ServiceError::BadRequest(format!("Invalid variable: {key}")));
+        }
+
+        all_vars.insert(key, value);
+    }
+
+    let args = render_args(&companion.args, &all_vars)?;
+    let envs = all_vars
+        .into_iter()
+        .map(|(k, v)| (format!("FFPLAYOUT_{}", k.to_uppercase()), v))
+        .collect();
+    let key = (companion.channel_id, companion.name.clone());
+    let mut runners = RUNNERS.lock().unwrap();
+
+    if runners.get(&key).is_some_and(|r| !r.task.is_finished()) {
+        return Err(ServiceError::Conflict(format!(
+            "{} is already running",
+            companion.name
+        )));
+    }
+
+    let stop = Arc::new(Notify::new());
+    let state = Arc::new(Mutex::new(CompanionStatus {
+        name: companion.name.clone(),
+        ..Default::default()
+    }));
+    let autostart = companion.autostart;
+    let task = tokio::spawn(supervise(
+        companion,
+        args,
+        envs,
+        policy,
+        stop.clone(),
+        state.clone(),
+    ));
+
+    runners.insert(
+        key,
+        Runner {
+            autostart,
+            stop,
+            state,
+            task,
+        },
+    );
+
+    Ok(())
+}
+
+/// Stop the companion, it gets SIGTERM and is killed after the timeout.
+pub fn stop(channel: i32, name: &str) -> Result<(), ServiceError> {
+    let runners = RUNNERS.lock().unwrap();
+
+    match runners.get(&(channel, name.to_string())) {
+        Some(runner) if !runner.task.is_finished() => {
+            runner.stop.notify_one();
+            Ok(())
+        }
+        _ => Err(ServiceError::NoContent(format!("{name} is not running"))),
+    }
+}
+
+pub fn status(channel: i32, name: &str) -> CompanionStatus {
+    RUNNERS
+        .lock()
+        .unwrap()
+        .get(&(channel, name.to_string()))
+        .map(|r| r.state.lock().unwrap().clone())
+        .unwrap_or_else(|| CompanionStatus {
+            name: name.to_string(),
+            ..Default::default()
+        })
+}
+
+/// Last lines from stdout and stderr.
+pub fn logs(channel: i32, name: &str) -> Vec<String> {
+    RUNNERS
+        .lock()
+        .unwrap()
+        .get(&(channel, name.to_string()))
+        .map(|r| r.state.lock().unwrap().logs.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Stop the matching companions and wait until they are gone.
+async fn stop_where<F: Fn(i32, bool) -> bool>(filter: F) {
+    let runners: Vec<Runner> = {
+        let mut runners = RUNNERS.lock().unwrap();
+        let keys: Vec<(i32, String)> = runners
+            .iter()
+            .filter(|(key, runner)| filter(key.0, runner.autostart))
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        keys.iter().filter_map(|key| runners.remove(key)).collect()
+    };
+
+    for runner in &runners {
+        runner.stop.notify_one();
+    }
+
+    for runner in runners {
+        let _ = timeout(STOP_TIMEOUT + Duration::from_secs(1), runner.task).await;
+    }
+}
+
+/// Start the companions with autostart, together with the playout.
+pub async fn start_auto(conn: &Pool<Sqlite>, manager: &ChannelManager) {
+    let id = manager.channel.lock().unwrap().id;
+
+    match handles::select_companions(conn, id).await {
+        Ok(companions) => {
+            for companion in companions.into_iter().filter(|c| c.autostart) {
+                if status(id, &companion.name).running {
+                    continue;
+                }
+
+                let name = companion.name.clone();
+
+                if let Err(e) = start(manager, companion, HashMap::new()) {
+                    error!(target: Target::file_mail(), channel = id; "Companion <b><magenta>{name}</></b> can not start: {e}");
+                }
+            }
+        }
+        Err(e) => error!(target: Target::file(), channel = id; "Unable to read companions: {e}"),
+    }
+}
+
+/// Stop the companions with autostart, together with the playout.
+pub async fn stop_auto(channel: i32) {
+    stop_where(|id, autostart| id == channel && autostart).await;
+}
+
+/// Stop all companions of a deleted channel.
+pub async fn stop_channel(channel: i32) {
+    stop_where(|id, _| id == channel).await;
+}
+
+/// Stop all companions, on shutdown.
+pub async fn stop_all() {
+    stop_where(|_, _| true).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn companion_restart_policy() {
+        assert_eq!("".parse::<RestartPolicy>(), Ok(RestartPolicy::Never));
+        assert_eq!(
+            "on_failure".parse::<RestartPolicy>(),
+            Ok(RestartPolicy::OnFailure)
+        );
+        assert!("sometimes".parse::<RestartPolicy>().is_err());
+
+        assert!(!should_restart(RestartPolicy::Never, false, 0, 5));
+        assert!(!should_restart(RestartPolicy::OnFailure, true, 0, 5));
+        assert!(should_restart(RestartPolicy::OnFailure, false, 4, 5));
+        assert!(!should_restart(RestartPolicy::OnFailure, false, 5, 5));
+        assert!(should_restart(RestartPolicy::Always, true, 100, 0));
+
+        assert_eq!(restart_delay(1).as_secs(), 2);
+        assert_eq!(restart_delay(3).as_secs(), 8);
+        assert_eq!(restart_delay(20).as_secs(), 60);
+    }
+
+    #[test]
+    fn companion_args() {
+        let vars = HashMap::from([
+            ("channel_id".to_string(), "1".to_string()),
+            ("channel_name".to_string(), "Channel 1".to_string()),
+        ]);
+
+        assert_eq!(
+            render_args("--id={channel_id} --name '{channel_name}'", &vars).unwrap(),
+            vec!["--id=1", "--name", "Channel 1"]
+        );
+        assert_eq!(render_args("", &vars).unwrap(), Vec::<String>::new());
+        assert!(render_args("--url={url}", &vars).is_err());
+        assert!(render_args("--name 'open", &vars).is_err());
+
+        assert_eq!(
+            ingest_target("-f live_flv -listen 1 -i rtmp://0.0.0.0:1936/live/stream"),
+            Some(":1936/live/stream".to_string())
+        );
+        assert_eq!(
+            ingest_target("-i srt://0.0.0.0:40077?mode=listener").as_deref(),
+            Some(":40077?mode=listener")
+        );
+        assert_eq!(ingest_target("-f flv"), None);
+    }
+
+    #[test]
+    fn relay_health() {
+        assert_eq!(
+            parse_progress("bitrate=4520.3kbits/s"),
+            Some(Progress::Bitrate(Some(4520.3)))
+        );
+        assert_eq!(parse_progress("bitrate=N/A"), Some(Progress::Bitrate(None)));
+        assert_eq!(
+            parse_progress("total_size=1048576"),
+            Some(Progress::TotalSize(1048576))
+        );
+        assert_eq!(parse_progress("stream_0_0_q=-1.0"), Some(Progress::Other));
+        assert_eq!(parse_progress("progress=continue"), Some(Progress::Other));
+        assert_eq!(parse_progress("[hls] Opening segment 12"), None);
+        assert_eq!(parse_progress("error: url=none"), None);
+
+        let start = std::time::Instant::now();
+
+        assert!(!is_stalled(None, start + STALL_TIMEOUT, STALL_TIMEOUT));
+        assert!(!is_stalled(Some(start), start, STALL_TIMEOUT));
+        assert!(is_stalled(
+            Some(start),
+            start + STALL_TIMEOUT,
+            STALL_TIMEOUT
+        ));
+    }
+}
@@ -0,0 +1,2391 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use chrono::NaiveTime;
+use flexi_logger::Level;
+use http::header::{HeaderName, HeaderValue};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use shlex::split;
+use sqlx::{Pool, Sqlite};
+use tokio::{fs, io::AsyncReadExt};
+use ts_rs::TS;
+
+use crate::db::{handles, models};
+use crate::player::{
+    output::{
+        broadcast::broadcast_flags,
+        dash::dash_flags,
+        decklink::{decklink_args, video_format},
+        radio::radio_args,
+        scte35::hls_cue_flags,
+        targets::target_args,
+    },
+    utils::passthrough::{house_audio_args, house_video_args},
+};
+use crate::utils::{
+    config_override::{apply_overrides, overrides},
+    files::norm_abs_path,
+    gen_tcp_socket,
+    process_limits::ProcessLimits,
+    time_to_sec,
+};
+use crate::vec_strings;
+use crate::AdvancedConfig;
+use crate::ARGS;
+
+use super::errors::ServiceError;
+
+pub const DUMMY_LEN: f64 = 60.0;
+pub const IMAGE_FORMAT: [&str; 21] = [
+    "bmp", "dds", "dpx", "exr", "gif", "hdr", "j2k", "jpg", "jpeg", "pcx", "pfm", "pgm", "phm",
+    "png", "psd", "ppm", "sgi", "svg", "tga", "tif", "webp",
+];
+
+// Some well known errors can be safely ignore
+pub const FFMPEG_IGNORE_ERRORS: [&str; 13] = [
+    "ac-tex damaged",
+    "codec s302m, is muxed as a private data stream",
+    "corrupt decoded frame in stream",
+    "corrupt input packet in stream",
+    "end mismatch left",
+    "Invalid mb type in I-frame at",
+    "Packet corrupt",
+    "Referenced QT chapter track not found",
+    "skipped MB in I-frame at",
+    "Thread message queue blocking",
+    "timestamp discontinuity",
+    "Warning MVs not available",
+    "frame size not set",
+];
+
+pub const FFMPEG_UNRECOVERABLE_ERRORS: [&str; 6] = [
+    "Address already in use",
+    "Invalid argument",
+    "Numerical result",
+    "Error initializing complex filters",
+    "Error while decoding stream #0:0: Invalid data found when processing input",
+    "Unrecognized option",
+];
+
+#[derive(Debug, Default, Clone, Eq, PartialEq, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(rename_all = "lowercase")]
+pub enum OutputMode {
+    Dash,
+    Decklink,
+    Desktop,
+    #[default]
+    HLS,
+    Null,
+    Stream,
+}
+
+impl OutputMode {
+    fn new(s: &str) -> Self {
+        match s {
+            "dash" => Self::Dash,
+            "decklink" => Self::Decklink,
+            "desktop" => Self::Desktop,
+            "null" => Self::Null,
+            "stream" => Self::Stream,
+            _ => Self::HLS,
+        }
+    }
+}
+
+impl FromStr for OutputMode {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "dash" => Ok(Self::Dash),
+            "decklink" => Ok(Self::Decklink),
+            "desktop" => Ok(Self::Desktop),
+            "hls" => Ok(Self::HLS),
+            "null" => Ok(Self::Null),
+            "stream" => Ok(Self::Stream),
+            _ => Err("Use 'dash', 'decklink', 'desktop', 'hls', 'null' or 'stream'".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for OutputMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OutputMode::Dash => write!(f, "dash"),
+            OutputMode::Decklink => write!(f, "decklink"),
+            OutputMode::Desktop => write!(f, "desktop"),
+            OutputMode::HLS => write!(f, "hls"),
+            OutputMode::Null => write!(f, "null"),
+            OutputMode::Stream => write!(f, "stream"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, Eq, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(rename_all = "lowercase")]
+pub enum ProcessMode {
+    Folder,
+    #[default]
+    Playlist,
+}
+
+impl ProcessMode {
+    fn new(s: &str) -> Self {
+        match s {
+            "folder" => Self::Folder,
+            _ => Self::Playlist,
+        }
+    }
+}
+
+impl fmt::Display for ProcessMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ProcessMode::Folder => write!(f, "folder"),
+            ProcessMode::Playlist => write!(f, "playlist"),
+        }
+    }
+}
+
+impl FromStr for ProcessMode {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "folder" => Ok(Self::Folder),
+            "playlist" => Ok(Self::Playlist),
+            _ => Err("Use 'folder' or 'playlist'".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(rename_all = "lowercase")]
+pub enum TransitionKind {
+    /// Hard cut.
+    #[default]
+    None,
+    /// Fade to black and silence, then fade in.
+    Fade,
+    /// Fade only the audio.
+    Audio,
+    /// Blend into the next clip.
+    Crossfade,
+}
+
+impl TransitionKind {
+    fn new(s: &str) -> Self {
+        match s {
+            "fade" => Self::Fade,
+            "audio" => Self::Audio,
+            "crossfade" => Self::Crossfade,
+            _ => Self::None,
+        }
+    }
+}
+
+impl fmt::Display for TransitionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TransitionKind::None => write!(f, "none"),
+            TransitionKind::Fade => write!(f, "fade"),
+            TransitionKind::Audio => write!(f, "audio"),
+            TransitionKind::Crossfade => write!(f, "crossfade"),
+        }
+    }
+}
+
+/// Handling of clips with another aspect ratio than the channel.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(rename_all = "lowercase")]
+pub enum AspectPolicy {
+    /// Black bars: pillarbox or letterbox.
+    #[default]
+    Pad,
+    /// Cut the picture to the channel aspect.
+    Crop,
+    /// Scale to the channel size, without keeping the aspect.
+    Stretch,
+    /// Picture with its own aspect, over a blurred and zoomed copy of it.
+    Blur,
+}
+
+impl AspectPolicy {
+    fn new(s: &str) -> Self {
+        match s {
+            "crop" => Self::Crop,
+            "stretch" => Self::Stretch,
+            "blur" => Self::Blur,
+            _ => Self::Pad,
+        }
+    }
+}
+
+impl fmt::Display for AspectPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AspectPolicy::Pad => write!(f, "pad"),
+            AspectPolicy::Crop => write!(f, "crop"),
+            AspectPolicy::Stretch => write!(f, "stretch"),
+            AspectPolicy::Blur => write!(f, "blur"),
+        }
+    }
+}
+
+/// Downmix of sources with more channels than the output.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(rename_all = "lowercase")]
+pub enum Downmix {
+    /// Default mix from ffmpeg.
+    #[default]
+    Auto,
+    /// ITU-R BS.775: center and surround channels at -3 dB, without LFE.
+    Itu,
+    /// Front channels and center only, for sources with loud surround channels.
+    Front,
+}
+
+impl Downmix {
+    fn new(s: &str) -> Self {
+        match s {
+            "itu" => Self::Itu,
+            "front" => Self::Front,
+            _ => Self::Auto,
+        }
+    }
+}
+
+impl fmt::Display for Downmix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Downmix::Auto => write!(f, "auto"),
+            Downmix::Itu => write!(f, "itu"),
+            Downmix::Front => write!(f, "front"),
+        }
+    }
+}
+
+/// What happens, when the playlist from the next day is missing shortly before the day rollover.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(rename_all = "lowercase")]
+pub enum PlaylistFallback {
+    #[default]
+    None,
+    Copy,
+    Generate,
+}
+
+impl PlaylistFallback {
+    fn new(s: &str) -> Self {
+        match s {
+            "copy" => Self::Copy,
+            "generate" => Self::Generate,
+            _ => Self::None,
+        }
+    }
+}
+
+impl fmt::Display for PlaylistFallback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PlaylistFallback::None => write!(f, "none"),
+            PlaylistFallback::Copy => write!(f, "copy"),
+            PlaylistFallback::Generate => write!(f, "generate"),
+        }
+    }
+}
+
+/// Transition between two clips, the outgoing clip fades out and the next one fades in.
+/// `Fade` dips video and audio through black/silence, `Audio` fades only the audio,
+/// `Crossfade` blends the end of the clip with the begin of the next clip.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+pub struct Transition {
+    #[serde(default)]
+    pub kind: Option<TransitionKind>,
+    #[serde(default)]
+    pub frames: Option<i32>,
+    /// Length of the audio fade, when it differs from the video fade.
+    #[serde(default)]
+    pub audio_frames: Option<i32>,
+}
+
+/// Audio track of the output.
+///
+/// - `language`: ISO 639 code, like `eng`; the source track with this language is mapped
+/// - `name`: label in the HLS master playlist, like `English` or `Audio Description`
+/// - `source`: source track, when no track has the language; -1 plays silence then
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct AudioTrack {
+    pub language: String,
+    pub name: String,
+    pub source: i32,
+}
+
+impl Default for AudioTrack {
+    fn default() -> Self {
+        Self {
+            language: String::new(),
+            name: String::new(),
+            source: -1,
+        }
+    }
+}
+
+/// Additional logo, which is only shown on given days and in a time window.
+///
+/// - `days`: weekdays from 1 (monday) to 7 (sunday), empty means every day
+/// - `start`/`end`: time window in `HH:MM:SS`, empty means the whole day
+/// - `ingest`: show logo only while a live ingest is running (e.g. a "LIVE" badge)
+///
+/// The path can point to a still image, an animated GIF/APNG or a PNG sequence like `logos/live_%03d.png`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+pub struct LogoRule {
+    pub path: String,
+    #[serde(default)]
+    pub position: String,
+    #[serde(default)]
+    pub scale: String,
+    #[serde(default = "default_opacity")]
+    pub opacity: f64,
+    #[serde(default)]
+    pub days: Vec<u32>,
+    #[serde(default)]
+    pub start: String,
+    #[serde(default)]
+    pub end: String,
+    #[serde(default)]
+    pub ingest: bool,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub abs_path: String,
+}
+
+fn default_opacity() -> f64 {
+    1.0
+}
+
+/// Logo is a file or a image sequence pattern (like `logo_%03d.png`) in an existing folder.
+pub fn logo_exists(path: &Path) -> bool {
+    if path.to_string_lossy().contains('%') {
+        return path.parent().is_some_and(Path::is_dir);
+    }
+
+    path.is_file()
+}
+
+/// Parental rating signaling.
+///
+/// - `enable`: write the rating of the running item to the HLS playlists
+/// - `system`: rating system, like `MPAA`, `VCHIP` or `FSK`
+/// - `default`: rating for items without own rating, empty means no rating
+/// - `mapping`: item ratings to ratings of the channel system, like `{ "PG-13": "12" }`
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct ParentalRating {
+    pub enable: bool,
+    pub system: String,
+    pub default: String,
+    pub mapping: HashMap<String, String>,
+}
+
+impl ParentalRating {
+    /// Rating in the channel system, unknown ratings are kept.
+    pub fn map(&self, rating: Option<&str>) -> Option<String> {
+        let rating = rating
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .or(Some(self.default.trim()).filter(|r| !r.is_empty()))?;
+
+        Some(
+            self.mapping
+                .get(rating)
+                .cloned()
+                .unwrap_or_else(|| rating.to_string()),
+        )
+    }
+}
+
+/// What the channel does outside of its broadcast hours.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(rename_all = "lowercase")]
+pub enum OffAir {
+    #[default]
+    Slate,
+    Off,
+}
+
+/// Broadcast hours of the channel.
+///
+/// - `enable`: the engine enforces the hours
+/// - `start`/`end`: on air window in `HH:MM`, like `06:00` to `24:00`, it can go over midnight (`18:00` to `02:00`)
+/// - `off_air`: `slate` plays the slate outside the hours, `off` stops the channel and starts it again at `start`
+/// - `slate`: image or video, relative paths are in the channel storage, empty means the dead-air fallback
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct AirHours {
+    pub enable: bool,
+    pub start: String,
+    pub end: String,
+    pub off_air: OffAir,
+    pub slate: String,
+}
+
+impl Default for AirHours {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            start: "06:00".to_string(),
+            end: "24:00".to_string(),
+            off_air: OffAir::default(),
+            slate: String::new(),
+        }
+    }
+}
+
+impl AirHours {
+    fn window(&self) -> (f64, f64) {
+        (
+            time_to_sec(&self.start) % 86400.0,
+            time_to_sec(&self.end) % 86400.0,
+        )
+    }
+
+    /// Channel is on air, `sec` is the time of the day in seconds.
+    pub fn on_air(&self, sec: f64) -> bool {
+        let (start, end) = self.window();
+
+        if !self.enable || start == end {
+            return true;
+        }
+
+        if start < end {
+            sec >= start && sec < end
+        } else {
+            sec >= start || sec < end
+        }
+    }
+
+    /// Start and end are times of the day, `24:00` is the end of the day.
+    pub fn is_valid(&self) -> bool {
+        [&self.start, &self.end].iter().all(|time| {
+            let parts: Option<Vec<u32>> = time.split(':').map(|p| p.parse().ok()).collect();
+
+            match parts.as_deref() {
+                Some([h, m]) | Some([h, m, 0]) if *h == 24 => *m == 0,
+                Some([h, m]) => *h < 24 && *m < 60,
+                Some([h, m, s]) => *h < 24 && *m < 60 && *s < 60,
+                _ => false,
+            }
+        })
+    }
+
+    /// Seconds until the channel goes on or off air.
+    pub fn next_change(&self, sec: f64) -> Option<f64> {
+        let (start, end) = self.window();
+
+        if !self.enable || start == end {
+            return None;
+        }
+
+        let target = if self.on_air(sec) { end } else { start };
+        let delta = (target - sec).rem_euclid(86400.0);
+
+        Some(if delta == 0.0 { 86400.0 } else { delta })
+    }
+}
+
+/// Auto ingest of files, which are dropped into a folder.
+///
+/// - `path`: drop folder, absolute or relative to the channel storage
+/// - `target`: folder in the storage, where the files are moved to
+/// - `transcode`: transcode to the house format of the channel
+/// - `append`: add the files to the end of the current playlist
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct WatchFolder {
+    pub enable: bool,
+    pub path: String,
+    pub target: String,
+    pub transcode: bool,
+    pub append: bool,
+}
+
+impl Default for WatchFolder {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            path: String::new(),
+            target: "ingest".to_string(),
+            transcode: false,
+            append: false,
+        }
+    }
+}
+
+/// Pull playlists and media from a FTP or SFTP server, like from a traffic system.
+///
+/// - `url`: `ftp://`, `ftps://` or `sftp://` url of the base folder
+/// - `username`, `password`: login, `key_file` is a private key for SFTP
+/// - `playlist_folder`, `media_folder`: folders below the url, empty to skip
+/// - `target`: folder in the storage, where the media files are saved
+/// - `interval`: minutes between two runs
+/// - `delete`: delete files on the server after they are imported
+/// - `notify`: send a report to the mail recipient and the notifiers after each run with changes
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct PullIngest {
+    pub enable: bool,
+    pub url: String,
+    pub username: String,
+    pub password: String,
+    pub key_file: String,
+    pub playlist_folder: String,
+    pub media_folder: String,
+    pub target: String,
+    pub interval: i64,
+    pub delete: bool,
+    pub notify: bool,
+}
+
+impl Default for PullIngest {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            url: String::new(),
+            username: String::new(),
+            password: String::new(),
+            key_file: String::new(),
+            playlist_folder: "playlists".to_string(),
+            media_folder: "media".to_string(),
+            target: "traffic".to_string(),
+            interval: 60,
+            delete: false,
+            notify: true,
+        }
+    }
+}
+
+/// Media in S3 compatible object storage, the channel storage folder is the local cache.
+///
+/// - `endpoint`, `region`, `bucket`, `access_key`, `secret_key`: S3 connection, path style
+/// - `prefix`: key prefix for the storage root in the bucket
+/// - `cache_size`: size of the local cache in MB, 0 is unlimited
+/// - `prefetch`: seconds of upcoming clips, which are downloaded before they air
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct ObjectStore {
+    pub enable: bool,
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    pub prefix: String,
+    pub access_key: String,
+    pub secret_key: String,
+    pub cache_size: u64,
+    pub prefetch: f64,
+}
+
+impl Default for ObjectStore {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            endpoint: String::new(),
+            region: String::new(),
+            bucket: String::new(),
+            prefix: String::new(),
+            access_key: String::new(),
+            secret_key: String::new(),
+            cache_size: 0,
+            prefetch: 3600.0,
+        }
+    }
+}
+
+/// Local copies of the upcoming clips, from slow or remote storage like NFS or SMB mounts.
+///
+/// - `path`: fast local folder for the copies, each channel gets a sub folder
+/// - `size`: size of the cache in MB, 0 is unlimited
+/// - `look_ahead`: seconds of upcoming clips, which are copied before they air
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct PreCache {
+    pub enable: bool,
+    pub path: String,
+    pub size: u64,
+    pub look_ahead: f64,
+}
+
+impl Default for PreCache {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            path: String::new(),
+            size: 10240,
+            look_ahead: 1800.0,
+        }
+    }
+}
+
+/// Target format for transcoded files, picture size and frame rate are from the processing config.
+///
+/// - `video_codec`, `preset`, `crf`: ffmpeg video encoder and its quality
+/// - `audio_codec`, `audio_bitrate`: ffmpeg audio encoder
+/// - `loudness`: integrated loudness in LUFS, with `true_peak` in dBTP, 0 disables the normalization
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct HouseFormat {
+    pub video_codec: String,
+    pub preset: String,
+    pub crf: i32,
+    pub audio_codec: String,
+    pub audio_bitrate: String,
+    pub loudness: f64,
+    pub true_peak: f64,
+}
+
+impl Default for HouseFormat {
+    fn default() -> Self {
+        Self {
+            video_codec: "libx264".to_string(),
+            preset: "medium".to_string(),
+            crf: 18,
+            audio_codec: "aac".to_string(),
+            audio_bitrate: "192k".to_string(),
+            loudness: -23.0,
+            true_peak: -1.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, TS)]
+pub struct Template {
+    pub sources: Vec<Source>,
+    #[serde(default)]
+    pub rules: ScheduleRules,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, TS)]
+pub struct Source {
+    #[ts(type = "string")]
+    pub start: NaiveTime,
+    #[ts(type = "string")]
+    pub duration: NaiveTime,
+    pub shuffle: bool,
+    pub paths: Vec<PathBuf>,
+    /// Maximal share of the block per category, from 0.0 to 1.0.
+    #[serde(default)]
+    pub quotas: HashMap<String, f64>,
+}
+
+/// Scheduling rules for the template generator.
+///
+/// The folders are the metadata: the first sub folder below a source path is the category,
+/// the folder from a clip is the series.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, TS)]
+#[serde(default)]
+pub struct ScheduleRules {
+    /// Clips from the same series in a row, 0 is unlimited.
+    pub max_series_run: usize,
+    /// Hours until the same clip plays again, also across days.
+    pub min_repeat_hours: f64,
+}
+
+impl ScheduleRules {
+    pub fn is_active(&self) -> bool {
+        self.max_series_run > 0 || self.min_repeat_hours > 0.0
+    }
+}
+
+/// Channel Config
+///
+/// This we init ones, when ffplayout is starting and use them globally in the hole program.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+pub struct PlayoutConfig {
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub channel: Channel,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub advanced: AdvancedConfig,
+    pub general: General,
+    pub mail: Mail,
+    pub logging: Logging,
+    pub processing: Processing,
+    pub ingest: Ingest,
+    pub playlist: Playlist,
+    pub storage: Storage,
+    pub text: Text,
+    pub task: Task,
+    #[serde(alias = "out")]
+    pub output: Output,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, TS)]
+pub struct Channel {
+    pub logs: PathBuf,
+    pub public: PathBuf,
+    pub playlists: PathBuf,
+    pub storage: PathBuf,
+    pub shared: bool,
+    pub headers: Vec<(String, String)>,
+    pub storage_quota: u64,
+    pub public_nowplaying: bool,
+    pub nowplaying_token: String,
+    #[ts(skip)]
+    #[serde(skip)]
+    pub limits: ProcessLimits,
+}
+
+impl Channel {
+    pub fn new(config: &models::GlobalSettings, channel: models::Channel) -> Self {
+        Self {
+            logs: PathBuf::from(config.logs.clone()),
+            public: PathBuf::from(channel.public.clone()),
+            playlists: PathBuf::from(channel.playlists.clone()),
+            storage: PathBuf::from(channel.storage.clone()),
+            shared: config.shared,
+            headers: parse_headers(&channel.public_headers),
+            storage_quota: channel.storage_quota.max(0) as u64,
+            public_nowplaying: channel.public_nowplaying,
+            nowplaying_token: channel.nowplaying_token,
+            limits: ProcessLimits::new(
+                channel.id,
+                &channel.cpu_affinity,
+                channel.nice,
+                channel.memory_limit,
+                &channel.gpu,
+            ),
+        }
+    }
+}
+
+/// Parse custom response headers, one `Name: value` per line.
+/// Invalid lines are skipped.
+pub fn parse_headers(headers: &str) -> Vec<(String, String)> {
+    headers
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .filter(|(name, value)| {
+            HeaderName::from_bytes(name.as_bytes()).is_ok() && HeaderValue::from_str(value).is_ok()
+        })
+        .collect()
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+pub struct General {
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub id: i32,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub channel_id: i32,
+    pub stop_threshold: f64,
+    /// Broadcast hours, the engine plays a slate or stops the channel outside of them.
+    #[serde(default)]
+    pub air_hours: AirHours,
+    /// Run the Lua scripts of the channel, at playlist generation and when the next clip is prepared.
+    #[serde(default)]
+    pub scripting: bool,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub generate: Option<Vec<String>>,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub ffmpeg_filters: Vec<String>,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub ffmpeg_libs: Vec<String>,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub template: Option<Template>,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub skip_validation: bool,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub validate: bool,
+}
+
+impl General {
+    fn new(config: &models::Configuration) -> Self {
+        Self {
+            id: config.id,
+            channel_id: config.channel_id,
+            stop_threshold: config.general_stop_threshold,
+            air_hours: serde_json::from_str(&config.general_air_hours).unwrap_or_default(),
+            scripting: config.general_scripting,
+            generate: None,
+            ffmpeg_filters: vec![],
+            ffmpeg_libs: vec![],
+            template: None,
+            skip_validation: false,
+            validate: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+pub struct Mail {
+    #[serde(skip_deserializing)]
+    pub show: bool,
+    pub subject: String,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub smtp_server: String,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub starttls: bool,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub sender_addr: String,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub sender_pass: String,
+    pub recipient: String,
+    #[ts(type = "string")]
+    pub mail_level: Level,
+    pub interval: i64,
+    /// Chat services, which get alerts like the mail recipient.
+    #[serde(default)]
+    pub notifiers: Vec<Notifier>,
+    /// Seconds until an identical message is sent again.
+    #[serde(default = "default_repeat_limit")]
+    pub repeat_limit: i64,
+}
+
+impl Mail {
+    fn new(global: &models::GlobalSettings, config: &models::Configuration) -> Self {
+        Self {
+            show: !global.mail_password.is_empty() && global.mail_smtp != "mail.example.org",
+            subject: config.mail_subject.clone(),
+            smtp_server: global.mail_smtp.clone(),
+            starttls: global.mail_starttls,
+            sender_addr: global.mail_user.clone(),
+            sender_pass: global.mail_password.clone(),
+            recipient: config.mail_recipient.clone(),
+            mail_level: string_to_log_level(config.mail_level.clone()),
+            interval: config.mail_interval,
+            notifiers: serde_json::from_str(&config.mail_notifiers).unwrap_or_default(),
+            repeat_limit: config.mail_repeat_limit,
+        }
+    }
+}
+
+impl Default for Mail {
+    fn default() -> Self {
+        Mail {
+            show: false,
+            subject: String::default(),
+            smtp_server: String::default(),
+            starttls: bool::default(),
+            sender_addr: String::default(),
+            sender_pass: String::default(),
+            recipient: String::default(),
+            mail_level: Level::Debug,
+            interval: i64::default(),
+            notifiers: vec![],
+            repeat_limit: default_repeat_limit(),
+        }
+    }
+}
+
+fn default_repeat_limit() -> i64 {
+    3600
+}
+
+fn default_file_inline() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(rename_all = "lowercase")]
+pub enum NotifierKind {
+    #[default]
+    Telegram,
+    Discord,
+    Slack,
+}
+
+/// Alert target in a chat service, with its own level.
+///
+/// - `url`: webhook url for Discord and Slack
+/// - `token`, `chat_id`: bot token and chat id for Telegram
+/// - `level`: lowest level which is sent, `INFO`, `WARNING` or `ERROR`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct Notifier {
+    pub kind: NotifierKind,
+    pub enable: bool,
+    pub url: String,
+    pub token: String,
+    pub chat_id: String,
+    pub level: String,
+}
+
+impl Notifier {
+    pub fn log_level(&self) -> Level {
+        string_to_log_level(self.level.clone())
+    }
+}
+
+impl Default for Notifier {
+    fn default() -> Self {
+        Self {
+            kind: NotifierKind::default(),
+            enable: true,
+            url: String::new(),
+            token: String::new(),
+            chat_id: String::new(),
+            level: "ERROR".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+pub struct Logging {
+    pub ffmpeg_level: String,
+    pub ingest_level: String,
+    pub detect_silence: bool,
+    pub ignore_lines: Vec<String>,
+    /// Log level of the channel: `debug`, `info`, `warn` or `error`, empty uses the server level.
+    #[serde(default)]
+    pub level: String,
+    #[serde(default)]
+    pub retention: LogRetention,
+}
+
+impl Logging {
+    fn new(config: &models::Configuration) -> Self {
+        Self {
+            ffmpeg_level: config.logging_ffmpeg_level.clone(),
+            ingest_level: config.logging_ingest_level.clone(),
+            detect_silence: config.logging_detect_silence,
+            ignore_lines: config.logging_ignore.split(';').map(String::from).collect(),
+            level: config.logging_level.clone(),
+            retention: serde_json::from_str(&config.logging_retention).unwrap_or_default(),
+        }
+    }
+}
+
+/// Retention of the log files from one channel.
+///
+/// - `days`: days to keep, `0` uses `--log-backup-count`
+/// - `max_size`: rotate the file also at this size in MB, `0` rotates only daily
+/// - `compress`: compress the rotated files with gzip
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct LogRetention {
+    pub days: usize,
+    pub max_size: u64,
+    pub compress: bool,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+pub struct Processing {
+    pub mode: ProcessMode,
+    pub audio_only: bool,
+    pub copy_audio: bool,
+    pub copy_video: bool,
+    /// Copy clips in the house format, encode only the other clips.
+    #[serde(default)]
+    pub passthrough: bool,
+    pub width: i64,
+    pub height: i64,
+    pub aspect: f64,
+    /// Clips with another aspect, can be overridden per playlist item.
+    #[serde(default)]
+    pub aspect_policy: AspectPolicy,
+    pub fps: f64,
+    pub add_logo: bool,
+    pub logo: String,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub logo_path: String,
+    pub logo_scale: String,
+    pub logo_opacity: f64,
+    pub logo_position: String,
+    pub audio_tracks: i32,
+    #[serde(default = "default_track_index")]
+    pub audio_track_index: i32,
+    pub audio_channels: u8,
+    /// Downmix of 5.1 sources for stereo output, can be overridden per playlist item with a channel map.
+    #[serde(default)]
+    pub audio_downmix: Downmix,
+    pub volume: f64,
+    pub custom_filter: String,
+    #[serde(default)]
+    pub vtt_enable: bool,
+    #[serde(default)]
+    pub vtt_dummy: Option<String>,
+    /// Burn subtitles into the video, instead of passing them through as WebVTT.
+    #[serde(default)]
+    pub subtitle_burn: bool,
+    #[serde(default)]
+    pub transition: TransitionKind,
+    #[serde(default)]
+    pub transition_frames: i32,
+    /// Length of the audio fade, 0 uses the video length.
+    #[serde(default)]
+    pub transition_audio_frames: i32,
+    #[serde(default)]
+    pub logos: Vec<LogoRule>,
+    /// Audio tracks of the output, like main and audio description. Empty maps the first `audio_tracks`.
+    #[serde(default)]
+    pub audio_track_map: Vec<AudioTrack>,
+    /// Seconds of frozen picture or silence, until the dead-air fallback starts. 0 disables the detection.
+    #[serde(default)]
+    pub dead_air_duration: f64,
+    /// Fallback source: image, video or stream url. Empty plays filler.
+    #[serde(default)]
+    pub dead_air_fallback: String,
+    /// Optional audio for a fallback image.
+    #[serde(default)]
+    pub dead_air_audio: String,
+    /// Measure loudness and peak levels of the first audio track, for the VU meters.
+    #[serde(default)]
+    pub audio_meter: bool,
+    /// Format of transcoded files and proxies.
+    #[serde(default)]
+    pub house_format: HouseFormat,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub cmd: Option<Vec<String>>,
+}
+
+impl Processing {
+    fn new(config: &models::Configuration) -> Self {
+        Self {
+            mode: ProcessMode::new(&config.processing_mode.clone()),
+            audio_only: config.processing_audio_only,
+            audio_track_index: config.processing_audio_track_index,
+            copy_audio: config.processing_copy_audio,
+            copy_video: config.processing_copy_video,
+            passthrough: config.processing_passthrough,
+            width: config.processing_width,
+            height: config.processing_height,
+            aspect: config.processing_aspect,
+            aspect_policy: AspectPolicy::new(&config.processing_aspect_policy),
+            fps: config.processing_fps,
+            add_logo: config.processing_add_logo,
+            logo: config.processing_logo.clone(),
+            logo_path: config.processing_logo.clone(),
+            logo_scale: config.processing_logo_scale.clone(),
+            logo_opacity: config.processing_logo_opacity,
+            logo_position: config.processing_logo_position.clone(),
+            audio_tracks: config.processing_audio_tracks,
+            audio_channels: config.processing_audio_channels,
+            audio_downmix: Downmix::new(&config.processing_audio_downmix),
+            volume: config.processing_volume,
+            custom_filter: config.processing_filter.clone(),
+            vtt_enable: config.processing_vtt_enable,
+            vtt_dummy: config.processing_vtt_dummy.clone(),
+            subtitle_burn: config.processing_subtitle_burn,
+            transition: TransitionKind::new(&config.processing_transition),
+            transition_frames: config.processing_transition_frames,
+            transition_audio_frames: config.processing_transition_audio_frames,
+            logos: serde_json::from_str(&config.processing_logos).unwrap_or_default(),
+            audio_track_map: serde_json::from_str(&config.processing_audio_track_map)
+                .unwrap_or_default(),
+            dead_air_duration: config.processing_dead_air_duration,
+            dead_air_fallback: config.processing_dead_air_fallback.clone(),
+            dead_air_audio: config.processing_dead_air_audio.clone(),
+            audio_meter: config.processing_audio_meter,
+            house_format: serde_json::from_str(&config.processing_house_format).unwrap_or_default(),
+            cmd: None,
+        }
+    }
+
+    /// Subtitles are passed through as WebVTT.
+    pub fn subtitle_passthrough(&self) -> bool {
+        self.vtt_enable && !self.subtitle_burn
+    }
+}
+
+/// Publisher authentication for an RTMP server in front of the ingest, like nginx-rtmp or SRS.
+///
+/// - `enable`: the server asks `/ingest/{id}/auth` before it accepts a stream
+/// - `keys`: stream keys of the publishers, the key from the ingest url is always valid
+/// - `allow`: IP addresses or networks (`10.0.0.0/8`), which can publish, empty allows all
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct IngestAuth {
+    pub enable: bool,
+    pub keys: Vec<String>,
+    pub allow: Vec<String>,
+}
+
+/// Preview of the incoming ingest stream, also before it goes on air.
+///
+/// - `enable`: the ingest writes a low latency HLS stream to `/{id}/preview/ingest.m3u8`
+/// - `height`: height of the preview video, the width follows the aspect ratio
+/// - `bitrate`: video bitrate in kbit/s
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct IngestPreview {
+    pub enable: bool,
+    pub height: i64,
+    pub bitrate: i64,
+}
+
+impl Default for IngestPreview {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            height: 360,
+            bitrate: 800,
+        }
+    }
+}
+
+/// WHIP ingest over a WebRTC gateway, like MediaMTX or SRS, which pushes the stream to the ingest.
+///
+/// - `enable`: clients can publish to `/{id}/whip`, with the stream key as bearer token
+/// - `gateway`: WHIP endpoint of the gateway, like `http://127.0.0.1:8889/live/whip`
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct IngestWhip {
+    pub enable: bool,
+    pub gateway: String,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+pub struct Ingest {
+    pub enable: bool,
+    pub input_param: String,
+    pub custom_filter: String,
+    /// Publisher authentication over the callback endpoint.
+    #[serde(default)]
+    pub auth: IngestAuth,
+    /// Low latency preview of the ingest stream.
+    #[serde(default)]
+    pub preview: IngestPreview,
+    /// WebRTC publishing over WHIP.
+    #[serde(default)]
+    pub whip: IngestWhip,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub input_cmd: Option<Vec<String>>,
+}
+
+impl Ingest {
+    fn new(config: &models::Configuration) -> Self {
+        Self {
+            enable: config.ingest_enable,
+            input_param: config.ingest_param.clone(),
+            custom_filter: config.ingest_filter.clone(),
+            auth: serde_json::from_str(&config.ingest_auth).unwrap_or_default(),
+            preview: serde_json::from_str(&config.ingest_preview).unwrap_or_default(),
+            whip: serde_json::from_str(&config.ingest_whip).unwrap_or_default(),
+            input_cmd: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+pub struct Playlist {
+    pub day_start: String,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub start_sec: Option<f64>,
+    pub length: String,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub length_sec: Option<f64>,
+    pub infinit: bool,
+    /// Minutes before the day rollover, in which the next playlist is checked. 0 disables the check.
+    #[serde(default)]
+    pub check_before: i64,
+    #[serde(default)]
+    pub fallback: PlaylistFallback,
+}
+
+impl Playlist {
+    fn new(config: &models::Configuration) -> Self {
+        Self {
+            day_start: config.playlist_day_start.clone(),
+            start_sec: None,
+            length: config.playlist_length.clone(),
+            length_sec: None,
+            infinit: config.playlist_infinit,
+            check_before: config.playlist_check_before,
+            fallback: PlaylistFallback::new(&config.playlist_fallback),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+pub struct Storage {
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub path: PathBuf,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub paths: Vec<PathBuf>,
+    pub filler: String,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub filler_path: PathBuf,
+    /// How often one filler clip can play per day, 0 is unlimited.
+    #[serde(default)]
+    pub filler_max_repeat: i64,
+    /// Prefer filler clips which fit completely into the gap.
+    #[serde(default)]
+    pub filler_fit: bool,
+    /// Sub folders of the filler folder, which are not used. Separated by semicolon.
+    #[serde(default)]
+    pub filler_exclude: String,
+    pub extensions: Vec<String>,
+    pub shuffle: bool,
+    #[serde(skip_deserializing)]
+    pub shared_storage: bool,
+    /// Folder with poster images, relative to storage. Files are matched by the clip title.
+    #[serde(default)]
+    pub artwork_path: String,
+    /// Url for artwork lookup, `{title}` is replaced by the clip title.
+    #[serde(default)]
+    pub artwork_api: String,
+    /// JSON pointer to the image url in the API response.
+    #[serde(default)]
+    pub artwork_field: String,
+    /// Days until removed files are deleted from trash, 0 disables the trash.
+    #[serde(default)]
+    pub trash_retention: i64,
+    /// Files from the storage are shown in the browser, not downloaded.
+    #[serde(default = "default_file_inline")]
+    pub file_inline: bool,
+    /// Folder, from which new files are ingested into the storage.
+    #[serde(default)]
+    pub watch_folder: WatchFolder,
+    /// S3 compatible storage for the media files.
+    #[serde(default)]
+    pub object_store: ObjectStore,
+    /// FTP or SFTP server, from which playlists and media are pulled.
+    #[serde(default)]
+    pub pull_ingest: PullIngest,
+    /// Local copies of the upcoming clips from slow storage.
+    #[serde(default)]
+    pub pre_cache: PreCache,
+}
+
+impl Storage {
+    fn new(config: &models::Configuration, path: PathBuf, shared_storage: bool) -> Self {
+        Self {
+            path,
+            paths: vec![],
+            filler: config.storage_filler.clone(),
+            filler_path: PathBuf::from(config.storage_filler.clone()),
+            filler_max_repeat: config.storage_filler_max_repeat,
+            filler_fit: config.storage_filler_fit,
+            filler_exclude: config.storage_filler_exclude.clone(),
+            extensions: config
+                .storage_extensions
+                .split(';')
+                .map(String::from)
+                .collect(),
+            shuffle: config.storage_shuffle,
+            shared_storage,
+            artwork_path: config.storage_artwork_path.clone(),
+            artwork_api: config.storage_artwork_api.clone(),
+            artwork_field: config.storage_artwork_field.clone(),
+            trash_retention: config.storage_trash_retention,
+            file_inline: config.storage_file_inline,
+            watch_folder: serde_json::from_str(&config.storage_watch_folder).unwrap_or_default(),
+            object_store: serde_json::from_str(&config.storage_object_store).unwrap_or_default(),
+            pull_ingest: serde_json::from_str(&config.storage_pull_ingest).unwrap_or_default(),
+            pre_cache: serde_json::from_str(&config.storage_pre_cache).unwrap_or_default(),
+        }
+    }
+}
+
+/// Web radio, an audio only channel which streams to an Icecast or SHOUTcast server.
+///
+/// - `server`: `icecast` or `shoutcast`
+/// - `host`: address of the server, like `radio.example.org:8000`
+/// - `mount`: mount point, SHOUTcast uses the stream id instead, like `/1`
+/// - `username`/`password`: source login, which also updates the metadata
+/// - `name`: name of the station
+/// - `codec`: `mp3` or `aac`, with `bitrate` in kbit/s
+/// - `metadata`: now playing text, with the placeholders `{title}`, `{category}` and `{file}`
+/// - `hls`: audio HLS variant in `radio/radio.m3u8` from the public folder
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct Radio {
+    pub enable: bool,
+    pub server: String,
+    pub host: String,
+    pub mount: String,
+    pub username: String,
+    pub password: String,
+    pub name: String,
+    pub codec: String,
+    pub bitrate: i64,
+    pub metadata: String,
+    pub hls: bool,
+}
+
+impl Default for Radio {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            server: "icecast".to_string(),
+            host: "127.0.0.1:8000".to_string(),
+            mount: "/radio".to_string(),
+            username: "source".to_string(),
+            password: String::new(),
+            name: String::new(),
+            codec: "mp3".to_string(),
+            bitrate: 128,
+            metadata: "{title}".to_string(),
+            hls: false,
+        }
+    }
+}
+
+/// Blackmagic Decklink SDI output, which replaces the output parameters in decklink mode.
+///
+/// - `device`: name of the card, like `DeckLink Duo (1)`, the system endpoint lists the installed cards
+/// - `format`: video format, like `1080i50`, `1080p25`, `720p59.94` or `576i50`
+/// - `ten_bit`: 10 bit video (v210) instead of 8 bit (uyvy422)
+/// - `audio_channels`: 2, 8 or 16 embedded audio channels
+/// - `audio_map`: source of each SDI channel as `track.channel`, like `1.0`; empty plays silence,
+///   without a map the channels from the tracks follow each other
+/// - `timing_offset`: genlock timing offset in pixels
+/// - `duplex_mode`: `unset`, `half` or `full`
+/// - `link`: `unset`, `single`, `dual` or `quad` link SDI
+/// - `preroll`: seconds, which are buffered before the playback starts
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct Decklink {
+    pub device: String,
+    pub format: String,
+    pub ten_bit: bool,
+    pub audio_channels: u8,
+    pub audio_map: Vec<String>,
+    pub timing_offset: Option<i32>,
+    pub duplex_mode: String,
+    pub link: String,
+    pub preroll: f64,
+}
+
+impl Default for Decklink {
+    fn default() -> Self {
+        Self {
+            device: String::new(),
+            format: "1080i50".to_string(),
+            ten_bit: false,
+            audio_channels: 2,
+            audio_map: vec![],
+            timing_offset: None,
+            duplex_mode: "unset".to_string(),
+            link: "unset".to_string(),
+            preroll: 0.5,
+        }
+    }
+}
+
+/// Field order of the output.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(rename_all = "lowercase")]
+pub enum FieldOrder {
+    #[default]
+    Progressive,
+    /// Top field first, like 1080i25.
+    Tff,
+    /// Bottom field first, like SD NTSC.
+    Bff,
+}
+
+/// Output for traditional broadcast chains.
+///
+/// - `field_order`: interlaced output with `tff` or `bff`; with a frame rate above 30 two frames
+///   become one interlaced frame, like 50p to 1080i25, otherwise the frames are only flagged
+/// - `bt709`: convert and flag the colors as BT.709, limited range
+/// - `legalizer`: clip luma to 16-235 and chroma to 16-240
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct Broadcast {
+    pub field_order: FieldOrder,
+    pub bt709: bool,
+    pub legalizer: bool,
+}
+
+/// Delayed copy of the HLS output, for a time shifted channel like +1h.
+///
+/// - `delay`: seconds from the live output to the delayed output
+/// - `output_param`: ffmpeg output parameters, which push the delayed stream to a server;
+///   without parameters the delayed stream is only written as HLS to the `delayed` folder
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct DelayedOutput {
+    pub enable: bool,
+    pub delay: f64,
+    pub output_param: String,
+}
+
+impl Default for DelayedOutput {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            delay: 3600.0,
+            output_param: String::new(),
+        }
+    }
+}
+
+/// Slate in the HLS output, while the channel is stopped.
+///
+/// - `image`: image for the slate, relative paths are in the channel storage, empty is black
+/// - `audio`: audio file, which plays in a loop, empty is silence
+/// - `text`: text in the center of the slate, like "Channel offline"
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct OfflineSlate {
+    pub enable: bool,
+    pub image: String,
+    pub audio: String,
+    pub text: String,
+}
+
+impl Default for OfflineSlate {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            image: String::new(),
+            audio: String::new(),
+            text: "Channel offline".to_string(),
+        }
+    }
+}
+
+/// Low bitrate preview of the program output, for monitoring in the web UI.
+///
+/// - `height`: video height, the width follows the aspect
+/// - `bitrate`: video bitrate in kbit/s
+/// - `gateway`: RTMP or RTSP url of a WebRTC gateway, like MediaMTX, which gets the preview too
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct ProgramPreview {
+    pub enable: bool,
+    pub height: i64,
+    pub bitrate: i64,
+    pub gateway: String,
+}
+
+impl Default for ProgramPreview {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            height: 360,
+            bitrate: 600,
+            gateway: String::new(),
+        }
+    }
+}
+
+/// Alarms for silence and black picture in the program output.
+///
+/// - `silence`: seconds of silence until the alarm, 0 disables it
+/// - `silence_level`: noise level in dB, under which the audio counts as silence
+/// - `black`: seconds of black picture until the alarm, 0 disables it
+/// - `black_level`: luminance from 0.0 to 1.0, under which a pixel counts as black
+/// - `cooldown`: seconds after an alarm, in which the next alarm of the same kind is only logged
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct OutputAlarms {
+    pub silence: f64,
+    pub silence_level: f64,
+    pub black: f64,
+    pub black_level: f64,
+    pub cooldown: i64,
+}
+
+impl Default for OutputAlarms {
+    fn default() -> Self {
+        Self {
+            silence: 0.0,
+            silence_level: -50.0,
+            black: 0.0,
+            black_level: 0.1,
+            cooldown: 900,
+        }
+    }
+}
+
+impl OutputAlarms {
+    pub fn enabled(&self) -> bool {
+        self.silence > 0.0 || self.black > 0.0
+    }
+}
+
+/// Image and HTML overlay, which is shown and hidden over the control API.
+///
+/// - `browser`: Chromium compatible browser, which renders HTML pages headless to images
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct ImageOverlay {
+    pub enable: bool,
+    pub browser: String,
+}
+
+impl Default for ImageOverlay {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            browser: "chromium".to_string(),
+        }
+    }
+}
+
+/// Dynamic text overlays, which are rendered with drawtext from text files.
+///
+/// - `clock`: current time in `clock_format` (strftime)
+/// - `countdown`: time until the next program, `countdown_text` has the placeholders
+///   `{title}` and `{time}`; clips with a category from `countdown_skip` (separated by semicolon)
+///   are no program, like commercials
+/// - `ticker`: crawl with the text from `ticker_url`, an API endpoint with plain text or
+///   a JSON list, or a RSS/Atom feed; it is loaded again every `ticker_interval` seconds
+/// - `*_style`: drawtext parameters, like position, size and color
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+#[serde(default)]
+pub struct Widgets {
+    pub clock: bool,
+    pub clock_format: String,
+    pub clock_style: String,
+    pub countdown: bool,
+    pub countdown_text: String,
+    pub countdown_skip: String,
+    pub countdown_style: String,
+    pub ticker: bool,
+    pub ticker_url: String,
+    pub ticker_interval: i64,
+    pub ticker_separator: String,
+    /// Speed in pixel per second.
+    pub ticker_speed: f64,
+    pub ticker_style: String,
+}
+
+impl Default for Widgets {
+    fn default() -> Self {
+        Self {
+            clock: false,
+            clock_format: "%H:%M:%S".to_string(),
+            clock_style: "x=w-tw-40:y=40:fontsize=32:fontcolor=white:borderw=2".to_string(),
+            countdown: false,
+            countdown_text: "Next: {title} in {time}".to_string(),
+            countdown_skip: "advertisement;filler".to_string(),
+            countdown_style: "x=40:y=40:fontsize=28:fontcolor=white:borderw=2".to_string(),
+            ticker: false,
+            ticker_url: String::new(),
+            ticker_interval: 300,
+            ticker_separator: " +++ ".to_string(),
+            ticker_speed: 120.0,
+            ticker_style:
+                "y=h-th-30:fontsize=30:fontcolor=white:box=1:boxcolor=black@0.6:boxborderw=8"
+                    .to_string(),
+        }
+    }
+}
+
+impl Widgets {
+    pub fn enabled(&self) -> bool {
+        self.clock || self.countdown || (self.ticker && !self.ticker_url.is_empty())
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+pub struct Text {
+    pub add_text: bool,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub node_pos: Option<usize>,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub zmq_stream_socket: Option<String>,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub zmq_server_socket: Option<String>,
+    #[serde(alias = "fontfile")]
+    pub font: String,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub font_path: String,
+    pub text_from_filename: bool,
+    pub style: String,
+    pub regex: String,
+    /// Clock, countdown and ticker overlays.
+    #[serde(default)]
+    pub widgets: Widgets,
+    /// Image and HTML overlay from the control API.
+    #[serde(default)]
+    pub overlay: ImageOverlay,
+}
+
+impl Text {
+    fn new(config: &models::Configuration) -> Self {
+        Self {
+            add_text: config.text_add,
+            node_pos: None,
+            zmq_stream_socket: None,
+            zmq_server_socket: None,
+            font: config.text_font.clone(),
+            font_path: config.text_font.clone(),
+            text_from_filename: config.text_from_filename,
+            style: config.text_style.clone(),
+            regex: config.text_regex.clone(),
+            widgets: serde_json::from_str(&config.text_widgets).unwrap_or_default(),
+            overlay: serde_json::from_str(&config.text_overlay).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+pub struct Task {
+    pub enable: bool,
+    pub path: PathBuf,
+}
+
+impl Task {
+    fn new(config: &models::Configuration) -> Self {
+        Self {
+            enable: config.task_enable,
+            path: PathBuf::from(config.task_path.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize, TS)]
+#[ts(export, export_to = "playout_config.d.ts")]
+pub struct Output {
+    pub mode: OutputMode,
+    pub output_param: String,
+    #[serde(default)]
+    pub s3_enable: bool,
+    #[serde(default)]
+    pub s3_endpoint: String,
+    #[serde(default)]
+    pub s3_region: String,
+    #[serde(default)]
+    pub s3_bucket: String,
+    #[serde(default)]
+    pub s3_prefix: String,
+    #[serde(default)]
+    pub s3_access_key: String,
+    #[serde(default)]
+    pub s3_secret_key: String,
+    #[serde(default)]
+    pub s3_concurrency: i32,
+    /// Write SCTE-35 markers for ad breaks to the HLS playlists.
+    #[serde(default)]
+    pub scte35: bool,
+    /// Write title and category of the playing item to the HLS playlists.
+    #[serde(default)]
+    pub item_metadata: bool,
+    /// Parental rating signaling for HLS and the program export.
+    #[serde(default)]
+    pub rating: ParentalRating,
+    /// Output parameters for a backup encoder, which takes over when the main encoder fails.
+    #[serde(default)]
+    pub backup_param: String,
+    /// Web radio output, which replaces the output parameters in stream mode.
+    #[serde(default)]
+    pub radio: Radio,
+    /// SDI output in decklink mode.
+    #[serde(default)]
+    pub decklink: Decklink,
+    /// Time shifted copy of the HLS output.
+    #[serde(default)]
+    pub delayed: DelayedOutput,
+    /// Interlacing and broadcast legal colors.
+    #[serde(default)]
+    pub broadcast: Broadcast,
+    /// Slate in the HLS output, while the channel is stopped.
+    #[serde(default)]
+    pub offline: OfflineSlate,
+    /// Low bitrate preview of the program output.
+    #[serde(default)]
+    pub preview: ProgramPreview,
+    /// Silence and black picture alarms.
+    #[serde(default)]
+    pub alarms: OutputAlarms,
+    /// Destinations of the stream output, which replace the one from the output parameters.
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub targets: Vec<models::OutputTarget>,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub output_count: usize,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub output_filter: Option<String>,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub output_cmd: Option<Vec<String>>,
+    #[ts(skip)]
+    #[serde(skip_serializing, skip_deserializing)]
+    pub backup_cmd: Option<Vec<String>>,
+}
+
+impl Output {
+    fn new(config: &models::Configuration) -> Self {
+        Self {
+            mode: OutputMode::new(&config.output_mode),
+            output_param: config.output_param.clone(),
+            s3_enable: config.output_s3_enable,
+            s3_endpoint: config.output_s3_endpoint.clone(),
+            s3_region: config.output_s3_region.clone(),
+            s3_bucket: config.output_s3_bucket.clone(),
+            s3_prefix: config.output_s3_prefix.clone(),
+            s3_access_key: config.output_s3_access_key.clone(),
+            s3_secret_key: config.output_s3_secret_key.clone(),
+            s3_concurrency: config.output_s3_concurrency,
+            scte35: config.output_scte35,
+            item_metadata: config.output_item_metadata,
+            rating: serde_json::from_str(&config.output_rating).unwrap_or_default(),
+            backup_param: config.output_backup_param.clone(),
+            radio: serde_json::from_str(&config.output_radio).unwrap_or_default(),
+            decklink: serde_json::from_str(&config.output_decklink).unwrap_or_default(),
+            delayed: serde_json::from_str(&config.output_delayed).unwrap_or_default(),
+            broadcast: serde_json::from_str(&config.output_broadcast).unwrap_or_default(),
+            offline: serde_json::from_str(&config.output_offline).unwrap_or_default(),
+            preview: serde_json::from_str(&config.output_preview).unwrap_or_default(),
+            alarms: serde_json::from_str(&config.output_alarms).unwrap_or_default(),
+            targets: vec![],
+            output_count: 0,
+            output_filter: None,
+            output_cmd: None,
+            backup_cmd: None,
+        }
+    }
+}
+
+pub fn string_to_log_level(l: String) -> Level {
+    match l.to_lowercase().as_str() {
+        "error" => Level::Error,
+        "info" => Level::Info,
+        "trace" => Level::Trace,
+        "warning" => Level::Warn,
+        _ => Level::Debug,
+    }
+}
+
+pub fn string_to_processing_mode(l: String) -> ProcessMode {
+    match l.to_lowercase().as_str() {
+        "playlist" => ProcessMode::Playlist,
+        "folder" => ProcessMode::Folder,
+        _ => ProcessMode::Playlist,
+    }
+}
+
+pub fn string_to_output_mode(l: String) -> OutputMode {
+    match l.to_lowercase().as_str() {
+        "dash" => OutputMode::Dash,
+        "decklink" => OutputMode::Decklink,
+        "desktop" => OutputMode::Desktop,
+        "hls" => OutputMode::HLS,
+        "null" => OutputMode::Null,
+        "stream" => OutputMode::Stream,
+        _ => OutputMode::HLS,
+    }
+}
+
+fn default_track_index() -> i32 {
+    -1
+}
+
+// fn default_tracks() -> i32 {
+//     1
+// }
+
+// fn default_channels() -> u8 {
+//     2
+// }
+
+impl PlayoutConfig {
+    pub async fn new(pool: &Pool<Sqlite>, channel_id: i32) -> Result<Self, ServiceError> {
+        let global = handles::select_global(pool).await?;
+        let channel = handles::select_channel(pool, &channel_id).await?;
+        let config = handles::select_configuration(pool, channel_id).await?;
+        let config = overrides(channel_id)
+            .and_then(|o| apply_overrides(config, &o))
+            .map_err(ServiceError::Conflict)?;
+        let adv_config = handles::select_advanced_configuration(pool, channel_id).await?;
+
+        let channel = Channel::new(&global, channel);
+        let advanced = AdvancedConfig::new(adv_config);
+        let general = General::new(&config);
+        let mail = Mail::new(&global, &config);
+        let logging = Logging::new(&config);
+        let mut processing = Processing::new(&config);
+        let mut ingest = Ingest::new(&config);
+        let mut playlist = Playlist::new(&config);
+        let mut text = Text::new(&config);
+        let task = Task::new(&config);
+        let mut output = Output::new(&config);
+
+        output.targets = handles::select_output_targets(pool, channel_id).await?;
+
+        if !channel.storage.is_dir() {
+            tokio::fs::create_dir_all(&channel.storage)
+                .await
+                .unwrap_or_else(|_| panic!("Can't create storage folder: {:#?}", channel.storage));
+        }
+
+        let mut storage = Storage::new(&config, channel.storage.clone(), channel.shared);
+
+        if !channel.playlists.is_dir() {
+            tokio::fs::create_dir_all(&channel.playlists).await?;
+        }
+
+        if !channel.logs.is_dir() {
+            tokio::fs::create_dir_all(&channel.logs).await?;
+        }
+
+        let (filler_path, _, filler) = norm_abs_path(&channel.storage, &config.storage_filler)?;
+
+        storage.filler = filler;
+        storage.filler_path = filler_path;
+
+        playlist.start_sec = Some(time_to_sec(&playlist.day_start));
+
+        if playlist.length.contains(':') {
+            playlist.length_sec = Some(time_to_sec(&playlist.length));
+        } else {
+            playlist.length_sec = Some(86400.0);
+        }
+
+        let (logo_path, _, logo) = norm_abs_path(&channel.storage, &processing.logo)?;
+
+        if processing.add_logo && !logo_exists(&logo_path) {
+            processing.add_logo = false;
+        }
+
+        processing.logo = logo;
+        processing.logo_path = logo_path.to_string_lossy().to_string();
+
+        for rule in &mut processing.logos {
+            let (rule_path, _, _) = norm_abs_path(&channel.storage, &rule.path)?;
+            rule.abs_path = rule_path.to_string_lossy().to_string();
+        }
+
+        if !processing.audio_track_map.is_empty() {
+            // every track from the map goes to the output
+            processing.audio_tracks = processing.audio_track_map.len() as i32;
+            processing.audio_track_index = -1;
+        }
+
+        if processing.audio_tracks < 1 {
+            processing.audio_tracks = 1;
+        }
+
+        let is_radio = output.radio.enable && output.mode == OutputMode::Stream;
+
+        // web radio needs no video, the clips are decoded without it
+        if is_radio {
+            processing.audio_only = true;
+        }
+
+        let is_decklink = output.mode == OutputMode::Decklink;
+
+        // the card plays only its own video format
+        if is_decklink {
+            processing.audio_only = false;
+
+            if let Some(format) = video_format(&output.decklink.format) {
+                processing.width = format.width;
+                processing.height = format.height;
+                processing.fps = format.fps;
+                processing.aspect = format.aspect;
+            }
+        }
+
+        let mut process_cmd = vec_strings![];
+
+        if processing.audio_only {
+            process_cmd.append(&mut vec_strings!["-vn"]);
+        } else if processing.copy_video {
+            process_cmd.append(&mut vec_strings!["-c:v", "copy"]);
+        } else if processing.passthrough {
+            process_cmd.append(&mut house_video_args(&processing));
+        } else if let Some(decoder_cmd) = &advanced.decoder.output_cmd {
+            process_cmd.append(&mut decoder_cmd.clone());
+        } else {
+            let bitrate = format!("{}k", processing.width * processing.height / 16);
+            let buff_size = format!("{}k", (processing.width * processing.height / 16) / 2);
+
+            process_cmd.append(&mut vec_strings![
+                "-pix_fmt",
+                "yuv420p",
+                "-r",
+                &processing.fps,
+                "-c:v",
+                "mpeg2video",
+                "-g",
+                "1",
+                "-b:v",
+                &bitrate,
+                "-minrate",
+                &bitrate,
+                "-maxrate",
+                &bitrate,
+                "-bufsize",
+                &buff_size,
+                "-mpegts_flags",
+                "initial_discontinuity"
+            ]);
+        }
+
+        if processing.copy_audio {
+            process_cmd.append(&mut vec_strings!["-c:a", "copy"]);
+        } else if processing.passthrough {
+            process_cmd.append(&mut house_audio_args(&processing));
+        } else if advanced.decoder.output_cmd.is_none() {
+            process_cmd.append(&mut pre_audio_codec(
+                &processing.custom_filter,
+                &ingest.custom_filter,
+                processing.audio_channels,
+            ));
+        }
+
+        process_cmd.append(&mut vec_strings!["-f", "mpegts", "-"]);
+
+        processing.cmd = Some(process_cmd);
+
+        ingest.input_cmd = split(ingest.input_param.as_str());
+
+        output.output_count = 1;
+        output.output_filter = None;
+
+        let output_param = if is_radio {
+            Some(radio_args(&output.radio))
+        } else if is_decklink {
+            Some(decklink_args(&output.decklink))
+        } else {
+            split(output.output_param.as_str())
+        };
+
+        if output.mode == OutputMode::Null {
+            output.output_cmd = Some(vec_strings!["-f", "null", "-"]);
+        } else if let Some(mut cmd) = output_param {
+            // get output count according to the var_stream_map value, or by counting output parameters
+            if let Some(i) = cmd.clone().iter().position(|m| m == "-var_stream_map") {
+                output.output_count = cmd[i + 1].split_whitespace().count();
+            } else {
+                output.output_count = cmd
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, p)| i > &0 && !p.starts_with('-') && !cmd[i - 1].starts_with('-'))
+                    .count();
+            }
+
+            if let Some(i) = cmd.clone().iter().position(|r| r == "-filter_complex") {
+                output.output_filter = Some(cmd[i + 1].clone());
+                cmd.remove(i);
+                cmd.remove(i);
+            }
+
+            let is_tee_muxer = cmd.contains(&"tee".to_string());
+            let re_ts = Regex::new(r"filename=(\S+?\.ts)").unwrap();
+            let re_m3 = Regex::new(r"\](\S+?\.m3u8)").unwrap();
+
+            for item in &mut cmd {
+                if item.ends_with(".ts")
+                    || (item.ends_with(".m3u8") && item != "master.m3u8")
+                    || item.ends_with(".mpd")
+                {
+                    if is_tee_muxer {
+                        // Processes the `item` string to replace `.ts` and `.m3u8` filenames with their absolute paths.
+                        // Ensures that the corresponding directories exist.
+                        //
+                        // - Uses regular expressions to identify `.ts` and `.m3u8` filenames within the `item` string.
+                        // - For each identified filename, normalizes its path and checks if the parent directory exists.
+                        // - Creates the parent directory if it does not exist.
+                        // - Replaces the original filename in the `item` string with the normalized absolute path.
+                        for s in item.clone().split('|') {
+                            if let Some(ts) = re_ts.captures(s).and_then(|p| p.get(1)) {
+                                let (segment_path, _, _) =
+                                    norm_abs_path(&channel.public, ts.as_str())?;
+                                let parent = segment_path.parent().ok_or("HLS parent path")?;
+
+                                if !parent.is_dir() {
+                                    fs::create_dir_all(parent).await?;
+                                }
+
+                                item.clone_from(
+                                    &item.replace(ts.as_str(), &segment_path.to_string_lossy()),
+                                );
+                            }
+
+                            if let Some(m3) = re_m3.captures(s).and_then(|p| p.get(1)) {
+                                let (m3u8_path, _, _) =
+                                    norm_abs_path(&channel.public, m3.as_str())?;
+                                let parent = m3u8_path.parent().ok_or("HLS parent path")?;
+
+                                if !parent.is_dir() {
+                                    fs::create_dir_all(parent).await?;
+                                }
+
+                                item.clone_from(
+                                    &item.replace(m3.as_str(), &m3u8_path.to_string_lossy()),
+                                );
+                            }
+                        }
+                    } else if let Ok((public, _, _)) = norm_abs_path(&channel.public, item) {
+                        let parent = public.parent().ok_or("HLS parent path")?;
+
+                        if !parent.is_dir() {
+                            fs::create_dir_all(parent).await?;
+                        }
+                        item.clone_from(&public.to_string_lossy().to_string());
+                    };
+                }
+            }
+
+            if output.scte35 || output.rating.enable || output.item_metadata {
+                hls_cue_flags(&mut cmd);
+            }
+
+            if output.mode == OutputMode::Dash {
+                dash_flags(&mut cmd);
+            }
+
+            if !processing.audio_only
+                && matches!(
+                    output.mode,
+                    OutputMode::Stream | OutputMode::HLS | OutputMode::Dash
+                )
+            {
+                broadcast_flags(&mut cmd, &output.broadcast);
+            }
+
+            if output.mode == OutputMode::Stream && !is_radio && !output.targets.is_empty() {
+                cmd = target_args(cmd, &output.targets);
+            }
+
+            output.output_cmd = Some(cmd);
+        }
+
+        output.backup_cmd = None;
+
+        // backup encoder runs only in stream and dash mode
+        if matches!(output.mode, OutputMode::Stream | OutputMode::Dash) {
+            if let Some(mut cmd) = split(output.backup_param.as_str()).filter(|c| !c.is_empty()) {
+                for item in &mut cmd {
+                    if item.ends_with(".ts") || item.ends_with(".mpd") {
+                        if let Ok((public, _, _)) = norm_abs_path(&channel.public, item) {
+                            let parent = public.parent().ok_or("Backup parent path")?;
+
+                            if !parent.is_dir() {
+                                fs::create_dir_all(parent).await?;
+                            }
+                            item.clone_from(&public.to_string_lossy().to_string());
+                        }
+                    }
+                }
+
+                if output.mode == OutputMode::Dash {
+                    dash_flags(&mut cmd);
+                }
+
+                if !processing.audio_only {
+                    broadcast_flags(&mut cmd, &output.broadcast);
+                }
+
+                output.backup_cmd = Some(cmd);
+            }
+        }
+
+        // when text overlay without text_from_filename is on, turn also the RPC server on,
+        // to get text messages from it
+        if text.add_text && !text.text_from_filename {
+            text.zmq_stream_socket = gen_tcp_socket("");
+            text.zmq_server_socket =
+                gen_tcp_socket(&text.zmq_stream_socket.clone().unwrap_or_default());
+            text.node_pos = Some(2);
+        } else {
+            text.zmq_stream_socket = None;
+            text.zmq_server_socket = None;
+            text.node_pos = None;
+        }
+
+        let (font_path, _, font) = norm_abs_path(&channel.storage, &text.font)?;
+        text.font = font;
+        text.font_path = font_path.to_string_lossy().to_string();
+
+        Ok(Self {
+            channel,
+            advanced,
+            general,
+            mail,
+            logging,
+            processing,
+            ingest,
+            playlist,
+            storage,
+            text,
+            task,
+            output,
+        })
+    }
+
+    pub async fn dump(pool: &Pool<Sqlite>, id: i32) -> Result<(), ServiceError> {
+        let config = Self::new(pool, id).await?;
+
+        let toml_string = toml_edit::ser::to_string_pretty(&config)?;
+        tokio::fs::write(&format!("ffplayout_{id}.toml"), toml_string).await?;
+
+        Ok(())
+    }
+
+    pub async fn import(pool: &Pool<Sqlite>, id: i32, path: &Path) -> Result<(), ServiceError> {
+        if path.is_file() {
+            let mut file = tokio::fs::File::open(path).await?;
+            let mut contents = String::new();
+            file.read_to_string(&mut contents).await?;
+
+            let config: PlayoutConfig = toml_edit::de::from_str(&contents).unwrap();
+
+            handles::update_configuration(pool, id, config).await?;
+        } else {
+            return Err(ServiceError::BadRequest("Path not exists!".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+// impl Default for PlayoutConfig {
+//     fn default() -> Self {
+//         Self::new(1)
+//     }
+// }
+
+/// When custom_filter contains loudnorm filter use a different audio encoder,
+/// s302m has higher quality, but is experimental
+/// and works not well together with the loudnorm filter.
+fn pre_audio_codec(proc_filter: &str, ingest_filter: &str, channel_count: u8) -> Vec<String> {
+    let mut codec = vec_strings![
+        "-c:a",
+        "s302m",
+        "-strict",
+        "-2",
+        "-sample_fmt",
+        "s16",
+        "-ar",
+        "48000",
+        "-ac",
+        channel_count
+    ];
+
+    if proc_filter.contains("loudnorm") || ingest_filter.contains("loudnorm") {
+        codec = vec_strings![
+            "-c:a",
+            "mp2",
+            "-b:a",
+            "384k",
+            "-ar",
+            "48000",
+            "-ac",
+            channel_count
+        ];
+    }
+
+    codec
+}
+
+/// Read command line arguments, and override the config with them.
+pub async fn get_config(
+    pool: &Pool<Sqlite>,
+    channel_id: i32,
+) -> Result<PlayoutConfig, ServiceError> {
+    let mut config = PlayoutConfig::new(pool, channel_id).await?;
+    let args = ARGS.clone();
+
+    config.general.generate = args.generate;
+    config.general.validate = args.validate;
+    config.general.skip_validation = args.skip_validation;
+
+    if let Some(template_file) = args.template {
+        let mut f = fs::File::options()
+            .read(true)
+            .write(false)
+            .open(template_file)
+            .await?;
+        let mut buffer = Vec::new();
+        f.read_to_end(&mut buffer).await?;
+
+        let mut template: Template = serde_json::from_slice(&buffer)?;
+
+        template.sources.sort_by_key(|d| d.start);
+
+        config.general.template = Some(template);
+    }
+
+    if let Some(paths) = args.paths {
+        config.storage.paths = paths;
+    }
+
+    if let Some(playlist) = args.playlists {
+        config.channel.playlists = PathBuf::from(&playlist);
+    }
+
+    if let Some(folder) = args.folder {
+        config.channel.storage = folder;
+        config.processing.mode = ProcessMode::Folder;
+    }
+
+    if let Some(start) = args.start {
+        config.playlist.day_start.clone_from(&start);
+        config.playlist.start_sec = Some(time_to_sec(&start));
+    }
+
+    if let Some(output) = args.output {
+        config.output.mode = output;
+
+        if config.output.mode == OutputMode::Null {
+            config.output.output_count = 1;
+            config.output.output_filter = None;
+            config.output.output_cmd = Some(vec_strings!["-f", "null", "-"]);
+        } else if config.output.mode == OutputMode::Decklink {
+            if let Some(format) = video_format(&config.output.decklink.format) {
+                config.processing.width = format.width;
+                config.processing.height = format.height;
+                config.processing.fps = format.fps;
+                config.processing.aspect = format.aspect;
+            }
+
+            config.output.output_count = 1;
+            config.output.output_filter = None;
+            config.output.output_cmd = Some(decklink_args(&config.output.decklink));
+        }
+    }
+
+    if let Some(volume) = args.volume {
+        config.processing.volume = volume;
+    }
+
+    if let Some(mail_smtp) = args.mail_smtp {
+        config.mail.smtp_server = mail_smtp;
+    }
+
+    if let Some(mail_user) = args.mail_user {
+        config.mail.sender_addr = mail_user;
+    }
+
+    if let Some(mail_password) = args.mail_password {
+        config.mail.sender_pass = mail_password;
+    }
+
+    if args.mail_starttls {
+        config.mail.starttls = true;
+    }
+
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_headers() {
+        let headers = parse_headers(
+        "Access-Control-Allow-Origin: *\nCache-Control: max-age=2, public\n\ninvalid line\nBad Name: x",
+    );
+
+        assert_eq!(
+            headers,
+            vec![
+                ("Access-Control-Allow-Origin".to_string(), "*".to_string()),
+                ("Cache-Control".to_string(), "max-age=2, public".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn air_hours_window() {
+        let mut air = AirHours {
+            enable: true,
+            ..Default::default()
+        };
+
+        assert!(!air.on_air(3600.0));
+        assert!(air.on_air(21600.0));
+        assert!(air.on_air(86399.0));
+        assert_eq!(air.next_change(3600.0), Some(18000.0));
+        assert_eq!(air.next_change(82800.0), Some(3600.0));
+
+        // over midnight
+        air.start = "18:00".to_string();
+        air.end = "02:00".to_string();
+
+        assert!(air.on_air(3600.0));
+        assert!(!air.on_air(43200.0));
+        assert_eq!(air.next_change(3600.0), Some(3600.0));
+        assert_eq!(air.next_change(7200.0), Some(57600.0));
+
+        air.enable = false;
+
+        assert!(air.on_air(43200.0));
+        assert_eq!(air.next_change(43200.0), None);
+        assert!(air.is_valid());
+
+        air.end = "24:00".to_string();
+        assert!(air.is_valid());
+
+        air.end = "24:30".to_string();
+        assert!(!air.is_valid());
+
+        air.end = "6 pm".to_string();
+        assert!(!air.is_valid());
+    }
+}
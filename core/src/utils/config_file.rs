@@ -0,0 +1,129 @@
+/*
+Export and import of the channel settings.
+
+The playout config and the advanced config of a channel go into one TOML document, with the
+tables `[playout.*]` and `[advanced.*]`, so the settings can be versioned in git and moved
+between channels or servers. TOML is also the format of the config dumps from the command line.
+
+An import is checked against the config structs: missing keys, wrong types and unknown keys are
+rejected. The changes against the running config are returned as a list of dotted keys, with a
+dry run nothing is saved.
+
+*/
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::utils::{advanced_config::AdvancedConfig, config::PlayoutConfig, errors::ServiceError};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigDocument {
+    pub playout: PlayoutConfig,
+    pub advanced: AdvancedConfig,
+}
+
+impl ConfigDocument {
+    pub fn new(config: &PlayoutConfig) -> Self {
+        Self {
+            playout: config.clone(),
+            advanced: config.advanced.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigChange {
+    pub key: String,
+    /// `None` when the key is new.
+    pub old: Option<Value>,
+    /// `None` when the key is removed.
+    pub new: Option<Value>,
+}
+
+/// Settings of the channel as TOML document.
+pub fn export_config(config: &PlayoutConfig) -> Result<String, ServiceError> {
+    let toml_string = toml_edit::ser::to_string_pretty(&ConfigDocument::new(config))?;
+
+    Ok(format!(
+        "# ffplayout {} - settings of channel {}\n\n{toml_string}",
+        env!("CARGO_PKG_VERSION"),
+        config.general.channel_id
+    ))
+}
+
+/// Leaf values of a JSON value with their dotted keys, arrays count as one value.
+fn flatten(prefix: &str, value: &Value, keys: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+
+                flatten(&key, value, keys);
+            }
+        }
+        _ => {
+            keys.insert(prefix.to_string(), value.clone());
+        }
+    }
+}
+
+/// Keys from `raw`, which are not in `known`.
+pub fn unknown_keys(raw: &Value, known: &Value) -> Vec<String> {
+    let mut raw_keys = BTreeMap::new();
+    let mut known_keys = BTreeMap::new();
+
+    flatten("", raw, &mut raw_keys);
+    flatten("", known, &mut known_keys);
+
+    raw_keys
+        .into_keys()
+        .filter(|k| !known_keys.contains_key(k))
+        .collect()
+}
+
+/// Changed, new and removed keys from `old` to `new`.
+pub fn config_diff(old: &Value, new: &Value) -> Vec<ConfigChange> {
+    let mut old_keys = BTreeMap::new();
+    let mut new_keys = BTreeMap::new();
+
+    flatten("", old, &mut old_keys);
+    flatten("", new, &mut new_keys);
+
+    let mut keys: Vec<&String> = old_keys.keys().chain(new_keys.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    keys.into_iter()
+        .filter(|k| old_keys.get(*k) != new_keys.get(*k))
+        .map(|k| ConfigChange {
+            key: k.clone(),
+            old: old_keys.get(k).cloned(),
+            new: new_keys.get(k).cloned(),
+        })
+        .collect()
+}
+
+/// Parse and check a TOML document from `export_config`.
+pub fn parse_config(text: &str) -> Result<ConfigDocument, ServiceError> {
+    let raw: Value = toml_edit::de::from_str(text)
+        .map_err(|e| ServiceError::BadRequest(format!("Invalid TOML: {e}")))?;
+    let doc: ConfigDocument = toml_edit::de::from_str(text)
+        .map_err(|e| ServiceError::BadRequest(format!("Invalid config: {e}")))?;
+    let unknown = unknown_keys(&raw, &serde_json::to_value(&doc)?);
+
+    if !unknown.is_empty() {
+        return Err(ServiceError::BadRequest(format!(
+            "Unknown keys in config: {}",
+            unknown.join(", ")
+        )));
+    }
+
+    Ok(doc)
+}
@@ -0,0 +1,202 @@
+/*
+Config overrides from environment variables and the command line.
+
+The overrides apply on top of the values from the database, every time a channel config loads,
+so containers can set fields without touching the database:
+
+- `FFPLAYOUT__OUTPUT__MODE=hls` for all channels, `FFPLAYOUT_2__OUTPUT__MODE=hls` for channel 2
+- `--set output.mode=hls` for all channels, `--set 2:output.mode=hls` for channel 2
+
+The key is the section and the field, like in the config, the command line wins over the
+environment and a channel override over a global one. Overridden values are not saved, but
+saving the config in the frontend writes the values it shows into the database.
+
+*/
+
+use serde_json::Value;
+
+use crate::db::models::Configuration;
+use crate::ARGS;
+
+pub const ENV_PREFIX: &str = "FFPLAYOUT";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigOverride {
+    /// `None` for all channels.
+    pub channel: Option<i32>,
+    /// Column name, like `output_mode`.
+    pub key: String,
+    pub value: String,
+}
+
+/// Override from an environment variable, other variables return `None`.
+pub fn parse_env(name: &str, value: &str) -> Option<ConfigOverride> {
+    let rest = name.strip_prefix(ENV_PREFIX)?;
+    let (channel, path) = match rest.strip_prefix("__") {
+        Some(path) => (None, path),
+        None => {
+            let (id, path) = rest.strip_prefix('_')?.split_once("__")?;
+
+            (Some(id.parse().ok()?), path)
+        }
+    };
+
+    if path.is_empty() {
+        return None;
+    }
+
+    Some(ConfigOverride {
+        channel,
+        key: path.replace("__", "_").to_lowercase(),
+        value: value.to_string(),
+    })
+}
+
+/// Override from the command line, like `output.mode=hls` or `2:output.mode=hls`.
+pub fn parse_arg(arg: &str) -> Result<ConfigOverride, String> {
+    let (key, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("Missing value in config override: {arg}"))?;
+    let (channel, key) = match key.split_once(':') {
+        Some((id, key)) => (
+            Some(
+                id.trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid channel in config override: {arg}"))?,
+            ),
+            key,
+        ),
+        None => (None, key),
+    };
+
+    Ok(ConfigOverride {
+        channel,
+        key: key.trim().replace('.', "_").to_lowercase(),
+        value: value.to_string(),
+    })
+}
+
+/// Overrides for the channel, in the order to apply: environment before command line,
+/// global before channel.
+pub fn layered(
+    env: Vec<ConfigOverride>,
+    cli: Vec<ConfigOverride>,
+    channel_id: i32,
+) -> Vec<ConfigOverride> {
+    let mut overrides = vec![];
+
+    for mut layer in [env, cli] {
+        layer.retain(|o| o.channel.is_none_or(|c| c == channel_id));
+        layer.sort_by_key(|o| o.channel.is_some());
+        overrides.append(&mut layer);
+    }
+
+    overrides
+}
+
+/// Overrides for the channel from the environment and the command line.
+pub fn overrides(channel_id: i32) -> Result<Vec<ConfigOverride>, String> {
+    let mut env: Vec<ConfigOverride> = std::env::vars()
+        .filter_map(|(name, value)| parse_env(&name, &value))
+        .collect();
+    let cli = ARGS
+        .config_set
+        .iter()
+        .flatten()
+        .map(|a| parse_arg(a))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // same order at every start
+    env.sort_by(|a, b| a.key.cmp(&b.key));
+
+    Ok(layered(env, cli, channel_id))
+}
+
+/// Value from text, with the type of the current value.
+fn typed_value(current: &Value, text: &str) -> Option<Value> {
+    match current {
+        Value::Bool(_) => match text.trim().to_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => Some(Value::Bool(true)),
+            "false" | "0" | "no" | "off" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        Value::Number(n) if n.is_f64() => text
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number),
+        Value::Number(_) => text.trim().parse::<i64>().ok().map(Value::from),
+        _ => Some(Value::String(text.to_string())),
+    }
+}
+
+/// Apply the overrides to the config from the database.
+pub fn apply_overrides(
+    config: Configuration,
+    overrides: &[ConfigOverride],
+) -> Result<Configuration, String> {
+    if overrides.is_empty() {
+        return Ok(config);
+    }
+
+    let mut value = serde_json::to_value(&config).map_err(|e| e.to_string())?;
+    let fields = value.as_object_mut().ok_or("Invalid config")?;
+
+    for o in overrides {
+        let current = fields
+            .get(&o.key)
+            .filter(|_| !matches!(o.key.as_str(), "id" | "channel_id"))
+            .ok_or_else(|| format!("Unknown config key in override: {}", o.key))?;
+        let new = typed_value(current, &o.value)
+            .ok_or_else(|| format!("Invalid value for {}: {}", o.key, o.value))?;
+
+        fields.insert(o.key.clone(), new);
+    }
+
+    serde_json::from_value(value).map_err(|e| format!("Invalid config override: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_overrides() {
+        let global = parse_env("FFPLAYOUT__OUTPUT__MODE", "hls").unwrap();
+
+        assert_eq!(
+            global,
+            ConfigOverride {
+                channel: None,
+                key: "output_mode".to_string(),
+                value: "hls".to_string()
+            }
+        );
+
+        let channel = parse_env("FFPLAYOUT_2__GENERAL__STOP_THRESHOLD", "5").unwrap();
+
+        assert_eq!(channel.channel, Some(2));
+        assert_eq!(channel.key, "general_stop_threshold");
+        assert!(parse_env("FFPLAYOUT_X__OUTPUT__MODE", "hls").is_none());
+        assert!(parse_env("FFPLAYOUT__", "hls").is_none());
+        assert!(parse_env("LISTEN", "127.0.0.1:8787").is_none());
+
+        let arg = parse_arg("2:output.mode=desktop").unwrap();
+
+        assert_eq!(arg.channel, Some(2));
+        assert_eq!(arg.key, "output_mode");
+        assert_eq!(arg.value, "desktop");
+        assert_eq!(parse_arg("processing.width=1280").unwrap().channel, None);
+        assert!(parse_arg("output.mode").is_err());
+        assert!(parse_arg("x:output.mode=hls").is_err());
+
+        let order = layered(
+            vec![channel.clone(), global.clone()],
+            vec![arg.clone(), parse_arg("3:output.mode=null").unwrap()],
+            2,
+        );
+
+        assert_eq!(order, vec![global, channel, arg]);
+    }
+}
@@ -0,0 +1,497 @@
+use std::{
+    error::Error,
+    fmt, fs,
+    str::FromStr,
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use chrono::TimeDelta;
+use log::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sqlx::{Pool, Sqlite};
+use zeromq::{Socket, SocketRecv, SocketSend, ZmqMessage};
+
+use crate::db::handles;
+use crate::player::{
+    controller::{ChannelManager, ProcessUnit::*},
+    input::{
+        emergency::{
+            remaining_time, text_file, OverrideEnd, OverrideKind, OverrideParams, OverrideState,
+        },
+        switch::{InputSource, InputSwitch, SwitchParams, SwitchTransition},
+    },
+    output::scte35::CueKind,
+    utils::{get_delta, get_media_map, is_remote, time_in_seconds},
+};
+use crate::utils::{
+    config::OutputMode::*, errors::ServiceError, files::norm_abs_path, logging::Target,
+    time_machine::time_now, TextFilter,
+};
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ControlParams {
+    pub control: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Scte35Params {
+    #[serde(default)]
+    pub cue: CueKind,
+    /// Length of the break in seconds.
+    #[serde(default)]
+    pub duration: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessCtl {
+    Status,
+    Start,
+    Stop,
+    Restart,
+}
+
+impl FromStr for ProcessCtl {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.to_lowercase().as_str() {
+            "status" => Ok(Self::Status),
+            "start" => Ok(Self::Start),
+            "stop" => Ok(Self::Stop),
+            "restart" => Ok(Self::Restart),
+            _ => Err(format!("Command '{input}' not found!")),
+        }
+    }
+}
+
+impl fmt::Display for ProcessCtl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Status => write!(f, "status"),
+            Self::Start => write!(f, "start"),
+            Self::Stop => write!(f, "stop"),
+            Self::Restart => write!(f, "restart"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Process {
+    pub command: ProcessCtl,
+}
+
+async fn zmq_send(msg: &str, socket_addr: &str) -> Result<String, Box<dyn Error>> {
+    let mut socket = zeromq::ReqSocket::new();
+    socket.connect(&format!("tcp://{socket_addr}")).await?;
+    socket.send(msg.into()).await?;
+    let repl: ZmqMessage = socket.recv().await?;
+    let response = String::from_utf8(repl.into_vec()[0].to_vec())?;
+
+    Ok(response)
+}
+
+pub async fn send_message(
+    manager: ChannelManager,
+    message: TextFilter,
+) -> Result<Map<String, Value>, ServiceError> {
+    let filter = message.to_string();
+    let mut data_map = Map::new();
+    let config = manager.config.lock().unwrap().clone();
+    let id = config.general.channel_id;
+
+    if config.text.zmq_stream_socket.is_some() {
+        if let Some(clips_filter) = manager.filter_chain.clone() {
+            *clips_filter.lock().unwrap() = vec![filter.clone()];
+        }
+
+        if config.output.mode == HLS {
+            if manager.ingest_is_running.load(Ordering::SeqCst) {
+                let filter_server = format!("drawtext@dyntext reinit {filter}");
+
+                if let Ok(reply) = zmq_send(
+                    &filter_server,
+                    &config.text.zmq_server_socket.clone().unwrap(),
+                )
+                .await
+                {
+                    data_map.insert("message".to_string(), json!(reply));
+                    return Ok(data_map);
+                };
+            } else if let Err(e) = manager.stop(Ingest) {
+                error!(target: Target::file_mail(), channel = id; "Ingest {e:?}");
+            }
+        }
+
+        if config.output.mode != HLS || !manager.ingest_is_running.load(Ordering::SeqCst) {
+            let filter_stream = format!("drawtext@dyntext reinit {filter}");
+
+            if let Ok(reply) = zmq_send(
+                &filter_stream,
+                &config.text.zmq_stream_socket.clone().unwrap(),
+            )
+            .await
+            {
+                data_map.insert("message".to_string(), json!(reply));
+                return Ok(data_map);
+            };
+        }
+    }
+
+    Err(ServiceError::ServiceUnavailable(
+        "text message missing!".to_string(),
+    ))
+}
+
+pub async fn control_state(
+    conn: &Pool<Sqlite>,
+    manager: &ChannelManager,
+    command: &str,
+) -> Result<Map<String, Value>, ServiceError> {
+    let config = manager.config.lock().unwrap().clone();
+    let id = config.general.channel_id;
+    let current_date = manager.current_date.lock().unwrap().clone();
+    let current_list = manager.current_list.lock().unwrap().clone();
+    let mut date = manager.current_date.lock().unwrap().clone();
+    let index = manager.current_index.load(Ordering::SeqCst);
+
+    match command {
+        "back" => {
+            if index > 1 && current_list.len() > 1 {
+                let mut data_map = Map::new();
+                let mut media = current_list[index - 2].clone();
+                let (delta, _) = get_delta(&config, &media.begin.unwrap_or(0.0));
+
+                info!(target: Target::file_mail(), channel = id; "Move to last clip");
+
+                manager.current_index.fetch_sub(2, Ordering::SeqCst);
+
+                if let Err(e) = media.add_probe(false) {
+                    error!(target: Target::file_mail(), channel = id; "{e:?}");
+                };
+
+                manager.channel.lock().unwrap().time_shift = delta;
+                date.clone_from(&current_date);
+                handles::update_stat(conn, config.general.channel_id, Some(current_date), delta)
+                    .await?;
+
+                if manager.stop(Decoder).is_err() {
+                    return Err(ServiceError::InternalServerError);
+                };
+
+                data_map.insert("operation".to_string(), json!("move_to_last"));
+                data_map.insert("shifted_seconds".to_string(), json!(delta));
+                data_map.insert("media".to_string(), get_media_map(media));
+
+                return Ok(data_map);
+            }
+        }
+
+        "next" => {
+            if index < current_list.len() {
+                let mut data_map = Map::new();
+                let mut media = current_list[index].clone();
+                let (delta, _) = get_delta(&config, &media.begin.unwrap_or(0.0));
+
+                info!(target: Target::file_mail(), channel = id; "Move to next clip");
+
+                if let Err(e) = media.add_probe(false) {
+                    error!(target: Target::file_mail(), channel = id; "{e:?}");
+                };
+
+                manager.channel.lock().unwrap().time_shift = delta;
+                date.clone_from(&current_date);
+                handles::update_stat(conn, config.general.channel_id, Some(current_date), delta)
+                    .await?;
+
+                if manager.stop(Decoder).is_err() {
+                    return Err(ServiceError::InternalServerError);
+                };
+
+                data_map.insert("operation".to_string(), json!("move_to_next"));
+                data_map.insert("shifted_seconds".to_string(), json!(delta));
+                data_map.insert("media".to_string(), get_media_map(media));
+
+                return Ok(data_map);
+            }
+        }
+
+        "reset" => {
+            let mut data_map = Map::new();
+
+            info!(target: Target::file_mail(), channel = id; "Reset playout to original state");
+
+            manager.channel.lock().unwrap().time_shift = 0.0;
+            date.clone_from(&current_date);
+            manager.list_init.store(true, Ordering::SeqCst);
+
+            handles::update_stat(conn, config.general.channel_id, Some(current_date), 0.0).await?;
+
+            if manager.stop(Decoder).is_err() {
+                return Err(ServiceError::InternalServerError);
+            };
+
+            data_map.insert("operation".to_string(), json!("reset_playout_state"));
+
+            return Ok(data_map);
+        }
+
+        _ => {
+            return Err(ServiceError::ServiceUnavailable(
+                "Command not found!".to_string(),
+            ))
+        }
+    }
+
+    Ok(Map::new())
+}
+
+/// Switch the program output to playlist, live ingest or a external input.
+pub fn switch_source(
+    manager: &ChannelManager,
+    params: SwitchParams,
+) -> Result<InputSwitch, ServiceError> {
+    let config = manager.config.lock().unwrap().clone();
+    let id = config.general.channel_id;
+    let ingest_on = manager.ingest_is_running.load(Ordering::SeqCst);
+    let url = params.url.trim().to_string();
+
+    match params.source {
+        InputSource::Live if !config.ingest.enable => {
+            return Err(ServiceError::BadRequest(
+                "Live ingest is not enabled".to_string(),
+            ));
+        }
+        InputSource::Live if !ingest_on => {
+            return Err(ServiceError::Conflict(
+                "No live ingest is connected".to_string(),
+            ));
+        }
+        InputSource::External if !is_remote(&url) => {
+            return Err(ServiceError::BadRequest(
+                "External input needs a RTMP/SRT/UDP url".to_string(),
+            ));
+        }
+        _ => (),
+    }
+
+    let old = manager.input_switch.lock().unwrap().source;
+    let external = old == InputSource::External || params.source == InputSource::External;
+    let live_changes = ingest_on && old.accepts_ingest() != params.source.accepts_ingest();
+    // fade in only the decoder sources, the live ingest fades in by itself
+    let decoder_follows = !(ingest_on && params.source.accepts_ingest());
+    let fade = match params.transition {
+        SwitchTransition::Fade if (external || live_changes) && decoder_follows => {
+            Some(params.duration.unwrap_or(1.0).clamp(0.1, 10.0))
+        }
+        _ => None,
+    };
+    let state = InputSwitch {
+        source: params.source,
+        url: if params.source == InputSource::External {
+            url
+        } else {
+            String::new()
+        },
+        since: Some(time_now().format("%Y-%m-%d %H:%M:%S").to_string()),
+        fade,
+    };
+
+    info!(target: Target::file_mail(), channel = id; "Switch input from <yellow>{old}</> to <yellow>{}</>", state.source);
+
+    *manager.input_switch.lock().unwrap() = state.clone();
+
+    if ingest_on && !params.source.accepts_ingest() && config.output.mode == HLS {
+        // HLS ingest writes the output by itself
+        if let Err(e) = manager.stop(Ingest) {
+            error!(target: Target::file_mail(), channel = id; "{e}");
+        }
+    }
+
+    if external {
+        manager.list_init.store(true, Ordering::SeqCst);
+
+        if manager.stop(Decoder).is_err() {
+            return Err(ServiceError::InternalServerError);
+        };
+    }
+
+    Ok(state)
+}
+
+/// Play the override now, or return to the schedule with `release`.
+pub fn set_override(
+    manager: &ChannelManager,
+    params: OverrideParams,
+) -> Result<OverrideState, ServiceError> {
+    let config = manager.config.lock().unwrap().clone();
+    let id = config.general.channel_id;
+    let ingest_on = manager.ingest_is_running.load(Ordering::SeqCst);
+    let url = params.url.trim().to_string();
+    let old = manager.emergency.lock().unwrap().clone();
+
+    if params.source == OverrideKind::Release {
+        if !old.active {
+            return Err(ServiceError::Conflict("No override is running".to_string()));
+        }
+
+        info!(target: Target::file_mail(), channel = id; "Release override, back to schedule");
+
+        *manager.emergency.lock().unwrap() = OverrideState::default();
+        let _ = fs::remove_file(text_file(id));
+
+        manager.list_init.store(true, Ordering::SeqCst);
+
+        if manager.stop(Decoder).is_err() {
+            return Err(ServiceError::InternalServerError);
+        };
+
+        return Ok(OverrideState::default());
+    }
+
+    match params.source {
+        OverrideKind::Live if !is_remote(&url) => {
+            return Err(ServiceError::BadRequest(
+                "Live override needs a RTMP/SRT/UDP url".to_string(),
+            ));
+        }
+        OverrideKind::Clip | OverrideKind::Slate if !params.path.is_empty() => {
+            let (path, _, _) = norm_abs_path(&config.channel.storage, &params.path)?;
+
+            if !path.is_file() {
+                return Err(ServiceError::BadRequest(format!(
+                    "Source {} not exists!",
+                    params.path
+                )));
+            }
+        }
+        OverrideKind::Clip => {
+            return Err(ServiceError::BadRequest(
+                "Clip override needs a path".to_string(),
+            ));
+        }
+        _ => (),
+    }
+
+    let now = time_now();
+    let deadline = match params.until {
+        // a new override keeps the end from the running one
+        OverrideEnd::Next if old.active && old.deadline.is_some() => old.deadline,
+        OverrideEnd::Next => {
+            let shift = manager.channel.lock().unwrap().time_shift;
+            let remaining = manager
+                .current_media
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|node| remaining_time(node, time_in_seconds(), shift))
+                .unwrap_or_default();
+
+            if remaining < 1.0 {
+                return Err(ServiceError::Conflict(
+                    "Running item ends now, no time for an override".to_string(),
+                ));
+            }
+
+            Some(Instant::now() + Duration::from_secs_f64(remaining))
+        }
+        OverrideEnd::Release => None,
+    };
+
+    if params.source == OverrideKind::Slate && !params.text.trim().is_empty() {
+        fs::write(text_file(id), params.text.trim())?;
+    }
+
+    let state = OverrideState {
+        active: true,
+        source: Some(params.source),
+        url: if params.source == OverrideKind::Live {
+            url
+        } else {
+            String::new()
+        },
+        path: if params.source == OverrideKind::Live {
+            String::new()
+        } else {
+            params.path
+        },
+        text: if params.source == OverrideKind::Slate {
+            params.text.trim().to_string()
+        } else {
+            String::new()
+        },
+        until: params.until,
+        since: Some(now.format("%Y-%m-%d %H:%M:%S").to_string()),
+        ends: deadline.map(|d| {
+            let remaining = d.saturating_duration_since(Instant::now()).as_millis();
+
+            (now + TimeDelta::milliseconds(remaining as i64))
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        }),
+        deadline,
+    };
+
+    warn!(target: Target::file_mail(), channel = id; "Override with <yellow>{}</>, until <yellow>{}</>",
+        params.source,
+        state.ends.as_deref().unwrap_or("release")
+    );
+
+    *manager.emergency.lock().unwrap() = state.clone();
+
+    if ingest_on && config.output.mode == HLS {
+        // HLS ingest writes the output by itself
+        if let Err(e) = manager.stop(Ingest) {
+            error!(target: Target::file_mail(), channel = id; "{e}");
+        }
+    }
+
+    manager.list_init.store(true, Ordering::SeqCst);
+
+    if manager.stop(Decoder).is_err() {
+        return Err(ServiceError::InternalServerError);
+    };
+
+    Ok(state)
+}
+
+/// Start or end a SCTE-35 ad break now.
+pub fn splice_cue(
+    manager: &ChannelManager,
+    params: &Scte35Params,
+) -> Result<Map<String, Value>, ServiceError> {
+    let config = manager.config.lock().unwrap().clone();
+    let id = config.general.channel_id;
+    let mut data_map = Map::new();
+
+    if !config.output.scte35 {
+        return Err(ServiceError::BadRequest(
+            "SCTE-35 is not enabled".to_string(),
+        ));
+    }
+
+    let cue = match params.cue {
+        CueKind::Out if params.duration <= 0.0 => {
+            return Err(ServiceError::BadRequest(
+                "Ad break needs a duration".to_string(),
+            ));
+        }
+        CueKind::Out => manager.cues.lock().unwrap().cue_out(params.duration),
+        CueKind::In => manager
+            .cues
+            .lock()
+            .unwrap()
+            .cue_in()
+            .ok_or_else(|| ServiceError::Conflict("No ad break is running".to_string()))?,
+    };
+
+    info!(target: Target::file_mail(), channel = id; "SCTE-35 splice <yellow>{:?}</> ({})", cue.kind, cue.id);
+
+    data_map.insert("id".to_string(), json!(cue.id));
+    data_map.insert("cue".to_string(), json!(cue.kind));
+    data_map.insert("duration".to_string(), json!(cue.duration));
+
+    Ok(data_map)
+}
@@ -0,0 +1,396 @@
+/*
+Downloads of remote urls with yt-dlp.
+
+Jobs are stored in the database and one worker runs them one after the other, oldest first,
+like the transcoding jobs. yt-dlp supports YouTube, Vimeo and many other sites, direct links to
+media files are downloaded too.
+
+Without an own format selector the format is chosen to match the house format of the channel:
+not larger than the channel resolution and frame rate, merged into the container of the house
+format. Audio channels get the audio only.
+
+The file is stored in the target folder of the storage. On request it is added to the end of the
+playlist from a date, the playlist is created when it not exists.
+
+yt-dlp is taken from `YTDLP_PATH`, or from the search path.
+
+*/
+
+use std::{
+    collections::HashSet,
+    env, fs,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::{Arc, LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::NaiveDate;
+use log::*;
+use sqlx::{Pool, Sqlite};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    process::Command,
+    sync::Notify,
+    time::{sleep, timeout},
+};
+
+use crate::db::{handles, models::DownloadJob};
+use crate::player::{
+    controller::{ChannelController, ChannelManager},
+    utils::MediaProbe,
+};
+use crate::utils::{
+    config::PlayoutConfig,
+    errors::ServiceError,
+    files::norm_abs_path,
+    logging::Target,
+    time_machine::time_now,
+    transcode::{house_extension, CANCELED, DONE, FAILED, QUEUED, RUNNING},
+    watch_folder::append_to_playlist,
+};
+
+/// Interval in which the progress of a running download is saved.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Prefix of the progress lines from yt-dlp.
+const PROGRESS_PREFIX: &str = "[progress]";
+
+/// Running downloads, which have to be stopped.
+static CANCEL: LazyLock<Mutex<HashSet<i32>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Wakes the worker, when new downloads are queued.
+static WAKE: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+fn timestamp() -> String {
+    time_now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+fn ytdlp_path() -> String {
+    env::var("YTDLP_PATH").unwrap_or_else(|_| "yt-dlp".to_string())
+}
+
+/// yt-dlp arguments without the url, for a download into `dir`.
+pub fn download_args(config: &PlayoutConfig, format: &str, dir: &Path) -> Vec<String> {
+    let processing = &config.processing;
+    let mut args = vec![
+        "--newline".to_string(),
+        "--no-playlist".to_string(),
+        "--progress".to_string(),
+        "--progress-template".to_string(),
+        format!("download:{PROGRESS_PREFIX} %(progress._percent_str)s"),
+        "--print".to_string(),
+        "after_move:filepath".to_string(),
+        "-P".to_string(),
+        dir.to_string_lossy().to_string(),
+        "-o".to_string(),
+        "%(title).150B [%(id)s].%(ext)s".to_string(),
+    ];
+
+    if processing.audio_only {
+        args.extend([
+            "-f".to_string(),
+            if format.is_empty() { "ba/b" } else { format }.to_string(),
+            "-x".to_string(),
+            "--audio-format".to_string(),
+            house_extension(config).to_string(),
+        ]);
+    } else {
+        if format.is_empty() {
+            args.extend([
+                "-f".to_string(),
+                "bv*+ba/b".to_string(),
+                "-S".to_string(),
+                format!("res:{},fps:{}", processing.height, processing.fps.round()),
+            ]);
+        } else {
+            args.extend(["-f".to_string(), format.to_string()]);
+        }
+
+        args.extend([
+            "--merge-output-format".to_string(),
+            house_extension(config).to_string(),
+        ]);
+    }
+
+    args
+}
+
+/// Percent from a progress line of yt-dlp.
+pub fn parse_progress(line: &str) -> Option<f64> {
+    let percent: f64 = line
+        .trim()
+        .strip_prefix(PROGRESS_PREFIX)?
+        .trim()
+        .strip_suffix('%')?
+        .parse()
+        .ok()?;
+
+    Some(percent.clamp(0.0, 100.0))
+}
+
+/// Only http and https urls, so yt-dlp reads no local files.
+pub fn check_url(url: &str) -> Result<(), ServiceError> {
+    let parsed = url::Url::parse(url).map_err(|e| ServiceError::BadRequest(e.to_string()))?;
+
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+        return Err(ServiceError::BadRequest(
+            "Only http and https urls can be downloaded".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Queue a download.
+pub async fn submit(
+    pool: &Pool<Sqlite>,
+    config: &PlayoutConfig,
+    mut job: DownloadJob,
+) -> Result<DownloadJob, ServiceError> {
+    let id = config.general.channel_id;
+
+    check_url(&job.url)?;
+
+    if let Some(date) = &job.playlist_date {
+        if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+            return Err(ServiceError::BadRequest(
+                "Date must be in format: YYYY-MM-DD".into(),
+            ));
+        }
+    }
+
+    let (_, _, folder) = norm_abs_path(&config.channel.storage, &job.folder)?;
+
+    job.channel_id = id;
+    job.folder = folder;
+    job.status = QUEUED.to_string();
+    job.created = timestamp();
+
+    let job = handles::insert_download_job(pool, &job).await?;
+
+    info!(target: Target::file_mail(), channel = id; "Queue download of <b><magenta>{}</></b>", job.url);
+
+    WAKE.notify_one();
+
+    Ok(job)
+}
+
+/// Cancel a queued or running download, finished downloads are removed from the list.
+pub async fn cancel(pool: &Pool<Sqlite>, channel: i32, id: i32) -> Result<String, ServiceError> {
+    let job = handles::select_download_job(pool, channel, id).await?;
+
+    match job.status.as_str() {
+        QUEUED => {
+            let result =
+                handles::switch_download_status(pool, id, QUEUED, CANCELED, &timestamp()).await?;
+
+            // worker took the job in between
+            if result.rows_affected() == 0 {
+                CANCEL.lock().unwrap().insert(id);
+            }
+
+            Ok("Cancel download".to_string())
+        }
+        RUNNING => {
+            CANCEL.lock().unwrap().insert(id);
+
+            Ok("Cancel download".to_string())
+        }
+        _ => {
+            handles::delete_download_job(pool, channel, id).await?;
+
+            Ok("Delete download".to_string())
+        }
+    }
+}
+
+/// Run yt-dlp for the job and save the progress, until it is finished or canceled.
+async fn run_job(
+    pool: &Pool<Sqlite>,
+    config: &PlayoutConfig,
+    job: &mut DownloadJob,
+) -> Result<PathBuf, ServiceError> {
+    let (dir, _, _) = norm_abs_path(&config.channel.storage, &job.folder)?;
+
+    fs::create_dir_all(&dir)?;
+
+    let mut child = Command::new(ytdlp_path())
+        .args(download_args(config, &job.format, &dir))
+        .arg("--")
+        .arg(&job.url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let mut stdout = BufReader::new(child.stdout.take().unwrap()).lines();
+    let mut stderr = BufReader::new(child.stderr.take().unwrap()).lines();
+    let (mut out_done, mut err_done) = (false, false);
+    let mut target = None;
+    let mut errors = vec![];
+    let mut saved = Instant::now();
+    let mut canceled = false;
+
+    while !out_done || !err_done {
+        let line = tokio::select! {
+            line = stdout.next_line(), if !out_done => {
+                let line = line?;
+                out_done = line.is_none();
+                line
+            }
+            line = stderr.next_line(), if !err_done => {
+                let line = line?;
+                err_done = line.is_none();
+                line
+            }
+        };
+
+        if CANCEL.lock().unwrap().contains(&job.id) {
+            child.kill().await?;
+            canceled = true;
+            break;
+        }
+
+        let Some(line) = line else {
+            continue;
+        };
+
+        if let Some(progress) = parse_progress(&line) {
+            job.progress = (progress * 10.0).round() / 10.0;
+
+            if saved.elapsed() >= PROGRESS_INTERVAL {
+                job.updated = timestamp();
+                handles::update_download_job(pool, job).await?;
+                saved = Instant::now();
+            }
+        } else if line.starts_with("ERROR:") {
+            errors.push(line);
+        } else if Path::new(line.trim()).starts_with(&dir) {
+            target = Some(PathBuf::from(line.trim()));
+        }
+    }
+
+    let status = child.wait().await?;
+
+    if canceled {
+        return Err(ServiceError::Conflict("Canceled".to_string()));
+    }
+
+    if !status.success() {
+        return Err(ServiceError::Conflict(format!(
+            "Download failed: {}",
+            errors.join(" ")
+        )));
+    }
+
+    target
+        .filter(|t| t.is_file())
+        .ok_or_else(|| ServiceError::Conflict("yt-dlp wrote no file".to_string()))
+}
+
+/// Download the file and add it to the playlist, when the job has a date.
+async fn download(
+    pool: &Pool<Sqlite>,
+    manager: &ChannelManager,
+    job: &mut DownloadJob,
+) -> Result<String, ServiceError> {
+    let config = manager.config.lock().unwrap().clone();
+    let target = run_job(pool, &config, job).await?;
+
+    job.target = target.to_string_lossy().to_string();
+
+    let Some(date) = job.playlist_date.clone() else {
+        return Ok(String::new());
+    };
+
+    let source = job.target.clone();
+    let length = tokio::task::spawn_blocking(move || MediaProbe::new(&source))
+        .await?
+        .ok()
+        .and_then(|p| p.format.duration)
+        .and_then(|d| d.parse().ok())
+        .unwrap_or_default();
+    let date = append_to_playlist(manager, &config, &target, length, Some(&date))?;
+
+    Ok(format!("added to playlist {date}"))
+}
+
+async fn process(
+    pool: &Pool<Sqlite>,
+    controllers: &Mutex<ChannelController>,
+    mut job: DownloadJob,
+) {
+    let id = job.channel_id;
+
+    match handles::switch_download_status(pool, job.id, QUEUED, RUNNING, &timestamp()).await {
+        Ok(result) if result.rows_affected() == 1 => {}
+        // canceled in between
+        Ok(_) => return,
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "Download job: {e}");
+            return;
+        }
+    }
+
+    job.status = RUNNING.to_string();
+
+    info!(target: Target::file_mail(), channel = id; "Download <b><magenta>{}</></b>", job.url);
+
+    let manager = controllers.lock().unwrap().get(id);
+    let result = match manager {
+        Some(manager) => download(pool, &manager, &mut job).await,
+        None => Err(ServiceError::Conflict("Channel not exists".to_string())),
+    };
+    let canceled = CANCEL.lock().unwrap().remove(&job.id);
+
+    match result {
+        Ok(message) => {
+            info!(target: Target::file_mail(), channel = id; "Download of <b><magenta>{}</></b> finished", job.url);
+
+            job.status = DONE.to_string();
+            job.progress = 100.0;
+            job.message = message;
+        }
+        Err(_) if canceled => {
+            info!(target: Target::file_mail(), channel = id; "Download of <b><magenta>{}</></b> canceled", job.url);
+
+            job.status = CANCELED.to_string();
+        }
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "Download of <b><magenta>{}</></b> failed: {e}", job.url);
+
+            job.status = FAILED.to_string();
+            job.message = e.to_string();
+        }
+    }
+
+    job.updated = timestamp();
+
+    if let Err(e) = handles::update_download_job(pool, &job).await {
+        error!(target: Target::file_mail(), channel = id; "Download job: {e}");
+    }
+}
+
+/// Run the queued downloads from all channels, one after the other.
+pub fn download_worker(pool: Pool<Sqlite>, controllers: Arc<Mutex<ChannelController>>) {
+    tokio::spawn(async move {
+        if let Err(e) = handles::requeue_download_jobs(&pool).await {
+            error!("Queue unfinished downloads: {e}");
+        }
+
+        loop {
+            match handles::select_next_download_job(&pool).await {
+                Ok(Some(job)) => process(&pool, &controllers, job).await,
+                Ok(None) => {
+                    let _ = timeout(Duration::from_secs(30), WAKE.notified()).await;
+                }
+                Err(e) => {
+                    error!("Download queue: {e}");
+                    sleep(Duration::from_secs(30)).await;
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,438 @@
+/*
+Active/passive failover between two ffplayout instances.
+
+Both instances have the same channels and the same storage, one runs as primary, the other as backup
+(`--failover-role`). Every second they ask each other over the API for a heartbeat,
+with a shared token (`--failover-token`):
+
+- the primary outputs, as long as it runs
+- the backup starts the channels, which ran on the primary, when the primary is not reachable
+  for `--failover-timeout` seconds, and stops them again, when the primary is back and outputs
+
+While the backup waits, it takes over the playout config from the primary, when it changes.
+In playlist mode the position follows the clock, so the backup continues at the same point.
+
+*/
+
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::{DefaultHasher, Hash, Hasher},
+    str::FromStr,
+    sync::{atomic::Ordering, Arc, LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use log::*;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use tokio::{task, time::interval};
+
+use crate::db::handles;
+use crate::player::controller::{ChannelController, ChannelManager};
+use crate::utils::{
+    config::{get_config, PlayoutConfig},
+    errors::ServiceError,
+    logging::Target,
+    maintenance::in_maintenance,
+    time_machine::time_now,
+};
+
+const HEARTBEAT: Duration = Duration::from_secs(1);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Default seconds without heartbeat from the primary, until the backup takes over.
+pub const DEFAULT_TIMEOUT: u64 = 5;
+
+static STATUS: LazyLock<Mutex<FailoverStatus>> =
+    LazyLock::new(|| Mutex::new(FailoverStatus::default()));
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailoverRole {
+    #[default]
+    Primary,
+    Backup,
+}
+
+impl FromStr for FailoverRole {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "primary" => Ok(Self::Primary),
+            "backup" => Ok(Self::Backup),
+            _ => Err("Use 'primary' or 'backup'".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for FailoverRole {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::Primary => write!(f, "primary"),
+            Self::Backup => write!(f, "backup"),
+        }
+    }
+}
+
+/// Channel state in the heartbeat.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChannelBeat {
+    pub id: i32,
+    pub running: bool,
+    /// Revision of the playout config, it changes with every update.
+    pub revision: String,
+    /// Source of the running clip.
+    pub current: Option<String>,
+    pub index: usize,
+}
+
+/// Heartbeat from one instance.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Heartbeat {
+    pub role: FailoverRole,
+    pub active: bool,
+    pub channels: Vec<ChannelBeat>,
+}
+
+/// Health of the other instance.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct PeerHealth {
+    pub url: String,
+    pub reachable: bool,
+    pub role: Option<FailoverRole>,
+    pub active: bool,
+    pub last_seen: Option<String>,
+    pub latency_ms: Option<u64>,
+    pub error: Option<String>,
+    pub channels: Vec<ChannelBeat>,
+}
+
+/// Failover state, for the status API.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct FailoverStatus {
+    pub enable: bool,
+    pub role: FailoverRole,
+    /// This instance outputs.
+    pub active: bool,
+    pub since: Option<String>,
+    pub timeout: u64,
+    pub peer: PeerHealth,
+    #[serde(skip)]
+    pub token: String,
+}
+
+/// Set the role of this instance, before the channels start.
+/// Failover needs the role, the address of the other instance and the token.
+pub fn init_failover(
+    role: Option<FailoverRole>,
+    peer: Option<String>,
+    token: Option<String>,
+    timeout: Option<u64>,
+) {
+    let mut status = STATUS.lock().unwrap();
+
+    let (Some(role), Some(peer), Some(token)) = (
+        role,
+        peer.filter(|p| !p.trim().is_empty()),
+        token.filter(|t| !t.trim().is_empty()),
+    ) else {
+        if role.is_some() {
+            error!("Failover needs the address of the other instance and a token, it is disabled");
+        }
+
+        *status = FailoverStatus::default();
+        return;
+    };
+
+    *status = FailoverStatus {
+        enable: true,
+        role,
+        active: role == FailoverRole::Primary,
+        since: Some(time_now().format("%Y-%m-%d %H:%M:%S").to_string()),
+        timeout: timeout.unwrap_or(DEFAULT_TIMEOUT).max(2),
+        peer: PeerHealth {
+            url: peer.trim().trim_end_matches('/').to_string(),
+            ..Default::default()
+        },
+        token: token.trim().to_string(),
+    };
+}
+
+pub fn failover_status() -> FailoverStatus {
+    STATUS.lock().unwrap().clone()
+}
+
+/// This instance is the backup and the primary outputs, the channels wait.
+pub fn is_standby() -> bool {
+    let status = STATUS.lock().unwrap();
+
+    status.enable && !status.active
+}
+
+/// Check the token from the other instance.
+pub fn check_token(header: Option<&str>) -> Result<(), ServiceError> {
+    let status = STATUS.lock().unwrap();
+
+    if !status.enable {
+        return Err(ServiceError::Forbidden(
+            "Failover is not enabled".to_string(),
+        ));
+    }
+
+    if header
+        .and_then(|h| h.trim().strip_prefix("Bearer "))
+        .is_none_or(|t| t.trim() != status.token)
+    {
+        return Err(ServiceError::Unauthorized("Invalid token".to_string()));
+    }
+
+    Ok(())
+}
+
+/// Config revision, the backup compares it with the last one from the primary.
+/// The JSON value has sorted keys, so maps give always the same revision.
+pub fn config_revision(config: &PlayoutConfig) -> String {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_value(config)
+        .map(|v| v.to_string())
+        .unwrap_or_default()
+        .hash(&mut hasher);
+
+    format!("{:016x}", hasher.finish())
+}
+
+/// Heartbeat with the state from this instance.
+pub fn heartbeat(controllers: &ChannelController) -> Heartbeat {
+    let status = STATUS.lock().unwrap();
+
+    Heartbeat {
+        role: status.role,
+        active: status.active,
+        channels: controllers
+            .channels
+            .iter()
+            .map(|manager| ChannelBeat {
+                id: manager.channel.lock().unwrap().id,
+                running: manager.is_alive.load(Ordering::SeqCst),
+                revision: config_revision(&manager.config.lock().unwrap()),
+                current: manager
+                    .current_media
+                    .lock()
+                    .unwrap()
+                    .as_ref()
+                    .map(|m| m.source.clone()),
+                index: manager.current_index.load(Ordering::SeqCst),
+            })
+            .collect(),
+    }
+}
+
+/// Next state of the backup: it takes over, when the primary is silent for the timeout,
+/// and stays active, until the primary outputs again.
+pub fn backup_output(active: bool, silent: Duration, peer_active: bool, timeout: Duration) -> bool {
+    silent >= timeout || (active && !peer_active)
+}
+
+async fn fetch<T: serde::de::DeserializeOwned>(
+    client: &reqwest::Client,
+    url: &str,
+    token: &str,
+) -> Result<T, String> {
+    client
+        .get(url)
+        .bearer_auth(token)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| e.without_url().to_string())?
+        .json::<T>()
+        .await
+        .map_err(|e| e.without_url().to_string())
+}
+
+/// Start the channels, which ran on the primary.
+async fn take_over(controllers: &Arc<Mutex<ChannelController>>, timeout: u64) {
+    let channels = controllers.lock().unwrap().channels.clone();
+
+    for manager in channels {
+        let id = manager.channel.lock().unwrap().id;
+
+        if in_maintenance(id) {
+            continue;
+        }
+
+        if manager.standby.swap(false, Ordering::SeqCst) {
+            warn!(target: Target::file_mail(), channel = id; "Primary not reachable for {timeout} seconds, backup takes over");
+
+            manager.channel.lock().unwrap().active = true;
+            manager.async_start().await;
+        }
+    }
+}
+
+/// Stop the channels, the primary outputs again.
+async fn stand_down(controllers: &Arc<Mutex<ChannelController>>) {
+    let channels = controllers.lock().unwrap().channels.clone();
+
+    for manager in channels {
+        if manager.is_alive.load(Ordering::SeqCst) {
+            let id = manager.channel.lock().unwrap().id;
+
+            info!(target: Target::file_mail(), channel = id; "Primary outputs again, backup stands by");
+
+            manager.standby.store(true, Ordering::SeqCst);
+            manager.channel.lock().unwrap().active = false;
+
+            if let Err(e) = task::spawn_blocking(move || manager.stop_all()).await {
+                error!(target: Target::file_mail(), channel = id; "{e}");
+            }
+        }
+    }
+}
+
+/// Take over the playout config from the primary.
+async fn sync_config(
+    pool: &Pool<Sqlite>,
+    manager: &ChannelManager,
+    config: PlayoutConfig,
+) -> Result<(), ServiceError> {
+    let id = manager.channel.lock().unwrap().id;
+    let config_id = manager.config.lock().unwrap().general.id;
+
+    handles::update_configuration(pool, config_id, config).await?;
+    manager.update_config(get_config(pool, id).await?);
+
+    Ok(())
+}
+
+/// Heartbeat to the other instance, the backup decides here, when it outputs.
+pub fn failover_monitor(pool: Pool<Sqlite>, controllers: Arc<Mutex<ChannelController>>) {
+    let status = failover_status();
+
+    if !status.enable {
+        return;
+    }
+
+    info!(
+        "Failover as <yellow>{}</>, peer: <b><magenta>{}</></b>",
+        status.role, status.peer.url
+    );
+
+    tokio::spawn(async move {
+        let Ok(client) = reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() else {
+            error!("Failover: no HTTP client");
+            return;
+        };
+        let timeout = Duration::from_secs(status.timeout);
+        let mut interval = interval(HEARTBEAT);
+        let mut seen = Instant::now();
+        let mut revisions: HashMap<i32, String> = HashMap::new();
+
+        loop {
+            interval.tick().await;
+
+            let start = Instant::now();
+            let beat = fetch::<Heartbeat>(
+                &client,
+                &format!("{}/failover/heartbeat", status.peer.url),
+                &status.token,
+            )
+            .await;
+
+            let (role, active, peer_active) = {
+                let mut state = STATUS.lock().unwrap();
+
+                match &beat {
+                    Ok(beat) => {
+                        seen = Instant::now();
+                        state.peer.reachable = true;
+                        state.peer.role = Some(beat.role);
+                        state.peer.active = beat.active;
+                        state.peer.last_seen =
+                            Some(time_now().format("%Y-%m-%d %H:%M:%S").to_string());
+                        state.peer.latency_ms = Some(start.elapsed().as_millis() as u64);
+                        state.peer.error = None;
+                        state.peer.channels.clone_from(&beat.channels);
+                    }
+                    Err(e) => {
+                        state.peer.reachable = false;
+                        state.peer.active = false;
+                        state.peer.error = Some(e.clone());
+                    }
+                }
+
+                (state.role, state.active, state.peer.active)
+            };
+
+            if role != FailoverRole::Backup {
+                continue;
+            }
+
+            let output = backup_output(active, seen.elapsed(), peer_active, timeout);
+
+            if output != active {
+                {
+                    let mut state = STATUS.lock().unwrap();
+                    state.active = output;
+                    state.since = Some(time_now().format("%Y-%m-%d %H:%M:%S").to_string());
+                }
+
+                if output {
+                    take_over(&controllers, status.timeout).await;
+                } else {
+                    stand_down(&controllers).await;
+                }
+
+                continue;
+            }
+
+            // the config is taken over, while the backup waits
+            let Ok(beat) = beat else {
+                continue;
+            };
+
+            if output {
+                continue;
+            }
+
+            let channels = controllers.lock().unwrap().channels.clone();
+
+            for manager in channels {
+                let id = manager.channel.lock().unwrap().id;
+                let Some(channel) = beat.channels.iter().find(|c| c.id == id) else {
+                    continue;
+                };
+
+                // the backup starts, what runs on the primary
+                manager.standby.store(channel.running, Ordering::SeqCst);
+
+                if revisions.get(&id) == Some(&channel.revision) {
+                    continue;
+                }
+
+                let config = fetch::<PlayoutConfig>(
+                    &client,
+                    &format!("{}/failover/config/{id}", status.peer.url),
+                    &status.token,
+                )
+                .await;
+
+                match config {
+                    Ok(config) => match sync_config(&pool, &manager, config).await {
+                        Ok(_) => {
+                            revisions.insert(id, channel.revision.clone());
+
+                            info!(target: Target::file(), channel = id; "Playout config from the primary applied");
+                        }
+                        Err(e) => {
+                            error!(target: Target::file_mail(), channel = id; "Failover config: {e}");
+                        }
+                    },
+                    Err(e) => error!(target: Target::file(), channel = id; "Failover config: {e}"),
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,306 @@
+/*
+Capabilities of the installed ffmpeg and check of the channel configs against them.
+
+At startup the engine reads version, build flags, encoders, muxers and filters from ffmpeg
+and checks the config of every channel, and again after the config of a channel changes.
+Missing parts are logged as errors, like `Encoder libx265 not available`, so they show up
+before the channel fails at runtime.
+
+`GET /api/system/{id}/ffmpeg` probes ffmpeg again and returns the capabilities with the problems
+of the channel config.
+
+*/
+
+use std::{
+    collections::HashSet,
+    process::{Command, Stdio},
+    sync::{LazyLock, Mutex},
+};
+
+use log::*;
+use serde::Serialize;
+
+use crate::player::filter::custom::{ffmpeg_filters, filter_names};
+use crate::utils::{
+    config::{OutputMode::Desktop, PlayoutConfig},
+    logging::Target,
+};
+
+/// Last probed capabilities.
+static CAPS: LazyLock<Mutex<Option<FfmpegCaps>>> = LazyLock::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FfmpegCaps {
+    pub version: String,
+    /// Enabled parts from the build configuration, like `libx264`.
+    pub libs: Vec<String>,
+    pub encoders: Vec<String>,
+    pub muxers: Vec<String>,
+    pub filters: Vec<String>,
+    pub ffprobe: bool,
+    pub ffplay: bool,
+}
+
+/// Response of the ffmpeg route.
+#[derive(Debug, Clone, Serialize)]
+pub struct FfmpegReport {
+    pub ffmpeg: FfmpegCaps,
+    pub problems: Vec<String>,
+}
+
+/// Version and enabled libraries from `ffmpeg -version`.
+pub fn parse_version(output: &str) -> (String, Vec<String>) {
+    let version = output
+        .lines()
+        .next()
+        .and_then(|l| l.strip_prefix("ffmpeg version "))
+        .and_then(|l| l.split_whitespace().next())
+        .unwrap_or_default()
+        .to_string();
+    let libs = output
+        .lines()
+        .find_map(|l| l.trim().strip_prefix("configuration:"))
+        .map(|l| {
+            l.split_whitespace()
+                .filter_map(|f| f.strip_prefix("--enable-"))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    (version, libs)
+}
+
+/// Names from `ffmpeg -encoders` or `ffmpeg -muxers`, the list starts after the `---` line.
+pub fn parse_names(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip_while(|l| !l.trim().starts_with("--"))
+        .skip(1)
+        .filter_map(|l| l.split_whitespace().nth(1))
+        .flat_map(|n| n.split(','))
+        .map(str::to_string)
+        .collect()
+}
+
+fn run(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| format!("{program} not available: {e}"))?;
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Read the capabilities from ffmpeg, they are kept for the config checks.
+pub fn probe_ffmpeg() -> Result<FfmpegCaps, String> {
+    let (version, libs) = parse_version(&run("ffmpeg", &["-hide_banner", "-version"])?);
+    let caps = FfmpegCaps {
+        version,
+        libs,
+        encoders: parse_names(&run("ffmpeg", &["-hide_banner", "-encoders"])?),
+        muxers: parse_names(&run("ffmpeg", &["-hide_banner", "-muxers"])?),
+        filters: ffmpeg_filters().to_vec(),
+        ffprobe: run("ffprobe", &["-version"]).is_ok(),
+        ffplay: run("ffplay", &["-version"]).is_ok(),
+    };
+
+    *CAPS.lock().unwrap() = Some(caps.clone());
+
+    Ok(caps)
+}
+
+fn is_codec_key(key: &str) -> bool {
+    ["-c", "-codec", "-vcodec", "-acodec", "-scodec"].contains(&key)
+        || key.starts_with("-c:")
+        || key.starts_with("-codec:")
+}
+
+fn is_filter_key(key: &str) -> bool {
+    ["-vf", "-af", "-filter_complex", "-lavfi"].contains(&key) || key.starts_with("-filter:")
+}
+
+/// Without a list from ffmpeg all names are accepted.
+fn has(list: &[String], name: &str) -> bool {
+    list.is_empty() || list.iter().any(|n| n == name)
+}
+
+fn filter_problems(filter: &str, caps: &FfmpegCaps, problems: &mut Vec<String>) {
+    for name in filter_names(filter).unwrap_or_default() {
+        if !has(&caps.filters, &name) {
+            problems.push(format!("Filter {name} not available"));
+        }
+    }
+}
+
+/// Encoders, muxers and filters of the output options. Options before an input are skipped.
+fn arg_problems(args: &[String], caps: &FfmpegCaps, problems: &mut Vec<String>) {
+    for (i, pair) in args.windows(2).enumerate() {
+        let (key, value) = (pair[0].as_str(), pair[1].as_str());
+
+        if args[i + 2..].iter().any(|a| a == "-i") {
+            continue;
+        }
+
+        if is_codec_key(key) && value != "copy" && !has(&caps.encoders, value) {
+            problems.push(format!("Encoder {value} not available"));
+        } else if key == "-f" && !has(&caps.muxers, value) {
+            problems.push(format!("Muxer {value} not available"));
+        } else if is_filter_key(key) {
+            filter_problems(value, caps, problems);
+        }
+    }
+}
+
+/// Problems of the channel config with the capabilities of ffmpeg.
+pub fn check_config(config: &PlayoutConfig, caps: &FfmpegCaps) -> Vec<String> {
+    let mut problems = vec![];
+
+    if !caps.ffprobe {
+        problems.push("ffprobe not available".to_string());
+    }
+
+    if config.output.mode == Desktop && !caps.ffplay {
+        problems.push("ffplay not available, it is needed for the desktop output".to_string());
+    }
+
+    for args in [
+        &config.processing.cmd,
+        &config.advanced.decoder.output_cmd,
+        &config.output.output_cmd,
+        &config.output.backup_cmd,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        arg_problems(args, caps, &mut problems);
+    }
+
+    filter_problems(&config.processing.custom_filter, caps, &mut problems);
+
+    if config.ingest.enable {
+        filter_problems(&config.ingest.custom_filter, caps, &mut problems);
+    }
+
+    if config.text.add_text {
+        if !has(&caps.filters, "drawtext") {
+            problems.push(
+                "Filter drawtext not available, it is needed for the text overlay".to_string(),
+            );
+        }
+
+        if !config.text.text_from_filename && !has(&caps.filters, "zmq") {
+            problems.push(
+                "Filter zmq not available, it is needed for the text overlay over the API"
+                    .to_string(),
+            );
+        }
+    }
+
+    let mut seen = HashSet::new();
+    problems.retain(|p| seen.insert(p.clone()));
+
+    problems
+}
+
+/// Check the channel config with the last probed capabilities and log the problems.
+pub fn validate_config(config: &PlayoutConfig) {
+    if config.general.skip_validation {
+        return;
+    }
+
+    let caps = CAPS.lock().unwrap().clone();
+    let id = config.general.channel_id;
+
+    let caps = match caps {
+        Some(caps) => caps,
+        None => match probe_ffmpeg() {
+            Ok(caps) => caps,
+            Err(e) => {
+                error!(target: Target::file_mail(), channel = id; "{e}");
+                return;
+            }
+        },
+    };
+
+    for problem in check_config(config, &caps) {
+        error!(target: Target::file_mail(), channel = id; "ffmpeg: {problem}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::config::PlayoutConfig;
+    use crate::vec_strings;
+
+    #[test]
+    fn ffmpeg_capabilities() {
+        let (version, libs) = parse_version(
+        "ffmpeg version 7.1 Copyright (c) 2000-2024 the FFmpeg developers\n  built with gcc 14\n  configuration: --prefix=/usr --enable-gpl --enable-libx264 --enable-libzmq\n",
+    );
+
+        assert_eq!(version, "7.1");
+        assert_eq!(libs, vec!["gpl", "libx264", "libzmq"]);
+
+        let encoders = parse_names(
+        "Encoders:\n V..... = Video\n A..... = Audio\n ------\n V....D libx264              libx264 H.264\n A....D aac                  AAC (Advanced Audio Coding)\n",
+    );
+        let muxers = parse_names(
+        " D. = Demuxing supported\n .E = Muxing supported\n ---\n  E hls             Apple HTTP Live Streaming\n  E mpegts          MPEG-TS (MPEG-2 Transport Stream)\n",
+    );
+
+        assert_eq!(encoders, vec!["libx264", "aac"]);
+        assert_eq!(muxers, vec!["hls", "mpegts"]);
+
+        let caps = FfmpegCaps {
+            version,
+            libs,
+            encoders,
+            muxers,
+            filters: vec_strings!["drawtext", "scale"],
+            ffprobe: true,
+            ffplay: false,
+        };
+        let mut config = PlayoutConfig::default();
+
+        config.processing.cmd = Some(vec_strings![
+            "-f", "lavfi", "-i", "anullsrc", "-c:v", "libx264", "-c:s", "copy", "-f", "mpegts", "-"
+        ]);
+        config.output.output_cmd = Some(vec_strings![
+            "-c:v",
+            "libx265",
+            "-c:a",
+            "aac",
+            "-vf",
+            "scale=1280:720,yadif",
+            "-f",
+            "hls",
+            "-c:v",
+            "libx265",
+            "out.m3u8"
+        ]);
+
+        assert_eq!(
+            check_config(&config, &caps),
+            vec![
+                "Encoder libx265 not available",
+                "Filter yadif not available"
+            ]
+        );
+
+        config.text.add_text = true;
+
+        assert_eq!(
+            check_config(&config, &caps).last().unwrap(),
+            "Filter zmq not available, it is needed for the text overlay over the API"
+        );
+
+        config.text.text_from_filename = true;
+        config.output.output_cmd = None;
+
+        assert!(check_config(&config, &caps).is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use relative_path::RelativePath;
+use tokio::task;
+use walkdir::WalkDir;
+
+use crate::utils::{config::PlayoutConfig, errors::ServiceError, trash::TRASH_DIR};
+
+/// Normalize absolut path
+///
+/// This function takes care, that it is not possible to break out from root_path.
+pub fn norm_abs_path(
+    root_path: &Path,
+    input_path: &str,
+) -> Result<(PathBuf, String, String), ServiceError> {
+    let path_relative = RelativePath::new(&root_path.to_string_lossy())
+        .normalize()
+        .to_string()
+        .replace("../", "");
+    let path_suffix = root_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let mut source_relative = RelativePath::new(input_path)
+        .normalize()
+        .to_string()
+        .replace("../", "");
+
+    if input_path.starts_with(&*root_path.to_string_lossy())
+        || source_relative.starts_with(&path_relative)
+    {
+        source_relative = source_relative
+            .strip_prefix(&path_relative)
+            .and_then(|s| s.strip_prefix('/'))
+            .unwrap_or_default()
+            .to_string();
+    } else {
+        source_relative = source_relative
+            .strip_prefix(&path_suffix)
+            .and_then(|s| s.strip_prefix('/'))
+            .unwrap_or(&source_relative)
+            .to_string();
+    }
+
+    let path = &root_path.join(&source_relative);
+
+    Ok((path.clone(), path_suffix, source_relative))
+}
+
+/// Size of all files in a folder, in bytes.
+pub fn dir_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.metadata().ok())
+        .filter(std::fs::Metadata::is_file)
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Size of the channel storage, without the trash.
+/// Files in the trash don't count to the quota, so deleting frees space at once.
+pub fn storage_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_entry(|e| e.depth() != 1 || e.file_name() != TRASH_DIR)
+        .flatten()
+        .filter_map(|e| e.metadata().ok())
+        .filter(std::fs::Metadata::is_file)
+        .map(|m| m.len())
+        .sum()
+}
+
+// Cached folder sizes, for status requests which run often
+static STORAGE_USAGE: LazyLock<Mutex<HashMap<PathBuf, (Instant, u64)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Like [`storage_size`], but the result is cached for a few seconds.
+pub fn storage_usage(path: &Path) -> u64 {
+    if let Some((time, size)) = STORAGE_USAGE.lock().unwrap().get(path) {
+        if time.elapsed() < Duration::from_secs(10) {
+            return *size;
+        }
+    }
+
+    let size = storage_size(path);
+
+    STORAGE_USAGE
+        .lock()
+        .unwrap()
+        .insert(path.to_path_buf(), (Instant::now(), size));
+
+    size
+}
+
+/// Check if the channel storage has space for `additional` bytes.
+///
+/// Returns the used bytes, or 507 Insufficient Storage when the quota would be exceeded.
+pub async fn check_quota(config: &PlayoutConfig, additional: u64) -> Result<u64, ServiceError> {
+    let quota = config.channel.storage_quota;
+    let storage = config.channel.storage.clone();
+
+    if quota == 0 {
+        return Ok(0);
+    }
+
+    let used = task::spawn_blocking(move || storage_size(&storage)).await?;
+
+    if used + additional > quota {
+        return Err(ServiceError::InsufficientStorage(format!(
+            "Storage quota exceeded: {used} of {quota} bytes used"
+        )));
+    }
+
+    Ok(used)
+}
@@ -5,11 +5,13 @@
 /// The generator takes the files from storage, which are set in config.
 /// It also respect the shuffle/sort mode.
 use std::{
-    fs::{create_dir_all, write},
+    collections::HashMap,
+    fs::{create_dir_all, read_to_string, write},
     io::Error,
+    path::Path,
 };
 
-use chrono::Timelike;
+use chrono::{Datelike, NaiveDate, TimeDelta, Timelike};
 use lexical_sort::{natural_lexical_cmp, StringSort};
 use log::*;
 use rand::{seq::SliceRandom, thread_rng, Rng};
@@ -18,6 +20,7 @@ use walkdir::WalkDir;
 use crate::player::{
     controller::ChannelManager,
     utils::{
+        filler::{FillerHistory, FillerRules},
         folder::{fill_filler_list, FolderSource},
         get_date_range, include_file_extension,
         json_serializer::JsonPlaylist,
@@ -25,8 +28,9 @@ use crate::player::{
     },
 };
 use crate::utils::{
-    config::{PlayoutConfig, Template},
+    config::{PlayoutConfig, ScheduleRules, Template},
     logging::Target,
+    scripting::on_generate,
     time_to_sec,
 };
 
@@ -94,26 +98,31 @@ pub fn ordered_list(clip_list: Vec<Media>, total_length: f64) -> Vec<Media> {
     ordered_clip_list
 }
 
-pub fn filler_list(config: &PlayoutConfig, total_length: f64) -> Vec<Media> {
+pub fn filler_list(
+    config: &PlayoutConfig,
+    total_length: f64,
+    history: &mut FillerHistory,
+) -> Vec<Media> {
     let filler_list = fill_filler_list(config, None);
+    let rules = FillerRules::new(&config.storage);
     let mut index = 0;
     let mut filler_clip_list: Vec<Media> = vec![];
     let mut target_duration = 0.0;
-    let clip_list_length = filler_list.len();
-
-    if clip_list_length > 0 {
-        while target_duration < total_length {
-            if index == clip_list_length {
-                index = 0;
-            }
 
-            let selected_clip = filler_list[index].clone();
+    while target_duration < total_length {
+        let Some(selected) =
+            history.select(&filler_list, &rules, index, total_length - target_duration)
+        else {
+            break;
+        };
+        let selected_clip = filler_list[selected].clone();
 
-            target_duration += selected_clip.duration;
-            filler_clip_list.push(selected_clip);
-            index += 1;
-        }
+        index = selected + 1;
+        target_duration += selected_clip.duration;
+        filler_clip_list.push(selected_clip);
+    }
 
+    if target_duration > total_length {
         let over_length = target_duration - total_length;
         let last_index = filler_clip_list.len() - 1;
 
@@ -123,14 +132,193 @@ pub fn filler_list(config: &PlayoutConfig, total_length: f64) -> Vec<Media> {
     filler_clip_list
 }
 
+/// Played clips and series from the template generator, over all generated days.
+#[derive(Debug, Clone, Default)]
+pub struct ScheduleHistory {
+    /// Seconds since the first day of the schedule, where the next clip starts.
+    pub time: f64,
+    day_offset: f64,
+    plays: HashMap<String, f64>,
+    last_source: String,
+    last_series: String,
+    series_run: usize,
+}
+
+impl ScheduleHistory {
+    /// Move the time to the begin of the day.
+    pub fn set_date(&mut self, date: &str) {
+        if let Ok(day) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+            self.day_offset = day.num_days_from_ce() as f64 * 86400.0;
+            self.time = self.day_offset;
+        }
+    }
+
+    /// Add the clips from the existing playlists before the date, which are inside the repeat time.
+    pub fn load_days(&mut self, config: &PlayoutConfig, date: &str, rules: &ScheduleRules) {
+        let Ok(day) = NaiveDate::parse_from_str(date, "%Y-%m-%d") else {
+            return;
+        };
+        let days = (rules.min_repeat_hours / 24.0).ceil() as i64;
+
+        for d in (1..=days.max(1)).rev() {
+            let date = (day - TimeDelta::days(d)).format("%Y-%m-%d").to_string();
+            let path = config
+                .channel
+                .playlists
+                .join(&date[..4])
+                .join(&date[5..7])
+                .join(format!("{date}.json"));
+
+            let Some(playlist) = read_to_string(path)
+                .ok()
+                .and_then(|json| serde_json::from_str::<JsonPlaylist>(&json).ok())
+            else {
+                continue;
+            };
+
+            self.set_date(&date);
+
+            for item in &playlist.program {
+                self.push(item);
+            }
+        }
+    }
+
+    /// Move the time to the start of a block, in seconds from the day begin.
+    pub fn set_block(&mut self, start: f64) {
+        self.time = self.day_offset + start;
+    }
+
+    /// Seconds since the clip was played the last time.
+    pub fn since_played(&self, source: &str) -> Option<f64> {
+        self.plays.get(source).map(|start| self.time - start)
+    }
+
+    pub fn series_run(&self, series: &str) -> usize {
+        if !series.is_empty() && self.last_series == series {
+            self.series_run
+        } else {
+            0
+        }
+    }
+
+    pub fn push(&mut self, media: &Media) {
+        let series = series_name(media);
+
+        self.series_run = self.series_run(&series) + 1;
+        self.last_series = series;
+        self.last_source.clone_from(&media.source);
+        self.plays.insert(media.source.clone(), self.time);
+        self.time += media.out - media.seek;
+    }
+
+    /// Skip time, for example for fillers.
+    pub fn skip(&mut self, seconds: f64) {
+        self.time += seconds;
+    }
+
+    fn allows(&self, media: &Media, rules: &ScheduleRules) -> bool {
+        if self.last_source == media.source {
+            return false;
+        }
+
+        if rules.max_series_run > 0 && self.series_run(&series_name(media)) >= rules.max_series_run
+        {
+            return false;
+        }
+
+        rules.min_repeat_hours <= 0.0
+            || self
+                .since_played(&media.source)
+                .is_none_or(|sec| sec >= rules.min_repeat_hours * 3600.0)
+    }
+}
+
+/// The folder from a clip in a category folder, clips without category have no series.
+pub fn series_name(media: &Media) -> String {
+    if media.category.is_empty() {
+        return String::new();
+    }
+
+    Path::new(&media.source)
+        .parent()
+        .and_then(Path::file_name)
+        .map(|f| f.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Fill a block with clips, which follow the scheduling rules and category quotas.
+///
+/// Shuffled blocks take a random clip from the allowed ones, sorted blocks the next allowed clip.
+/// When no clip is allowed anymore, the rest of the block stays for the fillers.
+pub fn ruled_list(
+    clip_list: &[Media],
+    total_length: f64,
+    shuffle: bool,
+    rules: &ScheduleRules,
+    quotas: &HashMap<String, f64>,
+    history: &mut ScheduleHistory,
+) -> Vec<Media> {
+    let mut rng = thread_rng();
+    let mut list: Vec<Media> = vec![];
+    let mut length = 0.0;
+    let mut index = 0;
+    let mut category_length: HashMap<String, f64> = HashMap::new();
+    let quotas: HashMap<String, f64> = quotas
+        .iter()
+        .map(|(c, q)| (c.to_lowercase(), q.clamp(0.0, 1.0) * total_length))
+        .collect();
+
+    loop {
+        let allowed = (0..clip_list.len())
+            .map(|i| (index + i) % clip_list.len())
+            .filter(|&i| {
+                let clip = &clip_list[i];
+                let category = clip.category.to_lowercase();
+
+                length + clip.duration <= total_length
+                    && quotas.get(&category).is_none_or(|quota| {
+                        category_length.get(&category).unwrap_or(&0.0) + clip.duration <= *quota
+                    })
+                    && history.allows(clip, rules)
+            })
+            .collect::<Vec<usize>>();
+
+        let selected = if shuffle {
+            allowed.choose(&mut rng)
+        } else {
+            allowed.first()
+        };
+
+        let Some(&selected) = selected else {
+            break;
+        };
+
+        let clip = clip_list[selected].clone();
+
+        *category_length
+            .entry(clip.category.to_lowercase())
+            .or_default() += clip.duration;
+        length += clip.duration;
+        index = selected + 1;
+
+        history.push(&clip);
+        list.push(clip);
+    }
+
+    list
+}
+
 pub fn generate_from_template(
     config: &PlayoutConfig,
     manager: &ChannelManager,
     template: Template,
+    schedule: &mut ScheduleHistory,
 ) -> FolderSource {
     let mut media_list = vec![];
     let mut rng = thread_rng();
     let mut index: usize = 0;
+    let mut history = FillerHistory::default();
     let id = config.general.channel_id;
 
     for source in template.sources {
@@ -141,6 +329,8 @@ pub fn generate_from_template(
 
         debug!(target: Target::all(), channel = id; "Generating playlist block with <yellow>{duration:.2}</> seconds length");
 
+        schedule.set_block(source.start.num_seconds_from_midnight() as f64);
+
         for path in source.paths {
             debug!("Search files in <b><magenta>{path:?}</></b>");
 
@@ -157,12 +347,36 @@ pub fn generate_from_template(
             }
 
             for entry in file_list {
-                let media = Media::new(0, &entry, true);
+                let mut media = Media::new(0, &entry, true);
+
+                // clips in sub folders get the folder name as category, for the scheduling rules
+                if let Some(folder) = Path::new(&entry)
+                    .strip_prefix(&path)
+                    .ok()
+                    .and_then(Path::parent)
+                    .and_then(|p| p.components().next())
+                {
+                    media.category = folder.as_os_str().to_string_lossy().to_string();
+                }
+
                 source_list.push(media);
             }
         }
 
-        let mut timed_list = if source.shuffle {
+        let mut timed_list = if template.rules.is_active() || !source.quotas.is_empty() {
+            if source.shuffle {
+                source_list.shuffle(&mut rng);
+            }
+
+            ruled_list(
+                &source_list,
+                duration,
+                source.shuffle,
+                &template.rules,
+                &source.quotas,
+                schedule,
+            )
+        } else if source.shuffle {
             source_list.shuffle(&mut rng);
 
             random_list(source_list, duration)
@@ -173,8 +387,9 @@ pub fn generate_from_template(
         let total_length = sum_durations(&timed_list);
 
         if duration > total_length {
-            let mut filler = filler_list(config, duration - total_length);
+            let mut filler = filler_list(config, duration - total_length, &mut history);
 
+            schedule.skip(sum_durations(&filler));
             timed_list.append(&mut filler);
         }
 
@@ -209,7 +424,6 @@ pub fn playlist_generator(manager: &ChannelManager) -> Result<Vec<JsonPlaylist>,
     let playlist_root = &config.channel.playlists;
     let mut playlists = vec![];
     let mut date_range = vec![];
-    let mut from_template = false;
 
     if !playlist_root.is_dir() {
         error!(
@@ -228,13 +442,16 @@ pub fn playlist_generator(manager: &ChannelManager) -> Result<Vec<JsonPlaylist>,
     }
 
     // gives an iterator with infinit length
-    let folder_iter = if let Some(template) = &config.general.template {
-        from_template = true;
-
-        generate_from_template(&config, manager, template.clone())
-    } else {
-        FolderSource::new(&config, manager.clone())
-    };
+    let folder_iter = config
+        .general
+        .template
+        .is_none()
+        .then(|| FolderSource::new(&config, manager.clone()));
+    let mut schedule = ScheduleHistory::default();
+
+    if let (Some(template), Some(first)) = (&config.general.template, date_range.first()) {
+        schedule.load_days(&config, first, &template.rules);
+    }
 
     let list_length = manager.current_list.lock().unwrap().len();
 
@@ -246,6 +463,7 @@ pub fn playlist_generator(manager: &ChannelManager) -> Result<Vec<JsonPlaylist>,
         let playlist_file = &playlist_path.join(format!("{date}.json"));
         let mut length = 0.0;
         let mut round = 0;
+        let mut history = FillerHistory::new(&date);
 
         create_dir_all(playlist_path)?;
 
@@ -275,10 +493,14 @@ pub fn playlist_generator(manager: &ChannelManager) -> Result<Vec<JsonPlaylist>,
             program: vec![],
         };
 
-        if from_template {
+        if let Some(template) = config.general.template.clone() {
+            // every day gets its own list, the rules see the days before
+            schedule.set_date(&playlist.date);
+            generate_from_template(&config, manager, template, &mut schedule);
+
             let media_list = manager.current_list.lock().unwrap();
             playlist.program = media_list.to_vec();
-        } else {
+        } else if let Some(folder_iter) = &folder_iter {
             for item in folder_iter.clone() {
                 let duration = item.duration;
 
@@ -297,12 +519,13 @@ pub fn playlist_generator(manager: &ChannelManager) -> Result<Vec<JsonPlaylist>,
 
             if config.playlist.length_sec.unwrap() > list_duration {
                 let time_left = config.playlist.length_sec.unwrap() - list_duration;
-                let mut fillers = filler_list(&config, time_left);
+                let mut fillers = filler_list(&config, time_left, &mut history);
 
                 playlist.program.append(&mut fillers);
             }
         }
 
+        let playlist = on_generate(manager, playlist);
         let json: String = serde_json::to_string_pretty(&playlist)?;
         write(playlist_file, json)?;
 
@@ -0,0 +1,235 @@
+/*
+GPU assignment of the channels, for hardware encoders.
+
+The GPU setting of a channel is:
+
+- empty: the channel uses no GPU
+- `auto`: the scheduler takes the GPU with the fewest channels, on a tie the GPU with the lowest usage
+- a GPU index, like `1`: the channel runs always on this GPU
+
+The GPUs come from the system stats. A GPU is assigned when the channel starts and is free again,
+when the channel stops. The ffmpeg processes of the channel see only their GPU, over `CUDA_VISIBLE_DEVICES`,
+so in the ffmpeg parameters the GPU is always `0`.
+
+`--gpu-channels` limits the channels per GPU. A channel, which would oversubscribe a GPU, is not started
+and the error is returned from the start command and logged.
+
+*/
+
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{LazyLock, Mutex},
+};
+
+use log::*;
+
+use crate::utils::{
+    errors::ServiceError,
+    logging::Target,
+    system::{gpus, Gpu},
+};
+use crate::ARGS;
+
+/// GPU index of the running channels.
+static ASSIGNED: LazyLock<Mutex<HashMap<i32, usize>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GpuSetting {
+    #[default]
+    None,
+    Auto,
+    Device(usize),
+}
+
+impl FromStr for GpuSetting {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "" | "none" => Ok(Self::None),
+            "auto" => Ok(Self::Auto),
+            index => index
+                .parse()
+                .map(Self::Device)
+                .map_err(|_| format!("Invalid GPU: {s}")),
+        }
+    }
+}
+
+/// Choose the GPU for the channel.
+/// `assigned` has the GPUs of the running channels, `limit` are the channels per GPU, 0 for no limit.
+pub fn pick_gpu(
+    setting: GpuSetting,
+    gpus: &[Gpu],
+    assigned: &HashMap<i32, usize>,
+    channel: i32,
+    limit: usize,
+) -> Result<Option<usize>, ServiceError> {
+    let count = |index: usize| {
+        assigned
+            .iter()
+            .filter(|(c, g)| **c != channel && **g == index)
+            .count()
+    };
+    let free = |index: usize| limit == 0 || count(index) < limit;
+    let exists = |index: usize| gpus.iter().any(|g| g.index == index);
+
+    match setting {
+        GpuSetting::None => Ok(None),
+        GpuSetting::Device(index) => {
+            if !exists(index) {
+                return Err(ServiceError::Conflict(format!("GPU {index} not found")));
+            }
+
+            if !free(index) {
+                return Err(ServiceError::Conflict(format!(
+                    "GPU {index} is oversubscribed, it runs already {} channels",
+                    count(index)
+                )));
+            }
+
+            Ok(Some(index))
+        }
+        GpuSetting::Auto => {
+            if gpus.is_empty() {
+                return Err(ServiceError::Conflict("No GPU found".to_string()));
+            }
+
+            // a restarted channel keeps its GPU
+            if let Some(index) = assigned.get(&channel) {
+                if exists(*index) && free(*index) {
+                    return Ok(Some(*index));
+                }
+            }
+
+            gpus.iter()
+                .filter(|g| free(g.index))
+                .min_by(|a, b| {
+                    count(a.index)
+                        .cmp(&count(b.index))
+                        .then(a.usage.total_cmp(&b.usage))
+                        .then(a.index.cmp(&b.index))
+                })
+                .map(|g| Some(g.index))
+                .ok_or_else(|| {
+                    ServiceError::Conflict(format!(
+                        "All GPUs are oversubscribed, the limit is {limit} channels per GPU"
+                    ))
+                })
+        }
+    }
+}
+
+/// Assign a GPU to the channel, before it starts.
+pub fn assign_gpu(channel: i32, setting: GpuSetting) -> Result<Option<usize>, ServiceError> {
+    if setting == GpuSetting::None {
+        release_gpu(channel);
+
+        return Ok(None);
+    }
+
+    let gpus = gpus();
+    let mut assigned = ASSIGNED.lock().unwrap();
+    let gpu = pick_gpu(
+        setting,
+        &gpus,
+        &assigned,
+        channel,
+        ARGS.gpu_channels.unwrap_or(0),
+    )?;
+
+    if let Some(index) = gpu {
+        if assigned.insert(channel, index) != Some(index) {
+            info!(target: Target::file(), channel = channel; "Channel runs on GPU <yellow>{index}</>");
+        }
+    }
+
+    Ok(gpu)
+}
+
+pub fn release_gpu(channel: i32) {
+    ASSIGNED.lock().unwrap().remove(&channel);
+}
+
+pub fn channel_gpu(channel: i32) -> Option<usize> {
+    ASSIGNED.lock().unwrap().get(&channel).copied()
+}
+
+/// Fill the channels, which run on the GPUs.
+pub fn gpu_channels(gpus: &mut [Gpu]) {
+    let assigned = ASSIGNED.lock().unwrap();
+
+    for gpu in gpus.iter_mut() {
+        gpu.channels = assigned
+            .iter()
+            .filter(|(_, g)| **g == gpu.index)
+            .map(|(c, _)| *c)
+            .collect();
+        gpu.channels.sort_unstable();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::utils::system::parse_gpus;
+
+    #[test]
+    fn gpu_scheduler() {
+        let gpus = parse_gpus(
+            "0, NVIDIA RTX A4000, 16376, 1024, 35, 2\n1, NVIDIA RTX A4000, 16376, 512, 10, [N/A]\n",
+        );
+
+        assert_eq!(gpus.len(), 2);
+        assert_eq!(gpus[0].memory_total, 16376 * 1024 * 1024);
+        assert_eq!(gpus[1].encoder_sessions, 0);
+
+        assert_eq!("".parse::<GpuSetting>().unwrap(), GpuSetting::None);
+        assert_eq!("Auto".parse::<GpuSetting>().unwrap(), GpuSetting::Auto);
+        assert_eq!("1".parse::<GpuSetting>().unwrap(), GpuSetting::Device(1));
+        assert!("gpu1".parse::<GpuSetting>().is_err());
+
+        let mut assigned = HashMap::new();
+
+        // lowest usage on a tie
+        assert_eq!(
+            pick_gpu(GpuSetting::Auto, &gpus, &assigned, 1, 2).unwrap(),
+            Some(1)
+        );
+
+        assigned.insert(1, 1);
+        assert_eq!(
+            pick_gpu(GpuSetting::Auto, &gpus, &assigned, 2, 2).unwrap(),
+            Some(0)
+        );
+        assert_eq!(
+            pick_gpu(GpuSetting::Auto, &gpus, &assigned, 1, 2).unwrap(),
+            Some(1)
+        );
+
+        assigned.insert(2, 1);
+        assert!(pick_gpu(GpuSetting::Device(1), &gpus, &assigned, 3, 2).is_err());
+        assert_eq!(
+            pick_gpu(GpuSetting::Device(1), &gpus, &assigned, 3, 0).unwrap(),
+            Some(1)
+        );
+        assert_eq!(
+            pick_gpu(GpuSetting::Device(1), &gpus, &assigned, 2, 2).unwrap(),
+            Some(1)
+        );
+        assert!(pick_gpu(GpuSetting::Device(5), &gpus, &assigned, 3, 0).is_err());
+        assert_eq!(
+            pick_gpu(GpuSetting::None, &[], &assigned, 3, 0).unwrap(),
+            None
+        );
+
+        assigned.insert(3, 0);
+        assigned.insert(4, 0);
+        assert!(pick_gpu(GpuSetting::Auto, &gpus, &assigned, 5, 2).is_err());
+        assert!(pick_gpu(GpuSetting::Auto, &[], &HashMap::new(), 5, 0).is_err());
+    }
+}
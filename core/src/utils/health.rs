@@ -0,0 +1,283 @@
+/*
+Health checks for load balancers and container orchestration.
+
+`live` only shows, that the server answers. `ready` checks the database, the ffmpeg binary,
+the free disk space from the storage and public folders and the state of the channels.
+Active channels must have a running engine, unless they are switched off by the air hours.
+During a shutdown `ready` fails, so no new requests are sent.
+
+The probes need no login, so they get only the summary: the names of the checks and if they passed.
+Messages, paths and channels are in the details for admins.
+
+*/
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{atomic::Ordering, Mutex},
+};
+
+use serde::Serialize;
+use sqlx::{Pool, Sqlite};
+use sysinfo::Disk;
+
+use crate::db::handles;
+use crate::player::controller::ChannelController;
+use crate::utils::{maintenance::in_maintenance, shutdown::is_shutting_down};
+use crate::DISKS;
+
+/// Minimal free space in bytes on the storage and public disks.
+pub const MIN_FREE_SPACE: u64 = 512 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub ok: bool,
+    pub message: String,
+}
+
+impl Check {
+    fn new(name: &str, result: Result<String, String>) -> Self {
+        let ok = result.is_ok();
+        let message = result.unwrap_or_else(|e| e);
+
+        Self {
+            name: name.to_string(),
+            ok,
+            message,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelHealth {
+    pub id: i32,
+    pub name: String,
+    pub active: bool,
+    pub running: bool,
+    pub off_air: bool,
+    pub maintenance: bool,
+    pub ok: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Health {
+    /// `ok` or `fail`
+    pub status: String,
+    pub checks: Vec<Check>,
+    pub channels: Vec<ChannelHealth>,
+}
+
+/// Check without details, for the public readiness probe.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CheckState {
+    pub name: String,
+    pub ok: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthSummary {
+    /// `ok` or `fail`
+    pub status: String,
+    pub checks: Vec<CheckState>,
+}
+
+impl Health {
+    pub fn is_ok(&self) -> bool {
+        self.status == "ok"
+    }
+
+    /// The checks by their kind, like `disk` for all disks and `channels` for all channels.
+    pub fn summary(&self) -> HealthSummary {
+        let mut checks: Vec<CheckState> = vec![];
+
+        for check in &self.checks {
+            let name = check.name.split(':').next().unwrap_or_default();
+
+            match checks.iter_mut().find(|c| c.name == name) {
+                Some(state) => state.ok &= check.ok,
+                None => checks.push(CheckState {
+                    name: name.to_string(),
+                    ok: check.ok,
+                }),
+            }
+        }
+
+        if !self.channels.is_empty() {
+            checks.push(CheckState {
+                name: "channels".to_string(),
+                ok: self.channels.iter().all(|c| c.ok),
+            });
+        }
+
+        HealthSummary {
+            status: self.status.clone(),
+            checks,
+        }
+    }
+}
+
+/// Free bytes on the disk with the path, from the disk with the longest mount point.
+pub fn free_space(path: &Path) -> Option<u64> {
+    let mut disks = DISKS.lock().unwrap();
+
+    disks.refresh(true);
+
+    disks
+        .iter()
+        .filter(|d| path.starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len())
+        .map(Disk::available_space)
+}
+
+/// Version line from `ffmpeg -version`.
+pub fn ffmpeg_version() -> Result<String, String> {
+    let output = Command::new("ffmpeg")
+        .arg("-version")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| format!("ffmpeg not executable: {e}"))?;
+
+    if !output.status.success() {
+        return Err("ffmpeg not executable".to_string());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string())
+}
+
+fn disk_check(path: &Path) -> Result<String, String> {
+    if !path.is_dir() {
+        return Err(format!("{} not exists", path.display()));
+    }
+
+    match free_space(path) {
+        Some(free) if free < MIN_FREE_SPACE => Err(format!("{free} bytes free")),
+        Some(free) => Ok(format!("{free} bytes free")),
+        None => Ok("Unknown disk".to_string()),
+    }
+}
+
+/// Run all checks, the state is `ok` when no check and no channel failed.
+pub async fn readiness(pool: &Pool<Sqlite>, controllers: &Mutex<ChannelController>) -> Health {
+    let mut checks = vec![];
+    let mut channels = vec![];
+    let mut paths = HashSet::new();
+
+    checks.push(Check::new(
+        "database",
+        handles::check_connection(pool)
+            .await
+            .map(|_| "Connected".to_string())
+            .map_err(|e| e.to_string()),
+    ));
+    checks.push(Check::new(
+        "ffmpeg",
+        tokio::task::spawn_blocking(ffmpeg_version)
+            .await
+            .unwrap_or_else(|e| Err(e.to_string())),
+    ));
+
+    if is_shutting_down() {
+        checks.push(Check::new("shutdown", Err("Server shuts down".to_string())));
+    }
+
+    let managers = controllers.lock().unwrap().channels.clone();
+
+    for manager in managers {
+        let channel = manager.channel.lock().unwrap().clone();
+        let config = manager.config.lock().unwrap().clone();
+        let running = manager.is_alive.load(Ordering::SeqCst);
+        let off_air = manager.off_air.load(Ordering::SeqCst);
+        let maintenance = in_maintenance(channel.id);
+
+        paths.insert(config.channel.storage.clone());
+        paths.insert(config.channel.public.clone());
+
+        channels.push(ChannelHealth {
+            id: channel.id,
+            name: channel.name,
+            active: channel.active,
+            running,
+            off_air,
+            maintenance,
+            ok: !channel.active || running || off_air || maintenance,
+        });
+    }
+
+    let mut paths: Vec<PathBuf> = paths.into_iter().collect();
+    paths.sort();
+
+    for path in paths {
+        let result = tokio::task::spawn_blocking({
+            let path = path.clone();
+            move || disk_check(&path)
+        })
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()));
+
+        checks.push(Check::new(&format!("disk:{}", path.display()), result));
+    }
+
+    let ok = checks.iter().all(|c| c.ok) && channels.iter().all(|c| c.ok);
+
+    Health {
+        status: if ok { "ok" } else { "fail" }.to_string(),
+        checks,
+        channels,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn health_summary() {
+        let check = |name: &str, ok| Check {
+            name: name.to_string(),
+            ok,
+            message: "/var/lib/ffplayout".to_string(),
+        };
+        let health = Health {
+            status: "fail".to_string(),
+            checks: vec![
+                check("database", true),
+                check("disk:/var/lib/ffplayout/tv-media", true),
+                check("disk:/usr/share/ffplayout/public", false),
+            ],
+            channels: vec![ChannelHealth {
+                id: 1,
+                name: "Channel 1".to_string(),
+                active: true,
+                running: true,
+                off_air: false,
+                maintenance: false,
+                ok: true,
+            }],
+        };
+        let summary = health.summary();
+        let state = |name: &str, ok| CheckState {
+            name: name.to_string(),
+            ok,
+        };
+
+        assert_eq!(summary.status, "fail");
+        assert_eq!(
+            summary.checks,
+            vec![
+                state("database", true),
+                state("disk", false),
+                state("channels", true)
+            ]
+        );
+        assert!(!serde_json::to_string(&summary)
+            .unwrap()
+            .contains("/var/lib"));
+    }
+}
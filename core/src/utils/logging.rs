@@ -0,0 +1,874 @@
+use std::{
+    collections::HashMap,
+    env,
+    io::{self, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, SecondsFormat};
+use flexi_logger::{
+    filter::{LogLineFilter, LogLineWriter},
+    writers::{FileLogWriter, LogWriter},
+    Age, Cleanup, Criterion, DeferredNow, FileSpec, Level, LogSpecification, Logger, LoggerHandle,
+    Naming,
+};
+use lettre::{
+    message::header, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+use log::{
+    kv::{self, Key, Value, VisitSource},
+    *,
+};
+use once_cell::sync::{Lazy, OnceCell};
+use paris::formatter::colorize_string;
+use regex::Regex;
+use serde_json::{json, Map};
+use tokio::{sync::broadcast, time::interval};
+
+use super::ARGS;
+
+use crate::db::GLOBAL_SETTINGS;
+use crate::utils::{
+    config::{LogRetention, Logging, Mail, Notifier},
+    errors::ProcessError,
+    maintenance::in_maintenance,
+    notifier::send_notification,
+    round_to_nearest_ten,
+    time_machine::time_now,
+};
+
+/// Log line for live listeners, like the log WebSocket.
+#[derive(Clone, Debug)]
+pub struct LiveLine {
+    pub channel: i32,
+    pub level: Level,
+    pub line: String,
+}
+
+static LIVE_LINES: Lazy<broadcast::Sender<LiveLine>> = Lazy::new(|| broadcast::channel(1000).0);
+
+/// Receive all new file log lines, from all channels.
+pub fn subscribe_lines() -> broadcast::Receiver<LiveLine> {
+    LIVE_LINES.subscribe()
+}
+
+fn record_channel(record: &Record) -> i32 {
+    i32::try_from(
+        record
+            .key_values()
+            .get("channel".into())
+            .and_then(|v| Value::to_i64(&v))
+            .unwrap_or(0),
+    )
+    .unwrap_or(0)
+}
+
+fn publish_line(now: &mut DeferredNow, record: &Record) -> io::Result<()> {
+    if LIVE_LINES.receiver_count() == 0 {
+        return Ok(());
+    }
+
+    let mut line = vec![];
+    file_formatter(&mut line, now, record)?;
+
+    let _ = LIVE_LINES.send(LiveLine {
+        channel: record_channel(record),
+        level: record.level(),
+        line: String::from_utf8_lossy(&line).to_string(),
+    });
+
+    Ok(())
+}
+
+/// Log level and file retention from the channel configs.
+#[derive(Clone, Debug)]
+struct ChannelLog {
+    level: Option<LevelFilter>,
+    retention: LogRetention,
+}
+
+static CHANNEL_LOGS: Lazy<Mutex<HashMap<i32, ChannelLog>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static LOG_HANDLE: OnceCell<LoggerHandle> = OnceCell::new();
+
+/// Level filter from a name like `info`, `None` for unknown names.
+pub fn level_filter(level: &str) -> Option<LevelFilter> {
+    match level.trim().to_lowercase().as_str() {
+        "debug" => Some(LevelFilter::Debug),
+        "error" => Some(LevelFilter::Error),
+        "info" => Some(LevelFilter::Info),
+        "trace" => Some(LevelFilter::Trace),
+        "warn" | "warning" => Some(LevelFilter::Warn),
+        "off" => Some(LevelFilter::Off),
+        _ => None,
+    }
+}
+
+/// Level from `--log-level`, for messages without channel and channels without own level.
+fn server_level() -> LevelFilter {
+    ARGS.log_level
+        .as_deref()
+        .and_then(level_filter)
+        .unwrap_or(LevelFilter::Debug)
+}
+
+/// Level of the channel, the server level when the channel has none.
+pub fn channel_level(channel: i32) -> LevelFilter {
+    CHANNEL_LOGS
+        .lock()
+        .unwrap()
+        .get(&channel)
+        .and_then(|c| c.level)
+        .unwrap_or_else(server_level)
+}
+
+fn channel_retention(channel: i32) -> LogRetention {
+    CHANNEL_LOGS
+        .lock()
+        .unwrap()
+        .get(&channel)
+        .map(|c| c.retention.clone())
+        .unwrap_or_default()
+}
+
+/// Apply the log level and the retention of a channel, without restart.
+///
+/// The logger lets the most verbose level pass, the filter drops the lines
+/// under the level of their channel.
+pub fn set_channel_logging(channel: i32, config: &Logging) {
+    let level = {
+        let mut logs = CHANNEL_LOGS.lock().unwrap();
+
+        logs.insert(
+            channel,
+            ChannelLog {
+                level: level_filter(&config.level),
+                retention: config.retention.clone(),
+            },
+        );
+
+        logs.values()
+            .filter_map(|c| c.level)
+            .fold(server_level(), Ord::max)
+    };
+
+    if let Some(handle) = LOG_HANDLE.get() {
+        handle.set_new_spec(log_specification(level));
+    }
+}
+
+/// Drop lines under the level of their channel.
+struct ChannelFilter;
+
+impl LogLineFilter for ChannelFilter {
+    fn write(
+        &self,
+        now: &mut DeferredNow,
+        record: &Record,
+        log_line_writer: &dyn LogLineWriter,
+    ) -> io::Result<()> {
+        if record.level() <= channel_level(record_channel(record)) {
+            log_line_writer.write(now, record)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Target;
+
+impl Target {
+    pub fn all() -> &'static str {
+        if ARGS.log_to_console {
+            "{_Default}"
+        } else {
+            "{file,mail,_Default}"
+        }
+    }
+
+    pub fn console() -> &'static str {
+        "{console}"
+    }
+
+    pub fn file() -> &'static str {
+        "{file}"
+    }
+
+    pub fn mail() -> &'static str {
+        "{mail}"
+    }
+
+    pub fn file_mail() -> &'static str {
+        "{file,mail}"
+    }
+}
+
+/// Write log lines as JSON objects, instead of plain text.
+pub fn json_format() -> bool {
+    ARGS.log_format
+        .as_deref()
+        .is_some_and(|f| f.eq_ignore_ascii_case("json"))
+}
+
+pub struct LogConsole;
+
+impl LogWriter for LogConsole {
+    fn write(&self, now: &mut DeferredNow, record: &Record<'_>) -> std::io::Result<()> {
+        publish_line(now, record)?;
+
+        if json_format() {
+            json_formatter(&mut std::io::stderr(), now, record)?;
+        } else {
+            console_formatter(&mut std::io::stderr(), now, record)?;
+        }
+
+        println!();
+        Ok(())
+    }
+    fn flush(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+type ChannelWriter = (LogRetention, Arc<Mutex<FileLogWriter>>);
+
+struct MultiFileLogger {
+    log_path: PathBuf,
+    writers: Arc<Mutex<HashMap<i32, ChannelWriter>>>,
+}
+
+impl MultiFileLogger {
+    pub fn new(log_path: PathBuf) -> Self {
+        Self {
+            log_path,
+            writers: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn get_writer(&self, channel: i32) -> io::Result<Arc<Mutex<FileLogWriter>>> {
+        let retention = channel_retention(channel);
+        let mut writers = self.writers.lock().unwrap();
+
+        match writers.get(&channel) {
+            Some((r, writer)) if *r == retention => return Ok(writer.clone()),
+            Some((_, writer)) => writer.lock().unwrap().flush()?,
+            None => {}
+        }
+
+        let days = if retention.days > 0 {
+            retention.days
+        } else {
+            ARGS.log_backup_count.unwrap_or(14)
+        };
+        let criterion = if retention.max_size > 0 {
+            Criterion::AgeOrSize(Age::Day, retention.max_size * 1024 * 1024)
+        } else {
+            Criterion::Age(Age::Day)
+        };
+        let cleanup = if retention.compress {
+            Cleanup::KeepCompressedFiles(days)
+        } else {
+            Cleanup::KeepLogFiles(days)
+        };
+
+        let writer = FileLogWriter::builder(
+            FileSpec::default()
+                .suppress_timestamp()
+                .directory(&self.log_path)
+                .basename("ffplayout")
+                .discriminant(channel.to_string())
+                .suffix(if json_format() { "json" } else { "log" }),
+        )
+        .format(if json_format() {
+            json_formatter
+        } else {
+            file_formatter
+        })
+        .append()
+        .rotate(
+            criterion,
+            Naming::TimestampsCustomFormat {
+                current_infix: Some(""),
+                format: "%Y-%m-%d",
+            },
+            cleanup,
+        )
+        .try_build()
+        .map_err(|e| io::Error::other(e.to_string()))?;
+        let writer = Arc::new(Mutex::new(writer));
+
+        writers.insert(channel, (retention, writer.clone()));
+
+        Ok(writer)
+    }
+}
+
+impl LogWriter for MultiFileLogger {
+    fn write(&self, now: &mut DeferredNow, record: &Record) -> io::Result<()> {
+        publish_line(now, record)?;
+
+        let channel = record_channel(record);
+        let writer = self.get_writer(channel);
+        let w = writer?.lock().unwrap().write(now, record);
+
+        w
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let writers = self.writers.lock().unwrap();
+        for (_, writer) in writers.values() {
+            writer.lock().unwrap().flush()?;
+        }
+        Ok(())
+    }
+}
+
+pub struct LogMailer {
+    pub mail_queues: Arc<Mutex<Vec<Arc<Mutex<MailQueue>>>>>,
+}
+
+impl LogMailer {
+    pub fn new(mail_queues: Arc<Mutex<Vec<Arc<Mutex<MailQueue>>>>>) -> Self {
+        Self { mail_queues }
+    }
+}
+
+impl LogWriter for LogMailer {
+    fn write(&self, now: &mut DeferredNow, record: &Record<'_>) -> std::io::Result<()> {
+        let id = record_channel(record);
+
+        // planned work makes no pager noise
+        if in_maintenance(id) {
+            return Ok(());
+        }
+
+        let mut queues = self.mail_queues.lock().unwrap_or_else(|poisoned| {
+            error!("Queues mutex was poisoned");
+            poisoned.into_inner()
+        });
+
+        for queue in queues.iter_mut() {
+            let mut q_lock = queue.lock().unwrap_or_else(|poisoned| {
+                error!("Queue mutex was poisoned");
+                poisoned.into_inner()
+            });
+
+            if q_lock.id == id && q_lock.level_eq(record.level()) {
+                let msg = strip_tags(&record.args().to_string());
+                let time = now.now();
+
+                if q_lock.is_repeated(&msg, time.timestamp()) {
+                    break;
+                }
+
+                q_lock.push(
+                    record.level(),
+                    format!(
+                        "[{}] [{:>5}] {}",
+                        time.format("%Y-%m-%d %H:%M:%S"),
+                        record.level(),
+                        msg
+                    ),
+                );
+
+                break;
+            }
+        }
+
+        Ok(())
+    }
+    fn flush(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Collect log lines from one channel, for the mail recipient and the notifiers.
+#[derive(Clone, Debug)]
+pub struct MailQueue {
+    pub id: i32,
+    pub config: Mail,
+    pub lines: Vec<(Level, String)>,
+    /// Last time a message was queued, for the repeat limit.
+    sent: HashMap<String, i64>,
+}
+
+impl MailQueue {
+    pub fn new(id: i32, config: Mail) -> Self {
+        Self {
+            id,
+            config,
+            lines: vec![],
+            sent: HashMap::new(),
+        }
+    }
+
+    /// Level is needed from the mail or from one of the enabled notifiers.
+    pub fn level_eq(&self, level: Level) -> bool {
+        level <= self.config.mail_level
+            || self
+                .config
+                .notifiers
+                .iter()
+                .any(|n| n.enable && level <= n.log_level())
+    }
+
+    /// Identical messages are only queued again, after the repeat limit.
+    pub fn is_repeated(&mut self, msg: &str, timestamp: i64) -> bool {
+        let limit = self.config.repeat_limit;
+
+        if self
+            .sent
+            .get(msg)
+            .is_some_and(|last| timestamp - last < limit)
+        {
+            return true;
+        }
+
+        if self.sent.len() > 1000 {
+            self.sent.retain(|_, last| timestamp - *last < limit);
+        }
+
+        self.sent.insert(msg.to_string(), timestamp);
+
+        false
+    }
+
+    pub fn update(&mut self, config: Mail) {
+        self.config = config;
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    pub fn push(&mut self, level: Level, line: String) {
+        self.lines.push((level, line));
+    }
+
+    /// All lines with the given level or a more important one.
+    pub fn text(&self, level: Level) -> String {
+        self.lines
+            .iter()
+            .filter(|(l, _)| *l <= level)
+            .map(|(_, line)| line.as_str())
+            .collect::<Vec<&str>>()
+            .join("\n")
+    }
+
+    fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
+
+fn strip_tags(input: &str) -> String {
+    let re = Regex::new(r"<[^>]*>").unwrap();
+    re.replace_all(input, "").to_string()
+}
+
+fn console_formatter(w: &mut dyn Write, now: &mut DeferredNow, record: &Record) -> io::Result<()> {
+    let log_line = match record.level() {
+        Level::Debug => colorize_string(format!("<bright-blue>[DEBUG]</> {}", record.args())),
+        Level::Error => colorize_string(format!("<bright-red>[ERROR]</> {}", record.args())),
+        Level::Info => colorize_string(format!("<bright-green>[ INFO]</> {}", record.args())),
+        Level::Trace => colorize_string(format!(
+            "<bright-yellow>[TRACE]</> {}:{} {}",
+            record.file().unwrap_or_default(),
+            record.line().unwrap_or_default(),
+            record.args()
+        )),
+        Level::Warn => colorize_string(format!("<yellow>[ WARN]</> {}", record.args())),
+    };
+
+    if ARGS.log_timestamp {
+        let time = if ARGS.fake_time.is_some() || ARGS.time_speed.is_some() {
+            time_now()
+        } else {
+            *now.now()
+        };
+
+        write!(
+            w,
+            "{} {}",
+            colorize_string(format!(
+                "<bright black>[{}]</>",
+                time.format("%Y-%m-%d %H:%M:%S%.6f")
+            )),
+            log_line
+        )
+    } else {
+        write!(w, "{log_line}")
+    }
+}
+
+fn file_formatter(
+    w: &mut dyn Write,
+    now: &mut DeferredNow,
+    record: &Record,
+) -> std::io::Result<()> {
+    write!(
+        w,
+        "[{}] [{:>5}] {}",
+        now.now().format("%Y-%m-%d %H:%M:%S%.6f"),
+        record.level(),
+        record.args()
+    )
+}
+
+/// Key-values from a log record, without the channel.
+struct Fields(Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for Fields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        if key.as_str() != "channel" {
+            self.0.insert(
+                key.to_string(),
+                serde_json::to_value(value).unwrap_or_default(),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+fn json_formatter(
+    w: &mut dyn Write,
+    now: &mut DeferredNow,
+    record: &Record,
+) -> std::io::Result<()> {
+    let time = if ARGS.fake_time.is_some() || ARGS.time_speed.is_some() {
+        time_now()
+    } else {
+        *now.now()
+    };
+    let mut fields = Fields(Map::new());
+    let _ = record.key_values().visit(&mut fields);
+
+    let line = json!({
+        "timestamp": time.to_rfc3339_opts(SecondsFormat::Micros, false),
+        "level": record.level().as_str(),
+        "channel": record.key_values().get("channel".into()).and_then(|v| Value::to_i64(&v)),
+        "module": record.module_path().unwrap_or_default(),
+        "message": strip_tags(&record.args().to_string()),
+        "fields": fields.0,
+    });
+
+    write!(w, "{line}")
+}
+
+/// Convert JSON log lines back to the plain format, for the log viewer in the frontend.
+pub fn json_to_plain(content: &str) -> String {
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .map(|line| {
+            let time = line["timestamp"]
+                .as_str()
+                .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                .map(|t| t.format("%Y-%m-%d %H:%M:%S%.6f").to_string())
+                .unwrap_or_default();
+
+            format!(
+                "[{time}] [{:>5}] {}",
+                line["level"].as_str().unwrap_or_default(),
+                line["message"].as_str().unwrap_or_default()
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn log_file_path() -> PathBuf {
+    let config = GLOBAL_SETTINGS.get().unwrap();
+    let mut log_path = PathBuf::from(&ARGS.logs.as_ref().unwrap_or(&config.logs));
+
+    if !log_path.is_absolute() {
+        log_path = env::current_dir().unwrap().join(log_path);
+    }
+
+    if !log_path.is_dir() {
+        log_path = env::current_dir().unwrap();
+    }
+
+    log_path
+}
+
+fn file_logger() -> Box<dyn LogWriter> {
+    if ARGS.log_to_console {
+        Box::new(LogConsole)
+    } else {
+        Box::new(MultiFileLogger::new(log_file_path()))
+    }
+}
+
+/// send log messages to mail recipient
+pub async fn send_mail(config: &Mail, msg: String) -> Result<(), ProcessError> {
+    let recipient = config
+        .recipient
+        .split_terminator([',', ';', ' '])
+        .filter(|s| s.contains('@'))
+        .map(str::trim)
+        .collect::<Vec<&str>>();
+
+    let mut message = Message::builder()
+        .from(config.sender_addr.parse()?)
+        .subject(&config.subject)
+        .header(header::ContentType::TEXT_PLAIN);
+
+    for r in recipient {
+        message = message.to(r.parse()?);
+    }
+
+    let mail = message.body(msg)?;
+    let transporter = if config.starttls {
+        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_server)?
+    } else {
+        AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_server)?
+    };
+
+    let credentials = Credentials::new(config.sender_addr.clone(), config.sender_pass.clone());
+    let mailer = transporter.credentials(credentials).build();
+
+    // Send the mail
+    mailer.send(mail).await?;
+
+    Ok(())
+}
+
+enum Alert {
+    Mail(Mail, String, i32),
+    Notifier(Notifier, String, String, i32),
+}
+
+/// Basic Mail Queue
+///
+/// Check every give seconds for messages and send them to the mail recipient and the notifiers.
+pub fn mail_queue(mail_queues: Arc<Mutex<Vec<Arc<Mutex<MailQueue>>>>>) {
+    tokio::spawn(async move {
+        let sec = 10;
+        let mut interval = interval(Duration::from_secs(sec));
+        let mut counter = 0;
+
+        loop {
+            interval.tick().await;
+            let mut tasks = vec![];
+
+            // Reset the counter after one day
+            if counter >= 86400 {
+                counter = 0;
+            } else {
+                counter += sec;
+            }
+
+            {
+                let mut queues = match mail_queues.lock() {
+                    Ok(l) => l,
+                    Err(e) => {
+                        error!("Failed to lock mail_queues {e}");
+                        continue;
+                    }
+                };
+
+                // Process mail queues and send emails
+                for queue in queues.iter_mut() {
+                    let interval = round_to_nearest_ten(counter as i64);
+                    let mut q_lock = queue.lock().unwrap_or_else(|poisoned| {
+                        error!("Queue mutex was poisoned");
+
+                        poisoned.into_inner()
+                    });
+
+                    let expire = round_to_nearest_ten(q_lock.config.interval.max(30));
+
+                    if interval % expire == 0 && !q_lock.is_empty() {
+                        let mail_text = q_lock.text(q_lock.config.mail_level);
+
+                        if q_lock.config.recipient.contains('@') && !mail_text.is_empty() {
+                            tasks.push(Alert::Mail(q_lock.config.clone(), mail_text, q_lock.id));
+                        }
+
+                        for notifier in q_lock.config.notifiers.iter().filter(|n| n.enable) {
+                            let text = q_lock.text(notifier.log_level());
+
+                            if !text.is_empty() {
+                                tasks.push(Alert::Notifier(
+                                    notifier.clone(),
+                                    q_lock.config.subject.clone(),
+                                    text,
+                                    q_lock.id,
+                                ));
+                            }
+                        }
+
+                        // Clear the messages after sending the email
+                        q_lock.clear();
+                    }
+                }
+            }
+
+            for task in tasks {
+                match task {
+                    Alert::Mail(config, text, id) => {
+                        if let Err(e) = send_mail(&config, text).await {
+                            error!(target: "{file}", channel = id; "Failed to send mail: {e}");
+                        }
+                    }
+                    Alert::Notifier(notifier, subject, text, id) => {
+                        if let Err(e) = send_notification(&notifier, &subject, &text).await {
+                            error!(target: "{file}", channel = id; "Failed to send {:?} notification: {e}", notifier.kind);
+                        }
+                    }
+                }
+            }
+        }
+    });
+}
+
+fn log_specification(level: LevelFilter) -> LogSpecification {
+    let mut builder = LogSpecification::builder();
+    builder
+        .default(level)
+        .module("actix", LevelFilter::Info)
+        .module("actix_files", LevelFilter::Info)
+        .module("actix_web", LevelFilter::Info)
+        .module("actix_web_service", LevelFilter::Error)
+        .module("hyper", LevelFilter::Error)
+        .module("flexi_logger", LevelFilter::Error)
+        .module("libc", LevelFilter::Error)
+        .module("log", LevelFilter::Error)
+        .module("mio", LevelFilter::Error)
+        .module("neli", LevelFilter::Error)
+        .module("reqwest", LevelFilter::Error)
+        .module("rpc", LevelFilter::Error)
+        .module("rustls", LevelFilter::Error)
+        .module("serial_test", LevelFilter::Error)
+        .module("sqlx", LevelFilter::Error)
+        .module("tokio", LevelFilter::Error);
+
+    builder.build()
+}
+
+/// Initialize our logging, to have:
+///
+/// - console logger
+/// - file logger
+/// - mail logger
+pub fn init_logging(mail_queues: Arc<Mutex<Vec<Arc<Mutex<MailQueue>>>>>) -> io::Result<()> {
+    mail_queue(mail_queues.clone());
+
+    let handle = Logger::with(log_specification(server_level()))
+        .format(if json_format() {
+            json_formatter
+        } else {
+            console_formatter
+        })
+        .filter(Box::new(ChannelFilter))
+        .log_to_stderr()
+        .add_writer("file", file_logger())
+        .add_writer("mail", Box::new(LogMailer::new(mail_queues)))
+        .start()
+        .map_err(|e| io::Error::other(e.to_string()))?;
+
+    let _ = LOG_HANDLE.set(handle);
+
+    Ok(())
+}
+
+/// Format ingest and HLS logging output
+pub fn log_line(line: &str, level: &str) {
+    if line.contains("[info]") && level.to_lowercase() == "info" {
+        info!("<bright black>[Server]</> {}", line.replace("[info] ", ""));
+    } else if line.contains("[warning]")
+        && (level.to_lowercase() == "warning" || level.to_lowercase() == "info")
+    {
+        warn!(
+            "<bright black>[Server]</> {}",
+            line.replace("[warning] ", "")
+        );
+    } else if line.contains("[error]")
+        && !line.contains("Input/output error")
+        && !line.contains("Broken pipe")
+    {
+        error!("<bright black>[Server]</> {}", line.replace("[error] ", ""));
+    } else if line.contains("[fatal]") {
+        error!("<bright black>[Server]</> {}", line.replace("[fatal] ", ""));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+    use log::LevelFilter;
+
+    use super::*;
+    use crate::utils::config::Logging;
+    use crate::utils::config::Mail;
+    use crate::utils::config::Notifier;
+
+    #[test]
+    fn json_log_lines() {
+        let content = r#"{"timestamp":"2024-05-01T10:00:00.123456+02:00","level":"INFO","channel":1,"module":"ffplayout::player","message":"Play: clip.mp4","fields":{}}
+not json
+{"timestamp":"2024-05-01T10:00:01.000000+02:00","level":"ERROR","channel":1,"module":"ffplayout::player","message":"Decoder failed","fields":{}}"#;
+
+        assert_eq!(
+        json_to_plain(content),
+        "[2024-05-01 10:00:00.123456] [ INFO] Play: clip.mp4\n[2024-05-01 10:00:01.000000] [ERROR] Decoder failed"
+    );
+    }
+
+    #[test]
+    fn channel_log_level() {
+        assert_eq!(level_filter("WARN"), Some(LevelFilter::Warn));
+        assert_eq!(level_filter("warning"), Some(LevelFilter::Warn));
+        assert_eq!(level_filter("debug"), Some(LevelFilter::Debug));
+        assert_eq!(level_filter(""), None);
+
+        let server = channel_level(-1);
+        let mut logging = Logging {
+            level: "warn".to_string(),
+            ..Default::default()
+        };
+
+        set_channel_logging(901, &logging);
+        assert_eq!(channel_level(901), LevelFilter::Warn);
+
+        logging.level = String::new();
+        set_channel_logging(901, &logging);
+        assert_eq!(channel_level(901), server);
+    }
+
+    #[test]
+    fn mail_queue_notifiers() {
+        let mut config = Mail {
+            mail_level: Level::Error,
+            ..Default::default()
+        };
+        config.notifiers.push(Notifier {
+            level: "WARNING".to_string(),
+            ..Default::default()
+        });
+
+        let mut queue = MailQueue::new(1, config);
+
+        assert!(queue.level_eq(Level::Warn));
+        assert!(!queue.level_eq(Level::Info));
+
+        queue.push(Level::Error, "[ERROR] Clip is missing".to_string());
+        queue.push(Level::Warn, "[ WARN] Clip is too short".to_string());
+
+        assert_eq!(queue.text(Level::Error), "[ERROR] Clip is missing");
+        assert_eq!(queue.text(Level::Warn).lines().count(), 2);
+
+        assert!(!queue.is_repeated("Clip is missing", 1000));
+        assert!(queue.is_repeated("Clip is missing", 1000 + 3599));
+        assert!(!queue.is_repeated("Clip is missing", 1000 + 3600));
+
+        queue.config.notifiers[0].enable = false;
+
+        assert!(!queue.level_eq(Level::Warn));
+    }
+}
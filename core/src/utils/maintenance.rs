@@ -0,0 +1,54 @@
+/*
+Maintenance mode of the channels.
+
+A channel in maintenance keeps its settings editable and can be started and stopped by hand,
+but the planned work triggers no pager noise and no accidental starts:
+
+- log lines of the channel go not to the mail recipient and the notifiers
+- the engine does not start the channel by itself: not at startup, not when the air hours begin,
+  not on a failover takeover and not after a move to another node
+- the playout is not started again after it failed, companions are not restarted
+- status endpoints show `maintenance`, the readiness probe does not fail for the channel
+
+The flag is stored in the channel settings, so it survives a restart of the engine.
+
+*/
+
+use std::{
+    collections::HashSet,
+    sync::{LazyLock, Mutex},
+};
+
+static MAINTENANCE: LazyLock<Mutex<HashSet<i32>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Switch the maintenance mode of the channel, returns `true` when it changed.
+pub fn set_maintenance(channel: i32, enable: bool) -> bool {
+    let mut channels = MAINTENANCE.lock().unwrap();
+
+    if enable {
+        channels.insert(channel)
+    } else {
+        channels.remove(&channel)
+    }
+}
+
+pub fn in_maintenance(channel: i32) -> bool {
+    MAINTENANCE.lock().unwrap().contains(&channel)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maintenance_mode() {
+        assert!(!in_maintenance(911));
+        assert!(set_maintenance(911, true));
+        assert!(!set_maintenance(911, true));
+        assert!(in_maintenance(911));
+        assert!(!in_maintenance(912));
+        assert!(set_maintenance(911, false));
+        assert!(!set_maintenance(911, false));
+        assert!(!in_maintenance(911));
+    }
+}
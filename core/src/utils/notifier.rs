@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use crate::utils::{
+    config::{Notifier, NotifierKind},
+    errors::ProcessError,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Discord rejects messages with more characters.
+const DISCORD_LIMIT: usize = 2000;
+
+/// Telegram rejects messages with more characters.
+const TELEGRAM_LIMIT: usize = 4096;
+
+fn truncate(text: &str, limit: usize) -> String {
+    if text.chars().count() <= limit {
+        return text.to_string();
+    }
+
+    let mut short = text.chars().take(limit - 3).collect::<String>();
+    short.push_str("...");
+
+    short
+}
+
+/// Endpoint and JSON body for the chat service.
+pub fn notification_request(notifier: &Notifier, subject: &str, text: &str) -> (String, Value) {
+    let message = format!("{subject}\n\n{text}");
+
+    match notifier.kind {
+        NotifierKind::Telegram => (
+            format!("https://api.telegram.org/bot{}/sendMessage", notifier.token),
+            json!({
+                "chat_id": notifier.chat_id,
+                "text": truncate(&message, TELEGRAM_LIMIT),
+            }),
+        ),
+        NotifierKind::Discord => (
+            notifier.url.clone(),
+            json!({ "content": truncate(&message, DISCORD_LIMIT) }),
+        ),
+        NotifierKind::Slack => (notifier.url.clone(), json!({ "text": message })),
+    }
+}
+
+/// send log messages to a chat service
+pub async fn send_notification(
+    notifier: &Notifier,
+    subject: &str,
+    text: &str,
+) -> Result<(), ProcessError> {
+    let (url, body) = notification_request(notifier, subject, text);
+    let client = reqwest::Client::builder()
+        .timeout(TIMEOUT)
+        .build()
+        .map_err(|e| ProcessError::Custom(e.to_string()))?;
+
+    // without url, the Telegram token is part of it
+    client
+        .post(url)
+        .json(&body)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| ProcessError::Custom(e.without_url().to_string()))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::config::Notifier;
+    use crate::utils::config::NotifierKind;
+
+    #[test]
+    fn notifier_requests() {
+        let telegram = Notifier {
+            token: "123:abc".to_string(),
+            chat_id: "-100".to_string(),
+            ..Default::default()
+        };
+        let discord = Notifier {
+            kind: NotifierKind::Discord,
+            url: "https://discord.com/api/webhooks/1/x".to_string(),
+            ..Default::default()
+        };
+        let slack = Notifier {
+            kind: NotifierKind::Slack,
+            url: "https://hooks.slack.com/services/x".to_string(),
+            ..Default::default()
+        };
+
+        let (url, body) = notification_request(&telegram, "Alert", "Clip is missing");
+
+        assert_eq!(url, "https://api.telegram.org/bot123:abc/sendMessage");
+        assert_eq!(body["chat_id"], "-100");
+        assert_eq!(body["text"], "Alert\n\nClip is missing");
+
+        let (url, body) = notification_request(&discord, "Alert", &"x".repeat(3000));
+
+        assert_eq!(url, discord.url);
+        assert_eq!(body["content"].as_str().unwrap().chars().count(), 2000);
+
+        let (_, body) = notification_request(&slack, "Alert", "Clip is missing");
+
+        assert_eq!(body["text"], "Alert\n\nClip is missing");
+    }
+}
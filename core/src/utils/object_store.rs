@@ -0,0 +1,453 @@
+/*
+Media files in S3 compatible object storage.
+
+The bucket holds the media under `{prefix}/{path in storage}` and the channel storage folder
+is the local cache: an object is downloaded to the same relative path, so the player, the probes
+and the playlist validation work with local files as before.
+
+- the file browser lists folders and files from the bucket
+- uploads are written to the cache and streamed from there to the bucket
+- a prefetcher downloads the clips of the next `prefetch` seconds, before they air
+- when the cache is larger than `cache_size`, the least used downloads are removed
+
+Files which are in the storage folder, but not downloaded by the cache, are never removed.
+
+*/
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{atomic::Ordering, LazyLock, Mutex},
+    thread::sleep,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use log::*;
+use serde::{Deserialize, Serialize};
+use tokio::task;
+
+use crate::player::{controller::ChannelManager, output::s3::S3Client};
+use crate::utils::{config::PlayoutConfig, errors::ServiceError, logging::Target};
+
+const INDEX_FILE: &str = ".object_cache.json";
+const PREFETCH_INTERVAL: Duration = Duration::from_secs(5);
+/// Presigned urls for probing files, which are not in the cache.
+const PROBE_EXPIRE: Duration = Duration::from_secs(300);
+
+/// Cache index by storage folder.
+static CACHES: LazyLock<Mutex<HashMap<PathBuf, CacheIndex>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+/// Files, which are downloading right now.
+static FETCHING: LazyLock<Mutex<HashSet<PathBuf>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub size: u64,
+    /// Unix time of the last use.
+    pub used: u64,
+}
+
+/// Downloaded files in the cache folder, it is stored next to them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CacheIndex {
+    pub files: HashMap<PathBuf, CacheEntry>,
+}
+
+impl CacheIndex {
+    fn load(storage: &Path) -> Self {
+        fs::read_to_string(storage.join(INDEX_FILE))
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, storage: &Path) {
+        if let Ok(json) = serde_json::to_string(self) {
+            if let Err(e) = fs::write(storage.join(INDEX_FILE), json) {
+                error!("Unable to write cache index: {e}");
+            }
+        }
+    }
+
+    pub fn size(&self) -> u64 {
+        self.files.values().map(|e| e.size).sum()
+    }
+
+    /// Files which have to be removed to fit into `limit` bytes, the least used first.
+    /// Files in `keep` stay.
+    pub fn expired(&self, limit: u64, keep: &HashSet<PathBuf>) -> Vec<PathBuf> {
+        let mut size = self.size();
+        let mut files: Vec<(&PathBuf, &CacheEntry)> = self
+            .files
+            .iter()
+            .filter(|(path, _)| !keep.contains(*path))
+            .collect();
+        let mut expired = vec![];
+
+        files.sort_by_key(|(path, entry)| (entry.used, path.to_path_buf()));
+
+        for (path, entry) in files {
+            if size <= limit {
+                break;
+            }
+
+            size -= entry.size;
+            expired.push(path.clone());
+        }
+
+        expired
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Work on the cache index of the storage folder, or of another cache folder.
+pub fn with_index<T>(storage: &Path, f: impl FnOnce(&mut CacheIndex) -> T) -> T {
+    let mut caches = CACHES.lock().unwrap();
+    let index = caches
+        .entry(storage.to_path_buf())
+        .or_insert_with(|| CacheIndex::load(storage));
+    let result = f(index);
+
+    index.save(storage);
+
+    result
+}
+
+pub fn client(config: &PlayoutConfig) -> Result<S3Client, String> {
+    let store = &config.storage.object_store;
+
+    S3Client::connect(
+        &store.endpoint,
+        &store.region,
+        &store.bucket,
+        &store.access_key,
+        &store.secret_key,
+    )
+}
+
+/// Object key for a path in the storage, `None` when the path is outside of it.
+pub fn object_key(config: &PlayoutConfig, path: &Path) -> Option<String> {
+    let relative = path
+        .strip_prefix(&config.channel.storage)
+        .ok()?
+        .to_string_lossy()
+        .trim_matches('/')
+        .to_string();
+    let prefix = config.storage.object_store.prefix.trim_matches('/');
+
+    match (prefix.is_empty(), relative.is_empty()) {
+        (true, _) => Some(relative),
+        (false, true) => Some(prefix.to_string()),
+        (false, false) => Some(format!("{prefix}/{relative}")),
+    }
+}
+
+/// Key of a folder, for listing its content.
+fn folder_key(config: &PlayoutConfig, path: &Path) -> Result<String, ServiceError> {
+    let key = object_key(config, path)
+        .ok_or_else(|| ServiceError::BadRequest("Path is outside the storage".into()))?;
+
+    if key.is_empty() {
+        Ok(key)
+    } else {
+        Ok(format!("{key}/"))
+    }
+}
+
+/// Folders and files in a storage folder, with `true` for folders.
+pub async fn folder_entries(
+    config: &PlayoutConfig,
+    path: &Path,
+) -> Result<Vec<(PathBuf, bool)>, ServiceError> {
+    let client = client(config)?;
+    let prefix = folder_key(config, path)?;
+    let list_prefix = prefix.clone();
+    let list = task::spawn_blocking(move || client.list(&list_prefix)).await??;
+    let mut entries = vec![];
+
+    for folder in list.folders {
+        let name = folder
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or("");
+
+        if !name.is_empty() {
+            entries.push((path.join(name), true));
+        }
+    }
+
+    for (key, _) in list.objects {
+        // the empty object of the folder itself
+        if let Some(name) = key.strip_prefix(&prefix).filter(|n| !n.is_empty()) {
+            entries.push((path.join(name), false));
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Input for ffprobe: the cached file, or a presigned url to the object.
+pub fn probe_source(config: &PlayoutConfig, path: &Path) -> String {
+    if !path.is_file() && config.storage.object_store.enable {
+        if let (Ok(client), Some(key)) = (client(config), object_key(config, path)) {
+            return client.presign(&key, PROBE_EXPIRE);
+        }
+    }
+
+    path.to_string_lossy().to_string()
+}
+
+/// Download the object of a storage file, when it is not in the cache.
+/// Returns `false` when the file was there already.
+pub fn fetch(config: &PlayoutConfig, source: &str) -> Result<bool, String> {
+    let path = Path::new(source);
+    let storage = &config.channel.storage;
+    let key = object_key(config, path).ok_or_else(|| format!("{source} is outside the storage"))?;
+
+    // wait for a running download of the same file
+    while !FETCHING.lock().unwrap().insert(path.to_path_buf()) {
+        sleep(Duration::from_millis(100));
+    }
+
+    if path.is_file() {
+        FETCHING.lock().unwrap().remove(path);
+
+        with_index(storage, |index| {
+            if let Some(entry) = index.files.get_mut(path) {
+                entry.used = unix_now();
+            }
+        });
+
+        return Ok(false);
+    }
+
+    let part = PathBuf::from(format!("{source}.part"));
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .map_err(|e| e.to_string())
+        .and_then(|_| client(config))
+        .and_then(|c| c.get_file(&key, &part))
+        .and_then(|size| {
+            fs::rename(&part, path)
+                .map(|_| size)
+                .map_err(|e| e.to_string())
+        });
+
+    FETCHING.lock().unwrap().remove(path);
+
+    match result {
+        Ok(size) => {
+            with_index(storage, |index| {
+                index.files.insert(
+                    path.to_path_buf(),
+                    CacheEntry {
+                        size,
+                        used: unix_now(),
+                    },
+                )
+            });
+
+            Ok(true)
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&part);
+
+            Err(format!("Download of {key} failed: {e}"))
+        }
+    }
+}
+
+/// Upload a file from the storage folder to the bucket, it stays in the cache.
+pub async fn push(config: &PlayoutConfig, path: &Path) -> Result<(), ServiceError> {
+    let client = client(config)?;
+    let key = object_key(config, path)
+        .ok_or_else(|| ServiceError::BadRequest("Path is outside the storage".into()))?;
+    let file = path.to_path_buf();
+
+    task::spawn_blocking(move || client.put_file(&key, &file)).await??;
+
+    let size = fs::metadata(path).map(|m| m.len()).unwrap_or_default();
+
+    with_index(&config.channel.storage, |index| {
+        index.files.insert(
+            path.to_path_buf(),
+            CacheEntry {
+                size,
+                used: unix_now(),
+            },
+        )
+    });
+
+    Ok(())
+}
+
+/// Empty object, which marks a folder.
+pub async fn create_folder(config: &PlayoutConfig, path: &Path) -> Result<(), ServiceError> {
+    let client = client(config)?;
+    let key = folder_key(config, path)?;
+
+    task::spawn_blocking(move || client.put(&key, vec![], "application/x-directory")).await??;
+
+    Ok(())
+}
+
+fn remove_objects(client: &S3Client, prefix: &str) -> Result<(), String> {
+    let list = client.list(prefix)?;
+
+    for folder in &list.folders {
+        remove_objects(client, folder)?;
+    }
+
+    for (key, _) in &list.objects {
+        client.delete(key)?;
+    }
+
+    Ok(())
+}
+
+/// Remove a file or folder from the bucket and the cache.
+pub async fn remove(
+    config: &PlayoutConfig,
+    path: &Path,
+    recursive: bool,
+) -> Result<(), ServiceError> {
+    let client = client(config)?;
+    let key = object_key(config, path)
+        .ok_or_else(|| ServiceError::BadRequest("Path is outside the storage".into()))?;
+    let prefix = folder_key(config, path)?;
+    let list_client = client.clone();
+    let list_prefix = prefix.clone();
+    let content = task::spawn_blocking(move || list_client.list(&list_prefix)).await??;
+    let is_folder = !content.folders.is_empty() || !content.objects.is_empty();
+
+    if is_folder {
+        let has_content =
+            !content.folders.is_empty() || content.objects.iter().any(|(k, _)| *k != prefix);
+
+        if has_content && !recursive {
+            return Err(ServiceError::BadRequest(
+                "Delete folder failed! (Folder must be empty)".into(),
+            ));
+        }
+
+        task::spawn_blocking(move || remove_objects(&client, &prefix)).await??;
+    } else {
+        task::spawn_blocking(move || client.delete(&key)).await??;
+    }
+
+    with_index(&config.channel.storage, |index| {
+        index.files.retain(|p, _| !p.starts_with(path));
+    });
+
+    if path.is_dir() {
+        tokio::fs::remove_dir_all(path).await?;
+    } else if path.is_file() {
+        tokio::fs::remove_file(path).await?;
+    }
+
+    Ok(())
+}
+
+/// Remove the least used downloads, until the cache fits into its size.
+fn evict(config: &PlayoutConfig, keep: &HashSet<PathBuf>) {
+    let limit = config.storage.object_store.cache_size * 1024 * 1024;
+    let id = config.general.channel_id;
+
+    if limit == 0 {
+        return;
+    }
+
+    with_index(&config.channel.storage, |index| {
+        for path in index.expired(limit, keep) {
+            match fs::remove_file(&path) {
+                Ok(_) => {
+                    debug!(target: Target::file_mail(), channel = id; "Remove <b><magenta>{}</></b> from cache", path.display());
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    error!(target: Target::file_mail(), channel = id; "Remove <b><magenta>{}</></b> from cache failed: {e}", path.display());
+                    continue;
+                }
+            }
+
+            index.files.remove(&path);
+        }
+    });
+}
+
+/// Sources from the current clip on, until `length` seconds are covered.
+pub fn upcoming(manager: &ChannelManager, length: f64) -> Vec<String> {
+    let list = manager.current_list.lock().unwrap();
+    let index = manager.current_index.load(Ordering::SeqCst);
+    let start = index.saturating_sub(1).min(list.len());
+    let mut duration = 0.0;
+    let mut sources = vec![];
+
+    for item in &list[start..] {
+        if duration > length {
+            break;
+        }
+
+        if !item.source.is_empty() && !sources.contains(&item.source) {
+            sources.push(item.source.clone());
+        }
+
+        duration += item.out - item.seek;
+    }
+
+    sources
+}
+
+/// Download the upcoming clips in background, until the channel stops.
+pub fn prefetcher(manager: ChannelManager) {
+    let is_terminated = manager.is_terminated.clone();
+
+    while !is_terminated.load(Ordering::SeqCst) {
+        let config = manager.config.lock().unwrap().clone();
+        let id = config.general.channel_id;
+
+        if !config.storage.object_store.enable {
+            break;
+        }
+
+        let sources = upcoming(&manager, config.storage.object_store.prefetch);
+        let mut keep = HashSet::new();
+
+        for source in sources {
+            let path = PathBuf::from(&source);
+
+            if !path.starts_with(&config.channel.storage) {
+                continue;
+            }
+
+            match fetch(&config, &source) {
+                Ok(true) => {
+                    info!(target: Target::file_mail(), channel = id; "Prefetched <b><magenta>{source}</></b>");
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    error!(target: Target::file_mail(), channel = id; "{e}");
+                }
+            }
+
+            keep.insert(path);
+
+            if is_terminated.load(Ordering::SeqCst) {
+                return;
+            }
+        }
+
+        evict(&config, &keep);
+
+        sleep(PREFETCH_INTERVAL);
+    }
+}
@@ -0,0 +1,400 @@
+/*
+Image and HTML overlay, controlled over the API.
+
+The output has a transparent canvas in the size of the channel as overlay, which is read again
+for every frame. Showing an image draws it with ffmpeg onto the canvas, at the given position,
+hiding it draws an empty canvas. So the overlay changes right away, without restarting the output.
+
+HTML pages are rendered headless with a Chromium compatible browser to an image, with `refresh`
+they are rendered again every few seconds, for pages with changing content.
+
+*/
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::atomic::Ordering,
+    thread::{self, sleep},
+    time::{Duration, Instant},
+};
+
+use chrono::TimeDelta;
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::player::controller::ChannelManager;
+use crate::utils::{
+    config::PlayoutConfig, errors::ServiceError, files::norm_abs_path, logging::Target,
+    thumbnails::run_ffmpeg, time_machine::time_now,
+};
+
+const TIMER_STEP: Duration = Duration::from_millis(200);
+
+/// Overlay parameters from the control API.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct OverlayParams {
+    pub show: bool,
+    /// Image in the storage, best a PNG with alpha channel.
+    pub source: String,
+    /// HTML page, which is rendered headless.
+    pub url: String,
+    /// Position, in pixel or as ffmpeg expression, like `(W-w)/2`.
+    pub x: String,
+    pub y: String,
+    /// Width in pixel, 0 keeps the size of the image.
+    pub width: i64,
+    /// Seconds until the overlay hides by itself, 0 shows it until it is hidden.
+    pub duration: f64,
+    /// Seconds until the HTML page is rendered again, 0 renders it once.
+    pub refresh: f64,
+}
+
+impl Default for OverlayParams {
+    fn default() -> Self {
+        Self {
+            show: true,
+            source: String::new(),
+            url: String::new(),
+            x: "0".to_string(),
+            y: "0".to_string(),
+            width: 0,
+            duration: 0.0,
+            refresh: 0.0,
+        }
+    }
+}
+
+/// Current overlay, for the status API.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OverlayState {
+    pub visible: bool,
+    pub params: Option<OverlayParams>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    /// Counts the changes, timers from older overlays stop.
+    #[serde(skip)]
+    pub generation: u64,
+}
+
+/// Canvas, which the output overlays.
+pub fn canvas_file(id: i32) -> PathBuf {
+    env::temp_dir().join(format!("ffplayout_{id}_overlay.png"))
+}
+
+/// Position is a number or a simple expression, nothing which can break out of the filter.
+pub fn valid_position(value: &str) -> bool {
+    !value.trim().is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+-*/(). _".contains(c))
+}
+
+/// Arguments for ffmpeg, which draw the image onto a transparent canvas.
+pub fn canvas_args(
+    width: i64,
+    height: i64,
+    image: Option<(&Path, &OverlayParams)>,
+    output: &Path,
+) -> Vec<String> {
+    let mut args = vec![
+        "-f".to_string(),
+        "lavfi".to_string(),
+        "-i".to_string(),
+        format!("color=c=black@0.0:s={width}x{height},format=rgba"),
+    ];
+
+    if let Some((path, params)) = image {
+        let scale = if params.width > 0 {
+            format!("scale={}:-1,", params.width)
+        } else {
+            String::new()
+        };
+
+        args.append(&mut vec![
+            "-i".to_string(),
+            path.to_string_lossy().to_string(),
+            "-filter_complex".to_string(),
+            format!(
+                "[1:v]{scale}format=rgba[o];[0:v][o]overlay=x={}:y={}:format=auto,format=rgba",
+                params.x.trim(),
+                params.y.trim()
+            ),
+        ]);
+    }
+
+    args.append(&mut vec![
+        "-frames:v".to_string(),
+        "1".to_string(),
+        output.to_string_lossy().to_string(),
+    ]);
+
+    args
+}
+
+/// Draw the canvas, it is written to a temporary file first,
+/// so the output never reads a half written image.
+fn draw_canvas(
+    config: &PlayoutConfig,
+    image: Option<(&Path, &OverlayParams)>,
+) -> Result<(), ServiceError> {
+    let canvas = canvas_file(config.general.channel_id);
+    let tmp = canvas.with_extension("tmp.png");
+    let args = canvas_args(
+        config.processing.width,
+        config.processing.height,
+        image,
+        &tmp,
+    );
+
+    run_ffmpeg(&args)?;
+    fs::rename(&tmp, &canvas)?;
+
+    Ok(())
+}
+
+/// Render the HTML page to an image in the size of the channel.
+fn render_html(config: &PlayoutConfig, url: &str, target: &Path) -> Result<(), ServiceError> {
+    let output = Command::new(&config.text.overlay.browser)
+        .args([
+            "--headless",
+            "--disable-gpu",
+            "--hide-scrollbars",
+            "--default-background-color=00000000",
+            "--virtual-time-budget=5000",
+            &format!(
+                "--window-size={},{}",
+                config.processing.width, config.processing.height
+            ),
+            &format!("--screenshot={}", target.display()),
+            url,
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()?;
+
+    if !output.status.success() || !target.is_file() {
+        return Err(ServiceError::Conflict(format!(
+            "Render {url} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Draw the overlay from the parameters onto the canvas.
+fn draw(config: &PlayoutConfig, params: &OverlayParams) -> Result<(), ServiceError> {
+    if params.url.is_empty() {
+        let (path, _, _) = norm_abs_path(&config.channel.storage, &params.source)?;
+
+        if !path.is_file() {
+            return Err(ServiceError::BadRequest(format!(
+                "Image {} not exists!",
+                params.source
+            )));
+        }
+
+        return draw_canvas(config, Some((&path, params)));
+    }
+
+    let page = env::temp_dir().join(format!("ffplayout_{}_page.png", config.general.channel_id));
+    let result = render_html(config, &params.url, &page)
+        .and_then(|_| draw_canvas(config, Some((&page, params))));
+
+    let _ = fs::remove_file(&page);
+
+    result
+}
+
+/// Empty canvas and no overlay, when the channel starts.
+pub fn reset_overlay(manager: &ChannelManager) {
+    let config = manager.config.lock().unwrap().clone();
+    let mut state = manager.overlay.lock().unwrap();
+
+    if let Err(e) = draw_canvas(&config, None) {
+        error!(target: Target::file_mail(), channel = config.general.channel_id; "Overlay canvas: {e}");
+    }
+
+    *state = OverlayState {
+        generation: state.generation + 1,
+        ..Default::default()
+    };
+}
+
+/// Show or hide the overlay.
+pub fn set_overlay(
+    manager: &ChannelManager,
+    params: OverlayParams,
+) -> Result<OverlayState, ServiceError> {
+    let config = manager.config.lock().unwrap().clone();
+    let id = config.general.channel_id;
+
+    if !config.text.overlay.enable {
+        return Err(ServiceError::BadRequest(
+            "Overlay is not enabled".to_string(),
+        ));
+    }
+
+    if params.show {
+        if params.source.is_empty() == params.url.is_empty() {
+            return Err(ServiceError::BadRequest(
+                "Overlay needs a source or a url".to_string(),
+            ));
+        }
+
+        if !valid_position(&params.x) || !valid_position(&params.y) {
+            return Err(ServiceError::BadRequest("Invalid position".to_string()));
+        }
+
+        if !params.url.is_empty() && !params.url.starts_with("http") {
+            return Err(ServiceError::BadRequest(
+                "Url must start with http".to_string(),
+            ));
+        }
+    }
+
+    // the lock keeps the timers from drawing at the same time
+    let mut state = manager.overlay.lock().unwrap();
+
+    if params.show {
+        draw(&config, &params)?;
+    } else {
+        draw_canvas(&config, None)?;
+    }
+
+    let now = time_now();
+
+    state.generation += 1;
+    state.visible = params.show;
+    state.since = Some(now.format("%Y-%m-%d %H:%M:%S").to_string());
+    state.until = (params.show && params.duration > 0.0).then(|| {
+        (now + TimeDelta::milliseconds((params.duration * 1000.0) as i64))
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    });
+
+    if params.show {
+        info!(target: Target::file(), channel = id; "Show overlay <b><magenta>{}{}</></b>", params.source, params.url);
+
+        if params.duration > 0.0 || (!params.url.is_empty() && params.refresh > 0.0) {
+            let manager = manager.clone();
+            let generation = state.generation;
+            let params = params.clone();
+
+            thread::spawn(move || overlay_timer(manager, generation, params));
+        }
+    } else {
+        info!(target: Target::file(), channel = id; "Hide overlay");
+    }
+
+    state.params = params.show.then_some(params);
+
+    Ok(state.clone())
+}
+
+/// Render HTML pages again and hide the overlay after its duration,
+/// until the overlay is changed.
+fn overlay_timer(manager: ChannelManager, generation: u64, params: OverlayParams) {
+    let start = Instant::now();
+    let mut rendered = Instant::now();
+    let is_current = || {
+        manager.overlay.lock().unwrap().generation == generation
+            && !manager.is_terminated.load(Ordering::SeqCst)
+    };
+
+    while is_current() {
+        let config = manager.config.lock().unwrap().clone();
+        let id = config.general.channel_id;
+
+        if params.duration > 0.0 && start.elapsed().as_secs_f64() >= params.duration {
+            let mut state = manager.overlay.lock().unwrap();
+
+            if state.generation == generation {
+                if let Err(e) = draw_canvas(&config, None) {
+                    error!(target: Target::file_mail(), channel = id; "Overlay canvas: {e}");
+                }
+
+                *state = OverlayState {
+                    generation: generation + 1,
+                    ..Default::default()
+                };
+
+                info!(target: Target::file(), channel = id; "Hide overlay after {} seconds", params.duration);
+            }
+
+            break;
+        }
+
+        if !params.url.is_empty()
+            && params.refresh > 0.0
+            && rendered.elapsed().as_secs_f64() >= params.refresh
+        {
+            let state = manager.overlay.lock().unwrap();
+
+            if state.generation != generation {
+                break;
+            }
+
+            if let Err(e) = draw(&config, &params) {
+                error!(target: Target::file_mail(), channel = id; "Overlay: {e}");
+            }
+
+            rendered = Instant::now();
+        }
+
+        sleep(TIMER_STEP);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn image_overlay_canvas() {
+        assert!(valid_position("(W-w)/2"));
+        assert!(valid_position("H-h-60"));
+        assert!(!valid_position(""));
+        assert!(!valid_position("0:enable=0"));
+        assert!(!valid_position("0,drawbox"));
+
+        let params: OverlayParams =
+            serde_json::from_str(r#"{"source": "banner.png", "x": "(W-w)/2", "width": 640}"#)
+                .unwrap();
+
+        assert!(params.show);
+        assert_eq!(params.y, "0");
+
+        let empty = canvas_args(1280, 720, None, Path::new("/tmp/canvas.png"));
+
+        assert_eq!(
+            empty,
+            vec![
+                "-f",
+                "lavfi",
+                "-i",
+                "color=c=black@0.0:s=1280x720,format=rgba",
+                "-frames:v",
+                "1",
+                "/tmp/canvas.png"
+            ]
+        );
+
+        let args = canvas_args(
+            1280,
+            720,
+            Some((Path::new("/tv-media/banner.png"), &params)),
+            Path::new("/tmp/canvas.png"),
+        );
+
+        assert_eq!(args[5], "/tv-media/banner.png");
+        assert_eq!(
+        args[7],
+        "[1:v]scale=640:-1,format=rgba[o];[0:v][o]overlay=x=(W-w)/2:y=0:format=auto,format=rgba"
+    );
+    }
+}
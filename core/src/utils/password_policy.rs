@@ -0,0 +1,194 @@
+/*
+Password policy for all users.
+
+The policy is in the global settings and is checked, when a user is added, a password is changed
+or reset:
+
+- `min_length`: minimum count of characters
+- `uppercase`, `lowercase`, `digit`, `symbol`: the password needs at least one of them
+- `history`: count of last passwords, with the current one, which can not be used again
+- `max_age`: days until a password expires, `0` never
+
+An expired password still works, but the login response has `must_change_password`, so the
+frontend asks for a new password. Global admins can set the same flag for other users.
+
+*/
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, SaltString},
+    Argon2, PasswordHasher, PasswordVerifier,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use tokio::task;
+
+use crate::db::handles;
+use crate::utils::{errors::ServiceError, time_machine::time_now};
+
+/// Rule for the password: enabled, name for the message and the check for a character.
+type Rule = (bool, &'static str, fn(&char) -> bool);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub uppercase: bool,
+    pub lowercase: bool,
+    pub digit: bool,
+    pub symbol: bool,
+    pub history: usize,
+    pub max_age: i64,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            uppercase: false,
+            lowercase: false,
+            digit: false,
+            symbol: false,
+            history: 0,
+            max_age: 0,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Check length and complexity, the error lists all missing rules.
+    pub fn check(&self, password: &str) -> Result<(), ServiceError> {
+        if password.is_empty() {
+            return Err(ServiceError::BadRequest("Password is empty".to_string()));
+        }
+
+        let mut missing = vec![];
+
+        if password.chars().count() < self.min_length {
+            missing.push(format!("at least {} characters", self.min_length));
+        }
+
+        let rules: [Rule; 4] = [
+            (self.uppercase, "an uppercase letter", |c| c.is_uppercase()),
+            (self.lowercase, "a lowercase letter", |c| c.is_lowercase()),
+            (self.digit, "a digit", |c| c.is_ascii_digit()),
+            (self.symbol, "a symbol", |c| !c.is_alphanumeric()),
+        ];
+
+        for (enabled, name, rule) in rules {
+            if enabled && !password.chars().any(|c| rule(&c)) {
+                missing.push(name.to_string());
+            }
+        }
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(ServiceError::BadRequest(format!(
+                "Password needs {}",
+                missing.join(", ")
+            )))
+        }
+    }
+
+    /// Password, which was changed at the `changed` timestamp, is expired.
+    pub fn is_expired(&self, changed: i64, now: i64) -> bool {
+        self.max_age > 0 && now - changed > self.max_age * 86400
+    }
+}
+
+fn hash_password(password: &str) -> Result<String, ServiceError> {
+    let salt = SaltString::generate(&mut OsRng);
+
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|p| p.to_string())
+        .map_err(|_| ServiceError::InternalServerError)
+}
+
+fn matches_any(password: &str, hashes: &[String]) -> bool {
+    hashes.iter().any(|hash| {
+        PasswordHash::new(hash).is_ok_and(|h| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &h)
+                .is_ok()
+        })
+    })
+}
+
+/// Check the password against the policy and save it, the old one goes to the history.
+pub async fn set_password(
+    conn: &Pool<Sqlite>,
+    user_id: i32,
+    password: &str,
+    must_change: bool,
+) -> Result<(), ServiceError> {
+    let policy = handles::select_password_policy(conn).await?;
+
+    policy.check(password)?;
+
+    let current = handles::select_login_hash(conn, user_id).await?;
+    // the current password counts as first one of the history
+    let older = policy.history.saturating_sub(1);
+    let mut hashes = vec![];
+
+    if policy.history > 0 {
+        hashes.push(current.clone());
+        hashes.append(&mut handles::select_password_history(conn, user_id, older).await?);
+    }
+
+    let password = password.to_string();
+    let hash = task::spawn_blocking(move || {
+        if matches_any(&password, &hashes) {
+            return Err(ServiceError::BadRequest(
+                "Password was used before".to_string(),
+            ));
+        }
+
+        hash_password(&password)
+    })
+    .await??;
+
+    if older > 0 {
+        handles::insert_password_history(conn, user_id, &current, older).await?;
+    }
+
+    handles::update_user_password(conn, user_id, &hash, time_now().timestamp(), must_change)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::errors::ServiceError;
+
+    #[test]
+    fn password_policy_check() {
+        let mut policy = PasswordPolicy::default();
+
+        assert!(policy.check("").is_err());
+        assert!(policy.check("short").is_err());
+        assert!(policy.check("longenough").is_ok());
+
+        policy.uppercase = true;
+        policy.digit = true;
+        policy.symbol = true;
+
+        match policy.check("longenough") {
+            Err(ServiceError::BadRequest(e)) => {
+                assert_eq!(e, "Password needs an uppercase letter, a digit, a symbol");
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+
+        assert!(policy.check("Long-enough1").is_ok());
+
+        policy.max_age = 0;
+        assert!(!policy.is_expired(0, 100 * 86400));
+
+        policy.max_age = 30;
+        assert!(!policy.is_expired(1000, 1000 + 30 * 86400));
+        assert!(policy.is_expired(1000, 1000 + 30 * 86400 + 1));
+    }
+}
@@ -0,0 +1,140 @@
+/*
+Password reset by mail.
+
+`/auth/reset-request/` sends a link with a token to the mail address of the user. The token has
+the user id, the expire time and a random nonce, signed with the secret from the global settings.
+The database has only the hash of the token, so a token works once and only the last requested
+token of a user is valid.
+
+The mail goes over the SMTP server from the global settings. The link in the mail points to
+`--public-url`, never to the host of the request, which a client can forge. Without public URL
+no reset mail is sent.
+
+*/
+
+use hmac::{Hmac, Mac};
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+
+use crate::db::models::GlobalSettings;
+use crate::utils::{config::Mail, errors::ServiceError};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Seconds until a reset token expires.
+pub const RESET_TTL: i64 = 3600;
+
+fn signature(secret: &str, payload: &str) -> HmacSha256 {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
+    mac.update(payload.as_bytes());
+
+    mac
+}
+
+/// Signed token for the user, like `<user id>.<expires>.<nonce>.<signature>`.
+pub fn reset_token(secret: &str, user_id: i32, expires: i64) -> String {
+    let nonce: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect();
+    let payload = format!("{user_id}.{expires}.{nonce}");
+    let sig = hex::encode(signature(secret, &payload).finalize().into_bytes());
+
+    format!("{payload}.{sig}")
+}
+
+/// User id from the token, when the signature is valid and the token is not expired.
+pub fn verify_token(secret: &str, token: &str, now: i64) -> Result<i32, ServiceError> {
+    let invalid = || ServiceError::Forbidden("Invalid or expired reset token".to_string());
+    let (payload, sig) = token.trim().rsplit_once('.').ok_or_else(invalid)?;
+    let sig = hex::decode(sig).map_err(|_| invalid())?;
+
+    signature(secret, payload)
+        .verify_slice(&sig)
+        .map_err(|_| invalid())?;
+
+    let mut parts = payload.split('.');
+    let user_id = parts
+        .next()
+        .and_then(|p| p.parse().ok())
+        .ok_or_else(invalid)?;
+    let expires = parts
+        .next()
+        .and_then(|p| p.parse::<i64>().ok())
+        .ok_or_else(invalid)?;
+
+    if expires < now {
+        return Err(invalid());
+    }
+
+    Ok(user_id)
+}
+
+/// Hash of the token, for the database.
+pub fn token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.trim().as_bytes()))
+}
+
+/// Mail settings from the global SMTP config, `None` when no server is configured.
+pub fn reset_mail(global: &GlobalSettings, recipient: &str) -> Option<Mail> {
+    if global.mail_smtp.is_empty() || global.mail_user.is_empty() {
+        return None;
+    }
+
+    Some(Mail {
+        subject: "ffplayout password reset".to_string(),
+        smtp_server: global.mail_smtp.clone(),
+        starttls: global.mail_starttls,
+        sender_addr: global.mail_user.clone(),
+        sender_pass: global.mail_password.clone(),
+        recipient: recipient.to_string(),
+        ..Default::default()
+    })
+}
+
+/// Base for the reset link from `--public-url`, `None` when it is not set or not http(s).
+pub fn reset_base(public_url: Option<&str>) -> Option<String> {
+    let base = public_url?.trim().trim_end_matches('/');
+
+    if !base.starts_with("http://") && !base.starts_with("https://") {
+        return None;
+    }
+
+    Some(base.to_string())
+}
+
+pub fn reset_text(username: &str, link: &str) -> String {
+    format!(
+        "Hello {username},\n\na password reset was requested for your ffplayout account. \
+        Set a new password within {} minutes with this link:\n\n{link}\n\n\
+        When you did not request it, ignore this mail, your password stays the same.",
+        RESET_TTL / 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn password_reset_token() {
+        let token = reset_token("secret", 7, 1000);
+
+        assert_eq!(verify_token("secret", &token, 999).unwrap(), 7);
+        assert_eq!(verify_token("secret", &token, 1000).unwrap(), 7);
+        assert!(verify_token("secret", &token, 1001).is_err());
+        assert!(verify_token("other", &token, 999).is_err());
+        assert!(verify_token("secret", &token.replacen('7', "8", 1), 999).is_err());
+        assert!(verify_token("secret", "7.1000", 999).is_err());
+        assert_ne!(reset_token("secret", 7, 1000), token);
+        assert_eq!(token_hash(&token), token_hash(&format!(" {token}\n")));
+        assert_eq!(
+            reset_base(Some("https://play.example.org/")),
+            Some("https://play.example.org".to_string())
+        );
+        assert_eq!(reset_base(Some("play.example.org")), None);
+        assert_eq!(reset_base(None), None);
+    }
+}
@@ -0,0 +1,901 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use log::*;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use tokio::{sync::Mutex, task};
+
+use crate::db::handles;
+use crate::player::controller::ChannelManager;
+use crate::player::filter::{
+    audio_layout::validate_channel_map,
+    custom::{ffmpeg_filters, validate_filter},
+};
+use crate::player::utils::{is_remote, json_reader, json_writer, JsonPlaylist, Media};
+use crate::utils::{
+    config::PlayoutConfig, errors::ServiceError, files::norm_abs_path,
+    generator::playlist_generator, time_machine::time_now,
+};
+
+/// Versions which are kept per playlist.
+const MAX_VERSIONS: i64 = 50;
+
+/// Item edits read and write the playlist one after the other.
+static EDIT_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemDiff {
+    pub kind: DiffKind,
+    pub old_index: Option<usize>,
+    pub new_index: Option<usize>,
+    pub old: Option<Media>,
+    pub new: Option<Media>,
+}
+
+pub async fn read_playlist(
+    config: &PlayoutConfig,
+    date: String,
+) -> Result<JsonPlaylist, ServiceError> {
+    let d: Vec<&str> = date.split('-').collect();
+    let mut playlist_path = config.channel.playlists.clone();
+
+    playlist_path = playlist_path
+        .join(d[0])
+        .join(d[1])
+        .join(date.clone())
+        .with_extension("json");
+
+    match json_reader(&playlist_path) {
+        Ok(p) => Ok(p),
+        Err(e) => Err(ServiceError::NoContent(e.to_string())),
+    }
+}
+
+pub async fn write_playlist(
+    config: &PlayoutConfig,
+    mut json_data: JsonPlaylist,
+) -> Result<String, ServiceError> {
+    for (i, item) in json_data.program.iter_mut().enumerate() {
+        validate_filter(&item.custom_filter, ffmpeg_filters())
+            .map_err(|e| ServiceError::BadRequest(format!("Item {}: {e}", i + 1)))?;
+
+        if let Some(map) = &item.channel_map {
+            validate_channel_map(map)
+                .map_err(|e| ServiceError::BadRequest(format!("Item {}: {e}", i + 1)))?;
+        }
+
+        if let Some(artwork) = item.artwork.take().filter(|a| !a.is_empty()) {
+            let (path, _, relative) = norm_abs_path(&config.channel.storage, &artwork)?;
+
+            if !path.is_file() {
+                return Err(ServiceError::BadRequest(format!(
+                    "Artwork not found in storage: {artwork}"
+                )));
+            }
+
+            item.artwork = Some(relative);
+        }
+    }
+
+    let date = json_data.date.clone();
+    let d: Vec<&str> = date.split('-').collect();
+    let mut playlist_path = config.channel.playlists.clone();
+
+    if !playlist_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case("json"))
+        .unwrap_or(false)
+    {
+        playlist_path = playlist_path
+            .join(d[0])
+            .join(d[1])
+            .join(date.clone())
+            .with_extension("json");
+    }
+
+    let mut file_exists = false;
+
+    if let Some(p) = playlist_path.parent() {
+        fs::create_dir_all(p)?;
+    }
+
+    if playlist_path.is_file() {
+        file_exists = true;
+        if let Ok(existing_data) = json_reader(&playlist_path) {
+            if json_data == existing_data {
+                return Err(ServiceError::Conflict(format!(
+                    "Playlist from {date}, already exists!"
+                )));
+            }
+        }
+    }
+
+    match json_writer(&playlist_path, json_data) {
+        Ok(..) if file_exists => {
+            return Ok(format!("Update playlist from {date} success!"));
+        }
+        Ok(..) => {
+            return Ok(format!("Write playlist from {date} success!"));
+        }
+        Err(e) => {
+            error!("{e}");
+        }
+    }
+
+    Err(ServiceError::InternalServerError)
+}
+
+pub fn generate_playlist(manager: ChannelManager) -> Result<JsonPlaylist, ServiceError> {
+    let mut config = manager.config.lock().unwrap();
+
+    if let Some(mut template) = config.general.template.take() {
+        for source in &mut template.sources {
+            let mut paths = vec![];
+
+            for path in &source.paths {
+                let (safe_path, _, _) =
+                    norm_abs_path(&config.channel.storage, &path.to_string_lossy())?;
+                paths.push(safe_path);
+            }
+
+            source.paths = paths;
+        }
+
+        config.general.template = Some(template);
+    }
+
+    drop(config);
+
+    match playlist_generator(&manager) {
+        Ok(playlists) => {
+            if playlists.is_empty() {
+                Err(ServiceError::Conflict(
+                    "The playlist could not be written, maybe it already exists!".into(),
+                ))
+            } else {
+                Ok(playlists[0].clone())
+            }
+        }
+        Err(e) => {
+            error!("{e}");
+            Err(ServiceError::InternalServerError)
+        }
+    }
+}
+
+pub async fn delete_playlist(config: &PlayoutConfig, date: &str) -> Result<String, ServiceError> {
+    let d: Vec<&str> = date.split('-').collect();
+    let mut playlist_path = PathBuf::from(&config.channel.playlists);
+
+    playlist_path = playlist_path
+        .join(d[0])
+        .join(d[1])
+        .join(date)
+        .with_extension("json");
+
+    if playlist_path.is_file() {
+        match fs::remove_file(playlist_path) {
+            Ok(..) => Ok(format!("Delete playlist from {date} success!")),
+            Err(e) => {
+                error!("{e}");
+                Err(ServiceError::InternalServerError)
+            }
+        }
+    } else {
+        Ok(format!("No playlist to delete on: {date}"))
+    }
+}
+
+/// Copy of a playlist for another date and channel.
+///
+/// Sources in the storage of the original channel are rewritten to the target storage.
+pub fn copy_playlist(
+    playlist: &JsonPlaylist,
+    channel: &str,
+    date: &str,
+    from_storage: &Path,
+    to_storage: &Path,
+) -> JsonPlaylist {
+    let mut copy = playlist.clone();
+
+    copy.channel = channel.to_string();
+    copy.date = date.to_string();
+
+    if from_storage != to_storage {
+        for item in &mut copy.program {
+            if let Ok(relative) = Path::new(&item.source).strip_prefix(from_storage) {
+                item.source = to_storage.join(relative).to_string_lossy().to_string();
+            }
+        }
+    }
+
+    copy
+}
+
+/// Change on the items of a playlist.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ItemOperation {
+    /// Move the start of all items from index `from` on, positive seconds insert a gap
+    /// which is filled with filler, negative seconds cut the items.
+    Shift {
+        seconds: f64,
+        #[serde(default)]
+        from: usize,
+    },
+    /// Replace a source in all items.
+    Replace {
+        source: String,
+        with: String,
+        /// Length of the new source, probed before the playlist is locked.
+        #[serde(skip)]
+        duration: Option<f64>,
+    },
+    /// Delete all items from a category.
+    Delete { category: String },
+    /// Insert an item before index, an index after the last item appends it.
+    Insert { index: usize, item: Box<Media> },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemsResult {
+    pub date: String,
+    /// Changed items per operation.
+    pub changes: Vec<usize>,
+    pub items: usize,
+    pub length: f64,
+}
+
+fn shift_items(program: &mut Vec<Media>, seconds: f64, from: usize) -> Result<usize, ServiceError> {
+    if from > program.len() {
+        return Err(ServiceError::BadRequest(format!(
+            "Index {from} is out of range"
+        )));
+    }
+
+    if seconds > 0.0 {
+        // source less item, the player fills it with filler
+        let mut gap = Media::new(0, "", false);
+        gap.title = None;
+        gap.duration = seconds;
+        gap.out = seconds;
+        gap.category = "filler".to_string();
+
+        program.insert(from, gap);
+
+        return Ok(1);
+    }
+
+    let mut cut = -seconds;
+    let mut changed = 0;
+
+    while cut > 0.0 && from < program.len() {
+        let item = &mut program[from];
+        let length = item.out - item.seek;
+
+        if length <= cut {
+            program.remove(from);
+            cut -= length;
+        } else {
+            item.seek += cut;
+            cut = 0.0;
+        }
+
+        changed += 1;
+    }
+
+    Ok(changed)
+}
+
+fn replace_source(program: &mut [Media], source: &str, with: &str, duration: Option<f64>) -> usize {
+    let mut changed = 0;
+
+    for item in program.iter_mut().filter(|i| i.source == source) {
+        let whole = item.seek == 0.0 && item.out == item.duration;
+
+        item.source = with.to_string();
+
+        if let Some(duration) = duration {
+            // the new clip plays complete, when the old one played complete
+            if whole || item.out > duration {
+                item.seek = item.seek.min(duration);
+                item.out = duration;
+            }
+
+            item.duration = duration;
+        }
+
+        changed += 1;
+    }
+
+    changed
+}
+
+/// Normalize the new sources into the storage and probe them, before the playlist is locked.
+async fn prepare_operations(
+    config: &PlayoutConfig,
+    operations: &[ItemOperation],
+) -> Result<Vec<ItemOperation>, ServiceError> {
+    let mut prepared = operations.to_vec();
+
+    for operation in &mut prepared {
+        if let ItemOperation::Replace { with, duration, .. } = operation {
+            if !is_remote(with) {
+                let (path, _, _) = norm_abs_path(&config.channel.storage, with)?;
+                *with = path.to_string_lossy().to_string();
+            }
+
+            let source = with.clone();
+
+            *duration = task::spawn_blocking(move || {
+                Path::new(&source)
+                    .is_file()
+                    .then(|| Media::new(0, &source, true).duration)
+            })
+            .await?;
+        }
+    }
+
+    Ok(prepared)
+}
+
+/// Apply the operations in the given order.
+pub fn apply_operations(
+    playlist: &mut JsonPlaylist,
+    operations: &[ItemOperation],
+) -> Result<Vec<usize>, ServiceError> {
+    let mut changes = vec![];
+
+    for operation in operations {
+        let program = &mut playlist.program;
+
+        let changed = match operation {
+            ItemOperation::Shift { seconds, from } => shift_items(program, *seconds, *from)?,
+            ItemOperation::Replace {
+                source,
+                with,
+                duration,
+            } => replace_source(program, source, with, *duration),
+            ItemOperation::Delete { category } => {
+                let count = program.len();
+                program.retain(|i| &i.category != category);
+
+                count - program.len()
+            }
+            ItemOperation::Insert { index, item } => {
+                if *index > program.len() {
+                    return Err(ServiceError::BadRequest(format!(
+                        "Index {index} is out of range"
+                    )));
+                }
+
+                program.insert(*index, item.as_ref().clone());
+
+                1
+            }
+        };
+
+        changes.push(changed);
+    }
+
+    Ok(changes)
+}
+
+/// Edit the items of a stored playlist, the previous playlist is saved as version.
+pub async fn edit_items(
+    pool: &Pool<Sqlite>,
+    config: &PlayoutConfig,
+    date: &str,
+    operations: &[ItemOperation],
+    user_id: i32,
+) -> Result<ItemsResult, ServiceError> {
+    let operations = prepare_operations(config, operations).await?;
+    let _lock = EDIT_LOCK.lock().await;
+    let previous = read_playlist(config, date.to_string()).await?;
+    let mut playlist = previous.clone();
+    let changes = apply_operations(&mut playlist, &operations)?;
+    let result = ItemsResult {
+        date: date.to_string(),
+        changes,
+        items: playlist.program.len(),
+        length: playlist.program.iter().map(|i| i.out - i.seek).sum(),
+    };
+
+    if result.changes.iter().any(|c| *c > 0) {
+        write_playlist(config, playlist).await?;
+        save_version(pool, config, &previous, user_id).await?;
+    }
+
+    Ok(result)
+}
+
+/// Repair of a gap, when the playlist is shorter than the day.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GapRepair {
+    /// Make the last filler item longer, without filler item a new one is appended.
+    Stretch,
+    /// Append a filler item.
+    #[default]
+    Insert,
+    None,
+}
+
+/// Repair of an overlap, when the playlist is longer than the day.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlapRepair {
+    /// Cut the tail at the end of the day, items which start after the end are removed.
+    #[default]
+    Trim,
+    None,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct RepairParams {
+    #[serde(default)]
+    pub gap: GapRepair,
+    #[serde(default)]
+    pub overlap: OverlapRepair,
+    /// Save the repaired playlist, otherwise it is only returned for confirmation.
+    #[serde(default)]
+    pub save: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RepairResult {
+    pub date: String,
+    /// Day length from the config.
+    pub target: f64,
+    pub length: f64,
+    /// Difference to the day length, a gap is negative.
+    pub delta: f64,
+    pub repaired_length: f64,
+    pub changes: Vec<String>,
+    pub diff: Vec<ItemDiff>,
+    pub playlist: JsonPlaylist,
+    pub saved: bool,
+}
+
+fn program_length(program: &[Media]) -> f64 {
+    program.iter().map(|i| i.out - i.seek).sum()
+}
+
+/// Fill a gap or cut an overlap, so that the items fit into `target` seconds.
+/// Differences up to `tolerance` stay as they are. Returns the changes.
+pub fn repair_items(
+    program: &mut Vec<Media>,
+    target: f64,
+    tolerance: f64,
+    gap: GapRepair,
+    overlap: OverlapRepair,
+) -> Vec<String> {
+    let delta = program_length(program) - target;
+    let mut changes = vec![];
+
+    if delta < -tolerance {
+        let missing = -delta;
+        let filler = program
+            .iter()
+            .rposition(|i| i.category == "filler" || i.source.is_empty());
+
+        match (gap, filler) {
+            (GapRepair::Stretch, Some(index)) => {
+                let item = &mut program[index];
+
+                // clips loop, when out is longer than the clip
+                item.out += missing;
+
+                if item.source.is_empty() {
+                    item.duration = item.out;
+                }
+
+                changes.push(format!(
+                    "Stretch filler item {index} by {missing:.3} seconds"
+                ));
+            }
+            (GapRepair::Stretch | GapRepair::Insert, _) => {
+                // source less item, the player fills it with filler
+                let mut item = Media::new(0, "", false);
+                item.title = None;
+                item.duration = missing;
+                item.out = missing;
+                item.category = "filler".to_string();
+
+                program.push(item);
+                changes.push(format!(
+                    "Insert filler item with {missing:.3} seconds at the end"
+                ));
+            }
+            (GapRepair::None, _) => {}
+        }
+    } else if delta > tolerance && overlap == OverlapRepair::Trim {
+        let mut cut = delta;
+
+        while cut > 0.0 && !program.is_empty() {
+            let index = program.len() - 1;
+            let item = &mut program[index];
+            let length = item.out - item.seek;
+
+            if length <= cut {
+                program.pop();
+                changes.push(format!(
+                    "Remove item {index}, it starts after the end of the day"
+                ));
+                cut -= length;
+            } else {
+                item.out -= cut;
+                changes.push(format!("Trim item {index} by {cut:.3} seconds"));
+                cut = 0.0;
+            }
+        }
+    }
+
+    changes
+}
+
+/// Check the length of a stored playlist against the day length and repair it.
+/// The repaired playlist is saved only with `save`, the previous playlist is saved as version.
+pub async fn repair_playlist(
+    pool: &Pool<Sqlite>,
+    config: &PlayoutConfig,
+    date: &str,
+    params: &RepairParams,
+    user_id: i32,
+) -> Result<RepairResult, ServiceError> {
+    if config.playlist.infinit {
+        return Err(ServiceError::BadRequest(
+            "Infinit playlists have no day length".to_string(),
+        ));
+    }
+
+    let _lock = EDIT_LOCK.lock().await;
+    let previous = read_playlist(config, date.to_string()).await?;
+    let mut playlist = previous.clone();
+    let target = config.playlist.length_sec.unwrap_or(86400.0);
+    let length = program_length(&previous.program);
+    let changes = repair_items(
+        &mut playlist.program,
+        target,
+        1.0 / config.processing.fps,
+        params.gap,
+        params.overlap,
+    );
+    let saved = params.save && !changes.is_empty();
+
+    if saved {
+        write_playlist(config, playlist.clone()).await?;
+        save_version(pool, config, &previous, user_id).await?;
+    }
+
+    Ok(RepairResult {
+        date: date.to_string(),
+        target,
+        length,
+        delta: length - target,
+        repaired_length: program_length(&playlist.program),
+        changes,
+        diff: diff_playlists(&previous.program, &playlist.program),
+        playlist,
+        saved,
+    })
+}
+
+/// Store a playlist as new version, before it gets overwritten or deleted.
+pub async fn save_version(
+    pool: &Pool<Sqlite>,
+    config: &PlayoutConfig,
+    playlist: &JsonPlaylist,
+    user_id: i32,
+) -> Result<(), ServiceError> {
+    let id = config.general.channel_id;
+    let data = serde_json::to_string(playlist)?;
+    let timestamp = time_now().format("%Y-%m-%d %H:%M:%S").to_string();
+
+    handles::insert_playlist_version(pool, id, &playlist.date, user_id, &data, &timestamp).await?;
+    handles::delete_old_playlist_versions(pool, id, &playlist.date, MAX_VERSIONS).await?;
+
+    Ok(())
+}
+
+pub async fn read_version(
+    pool: &Pool<Sqlite>,
+    config: &PlayoutConfig,
+    version: i32,
+) -> Result<JsonPlaylist, ServiceError> {
+    let v = handles::select_playlist_version(pool, config.general.channel_id, version)
+        .await
+        .map_err(|_| ServiceError::BadRequest(format!("Playlist version {version} not found!")))?;
+
+    Ok(serde_json::from_str(&v.playlist)?)
+}
+
+/// Add the removed and added items between two equal items to the diff list.
+fn push_changes(
+    old: &[Media],
+    new: &[Media],
+    removed: &mut Vec<usize>,
+    added: &mut Vec<usize>,
+    diffs: &mut Vec<ItemDiff>,
+) {
+    let pairs = removed.len().min(added.len());
+
+    for (k, o) in removed.iter().enumerate() {
+        let a = added.get(k).filter(|_| k < pairs);
+
+        diffs.push(ItemDiff {
+            kind: if a.is_some() {
+                DiffKind::Changed
+            } else {
+                DiffKind::Removed
+            },
+            old_index: Some(*o),
+            new_index: a.copied(),
+            old: Some(old[*o].clone()),
+            new: a.map(|a| new[*a].clone()),
+        });
+    }
+
+    for a in added.iter().skip(pairs) {
+        diffs.push(ItemDiff {
+            kind: DiffKind::Added,
+            old_index: None,
+            new_index: Some(*a),
+            old: None,
+            new: Some(new[*a].clone()),
+        });
+    }
+
+    removed.clear();
+    added.clear();
+}
+
+/// Compare two playlists item by item.
+///
+/// Items are matched by the longest common subsequence,
+/// removed and added items at the same position count as changed.
+pub fn diff_playlists(old: &[Media], new: &[Media]) -> Vec<ItemDiff> {
+    let (n, m) = (old.len(), new.len());
+    let mut lcs = vec![vec![0_usize; m + 1]; n + 1];
+
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diffs = vec![];
+    let mut removed = vec![];
+    let mut added = vec![];
+    let (mut i, mut j) = (0, 0);
+
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            push_changes(old, new, &mut removed, &mut added, &mut diffs);
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            added.push(j);
+            j += 1;
+        } else {
+            removed.push(i);
+            i += 1;
+        }
+    }
+
+    push_changes(old, new, &mut removed, &mut added, &mut diffs);
+
+    diffs
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn playlist_diff_items() {
+        let media = |src: &str| Media::new(0, src, false);
+        let old = vec![
+            media("a.mp4"),
+            media("b.mp4"),
+            media("c.mp4"),
+            media("d.mp4"),
+        ];
+        let new = vec![
+            media("a.mp4"),
+            media("x.mp4"),
+            media("c.mp4"),
+            media("e.mp4"),
+            media("f.mp4"),
+        ];
+
+        let diff = diff_playlists(&old, &new);
+
+        assert_eq!(diff.len(), 3);
+        assert_eq!(diff[0].kind, DiffKind::Changed);
+        assert_eq!((diff[0].old_index, diff[0].new_index), (Some(1), Some(1)));
+        assert_eq!(diff[1].kind, DiffKind::Changed);
+        assert_eq!((diff[1].old_index, diff[1].new_index), (Some(3), Some(3)));
+        assert_eq!(diff[2].kind, DiffKind::Added);
+        assert_eq!(diff[2].new.as_ref().unwrap().source, "f.mp4");
+
+        let diff = diff_playlists(&old, &old[1..]);
+
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[0].kind, DiffKind::Removed);
+        assert_eq!(diff[0].old_index, Some(0));
+        assert!(diff_playlists(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn playlist_item_operations() {
+        let media = |src: &str, category: &str, length: f64| {
+            let mut item = Media::new(0, src, false);
+            item.category = category.to_string();
+            item.duration = length;
+            item.out = length;
+            item
+        };
+        let mut playlist = JsonPlaylist::new("2024-10-27".to_string(), 0.0);
+        playlist.program = vec![
+            media("a.mp4", "", 60.0),
+            media("spot.mp4", "advertisement", 20.0),
+            media("b.mp4", "", 60.0),
+            media("spot.mp4", "advertisement", 20.0),
+            media("a.mp4", "", 60.0),
+        ];
+
+        let operations: Vec<ItemOperation> = serde_json::from_value(serde_json::json!([
+        {"op": "delete", "category": "advertisement"},
+        {"op": "replace", "source": "a.mp4", "with": "c.mp4"},
+        {"op": "insert", "index": 1, "item": {"in": 0, "out": 30, "duration": 30, "source": "promo.mp4"}},
+        {"op": "shift", "seconds": -70},
+        {"op": "shift", "seconds": 15, "from": 1}
+    ]))
+    .unwrap();
+
+        let changes = apply_operations(&mut playlist, &operations).unwrap();
+        let sources: Vec<&str> = playlist.program.iter().map(|i| i.source.as_str()).collect();
+
+        assert_eq!(changes, vec![2, 2, 1, 2, 1]);
+        // c.mp4 removed, promo cut by 10 seconds, gap for filler inserted
+        assert_eq!(sources, vec!["promo.mp4", "", "b.mp4", "c.mp4"]);
+        assert_eq!(playlist.program[0].seek, 10.0);
+        assert_eq!(playlist.program[1].out, 15.0);
+
+        let insert: Vec<ItemOperation> = serde_json::from_value(serde_json::json!([
+        {"op": "insert", "index": 9, "item": {"in": 0, "out": 30, "duration": 30, "source": "x.mp4"}}
+    ]))
+    .unwrap();
+
+        assert!(apply_operations(&mut playlist, &insert).is_err());
+    }
+
+    #[test]
+    fn playlist_length_repair() {
+        let media = |src: &str, category: &str, length: f64| {
+            let mut item = Media::new(0, src, false);
+            item.category = category.to_string();
+            item.duration = length;
+            item.out = length;
+            item
+        };
+        let program = vec![
+            media("a.mp4", "", 1000.0),
+            media("loop.mp4", "filler", 300.0),
+            media("b.mp4", "", 1000.0),
+        ];
+
+        // gap of 1300 seconds
+        let mut items = program.clone();
+        let changes = repair_items(
+            &mut items,
+            3600.0,
+            0.04,
+            GapRepair::Stretch,
+            OverlapRepair::Trim,
+        );
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(items[1].out, 1600.0);
+        assert_eq!(items[1].duration, 300.0);
+
+        let mut items = program.clone();
+        repair_items(
+            &mut items,
+            3600.0,
+            0.04,
+            GapRepair::Insert,
+            OverlapRepair::Trim,
+        );
+
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[3].source, "");
+        assert_eq!(items[3].out, 1300.0);
+
+        // overlap of 1200 seconds, the last item is removed and the filler is cut
+        let mut items = program.clone();
+        let changes = repair_items(
+            &mut items,
+            1100.0,
+            0.04,
+            GapRepair::Insert,
+            OverlapRepair::Trim,
+        );
+
+        assert_eq!(changes.len(), 2);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[1].out, 100.0);
+
+        // within the tolerance and without repair nothing changes
+        let mut items = program.clone();
+
+        assert!(repair_items(
+            &mut items,
+            2300.02,
+            0.04,
+            GapRepair::Insert,
+            OverlapRepair::Trim
+        )
+        .is_empty());
+        assert!(repair_items(
+            &mut items,
+            5000.0,
+            0.04,
+            GapRepair::None,
+            OverlapRepair::None
+        )
+        .is_empty());
+        assert!(
+            repair_items(&mut items, 50.0, 0.04, GapRepair::None, OverlapRepair::None).is_empty()
+        );
+        assert_eq!(items, program);
+    }
+
+    #[test]
+    fn playlist_copy_paths() {
+        let mut playlist = JsonPlaylist::new("2024-10-27".to_string(), 0.0);
+        playlist.program = vec![
+            Media::new(0, "/var/lib/ffplayout/tv1/clips/a.mp4", false),
+            Media::new(1, "https://example.org/live.m3u8", false),
+            Media::new(2, "/opt/shared/b.mp4", false),
+        ];
+
+        let copy = copy_playlist(
+            &playlist,
+            "Channel 2",
+            "2024-10-28",
+            Path::new("/var/lib/ffplayout/tv1"),
+            Path::new("/var/lib/ffplayout/tv2"),
+        );
+
+        assert_eq!(copy.channel, "Channel 2");
+        assert_eq!(copy.date, "2024-10-28");
+        assert_eq!(copy.program[0].source, "/var/lib/ffplayout/tv2/clips/a.mp4");
+        assert_eq!(copy.program[1].source, "https://example.org/live.m3u8");
+        assert_eq!(copy.program[2].source, "/opt/shared/b.mp4");
+        assert_eq!(
+            playlist.program[0].source,
+            "/var/lib/ffplayout/tv1/clips/a.mp4"
+        );
+    }
+}
@@ -0,0 +1,266 @@
+/*
+Pre-caching of upcoming clips from slow or remote storage.
+
+Media on NFS, SMB or other network mounts can stall in the middle of a clip, when the network
+or the server is busy. With the pre-cache, a worker copies the clips of the next `look_ahead`
+seconds from the playlist to a fast local folder. A clip plays from its copy, when the copy is
+ready, otherwise from the source.
+
+- copies, which are older than their source or have another size, are copied again
+- when the cache is larger than `size`, the least used copies are removed
+- hits and misses are counted per channel, also for the downloads from the object storage
+
+*/
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::{atomic::Ordering, LazyLock, Mutex},
+    thread::sleep,
+    time::Duration,
+};
+
+use log::*;
+use serde::Serialize;
+
+use crate::player::controller::ChannelManager;
+use crate::utils::{
+    config::PlayoutConfig,
+    logging::Target,
+    object_store::{unix_now, upcoming, with_index, CacheEntry},
+    transcode::preferred_source,
+};
+
+const CACHE_INTERVAL: Duration = Duration::from_secs(5);
+
+static STATS: LazyLock<Mutex<HashMap<i32, CacheStats>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Cache statistics of a channel, since the engine started.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct CacheStats {
+    /// Clips, which played from the cache.
+    pub hits: u64,
+    /// Clips, which played from the source, because the copy was not ready.
+    pub misses: u64,
+    pub copied: u64,
+    pub errors: u64,
+    pub last_error: Option<String>,
+    pub files: usize,
+    /// Size of the cache in bytes.
+    pub size: u64,
+    /// Limit in bytes, 0 is unlimited.
+    pub limit: u64,
+}
+
+pub fn cache_stats(id: i32) -> CacheStats {
+    STATS.lock().unwrap().get(&id).cloned().unwrap_or_default()
+}
+
+fn update_stats(id: i32, f: impl FnOnce(&mut CacheStats)) {
+    f(STATS.lock().unwrap().entry(id).or_default());
+}
+
+/// Count a played clip, `hit` when it came from the cache.
+pub fn count_hit(id: i32, hit: bool) {
+    update_stats(id, |stats| {
+        if hit {
+            stats.hits += 1;
+        } else {
+            stats.misses += 1;
+        }
+    });
+}
+
+/// Cache folder of the channel.
+pub fn cache_dir(config: &PlayoutConfig) -> PathBuf {
+    PathBuf::from(config.storage.pre_cache.path.trim()).join(config.general.channel_id.to_string())
+}
+
+/// Path of the copy: the full source path below the cache folder.
+pub fn cache_path(dir: &Path, source: &Path) -> PathBuf {
+    dir.join(
+        source
+            .components()
+            .filter(|c| matches!(c, Component::Normal(_)))
+            .collect::<PathBuf>(),
+    )
+}
+
+/// The copy exists, has the same size and is not older than the source.
+pub fn is_fresh(source: &Path, copy: &Path) -> bool {
+    let (Ok(src), Ok(dst)) = (fs::metadata(source), fs::metadata(copy)) else {
+        return false;
+    };
+
+    src.len() == dst.len() && matches!((src.modified(), dst.modified()), (Ok(s), Ok(d)) if s <= d)
+}
+
+fn is_enabled(config: &PlayoutConfig) -> bool {
+    config.storage.pre_cache.enable && !config.storage.pre_cache.path.trim().is_empty()
+}
+
+/// Clips in the storage are already local, when it is the cache of the object storage.
+fn needs_copy(config: &PlayoutConfig, dir: &Path, path: &Path) -> bool {
+    path.is_file()
+        && !path.starts_with(dir)
+        && !(config.storage.object_store.enable && path.starts_with(&config.channel.storage))
+}
+
+/// Source for the player: the copy, when it is ready in the cache.
+pub fn cached_source(config: &PlayoutConfig, source: &str) -> String {
+    let path = Path::new(source);
+    let dir = cache_dir(config);
+
+    if !is_enabled(config) || !needs_copy(config, &dir, path) {
+        return source.to_string();
+    }
+
+    let copy = cache_path(&dir, path);
+    let hit = is_fresh(path, &copy);
+
+    count_hit(config.general.channel_id, hit);
+
+    if !hit {
+        return source.to_string();
+    }
+
+    with_index(&dir, |index| {
+        if let Some(entry) = index.files.get_mut(&copy) {
+            entry.used = unix_now();
+        }
+    });
+
+    copy.to_string_lossy().to_string()
+}
+
+/// Copy a clip into the cache, returns `false` when the copy is there already.
+fn copy_clip(dir: &Path, source: &Path) -> Result<bool, String> {
+    let copy = cache_path(dir, source);
+
+    if is_fresh(source, &copy) {
+        return Ok(false);
+    }
+
+    let part = PathBuf::from(format!("{}.part", copy.display()));
+    let result = copy
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::copy(source, &part))
+        .and_then(|size| fs::rename(&part, &copy).map(|_| size));
+
+    match result {
+        Ok(size) => {
+            with_index(dir, |index| {
+                index.files.insert(
+                    copy,
+                    CacheEntry {
+                        size,
+                        used: unix_now(),
+                    },
+                )
+            });
+
+            Ok(true)
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&part);
+
+            Err(format!("Copy of {} to cache failed: {e}", source.display()))
+        }
+    }
+}
+
+/// Remove the least used copies, until the cache fits into its size.
+fn evict(config: &PlayoutConfig, dir: &Path, keep: &HashSet<PathBuf>) {
+    let limit = config.storage.pre_cache.size * 1024 * 1024;
+    let id = config.general.channel_id;
+
+    let (files, size) = with_index(dir, |index| {
+        if limit > 0 {
+            for path in index.expired(limit, keep) {
+                match fs::remove_file(&path) {
+                    Ok(_) => {
+                        debug!(target: Target::file_mail(), channel = id; "Remove <b><magenta>{}</></b> from pre-cache", path.display());
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(e) => {
+                        error!(target: Target::file_mail(), channel = id; "Remove <b><magenta>{}</></b> from pre-cache failed: {e}", path.display());
+                        continue;
+                    }
+                }
+
+                index.files.remove(&path);
+            }
+        }
+
+        (index.files.len(), index.size())
+    });
+
+    update_stats(id, |stats| {
+        stats.files = files;
+        stats.size = size;
+        stats.limit = limit;
+    });
+}
+
+/// Copy the upcoming clips in background, until the channel stops.
+pub fn pre_cache_worker(manager: ChannelManager) {
+    let is_terminated = manager.is_terminated.clone();
+
+    while !is_terminated.load(Ordering::SeqCst) {
+        let config = manager.config.lock().unwrap().clone();
+        let id = config.general.channel_id;
+
+        if !is_enabled(&config) {
+            break;
+        }
+
+        let dir = cache_dir(&config);
+        let mut keep = HashSet::new();
+
+        if let Err(e) = fs::create_dir_all(&dir) {
+            error!(target: Target::file_mail(), channel = id; "Unable to create pre-cache folder: {e}");
+            sleep(CACHE_INTERVAL);
+            continue;
+        }
+
+        for source in upcoming(&manager, config.storage.pre_cache.look_ahead) {
+            // the player takes the proxy, when there is one
+            let source = preferred_source(&config, &source).unwrap_or(source);
+            let path = PathBuf::from(&source);
+
+            if !needs_copy(&config, &dir, &path) {
+                continue;
+            }
+
+            match copy_clip(&dir, &path) {
+                Ok(true) => {
+                    update_stats(id, |stats| stats.copied += 1);
+
+                    debug!(target: Target::file_mail(), channel = id; "Pre-cached <b><magenta>{source}</></b>");
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    error!(target: Target::file_mail(), channel = id; "{e}");
+
+                    update_stats(id, |stats| {
+                        stats.errors += 1;
+                        stats.last_error = Some(e);
+                    });
+                }
+            }
+
+            keep.insert(cache_path(&dir, &path));
+
+            if is_terminated.load(Ordering::SeqCst) {
+                return;
+            }
+        }
+
+        evict(&config, &dir, &keep);
+
+        sleep(CACHE_INTERVAL);
+    }
+}
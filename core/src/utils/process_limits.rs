@@ -0,0 +1,238 @@
+/*
+Resource limits for the ffmpeg processes of a channel.
+
+On a shared host one misbehaving channel should not starve the others. Every channel can get:
+
+- CPU affinity: the processes run only on the given cores, like `0-3,8`
+- nice level: from -20 to 19, a higher level gets less CPU time; a level under 0 needs root or `CAP_SYS_NICE`
+- memory limit: for all processes of the channel together, with `memory.max` of a cgroup v2
+
+Affinity, nice level and cgroup are set in the new process, before ffmpeg starts, so all its threads get them.
+The cgroup of the channel is `channel-<id>` in `--cgroup-root`, the engine needs write access to it.
+When the cgroup can not be used, the processes run without memory limit. A failed limit never stops the channel.
+
+*/
+
+use std::{
+    collections::HashSet,
+    ffi::CString,
+    fs, io,
+    os::unix::{ffi::OsStrExt, process::CommandExt},
+    path::PathBuf,
+    process::Command,
+    sync::{LazyLock, Mutex},
+};
+
+use log::*;
+use nix::{
+    sched::{sched_setaffinity, CpuSet},
+    unistd::Pid,
+};
+
+use crate::utils::{
+    errors::ServiceError,
+    gpu::{channel_gpu, GpuSetting},
+    logging::Target,
+};
+use crate::ARGS;
+
+/// Channels, which got already a warning about their cgroup.
+static CGROUP_WARNED: LazyLock<Mutex<HashSet<i32>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProcessLimits {
+    pub channel: i32,
+    pub cpus: Vec<usize>,
+    pub nice: i32,
+    /// Memory in bytes, 0 for no limit.
+    pub memory: u64,
+    pub gpu: GpuSetting,
+}
+
+impl ProcessLimits {
+    /// Limits from the channel settings, invalid values are ignored.
+    pub fn new(channel: i32, affinity: &str, nice: i32, memory: i64, gpu: &str) -> Self {
+        Self {
+            channel,
+            cpus: parse_cpus(affinity).unwrap_or_default(),
+            nice: nice.clamp(-20, 19),
+            memory: memory.max(0) as u64,
+            gpu: gpu.parse().unwrap_or_default(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cpus.is_empty() && self.nice == 0 && self.memory == 0
+    }
+}
+
+/// Parse a CPU list, like `0-3,8`. An empty list means all CPUs.
+pub fn parse_cpus(list: &str) -> Result<Vec<usize>, ServiceError> {
+    let mut cpus = vec![];
+
+    for part in list.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let (first, last) = part.split_once('-').unwrap_or((part, part));
+
+        match (first.trim().parse::<usize>(), last.trim().parse::<usize>()) {
+            (Ok(first), Ok(last)) if first <= last && last < CpuSet::count() => {
+                cpus.extend(first..=last);
+            }
+            _ => {
+                return Err(ServiceError::BadRequest(format!(
+                    "Invalid CPU list: {list}"
+                )))
+            }
+        }
+    }
+
+    cpus.sort_unstable();
+    cpus.dedup();
+
+    Ok(cpus)
+}
+
+/// Check the limits from the channel settings.
+pub fn validate_limits(affinity: &str, nice: i32, memory: i64) -> Result<(), ServiceError> {
+    parse_cpus(affinity)?;
+
+    if !(-20..=19).contains(&nice) {
+        return Err(ServiceError::BadRequest(
+            "Nice level must be between -20 and 19".to_string(),
+        ));
+    }
+
+    if memory < 0 {
+        return Err(ServiceError::BadRequest(
+            "Memory limit can not be negative".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn cgroup_path(channel: i32) -> PathBuf {
+    ARGS.cgroup_root
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("/sys/fs/cgroup/ffplayout"))
+        .join(format!("channel-{channel}"))
+}
+
+/// Create the cgroup of the channel with its memory limit, returns the path to `cgroup.procs`.
+fn prepare_cgroup(channel: i32, memory: u64) -> io::Result<CString> {
+    let path = cgroup_path(channel);
+
+    if let Some(root) = path.parent() {
+        fs::create_dir_all(root)?;
+
+        // can be enabled already by the admin, without write access for the engine
+        let _ = fs::write(root.join("cgroup.subtree_control"), "+memory");
+    }
+
+    fs::create_dir_all(&path)?;
+    fs::write(path.join("memory.max"), memory.to_string())?;
+
+    CString::new(path.join("cgroup.procs").as_os_str().as_bytes()).map_err(io::Error::other)
+}
+
+pub trait LimitProcess {
+    /// Run the process with the resource limits of the channel.
+    fn limits(&mut self, limits: &ProcessLimits) -> &mut Self;
+}
+
+impl LimitProcess for Command {
+    fn limits(&mut self, limits: &ProcessLimits) -> &mut Self {
+        if let Some(gpu) = channel_gpu(limits.channel) {
+            // same order of the GPUs like in nvidia-smi
+            self.env("CUDA_DEVICE_ORDER", "PCI_BUS_ID")
+                .env("CUDA_VISIBLE_DEVICES", gpu.to_string());
+        }
+
+        if limits.is_empty() {
+            return self;
+        }
+
+        let channel = limits.channel;
+        let nice = limits.nice;
+        let mut cpus = None;
+        let mut procs = None;
+
+        if !limits.cpus.is_empty() {
+            let mut set = CpuSet::new();
+
+            for cpu in &limits.cpus {
+                let _ = set.set(*cpu);
+            }
+
+            cpus = Some(set);
+        }
+
+        if limits.memory > 0 {
+            match prepare_cgroup(channel, limits.memory) {
+                Ok(path) => {
+                    CGROUP_WARNED.lock().unwrap().remove(&channel);
+                    procs = Some(path);
+                }
+                Err(e) => {
+                    if CGROUP_WARNED.lock().unwrap().insert(channel) {
+                        warn!(target: Target::file_mail(), channel = channel;
+                            "No memory limit, cgroup <b><magenta>{}</></b> is not usable: {e}",
+                            cgroup_path(channel).display()
+                        );
+                    }
+                }
+            }
+        }
+
+        // Runs in the new process, before ffmpeg starts: only system calls, no allocations.
+        unsafe {
+            self.pre_exec(move || {
+                if let Some(procs) = &procs {
+                    let fd = libc::open(procs.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+
+                    if fd >= 0 {
+                        libc::write(fd, b"0".as_ptr().cast(), 1);
+                        libc::close(fd);
+                    }
+                }
+
+                if let Some(set) = &cpus {
+                    let _ = sched_setaffinity(Pid::from_raw(0), set);
+                }
+
+                if nice != 0 {
+                    libc::setpriority(libc::PRIO_PROCESS, 0, nice);
+                }
+
+                Ok(())
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::gpu::GpuSetting;
+
+    #[test]
+    fn channel_process_limits() {
+        assert_eq!(parse_cpus("").unwrap(), Vec::<usize>::new());
+        assert_eq!(parse_cpus("0-3, 8,2").unwrap(), vec![0, 1, 2, 3, 8]);
+        assert!(parse_cpus("3-1").is_err());
+        assert!(parse_cpus("a").is_err());
+        assert!(parse_cpus("0-99999").is_err());
+
+        assert!(validate_limits("0-1", -20, 0).is_ok());
+        assert!(validate_limits("", 20, 0).is_err());
+        assert!(validate_limits("", 0, -1).is_err());
+
+        let limits = ProcessLimits::new(3, "x", 30, -5, "auto");
+
+        assert_eq!(limits.cpus, Vec::<usize>::new());
+        assert_eq!(limits.nice, 19);
+        assert_eq!(limits.memory, 0);
+        assert_eq!(limits.gpu, GpuSetting::Auto);
+        assert!(!limits.is_empty());
+        assert!(ProcessLimits::new(3, "", 0, 0, "").is_empty());
+    }
+}
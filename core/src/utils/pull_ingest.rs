@@ -0,0 +1,622 @@
+/*
+Pull ingest from FTP and SFTP servers.
+
+Traffic systems often deliver schedules and spots to a FTP server. Every `interval` minutes
+the media folder and the playlist folder on the server are checked. New media files are downloaded
+into the `target` folder in the storage, after that new playlists are imported:
+
+- `*.json`: playlist in the ffplayout format, with the date from the file
+- `*.m3u`, `*.m3u8`, `*.txt`, `*.csv`: date from the file name, like `2024-10-27.csv`
+
+Relative sources in playlists are in the `target` folder. Imported playlists replace the existing ones.
+
+Transfers run with `curl`, the login is handed over on stdin, so it is not visible in the process list.
+Imported files are remembered in `.pull_state.json` in the storage, until they are removed from the server.
+
+*/
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    env, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{Arc, LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::NaiveDate;
+use log::*;
+use regex::Regex;
+use serde::Serialize;
+use tokio::{task, time::interval};
+
+use crate::player::{
+    controller::{ChannelController, ChannelManager},
+    output::s3::uri_encode,
+    utils::{
+        file_extension,
+        import::{read_items, write_items},
+        json_reader, MediaProbe,
+    },
+};
+use crate::utils::{
+    config::{PlayoutConfig, PullIngest},
+    errors::ServiceError,
+    files::norm_abs_path,
+    logging::{send_mail, Target},
+    notifier::send_notification,
+    time_machine::time_now,
+    watch_folder::IngestResult,
+};
+
+const STATE_FILE: &str = ".pull_state.json";
+const PLAYLIST_EXTENSIONS: [&str; 5] = ["json", "m3u", "m3u8", "txt", "csv"];
+/// Number of results in the status.
+const HISTORY: usize = 50;
+
+static DATE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d{4}-\d{2}-\d{2}").unwrap());
+
+/// Pull state from one channel, for the status API.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PullStatus {
+    pub running: bool,
+    pub last_run: Option<String>,
+    /// Error from the last run, like a failed login.
+    pub error: Option<String>,
+    pub imported: u64,
+    pub failed: u64,
+    /// Last results, newest first.
+    pub history: VecDeque<IngestResult>,
+}
+
+impl PullStatus {
+    fn add(&mut self, results: &[IngestResult]) {
+        for result in results {
+            if result.success {
+                self.imported += 1;
+            } else {
+                self.failed += 1;
+            }
+
+            self.history.push_front(result.clone());
+        }
+
+        self.history.truncate(HISTORY);
+    }
+}
+
+/// Url of a file or folder below the base url.
+pub fn remote_url(base: &str, folder: &str, name: &str) -> String {
+    let mut url = base.trim_end_matches('/').to_string();
+
+    for part in [folder, name] {
+        let part = part.trim_matches('/');
+
+        if !part.is_empty() {
+            url.push('/');
+            url.push_str(&uri_encode(part));
+        }
+    }
+
+    url
+}
+
+/// Path on the SFTP server, for commands. Paths in the home folder are relative.
+pub fn sftp_path(base: &str, folder: &str, name: &str) -> String {
+    let path = base
+        .split_once("://")
+        .and_then(|(_, rest)| rest.find('/').map(|i| &rest[i..]))
+        .unwrap_or("/")
+        .trim_end_matches('/');
+    let mut path = path.strip_prefix("/~").unwrap_or(path).to_string();
+
+    for part in [folder, name] {
+        let part = part.trim_matches('/');
+
+        if !part.is_empty() {
+            path.push('/');
+            path.push_str(part);
+        }
+    }
+
+    if base.contains("/~/") || base.ends_with("/~") {
+        path.trim_start_matches('/').to_string()
+    } else {
+        path
+    }
+}
+
+/// Options for curl, which are handed over on stdin.
+pub fn curl_config(settings: &PullIngest) -> String {
+    let quote = |v: &str| v.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut config = String::new();
+
+    if !settings.username.is_empty() {
+        config.push_str(&format!(
+            "user = \"{}:{}\"\n",
+            quote(&settings.username),
+            quote(&settings.password)
+        ));
+    }
+
+    if !settings.key_file.is_empty() {
+        config.push_str(&format!("key = \"{}\"\n", quote(&settings.key_file)));
+    }
+
+    config
+}
+
+fn curl(settings: &PullIngest, args: &[&str]) -> Result<Vec<u8>, String> {
+    let mut child = Command::new("curl")
+        .args([
+            "--silent",
+            "--show-error",
+            "--fail",
+            "--connect-timeout",
+            "30",
+            "--config",
+            "-",
+        ])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("curl: {e}"))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(curl_config(settings).as_bytes())
+            .map_err(|e| format!("curl: {e}"))?;
+    }
+
+    let output = child.wait_with_output().map_err(|e| format!("curl: {e}"))?;
+
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// File names in a folder on the server.
+fn list_remote(settings: &PullIngest, folder: &str) -> Result<Vec<String>, String> {
+    let url = format!("{}/", remote_url(&settings.url, folder, ""));
+    let output = curl(settings, &["--list-only", &url])?;
+
+    Ok(String::from_utf8_lossy(&output)
+        .lines()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty() && l != "." && l != "..")
+        .collect())
+}
+
+fn download(settings: &PullIngest, url: &str, target: &Path) -> Result<(), String> {
+    let part = PathBuf::from(format!("{}.part", target.display()));
+
+    curl(settings, &["--output", &part.to_string_lossy(), url])
+        .and_then(|_| fs::rename(&part, target).map_err(|e| e.to_string()))
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&part);
+        })
+}
+
+fn delete_remote(settings: &PullIngest, folder: &str, name: &str) -> Result<(), String> {
+    let url = format!("{}/", remote_url(&settings.url, folder, ""));
+    let command = if settings.url.starts_with("sftp://") {
+        format!(
+            "rm \"{}\"",
+            sftp_path(&settings.url, folder, name).replace('"', "\\\"")
+        )
+    } else {
+        format!("DELE {name}")
+    };
+
+    curl(
+        settings,
+        &[
+            "--list-only",
+            "--quote",
+            &command,
+            "--output",
+            "/dev/null",
+            &url,
+        ],
+    )
+    .map(|_| ())
+}
+
+/// Playlist date from a file name, like `schedule_2024-10-27.csv`.
+pub fn date_from_name(name: &str) -> Option<String> {
+    DATE.find_iter(name)
+        .map(|m| m.as_str())
+        .find(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").is_ok())
+        .map(str::to_string)
+}
+
+fn load_state(path: &Path) -> HashSet<String> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(path: &Path, state: &HashSet<String>) -> Result<(), String> {
+    let json = serde_json::to_string(state).map_err(|e| e.to_string())?;
+
+    fs::write(path, json).map_err(|e| e.to_string())
+}
+
+fn pull_media(
+    settings: &PullIngest,
+    url: &str,
+    name: &str,
+    target_dir: &Path,
+) -> Result<String, String> {
+    let target = target_dir.join(sanitize_filename::sanitize(name));
+
+    download(settings, url, &target)?;
+
+    if let Err(e) = MediaProbe::new(&target.to_string_lossy()) {
+        let _ = fs::remove_file(&target);
+
+        return Err(format!("Probe failed: {e}"));
+    }
+
+    Ok(format!("saved to {}", target.display()))
+}
+
+fn pull_playlist(
+    manager: &ChannelManager,
+    config: &PlayoutConfig,
+    url: &str,
+    name: &str,
+    target_dir: &Path,
+) -> Result<String, String> {
+    let settings = &config.storage.pull_ingest;
+    let channel_name = manager.channel.lock().unwrap().name.clone();
+    let file = env::temp_dir().join(format!(
+        "ffplayout_pull_{}_{}",
+        config.general.channel_id,
+        sanitize_filename::sanitize(name)
+    ));
+
+    download(settings, url, &file)?;
+
+    let result = if file_extension(&file).is_some_and(|e| e.eq_ignore_ascii_case("json")) {
+        json_reader(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|playlist| {
+                NaiveDate::parse_from_str(&playlist.date, "%Y-%m-%d")
+                    .map_err(|_| format!("Invalid date: {}", playlist.date))?;
+
+                let mut program = playlist.program;
+
+                for item in &mut program {
+                    if Path::new(&item.source).is_relative() && !item.source.contains("://") {
+                        item.source = target_dir.join(&item.source).to_string_lossy().to_string();
+                    }
+                }
+
+                write_items(config, &playlist.date, Some(channel_name), program, true)
+                    .map_err(|e| e.to_string())
+            })
+    } else {
+        date_from_name(name)
+            .ok_or_else(|| "No date in file name".to_string())
+            .and_then(|date| {
+                let items = read_items(&file, target_dir).map_err(|e| e.to_string())?;
+
+                write_items(config, &date, Some(channel_name), items, true)
+                    .map_err(|e| e.to_string())
+            })
+    };
+
+    let _ = fs::remove_file(&file);
+
+    result
+}
+
+/// Pull and import the new files from the server once.
+pub fn pull(manager: &ChannelManager) -> Result<Vec<IngestResult>, String> {
+    let config = manager.config.lock().unwrap().clone();
+    let id = config.general.channel_id;
+    let settings = &config.storage.pull_ingest;
+    let (target_dir, _, _) =
+        norm_abs_path(&config.channel.storage, &settings.target).map_err(|e| e.to_string())?;
+    let state_file = config.channel.storage.join(STATE_FILE);
+    let mut state = load_state(&state_file);
+    let mut results = vec![];
+
+    fs::create_dir_all(&target_dir).map_err(|e| e.to_string())?;
+
+    // media first, so the playlists find their clips
+    for (folder, is_playlist) in [
+        (&settings.media_folder, false),
+        (&settings.playlist_folder, true),
+    ] {
+        if folder.is_empty() {
+            continue;
+        }
+
+        let names = match list_remote(settings, folder) {
+            Ok(names) => names,
+            Err(e) => {
+                save_state(&state_file, &state)?;
+
+                return Err(e);
+            }
+        };
+        let folder_url = format!("{}/", remote_url(&settings.url, folder, ""));
+        let urls: HashSet<String> = names
+            .iter()
+            .map(|n| remote_url(&settings.url, folder, n))
+            .collect();
+
+        // files which are not on the server anymore can come again
+        state.retain(|u| !u.starts_with(&folder_url) || urls.contains(u));
+
+        for name in names {
+            let url = remote_url(&settings.url, folder, &name);
+            let ext = file_extension(Path::new(&name))
+                .unwrap_or_default()
+                .to_lowercase();
+            let wanted = if is_playlist {
+                PLAYLIST_EXTENSIONS.contains(&ext.as_str())
+            } else {
+                config.storage.extensions.contains(&ext)
+            };
+
+            if !wanted || state.contains(&url) {
+                continue;
+            }
+
+            let result = if is_playlist {
+                pull_playlist(manager, &config, &url, &name, &target_dir)
+            } else {
+                pull_media(settings, &url, &name, &target_dir)
+            };
+            let file = format!("{}/{name}", folder.trim_matches('/'));
+            let mut ingest = IngestResult {
+                file: file.clone(),
+                timestamp: time_now().format("%Y-%m-%d %H:%M:%S").to_string(),
+                ..Default::default()
+            };
+
+            match result {
+                Ok(message) => {
+                    info!(target: Target::file(), channel = id; "Pull <b><magenta>{file}</></b>: {message}");
+
+                    if !is_playlist {
+                        ingest.target = Some(
+                            target_dir
+                                .join(sanitize_filename::sanitize(&name))
+                                .to_string_lossy()
+                                .to_string(),
+                        );
+                    }
+
+                    ingest.success = true;
+                    ingest.message = message;
+
+                    if settings.delete {
+                        match delete_remote(settings, folder, &name) {
+                            Ok(_) => {}
+                            Err(e) => {
+                                warn!(target: Target::file(), channel = id; "Delete <b><magenta>{file}</></b> on server failed: {e}");
+                                state.insert(url);
+                            }
+                        }
+                    } else {
+                        state.insert(url);
+                    }
+                }
+                Err(e) => {
+                    error!(target: Target::file(), channel = id; "Pull <b><magenta>{file}</></b> failed: {e}");
+
+                    ingest.message = e;
+                }
+            }
+
+            results.push(ingest);
+        }
+    }
+
+    save_state(&state_file, &state)?;
+
+    Ok(results)
+}
+
+/// Report from one run.
+pub fn report_text(channel: &str, results: &[IngestResult], error: Option<&str>) -> String {
+    let imported = results.iter().filter(|r| r.success).count();
+    let mut text = format!(
+        "Pull ingest for {channel}: {imported} imported, {} failed\n",
+        results.len() - imported
+    );
+
+    if let Some(e) = error {
+        text.push_str(&format!("\nError: {e}\n"));
+    }
+
+    for result in results {
+        let state = if result.success { "ok" } else { "failed" };
+
+        text.push_str(&format!("\n[{state}] {}: {}", result.file, result.message));
+    }
+
+    text
+}
+
+async fn send_report(config: &PlayoutConfig, text: String) {
+    let id = config.general.channel_id;
+    let mut mail = config.mail.clone();
+    mail.subject = format!("{} - Pull ingest", mail.subject);
+
+    if mail.show && mail.recipient.contains('@') {
+        if let Err(e) = send_mail(&mail, text.clone()).await {
+            error!(target: Target::file(), channel = id; "Send pull report failed: {e}");
+        }
+    }
+
+    for notifier in mail.notifiers.iter().filter(|n| n.enable) {
+        if let Err(e) = send_notification(notifier, &mail.subject, &text).await {
+            error!(target: Target::file(), channel = id; "Send pull report to {:?} failed: {e}", notifier.kind);
+        }
+    }
+}
+
+/// Run the pull once, update the status and send the report.
+pub async fn run(manager: ChannelManager) -> Result<Vec<IngestResult>, ServiceError> {
+    {
+        let mut status = manager.pull_status.lock().unwrap();
+
+        if status.running {
+            return Err(ServiceError::Conflict("Pull is running already".into()));
+        }
+
+        status.running = true;
+    }
+
+    let manager_clone = manager.clone();
+    let result = task::spawn_blocking(move || pull(&manager_clone))
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r);
+    let config = manager.config.lock().unwrap().clone();
+    let channel = manager.channel.lock().unwrap().name.clone();
+    let (results, error) = match result {
+        Ok(results) => (results, None),
+        Err(e) => {
+            error!(target: Target::file(), channel = config.general.channel_id; "Pull ingest failed: {e}");
+
+            (vec![], Some(e))
+        }
+    };
+
+    {
+        let mut status = manager.pull_status.lock().unwrap();
+        status.running = false;
+        status.last_run = Some(time_now().format("%Y-%m-%d %H:%M:%S").to_string());
+        status.error.clone_from(&error);
+        status.add(&results);
+    }
+
+    if config.storage.pull_ingest.notify && (!results.is_empty() || error.is_some()) {
+        send_report(&config, report_text(&channel, &results, error.as_deref())).await;
+    }
+
+    match error {
+        Some(e) => Err(ServiceError::BadRequest(e)),
+        None => Ok(results),
+    }
+}
+
+/// Start the pulls from all channels, when their interval is over.
+pub fn pull_ingest(controllers: Arc<Mutex<ChannelController>>) {
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(30));
+        let mut last_runs: HashMap<i32, Instant> = HashMap::new();
+
+        loop {
+            interval.tick().await;
+
+            let channels = controllers.lock().unwrap().channels.clone();
+
+            for manager in channels {
+                let settings = manager.config.lock().unwrap().storage.pull_ingest.clone();
+                let id = manager.channel.lock().unwrap().id;
+                let every = Duration::from_secs(settings.interval.max(1) as u64 * 60);
+
+                if !settings.enable || settings.url.is_empty() {
+                    last_runs.remove(&id);
+                    continue;
+                }
+
+                if last_runs.get(&id).is_none_or(|t| t.elapsed() >= every)
+                    && !manager.pull_status.lock().unwrap().running
+                {
+                    last_runs.insert(id, Instant::now());
+
+                    tokio::spawn(async move {
+                        let _ = run(manager).await;
+                    });
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::config::PullIngest;
+
+    #[test]
+    fn pull_ingest_helpers() {
+        assert_eq!(
+            remote_url(
+                "sftp://traffic.example.org/export/",
+                "/play lists/",
+                "2024-10-27.csv"
+            ),
+            "sftp://traffic.example.org/export/play%20lists/2024-10-27.csv"
+        );
+        assert_eq!(
+            remote_url("ftp://traffic.example.org", "", "spot.mp4"),
+            "ftp://traffic.example.org/spot.mp4"
+        );
+
+        assert_eq!(
+            sftp_path("sftp://traffic.example.org/export", "media", "spot 1.mp4"),
+            "/export/media/spot 1.mp4"
+        );
+        assert_eq!(
+            sftp_path("sftp://traffic.example.org/~/export", "media", "spot.mp4"),
+            "export/media/spot.mp4"
+        );
+
+        assert_eq!(
+            date_from_name("schedule_2024-10-27.m3u"),
+            Some("2024-10-27".to_string())
+        );
+        assert_eq!(
+            date_from_name("2024-13-45_2024-02-29.csv"),
+            Some("2024-02-29".to_string())
+        );
+        assert_eq!(date_from_name("schedule.csv"), None);
+
+        let settings = PullIngest {
+            username: "traffic".to_string(),
+            password: r#"pa"ss\word"#.to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            curl_config(&settings),
+            "user = \"traffic:pa\\\"ss\\\\word\"\n"
+        );
+
+        let results = vec![
+            IngestResult {
+                file: "media/spot.mp4".to_string(),
+                success: true,
+                message: "saved".to_string(),
+                ..Default::default()
+            },
+            IngestResult {
+                file: "playlists/schedule.csv".to_string(),
+                message: "No date in file name".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        assert_eq!(
+            report_text("Channel 1", &results, None),
+            "Pull ingest for Channel 1: 1 imported, 1 failed\n\n\
+        [ok] media/spot.mp4: saved\n\
+        [failed] playlists/schedule.csv: No date in file name"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+/*
+Daily report mail.
+
+After midnight every channel with active mailing gets a report from the previous day,
+with the notes from the shift handover log.
+
+*/
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{NaiveDate, TimeDelta};
+use log::*;
+use sqlx::{Pool, Sqlite};
+use tokio::time::interval;
+
+use crate::db::{handles, models::HandoverNote};
+use crate::player::controller::ChannelController;
+use crate::utils::{
+    logging::{send_mail, Target},
+    time_machine::time_now,
+};
+
+/// Report text for one channel and day.
+pub fn report_text(channel: &str, date: &NaiveDate, notes: &[HandoverNote]) -> String {
+    let mut text = format!("Daily report for {channel}, {date}\n\nShift handover notes:\n");
+
+    if notes.is_empty() {
+        text.push_str("  - none -\n");
+    }
+
+    for note in notes {
+        let time = note.timestamp.split(' ').nth(1).unwrap_or(&note.timestamp);
+
+        text.push_str(&format!("\n[{time}] {}", note.username));
+
+        if !note.event.is_empty() {
+            text.push_str(&format!(" | on air: {}", note.event));
+        }
+
+        text.push_str(&format!("\n{}\n", note.note.trim()));
+    }
+
+    text
+}
+
+async fn send_reports(
+    pool: &Pool<Sqlite>,
+    controllers: &Arc<Mutex<ChannelController>>,
+    date: NaiveDate,
+) {
+    let channels: Vec<_> = controllers
+        .lock()
+        .unwrap()
+        .channels
+        .iter()
+        .map(|m| {
+            (
+                m.channel.lock().unwrap().clone(),
+                m.config.lock().unwrap().mail.clone(),
+            )
+        })
+        .collect();
+
+    for (channel, mail) in channels {
+        if !mail.show || !mail.recipient.contains('@') {
+            continue;
+        }
+
+        let notes = match handles::select_handover(
+            pool,
+            channel.id,
+            &format!("{date} 00:00:00"),
+            &format!("{date} 23:59:59"),
+        )
+        .await
+        {
+            Ok(n) => n,
+            Err(e) => {
+                error!(target: Target::file_mail(), channel = channel.id; "Read handover notes failed: {e}");
+                continue;
+            }
+        };
+
+        let mut config = mail.clone();
+        config.subject = format!("{} - Daily report {date}", mail.subject);
+
+        if let Err(e) = send_mail(&config, report_text(&channel.name, &date, &notes)).await {
+            error!(target: Target::file(), channel = channel.id; "Send daily report failed: {e}");
+        }
+    }
+}
+
+/// Send the reports from the previous day, once the date changes.
+pub fn daily_report(pool: Pool<Sqlite>, controllers: Arc<Mutex<ChannelController>>) {
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(60));
+        let mut last_date = time_now().date_naive();
+
+        loop {
+            interval.tick().await;
+
+            let today = time_now().date_naive();
+
+            if today != last_date {
+                send_reports(&pool, &controllers, today - TimeDelta::days(1)).await;
+                last_date = today;
+            }
+        }
+    });
+}
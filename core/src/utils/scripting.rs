@@ -0,0 +1,739 @@
+/*
+Lua scripts for station specific scheduling rules.
+
+Every script returns a table with hook functions. All enabled scripts from a channel are loaded
+into one Lua state, so they can keep data between the calls, like a cached API result:
+
+    return {
+        -- playlist is generated, return the changed playlist or nil
+        generate = function(playlist, ctx) return playlist end,
+        -- next clip is prepared, return the changed clip or nil
+        next = function(clip, ctx) return nil end,
+        -- gap has to be filled, return the source of the filler or nil
+        filler = function(gap, fillers, ctx) return nil end,
+    }
+
+The state is sandboxed: only the table, string, math, utf8 and coroutine libraries are there,
+plus the `ffplayout` table with `log`, `http_get`, `json_decode` and `json_encode`.
+`http_get` reaches only public http(s) servers, no loopback or private networks, so a script
+can not reach services on the server or in the local network.
+Memory is limited and every call stops after a few seconds. When a script fails,
+the engine continues without its result.
+
+Sources from the `next` and `filler` hooks have to be in the channel storage or the filler folder,
+other sources are ignored. A changed custom filter is checked like the filters from a playlist.
+
+*/
+
+use std::{
+    fmt,
+    io::Read,
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::*;
+use mlua::{
+    Function, HookTriggers, Lua, LuaOptions, LuaSerdeExt, SerializeOptions, StdLib, Table, Value,
+    VmState,
+};
+use reqwest::{blocking::Response, redirect::Policy, Url};
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::{Pool, Sqlite};
+
+use crate::db::{handles, models::Script};
+use crate::player::{
+    controller::ChannelManager,
+    filter::custom::{ffmpeg_filters, validate_filter},
+    utils::{time_in_seconds, JsonPlaylist, Media},
+};
+use crate::utils::{
+    config::PlayoutConfig, errors::ServiceError, files::norm_abs_path, logging::Target,
+};
+
+/// Memory limit from the Lua state of one channel.
+const MEMORY_LIMIT: usize = 32 * 1024 * 1024;
+/// Maximal run time from one hook call.
+const CALL_TIMEOUT: Duration = Duration::from_secs(5);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(3);
+/// Maximal size of a `http_get` response.
+const HTTP_MAX_SIZE: u64 = 1024 * 1024;
+const HOOKS: [&str; 3] = ["generate", "next", "filler"];
+
+/// Channel data, which every hook gets as last argument.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScriptContext {
+    pub channel: i32,
+    pub name: String,
+    /// Playlist date.
+    pub date: String,
+    /// Seconds since midnight.
+    pub time: f64,
+}
+
+impl ScriptContext {
+    pub fn new(manager: &ChannelManager) -> Self {
+        let channel = manager.channel.lock().unwrap();
+
+        Self {
+            channel: channel.id,
+            name: channel.name.clone(),
+            date: manager.current_date.lock().unwrap().clone(),
+            time: time_in_seconds(),
+        }
+    }
+}
+
+/// Loaded scripts from one channel.
+pub struct Scripts {
+    channel_id: i32,
+    lua: Lua,
+    hooks: Vec<(String, Table)>,
+    deadline: Arc<Mutex<Instant>>,
+}
+
+impl fmt::Debug for Scripts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&String> = self.hooks.iter().map(|(name, _)| name).collect();
+
+        f.debug_struct("Scripts")
+            .field("channel_id", &self.channel_id)
+            .field("scripts", &names)
+            .finish()
+    }
+}
+
+/// Address is reachable from the internet: no loopback, private, link-local or other special network.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+
+            !(a == 0
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                // shared address space (carrier-grade NAT) and reserved
+                || (a == 100 && (64..128).contains(&b))
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(ip));
+            }
+
+            let first = ip.segments()[0];
+
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // unique local and link-local
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// GET request from a script. Only to public http(s) servers, without redirects and with limited size.
+/// The host is resolved once and the request goes to the checked addresses.
+pub fn http_get(url: &str) -> mlua::Result<String> {
+    let url = Url::parse(url).map_err(mlua::Error::external)?;
+
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(mlua::Error::runtime(format!(
+            "URL scheme {} is not allowed",
+            url.scheme()
+        )));
+    }
+
+    let host = url
+        .host_str()
+        .ok_or_else(|| mlua::Error::runtime("URL has no host"))?
+        .to_string();
+    let port = url.port_or_known_default().unwrap_or(80);
+    let addrs: Vec<SocketAddr> = (host.trim_matches(['[', ']']), port)
+        .to_socket_addrs()
+        .map_err(mlua::Error::external)?
+        .collect();
+
+    if addrs.is_empty() || addrs.iter().any(|a| !is_public_ip(a.ip())) {
+        return Err(mlua::Error::runtime(format!(
+            "Host {host} is not a public address"
+        )));
+    }
+
+    let response = reqwest::blocking::Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .redirect(Policy::none())
+        .resolve_to_addrs(&host, &addrs)
+        .build()
+        .and_then(|c| c.get(url).send())
+        .and_then(Response::error_for_status)
+        .map_err(mlua::Error::external)?;
+    let mut body = vec![];
+
+    response
+        .take(HTTP_MAX_SIZE + 1)
+        .read_to_end(&mut body)
+        .map_err(mlua::Error::external)?;
+
+    if body.len() as u64 > HTTP_MAX_SIZE {
+        return Err(mlua::Error::runtime(format!(
+            "Response is larger than {HTTP_MAX_SIZE} bytes"
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&body).to_string())
+}
+
+/// Lua state without access to files, processes and the system.
+fn sandbox(channel_id: i32, deadline: Arc<Mutex<Instant>>) -> mlua::Result<Lua> {
+    let lua = Lua::new_with(
+        StdLib::TABLE | StdLib::STRING | StdLib::MATH | StdLib::UTF8 | StdLib::COROUTINE,
+        LuaOptions::default(),
+    )?;
+    let globals = lua.globals();
+
+    lua.set_memory_limit(MEMORY_LIMIT)?;
+    lua.set_hook(
+        HookTriggers::new().every_nth_instruction(1000),
+        move |_, _| {
+            if Instant::now() > *deadline.lock().unwrap() {
+                return Err(mlua::Error::runtime("script timeout"));
+            }
+
+            Ok(VmState::Continue)
+        },
+    );
+
+    for name in ["dofile", "loadfile", "load", "require"] {
+        globals.set(name, Value::Nil)?;
+    }
+
+    globals.get::<Table>("string")?.set("dump", Value::Nil)?;
+
+    let api = lua.create_table()?;
+
+    api.set(
+        "log",
+        lua.create_function(move |_, (level, msg): (String, String)| {
+            match level.to_lowercase().as_str() {
+                "error" => {
+                    error!(target: Target::file_mail(), channel = channel_id; "Script: {msg}");
+                }
+                "warn" | "warning" => {
+                    warn!(target: Target::file_mail(), channel = channel_id; "Script: {msg}");
+                }
+                "debug" => {
+                    debug!(target: Target::file_mail(), channel = channel_id; "Script: {msg}");
+                }
+                _ => info!(target: Target::file_mail(), channel = channel_id; "Script: {msg}"),
+            }
+
+            Ok(())
+        })?,
+    )?;
+    api.set(
+        "http_get",
+        lua.create_function(|_, url: String| {
+            // own thread, the blocking client can not run inside of the async runtime
+            thread::spawn(move || http_get(&url))
+                .join()
+                .map_err(|_| mlua::Error::runtime("http request panicked"))?
+        })?,
+    )?;
+    api.set(
+        "json_decode",
+        lua.create_function(|lua, text: String| {
+            let value: serde_json::Value =
+                serde_json::from_str(&text).map_err(mlua::Error::external)?;
+
+            lua.to_value_with(&value, options())
+        })?,
+    )?;
+    api.set(
+        "json_encode",
+        lua.create_function(|lua, value: Value| {
+            let value: serde_json::Value = lua.from_value(value)?;
+
+            serde_json::to_string(&value).map_err(mlua::Error::external)
+        })?,
+    )?;
+
+    globals.set("ffplayout", api)?;
+    globals.set(
+        "print",
+        lua.create_function(move |_, msg: String| {
+            info!(target: Target::file_mail(), channel = channel_id; "Script: {msg}");
+
+            Ok(())
+        })?,
+    )?;
+
+    Ok(lua)
+}
+
+/// Missing values are `nil` in Lua, not `null`.
+fn options() -> SerializeOptions {
+    SerializeOptions::new()
+        .serialize_none_to_null(false)
+        .serialize_unit_to_null(false)
+}
+
+/// Run the script and get its hook table.
+fn load(lua: &Lua, name: &str, code: &str) -> mlua::Result<Table> {
+    let hooks: Table = lua.load(code).set_name(name).eval()?;
+
+    for hook in HOOKS {
+        match hooks.get::<Value>(hook)? {
+            Value::Nil | Value::Function(_) => {}
+            _ => {
+                return Err(mlua::Error::runtime(format!(
+                    "hook '{hook}' is not a function"
+                )))
+            }
+        }
+    }
+
+    Ok(hooks)
+}
+
+/// Check a script before it is saved.
+pub fn check_script(code: &str) -> Result<(), ServiceError> {
+    let deadline = Arc::new(Mutex::new(Instant::now() + CALL_TIMEOUT));
+    let lua = sandbox(0, deadline).map_err(|e| ServiceError::Conflict(e.to_string()))?;
+
+    load(&lua, "check", code)
+        .map(|_| ())
+        .map_err(|e| ServiceError::BadRequest(format!("Invalid script: {e}")))
+}
+
+impl Scripts {
+    /// Load the enabled scripts, scripts with errors are skipped.
+    pub fn new(channel_id: i32, scripts: &[Script]) -> Result<Self, ServiceError> {
+        let deadline = Arc::new(Mutex::new(Instant::now() + CALL_TIMEOUT));
+        let lua = sandbox(channel_id, deadline.clone())
+            .map_err(|e| ServiceError::Conflict(e.to_string()))?;
+        let mut hooks = vec![];
+
+        for script in scripts.iter().filter(|s| s.enabled) {
+            *deadline.lock().unwrap() = Instant::now() + CALL_TIMEOUT;
+
+            match load(&lua, &script.name, &script.code) {
+                Ok(table) => hooks.push((script.name.clone(), table)),
+                Err(e) => {
+                    error!(target: Target::file_mail(), channel = channel_id;
+                        "Script <b><magenta>{}</></b> not loaded: {e}", script.name
+                    );
+                }
+            }
+        }
+
+        Ok(Self {
+            channel_id,
+            lua,
+            hooks,
+            deadline,
+        })
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.hooks.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    fn call(&self, name: &str, hooks: &Table, hook: &str, args: impl mlua::IntoLuaMulti) -> Value {
+        let Ok(Some(func)) = hooks.get::<Option<Function>>(hook) else {
+            return Value::Nil;
+        };
+
+        *self.deadline.lock().unwrap() = Instant::now() + CALL_TIMEOUT;
+
+        func.call::<Value>(args).unwrap_or_else(|e| {
+            error!(target: Target::file_mail(), channel = self.channel_id;
+                "Script <b><magenta>{name}</></b> failed in <yellow>{hook}</>: {e}"
+            );
+
+            Value::Nil
+        })
+    }
+
+    /// Give the value through the hook of all scripts, `nil` keeps it unchanged.
+    fn chain<T: Serialize + DeserializeOwned>(
+        &self,
+        hook: &str,
+        mut value: T,
+        ctx: &ScriptContext,
+    ) -> T {
+        for (name, hooks) in &self.hooks {
+            let args = match (
+                self.lua.to_value_with(&value, options()),
+                self.lua.to_value_with(ctx, options()),
+            ) {
+                (Ok(v), Ok(c)) => (v, c),
+                (Err(e), _) | (_, Err(e)) => {
+                    error!(target: Target::file_mail(), channel = self.channel_id; "Script {hook}: {e}");
+                    continue;
+                }
+            };
+
+            let result = self.call(name, hooks, hook, args);
+
+            if result.is_nil() {
+                continue;
+            }
+
+            match self.lua.from_value(result) {
+                Ok(v) => value = v,
+                Err(e) => {
+                    error!(target: Target::file_mail(), channel = self.channel_id;
+                        "Script <b><magenta>{name}</></b> returns invalid data from <yellow>{hook}</>: {e}"
+                    );
+                }
+            }
+        }
+
+        value
+    }
+
+    /// Playlist after the `generate` hooks.
+    pub fn generate(&self, playlist: JsonPlaylist, ctx: &ScriptContext) -> JsonPlaylist {
+        let date = playlist.date.clone();
+        let mut playlist = self.chain("generate", playlist, ctx);
+
+        playlist.date = date;
+
+        playlist
+    }
+
+    /// Clip after the `next` hooks.
+    pub fn next_clip(&self, clip: Media, ctx: &ScriptContext) -> Media {
+        self.chain("next", clip, ctx)
+    }
+
+    /// Filler source from the first script, which has one for the gap.
+    pub fn filler(&self, gap: f64, fillers: &[Media], ctx: &ScriptContext) -> Option<String> {
+        for (name, hooks) in &self.hooks {
+            let Ok(list) = self.lua.to_value_with(fillers, options()) else {
+                continue;
+            };
+            let Ok(context) = self.lua.to_value_with(ctx, options()) else {
+                continue;
+            };
+
+            if let Value::String(source) = self.call(name, hooks, "filler", (gap, list, context)) {
+                return Some(source.to_string_lossy());
+            }
+        }
+
+        None
+    }
+}
+
+/// Load the scripts of the channel from the database, call it again after a script changed.
+pub async fn load_scripts(conn: &Pool<Sqlite>, manager: &ChannelManager) {
+    let id = manager.channel.lock().unwrap().id;
+
+    let scripts = match handles::select_scripts(conn, id).await {
+        Ok(scripts) => scripts,
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "Unable to read scripts: {e}");
+            return;
+        }
+    };
+
+    if scripts.is_empty() {
+        *manager.scripts.lock().unwrap() = None;
+        return;
+    }
+
+    match Scripts::new(id, &scripts) {
+        Ok(s) => {
+            debug!(target: Target::file_mail(), channel = id; "Load scripts: {:?}", s.names());
+
+            *manager.scripts.lock().unwrap() = Some(s);
+        }
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "Unable to load scripts: {e}");
+        }
+    }
+}
+
+fn enabled(manager: &ChannelManager) -> bool {
+    manager.config.lock().unwrap().general.scripting
+}
+
+/// Run the `generate` hooks, when scripting is enabled for the channel.
+pub fn on_generate(manager: &ChannelManager, playlist: JsonPlaylist) -> JsonPlaylist {
+    if !enabled(manager) {
+        return playlist;
+    }
+
+    match manager.scripts.lock().unwrap().as_ref() {
+        Some(scripts) => {
+            let ctx = ScriptContext {
+                date: playlist.date.clone(),
+                ..ScriptContext::new(manager)
+            };
+
+            scripts.generate(playlist, &ctx)
+        }
+        None => playlist,
+    }
+}
+
+/// Source from a script, normalized into the storage or the filler folder.
+/// Sources outside of them are rejected.
+pub fn script_source(config: &PlayoutConfig, source: &str) -> Option<PathBuf> {
+    let path = Path::new(source);
+    let filler = &config.storage.filler_path;
+
+    if path.components().any(|c| c == Component::ParentDir) {
+        return None;
+    }
+
+    if filler.is_file() && path == filler {
+        return Some(filler.clone());
+    }
+
+    let root = if filler.is_dir() && path.starts_with(filler) {
+        filler
+    } else {
+        &config.channel.storage
+    };
+
+    if !path.starts_with(root) {
+        return None;
+    }
+
+    norm_abs_path(root, source).ok().map(|(path, _, _)| path)
+}
+
+/// Run the `next` hooks on the clip, which plays next.
+pub fn on_next(manager: &ChannelManager, node: &mut Media) {
+    if !enabled(manager) {
+        return;
+    }
+
+    let scripts = manager.scripts.lock().unwrap();
+    let Some(scripts) = scripts.as_ref() else {
+        return;
+    };
+
+    let id = manager.channel.lock().unwrap().id;
+    let config = manager.config.lock().unwrap().clone();
+    let mut clip = scripts.next_clip(node.clone(), &ScriptContext::new(manager));
+
+    if clip.source != node.source {
+        match script_source(&config, &clip.source) {
+            Some(path) => {
+                node.source = path.to_string_lossy().to_string();
+                node.probe = None;
+                node.probe_audio = None;
+            }
+            None => {
+                warn!(target: Target::file_mail(), channel = id;
+                    "Source from script is outside of the storage: <b><magenta>{}</></b>", clip.source
+                );
+            }
+        }
+    }
+
+    if clip.custom_filter != node.custom_filter {
+        if let Err(e) = validate_filter(&clip.custom_filter, ffmpeg_filters()) {
+            warn!(target: Target::file_mail(), channel = id; "Filter from script is not valid: {e}");
+
+            clip.custom_filter.clone_from(&node.custom_filter);
+        }
+    }
+
+    node.title = clip.title;
+    node.seek = clip.seek;
+    node.out = clip.out;
+    node.duration = clip.duration;
+    node.category = clip.category;
+    node.audio = clip.audio;
+    node.custom_filter = clip.custom_filter;
+    node.description = clip.description;
+}
+
+/// Filler from the `filler` hooks, from the filler list or any other existing file.
+pub fn on_filler(manager: &ChannelManager, gap: f64, fillers: &[Media]) -> Option<Media> {
+    if !enabled(manager) {
+        return None;
+    }
+
+    let source = manager.scripts.lock().unwrap().as_ref()?.filler(
+        gap,
+        fillers,
+        &ScriptContext::new(manager),
+    )?;
+
+    if let Some(filler) = fillers.iter().find(|f| f.source == source) {
+        return Some(filler.clone());
+    }
+
+    let id = manager.channel.lock().unwrap().id;
+    let config = manager.config.lock().unwrap().clone();
+
+    let Some(path) = script_source(&config, &source) else {
+        warn!(target: Target::file_mail(), channel = id;
+            "Filler from script is outside of the storage: <b><magenta>{source}</></b>"
+        );
+
+        return None;
+    };
+
+    if path.is_file() {
+        return Some(Media::new(0, &path.to_string_lossy(), true));
+    }
+
+    warn!(target: Target::file_mail(), channel = id;
+        "Filler from script not exists: <b><magenta>{source}</></b>"
+    );
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::db::models::Script;
+    use crate::utils::config::PlayoutConfig;
+
+    #[test]
+    fn script_hooks() {
+        let script = |name: &str, code: &str| Script {
+            name: name.to_string(),
+            code: code.to_string(),
+            enabled: true,
+            ..Default::default()
+        };
+        let scripts = Scripts::new(
+        1,
+        &[
+            script(
+                "a",
+                r#"return {
+                    next = function(clip, ctx)
+                        if clip.category == "kids" and ctx.time > 72000 then
+                            clip.source = "/media/night.mp4"
+                            return clip
+                        end
+                    end,
+                    filler = function(gap, fillers, ctx)
+                        for _, f in ipairs(fillers) do
+                            if f.duration <= gap then return f.source end
+                        end
+                    end,
+                }"#,
+            ),
+            script(
+                "b",
+                r#"return {
+                    next = function(clip) clip.title = "checked"; return clip end,
+                    generate = function(playlist) table.remove(playlist.program, 1); return playlist end,
+                }"#,
+            ),
+            script("broken", "return {"),
+            script("loop", "return { filler = function() while true do end end }"),
+        ],
+    )
+    .unwrap();
+
+        assert_eq!(scripts.names(), vec!["a", "b", "loop"]);
+
+        let mut clip = Media::new(0, "/media/a.mp4", false);
+        clip.category = "kids".to_string();
+        let ctx = ScriptContext {
+            time: 80000.0,
+            ..Default::default()
+        };
+        let next = scripts.next_clip(clip.clone(), &ctx);
+
+        assert_eq!(next.source, "/media/night.mp4");
+        assert_eq!(next.title, Some("checked".to_string()));
+
+        let next = scripts.next_clip(clip, &ScriptContext::default());
+
+        assert_eq!(next.source, "/media/a.mp4");
+
+        let mut playlist = JsonPlaylist::new("2024-10-27".to_string(), 0.0);
+        playlist.program.push(Media::new(1, "/media/b.mp4", false));
+
+        assert_eq!(scripts.generate(playlist, &ctx).program.len(), 1);
+
+        let mut long = Media::new(0, "/media/long.mp4", false);
+        long.duration = 300.0;
+        let mut short = Media::new(1, "/media/short.mp4", false);
+        short.duration = 20.0;
+
+        assert_eq!(
+            scripts.filler(30.0, &[long, short], &ctx),
+            Some("/media/short.mp4".to_string())
+        );
+
+        // the loop script stops with a timeout
+        assert_eq!(scripts.filler(10.0, &[], &ctx), None);
+
+        assert!(check_script("return {}").is_ok());
+        assert!(check_script("return io.open('/etc/passwd')").is_err());
+        assert!(check_script("return { next = 1 }").is_err());
+        assert!(check_script("return 1").is_err());
+    }
+
+    #[test]
+    fn script_sources() {
+        let mut config = PlayoutConfig::default();
+        config.channel.storage = PathBuf::from("/var/lib/ffplayout/tv-media");
+
+        assert_eq!(
+            script_source(&config, "/var/lib/ffplayout/tv-media/night.mp4"),
+            Some(PathBuf::from("/var/lib/ffplayout/tv-media/night.mp4"))
+        );
+        assert_eq!(
+            script_source(&config, "/var/lib/ffplayout/tv-media/../../../etc/passwd"),
+            None
+        );
+        assert_eq!(script_source(&config, "/etc/passwd"), None);
+        assert_eq!(script_source(&config, "http://example.org/live.m3u8"), None);
+        assert_eq!(script_source(&config, "night.mp4"), None);
+    }
+
+    #[test]
+    fn script_http_restrictions() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+
+        assert!(is_public_ip("93.184.216.34".parse().unwrap()));
+        assert!(is_public_ip("2606:2800:220:1::1".parse().unwrap()));
+
+        // rejected before any connection
+        assert!(http_get("file:///etc/passwd").is_err());
+        assert!(http_get("ftp://example.org/file").is_err());
+        assert!(http_get("http://127.0.0.1:8787/api/channels").is_err());
+        assert!(http_get("http://[::1]/").is_err());
+        assert!(http_get("http://169.254.169.254/latest/meta-data/").is_err());
+        assert!(http_get("http://localhost/").is_err());
+    }
+}
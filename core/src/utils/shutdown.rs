@@ -0,0 +1,167 @@
+/*
+Graceful shutdown.
+
+On SIGTERM, SIGINT or a call to `/api/system/shutdown` the server stops accepting connections,
+new API requests get a 503 and the requests in flight, like uploads, can finish until the
+shutdown timeout. Then every channel writes its playlist state to the database and its ffmpeg
+processes get SIGTERM, so they can close their outputs. Processes which are still running after
+`FFMPEG_TIMEOUT` are killed.
+
+The web server part is in the engine, here are the shutdown state and the stop of the channels.
+
+*/
+
+use std::{
+    process::Child,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use futures_util::future::join_all;
+use log::*;
+use nix::{
+    sys::signal::{kill, Signal},
+    unistd::Pid,
+};
+use serde::Serialize;
+use sqlx::{Pool, Sqlite};
+use tokio::task;
+
+use crate::db::handles;
+use crate::player::controller::{ChannelController, ChannelManager};
+use crate::utils::logging::Target;
+
+/// Time for ffmpeg to close its output after SIGTERM, before it gets killed.
+pub const FFMPEG_TIMEOUT: Duration = Duration::from_secs(10);
+
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+static UPLOADS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShutdownStatus {
+    pub shutdown: bool,
+    /// Uploads, which are still running.
+    pub uploads: usize,
+}
+
+pub fn shutdown_status() -> ShutdownStatus {
+    ShutdownStatus {
+        shutdown: is_shutting_down(),
+        uploads: UPLOADS.load(Ordering::SeqCst),
+    }
+}
+
+pub fn is_shutting_down() -> bool {
+    SHUTDOWN.load(Ordering::SeqCst)
+}
+
+/// Counts a running upload, as long as it lives.
+pub struct UploadGuard(());
+
+impl UploadGuard {
+    pub fn new() -> Self {
+        UPLOADS.fetch_add(1, Ordering::SeqCst);
+
+        Self(())
+    }
+}
+
+impl Default for UploadGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for UploadGuard {
+    fn drop(&mut self) {
+        UPLOADS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Mark the shutdown as started, returns `false` when it was already started.
+pub fn start_shutdown(reason: &str) -> bool {
+    if SHUTDOWN.swap(true, Ordering::SeqCst) {
+        return false;
+    }
+
+    info!(
+        "Shutdown by {reason}, wait for <yellow>{}</> running uploads",
+        UPLOADS.load(Ordering::SeqCst)
+    );
+
+    true
+}
+
+/// Send SIGTERM to the processes and kill them, when they are still running after the timeout.
+/// Returns the number of killed processes.
+pub fn terminate(mut children: Vec<Child>, timeout: Duration) -> usize {
+    let deadline = Instant::now() + timeout;
+    let mut killed = 0;
+
+    for child in &children {
+        let _ = kill(Pid::from_raw(child.id() as i32), Signal::SIGTERM);
+    }
+
+    while !children.is_empty() {
+        let timed_out = Instant::now() >= deadline;
+
+        children.retain_mut(|child| match child.try_wait() {
+            Ok(None) if timed_out => {
+                let _ = child.kill();
+                let _ = child.wait();
+                killed += 1;
+
+                false
+            }
+            Ok(None) => true,
+            _ => false,
+        });
+
+        sleep(Duration::from_millis(50));
+    }
+
+    killed
+}
+
+/// Store the playlist state and the last clip of the channel.
+async fn write_final_state(pool: &Pool<Sqlite>, manager: &ChannelManager) {
+    if !manager.is_alive.load(Ordering::SeqCst) {
+        return;
+    }
+
+    let channel = manager.channel.lock().unwrap().clone();
+    let date = manager.current_date.lock().unwrap().clone();
+    let media = manager.current_media.lock().unwrap().clone();
+    let last_date = (!date.is_empty()).then_some(date);
+
+    if let Err(e) = handles::update_stat(pool, channel.id, last_date, channel.time_shift).await {
+        error!(target: Target::all(), channel = channel.id; "Unable to write status: {e}");
+    }
+
+    if let Some(media) = media {
+        info!(target: Target::file_mail(), channel = channel.id;
+            "As-run at shutdown: <b><magenta>{}</></b>, index <yellow>{}</>",
+            media.source,
+            media.index.unwrap_or_default()
+        );
+    }
+}
+
+/// Write the state of all channels and stop their ffmpeg processes.
+pub async fn shutdown_channels(pool: &Pool<Sqlite>, controllers: &Mutex<ChannelController>) {
+    let managers = controllers.lock().unwrap().channels.clone();
+
+    join_all(managers.into_iter().map(|manager| async move {
+        write_final_state(pool, &manager).await;
+
+        // only in memory, active channels start again with the next run
+        manager.channel.lock().unwrap().active = false;
+
+        let _ = task::spawn_blocking(move || manager.graceful_stop(FFMPEG_TIMEOUT)).await;
+    }))
+    .await;
+}
@@ -0,0 +1,271 @@
+/*
+Dry run of a playlist.
+
+The simulation goes through the playlist like the player, but without ffmpeg, and returns the
+timeline as it would play out:
+
+- the clips follow one after the other from the playlist start, a crossfade shortens the next clip
+- clips shorter than one second are skipped
+- a missing source plays as filler, with the length of the clip
+- with a fixed playlist length, clips after the end of the day are skipped, the last clip
+  is cut at the end of the day and a short playlist is padded with filler
+
+Commercial breaks are listed as they are, the spots are chosen only when the playlist loads.
+
+*/
+
+use std::path::Path;
+
+use chrono::{Local, NaiveDate, NaiveTime, TimeDelta, TimeZone};
+use serde::Serialize;
+
+use crate::player::{
+    filter::crossfade_duration,
+    utils::{is_remote, json_serializer::set_defaults, JsonPlaylist, Media},
+};
+use crate::utils::{
+    config::{PlayoutConfig, IMAGE_FORMAT},
+    errors::ServiceError,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SimulatedKind {
+    Clip,
+    /// The source is missing, a filler plays instead.
+    Missing,
+    /// Filler to the end of the day.
+    Filler,
+    /// Commercial break, filled with spots at air time.
+    Break,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulatedItem {
+    /// Index in the playlist, padding has no index.
+    pub index: Option<usize>,
+    pub kind: SimulatedKind,
+    /// Wall clock start and end.
+    pub start: String,
+    pub end: String,
+    pub source: String,
+    pub title: Option<String>,
+    #[serde(rename = "in")]
+    pub seek: f64,
+    pub out: f64,
+    pub duration: f64,
+    /// Play time of the item, with trimming and crossfade.
+    pub length: f64,
+    pub trimmed: bool,
+    pub on_air: bool,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Simulation {
+    pub date: String,
+    pub start: String,
+    pub end: String,
+    /// Playlist length from the config, in seconds.
+    pub target: f64,
+    /// Play time of the timeline, in seconds.
+    pub length: f64,
+    pub items: Vec<SimulatedItem>,
+    pub warnings: Vec<String>,
+}
+
+fn source_exists(source: &str) -> bool {
+    is_remote(source) || Path::new(source).is_file()
+}
+
+/// Crossfades go only into local video files.
+fn can_crossfade(source: &str) -> bool {
+    !is_remote(source)
+        && Path::new(source).is_file()
+        && !source
+            .rsplit_once('.')
+            .is_some_and(|(_, e)| IMAGE_FORMAT.contains(&e.to_lowercase().as_str()))
+}
+
+/// Go through the playlist of `date` like the player and return the timeline.
+pub fn simulate(
+    config: &PlayoutConfig,
+    date: &str,
+    mut playlist: JsonPlaylist,
+) -> Result<Simulation, ServiceError> {
+    let day = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| ServiceError::BadRequest(format!("Invalid date: {date}")))?;
+    let start_sec = config.playlist.start_sec.unwrap_or_default();
+    let target = config.playlist.length_sec.unwrap_or(86400.0);
+    let fixed = config.playlist.length.contains(':') && !config.playlist.infinit;
+    let base = Local
+        .from_local_datetime(&day.and_time(NaiveTime::MIN))
+        .earliest()
+        .ok_or_else(|| ServiceError::BadRequest(format!("Invalid date: {date}")))?;
+    let wall_clock = |sec: f64| {
+        (base + TimeDelta::milliseconds((sec * 1000.0).round() as i64))
+            .format("%Y-%m-%d %H:%M:%S%.3f%:z")
+            .to_string()
+    };
+    let on_air = |sec: f64| config.general.air_hours.on_air(sec % 86400.0);
+
+    playlist.start_sec = Some(start_sec);
+    set_defaults(&mut playlist);
+
+    let program = &playlist.program;
+    let last = program.len().saturating_sub(1);
+    let mut items = vec![];
+    let mut warnings = vec![];
+    let mut position = 0.0;
+    let mut crossfade = 0.0;
+
+    if program.is_empty() {
+        warnings.push("Playlist is empty".to_string());
+    }
+
+    for (i, node) in program.iter().enumerate() {
+        let seek = node.seek + crossfade;
+        let mut out = node.out;
+        let remaining = target - position;
+        let mut kind = SimulatedKind::Clip;
+        let mut trimmed = false;
+        let mut note = None;
+
+        crossfade = 0.0;
+
+        if node.break_name.is_some() {
+            kind = SimulatedKind::Break;
+        } else if node.out - node.seek < 1.0 {
+            kind = SimulatedKind::Skipped;
+            note = Some("Clip is shorter than one second".to_string());
+        } else if fixed && i > 1 && remaining <= 0.0 {
+            kind = SimulatedKind::Skipped;
+            note = Some("Clip starts after the end of the day".to_string());
+        } else if fixed && i > 1 && (remaining < node.duration - seek || i == last) {
+            if (node.duration > remaining || out > remaining)
+                && (node.duration - seek >= remaining || out - seek >= remaining)
+                && remaining > 1.0
+            {
+                out = seek + remaining;
+                trimmed = true;
+                note = Some("Clip is cut at the end of the day".to_string());
+            }
+        } else if fixed && i > 1 && remaining <= out - seek {
+            kind = SimulatedKind::Skipped;
+            note = Some("Clip does not fit before the end of the day".to_string());
+        }
+
+        if kind == SimulatedKind::Clip && !source_exists(&node.source) {
+            kind = SimulatedKind::Missing;
+            note = Some("Source not found, filler plays instead".to_string());
+            warnings.push(format!("Source not found: {}", node.source));
+        }
+
+        if kind == SimulatedKind::Skipped {
+            items.push(simulated_item(
+                node,
+                wall_clock(start_sec + position),
+                wall_clock(start_sec + position),
+                (seek, out, 0.0),
+                kind,
+                false,
+                note,
+            ));
+
+            continue;
+        }
+
+        let length = out - seek;
+
+        if kind == SimulatedKind::Clip && !trimmed {
+            if let Some(next) = program.get(i + 1).filter(|n| can_crossfade(&n.source)) {
+                // the next clip starts inside the crossfade, so it gets shorter
+                crossfade = crossfade_duration(node, next, config).unwrap_or_default();
+            }
+        }
+
+        let start = start_sec + position;
+        let mut item = simulated_item(
+            node,
+            wall_clock(start),
+            wall_clock(start + length),
+            (seek, out, length),
+            kind,
+            trimmed,
+            note,
+        );
+        item.on_air = on_air(start);
+
+        items.push(item);
+        position += length;
+    }
+
+    if fixed {
+        let rest = target - position;
+
+        if rest > 1.0 {
+            warnings.push(format!(
+                "Playlist is {rest:.3} seconds too short, filler pads the end"
+            ));
+
+            let start = start_sec + position;
+            let mut filler = Media::new(0, &config.storage.filler_path.to_string_lossy(), false);
+            filler.index = None;
+            filler.title = None;
+            filler.duration = rest;
+            filler.out = rest;
+
+            let mut item = simulated_item(
+                &filler,
+                wall_clock(start),
+                wall_clock(start + rest),
+                (0.0, rest, rest),
+                SimulatedKind::Filler,
+                false,
+                Some("Filler until the next playlist".to_string()),
+            );
+            item.index = None;
+            item.on_air = on_air(start);
+
+            items.push(item);
+            position += rest;
+        }
+    }
+
+    Ok(Simulation {
+        date: date.to_string(),
+        start: wall_clock(start_sec),
+        end: wall_clock(start_sec + position),
+        target,
+        length: position,
+        items,
+        warnings,
+    })
+}
+
+fn simulated_item(
+    node: &Media,
+    start: String,
+    end: String,
+    (seek, out, length): (f64, f64, f64),
+    kind: SimulatedKind,
+    trimmed: bool,
+    note: Option<String>,
+) -> SimulatedItem {
+    SimulatedItem {
+        index: node.index,
+        kind,
+        start,
+        end,
+        source: node.source.clone(),
+        title: node.title.clone(),
+        seek,
+        out,
+        duration: node.duration,
+        length,
+        trimmed,
+        on_air: false,
+        note,
+    }
+}
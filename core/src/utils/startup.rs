@@ -0,0 +1,205 @@
+/*
+Start of the channels at boot.
+
+The server and applications which embed the core load and start the channels the same way:
+`load_channels` puts all channels from the database in the controller, `start_channels` starts
+the active ones and the background workers. Channels in maintenance stay stopped, outside of their
+air hours they are off air and on a failover standby they wait for the primary.
+
+## Staggered start
+
+With many channels all ffmpeg processes would start at the same moment and spike CPU and IO.
+So the active channels start in groups, ordered by their start priority: a higher priority starts
+first and the next group waits, until all channels of the group are started. So a channel can
+depend on another one, like a channel which takes the output of another channel as ingest.
+
+Inside a group `--start-concurrency` channels start at the same time. A started channel keeps
+its slot for `--start-delay` seconds, while its processes warm up, then the next channel starts.
+Without limit and delay all channels of a group start at once, like before.
+
+*/
+
+use std::{
+    cmp::Reverse,
+    sync::{atomic::Ordering, Arc, Mutex},
+    time::Duration,
+};
+
+use futures_util::future::join_all;
+use log::*;
+use sqlx::{Pool, Sqlite};
+use tokio::{sync::Semaphore, time::sleep};
+
+use crate::db::handles;
+use crate::player::{
+    controller::{ChannelController, ChannelManager},
+    output::regions::load_regions,
+};
+use crate::utils::{
+    ad_breaks::load_ad_breaks,
+    air_hours::{air_scheduler, is_switched_off},
+    config::get_config,
+    download::download_worker,
+    errors::ServiceError,
+    failover::{failover_monitor, is_standby},
+    ffmpeg_caps::validate_config,
+    logging::{MailQueue, Target},
+    maintenance::in_maintenance,
+    pull_ingest::pull_ingest,
+    report::daily_report,
+    scripting::load_scripts,
+    transcode::transcode_worker,
+    watch_folder::watch_folders,
+};
+use crate::ARGS;
+
+/// Group the channels by their priority, the highest priority first.
+/// Channels with the same priority keep their order.
+pub fn start_groups<T>(mut channels: Vec<(i32, T)>) -> Vec<Vec<T>> {
+    let mut groups: Vec<Vec<T>> = vec![];
+    let mut last = None;
+
+    channels.sort_by_key(|c| Reverse(c.0));
+
+    for (priority, channel) in channels {
+        match groups.last_mut() {
+            Some(group) if last == Some(priority) => group.push(channel),
+            _ => groups.push(vec![channel]),
+        }
+
+        last = Some(priority);
+    }
+
+    groups
+}
+
+/// Start the channel, when a slot is free, and keep the slot for the delay.
+async fn start_in_slot(manager: ChannelManager, slots: Arc<Semaphore>, delay: Duration) {
+    let Ok(_slot) = slots.acquire().await else {
+        return;
+    };
+    let (id, active, priority) = {
+        let channel = manager.channel.lock().unwrap();
+        (channel.id, channel.active, channel.start_priority)
+    };
+
+    // stopped or started by hand, while it was waiting
+    if !active || manager.is_alive.load(Ordering::SeqCst) {
+        return;
+    }
+
+    debug!(target: Target::file(), channel = id; "Start channel with priority <yellow>{priority}</>");
+
+    manager.async_start().await;
+    sleep(delay).await;
+}
+
+/// Start the channels in the background, group after group.
+pub fn staggered_start(managers: Vec<ChannelManager>) {
+    let concurrency = ARGS
+        .start_concurrency
+        .filter(|c| *c > 0)
+        .unwrap_or(Semaphore::MAX_PERMITS);
+    let delay = Duration::from_secs(ARGS.start_delay.unwrap_or(0));
+    let channels = managers
+        .into_iter()
+        .map(|m| {
+            let priority = m.channel.lock().unwrap().start_priority;
+
+            (priority, m)
+        })
+        .collect();
+
+    tokio::spawn(async move {
+        let slots = Arc::new(Semaphore::new(concurrency));
+
+        for group in start_groups(channels) {
+            join_all(
+                group
+                    .into_iter()
+                    .map(|manager| start_in_slot(manager, slots.clone(), delay)),
+            )
+            .await;
+        }
+    });
+}
+
+/// Load all channels from the database in the controller, with their mail queue.
+pub async fn load_channels(
+    pool: &Pool<Sqlite>,
+    controllers: &Mutex<ChannelController>,
+    mail_queues: &Mutex<Vec<Arc<Mutex<MailQueue>>>>,
+) -> Result<(), ServiceError> {
+    for channel in handles::select_related_channels(pool, None).await? {
+        let config = get_config(pool, channel.id).await?;
+        validate_config(&config);
+
+        let manager = ChannelManager::new(Some(pool.clone()), channel.clone(), config.clone());
+        let m_queue = Arc::new(Mutex::new(MailQueue::new(channel.id, config.mail)));
+
+        load_scripts(pool, &manager).await;
+        load_ad_breaks(pool, &manager).await;
+        load_regions(pool, &manager).await;
+
+        controllers.lock().unwrap().add(manager);
+        mail_queues.lock().unwrap().push(m_queue);
+    }
+
+    Ok(())
+}
+
+/// Start the active channels and the background workers.
+///
+/// Channels for which `remote` is true run on another node, they are only marked as inactive.
+pub fn start_channels(
+    pool: &Pool<Sqlite>,
+    controllers: &Arc<Mutex<ChannelController>>,
+    remote: impl Fn(i32) -> bool,
+) {
+    let managers = controllers.lock().unwrap().channels.clone();
+    let mut start_managers = vec![];
+
+    for manager in managers {
+        let (id, active) = {
+            let channel = manager.channel.lock().unwrap();
+            (channel.id, channel.active)
+        };
+        let config = manager.config.lock().unwrap().clone();
+
+        if remote(id) {
+            // runs on its playout node
+            manager.channel.lock().unwrap().active = false;
+        } else if active && in_maintenance(id) {
+            info!(target: Target::file(), channel = id; "Channel is in maintenance, it is not started");
+        } else if active && is_switched_off(&config) {
+            manager.off_air.store(true, Ordering::SeqCst);
+        } else if active && is_standby() {
+            manager.standby.store(true, Ordering::SeqCst);
+        } else if active {
+            start_managers.push(manager);
+        }
+    }
+
+    staggered_start(start_managers);
+
+    daily_report(pool.clone(), controllers.clone());
+    air_scheduler(controllers.clone());
+    failover_monitor(pool.clone(), controllers.clone());
+    watch_folders(controllers.clone());
+    pull_ingest(controllers.clone());
+    transcode_worker(pool.clone(), controllers.clone());
+    download_worker(pool.clone(), controllers.clone());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn staggered_start_groups() {
+        let groups = start_groups(vec![(0, 1), (5, 2), (0, 3), (-1, 4), (5, 5)]);
+
+        assert_eq!(groups, vec![vec![2, 5], vec![1, 3], vec![4]]);
+        assert!(start_groups::<i32>(vec![]).is_empty());
+    }
+}
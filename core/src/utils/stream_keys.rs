@@ -0,0 +1,294 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use rand::{distributions::Alphanumeric, Rng};
+use regex::Regex;
+
+use crate::utils::config::Ingest;
+
+/// Default time in which old ingest keys are still accepted after a rotation.
+pub const KEY_GRACE_PERIOD: u64 = 600;
+
+/// Old ingest keys, with the time until they are valid.
+type GraceKeys = Vec<(String, Instant)>;
+
+// Old ingest keys per channel
+static GRACE_KEYS: LazyLock<Mutex<HashMap<i32, GraceKeys>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+static RTMP_URL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(rtmps?://([^\s/:]+)(?::\d+)?/(?:[^\s/]+/)+)([^\s/?]+)").unwrap()
+});
+
+pub fn generate_key() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(24)
+        .map(char::from)
+        .collect()
+}
+
+/// Replace the stream key (last path segment) from rtmp urls, where `host_filter` returns true.
+/// Returns the new parameters and a list of (old_key, new_key).
+pub fn rotate_url_keys(
+    param: &str,
+    first_only: bool,
+    host_filter: impl Fn(&str) -> bool,
+) -> (String, Vec<(String, String)>) {
+    let mut keys = vec![];
+    let mut result = String::new();
+    let mut last = 0;
+
+    for caps in RTMP_URL.captures_iter(param) {
+        if first_only && !keys.is_empty() {
+            break;
+        }
+
+        if !host_filter(&caps[2]) {
+            continue;
+        }
+
+        let key = caps.get(3).unwrap();
+        let new_key = generate_key();
+
+        result.push_str(&param[last..key.start()]);
+        result.push_str(&new_key);
+        last = key.end();
+
+        keys.push((key.as_str().to_string(), new_key));
+    }
+
+    result.push_str(&param[last..]);
+
+    (result, keys)
+}
+
+pub fn is_local_host(host: &str) -> bool {
+    matches!(host, "127.0.0.1" | "localhost" | "0.0.0.0" | "[::1]")
+}
+
+/// Accept the old key for the given time.
+pub fn add_grace_key(id: i32, key: String, duration: Duration) {
+    let mut keys = GRACE_KEYS.lock().unwrap();
+
+    keys.entry(id)
+        .or_default()
+        .push((key, Instant::now() + duration));
+}
+
+/// Check if the key is old, but still valid.
+pub fn is_grace_key(id: i32, key: &str) -> bool {
+    let mut keys = GRACE_KEYS.lock().unwrap();
+    let Some(channel_keys) = keys.get_mut(&id) else {
+        return false;
+    };
+
+    channel_keys.retain(|(_, until)| *until > Instant::now());
+
+    channel_keys.iter().any(|(k, _)| k == key)
+}
+
+/// Stream key from a ffmpeg "Unexpected stream" line.
+pub fn unexpected_key(line: &str) -> Option<&str> {
+    line.split_once("Unexpected stream ")
+        .and_then(|(_, rest)| rest.split(',').next())
+        .and_then(|stream| stream.trim().rsplit('/').next())
+}
+
+/// Check if the stream from a ffmpeg "Unexpected stream" line uses a old, but still valid key.
+pub fn is_grace_stream(id: i32, line: &str) -> bool {
+    unexpected_key(line).is_some_and(|key| is_grace_key(id, key))
+}
+
+/// Check if the stream from a ffmpeg "Unexpected stream" line uses one of the extra keys.
+pub fn is_auth_stream(ingest: &Ingest, line: &str) -> bool {
+    ingest.auth.enable
+        && unexpected_key(line).is_some_and(|key| ingest.auth.keys.iter().any(|k| k.trim() == key))
+}
+
+/// Stream key from the first rtmp url in the parameters.
+pub fn url_key(param: &str) -> Option<String> {
+    RTMP_URL.captures(param).map(|caps| caps[3].to_string())
+}
+
+fn in_network(ip: IpAddr, entry: &str) -> bool {
+    let (net, prefix) = entry.split_once('/').unwrap_or((entry, ""));
+    let Ok(net) = net.trim().parse::<IpAddr>() else {
+        return false;
+    };
+    let bits = if net.is_ipv4() { 32 } else { 128 };
+    let prefix = if prefix.is_empty() {
+        bits
+    } else {
+        match prefix.trim().parse::<u32>() {
+            Ok(p) if p <= bits => p,
+            _ => return false,
+        }
+    };
+
+    match (ip, net) {
+        (IpAddr::V4(ip), IpAddr::V4(net)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(net) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(net)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(net) & mask
+        }
+        _ => false,
+    }
+}
+
+/// Check the address against the allowlist, with single addresses or networks like `10.0.0.0/8`.
+/// An empty list allows every address.
+pub fn ip_allowed(addr: &str, allow: &[String]) -> bool {
+    if allow.iter().all(|a| a.trim().is_empty()) {
+        return true;
+    }
+
+    let Ok(ip) = addr.trim().parse::<IpAddr>() else {
+        return false;
+    };
+
+    // IPv4 clients on a dual stack socket
+    let ip = ip.to_canonical();
+
+    allow
+        .iter()
+        .filter(|a| !a.trim().is_empty())
+        .any(|a| in_network(ip, a.trim()))
+}
+
+/// Check the stream key: the key from the ingest url, the extra keys from the auth settings
+/// or an old key after a rotation.
+pub fn is_valid_key(id: i32, ingest: &Ingest, key: &str) -> bool {
+    let key = key.trim();
+
+    !key.is_empty()
+        && (url_key(&ingest.input_param).is_some_and(|k| k == key)
+            || ingest.auth.keys.iter().any(|k| k.trim() == key)
+            || is_grace_key(id, key))
+}
+
+/// Check if a publisher may stream to the ingest, with a valid key and an allowed address.
+pub fn check_publisher(id: i32, ingest: &Ingest, key: &str, addr: &str) -> Result<(), String> {
+    if !ingest.enable || !ingest.auth.enable {
+        return Err("Ingest authentication is not enabled".to_string());
+    }
+
+    if !ip_allowed(addr, &ingest.auth.allow) {
+        return Err(format!("Address {addr} is not allowed"));
+    }
+
+    if !is_valid_key(id, ingest, key) {
+        return Err("Invalid stream key".to_string());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::config::Ingest;
+    use crate::utils::config::IngestAuth;
+
+    #[test]
+    fn stream_key_rotation() {
+        let (param, keys) = rotate_url_keys(
+            "-f live_flv -listen 1 -i rtmp://127.0.0.1:1936/live/stream",
+            true,
+            |_| true,
+        );
+
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].0, "stream");
+        assert_eq!(
+            param,
+            format!(
+                "-f live_flv -listen 1 -i rtmp://127.0.0.1:1936/live/{}",
+                keys[0].1
+            )
+        );
+
+        // only local targets are changed
+        let (param, keys) = rotate_url_keys(
+            "-f flv rtmp://localhost/live/abc -f flv rtmp://example.org/app/xyz",
+            false,
+            is_local_host,
+        );
+
+        assert_eq!(keys.len(), 1);
+        assert!(param.ends_with("rtmp://example.org/app/xyz"));
+
+        add_grace_key(99, "oldkey".to_string(), std::time::Duration::from_secs(60));
+
+        assert!(is_grace_stream(
+            99,
+            "[rtmp @ 0x0] Unexpected stream live/oldkey, expecting live/newkey"
+        ));
+        assert!(!is_grace_stream(
+            99,
+            "[rtmp @ 0x0] Unexpected stream live/other, expecting live/newkey"
+        ));
+    }
+
+    #[test]
+    fn ingest_publisher_auth() {
+        let allow = vec![
+            "10.0.0.0/8".to_string(),
+            "2001:db8::/32".to_string(),
+            "203.0.113.10".to_string(),
+        ];
+
+        assert!(ip_allowed("10.20.30.40", &allow));
+        assert!(ip_allowed("::ffff:10.1.1.1", &allow));
+        assert!(ip_allowed("2001:db8::1", &allow));
+        assert!(ip_allowed("203.0.113.10", &allow));
+        assert!(!ip_allowed("203.0.113.11", &allow));
+        assert!(!ip_allowed("not-an-ip", &allow));
+        assert!(ip_allowed("198.51.100.1", &[]));
+
+        assert_eq!(
+            url_key("-f live_flv -listen 1 -i rtmp://0.0.0.0:1936/live/urlkey"),
+            Some("urlkey".to_string())
+        );
+
+        let mut ingest = Ingest {
+            enable: true,
+            input_param: "-f live_flv -listen 1 -i rtmp://0.0.0.0:1936/live/urlkey".to_string(),
+            auth: IngestAuth {
+                enable: true,
+                keys: vec!["studio".to_string()],
+                allow: vec!["192.168.0.0/16".to_string()],
+            },
+            ..Default::default()
+        };
+
+        assert!(check_publisher(98, &ingest, "urlkey", "192.168.1.5").is_ok());
+        assert!(check_publisher(98, &ingest, "studio", "192.168.1.5").is_ok());
+        assert!(check_publisher(98, &ingest, "wrong", "192.168.1.5").is_err());
+        assert!(check_publisher(98, &ingest, "", "192.168.1.5").is_err());
+        assert!(check_publisher(98, &ingest, "studio", "10.0.0.1").is_err());
+
+        add_grace_key(98, "oldkey".to_string(), std::time::Duration::from_secs(60));
+
+        assert!(check_publisher(98, &ingest, "oldkey", "192.168.1.5").is_ok());
+        assert!(is_auth_stream(
+            &ingest,
+            "[rtmp @ 0x0] Unexpected stream live/studio, expecting live/urlkey"
+        ));
+
+        ingest.auth.enable = false;
+
+        assert!(check_publisher(98, &ingest, "urlkey", "192.168.1.5").is_err());
+        assert!(!is_auth_stream(
+            &ingest,
+            "[rtmp @ 0x0] Unexpected stream live/studio, expecting live/urlkey"
+        ));
+    }
+}
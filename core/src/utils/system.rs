@@ -1,10 +1,11 @@
-use std::fmt;
+use std::{fmt, process::Command};
 
 use local_ip_address::list_afinet_netifas;
 use serde::Serialize;
 use sysinfo::System;
 
-use crate::utils::config::PlayoutConfig;
+use crate::player::output::decklink::devices;
+use crate::utils::{config::PlayoutConfig, files::storage_usage, gpu::gpu_channels};
 use crate::{DISKS, NETWORKS, SYS};
 
 const IGNORE_INTERFACES: [&str; 7] = ["docker", "lxdbr", "tab", "tun", "virbr", "veth", "vnet"];
@@ -20,6 +21,10 @@ pub struct Storage {
     pub path: String,
     pub total: u64,
     pub used: u64,
+    /// Channel storage quota in bytes, 0 when not limited.
+    pub quota: u64,
+    /// Used bytes in the channel storage folder, only counted when a quota is set.
+    pub quota_used: u64,
 }
 
 #[derive(Debug, Serialize)]
@@ -60,6 +65,20 @@ pub struct Swap {
     pub free: u64,
 }
 
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Gpu {
+    pub index: usize,
+    pub name: String,
+    pub memory_total: u64,
+    pub memory_used: u64,
+    /// Usage in percent.
+    pub usage: f32,
+    /// Running hardware encoder sessions.
+    pub encoder_sessions: u32,
+    /// Channels, which run on the GPU.
+    pub channels: Vec<i32>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct SystemStat {
     pub cpu: Cpu,
@@ -69,6 +88,10 @@ pub struct SystemStat {
     pub storage: Storage,
     pub swap: Swap,
     pub system: MySystem,
+    /// Installed Decklink cards.
+    pub decklink: Vec<String>,
+    /// Installed NVIDIA GPUs.
+    pub gpus: Vec<Gpu>,
 }
 
 impl fmt::Display for SystemStat {
@@ -77,6 +100,52 @@ impl fmt::Display for SystemStat {
     }
 }
 
+/// Parse the CSV lines from `nvidia-smi`, values which are not available are 0.
+pub fn parse_gpus(output: &str) -> Vec<Gpu> {
+    let mut gpus = vec![];
+
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+
+        if fields.len() < 6 {
+            continue;
+        }
+
+        let Ok(index) = fields[0].parse() else {
+            continue;
+        };
+        let mib = |value: &str| value.parse::<u64>().unwrap_or_default() * 1024 * 1024;
+
+        gpus.push(Gpu {
+            index,
+            name: fields[1].to_string(),
+            memory_total: mib(fields[2]),
+            memory_used: mib(fields[3]),
+            usage: fields[4].parse().unwrap_or_default(),
+            encoder_sessions: fields[5].parse().unwrap_or_default(),
+            channels: vec![],
+        });
+    }
+
+    gpus
+}
+
+/// Installed NVIDIA GPUs, empty when `nvidia-smi` is not available.
+pub fn gpus() -> Vec<Gpu> {
+    match Command::new("nvidia-smi")
+        .args([
+            "--query-gpu=index,name,memory.total,memory.used,utilization.gpu,encoder.stats.sessionCount",
+            "--format=csv,noheader,nounits",
+        ])
+        .output()
+    {
+        Ok(output) if output.status.success() => {
+            parse_gpus(&String::from_utf8_lossy(&output.stdout))
+        }
+        _ => vec![],
+    }
+}
+
 pub fn stat(config: &PlayoutConfig) -> SystemStat {
     let mut disks = DISKS.lock().unwrap();
     let mut networks = NETWORKS.lock().unwrap();
@@ -126,6 +195,11 @@ pub fn stat(config: &PlayoutConfig) -> SystemStat {
         }
     }
 
+    if config.channel.storage_quota > 0 {
+        storage.quota = config.channel.storage_quota;
+        storage.quota_used = storage_usage(&config.channel.storage);
+    }
+
     let load_avg = System::load_average();
     let load = Load {
         one: load_avg.one,
@@ -157,6 +231,10 @@ pub fn stat(config: &PlayoutConfig) -> SystemStat {
         free: sys.free_swap(),
     };
 
+    let mut gpus = gpus();
+
+    gpu_channels(&mut gpus);
+
     let system = MySystem {
         name: System::name(),
         kernel: System::kernel_version(),
@@ -172,5 +250,7 @@ pub fn stat(config: &PlayoutConfig) -> SystemStat {
         storage,
         swap,
         system,
+        decklink: devices(),
+        gpus,
     }
 }
@@ -0,0 +1,358 @@
+/*
+Thumbnails and storyboard sprites for media files.
+
+Thumbnails are single JPEG frames, storyboards are sprite sheets with a frame every few seconds,
+for the scrubbing preview in the web player. Both are cached in the hidden `.thumbnails` folder
+from the channel storage, the cache name contains the modification time from the source,
+so changed files get new images.
+
+HLS playlists (`.m3u8`) are read from the public folder, for a preview from the running stream.
+Their thumbnails are renewed after a few seconds.
+
+*/
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{LazyLock, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use log::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use walkdir::{DirEntry, WalkDir};
+
+use crate::player::utils::{file_extension, MediaProbe};
+use crate::utils::{
+    config::{PlayoutConfig, IMAGE_FORMAT},
+    errors::ServiceError,
+    logging::Target,
+};
+
+pub const CACHE_FOLDER: &str = ".thumbnails";
+pub const THUMB_WIDTH: i64 = 320;
+pub const TILE_WIDTH: i64 = 160;
+/// Maximal number of frames in a storyboard.
+pub const SPRITE_FRAMES: i64 = 100;
+pub const SPRITE_COLUMNS: i64 = 10;
+/// Age in seconds, until a thumbnail from a HLS playlist is renewed.
+const LIVE_AGE: u64 = 10;
+
+/// Channels with a running thumbnail job.
+static RUNNING: LazyLock<Mutex<HashSet<i32>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Layout from a storyboard sprite, the frame from second `n * interval` is on tile `n`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Storyboard {
+    pub interval: f64,
+    pub count: i64,
+    pub columns: i64,
+    pub rows: i64,
+    pub width: i64,
+    pub height: i64,
+}
+
+impl Storyboard {
+    /// Frame interval and tiles for the file duration and picture size.
+    pub fn new(duration: f64, width: i64, height: i64) -> Self {
+        let interval = (duration / SPRITE_FRAMES as f64).max(2.0).ceil();
+        let count = ((duration / interval).ceil() as i64).clamp(1, SPRITE_FRAMES);
+        let columns = count.min(SPRITE_COLUMNS);
+        let rows = (count + columns - 1) / columns;
+        let tile_height = if width > 0 && height > 0 {
+            (TILE_WIDTH * height / width + 1) / 2 * 2
+        } else {
+            TILE_WIDTH * 9 / 16
+        };
+
+        Self {
+            interval,
+            count,
+            columns,
+            rows,
+            width: TILE_WIDTH,
+            height: tile_height,
+        }
+    }
+}
+
+/// Cache file name from the source, its modification time and the variant (time or sprite).
+pub fn cache_name(source: &Path, modified: u64, variant: &str, ext: &str) -> String {
+    let hash = Sha256::digest(format!("{}|{modified}|{variant}", source.display()).as_bytes());
+
+    format!("{}.{ext}", hex::encode(&hash[..16]))
+}
+
+fn is_playlist(path: &Path) -> bool {
+    file_extension(path).is_some_and(|e| e.eq_ignore_ascii_case("m3u8"))
+}
+
+/// Modification time in seconds, for the cache names.
+pub fn modified(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+pub fn cache_dir(config: &PlayoutConfig) -> Result<PathBuf, ServiceError> {
+    let dir = config.channel.storage.join(CACHE_FOLDER);
+
+    if !dir.is_dir() {
+        fs::create_dir_all(&dir)?;
+    }
+
+    Ok(dir)
+}
+
+/// Cached file is there and, for HLS playlists, not too old.
+fn is_cached(path: &Path, live: bool) -> bool {
+    let Ok(meta) = fs::metadata(path) else {
+        return false;
+    };
+
+    !live
+        || meta
+            .modified()
+            .ok()
+            .and_then(|m| SystemTime::now().duration_since(m).ok())
+            .is_some_and(|age| age < Duration::from_secs(LIVE_AGE))
+}
+
+pub fn run_ffmpeg(args: &[String]) -> Result<(), ServiceError> {
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-nostats", "-v", "error", "-y"])
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(ServiceError::Conflict(format!(
+            "ffmpeg failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(())
+}
+
+fn duration(probe: &MediaProbe) -> f64 {
+    probe
+        .format
+        .duration
+        .as_ref()
+        .and_then(|d| d.parse().ok())
+        .unwrap_or_default()
+}
+
+/// JPEG thumbnail from the frame at `time`, without time from 10% of the file.
+pub fn thumbnail(
+    config: &PlayoutConfig,
+    source: &Path,
+    time: Option<f64>,
+) -> Result<PathBuf, ServiceError> {
+    let live = is_playlist(source);
+    let src = source.to_string_lossy().to_string();
+    let variant = time.map_or("auto".to_string(), |t| format!("{t:.2}"));
+    // the playlist changes with every segment, its thumbnail keeps the name and gets renewed
+    let mtime = if live { 0 } else { modified(source) };
+    let target = cache_dir(config)?.join(cache_name(source, mtime, &variant, "jpg"));
+
+    if is_cached(&target, live) {
+        return Ok(target);
+    }
+
+    let seek = match time {
+        Some(t) => t.max(0.0),
+        None if live => 0.0,
+        None => {
+            let probe = MediaProbe::new(&src).map_err(|e| ServiceError::Conflict(e.to_string()))?;
+
+            duration(&probe) * 0.1
+        }
+    };
+
+    let mut args = vec![];
+
+    if live {
+        // newest segment
+        args.extend(["-live_start_index".to_string(), "-1".to_string()]);
+    }
+
+    args.extend([
+        "-ss".to_string(),
+        format!("{seek:.3}"),
+        "-i".to_string(),
+        src,
+        "-frames:v".to_string(),
+        "1".to_string(),
+        "-vf".to_string(),
+        format!("scale={THUMB_WIDTH}:-2"),
+        "-q:v".to_string(),
+        "4".to_string(),
+        target.to_string_lossy().to_string(),
+    ]);
+
+    run_ffmpeg(&args)?;
+
+    Ok(target)
+}
+
+/// Storyboard sprite from the file, returns the sprite path and its layout.
+pub fn storyboard(
+    config: &PlayoutConfig,
+    source: &Path,
+) -> Result<(PathBuf, Storyboard), ServiceError> {
+    if is_playlist(source) {
+        return Err(ServiceError::BadRequest(
+            "Storyboards are only for media files".to_string(),
+        ));
+    }
+
+    let dir = cache_dir(config)?;
+    let mtime = modified(source);
+    let sprite = dir.join(cache_name(source, mtime, "sprite", "jpg"));
+    let layout_file = dir.join(cache_name(source, mtime, "sprite", "json"));
+
+    if sprite.is_file() {
+        if let Ok(layout) = fs::read_to_string(&layout_file)
+            .map_err(ServiceError::from)
+            .and_then(|l| serde_json::from_str(&l).map_err(ServiceError::from))
+        {
+            return Ok((sprite, layout));
+        }
+    }
+
+    let src = source.to_string_lossy().to_string();
+    let probe = MediaProbe::new(&src).map_err(|e| ServiceError::Conflict(e.to_string()))?;
+    let Some(video) = probe.video_streams.first() else {
+        return Err(ServiceError::BadRequest("File has no video".to_string()));
+    };
+    let layout = Storyboard::new(
+        duration(&probe),
+        video.width.unwrap_or_default(),
+        video.height.unwrap_or_default(),
+    );
+
+    run_ffmpeg(&[
+        "-i".to_string(),
+        src,
+        "-frames:v".to_string(),
+        "1".to_string(),
+        "-vf".to_string(),
+        format!(
+            "fps=1/{},scale={}:{},tile={}x{}",
+            layout.interval, layout.width, layout.height, layout.columns, layout.rows
+        ),
+        "-q:v".to_string(),
+        "5".to_string(),
+        sprite.to_string_lossy().to_string(),
+    ])?;
+
+    fs::write(&layout_file, serde_json::to_string(&layout)?)?;
+
+    Ok((sprite, layout))
+}
+
+/// Media files from a folder, or the single file. Hidden files and images are skipped.
+pub fn media_files(config: &PlayoutConfig, path: &Path) -> Vec<PathBuf> {
+    WalkDir::new(path)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .map(DirEntry::into_path)
+        .filter(|p| !p.to_string_lossy().contains("/."))
+        .filter(|p| {
+            file_extension(p).is_some_and(|e| {
+                let ext = e.to_lowercase();
+
+                config.storage.extensions.contains(&ext) && !IMAGE_FORMAT.contains(&ext.as_str())
+            })
+        })
+        .collect()
+}
+
+/// Create thumbnails and storyboards for all media files in background, returns the number of files.
+pub fn start_thumbnails(config: &PlayoutConfig, path: &Path) -> Result<usize, ServiceError> {
+    let id = config.general.channel_id;
+    let files = media_files(config, path);
+    let count = files.len();
+    let config = config.clone();
+
+    if !RUNNING.lock().unwrap().insert(id) {
+        return Err(ServiceError::Conflict(
+            "Thumbnail generation is already running".to_string(),
+        ));
+    }
+
+    info!(target: Target::file_mail(), channel = id; "Create thumbnails for <yellow>{count}</> files");
+
+    tokio::spawn(async move {
+        for file in files {
+            let config = config.clone();
+            let source = file.clone();
+
+            let result = tokio::task::spawn_blocking(move || {
+                thumbnail(&config, &source, None)?;
+                storyboard(&config, &source)
+            })
+            .await;
+
+            match result {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => {
+                    warn!(target: Target::file_mail(), channel = id;
+                        "Thumbnails of <b><magenta>{}</></b> failed: {e}", file.display()
+                    );
+                }
+                Err(e) => {
+                    error!(target: Target::file_mail(), channel = id; "{e:?}");
+                }
+            }
+        }
+
+        RUNNING.lock().unwrap().remove(&id);
+
+        info!(target: Target::file_mail(), channel = id; "Thumbnails finished");
+    });
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn thumbnail_storyboard() {
+        let short = Storyboard::new(30.0, 1920, 1080);
+
+        assert_eq!(short.interval, 2.0);
+        assert_eq!(short.count, 15);
+        assert_eq!((short.columns, short.rows), (10, 2));
+        assert_eq!((short.width, short.height), (160, 90));
+
+        let long = Storyboard::new(3600.0, 720, 576);
+
+        assert_eq!(long.interval, 36.0);
+        assert_eq!(long.count, 100);
+        assert_eq!((long.columns, long.rows), (10, 10));
+        assert_eq!(long.height, 128);
+
+        let source = Path::new("/media/a.mp4");
+        let name = cache_name(source, 100, "sprite", "jpg");
+
+        assert!(name.ends_with(".jpg"));
+        assert_eq!(name, cache_name(source, 100, "sprite", "jpg"));
+        assert_ne!(name, cache_name(source, 101, "sprite", "jpg"));
+        assert_ne!(name, cache_name(source, 100, "auto", "jpg"));
+    }
+}
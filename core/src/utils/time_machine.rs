@@ -0,0 +1,206 @@
+/// These functions are made for testing purposes.
+/// It allows, with a hidden command line argument, to override the time in this program.
+/// It is like a time machine where you can fake the time and make the hole program think it is running in the future or past.
+///
+/// All time requests go through a global [`Clock`]. At default this is the [`SystemClock`],
+/// with `--fake-time` an [`OffsetClock`] and with `--time-speed` a [`SimulatedClock`] is used.
+/// The player loops wait with [`clock_sleep`], so in simulation they run as fast as the clock.
+/// With `--clock-reference` the [`ReferenceClock`] follows an external time source.
+/// Tests can install a [`ManualClock`] and move the time forward by hand.
+use std::{
+    process,
+    sync::{Arc, LazyLock, Mutex, RwLock},
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::{prelude::*, TimeDelta};
+
+/// Source of the current time.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Local>;
+
+    /// How fast the clock runs compared to the real time.
+    fn speed(&self) -> f64 {
+        1.0
+    }
+}
+
+/// Real system time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+/// System time, shifted by a fixed offset.
+#[derive(Clone, Copy, Debug)]
+pub struct OffsetClock {
+    diff: TimeDelta,
+}
+
+impl OffsetClock {
+    pub fn new(start: DateTime<Local>) -> Self {
+        Self {
+            diff: Local::now() - start,
+        }
+    }
+}
+
+impl Clock for OffsetClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now() - self.diff
+    }
+}
+
+/// Time which starts at a given point and runs `speed` times faster than the real time.
+#[derive(Clone, Copy, Debug)]
+pub struct SimulatedClock {
+    start: DateTime<Local>,
+    instant: Instant,
+    speed: f64,
+}
+
+impl SimulatedClock {
+    pub fn new(start: DateTime<Local>, speed: f64) -> Self {
+        Self {
+            start,
+            instant: Instant::now(),
+            speed: speed.max(0.001),
+        }
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> DateTime<Local> {
+        let elapsed = self.instant.elapsed().as_secs_f64() * self.speed;
+
+        self.start + TimeDelta::milliseconds((elapsed * 1000.0) as i64)
+    }
+
+    fn speed(&self) -> f64 {
+        self.speed
+    }
+}
+
+/// System time, corrected by the offset to an external reference clock.
+/// The offset is measured in background, see `utils::clock_reference`.
+#[derive(Clone, Debug, Default)]
+pub struct ReferenceClock {
+    offset: Arc<RwLock<TimeDelta>>,
+}
+
+impl ReferenceClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Seconds, which the reference is ahead of the system time.
+    pub fn set_offset(&self, offset: f64) {
+        *self.offset.write().unwrap() = TimeDelta::microseconds((offset * 1_000_000.0) as i64);
+    }
+}
+
+impl Clock for ReferenceClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now() + *self.offset.read().unwrap()
+    }
+}
+
+/// Time which only changes when it is set or advanced by hand.
+#[derive(Debug)]
+pub struct ManualClock {
+    time: Mutex<DateTime<Local>>,
+}
+
+impl ManualClock {
+    pub fn new(start: DateTime<Local>) -> Self {
+        Self {
+            time: Mutex::new(start),
+        }
+    }
+
+    pub fn set(&self, time: DateTime<Local>) {
+        *self.time.lock().unwrap() = time;
+    }
+
+    pub fn advance(&self, delta: TimeDelta) {
+        let mut time = self.time.lock().unwrap();
+        *time += delta;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Local> {
+        *self.time.lock().unwrap()
+    }
+}
+
+impl<C: Clock> Clock for Arc<C> {
+    fn now(&self) -> DateTime<Local> {
+        (**self).now()
+    }
+
+    fn speed(&self) -> f64 {
+        (**self).speed()
+    }
+}
+
+static CLOCK: LazyLock<RwLock<Box<dyn Clock>>> =
+    LazyLock::new(|| RwLock::new(Box::new(SystemClock)));
+
+/// Replace the global clock.
+pub fn set_clock(clock: impl Clock + 'static) {
+    *CLOCK.write().unwrap() = Box::new(clock);
+}
+
+/// Go back to the real system time.
+pub fn reset_clock() {
+    set_clock(SystemClock);
+}
+
+fn parse_time(time: &str) -> DateTime<Local> {
+    match DateTime::parse_from_rfc3339(time) {
+        Ok(t) => t.with_timezone(&Local),
+        Err(_) => {
+            eprintln!(
+                "Error: Invalid date format for --fake-time, use time with offset in: 2024-10-27T00:59:00+02:00"
+            );
+            process::exit(1);
+        }
+    }
+}
+
+// Set the mock time offset if `--fake-time` argument is provided
+pub fn set_mock_time(fake_time: &Option<String>) {
+    if let Some(time) = fake_time {
+        set_clock(OffsetClock::new(parse_time(time)));
+    }
+}
+
+// Run the time accelerated if `--time-speed` argument is provided, starting at `--fake-time` or now
+pub fn set_simulation(fake_time: &Option<String>, speed: Option<f64>) {
+    match speed {
+        Some(speed) if speed != 1.0 => {
+            let start = fake_time.as_deref().map_or_else(Local::now, parse_time);
+
+            set_clock(SimulatedClock::new(start, speed));
+        }
+        _ => set_mock_time(fake_time),
+    }
+}
+
+// Function to get the current time, using either real, mock or simulated time
+pub fn time_now() -> DateTime<Local> {
+    CLOCK.read().unwrap().now()
+}
+
+/// Sleep for the given duration in clock time, in simulation mode this is shorter than in real time.
+pub fn clock_sleep(duration: Duration) {
+    let speed = CLOCK.read().unwrap().speed();
+
+    thread::sleep(duration.div_f64(speed));
+}
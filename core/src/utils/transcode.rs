@@ -0,0 +1,492 @@
+/*
+Background transcoding to the house format of a channel.
+
+Jobs are stored in the database and one worker runs them one after the other, oldest first.
+The results are proxies in the hidden `.proxies` folder of the channel storage, with the relative
+path of the source and the extension of the house format: `clips/a.mov` -> `.proxies/clips/a.mov.mp4`.
+The player plays the proxy instead of the source, as long as the proxy is newer than the source.
+Jobs with an out point write a trimmed copy instead, see the trim API.
+
+Jobs which were running when the engine stopped are queued again at the next start.
+
+*/
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::{Arc, LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use log::*;
+use sqlx::{Pool, Sqlite};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, BufReader},
+    process::Command,
+    sync::Notify,
+    time::{sleep, timeout},
+};
+
+use crate::db::{handles, models::TranscodeJob};
+use crate::player::{controller::ChannelController, utils::MediaProbe};
+use crate::utils::{
+    config::PlayoutConfig, errors::ServiceError, logging::Target, thumbnails::media_files,
+    time_machine::time_now,
+};
+
+pub const PROXY_FOLDER: &str = ".proxies";
+
+pub const QUEUED: &str = "queued";
+pub const RUNNING: &str = "running";
+pub const DONE: &str = "done";
+pub const FAILED: &str = "failed";
+pub const CANCELED: &str = "canceled";
+
+/// Interval in which the progress of a running job is saved.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Running jobs, which have to be stopped.
+static CANCEL: LazyLock<Mutex<HashSet<i32>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Wakes the worker, when new jobs are queued.
+static WAKE: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+fn timestamp() -> String {
+    time_now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// File extension from the house format.
+pub fn house_extension(config: &PlayoutConfig) -> &'static str {
+    if config.processing.audio_only {
+        "m4a"
+    } else {
+        "mp4"
+    }
+}
+
+/// ffmpeg output arguments for the house format, without the target.
+pub fn house_args(config: &PlayoutConfig) -> Vec<String> {
+    let processing = &config.processing;
+    let format = &processing.house_format;
+    let mut args = vec![];
+
+    if processing.audio_only {
+        args.push("-vn".to_string());
+    } else {
+        let (w, h) = (processing.width, processing.height);
+
+        args.extend([
+            "-map".to_string(),
+            "0:v:0".to_string(),
+            "-map".to_string(),
+            "0:a:0?".to_string(),
+            "-vf".to_string(),
+            format!(
+                "scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={}",
+                processing.fps
+            ),
+            "-c:v".to_string(),
+            format.video_codec.clone(),
+            "-preset".to_string(),
+            format.preset.clone(),
+            "-crf".to_string(),
+            format.crf.to_string(),
+            "-pix_fmt".to_string(),
+            "yuv420p".to_string(),
+        ]);
+    }
+
+    if format.loudness != 0.0 {
+        args.extend([
+            "-af".to_string(),
+            format!(
+                "loudnorm=I={}:TP={}:LRA=11",
+                format.loudness, format.true_peak
+            ),
+        ]);
+    }
+
+    args.extend([
+        "-c:a".to_string(),
+        format.audio_codec.clone(),
+        "-b:a".to_string(),
+        format.audio_bitrate.clone(),
+        "-ar".to_string(),
+        "48000".to_string(),
+        "-ac".to_string(),
+        "2".to_string(),
+        "-movflags".to_string(),
+        "+faststart".to_string(),
+    ]);
+
+    args
+}
+
+/// Temporary file in the target folder, while ffmpeg is writing.
+fn part_path(target: &Path) -> PathBuf {
+    let stem = target
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let ext = target
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    target.with_file_name(format!(".{stem}.part.{ext}"))
+}
+
+/// Transcode to the house format and wait until ffmpeg is finished.
+pub fn transcode(config: &PlayoutConfig, source: &Path, target: &Path) -> Result<(), ServiceError> {
+    let part = part_path(target);
+    let output = std::process::Command::new("ffmpeg")
+        .args(["-hide_banner", "-nostats", "-v", "error", "-y", "-i"])
+        .arg(source)
+        .args(house_args(config))
+        .arg(&part)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()?;
+
+    if !output.status.success() {
+        let _ = fs::remove_file(&part);
+
+        return Err(ServiceError::Conflict(format!(
+            "Transcoding failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    fs::rename(&part, target)?;
+
+    Ok(())
+}
+
+/// Proxy path from a file in the channel storage, files outside of the storage have no proxy.
+pub fn proxy_path(config: &PlayoutConfig, source: &Path) -> Option<PathBuf> {
+    let storage = &config.channel.storage;
+    let relative = source.strip_prefix(storage).ok()?;
+
+    if relative.as_os_str().is_empty() || relative.starts_with(PROXY_FOLDER) {
+        return None;
+    }
+
+    Some(storage.join(PROXY_FOLDER).join(format!(
+        "{}.{}",
+        relative.display(),
+        house_extension(config)
+    )))
+}
+
+/// Proxy, which is used instead of the source, when it exists and is newer than the source.
+pub fn preferred_source(config: &PlayoutConfig, source: &str) -> Option<String> {
+    let source = Path::new(source);
+    let proxy = proxy_path(config, source)?;
+    let proxy_time = fs::metadata(&proxy).and_then(|m| m.modified()).ok()?;
+    let source_time = fs::metadata(source).and_then(|m| m.modified()).ok()?;
+
+    (proxy_time >= source_time).then(|| proxy.to_string_lossy().to_string())
+}
+
+/// Percent from a line of `ffmpeg -progress`.
+pub fn parse_progress(line: &str, duration: f64) -> Option<f64> {
+    let time: f64 = line.strip_prefix("out_time_us=")?.trim().parse().ok()?;
+
+    if duration <= 0.0 {
+        return None;
+    }
+
+    Some((time / 1_000_000.0 / duration * 100.0).clamp(0.0, 100.0))
+}
+
+/// Queue jobs for all media files from a folder, or the single file.
+/// Files which are queued or running already are skipped.
+pub async fn submit(
+    pool: &Pool<Sqlite>,
+    config: &PlayoutConfig,
+    path: &Path,
+) -> Result<Vec<TranscodeJob>, ServiceError> {
+    let id = config.general.channel_id;
+    let open: HashSet<String> = handles::select_transcode_jobs(pool, id, None)
+        .await?
+        .into_iter()
+        .filter(|j| j.status == QUEUED || j.status == RUNNING)
+        .map(|j| j.source)
+        .collect();
+    let mut jobs = vec![];
+
+    for file in media_files(config, path) {
+        let source = file.to_string_lossy().to_string();
+
+        if open.contains(&source) {
+            continue;
+        }
+
+        let Some(target) = proxy_path(config, &file) else {
+            continue;
+        };
+
+        let job = TranscodeJob {
+            channel_id: id,
+            source,
+            target: target.to_string_lossy().to_string(),
+            status: QUEUED.to_string(),
+            created: timestamp(),
+            ..Default::default()
+        };
+
+        jobs.push(handles::insert_transcode_job(pool, &job).await?);
+    }
+
+    if !jobs.is_empty() {
+        info!(target: Target::file_mail(), channel = id; "Queue <yellow>{}</> transcoding jobs", jobs.len());
+
+        WAKE.notify_one();
+    }
+
+    Ok(jobs)
+}
+
+/// Queue a job, which writes the part between the in and out point to the target.
+pub async fn submit_trim(
+    pool: &Pool<Sqlite>,
+    config: &PlayoutConfig,
+    source: &Path,
+    target: &Path,
+    seek: f64,
+    out: f64,
+) -> Result<TranscodeJob, ServiceError> {
+    let id = config.general.channel_id;
+    let job = TranscodeJob {
+        channel_id: id,
+        source: source.to_string_lossy().to_string(),
+        target: target.to_string_lossy().to_string(),
+        status: QUEUED.to_string(),
+        seek,
+        out,
+        created: timestamp(),
+        ..Default::default()
+    };
+    let job = handles::insert_transcode_job(pool, &job).await?;
+
+    info!(target: Target::file_mail(), channel = id; "Queue trimmed copy of <b><magenta>{}</></b>", job.source);
+
+    WAKE.notify_one();
+
+    Ok(job)
+}
+
+/// Cancel a queued or running job, finished jobs are removed from the list.
+pub async fn cancel(pool: &Pool<Sqlite>, channel: i32, id: i32) -> Result<String, ServiceError> {
+    let job = handles::select_transcode_job(pool, channel, id).await?;
+
+    match job.status.as_str() {
+        QUEUED => {
+            let result =
+                handles::switch_transcode_status(pool, id, QUEUED, CANCELED, &timestamp()).await?;
+
+            // worker took the job in between
+            if result.rows_affected() == 0 {
+                CANCEL.lock().unwrap().insert(id);
+            }
+
+            Ok("Cancel transcoding job".to_string())
+        }
+        RUNNING => {
+            CANCEL.lock().unwrap().insert(id);
+
+            Ok("Cancel transcoding job".to_string())
+        }
+        _ => {
+            handles::delete_transcode_job(pool, channel, id).await?;
+
+            Ok("Delete transcoding job".to_string())
+        }
+    }
+}
+
+/// Run ffmpeg for the job and save the progress, until it is finished or canceled.
+async fn run_job(
+    pool: &Pool<Sqlite>,
+    config: &PlayoutConfig,
+    job: &mut TranscodeJob,
+) -> Result<(), ServiceError> {
+    let source = job.source.clone();
+    let target = PathBuf::from(&job.target);
+    let part = part_path(&target);
+
+    if !Path::new(&source).is_file() {
+        return Err(ServiceError::Conflict("Source not exists".to_string()));
+    }
+
+    let mut args = vec![];
+    let duration: f64 = if job.out > 0.0 {
+        // seeking in the input is frame accurate, because the file gets encoded again
+        args.extend([
+            "-ss".to_string(),
+            format!("{:.6}", job.seek),
+            "-t".to_string(),
+            format!("{:.6}", job.out - job.seek),
+        ]);
+
+        job.out - job.seek
+    } else {
+        tokio::task::spawn_blocking(move || MediaProbe::new(&source))
+            .await?
+            .ok()
+            .and_then(|p| p.format.duration)
+            .and_then(|d| d.parse().ok())
+            .unwrap_or_default()
+    };
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut child = Command::new("ffmpeg")
+        .args(["-hide_banner", "-nostats", "-v", "error", "-y"])
+        .args(["-progress", "pipe:1"])
+        .args(args)
+        .arg("-i")
+        .arg(&job.source)
+        .args(house_args(config))
+        .arg(&part)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let mut stderr = child.stderr.take().unwrap();
+    let errors = tokio::spawn(async move {
+        let mut buffer = String::new();
+        let _ = stderr.read_to_string(&mut buffer).await;
+
+        buffer
+    });
+
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    let mut saved = Instant::now();
+    let mut canceled = false;
+
+    while let Some(line) = lines.next_line().await? {
+        if CANCEL.lock().unwrap().contains(&job.id) {
+            child.kill().await?;
+            canceled = true;
+            break;
+        }
+
+        if let Some(progress) = parse_progress(&line, duration) {
+            job.progress = (progress * 10.0).round() / 10.0;
+
+            if saved.elapsed() >= PROGRESS_INTERVAL {
+                job.updated = timestamp();
+                handles::update_transcode_job(pool, job).await?;
+                saved = Instant::now();
+            }
+        }
+    }
+
+    let status = child.wait().await?;
+    let errors = errors.await.unwrap_or_default();
+
+    if canceled || !status.success() {
+        let _ = fs::remove_file(&part);
+
+        if canceled {
+            return Err(ServiceError::Conflict("Canceled".to_string()));
+        }
+
+        return Err(ServiceError::Conflict(format!(
+            "Transcoding failed: {}",
+            errors.trim()
+        )));
+    }
+
+    fs::rename(&part, &target)?;
+
+    Ok(())
+}
+
+async fn process(
+    pool: &Pool<Sqlite>,
+    controllers: &Mutex<ChannelController>,
+    mut job: TranscodeJob,
+) {
+    let id = job.channel_id;
+
+    match handles::switch_transcode_status(pool, job.id, QUEUED, RUNNING, &timestamp()).await {
+        Ok(result) if result.rows_affected() == 1 => {}
+        // canceled in between
+        Ok(_) => return,
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "Transcoding job: {e}");
+            return;
+        }
+    }
+
+    job.status = RUNNING.to_string();
+
+    info!(target: Target::file_mail(), channel = id; "Transcode <b><magenta>{}</></b>", job.source);
+
+    let config = controllers
+        .lock()
+        .unwrap()
+        .get(id)
+        .map(|m| m.config.lock().unwrap().clone());
+
+    let result = match config {
+        Some(config) => run_job(pool, &config, &mut job).await,
+        None => Err(ServiceError::Conflict("Channel not exists".to_string())),
+    };
+    let canceled = CANCEL.lock().unwrap().remove(&job.id);
+
+    match result {
+        Ok(_) => {
+            info!(target: Target::file_mail(), channel = id; "Transcoding of <b><magenta>{}</></b> finished", job.source);
+
+            job.status = DONE.to_string();
+            job.progress = 100.0;
+        }
+        Err(_) if canceled => {
+            info!(target: Target::file_mail(), channel = id; "Transcoding of <b><magenta>{}</></b> canceled", job.source);
+
+            job.status = CANCELED.to_string();
+        }
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "Transcoding of <b><magenta>{}</></b> failed: {e}", job.source);
+
+            job.status = FAILED.to_string();
+            job.message = e.to_string();
+        }
+    }
+
+    job.updated = timestamp();
+
+    if let Err(e) = handles::update_transcode_job(pool, &job).await {
+        error!(target: Target::file_mail(), channel = id; "Transcoding job: {e}");
+    }
+}
+
+/// Run the queued jobs from all channels, one after the other.
+pub fn transcode_worker(pool: Pool<Sqlite>, controllers: Arc<Mutex<ChannelController>>) {
+    tokio::spawn(async move {
+        if let Err(e) = handles::requeue_transcode_jobs(&pool).await {
+            error!("Queue unfinished transcoding jobs: {e}");
+        }
+
+        loop {
+            match handles::select_next_transcode_job(&pool).await {
+                Ok(Some(job)) => process(&pool, &controllers, job).await,
+                Ok(None) => {
+                    let _ = timeout(Duration::from_secs(30), WAKE.notified()).await;
+                }
+                Err(e) => {
+                    error!("Transcoding queue: {e}");
+                    sleep(Duration::from_secs(30)).await;
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,222 @@
+/*
+Trash for removed files and folders.
+
+Removed items are moved to the hidden `.trash` folder in the channel storage.
+Every item gets its own folder, with the original file/folder and a `info.json`:
+
+.trash/
+    20241027120000-aB3dE9/
+        info.json
+        clip.mp4
+
+Items which are longer in the trash then the retention days, are deleted permanently.
+The trash does not count to the storage quota, but a restored item must fit in it again.
+
+*/
+
+use std::path::{Path, PathBuf};
+
+use chrono::{NaiveDateTime, TimeDelta};
+use log::*;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::utils::{
+    config::PlayoutConfig,
+    errors::ServiceError,
+    files::{check_quota, dir_size, norm_abs_path},
+    time_machine::time_now,
+};
+
+pub const TRASH_DIR: &str = ".trash";
+const INFO_FILE: &str = "info.json";
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TrashItem {
+    pub id: String,
+    /// Original path, relative to the storage.
+    pub source: String,
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub deleted: String,
+}
+
+fn trash_path(config: &PlayoutConfig) -> PathBuf {
+    config.channel.storage.join(TRASH_DIR)
+}
+
+fn item_path(config: &PlayoutConfig, id: &str) -> Result<PathBuf, ServiceError> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(ServiceError::BadRequest("Invalid trash item!".into()));
+    }
+
+    let path = trash_path(config).join(id);
+
+    if !path.is_dir() {
+        return Err(ServiceError::BadRequest(format!(
+            "Trash item {id} not exists!"
+        )));
+    }
+
+    Ok(path)
+}
+
+async fn read_info(dir: &Path) -> Result<TrashItem, ServiceError> {
+    let data = fs::read_to_string(dir.join(INFO_FILE)).await?;
+
+    Ok(serde_json::from_str(&data)?)
+}
+
+/// Move file or folder to the trash, instead of deleting it.
+pub async fn move_to_trash(
+    config: &PlayoutConfig,
+    source: &Path,
+    relative: &str,
+) -> Result<TrashItem, ServiceError> {
+    if source.starts_with(trash_path(config)) {
+        return Err(ServiceError::BadRequest(
+            "Source is already in trash!".into(),
+        ));
+    }
+
+    cleanup_trash(config).await;
+
+    let now = time_now();
+    let rand_string: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(6)
+        .map(char::from)
+        .collect();
+    let id = format!("{}-{rand_string}", now.format("%Y%m%d%H%M%S"));
+    let dir = trash_path(config).join(&id);
+    let name = source
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let is_dir = source.is_dir();
+    let size = if is_dir {
+        let path = source.to_path_buf();
+        tokio::task::spawn_blocking(move || dir_size(&path)).await?
+    } else {
+        fs::metadata(source).await?.len()
+    };
+
+    fs::create_dir_all(&dir).await?;
+
+    if let Err(e) = fs::rename(source, dir.join(&name)).await {
+        error!("{e}");
+        fs::remove_dir_all(&dir).await?;
+
+        return Err(ServiceError::BadRequest("Move to trash failed!".into()));
+    }
+
+    let item = TrashItem {
+        id,
+        source: relative.trim_start_matches('/').to_string(),
+        name,
+        is_dir,
+        size,
+        deleted: now.format(TIME_FORMAT).to_string(),
+    };
+
+    fs::write(dir.join(INFO_FILE), serde_json::to_string(&item)?).await?;
+
+    Ok(item)
+}
+
+/// All items in trash, newest first.
+pub async fn list_trash(config: &PlayoutConfig) -> Result<Vec<TrashItem>, ServiceError> {
+    cleanup_trash(config).await;
+
+    let mut items = vec![];
+    let trash = trash_path(config);
+
+    if !trash.is_dir() {
+        return Ok(items);
+    }
+
+    let mut entries = fs::read_dir(trash).await?;
+
+    while let Some(entry) = entries.next_entry().await? {
+        match read_info(&entry.path()).await {
+            Ok(item) => items.push(item),
+            Err(e) => warn!("Invalid trash item {:?}: {e}", entry.path()),
+        }
+    }
+
+    items.sort_by(|a, b| b.deleted.cmp(&a.deleted));
+
+    Ok(items)
+}
+
+/// Move item back to its original place.
+pub async fn restore_trash(config: &PlayoutConfig, id: &str) -> Result<TrashItem, ServiceError> {
+    let dir = item_path(config, id)?;
+    let item = read_info(&dir).await?;
+    let (target, _, _) = norm_abs_path(&config.channel.storage, &item.source)?;
+
+    if target.exists() {
+        return Err(ServiceError::Conflict("Target already exists!".into()));
+    }
+
+    check_quota(config, item.size).await?;
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+
+    fs::rename(dir.join(&item.name), &target).await?;
+    fs::remove_dir_all(dir).await?;
+
+    Ok(item)
+}
+
+/// Delete one item, or the whole trash, permanently.
+pub async fn purge_trash(config: &PlayoutConfig, id: Option<&str>) -> Result<(), ServiceError> {
+    match id {
+        Some(id) => fs::remove_dir_all(item_path(config, id)?).await?,
+        None => {
+            let trash = trash_path(config);
+
+            if trash.is_dir() {
+                fs::remove_dir_all(trash).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Delete items which are older then the retention days.
+pub async fn cleanup_trash(config: &PlayoutConfig) {
+    let days = config.storage.trash_retention;
+    let trash = trash_path(config);
+
+    if days <= 0 || !trash.is_dir() {
+        return;
+    }
+
+    let now = time_now().naive_local();
+    let Ok(mut entries) = fs::read_dir(&trash).await else {
+        return;
+    };
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let Ok(item) = read_info(&entry.path()).await else {
+            continue;
+        };
+
+        if NaiveDateTime::parse_from_str(&item.deleted, TIME_FORMAT)
+            .is_ok_and(|t| now - t > TimeDelta::days(days))
+        {
+            match fs::remove_dir_all(entry.path()).await {
+                Ok(_) => info!("Remove <b><magenta>{}</></b> from trash", item.source),
+                Err(e) => error!("Remove trash item failed: {e}"),
+            }
+        }
+    }
+}
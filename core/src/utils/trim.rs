@@ -0,0 +1,567 @@
+/*
+Trim suggestions from black and silence.
+
+The analysis decodes the files with blackdetect and silencedetect.
+Black or silent parts at the begin and at the end of a file are suggested as in/out points.
+Suggestions are stored in the database and can be applied to playlist items, which are not trimmed yet.
+
+Editors can also set in/out points by hand, as seconds or timecode, they are snapped to the frames
+of the file. The points are stored like a suggestion, or a trimmed copy is written by the transcoding
+queue. A low resolution preview shows the first and the last seconds of the trimmed part.
+
+*/
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{LazyLock, Mutex},
+};
+
+use log::*;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use walkdir::{DirEntry, WalkDir};
+
+use crate::db::{handles, models::TrimSuggestion};
+use crate::player::utils::{file_extension, fps_calc, is_close, JsonPlaylist, MediaProbe};
+use crate::utils::{
+    config::{PlayoutConfig, IMAGE_FORMAT},
+    errors::ServiceError,
+    logging::Target,
+    thumbnails::{cache_dir, cache_name, modified, run_ffmpeg},
+    time_machine::time_now,
+    transcode::house_extension,
+    watch_folder::unique_path,
+};
+
+/// Gap in seconds between black/silent parts, which still counts as connected.
+const GAP: f64 = 0.1;
+
+/// Seconds from the in and from the out point, which are shown in the preview.
+const PREVIEW_SIDE: f64 = 5.0;
+const PREVIEW_WIDTH: i64 = 320;
+
+/// Channels with a running analysis.
+static RUNNING: LazyLock<Mutex<HashSet<i32>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+fn value_after(line: &str, key: &str) -> Option<f64> {
+    line.split_once(key)
+        .and_then(|(_, v)| v.split_whitespace().next())
+        .and_then(|v| v.parse().ok())
+}
+
+/// Start and end from a blackdetect log line.
+pub fn parse_black(line: &str) -> Option<(f64, f64)> {
+    Some((
+        value_after(line, "black_start:")?,
+        value_after(line, "black_end:")?,
+    ))
+}
+
+/// Suggested in and out point, from black or silent parts of a file.
+/// Leading parts move the in point, trailing parts the out point.
+/// When less then one second remains, the whole file is suggested.
+pub fn suggest_trim(duration: f64, parts: &[(f64, f64)]) -> (f64, f64) {
+    let mut parts = parts.to_vec();
+    let mut seek = 0.0_f64;
+    let mut out = duration;
+
+    parts.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    for (start, end) in &parts {
+        if *start <= seek + GAP {
+            seek = seek.max(*end);
+        }
+    }
+
+    parts.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    for (start, end) in &parts {
+        if *end >= out - GAP {
+            out = out.min(*start);
+        }
+    }
+
+    if out - seek < 1.0 {
+        return (0.0, duration);
+    }
+
+    (
+        (seek * 100.0).round() / 100.0,
+        (out * 100.0).round() / 100.0,
+    )
+}
+
+/// Find black and silent parts in the file and suggest in/out points.
+/// Returns in, out and duration.
+pub fn analyze_file(source: &str) -> Result<(f64, f64, f64), ServiceError> {
+    let probe = MediaProbe::new(source).map_err(|e| ServiceError::Conflict(e.to_string()))?;
+    let duration: f64 = probe
+        .format
+        .duration
+        .and_then(|d| d.parse().ok())
+        .unwrap_or_default();
+
+    if duration <= 0.0 {
+        return Err(ServiceError::Conflict(format!(
+            "File has no duration: {source}"
+        )));
+    }
+
+    let mut args = vec!["-hide_banner", "-nostats", "-v", "info", "-i", source];
+
+    if !probe.video_streams.is_empty() {
+        args.extend(["-vf", "blackdetect=d=0.1:pix_th=0.10"]);
+    }
+
+    if !probe.audio_streams.is_empty() {
+        args.extend(["-af", "silencedetect=n=-50dB:d=0.5"]);
+    }
+
+    args.extend(["-f", "null", "-"]);
+
+    let output = Command::new("ffmpeg")
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()?;
+
+    let mut parts = vec![];
+    let mut silence_start = None;
+
+    for line in String::from_utf8_lossy(&output.stderr).lines() {
+        if let Some(part) = parse_black(line) {
+            parts.push(part);
+        } else if let Some(start) = value_after(line, "silence_start:") {
+            silence_start = Some(start);
+        } else if let Some(end) = value_after(line, "silence_end:") {
+            parts.push((silence_start.take().unwrap_or_default(), end));
+        }
+    }
+
+    // silence until the end of the file
+    if let Some(start) = silence_start {
+        parts.push((start, duration));
+    }
+
+    let (seek, out) = suggest_trim(duration, &parts);
+
+    Ok((seek, out, duration))
+}
+
+/// Media files from a folder, or the single file.
+fn media_files(config: &PlayoutConfig, path: &Path) -> Vec<PathBuf> {
+    WalkDir::new(path)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+        .map(DirEntry::into_path)
+        .filter(|p| !p.to_string_lossy().contains("/."))
+        .filter(|p| {
+            file_extension(p).is_some_and(|e| {
+                let ext = e.to_lowercase();
+
+                config.storage.extensions.contains(&ext) && !IMAGE_FORMAT.contains(&ext.as_str())
+            })
+        })
+        .collect()
+}
+
+/// Analyze all media files in background, returns the number of files.
+pub fn start_analysis(
+    pool: Pool<Sqlite>,
+    config: &PlayoutConfig,
+    path: &Path,
+) -> Result<usize, ServiceError> {
+    let id = config.general.channel_id;
+    let files = media_files(config, path);
+    let count = files.len();
+
+    if !RUNNING.lock().unwrap().insert(id) {
+        return Err(ServiceError::Conflict(
+            "Trim analysis is already running".to_string(),
+        ));
+    }
+
+    info!(target: Target::file_mail(), channel = id; "Start trim analysis for <yellow>{count}</> files");
+
+    tokio::spawn(async move {
+        for file in files {
+            let source = file.to_string_lossy().to_string();
+            let src = source.clone();
+
+            match tokio::task::spawn_blocking(move || analyze_file(&src)).await {
+                Ok(Ok((seek, out, duration))) => {
+                    let trim = TrimSuggestion {
+                        channel_id: id,
+                        source,
+                        seek,
+                        out,
+                        duration,
+                        updated: time_now().format("%Y-%m-%d %H:%M:%S").to_string(),
+                        ..Default::default()
+                    };
+
+                    if let Err(e) = handles::upsert_trim_suggestion(&pool, &trim).await {
+                        error!(target: Target::file_mail(), channel = id; "Save trim suggestion failed: {e}");
+                    }
+                }
+                Ok(Err(e)) => {
+                    warn!(target: Target::file_mail(), channel = id; "Trim analysis of <b><magenta>{source}</></b> failed: {e}");
+                }
+                Err(e) => {
+                    error!(target: Target::file_mail(), channel = id; "{e:?}");
+                }
+            }
+        }
+
+        RUNNING.lock().unwrap().remove(&id);
+
+        info!(target: Target::file_mail(), channel = id; "Trim analysis finished");
+    });
+
+    Ok(count)
+}
+
+/// Apply the suggestions to all playlist items, which play the whole file.
+/// Returns the number of trimmed items.
+pub fn apply_trim(
+    playlist: &mut JsonPlaylist,
+    suggestions: &HashMap<String, TrimSuggestion>,
+) -> usize {
+    let mut count = 0;
+
+    for item in &mut playlist.program {
+        let Some(trim) = suggestions.get(&item.source) else {
+            continue;
+        };
+
+        if item.seek == 0.0
+            && is_close(item.out, item.duration, 0.1)
+            && is_close(trim.duration, item.duration, 1.0)
+            && (trim.seek > 0.0 || trim.out < trim.duration)
+        {
+            item.seek = trim.seek;
+            item.out = trim.out;
+            count += 1;
+        }
+    }
+
+    count
+}
+
+/// In or out point, as seconds or as timecode: `HH:MM:SS.mmm` or `HH:MM:SS:FF` with frames.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum TrimPoint {
+    Seconds(f64),
+    Timecode(String),
+}
+
+impl TrimPoint {
+    /// Time in seconds on the start of the nearest frame.
+    pub fn seconds(&self, fps: f64) -> Option<f64> {
+        let time = match self {
+            Self::Seconds(time) => *time,
+            Self::Timecode(timecode) => parse_timecode(timecode, fps)?,
+        };
+
+        (time.is_finite() && time >= 0.0).then(|| snap_to_frame(time, fps))
+    }
+}
+
+/// Seconds from a timecode, with four parts the last one are frames.
+pub fn parse_timecode(timecode: &str, fps: f64) -> Option<f64> {
+    let parts: Vec<&str> = timecode.trim().split(':').collect();
+    let (clock, frames) = match parts.len() {
+        1..=3 => (&parts[..], None),
+        4 => (&parts[..3], Some(parts[3])),
+        _ => return None,
+    };
+    let mut time = 0.0;
+
+    for part in clock {
+        time = time * 60.0 + part.parse::<f64>().ok().filter(|v| *v >= 0.0)?;
+    }
+
+    if let Some(frames) = frames {
+        let frames: u32 = frames.parse().ok()?;
+
+        if fps <= 0.0 || f64::from(frames) >= fps.ceil() {
+            return None;
+        }
+
+        time += f64::from(frames) / fps;
+    }
+
+    Some(time)
+}
+
+/// Time on the start of the nearest frame, without frame rate on the nearest millisecond.
+pub fn snap_to_frame(time: f64, fps: f64) -> f64 {
+    if fps <= 0.0 {
+        return (time * 1000.0).round() / 1000.0;
+    }
+
+    ((time * fps).round() / fps * 1_000_000.0).round() / 1_000_000.0
+}
+
+/// Duration and frame rate from the file, audio files have no frame rate.
+pub fn media_timing(source: &str) -> Result<(f64, f64), ServiceError> {
+    let probe = MediaProbe::new(source).map_err(|e| ServiceError::Conflict(e.to_string()))?;
+    let duration: f64 = probe
+        .format
+        .duration
+        .and_then(|d| d.parse().ok())
+        .unwrap_or_default();
+    let fps = probe
+        .video_streams
+        .first()
+        .map(|v| fps_calc(&v.r_frame_rate, 0.0))
+        .unwrap_or_default();
+
+    if duration <= 0.0 {
+        return Err(ServiceError::Conflict(format!(
+            "File has no duration: {source}"
+        )));
+    }
+
+    Ok((duration, fps))
+}
+
+/// Frame accurate in and out point, both must be inside the file.
+pub fn trim_points(
+    seek: &TrimPoint,
+    out: &TrimPoint,
+    duration: f64,
+    fps: f64,
+) -> Result<(f64, f64), ServiceError> {
+    let seek = seek
+        .seconds(fps)
+        .ok_or_else(|| ServiceError::BadRequest("Invalid in point!".into()))?;
+    let out = out
+        .seconds(fps)
+        .ok_or_else(|| ServiceError::BadRequest("Invalid out point!".into()))?;
+    // snapping can move the last frame a bit behind the duration
+    let frame = if fps > 0.0 { 1.0 / fps } else { 0.001 };
+
+    if out > duration + frame {
+        return Err(ServiceError::BadRequest(
+            "Out point is behind the end of the file!".into(),
+        ));
+    }
+
+    let out = out.min(duration);
+
+    if seek >= out {
+        return Err(ServiceError::BadRequest(
+            "In point must be before the out point!".into(),
+        ));
+    }
+
+    Ok((seek, out))
+}
+
+/// Target for a trimmed copy in the folder of the source, always with the extension of the house format.
+/// Without name the source name gets the suffix `_trim`, existing files are not overwritten.
+pub fn trim_target(config: &PlayoutConfig, source: &Path, name: Option<&str>) -> PathBuf {
+    let stem = name
+        .map(Path::new)
+        .and_then(Path::file_stem)
+        .map(|s| s.to_string_lossy().to_string())
+        .filter(|s| !s.trim().is_empty())
+        .unwrap_or_else(|| {
+            let stem = source
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            format!("{stem}_trim")
+        });
+    let dir = source.parent().unwrap_or(&config.channel.storage);
+
+    unique_path(dir, &format!("{stem}.{}", house_extension(config)))
+}
+
+/// Low resolution preview from the trimmed part, cached in the thumbnail folder.
+/// Long parts are cut in the middle, so the preview shows the seconds after the in point
+/// and before the out point.
+pub fn trim_preview(
+    config: &PlayoutConfig,
+    source: &Path,
+    seek: f64,
+    out: f64,
+) -> Result<PathBuf, ServiceError> {
+    let variant = format!("trim {seek:.6}-{out:.6}");
+    let target = cache_dir(config)?.join(cache_name(source, modified(source), &variant, "mp4"));
+
+    if target.is_file() {
+        return Ok(target);
+    }
+
+    let src = source.to_string_lossy().to_string();
+    let probe = MediaProbe::new(&src).map_err(|e| ServiceError::Conflict(e.to_string()))?;
+    let video = !probe.video_streams.is_empty();
+    let audio = !probe.audio_streams.is_empty();
+
+    if !video && !audio {
+        return Err(ServiceError::Conflict(format!(
+            "File has no audio or video: {src}"
+        )));
+    }
+
+    let parts = if out - seek > PREVIEW_SIDE * 2.0 {
+        vec![(seek, PREVIEW_SIDE), (out - PREVIEW_SIDE, PREVIEW_SIDE)]
+    } else {
+        vec![(seek, out - seek)]
+    };
+    let mut args = vec![];
+    let mut filter = String::new();
+    let mut inputs = String::new();
+    let mut outputs = vec![];
+
+    for (i, (start, length)) in parts.iter().enumerate() {
+        args.extend([
+            "-ss".to_string(),
+            format!("{start:.6}"),
+            "-t".to_string(),
+            format!("{length:.6}"),
+            "-i".to_string(),
+            src.clone(),
+        ]);
+
+        if video {
+            filter.push_str(&format!(
+                "[{i}:v:0]scale={PREVIEW_WIDTH}:-2,setsar=1,format=yuv420p[v{i}];"
+            ));
+            inputs.push_str(&format!("[v{i}]"));
+        }
+
+        if audio {
+            inputs.push_str(&format!("[{i}:a:0]"));
+        }
+    }
+
+    filter.push_str(&format!(
+        "{inputs}concat=n={}:v={}:a={}",
+        parts.len(),
+        u8::from(video),
+        u8::from(audio)
+    ));
+
+    if video {
+        filter.push_str("[v]");
+        outputs.extend([
+            "-map",
+            "[v]",
+            "-c:v",
+            "libx264",
+            "-preset",
+            "ultrafast",
+            "-crf",
+            "30",
+        ]);
+    }
+
+    if audio {
+        filter.push_str("[a]");
+        outputs.extend(["-map", "[a]", "-c:a", "aac", "-b:a", "64k", "-ac", "2"]);
+    }
+
+    args.extend(["-filter_complex".to_string(), filter]);
+    args.extend(outputs.into_iter().map(String::from));
+    args.extend([
+        "-movflags".to_string(),
+        "+faststart".to_string(),
+        target.to_string_lossy().to_string(),
+    ]);
+
+    if let Err(e) = run_ffmpeg(&args) {
+        let _ = std::fs::remove_file(&target);
+
+        return Err(e);
+    }
+
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::db::models::TrimSuggestion;
+    use crate::player::utils::Media;
+
+    #[test]
+    fn trim_suggestions() {
+        assert_eq!(
+            parse_black(
+                "[blackdetect @ 0x55d1c0] black_start:0 black_end:2.04 black_duration:2.04"
+            ),
+            Some((0.0, 2.04))
+        );
+        assert_eq!(
+            parse_black("[silencedetect @ 0x55d1c0] silence_start: 3"),
+            None
+        );
+
+        // leading black, followed by silence, trailing credits in black
+        let parts = [(0.0, 2.0), (1.95, 3.5), (40.0, 41.0), (95.5, 100.0)];
+        assert_eq!(suggest_trim(100.0, &parts), (3.5, 95.5));
+
+        // black over the whole file
+        assert_eq!(suggest_trim(10.0, &[(0.0, 10.0)]), (0.0, 10.0));
+
+        let mut playlist = JsonPlaylist::new("2024-10-27".to_string(), 0.0);
+        let mut full = Media::new(0, "/media/a.mp4", false);
+        full.duration = 100.0;
+        full.out = 100.0;
+        let mut trimmed = full.clone();
+        trimmed.seek = 10.0;
+        playlist.program = vec![full, trimmed];
+
+        let suggestions = HashMap::from([(
+            "/media/a.mp4".to_string(),
+            TrimSuggestion {
+                source: "/media/a.mp4".to_string(),
+                seek: 3.5,
+                out: 95.5,
+                duration: 100.0,
+                ..Default::default()
+            },
+        )]);
+
+        assert_eq!(apply_trim(&mut playlist, &suggestions), 1);
+        assert_eq!(playlist.program[0].seek, 3.5);
+        assert_eq!(playlist.program[0].out, 95.5);
+        assert_eq!(playlist.program[1].seek, 10.0);
+    }
+
+    #[test]
+    fn frame_accurate_trim() {
+        assert_eq!(parse_timecode("00:01:02.5", 25.0), Some(62.5));
+        assert_eq!(parse_timecode("00:00:02:12", 25.0), Some(2.48));
+        assert_eq!(parse_timecode("00:00:02:25", 25.0), None);
+        assert_eq!(parse_timecode("00:00:02:12", 0.0), None);
+        assert_eq!(parse_timecode("1:-2", 25.0), None);
+        assert_eq!(parse_timecode("", 25.0), None);
+
+        assert_eq!(snap_to_frame(2.49, 25.0), 2.48);
+        assert_eq!(snap_to_frame(1.0, 30000.0 / 1001.0), 1.001);
+        assert_eq!(snap_to_frame(2.4994, 0.0), 2.499);
+
+        let seek = TrimPoint::Timecode("00:00:01:05".to_string());
+        let out = TrimPoint::Seconds(9.99);
+
+        assert_eq!(trim_points(&seek, &out, 10.0, 25.0).unwrap(), (1.2, 10.0));
+        assert!(trim_points(&out, &seek, 10.0, 25.0).is_err());
+        assert!(trim_points(&seek, &TrimPoint::Seconds(12.0), 10.0, 25.0).is_err());
+        assert!(trim_points(&seek, &TrimPoint::Timecode("end".to_string()), 10.0, 25.0).is_err());
+
+        let point: TrimPoint = serde_json::from_str("\"00:00:03:10\"").unwrap();
+
+        assert_eq!(point.seconds(25.0), Some(3.4));
+    }
+}
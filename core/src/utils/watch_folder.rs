@@ -0,0 +1,518 @@
+/*
+Watch folder auto ingest.
+
+Files which are dropped into the watch folder of a channel are validated, optionally transcoded
+to the house format of the channel and moved into the storage. On request they are added
+to the end of the current playlist. Files which fail are moved to `.failed` in the watch folder.
+
+A file is ingested, when its size has not changed for a few seconds, so uploads can finish.
+The scheduler starts and stops the watchers, when the config changes.
+
+*/
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::channel,
+        Arc, Mutex,
+    },
+    thread::{self, sleep},
+    time::{Duration, Instant},
+};
+
+use log::*;
+use notify::{EventKind, RecursiveMode};
+use notify_debouncer_full::new_debouncer;
+use serde::Serialize;
+use tokio::time::interval;
+
+use crate::player::{
+    controller::{ChannelController, ChannelManager},
+    utils::{
+        file_extension, get_date, include_file_extension, json_reader, json_writer, JsonPlaylist,
+        Media, MediaProbe,
+    },
+};
+use crate::utils::{
+    config::{PlayoutConfig, IMAGE_FORMAT},
+    errors::ServiceError,
+    files::norm_abs_path,
+    logging::Target,
+    time_machine::time_now,
+    transcode::{house_extension, transcode},
+};
+
+pub const FAILED_FOLDER: &str = ".failed";
+/// Time without size change, until a file counts as complete.
+const STABLE_TIME: Duration = Duration::from_secs(5);
+/// Number of results in the status.
+const HISTORY: usize = 50;
+
+/// Result from one ingested file.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IngestResult {
+    pub file: String,
+    /// Path in the storage.
+    pub target: Option<String>,
+    pub success: bool,
+    pub message: String,
+    pub timestamp: String,
+}
+
+/// Watch folder state from one channel, for the status API.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WatchStatus {
+    pub running: bool,
+    pub path: String,
+    /// Files, which wait until they are complete.
+    pub pending: Vec<String>,
+    pub current: Option<String>,
+    pub ingested: u64,
+    pub failed: u64,
+    /// Last results, newest first.
+    pub history: VecDeque<IngestResult>,
+}
+
+impl WatchStatus {
+    fn add(&mut self, result: IngestResult) {
+        if result.success {
+            self.ingested += 1;
+        } else {
+            self.failed += 1;
+        }
+
+        self.current = None;
+        self.history.push_front(result);
+        self.history.truncate(HISTORY);
+    }
+}
+
+/// Watch folder of the channel, relative paths are in the storage.
+pub fn watch_path(config: &PlayoutConfig) -> Result<PathBuf, ServiceError> {
+    let path = Path::new(&config.storage.watch_folder.path);
+
+    if path.is_absolute() {
+        Ok(path.to_path_buf())
+    } else {
+        norm_abs_path(&config.channel.storage, &config.storage.watch_folder.path).map(|(p, _, _)| p)
+    }
+}
+
+/// Path in the folder, which not exists yet. Existing names get a number: `clip (1).mp4`.
+pub fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+
+    if !path.exists() {
+        return path;
+    }
+
+    let file = Path::new(name);
+    let stem = file
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let ext = file_extension(file)
+        .map(|e| format!(".{e}"))
+        .unwrap_or_default();
+
+    (1..)
+        .map(|i| dir.join(format!("{stem} ({i}){ext}")))
+        .find(|p| !p.exists())
+        .unwrap()
+}
+
+/// Rename, or copy and delete when the folders are on different file systems.
+fn move_file(source: &Path, target: &Path) -> Result<(), ServiceError> {
+    if fs::rename(source, target).is_err() {
+        fs::copy(source, target)?;
+        fs::remove_file(source)?;
+    }
+
+    Ok(())
+}
+
+fn is_image(path: &Path) -> bool {
+    file_extension(path).is_some_and(|e| IMAGE_FORMAT.contains(&e.to_lowercase().as_str()))
+}
+
+/// Check if the file is a readable media file.
+fn validate(config: &PlayoutConfig, path: &Path) -> Result<MediaProbe, ServiceError> {
+    if !include_file_extension(config, path) {
+        return Err(ServiceError::BadRequest(
+            "File extension not allowed".into(),
+        ));
+    }
+
+    let probe = MediaProbe::new(&path.to_string_lossy())
+        .map_err(|e| ServiceError::BadRequest(format!("Probe failed: {e}")))?;
+
+    if is_image(path) {
+        if probe.video_streams.is_empty() {
+            return Err(ServiceError::BadRequest("Image can not be read".into()));
+        }
+    } else if probe.video_streams.is_empty() && probe.audio_streams.is_empty() {
+        return Err(ServiceError::BadRequest(
+            "File has no audio or video".into(),
+        ));
+    } else if duration(&probe) <= 0.0 {
+        return Err(ServiceError::BadRequest("File has no duration".into()));
+    }
+
+    Ok(probe)
+}
+
+fn duration(probe: &MediaProbe) -> f64 {
+    probe
+        .format
+        .duration
+        .as_ref()
+        .and_then(|d| d.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Add the file to the end of the playlist from the date, default is the current playlist.
+/// The playlist is created when it not exists.
+pub fn append_to_playlist(
+    manager: &ChannelManager,
+    config: &PlayoutConfig,
+    source: &Path,
+    duration: f64,
+    date: Option<&str>,
+) -> Result<String, ServiceError> {
+    let mut date = match date {
+        Some(d) => d.to_string(),
+        None => manager.current_date.lock().unwrap().clone(),
+    };
+
+    if date.is_empty() {
+        date = get_date(true, config.playlist.start_sec.unwrap_or_default(), false);
+    }
+
+    let d: Vec<&str> = date.split('-').collect();
+    let folder = config.channel.playlists.join(d[0]).join(d[1]);
+    let path = folder.join(format!("{date}.json"));
+    let mut playlist = if path.is_file() {
+        json_reader(&path)?
+    } else {
+        fs::create_dir_all(&folder)?;
+
+        JsonPlaylist {
+            channel: manager.channel.lock().unwrap().name.clone(),
+            date: date.clone(),
+            path: None,
+            start_sec: None,
+            length: None,
+            modified: None,
+            program: vec![],
+        }
+    };
+
+    let mut media = Media::new(playlist.program.len(), &source.to_string_lossy(), false);
+    media.title = source.file_stem().map(|s| s.to_string_lossy().to_string());
+    media.duration = duration;
+    media.out = duration;
+
+    playlist.program.push(media);
+    json_writer(&path, playlist)?;
+
+    Ok(date)
+}
+
+/// Validate, transcode and move one file, returns the path in the storage.
+fn ingest_file(
+    manager: &ChannelManager,
+    config: &PlayoutConfig,
+    file: &Path,
+) -> Result<(PathBuf, String), ServiceError> {
+    let settings = &config.storage.watch_folder;
+    let probe = validate(config, file)?;
+    let (target_dir, _, _) = norm_abs_path(&config.channel.storage, &settings.target)?;
+    let name = file
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut message = vec![];
+
+    fs::create_dir_all(&target_dir)?;
+
+    let target = if settings.transcode && !is_image(file) {
+        let stem = Path::new(&name)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let ext = house_extension(config);
+        let target = unique_path(&target_dir, &format!("{stem}.{ext}"));
+
+        transcode(config, file, &target)?;
+        fs::remove_file(file)?;
+        message.push("transcoded".to_string());
+
+        target
+    } else {
+        let target = unique_path(&target_dir, &name);
+
+        move_file(file, &target)?;
+
+        target
+    };
+
+    if settings.append {
+        if is_image(&target) {
+            message.push("images are not added to the playlist".to_string());
+        } else {
+            let length = MediaProbe::new(&target.to_string_lossy())
+                .map(|p| duration(&p))
+                .unwrap_or_else(|_| duration(&probe));
+            let date = append_to_playlist(manager, config, &target, length, None)?;
+
+            message.push(format!("added to playlist {date}"));
+        }
+    }
+
+    Ok((target, message.join(", ")))
+}
+
+fn ingest(manager: &ChannelManager, file: &Path) {
+    let config = manager.config.lock().unwrap().clone();
+    let id = config.general.channel_id;
+    let name = file.to_string_lossy().to_string();
+
+    manager.watch_status.lock().unwrap().current = Some(name.clone());
+
+    let mut result = IngestResult {
+        file: name.clone(),
+        timestamp: time_now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        ..Default::default()
+    };
+
+    match ingest_file(manager, &config, file) {
+        Ok((target, message)) => {
+            info!(target: Target::file_mail(), channel = id;
+                "Ingest <b><magenta>{name}</></b> to <b><magenta>{}</></b>", target.display()
+            );
+
+            result.target = Some(target.to_string_lossy().to_string());
+            result.success = true;
+            result.message = message;
+        }
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id; "Ingest <b><magenta>{name}</></b> failed: {e}");
+
+            result.message = e.to_string();
+
+            if file.is_file() {
+                let failed = file.with_file_name(FAILED_FOLDER);
+                let file_name = file.file_name().unwrap_or_default().to_string_lossy();
+
+                if let Err(e) = fs::create_dir_all(&failed)
+                    .map_err(ServiceError::from)
+                    .and_then(|_| move_file(file, &unique_path(&failed, &file_name)))
+                {
+                    error!(target: Target::file_mail(), channel = id; "Move failed file: {e}");
+                }
+            }
+        }
+    }
+
+    manager.watch_status.lock().unwrap().add(result);
+}
+
+/// Visible files in the folder, without sub folders.
+fn folder_files(path: &Path) -> Vec<PathBuf> {
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.is_file() && !is_hidden(p))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|n| n.to_string_lossy().starts_with('.'))
+}
+
+/// Watch one folder, until `stop` is set.
+fn watcher(manager: ChannelManager, path: PathBuf, stop: Arc<AtomicBool>) {
+    let id = manager.channel.lock().unwrap().id;
+    let (tx, rx) = channel();
+
+    let result = fs::create_dir_all(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|_| new_debouncer(Duration::from_secs(1), None, tx).map_err(|e| e.to_string()))
+        .and_then(|mut debouncer| {
+            debouncer
+                .watch(&path, RecursiveMode::NonRecursive)
+                .map(|_| debouncer)
+                .map_err(|e| e.to_string())
+        });
+
+    let _debouncer = match result {
+        Ok(debouncer) => debouncer,
+        Err(e) => {
+            error!(target: Target::file_mail(), channel = id;
+                "Watch folder <b><magenta>{}</></b> not usable: {e}", path.display()
+            );
+
+            return;
+        }
+    };
+
+    info!(target: Target::file_mail(), channel = id; "Watch folder <b><magenta>{}</></b>", path.display());
+
+    {
+        let mut status = manager.watch_status.lock().unwrap();
+        status.running = true;
+        status.path = path.to_string_lossy().to_string();
+    }
+
+    // size and time of the last size change
+    let mut pending: HashMap<PathBuf, (u64, Instant)> = folder_files(&path)
+        .into_iter()
+        .map(|f| (f, (0, Instant::now())))
+        .collect();
+
+    while !stop.load(Ordering::SeqCst) {
+        while let Ok(result) = rx.try_recv() {
+            for event in result.unwrap_or_default() {
+                if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    continue;
+                }
+
+                for file in &event.paths {
+                    if file.parent() == Some(path.as_path()) && file.is_file() && !is_hidden(file) {
+                        pending.entry(file.clone()).or_insert((0, Instant::now()));
+                    }
+                }
+            }
+        }
+
+        let mut ready = vec![];
+
+        pending.retain(|file, (size, changed)| {
+            let Ok(meta) = fs::metadata(file) else {
+                return false;
+            };
+
+            if meta.len() != *size {
+                *size = meta.len();
+                *changed = Instant::now();
+            } else if changed.elapsed() >= STABLE_TIME {
+                ready.push(file.clone());
+
+                return false;
+            }
+
+            true
+        });
+
+        manager.watch_status.lock().unwrap().pending = pending
+            .keys()
+            .map(|f| f.to_string_lossy().to_string())
+            .collect();
+
+        for file in ready {
+            if stop.load(Ordering::SeqCst) {
+                break;
+            }
+
+            ingest(&manager, &file);
+        }
+
+        sleep(Duration::from_secs(1));
+    }
+
+    let mut status = manager.watch_status.lock().unwrap();
+    status.running = false;
+    status.pending.clear();
+}
+
+/// Start and stop the watchers from all channels, when their config changes.
+pub fn watch_folders(controllers: Arc<Mutex<ChannelController>>) {
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(5));
+        let mut watchers: HashMap<i32, (PathBuf, Arc<AtomicBool>)> = HashMap::new();
+
+        loop {
+            interval.tick().await;
+
+            let channels = controllers.lock().unwrap().channels.clone();
+            let mut active = vec![];
+
+            for manager in channels {
+                let config = manager.config.lock().unwrap().clone();
+                let id = config.general.channel_id;
+                let settings = &config.storage.watch_folder;
+
+                let path = if settings.enable && !settings.path.is_empty() {
+                    match watch_path(&config) {
+                        Ok(p) => Some(p),
+                        Err(e) => {
+                            error!(target: Target::file_mail(), channel = id; "Watch folder: {e}");
+                            None
+                        }
+                    }
+                } else {
+                    None
+                };
+
+                if path.is_some() && watchers.get(&id).map(|(p, _)| p) == path.as_ref() {
+                    active.push(id);
+                    continue;
+                }
+
+                if let Some((_, stop)) = watchers.remove(&id) {
+                    stop.store(true, Ordering::SeqCst);
+                }
+
+                if let Some(path) = path {
+                    let stop = Arc::new(AtomicBool::new(false));
+                    let stop_clone = stop.clone();
+                    let path_clone = path.clone();
+
+                    thread::spawn(move || watcher(manager, path_clone, stop_clone));
+                    watchers.insert(id, (path, stop));
+                    active.push(id);
+                }
+            }
+
+            // channels which are deleted
+            watchers.retain(|id, (_, stop)| {
+                if !active.contains(id) {
+                    stop.store(true, Ordering::SeqCst);
+                }
+
+                active.contains(id)
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watch_folder_names() {
+        let dir = std::env::temp_dir().join("ffplayout_watch_folder");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        assert_eq!(unique_path(&dir, "clip.mp4"), dir.join("clip.mp4"));
+
+        std::fs::write(dir.join("clip.mp4"), "").unwrap();
+        std::fs::write(dir.join("clip (1).mp4"), "").unwrap();
+        std::fs::write(dir.join("readme"), "").unwrap();
+
+        assert_eq!(unique_path(&dir, "clip.mp4"), dir.join("clip (2).mp4"));
+        assert_eq!(unique_path(&dir, "readme"), dir.join("readme (1)"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,214 @@
+/*
+WHIP ingest, for contributors which publish with WebRTC from a browser or a mobile app.
+
+ffmpeg can not receive WebRTC, so the WHIP requests go to a WebRTC gateway, like MediaMTX or SRS.
+The gateway pushes the stream as RTMP or SRT to the ingest listener, there it takes over
+like every other ingest stream.
+
+ffplayout checks the stream key, which the client sends as bearer token, and keeps the sessions,
+so the clients talk only to ffplayout and the gateway can listen on localhost.
+
+*/
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::Duration,
+};
+
+use reqwest::header::{CONTENT_TYPE, LOCATION};
+use url::Url;
+
+use crate::utils::{
+    config::Ingest,
+    errors::ServiceError,
+    stream_keys::{generate_key, is_valid_key},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// Open sessions, with the channel and the resource url on the gateway
+static SESSIONS: LazyLock<Mutex<HashMap<String, (i32, Url)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Stream key from the `Authorization: Bearer <KEY>` header.
+pub fn bearer_key(header: &str) -> Option<&str> {
+    header
+        .trim()
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+}
+
+/// Resource url on the gateway, the location can be relative to the WHIP endpoint.
+pub fn resource_url(gateway: &str, location: &str) -> Option<Url> {
+    Url::parse(gateway.trim()).ok()?.join(location.trim()).ok()
+}
+
+/// Check the settings and the stream key, before a request goes to the gateway.
+pub fn check_client(id: i32, ingest: &Ingest, key: Option<&str>) -> Result<(), ServiceError> {
+    if !ingest.enable || !ingest.whip.enable || ingest.whip.gateway.trim().is_empty() {
+        return Err(ServiceError::Forbidden(
+            "WHIP ingest is not enabled".to_string(),
+        ));
+    }
+
+    if !key.is_some_and(|k| is_valid_key(id, ingest, k)) {
+        return Err(ServiceError::Unauthorized("Invalid stream key".to_string()));
+    }
+
+    Ok(())
+}
+
+fn gateway_error(e: reqwest::Error) -> ServiceError {
+    ServiceError::ServiceUnavailable(format!("WebRTC gateway: {}", e.without_url()))
+}
+
+fn client() -> Result<reqwest::Client, ServiceError> {
+    reqwest::Client::builder()
+        .timeout(TIMEOUT)
+        .build()
+        .map_err(gateway_error)
+}
+
+fn session_url(id: i32, session: &str) -> Result<Url, ServiceError> {
+    SESSIONS
+        .lock()
+        .unwrap()
+        .get(session)
+        .filter(|(channel, _)| *channel == id)
+        .map(|(_, url)| url.clone())
+        .ok_or_else(|| ServiceError::NoContent(format!("WHIP session {session} not exists")))
+}
+
+/// Send the SDP offer to the gateway, returns the new session and the SDP answer.
+pub async fn publish(
+    id: i32,
+    ingest: &Ingest,
+    offer: String,
+) -> Result<(String, String), ServiceError> {
+    let gateway = ingest.whip.gateway.trim();
+    let response = client()?
+        .post(gateway)
+        .header(CONTENT_TYPE, "application/sdp")
+        .body(offer)
+        .send()
+        .await
+        .map_err(gateway_error)?;
+
+    if response.status().as_u16() != 201 {
+        return Err(ServiceError::ServiceUnavailable(format!(
+            "WebRTC gateway answered with {}",
+            response.status()
+        )));
+    }
+
+    let resource = response
+        .headers()
+        .get(LOCATION)
+        .and_then(|l| l.to_str().ok())
+        .and_then(|l| resource_url(gateway, l))
+        .ok_or_else(|| {
+            ServiceError::ServiceUnavailable("WebRTC gateway sent no location".to_string())
+        })?;
+    let answer = response.text().await.map_err(gateway_error)?;
+    let session = generate_key();
+
+    SESSIONS
+        .lock()
+        .unwrap()
+        .insert(session.clone(), (id, resource));
+
+    Ok((session, answer))
+}
+
+/// Trickle ICE and ICE restarts, the body goes unchanged to the gateway.
+/// Returns the status code and the body from the gateway.
+pub async fn update(
+    id: i32,
+    session: &str,
+    content_type: &str,
+    body: String,
+) -> Result<(u16, String), ServiceError> {
+    let response = client()?
+        .patch(session_url(id, session)?)
+        .header(CONTENT_TYPE, content_type)
+        .body(body)
+        .send()
+        .await
+        .map_err(gateway_error)?;
+    let status = response.status().as_u16();
+
+    Ok((status, response.text().await.map_err(gateway_error)?))
+}
+
+/// End the session on the gateway, the ingest stops when the gateway stops pushing.
+pub async fn stop(id: i32, session: &str) -> Result<(), ServiceError> {
+    let url = session_url(id, session)?;
+
+    SESSIONS.lock().unwrap().remove(session);
+
+    client()?
+        .delete(url)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(gateway_error)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::config::Ingest;
+    use crate::utils::config::IngestWhip;
+    use crate::utils::errors::ServiceError;
+
+    #[test]
+    fn whip_client_check() {
+        assert_eq!(bearer_key("Bearer abc123"), Some("abc123"));
+        assert_eq!(bearer_key("Bearer "), None);
+        assert_eq!(bearer_key("Basic abc123"), None);
+
+        assert_eq!(
+            resource_url("http://127.0.0.1:8889/live/whip", "/live/whip/5f3c")
+                .unwrap()
+                .as_str(),
+            "http://127.0.0.1:8889/live/whip/5f3c"
+        );
+        assert_eq!(
+            resource_url(
+                "http://127.0.0.1:8889/live/whip",
+                "http://10.0.0.2:8889/s/1"
+            )
+            .unwrap()
+            .as_str(),
+            "http://10.0.0.2:8889/s/1"
+        );
+
+        let mut ingest = Ingest {
+            enable: true,
+            input_param: "-f live_flv -listen 1 -i rtmp://127.0.0.1:1936/live/whipkey".to_string(),
+            whip: IngestWhip {
+                enable: true,
+                gateway: "http://127.0.0.1:8889/live/whip".to_string(),
+            },
+            ..Default::default()
+        };
+
+        assert!(check_client(97, &ingest, Some("whipkey")).is_ok());
+        assert!(matches!(
+            check_client(97, &ingest, Some("wrong")),
+            Err(ServiceError::Unauthorized(_))
+        ));
+        assert!(check_client(97, &ingest, None).is_err());
+
+        ingest.whip.enable = false;
+
+        assert!(matches!(
+            check_client(97, &ingest, Some("whipkey")),
+            Err(ServiceError::Forbidden(_))
+        ));
+    }
+}
@@ -0,0 +1,278 @@
+/*
+Clock, countdown and ticker overlays.
+
+The overlays are drawtext filters, which read their text from files with `reload=1`.
+While the channel runs, a thread writes the files:
+
+- clock: current time, every half second
+- countdown: time until the next program in the playlist, every half second
+- ticker: text from an API endpoint or a RSS/Atom feed, every `ticker_interval` seconds
+
+Files are written to a temporary file first and renamed, so ffmpeg never reads a half written text.
+
+*/
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use chrono::{
+    format::{Item, StrftimeItems},
+    DateTime, Local, Timelike,
+};
+use log::*;
+use regex::Regex;
+use reqwest::blocking::{Client, Response};
+use serde_json::Value;
+
+use crate::player::{controller::ChannelManager, utils::Media};
+use crate::utils::{
+    config::{PlayoutConfig, Widgets},
+    logging::Target,
+    time_machine::time_now,
+};
+
+const UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Text file of a widget.
+pub fn text_file(id: i32, name: &str) -> PathBuf {
+    env::temp_dir().join(format!("ffplayout_{id}_{name}.txt"))
+}
+
+fn write_text(path: &Path, text: &str) {
+    let tmp = path.with_extension("tmp");
+
+    if fs::write(&tmp, text)
+        .and_then(|_| fs::rename(&tmp, path))
+        .is_err()
+    {
+        let _ = fs::remove_file(&tmp);
+    }
+}
+
+/// Remaining time as `MM:SS`, or `H:MM:SS` from one hour on.
+pub fn format_remaining(sec: f64) -> String {
+    let sec = sec.max(0.0).ceil() as i64;
+
+    if sec >= 3600 {
+        format!("{}:{:02}:{:02}", sec / 3600, sec / 60 % 60, sec % 60)
+    } else {
+        format!("{:02}:{:02}", sec / 60, sec % 60)
+    }
+}
+
+/// Time in the strftime format, an invalid format shows the time as `%H:%M:%S`.
+pub fn clock_text(time: &DateTime<Local>, format: &str) -> String {
+    if StrftimeItems::new(format).any(|i| i == Item::Error) {
+        return time.format("%H:%M:%S").to_string();
+    }
+
+    time.format(format).to_string()
+}
+
+/// Seconds and title of the next program after the clip at `index`.
+/// Clips with a category from `skip` are no program.
+pub fn next_program(
+    list: &[Media],
+    index: usize,
+    now: f64,
+    shift: f64,
+    skip: &[String],
+) -> Option<(f64, String)> {
+    let is_program = |m: &&Media| !skip.iter().any(|s| s.eq_ignore_ascii_case(&m.category));
+    let item = list
+        .iter()
+        .skip(index + 1)
+        .find(|m| m.begin.is_some() && is_program(m))?;
+    let mut remaining = item.begin.unwrap_or_default() - shift - now;
+
+    // the next program is on the next day
+    while remaining < -43200.0 {
+        remaining += 86400.0;
+    }
+
+    let title = item.title.clone().unwrap_or_else(|| {
+        Path::new(&item.source)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default()
+    });
+
+    Some((remaining, title))
+}
+
+/// Text of the countdown, with the placeholders `{title}` and `{time}`.
+pub fn countdown_text(template: &str, remaining: f64, title: &str) -> String {
+    template
+        .replace("{title}", title)
+        .replace("{time}", &format_remaining(remaining))
+}
+
+fn decode_xml(text: &str) -> String {
+    let text = text.trim();
+    let text = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|t| t.strip_suffix("]]>"))
+        .unwrap_or(text);
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Ticker text from a response: titles from a RSS/Atom feed, a JSON list of texts,
+/// or plain text with one message per line.
+pub fn ticker_text(body: &str, separator: &str) -> String {
+    let body = body.trim();
+    let mut messages = vec![];
+
+    if body.starts_with('<') {
+        let item = Regex::new(r"(?s)<(item|entry)\b.*?</(item|entry)>").unwrap();
+        let title = Regex::new(r"(?s)<title[^>]*>(.*?)</title>").unwrap();
+
+        for m in item.find_iter(body) {
+            if let Some(c) = title.captures(m.as_str()) {
+                messages.push(decode_xml(&c[1]));
+            }
+        }
+    } else if let Ok(value) = serde_json::from_str::<Value>(body) {
+        let list = match value {
+            Value::Array(list) => list,
+            Value::Object(mut map) => match map.remove("items") {
+                Some(Value::Array(list)) => list,
+                _ => vec![Value::Object(map)],
+            },
+            v => vec![v],
+        };
+
+        for v in list {
+            let text = match &v {
+                Value::String(s) => Some(s.as_str()),
+                Value::Object(map) => map
+                    .get("text")
+                    .or_else(|| map.get("title"))
+                    .and_then(Value::as_str),
+                _ => None,
+            };
+
+            if let Some(text) = text {
+                messages.push(text.to_string());
+            }
+        }
+    } else {
+        messages = body.lines().map(str::to_string).collect();
+    }
+
+    messages
+        .iter()
+        .map(|m| m.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|m| !m.is_empty())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn load_ticker(widgets: &Widgets) -> Result<String, String> {
+    let response = Client::builder()
+        .timeout(Duration::from_secs(20))
+        .build()
+        .map_err(|e| e.to_string())?
+        .get(&widgets.ticker_url)
+        .send()
+        .and_then(Response::error_for_status)
+        .map_err(|e| e.to_string())?;
+    let body = response.text().map_err(|e| e.to_string())?;
+
+    Ok(ticker_text(&body, &widgets.ticker_separator))
+}
+
+fn update_countdown(manager: &ChannelManager, config: &PlayoutConfig) {
+    let widgets = &config.text.widgets;
+    let index = manager
+        .current_media
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|m| m.index);
+    let shift = manager.channel.lock().unwrap().time_shift;
+    let skip: Vec<String> = widgets
+        .countdown_skip
+        .split(';')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    let now = time_now();
+    let seconds = now.num_seconds_from_midnight() as f64 + now.nanosecond() as f64 / 1e9;
+
+    let text = index
+        .and_then(|i| {
+            let list = manager.current_list.lock().unwrap();
+
+            next_program(&list, i, seconds, shift, &skip)
+        })
+        .map(|(remaining, title)| countdown_text(&widgets.countdown_text, remaining, &title))
+        .unwrap_or_default();
+
+    write_text(&text_file(config.general.channel_id, "countdown"), &text);
+}
+
+/// Create the text files, before ffmpeg starts.
+pub fn init_files(id: i32) {
+    for name in ["clock", "countdown", "ticker"] {
+        let path = text_file(id, name);
+
+        if !path.is_file() {
+            write_text(&path, "");
+        }
+    }
+}
+
+/// Update the widget texts, until the channel stops.
+pub fn widget_updater(manager: ChannelManager) {
+    let is_terminated = manager.is_terminated.clone();
+    let mut last_ticker: Option<Instant> = None;
+
+    while !is_terminated.load(Ordering::SeqCst) {
+        let config = manager.config.lock().unwrap().clone();
+        let widgets = &config.text.widgets;
+        let id = config.general.channel_id;
+
+        if !widgets.enabled() {
+            break;
+        }
+
+        if widgets.clock {
+            let text = clock_text(&time_now(), &widgets.clock_format);
+
+            write_text(&text_file(id, "clock"), &text);
+        }
+
+        if widgets.countdown {
+            update_countdown(&manager, &config);
+        }
+
+        if widgets.ticker
+            && !widgets.ticker_url.is_empty()
+            && last_ticker.is_none_or(|t| {
+                t.elapsed() >= Duration::from_secs(widgets.ticker_interval.max(10) as u64)
+            })
+        {
+            last_ticker = Some(Instant::now());
+
+            match load_ticker(widgets) {
+                Ok(text) => write_text(&text_file(id, "ticker"), &text),
+                Err(e) => {
+                    error!(target: Target::file_mail(), channel = id; "Load ticker from <b><magenta>{}</></b> failed: {e}", widgets.ticker_url);
+                }
+            }
+        }
+
+        sleep(UPDATE_INTERVAL);
+    }
+}
@@ -0,0 +1,158 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    error::PayloadError,
+    http::{header, Method},
+    middleware::Next,
+    web, Error, HttpMessage,
+};
+use futures_util::stream;
+use log::*;
+use serde_json::Value;
+use sqlx::{Pool, Sqlite};
+
+use crate::db::{
+    handles,
+    models::{AuditEntry, UserMeta},
+};
+use crate::utils::time_machine::time_now;
+
+/// Bodies up to this size are stored (shortened) in the audit log.
+const MAX_PAYLOAD_READ: usize = 64 * 1024;
+/// Length of the stored payload summary.
+const MAX_SUMMARY_LEN: usize = 512;
+/// Parts of key names, which values never should land in the audit log.
+const REDACT_KEYS: [&str; 5] = ["pass", "secret", "token", "key", "credential"];
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, val) in map.iter_mut() {
+                let key = key.to_lowercase();
+
+                if REDACT_KEYS.iter().any(|k| key.contains(k)) {
+                    *val = Value::String("***".to_string());
+                } else {
+                    redact(val);
+                }
+            }
+        }
+        Value::Array(list) => list.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// Create a short, password free summary from the request body.
+fn payload_summary(content_type: &str, body: &[u8]) -> String {
+    let mut summary = if content_type.contains("json") {
+        match serde_json::from_slice::<Value>(body) {
+            Ok(mut value) => {
+                redact(&mut value);
+                value.to_string()
+            }
+            Err(_) => String::from_utf8_lossy(body).to_string(),
+        }
+    } else {
+        String::from_utf8_lossy(body).to_string()
+    };
+
+    if summary.chars().count() > MAX_SUMMARY_LEN {
+        summary = summary.chars().take(MAX_SUMMARY_LEN).collect();
+        summary.push_str("...");
+    }
+
+    summary
+}
+
+/// Get the channel id from the matched route, user routes have no channel.
+/// Entries without channel are listed for global admins over `GET /api/audit`.
+fn channel_from_request(pattern: &str, req: &actix_web::HttpRequest) -> Option<i32> {
+    if pattern.contains("/user") {
+        return None;
+    }
+
+    req.match_info()
+        .get("channel")
+        .or_else(|| req.match_info().get("id"))
+        .and_then(|id| id.parse::<i32>().ok())
+}
+
+/// Audit log middleware
+///
+/// Every state changing request (all methods except GET, HEAD and OPTIONS)
+/// is written to the `audit_log` table, together with the user, IP, route and a payload summary.
+pub async fn audit_log(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return next.call(req).await;
+    }
+
+    let content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|c| c.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let length: usize = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|c| c.to_str().ok())
+        .and_then(|c| c.parse().ok())
+        .unwrap_or_default();
+
+    let payload = if length > 0 && length <= MAX_PAYLOAD_READ && !content_type.contains("multipart")
+    {
+        let body = req.extract::<web::Bytes>().await?;
+        let summary = payload_summary(&content_type, &body);
+        let body_stream = stream::once(async move { Ok::<_, PayloadError>(body) });
+
+        req.set_payload(Payload::Stream {
+            payload: Box::pin(body_stream),
+        });
+
+        summary
+    } else if length > 0 {
+        format!("<{content_type}, {length} bytes>")
+    } else {
+        String::new()
+    };
+
+    let user_id = req
+        .extensions()
+        .get::<UserMeta>()
+        .map(|u| u.id)
+        .unwrap_or_default();
+    let ip = req
+        .connection_info()
+        .realip_remote_addr()
+        .unwrap_or_default()
+        .to_string();
+    let method = req.method().to_string();
+    let pool = req.app_data::<web::Data<Pool<Sqlite>>>().cloned();
+
+    let res = next.call(req).await?;
+
+    let pattern = res.request().match_pattern().unwrap_or_default();
+
+    let entry = AuditEntry {
+        id: 0,
+        channel_id: channel_from_request(&pattern, res.request()),
+        user_id,
+        ip,
+        method,
+        route: res.request().path().to_string(),
+        payload,
+        status: res.status().as_u16() as i32,
+        timestamp: time_now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    };
+
+    if let Some(pool) = pool {
+        if let Err(e) = handles::insert_audit(&pool, entry).await {
+            error!("Unable to write audit log: {e}");
+        }
+    }
+
+    Ok(res)
+}
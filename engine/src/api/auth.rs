@@ -5,7 +5,10 @@ use jsonwebtoken::{self, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    db::{models::Role, GLOBAL_SETTINGS},
+    db::{
+        models::{Permission, Role},
+        GLOBAL_SETTINGS,
+    },
     utils::errors::ServiceError,
 };
 
@@ -18,16 +21,25 @@ pub struct Claims {
     pub channels: Vec<i32>,
     pub username: String,
     pub role: Role,
+    #[serde(default)]
+    pub permissions: Vec<Permission>,
     exp: i64,
 }
 
 impl Claims {
-    pub fn new(id: i32, channels: Vec<i32>, username: String, role: Role) -> Self {
+    pub fn new(
+        id: i32,
+        channels: Vec<i32>,
+        username: String,
+        role: Role,
+        permissions: Vec<Permission>,
+    ) -> Self {
         Self {
             id,
             channels,
             username,
             role,
+            permissions,
             exp: (Utc::now() + TimeDelta::try_days(JWT_EXPIRATION_DAYS).unwrap()).timestamp(),
         }
     }
@@ -0,0 +1,457 @@
+/*
+GraphQL facade for dashboards.
+
+Only with the cargo feature `graphql`. The endpoint reads channels, playlists, media, program
+info and status with the same functions as the REST API, so a dashboard needs only one request
+for nested data, like channel -> playlist -> items -> probe data:
+
+{ channel(id: 1) { name status { running } playlist { date items { begin source probe { width height } } } } }
+
+It needs the same token as the REST API and users see only their channels.
+
+The queries have a depth and complexity limit. `probe` runs ffprobe, so it is expensive:
+a request probes at most `MAX_PROBES` sources and every source only once.
+
+*/
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        LazyLock, Mutex,
+    },
+};
+
+use actix_web::{post, web, Responder};
+use actix_web_grants::{authorities::AuthDetails, proc_macro::protect};
+use async_graphql::{
+    Context, EmptyMutation, EmptySubscription, Json, Object, Request, Schema, SimpleObject,
+};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use sqlx::{Pool, Sqlite};
+use tokio::task;
+
+use crate::api::{
+    pagination::ListQuery,
+    routes::{program_list, ProgramObj},
+};
+use crate::db::models::{Role, UserMeta};
+use crate::player::{
+    controller::{ChannelController, ChannelManager},
+    input::dead_air::source_path,
+    utils::{get_data_map, passthrough::ClipFormat, sec_to_time, Media, MediaProbe},
+};
+use crate::utils::{
+    errors::ServiceError,
+    files::{browser, PathObject},
+    maintenance::in_maintenance,
+    playlist::read_playlist,
+    time_machine::time_now,
+};
+
+pub type GraphSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+/// Maximal nesting of a query.
+const MAX_DEPTH: usize = 10;
+/// Maximal complexity of a query, every field counts 1, `probe` counts `PROBE_COMPLEXITY`.
+const MAX_COMPLEXITY: usize = 500;
+const PROBE_COMPLEXITY: usize = 100;
+/// Maximal ffprobe runs from one request.
+const MAX_PROBES: usize = 50;
+
+static SCHEMA: LazyLock<GraphSchema> = LazyLock::new(|| {
+    Schema::build(Query, EmptyMutation, EmptySubscription)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+});
+
+/// Probe results from one request, the same source is probed only once.
+#[derive(Default)]
+struct Probes {
+    count: AtomicUsize,
+    cache: Mutex<HashMap<String, Option<Probe>>>,
+}
+
+/// User from the token, for the channel access.
+struct Access {
+    user: UserMeta,
+    admin: bool,
+}
+
+impl Access {
+    fn allows(&self, id: i32) -> bool {
+        self.admin || self.user.channels.contains(&id)
+    }
+}
+
+fn parse_date(date: Option<String>) -> async_graphql::Result<String> {
+    match date {
+        Some(d) => NaiveDate::parse_from_str(&d, "%Y-%m-%d")
+            .map(|_| d)
+            .map_err(|_| "Date must be in format: YYYY-MM-DD".into()),
+        None => Ok(time_now().format("%Y-%m-%d").to_string()),
+    }
+}
+
+pub struct Query;
+
+#[Object]
+impl Query {
+    /// Channels of the user.
+    async fn channels(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<ChannelNode>> {
+        let access = ctx.data::<Access>()?;
+        let controllers = ctx.data::<web::Data<Mutex<ChannelController>>>()?;
+        let channels = controllers.lock().unwrap().channels.clone();
+
+        Ok(channels
+            .into_iter()
+            .filter(|m| access.allows(m.channel.lock().unwrap().id))
+            .map(ChannelNode)
+            .collect())
+    }
+
+    async fn channel(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<ChannelNode> {
+        let access = ctx.data::<Access>()?;
+
+        if !access.allows(id) {
+            return Err(ServiceError::Forbidden("No permission for the channel!".into()).into());
+        }
+
+        let controllers = ctx.data::<web::Data<Mutex<ChannelController>>>()?;
+        let manager = controllers
+            .lock()
+            .unwrap()
+            .get(id)
+            .ok_or_else(|| ServiceError::BadRequest(format!("Channel ({id}) not exists!")))?;
+
+        Ok(ChannelNode(manager))
+    }
+}
+
+pub struct ChannelNode(ChannelManager);
+
+#[Object]
+impl ChannelNode {
+    async fn id(&self) -> i32 {
+        self.0.channel.lock().unwrap().id
+    }
+
+    async fn name(&self) -> String {
+        self.0.channel.lock().unwrap().name.clone()
+    }
+
+    async fn preview_url(&self) -> String {
+        self.0.channel.lock().unwrap().preview_url.clone()
+    }
+
+    async fn active(&self) -> bool {
+        self.0.channel.lock().unwrap().active
+    }
+
+    /// Planned work, no alerts and no automatic starts.
+    async fn maintenance(&self) -> bool {
+        in_maintenance(self.0.channel.lock().unwrap().id)
+    }
+
+    /// Playout status, like `/api/control/{id}/media/current`.
+    async fn status(&self) -> Status {
+        let running = self.0.is_alive.load(Ordering::SeqCst);
+
+        Status {
+            running,
+            current: running.then(|| Json(get_data_map(&self.0))),
+        }
+    }
+
+    /// Playlist from the date, default is today. Empty, when there is no playlist.
+    async fn playlist(&self, date: Option<String>) -> async_graphql::Result<Option<Playlist>> {
+        let date = parse_date(date)?;
+        let config = self.0.config.lock().unwrap().clone();
+        let Ok(playlist) = read_playlist(&config, date).await else {
+            return Ok(None);
+        };
+        let mut begin = config.playlist.start_sec.unwrap_or_default();
+        let mut items = vec![];
+
+        for (index, media) in playlist.program.into_iter().enumerate() {
+            let length = media.out - media.seek;
+
+            items.push(Item {
+                index,
+                begin,
+                path: source_path(&config, &media.source),
+                media,
+            });
+
+            begin += length;
+        }
+
+        Ok(Some(Playlist {
+            date: playlist.date,
+            channel: playlist.channel,
+            items,
+        }))
+    }
+
+    /// Program from the date, default is today, like `/api/program/{id}/`.
+    async fn program(
+        &self,
+        ctx: &Context<'_>,
+        date: Option<String>,
+    ) -> async_graphql::Result<Vec<ProgramEntry>> {
+        let date = NaiveDate::parse_from_str(&parse_date(date)?, "%Y-%m-%d")?;
+        let pool = ctx.data::<web::Data<Pool<Sqlite>>>()?;
+        let config = self.0.config.lock().unwrap().clone();
+        let obj = ProgramObj {
+            start_after: date.and_hms_opt(0, 0, 0).unwrap_or_default(),
+            start_before: date.and_hms_opt(23, 59, 59).unwrap_or_default(),
+        };
+
+        Ok(program_list(pool, &config, &obj)
+            .await
+            .into_iter()
+            .map(|(_, _, item)| ProgramEntry {
+                source: item.source,
+                start: item.start,
+                title: item.title,
+                seek: item.r#in,
+                out: item.out,
+                duration: item.duration,
+                category: item.category,
+                description: item.description,
+                artwork: item.artwork,
+                rating: item.rating,
+            })
+            .collect())
+    }
+
+    /// Folders and files from the storage, like `/api/file/{id}/browse/`.
+    async fn media(&self, path: Option<String>) -> async_graphql::Result<MediaFolder> {
+        let config = self.0.config.lock().unwrap().clone();
+        let channel = self.0.channel.lock().unwrap().clone();
+        let path_obj: PathObject = serde_json::from_value(serde_json::json!({
+            "source": path.unwrap_or_default()
+        }))?;
+        let folder = browser(&config, &channel, &path_obj, &ListQuery::default()).await?;
+
+        Ok(serde_json::from_value(serde_json::to_value(folder)?)?)
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct Status {
+    running: bool,
+    /// Current clip and timing, only when the channel is running.
+    current: Option<Json<Map<String, Value>>>,
+}
+
+#[derive(SimpleObject)]
+pub struct Playlist {
+    date: String,
+    channel: String,
+    items: Vec<Item>,
+}
+
+pub struct Item {
+    index: usize,
+    begin: f64,
+    /// Source with the storage path.
+    path: String,
+    media: Media,
+}
+
+#[Object]
+impl Item {
+    async fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Start in seconds from midnight.
+    async fn begin(&self) -> f64 {
+        self.begin
+    }
+
+    async fn start(&self) -> String {
+        sec_to_time(self.begin)
+    }
+
+    async fn source(&self) -> &str {
+        &self.media.source
+    }
+
+    async fn title(&self) -> Option<&str> {
+        self.media.title.as_deref()
+    }
+
+    #[graphql(name = "in")]
+    async fn seek(&self) -> f64 {
+        self.media.seek
+    }
+
+    async fn out(&self) -> f64 {
+        self.media.out
+    }
+
+    async fn duration(&self) -> f64 {
+        self.media.duration
+    }
+
+    async fn category(&self) -> &str {
+        &self.media.category
+    }
+
+    async fn description(&self) -> Option<&str> {
+        self.media.description.as_deref()
+    }
+
+    /// ffprobe data from the source, `null` when it can not be probed.
+    #[graphql(complexity = "PROBE_COMPLEXITY")]
+    async fn probe(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<Probe>> {
+        let probes = ctx.data::<Probes>()?;
+
+        if let Some(probe) = probes.cache.lock().unwrap().get(&self.path) {
+            return Ok(probe.clone());
+        }
+
+        if probes.count.fetch_add(1, Ordering::SeqCst) >= MAX_PROBES {
+            return Err(format!("Only {MAX_PROBES} sources can be probed in one request").into());
+        }
+
+        let path = self.path.clone();
+        let probe = task::spawn_blocking(move || MediaProbe::new(&path))
+            .await?
+            .ok()
+            .map(|p| Probe {
+                duration: p
+                    .format
+                    .duration
+                    .as_deref()
+                    .and_then(|d| d.parse().ok())
+                    .unwrap_or_default(),
+                format: ClipFormat::new(&p),
+            });
+
+        probes
+            .cache
+            .lock()
+            .unwrap()
+            .insert(self.path.clone(), probe.clone());
+
+        Ok(probe)
+    }
+}
+
+#[derive(Clone)]
+pub struct Probe {
+    duration: f64,
+    format: ClipFormat,
+}
+
+#[Object]
+impl Probe {
+    async fn duration(&self) -> f64 {
+        self.duration
+    }
+
+    async fn video_codec(&self) -> Option<&str> {
+        self.format.video_codec.as_deref()
+    }
+
+    async fn width(&self) -> i64 {
+        self.format.width
+    }
+
+    async fn height(&self) -> i64 {
+        self.format.height
+    }
+
+    async fn fps(&self) -> f64 {
+        self.format.fps
+    }
+
+    async fn pix_fmt(&self) -> &str {
+        &self.format.pix_fmt
+    }
+
+    async fn audio_codec(&self) -> Option<&str> {
+        self.format.audio_codec.as_deref()
+    }
+
+    async fn sample_rate(&self) -> i64 {
+        self.format.sample_rate
+    }
+
+    async fn channels(&self) -> i64 {
+        self.format.channels
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct ProgramEntry {
+    source: String,
+    start: String,
+    title: Option<String>,
+    #[graphql(name = "in")]
+    seek: f64,
+    out: f64,
+    duration: f64,
+    category: String,
+    description: Option<String>,
+    artwork: Option<String>,
+    rating: Option<String>,
+}
+
+#[derive(Deserialize, SimpleObject)]
+pub struct MediaFile {
+    name: String,
+    duration: f64,
+}
+
+#[derive(Deserialize, SimpleObject)]
+pub struct MediaFolder {
+    source: String,
+    parent: Option<String>,
+    #[serde(default)]
+    folders: Option<Vec<String>>,
+    #[serde(default)]
+    files: Option<Vec<MediaFile>>,
+    total: Option<usize>,
+}
+
+/// **GraphQL**
+///
+/// Only with the cargo feature `graphql`.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/graphql -H 'Content-Type: application/json' \
+/// -H 'Authorization: Bearer <TOKEN>' \
+/// -d '{"query": "{ channels { id name status { running } playlist { items { source begin } } } }"}'
+/// ```
+#[post("/graphql")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role"
+)]
+pub async fn graphql(
+    pool: web::Data<Pool<Sqlite>>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    data: web::Json<Request>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let access = Access {
+        user: user.into_inner(),
+        admin: role.has_authority(&Role::GlobalAdmin),
+    };
+    let request = data
+        .into_inner()
+        .data(access)
+        .data(Probes::default())
+        .data(pool)
+        .data(controllers);
+
+    Ok(web::Json(SCHEMA.execute(request).await))
+}
@@ -1,2 +1,19 @@
+use actix_web::web;
+
+pub mod audit;
 pub mod auth;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+pub mod pagination;
+pub mod proxy;
 pub mod routes;
+pub mod ws;
+
+/// Register the GraphQL endpoint, when ffplayout is built with the `graphql` feature.
+pub fn graphql_routes(cfg: &mut web::ServiceConfig) {
+    #[cfg(feature = "graphql")]
+    cfg.service(graphql::graphql);
+
+    #[cfg(not(feature = "graphql"))]
+    let _ = cfg;
+}
@@ -0,0 +1,220 @@
+/*
+Pagination, filtering and sorting for list endpoints.
+
+    GET /api/users?page=2&per_page=20&sort=-username&filter=admin
+
+- `filter`: text which one field must contain, case insensitive; `field:text` checks only this field
+- `sort`: field name, with `-` in front in descending order
+- `page`, `per_page`: page starts at 1, `per_page` is 50 at default
+
+Without any of these parameters, the list comes as before. Otherwise it is wrapped in a
+[`Page`] with the total count of matching items.
+
+*/
+
+use std::{
+    cmp::Ordering,
+    future::{ready, Ready},
+    ops::Range,
+};
+
+use actix_web::{dev::Payload, web, FromRequest, HttpRequest};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::utils::errors::ServiceError;
+
+pub const DEFAULT_PER_PAGE: usize = 50;
+pub const MAX_PER_PAGE: usize = 1000;
+
+/// Query parameters of a list request.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ListQuery {
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+    pub sort: Option<String>,
+    pub filter: Option<String>,
+}
+
+/// One page from a list, with the count of all matching items.
+#[derive(Debug, Clone, Serialize)]
+pub struct Page<T> {
+    pub total: usize,
+    pub page: usize,
+    pub per_page: usize,
+    pub items: Vec<T>,
+}
+
+impl FromRequest for ListQuery {
+    type Error = ServiceError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            web::Query::<Self>::from_query(req.query_string())
+                .map_err(|e| ServiceError::BadRequest(e.to_string()))
+                .and_then(|q| q.into_inner().validate()),
+        )
+    }
+}
+
+impl ListQuery {
+    fn validate(self) -> Result<Self, ServiceError> {
+        if self.page == Some(0) {
+            return Err(ServiceError::BadRequest("Page starts at 1".into()));
+        }
+
+        if self.per_page.is_some_and(|p| p == 0 || p > MAX_PER_PAGE) {
+            return Err(ServiceError::BadRequest(format!(
+                "per_page must be between 1 and {MAX_PER_PAGE}"
+            )));
+        }
+
+        Ok(self)
+    }
+
+    /// No list parameter is set, the response keeps its old form.
+    pub fn is_empty(&self) -> bool {
+        self.page.is_none()
+            && self.per_page.is_none()
+            && self.sort.is_none()
+            && self.filter.is_none()
+    }
+
+    pub fn page(&self) -> usize {
+        self.page.unwrap_or(1)
+    }
+
+    pub fn per_page(&self) -> usize {
+        self.per_page.unwrap_or(DEFAULT_PER_PAGE)
+    }
+
+    /// Indexes of the current page, in a list with `total` items.
+    pub fn range(&self, total: usize) -> Range<usize> {
+        let start = ((self.page() - 1) * self.per_page()).min(total);
+
+        start..(start + self.per_page()).min(total)
+    }
+
+    /// Sort field and if the order is descending.
+    pub fn sort_field(&self) -> Option<(&str, bool)> {
+        let sort = self.sort.as_deref().filter(|s| !s.is_empty())?;
+
+        match sort.strip_prefix('-') {
+            Some(field) => Some((field, true)),
+            None => Some((sort, false)),
+        }
+    }
+
+    /// Field and lowercase text from the filter.
+    fn filter_parts(&self) -> Option<(Option<&str>, String)> {
+        let filter = self.filter.as_deref().filter(|f| !f.is_empty())?;
+
+        match filter.split_once(':') {
+            Some((field, text))
+                if field.starts_with(|c: char| c.is_ascii_alphabetic())
+                    && field.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') =>
+            {
+                Some((Some(field), text.to_lowercase()))
+            }
+            _ => Some((None, filter.to_lowercase())),
+        }
+    }
+
+    /// A single text, like a file name, matches the filter. Field filters don't apply to it.
+    pub fn matches_text(&self, text: &str) -> bool {
+        match self.filter_parts() {
+            Some((None, filter)) => text.to_lowercase().contains(&filter),
+            _ => true,
+        }
+    }
+
+    /// The item matches the filter.
+    pub fn matches(&self, item: &Value) -> bool {
+        let Some((field, filter)) = self.filter_parts() else {
+            return true;
+        };
+        let Value::Object(map) = item else {
+            return false;
+        };
+
+        match field {
+            Some(field) => map
+                .get(field)
+                .is_some_and(|v| value_text(v).contains(&filter)),
+            None => map.values().any(|v| value_text(v).contains(&filter)),
+        }
+    }
+
+    /// Filter, sort and page the items.
+    pub fn apply<T: Serialize>(&self, items: Vec<T>) -> Result<Page<Value>, ServiceError> {
+        let mut values = items
+            .into_iter()
+            .map(serde_json::to_value)
+            .collect::<Result<Vec<Value>, _>>()?;
+
+        values.retain(|v| self.matches(v));
+
+        if let Some((field, desc)) = self.sort_field() {
+            if values.iter().any(|v| v.get(field).is_none()) {
+                return Err(ServiceError::BadRequest(format!(
+                    "Unknown sort field: {field}"
+                )));
+            }
+
+            values.sort_by(|a, b| {
+                let order = compare(&a[field], &b[field]);
+
+                if desc {
+                    order.reverse()
+                } else {
+                    order
+                }
+            });
+        }
+
+        let total = values.len();
+        let items = values.drain(self.range(total)).collect();
+
+        Ok(Page {
+            total,
+            page: self.page(),
+            per_page: self.per_page(),
+            items,
+        })
+    }
+
+    /// The items as they are, or as page, when list parameters are set.
+    pub fn response<T: Serialize>(&self, items: Vec<T>) -> Result<Value, ServiceError> {
+        if self.is_empty() {
+            return Ok(serde_json::to_value(items)?);
+        }
+
+        Ok(serde_json::to_value(self.apply(items)?)?)
+    }
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.to_lowercase(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Order of two JSON values, numbers by value and text case insensitive. Null comes first.
+pub fn compare(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x
+            .as_f64()
+            .unwrap_or_default()
+            .total_cmp(&y.as_f64().unwrap_or_default()),
+        (Value::String(x), Value::String(y)) => x.to_lowercase().cmp(&y.to_lowercase()),
+        (Value::Bool(x), Value::Bool(y)) => x.cmp(y),
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Less,
+        (_, Value::Null) => Ordering::Greater,
+        _ => value_text(a).cmp(&value_text(b)),
+    }
+}
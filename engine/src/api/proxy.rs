@@ -0,0 +1,53 @@
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{header, StatusCode},
+    middleware::Next,
+    web, Error, HttpResponse, ResponseError,
+};
+
+use crate::utils::cluster::{channel_node, forward, proxy_channel};
+
+/// Headers, which go with the request to the node.
+const FORWARD_HEADERS: [header::HeaderName; 3] =
+    [header::AUTHORIZATION, header::CONTENT_TYPE, header::ACCEPT];
+
+/// Cluster proxy middleware
+///
+/// Control and status calls of a channel, which runs on a playout node, go to the node.
+/// The node checks the token and the permissions again, it has the same secret.
+pub async fn cluster_proxy(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let Some(url) = proxy_channel(req.path()).and_then(channel_node) else {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    };
+
+    let body = req.extract::<web::Bytes>().await?;
+    let headers = FORWARD_HEADERS
+        .iter()
+        .filter_map(|name| {
+            req.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| (name.to_string(), v.to_string()))
+        })
+        .collect();
+    let path = req
+        .uri()
+        .path_and_query()
+        .map_or(req.path(), |p| p.as_str())
+        .to_string();
+
+    let response = match forward(&url, req.method().as_str(), &path, headers, body.to_vec()).await {
+        Ok((status, content_type, body)) => {
+            HttpResponse::build(StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_GATEWAY))
+                .content_type(content_type)
+                .body(body)
+        }
+        Err(e) => e.error_response(),
+    };
+
+    Ok(req.into_response(response))
+}
@@ -20,18 +20,16 @@ use actix_multipart::Multipart;
 use actix_web::{
     delete, get,
     http::{
-        header::{ContentDisposition, DispositionType},
+        header::{ContentDisposition, DispositionParam, DispositionType},
         StatusCode,
     },
-    patch, post, put, web, HttpRequest, HttpResponse, Responder,
+    patch, post, put, route, web, Either, HttpRequest, HttpResponse, Responder,
 };
 use actix_web_grants::{authorities::AuthDetails, proc_macro::protect};
 
-use argon2::{
-    password_hash::{rand_core::OsRng, PasswordHash, SaltString},
-    Argon2, PasswordHasher, PasswordVerifier,
-};
-use chrono::{DateTime, Datelike, Local, NaiveDateTime, TimeDelta, TimeZone, Utc};
+use argon2::{password_hash::PasswordHash, Argon2, PasswordVerifier};
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, TimeDelta, TimeZone, Utc};
+use futures_util::StreamExt;
 use log::*;
 use path_clean::PathClean;
 use regex::Regex;
@@ -39,57 +37,137 @@ use serde::{Deserialize, Serialize};
 use sqlx::{Pool, Sqlite};
 use tokio::fs;
 
-use crate::db::models::Role;
+use crate::db::{models::Role, GLOBAL_SETTINGS};
 use crate::utils::{
+    ad_breaks::load_ad_breaks,
+    artwork::lookup_artwork,
+    backup::{create_backup, restore_backup, MAX_BACKUP_SIZE},
     channels::{create_channel, delete_channel},
-    config::{get_config, PlayoutConfig, Template},
-    control::{control_state, send_message, ControlParams, Process, ProcessCtl},
+    clock_reference::clock_status,
+    cluster, companion,
+    config::{get_config, AirHours, Ingest, PlayoutConfig, Template},
+    config_file::{config_diff, export_config, parse_config, ConfigChange, ConfigDocument},
+    control::{
+        control_state, send_message, set_override, splice_cue, switch_source, ControlParams,
+        Process, ProcessCtl, Scte35Params,
+    },
+    download,
     errors::ServiceError,
+    failover,
+    ffmpeg_caps::{check_config, probe_ffmpeg, validate_config, FfmpegReport},
     files::{
-        browser, create_directory, norm_abs_path, remove_file_or_folder, rename_file, upload,
-        MoveObject, PathObject,
+        browser, create_directory, media_mime, norm_abs_path, remove_file_or_folder, rename_file,
+        storage_usage, upload, MoveObject, PathObject,
     },
+    gpu::{assign_gpu, GpuSetting},
+    health::readiness,
+    maintenance::set_maintenance,
     naive_date_time_from_str,
-    playlist::{delete_playlist, generate_playlist, read_playlist, write_playlist},
-    public_path, read_log_file, system, TextFilter,
+    overlay::{set_overlay, OverlayParams},
+    password_policy::{set_password, PasswordPolicy},
+    password_reset::{
+        reset_base, reset_mail, reset_text, reset_token, token_hash, verify_token, RESET_TTL,
+    },
+    playlist::{
+        copy_playlist, delete_playlist, diff_playlists, edit_items, generate_playlist,
+        read_playlist, read_version, repair_playlist, save_version, write_playlist, ItemOperation,
+        RepairParams,
+    },
+    pre_cache,
+    process_limits::validate_limits,
+    public_path, pull_ingest, read_log_file,
+    scripting::{check_script, load_scripts},
+    shutdown::{request_shutdown, shutdown_status},
+    simulate::simulate,
+    system,
+    thumbnails::{start_thumbnails, storyboard, thumbnail},
+    time_machine::time_now,
+    transcode,
+    trash::{list_trash, purge_trash, restore_trash},
+    trim::{
+        apply_trim, media_timing, start_analysis, trim_points, trim_preview, trim_target, TrimPoint,
+    },
+    whip, TextFilter,
 };
 use crate::{
-    api::auth::{create_jwt, Claims},
+    api::{
+        auth::{create_jwt, Claims},
+        pagination::ListQuery,
+    },
     utils::advanced_config::AdvancedConfig,
     vec_strings,
 };
 use crate::{
     db::{
         handles,
-        models::{Channel, TextPreset, User, UserMeta},
+        models::{
+            AdBreak, Channel, Companion, DownloadJob, HandoverNote, Node, OutputTarget, Permission,
+            Region, Script, TextPreset, TrimSuggestion, User, UserMeta,
+        },
+    },
+    player::{
+        controller::{ChannelController, ChannelManager, ProcessUnit},
+        input::{air::AirStatus, emergency::OverrideParams, switch::SwitchParams},
+        output::{
+            regions::{
+                load_regions, read_region_playlist, region_status, write_region_playlist,
+                RegionStatus,
+            },
+            targets::{check_target, keep_secrets, masked, test_connect},
+        },
+    },
+    sse::{
+        broadcast::Broadcaster,
+        events::{ChannelEvent, ConfigKind, PlaylistAction},
     },
-    player::controller::ChannelController,
 };
 use crate::{
     player::utils::{
-        get_data_map, get_date_range, import::import_file, sec_to_time, time_to_sec, JsonPlaylist,
+        chapters::split_by_chapters,
+        get_data_map, get_date_range,
+        import::import_file,
+        markdown_to_text,
+        now_playing::{check_access, now_next},
+        sec_to_time,
+        stream_stats::stream_stats,
+        time_in_seconds, time_to_sec, JsonPlaylist, Media,
     },
-    utils::logging::MailQueue,
+    utils::{
+        logging::{send_mail, MailQueue, Target},
+        stream_keys::{
+            add_grace_key, check_publisher, is_local_host, rotate_url_keys, KEY_GRACE_PERIOD,
+        },
+    },
+    ARGS,
 };
 
-use dirs::home_dir;
-use once_cell::sync::Lazy;
-use std::process::Stdio;
-use tokio::fs::metadata;
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Child;
-use tokio::process::Command;
 use tokio::time::Duration;
-use url::Url;
-//use actix_web::route;
-//use reqwest::Method;
-//use shell_escape::escape;
-//use std::borrow::Cow;
-//use serde_json::json;
-use actix_web::Scope;
-use thiserror::Error;
-use tokio::sync::Mutex as AsyncMutex;
-use tokio::time::timeout;
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct AuditObj {
+    #[serde(default)]
+    from: Option<String>,
+    #[serde(default)]
+    to: Option<String>,
+}
+
+impl AuditObj {
+    /// Time range for the database, a date only `to` includes the full day.
+    fn range(&self) -> (String, String) {
+        let from = self.from.clone().unwrap_or_default().replace('T', " ");
+        let mut to = self
+            .to
+            .clone()
+            .unwrap_or_else(|| "9999-12-31".to_string())
+            .replace('T', " ");
+
+        if to.len() == 10 {
+            to.push_str(" 23:59:59");
+        }
+
+        (from, to)
+    }
+}
 
 #[derive(Serialize)]
 struct UserObj<T> {
@@ -97,18 +175,54 @@ struct UserObj<T> {
     user: Option<T>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ResetRequestObj {
+    username: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResetObj {
+    token: String,
+    password: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct DateObj {
     #[serde(default)]
     date: String,
 }
 
+#[derive(Debug, Serialize)]
+struct RegionInfo {
+    #[serde(flatten)]
+    region: Region,
+    status: RegionStatus,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 struct FileObj {
     #[serde(default)]
     path: PathBuf,
 }
 
+#[derive(Debug, Deserialize)]
+struct FileQuery {
+    #[serde(default)]
+    download: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenObj {
+    #[serde(default)]
+    token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ItemsObj {
+    date: String,
+    operations: Vec<ItemOperation>,
+}
+
 #[derive(Debug, Default, Deserialize, Serialize)]
 pub struct PathsObj {
     #[serde(default)]
@@ -122,14 +236,73 @@ pub struct ImportObj {
     file: PathBuf,
     #[serde(default)]
     date: String,
+    #[serde(default)]
+    chapters: bool,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ChapterObj {
+    path: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ThumbnailObj {
+    path: String,
+    /// Time in seconds, without it the frame from 10% of the file is used.
+    t: Option<f64>,
+    /// Storyboard sprite instead of a single frame.
+    #[serde(default)]
+    sprite: bool,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TrimObj {
+    #[serde(default)]
+    path: String,
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TrimMode {
+    /// In/out points are stored with the trim suggestions.
+    #[default]
+    Virtual,
+    /// Trimmed copy from the transcoding queue.
+    Copy,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TrimClipObj {
+    source: String,
+    #[serde(rename = "in")]
+    seek: TrimPoint,
+    out: TrimPoint,
+    #[serde(default)]
+    mode: TrimMode,
+    /// File name of the copy.
+    target: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TrimPreviewObj {
+    path: String,
+    #[serde(rename = "in")]
+    seek: TrimPoint,
+    out: TrimPoint,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TranscodeObj {
+    /// Only jobs with this status.
+    status: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct ProgramObj {
     #[serde(default = "time_after", deserialize_with = "naive_date_time_from_str")]
-    start_after: NaiveDateTime,
+    pub start_after: NaiveDateTime,
     #[serde(default = "time_before", deserialize_with = "naive_date_time_from_str")]
-    start_before: NaiveDateTime,
+    pub start_before: NaiveDateTime,
 }
 
 fn time_after() -> NaiveDateTime {
@@ -151,16 +324,323 @@ fn time_before() -> NaiveDateTime {
 }
 
 #[derive(Debug, Serialize)]
-struct ProgramItem {
-    source: String,
-    start: String,
-    title: Option<String>,
-    r#in: f64,
-    out: f64,
-    duration: f64,
-    category: String,
-    description: Option<String>,
-    enable_description: Option<bool>,
+pub struct ProgramItem {
+    pub source: String,
+    pub start: String,
+    pub title: Option<String>,
+    pub r#in: f64,
+    pub out: f64,
+    pub duration: f64,
+    pub category: String,
+    pub description: Option<String>,
+    pub enable_description: Option<bool>,
+    pub artwork: Option<String>,
+    pub rating: Option<String>,
+}
+
+/// #### Health
+///
+/// Liveness probe, the server answers. It needs no authentication.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/health/live
+/// ```
+#[get("/health/live")]
+pub async fn health_live() -> Result<impl Responder, ServiceError> {
+    Ok(web::Json(serde_json::json!({
+        "status": "ok",
+        "version": env!("CARGO_PKG_VERSION"),
+    })))
+}
+
+/// Readiness probe, with the database, ffmpeg, free disk space and the channel states.
+/// The status code is 503 when one check failed. It needs no authentication, so it shows
+/// only the checks and if they passed, the details are in `/api/system/health`.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/health/ready
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// {
+///     "status": "ok",
+///     "checks": [
+///         { "name": "database", "ok": true },
+///         { "name": "ffmpeg", "ok": true },
+///         { "name": "disk", "ok": true },
+///         { "name": "channels", "ok": true }
+///     ]
+/// }
+/// ```
+#[get("/health/ready")]
+pub async fn health_ready(
+    pool: web::Data<Pool<Sqlite>>,
+    controllers: web::Data<Mutex<ChannelController>>,
+) -> Result<impl Responder, ServiceError> {
+    let health = readiness(&pool, &controllers).await;
+
+    if health.is_ok() {
+        Ok(HttpResponse::Ok().json(health.summary()))
+    } else {
+        Ok(HttpResponse::ServiceUnavailable().json(health.summary()))
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct PublishObj {
+    /// Stream name from nginx-rtmp.
+    name: String,
+    /// Stream name from SRS.
+    stream: String,
+    /// Key as query parameter from the publish url.
+    key: String,
+    /// Query string from SRS, like `?key=<KEY>`.
+    param: String,
+    /// Client address from nginx-rtmp.
+    addr: String,
+    /// Client address from SRS.
+    ip: String,
+}
+
+impl PublishObj {
+    fn key(&self) -> String {
+        let from_param = self
+            .param
+            .trim_start_matches('?')
+            .split('&')
+            .find_map(|p| p.strip_prefix("key="));
+
+        [
+            self.key.as_str(),
+            from_param.unwrap_or_default(),
+            &self.stream,
+            &self.name,
+        ]
+        .into_iter()
+        .find(|k| !k.trim().is_empty())
+        .unwrap_or_default()
+        .to_string()
+    }
+}
+
+/// **Ingest Publisher Authentication**
+///
+/// Callback for the `on_publish` hook from an RTMP server (nginx-rtmp or SRS) in front of the ingest.
+/// The publisher needs a valid stream key and an address from the allowlist.
+/// Accepts JSON (SRS) and form data (nginx-rtmp), the status code is 403 when the publisher is rejected.
+/// It needs no authentication.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/ingest/1/auth -H "Content-Type: application/json" \
+/// -d '{ "stream": "<KEY>", "ip": "203.0.113.10" }'
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// { "code": 0 }
+/// ```
+#[post("/ingest/{id}/auth")]
+pub async fn ingest_auth(
+    id: web::Path<i32>,
+    data: Either<web::Json<PublishObj>, web::Form<PublishObj>>,
+    controllers: web::Data<Mutex<ChannelController>>,
+) -> Result<impl Responder, ServiceError> {
+    let publisher = data.into_inner();
+    let key = publisher.key();
+    let addr = [&publisher.addr, &publisher.ip]
+        .into_iter()
+        .find(|a| !a.is_empty())
+        .cloned()
+        .unwrap_or_default();
+    let manager = controllers.lock().unwrap().get(*id);
+
+    let result = match manager {
+        Some(manager) => {
+            let ingest = manager.config.lock().unwrap().ingest.clone();
+            check_publisher(*id, &ingest, &key, &addr)
+        }
+        None => Err("Channel not exists".to_string()),
+    };
+
+    match result {
+        Ok(_) => {
+            info!(target: Target::file(), channel = *id; "Ingest publisher <b><magenta>{addr}</></b> accepted");
+
+            Ok(HttpResponse::Ok().json(serde_json::json!({ "code": 0 })))
+        }
+        Err(e) => {
+            warn!(target: Target::file(), channel = *id; "Ingest publisher <b><magenta>{addr}</></b> rejected: {e}");
+
+            Ok(HttpResponse::Forbidden().json(serde_json::json!({ "code": 403, "error": e })))
+        }
+    }
+}
+
+fn whip_ingest(
+    controllers: &web::Data<Mutex<ChannelController>>,
+    req: &HttpRequest,
+    id: i32,
+) -> Result<Ingest, ServiceError> {
+    let manager = controllers
+        .lock()
+        .unwrap()
+        .get(id)
+        .ok_or_else(|| ServiceError::BadRequest(format!("Channel ({id}) not exists!")))?;
+    let ingest = manager.config.lock().unwrap().ingest.clone();
+    let key = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(whip::bearer_key);
+
+    whip::check_client(id, &ingest, key)?;
+
+    Ok(ingest)
+}
+
+/// **WHIP Publish**
+///
+/// WebRTC ingest over a gateway (MediaMTX or SRS), the stream key is the bearer token.
+/// The body is the SDP offer, the response the SDP answer with the session in the location header.
+/// It needs no login.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/1/whip -H "Content-Type: application/sdp" \
+/// -H "Authorization: Bearer <KEY>" --data-binary @offer.sdp
+/// ```
+#[post("/{id}/whip")]
+pub async fn whip_publish(
+    id: web::Path<i32>,
+    req: HttpRequest,
+    offer: String,
+    controllers: web::Data<Mutex<ChannelController>>,
+) -> Result<impl Responder, ServiceError> {
+    let ingest = whip_ingest(&controllers, &req, *id)?;
+    let (session, answer) = whip::publish(*id, &ingest, offer).await?;
+
+    info!(target: Target::file(), channel = *id; "WHIP session <b><magenta>{session}</></b> started");
+
+    Ok(HttpResponse::Created()
+        .content_type("application/sdp")
+        .insert_header(("Location", format!("/{id}/whip/{session}")))
+        .body(answer))
+}
+
+/// **WHIP Session Update**
+///
+/// Trickle ICE candidates and ICE restarts, they go unchanged to the gateway.
+///
+/// ```BASH
+/// curl -X PATCH http://127.0.0.1:8787/1/whip/<SESSION> -H "Content-Type: application/trickle-ice-sdpfrag" \
+/// -H "Authorization: Bearer <KEY>" --data-binary @candidate.sdpfrag
+/// ```
+#[patch("/{id}/whip/{session}")]
+pub async fn whip_update(
+    path: web::Path<(i32, String)>,
+    req: HttpRequest,
+    body: String,
+    controllers: web::Data<Mutex<ChannelController>>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, session) = path.into_inner();
+    whip_ingest(&controllers, &req, id)?;
+    let content_type = req
+        .headers()
+        .get("Content-Type")
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("application/trickle-ice-sdpfrag");
+    let (status, answer) = whip::update(id, &session, content_type, body).await?;
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_GATEWAY);
+
+    Ok(HttpResponse::build(status)
+        .content_type(content_type)
+        .body(answer))
+}
+
+/// **WHIP Session Stop**
+///
+/// ```BASH
+/// curl -X DELETE http://127.0.0.1:8787/1/whip/<SESSION> -H "Authorization: Bearer <KEY>"
+/// ```
+#[delete("/{id}/whip/{session}")]
+pub async fn whip_stop(
+    path: web::Path<(i32, String)>,
+    req: HttpRequest,
+    controllers: web::Data<Mutex<ChannelController>>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, session) = path.into_inner();
+    whip_ingest(&controllers, &req, id)?;
+    whip::stop(id, &session).await?;
+
+    info!(target: Target::file(), channel = id; "WHIP session <b><magenta>{session}</></b> stopped");
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// **Failover Heartbeat**
+///
+/// State from this instance, for the other instance of a failover pair.
+/// It needs the shared failover token instead of a login.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/failover/heartbeat -H "Authorization: Bearer <FAILOVER_TOKEN>"
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// {
+///     "role": "primary",
+///     "active": true,
+///     "channels": [
+///         { "id": 1, "running": true, "revision": "8c1f0e2d4b6a9735", "current": "/tv-media/clip.mp4", "index": 12 }
+///     ]
+/// }
+/// ```
+#[get("/failover/heartbeat")]
+pub async fn failover_heartbeat(
+    req: HttpRequest,
+    controllers: web::Data<Mutex<ChannelController>>,
+) -> Result<impl Responder, ServiceError> {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok());
+    failover::check_token(token)?;
+
+    Ok(web::Json(failover::heartbeat(&controllers.lock().unwrap())))
+}
+
+/// **Failover Config**
+///
+/// Playout config from a channel, the backup takes it over, when it changes on the primary.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/failover/config/1 -H "Authorization: Bearer <FAILOVER_TOKEN>"
+/// ```
+#[get("/failover/config/{id}")]
+pub async fn failover_config(
+    id: web::Path<i32>,
+    req: HttpRequest,
+    controllers: web::Data<Mutex<ChannelController>>,
+) -> Result<impl Responder, ServiceError> {
+    let token = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok());
+    failover::check_token(token)?;
+
+    let manager = controllers
+        .lock()
+        .unwrap()
+        .get(*id)
+        .ok_or_else(|| ServiceError::BadRequest(format!("Channel ({id}) not exists!")))?;
+    let config = manager.config.lock().unwrap().clone();
+
+    Ok(web::Json(config))
 }
 
 /// #### User Handling
@@ -205,11 +685,18 @@ pub async fn login(
             .await?;
 
             if verified_password.is_ok() {
+                let permissions = handles::select_user_permissions(&pool, &user, &role).await?;
+                let policy = handles::select_password_policy(&pool).await?;
+
+                if policy.is_expired(user.password_changed, time_now().timestamp()) {
+                    user.must_change_password = true;
+                }
                 let claims = Claims::new(
                     user.id,
                     user.channel_ids.clone().unwrap_or_default(),
                     username.clone(),
                     role.clone(),
+                    permissions,
                 );
 
                 if let Ok(token) = create_jwt(claims).await {
@@ -247,8 +734,110 @@ pub async fn login(
     }
 }
 
-/// From here on all request **must** contain the authorization header:\
-/// `"Authorization: Bearer <TOKEN>"`
+/// **Request Password Reset**
+///
+/// Send a link to set a new password to the mail address of the user, the `username` can also be
+/// the mail address. The link expires after one hour and points to `--public-url`.
+/// The response is the same, when no user matches or the mail can not be sent.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/auth/reset-request/ -H "Content-Type: application/json" \
+/// -d '{ "username": "<USER>" }'
+/// ```
+#[post("/auth/reset-request/")]
+pub async fn reset_request(
+    pool: web::Data<Pool<Sqlite>>,
+    data: web::Json<ResetRequestObj>,
+) -> Result<impl Responder, ServiceError> {
+    let global = GLOBAL_SETTINGS.get().unwrap();
+    let secret = global.secret.clone().unwrap_or_default();
+    let mut mail = reset_mail(global, "").ok_or_else(|| {
+        ServiceError::ServiceUnavailable("Mail server is not configured".to_string())
+    })?;
+    let base = reset_base(ARGS.public_url.as_deref()).ok_or_else(|| {
+        ServiceError::ServiceUnavailable("Public URL is not configured".to_string())
+    })?;
+    let users = handles::select_reset_users(&pool, data.username.trim())
+        .await
+        .unwrap_or_else(|e| {
+            error!("Password reset: {e}");
+            vec![]
+        });
+
+    // every outcome gets the same response, so it tells nothing about the accounts
+    for user in users {
+        let Some(address) = user.mail.filter(|m| m.contains('@')) else {
+            warn!(
+                "Password reset for {}: user has no mail address",
+                user.username
+            );
+            continue;
+        };
+
+        let expires = time_now().timestamp() + RESET_TTL;
+        let token = reset_token(&secret, user.id, expires);
+
+        if let Err(e) =
+            handles::insert_password_reset(&pool, user.id, &token_hash(&token), expires).await
+        {
+            error!("Password reset for {} failed: {e}", user.username);
+            continue;
+        }
+
+        mail.recipient = address;
+
+        if let Err(e) = send_mail(
+            &mail,
+            reset_text(&user.username, &format!("{base}/?reset={token}")),
+        )
+        .await
+        {
+            error!(
+                "Sending password reset mail to {} failed: {e}",
+                user.username
+            );
+            continue;
+        }
+
+        info!("Password reset requested for user {}", user.username);
+    }
+
+    Ok(web::Json("When the user exists, a reset link is sent"))
+}
+
+/// **Reset Password**
+///
+/// Set a new password with the token from the reset mail, the token works only once.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/auth/reset/ -H "Content-Type: application/json" \
+/// -d '{ "token": "<TOKEN>", "password": "<PASS>" }'
+/// ```
+#[post("/auth/reset/")]
+pub async fn reset_password(
+    pool: web::Data<Pool<Sqlite>>,
+    data: web::Json<ResetObj>,
+) -> Result<impl Responder, ServiceError> {
+    let global = GLOBAL_SETTINGS.get().unwrap();
+    let now = time_now().timestamp();
+    let user_id = verify_token(&global.secret.clone().unwrap_or_default(), &data.token, now)?;
+
+    if !handles::select_password_reset(&pool, user_id, &token_hash(&data.token), now).await? {
+        return Err(ServiceError::Forbidden(
+            "Invalid or expired reset token".to_string(),
+        ));
+    }
+
+    set_password(&pool, user_id, &data.password, false).await?;
+    handles::delete_password_resets(&pool, user_id).await?;
+
+    info!("Password from user {user_id} is reset");
+
+    Ok(web::Json("Password changed"))
+}
+
+// From here on all request **must** contain the authorization header:\
+// `"Authorization: Bearer <TOKEN>"`
 
 /// **Get current User**
 ///
@@ -303,9 +892,12 @@ async fn get_by_name(
 /// ```
 #[get("/users")]
 #[protect("Role::GlobalAdmin", ty = "Role")]
-async fn get_users(pool: web::Data<Pool<Sqlite>>) -> Result<impl Responder, ServiceError> {
+async fn get_users(
+    pool: web::Data<Pool<Sqlite>>,
+    list: ListQuery,
+) -> Result<impl Responder, ServiceError> {
     match handles::select_users(&pool).await {
-        Ok(users) => Ok(web::Json(users)),
+        Ok(users) => Ok(web::Json(list.response(users)?)),
         Err(e) => {
             error!("{e}");
             Err(ServiceError::InternalServerError)
@@ -333,6 +925,7 @@ async fn update_user(
     user: web::ReqData<UserMeta>,
 ) -> Result<impl Responder, ServiceError> {
     let channel_ids = data.channel_ids.clone().unwrap_or_default();
+    let permissions = data.permissions.clone();
     let mut fields = String::new();
 
     if let Some(mail) = data.mail.clone() {
@@ -343,27 +936,39 @@ async fn update_user(
         fields.push_str(&format!("mail = '{mail}'"));
     }
 
+    // only global admins can ask other users for a new password
+    let other_user = *id != user.id && role.has_authority(&Role::GlobalAdmin);
+    let must_change = other_user && data.must_change_password;
+
     if !data.password.is_empty() {
+        set_password(&pool, *id, &data.password, must_change).await?;
+    } else if other_user {
         if !fields.is_empty() {
             fields.push_str(", ");
         }
 
-        let password_hash = web::block(move || {
-            let salt = SaltString::generate(&mut OsRng);
-
-            let argon = Argon2::default()
-                .hash_password(data.password.clone().as_bytes(), &salt)
-                .map(|p| p.to_string());
+        fields.push_str(&format!("must_change = {}", i32::from(must_change)));
+    }
 
-            argon
-        })
-        .await?
-        .unwrap();
+    // permissions can only be changed by global admins, an empty list resets them to the role defaults
+    if let Some(permissions) = permissions.filter(|_| role.has_authority(&Role::GlobalAdmin)) {
+        if !fields.is_empty() {
+            fields.push_str(", ");
+        }
 
-        fields.push_str(&format!("password = '{password_hash}'"));
+        if permissions.is_empty() {
+            fields.push_str("permissions = NULL");
+        } else {
+            fields.push_str(&format!(
+                "permissions = '{}'",
+                Permission::join(&permissions)
+            ));
+        }
     }
 
-    handles::update_user(&pool, *id, fields).await?;
+    if !fields.is_empty() {
+        handles::update_user(&pool, *id, fields).await?;
+    }
 
     let related_channels = handles::select_related_channels(&pool, Some(*id)).await?;
 
@@ -391,6 +996,10 @@ async fn add_user(
     pool: web::Data<Pool<Sqlite>>,
     data: web::Json<User>,
 ) -> Result<impl Responder, ServiceError> {
+    handles::select_password_policy(&pool)
+        .await?
+        .check(&data.password)?;
+
     match handles::insert_user(&pool, data.into_inner()).await {
         Ok(..) => Ok("Add User Success"),
         Err(e) => {
@@ -421,18 +1030,102 @@ async fn remove_user(
     }
 }
 
-/// #### Settings
+/// **Get Permissions**
 ///
-/// **Get Settings from Channel**
+/// Permission matrix, with the default permissions of each role. Global admins can always do everything.
 ///
 /// ```BASH
-/// curl -X GET http://127.0.0.1:8787/api/channel/1 -H "Authorization: Bearer <TOKEN>"
+/// curl -X GET 'http://127.0.0.1:8787/api/permissions' -H 'Authorization: Bearer <TOKEN>'
 /// ```
+#[get("/permissions")]
+#[protect("Role::GlobalAdmin", ty = "Role")]
+async fn get_permissions(pool: web::Data<Pool<Sqlite>>) -> Result<impl Responder, ServiceError> {
+    let matrix = handles::select_role_permissions(&pool).await?;
+
+    Ok(web::Json(matrix))
+}
+
+/// **Update Role Permissions**
 ///
-/// **Response:**
-///
-/// ```JSON
-/// {
+/// ```BASH
+/// curl -X PUT 'http://127.0.0.1:8787/api/permissions/3' -H 'Content-Type: application/json' \
+/// -d '["playlist.edit", "file.edit"]' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[put("/permissions/{role}")]
+#[protect("Role::GlobalAdmin", ty = "Role")]
+async fn update_permissions(
+    pool: web::Data<Pool<Sqlite>>,
+    role_id: web::Path<i32>,
+    data: web::Json<Vec<Permission>>,
+) -> Result<impl Responder, ServiceError> {
+    handles::select_role(&pool, &role_id).await?;
+    handles::update_role_permissions(&pool, *role_id, &data).await?;
+
+    Ok("Update Success")
+}
+
+/// **Get Password Policy**
+///
+/// ```BASH
+/// curl -X GET 'http://127.0.0.1:8787/api/password-policy' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// {
+///     "min_length": 8,
+///     "uppercase": false,
+///     "lowercase": false,
+///     "digit": false,
+///     "symbol": false,
+///     "history": 0,
+///     "max_age": 0
+/// }
+/// ```
+#[get("/password-policy")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role"
+)]
+async fn get_password_policy(
+    pool: web::Data<Pool<Sqlite>>,
+) -> Result<impl Responder, ServiceError> {
+    Ok(web::Json(handles::select_password_policy(&pool).await?))
+}
+
+/// **Update Password Policy**
+///
+/// `history` is the count of last passwords, which can not be used again,
+/// `max_age` the days until a password expires, `0` turns it off.
+///
+/// ```BASH
+/// curl -X PUT 'http://127.0.0.1:8787/api/password-policy' -H 'Content-Type: application/json' \
+/// -d '{"min_length": 12, "digit": true, "history": 3, "max_age": 90}' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[put("/password-policy")]
+#[protect("Role::GlobalAdmin", ty = "Role")]
+async fn update_password_policy(
+    pool: web::Data<Pool<Sqlite>>,
+    data: web::Json<PasswordPolicy>,
+) -> Result<impl Responder, ServiceError> {
+    handles::update_password_policy(&pool, &data).await?;
+
+    Ok("Update Success")
+}
+
+/// #### Settings
+///
+/// **Get Settings from Channel**
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/channel/1 -H "Authorization: Bearer <TOKEN>"
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// {
 ///     "id": 1,
 ///     "name": "Channel 1",
 ///     "preview_url": "http://localhost/live/preview.m3u8",
@@ -452,7 +1145,10 @@ async fn get_channel(
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
 ) -> Result<impl Responder, ServiceError> {
-    if let Ok(channel) = handles::select_channel(&pool, &id).await {
+    if let Ok(mut channel) = handles::select_channel(&pool, &id).await {
+        let storage = PathBuf::from(&channel.storage);
+        channel.storage_used = web::block(move || storage_usage(&storage)).await? as i64;
+
         return Ok(web::Json(channel));
     }
 
@@ -471,10 +1167,20 @@ async fn get_channel(
 )]
 async fn get_all_channels(
     pool: web::Data<Pool<Sqlite>>,
+    list: ListQuery,
     user: web::ReqData<UserMeta>,
 ) -> Result<impl Responder, ServiceError> {
-    if let Ok(channel) = handles::select_related_channels(&pool, Some(user.id)).await {
-        return Ok(web::Json(channel));
+    if let Ok(mut channels) = handles::select_related_channels(&pool, Some(user.id)).await {
+        channels = web::block(move || {
+            for channel in &mut channels {
+                channel.storage_used = storage_usage(Path::new(&channel.storage)) as i64;
+            }
+
+            channels
+        })
+        .await?;
+
+        return Ok(web::Json(list.response(channels)?));
     }
 
     Err(ServiceError::InternalServerError)
@@ -491,7 +1197,7 @@ async fn get_all_channels(
 #[protect(
     any("Role::GlobalAdmin", "Role::ChannelAdmin"),
     ty = "Role",
-    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ChannelEdit)"
 )]
 async fn patch_channel(
     pool: web::Data<Pool<Sqlite>>,
@@ -514,9 +1220,33 @@ async fn patch_channel(
         data.public = channel.public;
         data.playlists = channel.playlists;
         data.storage = channel.storage;
+        data.storage_quota = channel.storage_quota;
+        data.start_priority = channel.start_priority;
+        data.cpu_affinity = channel.cpu_affinity;
+        data.nice = channel.nice;
+        data.memory_limit = channel.memory_limit;
+        data.gpu = channel.gpu;
     }
 
+    validate_limits(&data.cpu_affinity, data.nice, data.memory_limit)?;
+    data.gpu
+        .parse::<GpuSetting>()
+        .map_err(ServiceError::BadRequest)?;
+
+    let maintenance = data.maintenance;
+
     handles::update_channel(&pool, *id, data).await?;
+
+    if set_maintenance(*id, maintenance) {
+        manager.channel.lock().unwrap().maintenance = maintenance;
+
+        if maintenance {
+            warn!(target: Target::file(), channel = *id; "Maintenance mode on, alerts and automatic starts are suppressed");
+        } else {
+            info!(target: Target::file(), channel = *id; "Maintenance mode off");
+        }
+    }
+
     let new_config = get_config(&pool, *id).await?;
     manager.update_config(new_config);
 
@@ -547,7 +1277,7 @@ async fn add_channel(
     .await
     {
         Ok(c) => Ok(web::Json(c)),
-        Err(e) => Err(e),
+        Err(e) => Err(e.into()),
     }
 }
 
@@ -611,18 +1341,20 @@ async fn get_advanced_config(
 /// curl -X PUT http://127.0.0.1:8787/api/playout/advanced/1 -H "Content-Type: application/json" \
 /// -d { <CONFIG DATA> } -H 'Authorization: Bearer <TOKEN>'
 /// ```
+#[allow(clippy::too_many_arguments)]
 #[put("/playout/advanced/{id}")]
 #[protect(
     "Role::GlobalAdmin",
     "Role::ChannelAdmin",
     ty = "Role",
-    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ConfigEdit)"
 )]
 async fn update_advanced_config(
     pool: web::Data<Pool<Sqlite>>,
     id: web::Path<i32>,
     data: web::Json<AdvancedConfig>,
     controllers: web::Data<Mutex<ChannelController>>,
+    broadcaster: web::Data<Broadcaster>,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
 ) -> Result<impl Responder, ServiceError> {
@@ -633,6 +1365,11 @@ async fn update_advanced_config(
 
     manager.update_config(new_config);
 
+    let event = ChannelEvent::Config {
+        kind: ConfigKind::Advanced,
+    };
+    broadcaster.notify(*id, event).await;
+
     Ok(web::Json("Update success"))
 }
 
@@ -671,17 +1408,19 @@ async fn get_playout_config(
 /// curl -X PUT http://127.0.0.1:8787/api/playout/config/1 -H "Content-Type: application/json" \
 /// -d { <CONFIG DATA> } -H 'Authorization: Bearer <TOKEN>'
 /// ```
+#[allow(clippy::too_many_arguments)]
 #[put("/playout/config/{id}")]
 #[protect(
     any("Role::GlobalAdmin", "Role::ChannelAdmin"),
     ty = "Role",
-    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ConfigEdit)"
 )]
 async fn update_playout_config(
     pool: web::Data<Pool<Sqlite>>,
     id: web::Path<i32>,
     mut data: web::Json<PlayoutConfig>,
     controllers: web::Data<Mutex<ChannelController>>,
+    broadcaster: web::Data<Broadcaster>,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
 ) -> Result<impl Responder, ServiceError> {
@@ -701,6 +1440,338 @@ async fn update_playout_config(
     handles::update_configuration(&pool, config_id, data.clone()).await?;
     let new_config = get_config(&pool, *id).await?;
 
+    validate_config(&new_config);
+    manager.update_config(new_config);
+
+    let event = ChannelEvent::Config {
+        kind: ConfigKind::Playout,
+    };
+    broadcaster.notify(*id, event).await;
+
+    Ok(web::Json("Update success"))
+}
+
+/// **Export Config**
+///
+/// Playout and advanced config of the channel as one TOML document, for versioning the settings
+/// or moving them to another channel or server.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/playout/export/1 -H 'Authorization: Bearer <TOKEN>' -o channel_1.toml
+/// ```
+#[get("/playout/export/{id}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+async fn export_playout_config(
+    id: web::Path<i32>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers
+        .lock()
+        .unwrap()
+        .get(*id)
+        .ok_or_else(|| ServiceError::BadRequest(format!("Channel ({id}) not exists!")))?;
+    let config = manager.config.lock().unwrap().clone();
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/toml")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!("channel_{id}.toml"))],
+        })
+        .body(export_config(&config)?))
+}
+
+#[derive(Debug, Deserialize)]
+struct ConfigImportObj {
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ImportResult {
+    dry_run: bool,
+    changes: Vec<ConfigChange>,
+}
+
+/// **Import Config**
+///
+/// Import a TOML document from `/playout/export`. Missing keys, wrong types and unknown keys are rejected.
+/// Returns the changed keys with their old and new values, with `dry_run=true` nothing is saved.
+///
+/// ```BASH
+/// curl -X POST 'http://127.0.0.1:8787/api/playout/import/1?dry_run=true' -H "Content-Type: application/toml" \
+/// --data-binary @channel_1.toml -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[allow(clippy::too_many_arguments)]
+#[post("/playout/import/{id}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ConfigEdit)"
+)]
+async fn import_playout_config(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    obj: web::Query<ConfigImportObj>,
+    body: String,
+    controllers: web::Data<Mutex<ChannelController>>,
+    broadcaster: web::Data<Broadcaster>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers
+        .lock()
+        .unwrap()
+        .get(*id)
+        .ok_or_else(|| ServiceError::BadRequest(format!("Channel ({id}) not exists!")))?;
+    let p = manager.channel.lock().unwrap().storage.clone();
+    let storage = Path::new(&p);
+    let current = manager.config.lock().unwrap().clone();
+    let mut doc = parse_config(&body)?;
+
+    let (_, _, logo) = norm_abs_path(storage, &doc.playout.processing.logo)?;
+    let (_, _, filler) = norm_abs_path(storage, &doc.playout.storage.filler)?;
+    let (_, _, font) = norm_abs_path(storage, &doc.playout.text.font)?;
+
+    doc.playout.processing.logo = logo;
+    doc.playout.storage.filler = filler;
+    doc.playout.text.font = font;
+    // comes from the channel, not from the config
+    doc.playout.storage.shared_storage = current.storage.shared_storage;
+
+    let changes = config_diff(
+        &serde_json::to_value(ConfigDocument::new(&current))?,
+        &serde_json::to_value(&doc)?,
+    );
+
+    if !obj.dry_run && !changes.is_empty() {
+        handles::update_configuration(&pool, current.general.id, doc.playout).await?;
+        handles::update_advanced_configuration(&pool, *id, doc.advanced).await?;
+        let new_config = get_config(&pool, *id).await?;
+
+        manager.update_config(new_config);
+
+        for kind in [ConfigKind::Playout, ConfigKind::Advanced] {
+            broadcaster.notify(*id, ChannelEvent::Config { kind }).await;
+        }
+    }
+
+    Ok(web::Json(ImportResult {
+        dry_run: obj.dry_run,
+        changes,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct RotateObj {
+    grace: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct RotatedKeys {
+    ingest_key: Option<String>,
+    output_keys: Vec<String>,
+    grace_seconds: u64,
+    output_restart: bool,
+}
+
+/// **Rotate Stream Keys**
+///
+/// Generate new keys for the ingest and for local restream targets (rtmp urls to localhost) in the output.
+/// An idle ingest listener restarts directly with the new key, a running one when the stream ends.
+/// Old ingest keys are still accepted during the grace period (default 600 seconds).
+/// Output keys take effect with the next clip. Mail recipients from the channel get the new keys.
+///
+/// ```BASH
+/// curl -X POST 'http://127.0.0.1:8787/api/channel/1/rotate-keys?grace=300' \
+/// -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/channel/{id}/rotate-keys")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ChannelEdit)"
+)]
+async fn rotate_stream_keys(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    obj: web::Query<RotateObj>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers
+        .lock()
+        .unwrap()
+        .get(*id)
+        .ok_or_else(|| ServiceError::BadRequest(format!("Channel ({id}) not exists!")))?;
+    let mut config = manager.config.lock().unwrap().clone();
+    let grace = obj.grace.unwrap_or(KEY_GRACE_PERIOD);
+
+    let (ingest_param, ingest_keys) = rotate_url_keys(&config.ingest.input_param, true, |_| true);
+    let (output_param, output_keys) =
+        rotate_url_keys(&config.output.output_param, false, is_local_host);
+
+    if ingest_keys.is_empty() && output_keys.is_empty() {
+        return Err(ServiceError::BadRequest(
+            "No stream keys found in ingest or output parameters!".to_string(),
+        ));
+    }
+
+    config.ingest.input_param = ingest_param;
+    config.output.output_param = output_param;
+
+    handles::update_configuration(&pool, config.general.id, config.clone()).await?;
+    let new_config = get_config(&pool, *id).await?;
+    let mail = new_config.mail.clone();
+
+    manager.update_config(new_config);
+
+    for (old_key, _) in &ingest_keys {
+        add_grace_key(*id, old_key.clone(), Duration::from_secs(grace));
+    }
+
+    // restart idle listener, a connected stream keeps running until it ends
+    if !ingest_keys.is_empty() && !manager.ingest_is_running.load(Ordering::SeqCst) {
+        if let Err(e) = manager.stop(ProcessUnit::Ingest) {
+            error!(target: Target::file_mail(), channel = *id; "{e}");
+        }
+    }
+
+    let result = RotatedKeys {
+        ingest_key: ingest_keys.first().map(|(_, k)| k.clone()),
+        output_keys: output_keys.into_iter().map(|(_, k)| k).collect(),
+        grace_seconds: grace,
+        output_restart: manager.is_alive.load(Ordering::SeqCst),
+    };
+
+    info!(target: Target::file(), channel = *id; "Stream keys rotated by user <b><magenta>{}</></b>", user.id);
+
+    if mail.show && mail.recipient.contains('@') {
+        let mut msg = format!("Stream keys from channel {id} are rotated.\n");
+
+        if let Some(key) = &result.ingest_key {
+            msg.push_str(&format!(
+                "\nNew ingest key: {key}\nOld key stays valid for {grace} seconds.\n"
+            ));
+        }
+
+        for key in &result.output_keys {
+            msg.push_str(&format!("\nNew output key: {key}"));
+        }
+
+        if let Err(e) = send_mail(&mail, msg).await {
+            error!(target: Target::file(), channel = *id; "Sending key rotation mail failed: {e}");
+        }
+    }
+
+    Ok(web::Json(result))
+}
+
+/// **Air Time**
+///
+/// Broadcast hours of the channel, with the current state.
+/// `next_change` is the time of the day, when the channel goes on or off air.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/channel/1/airtime -H 'Authorization: Bearer <TOKEN>'
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// {
+///     "air_hours": {
+///         "enable": true,
+///         "start": "06:00",
+///         "end": "24:00",
+///         "off_air": "slate",
+///         "slate": "slates/off_air.jpg"
+///     },
+///     "enable": true,
+///     "on_air": true,
+///     "off_air": "slate",
+///     "next_change": "00:00:00.000",
+///     "running": true
+/// }
+/// ```
+#[get("/channel/{id}/airtime")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+async fn get_airtime(
+    id: web::Path<i32>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers
+        .lock()
+        .unwrap()
+        .get(*id)
+        .ok_or_else(|| ServiceError::BadRequest(format!("Channel ({id}) not exists!")))?;
+    let air_hours = manager.config.lock().unwrap().general.air_hours.clone();
+    let mut data = serde_json::to_value(AirStatus::new(&air_hours, time_in_seconds()))?;
+
+    data["air_hours"] = serde_json::to_value(air_hours)?;
+    data["running"] = serde_json::json!(manager.is_alive.load(Ordering::SeqCst));
+
+    Ok(web::Json(data))
+}
+
+/// Change the broadcast hours, the engine switches the channel at the next check.
+///
+/// ```BASH
+/// curl -X PUT http://127.0.0.1:8787/api/channel/1/airtime -H 'Content-Type: application/json' \
+/// -d '{ "enable": true, "start": "06:00", "end": "24:00", "off_air": "off", "slate": "" }' \
+/// -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[put("/channel/{id}/airtime")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ConfigEdit)"
+)]
+async fn update_airtime(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    data: web::Json<AirHours>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers
+        .lock()
+        .unwrap()
+        .get(*id)
+        .ok_or_else(|| ServiceError::BadRequest(format!("Channel ({id}) not exists!")))?;
+    let mut config = manager.config.lock().unwrap().clone();
+    let mut air_hours = data.into_inner();
+
+    if !air_hours.is_valid() {
+        return Err(ServiceError::BadRequest(
+            "Start and end must be times of the day (HH:MM)!".into(),
+        ));
+    }
+
+    if !air_hours.slate.is_empty() {
+        let (_, _, slate) = norm_abs_path(&config.channel.storage, &air_hours.slate)?;
+        air_hours.slate = slate;
+    }
+
+    config.general.air_hours = air_hours;
+
+    handles::update_configuration(&pool, config.general.id, config).await?;
+    let new_config = get_config(&pool, *id).await?;
+
     manager.update_config(new_config);
 
     Ok(web::Json("Update success"))
@@ -725,11 +1796,12 @@ async fn update_playout_config(
 async fn get_presets(
     pool: web::Data<Pool<Sqlite>>,
     id: web::Path<i32>,
+    list: ListQuery,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
 ) -> Result<impl Responder, ServiceError> {
     if let Ok(presets) = handles::select_presets(&pool, *id).await {
-        return Ok(web::Json(presets));
+        return Ok(web::Json(list.response(presets)?));
     }
 
     Err(ServiceError::InternalServerError)
@@ -746,7 +1818,7 @@ async fn get_presets(
 #[protect(
     any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
     ty = "Role",
-    expr = "user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)"
+    expr = "(user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::PresetEdit)"
 )]
 async fn update_preset(
     pool: web::Data<Pool<Sqlite>>,
@@ -778,7 +1850,7 @@ async fn update_preset(
 #[protect(
     any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
     ty = "Role",
-    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::PresetEdit)"
 )]
 async fn add_preset(
     pool: web::Data<Pool<Sqlite>>,
@@ -807,7 +1879,7 @@ async fn add_preset(
 #[protect(
     any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
     ty = "Role",
-    expr = "user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)"
+    expr = "(user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::PresetEdit)"
 )]
 async fn delete_preset(
     pool: web::Data<Pool<Sqlite>>,
@@ -824,1624 +1896,4379 @@ async fn delete_preset(
     Err(ServiceError::InternalServerError)
 }
 
-/// ### ffplayout controlling
+/// #### Scripts
 ///
-/// here we communicate with the engine for:
-/// - jump to last or next clip
-/// - reset playlist state
-/// - get infos about current, next, last clip
-/// - send text to the engine, for overlaying it (as lower third etc.)
+/// Lua scripts with scheduling rules, they run when scripting is enabled in the channel config.
 ///
-/// **Send Text to ffplayout**
+/// **Get Scripts**
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/control/1/text/ \
-/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>' \
-/// -d '{"text": "Hello from ffplayout", "x": "(w-text_w)/2", "y": "(h-text_h)/2", fontsize": "24", "line_spacing": "4", "fontcolor": "#ffffff", "box": "1", "boxcolor": "#000000", "boxborderw": "4", "alpha": "1.0"}'
+/// curl -X GET http://127.0.0.1:8787/api/scripts/1 -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[post("/control/{id}/text/")]
+#[get("/scripts/{id}")]
 #[protect(
-    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    any("Role::GlobalAdmin", "Role::ChannelAdmin"),
     ty = "Role",
     expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
 )]
-pub async fn send_text_message(
+async fn get_scripts(
+    pool: web::Data<Pool<Sqlite>>,
     id: web::Path<i32>,
-    data: web::Json<TextFilter>,
-    controllers: web::Data<Mutex<ChannelController>>,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
 ) -> Result<impl Responder, ServiceError> {
-    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let scripts = handles::select_scripts(&pool, *id).await?;
 
-    match send_message(manager, data.into_inner()).await {
-        Ok(res) => Ok(web::Json(res)),
-        Err(e) => Err(e),
-    }
+    Ok(web::Json(scripts))
 }
 
-/// **Control Playout**
+/// **Add Script**
 ///
-/// - next
-/// - back
-/// - reset
+/// The script is checked before it is saved, it must return a table with hook functions.
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/control/1/playout/ -H 'Content-Type: application/json'
-/// -d '{ "command": "reset" }' -H 'Authorization: Bearer <TOKEN>'
+/// curl -X POST http://127.0.0.1:8787/api/scripts/1/ -H 'Content-Type: application/json' \
+/// -d '{ "name": "weather", "code": "return { filler = function(gap, fillers, ctx) return nil end }", "enabled": true }' \
+/// -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[post("/control/{id}/playout/")]
+#[post("/scripts/{id}/")]
 #[protect(
-    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    any("Role::GlobalAdmin", "Role::ChannelAdmin"),
     ty = "Role",
-    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ConfigEdit)"
 )]
-pub async fn control_playout(
+async fn add_script(
     pool: web::Data<Pool<Sqlite>>,
     id: web::Path<i32>,
-    control: web::Json<ControlParams>,
+    data: web::Json<Script>,
     controllers: web::Data<Mutex<ChannelController>>,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
 ) -> Result<impl Responder, ServiceError> {
     let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let mut script = data.into_inner();
 
-    if manager.is_processing.load(Ordering::SeqCst) {
-        return Err(ServiceError::Conflict(
-            "A command is already being processed, please wait".to_string(),
-        ));
-    }
-
-    manager.is_processing.store(true, Ordering::SeqCst);
+    check_script(&script.code)?;
 
-    let resp = match control_state(&pool, &manager, &control.control).await {
-        Ok(res) => Ok(web::Json(res)),
-        Err(e) => Err(e),
-    };
+    script.channel_id = *id;
+    script.updated = time_now().format("%Y-%m-%d %H:%M:%S").to_string();
 
-    manager.is_processing.store(false, Ordering::SeqCst);
+    handles::insert_script(&pool, &script).await?;
+    load_scripts(&pool, &manager).await;
 
-    resp
+    Ok("Add script success")
 }
 
-/// **Get current Clip**
+/// **Update Script**
 ///
 /// ```BASH
-/// curl -X GET http://127.0.0.1:8787/api/control/1/media/current
-/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
-/// ```
-///
-/// **Response:**
-///
-/// ```JSON
-///     {
-///       "media": {
-///         "category": "",
-///         "duration": 154.2,
-///         "out": 154.2,
-///         "in": 0.0,
-///         "source": "/opt/tv-media/clip.mp4"
-///       },
-///       "index": 39,
-///       "ingest": false,
-///       "mode": "playlist",
-///       "played": 67.808
-///     }
+/// curl -X PUT http://127.0.0.1:8787/api/scripts/1/2 -H 'Content-Type: application/json' \
+/// -d '{ "name": "weather", "code": "<CODE>", "enabled": false }' -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[get("/control/{id}/media/current")]
+#[put("/scripts/{id}/{script}")]
 #[protect(
-    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    any("Role::GlobalAdmin", "Role::ChannelAdmin"),
     ty = "Role",
-    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+    expr = "(user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ConfigEdit)"
 )]
-pub async fn media_current(
-    id: web::Path<i32>,
+async fn update_script(
+    pool: web::Data<Pool<Sqlite>>,
+    path: web::Path<(i32, i32)>,
+    data: web::Json<Script>,
     controllers: web::Data<Mutex<ChannelController>>,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
 ) -> Result<impl Responder, ServiceError> {
-    let manager = controllers.lock().unwrap().get(*id).unwrap();
-    let media_map = get_data_map(&manager);
+    let (id, script_id) = path.into_inner();
+    let manager = controllers.lock().unwrap().get(id).unwrap();
+    let mut script = data.into_inner();
 
-    Ok(web::Json(media_map))
+    check_script(&script.code)?;
+
+    script.id = script_id;
+    script.channel_id = id;
+    script.updated = time_now().format("%Y-%m-%d %H:%M:%S").to_string();
+
+    let result = handles::update_script(&pool, &script).await?;
+
+    if result.rows_affected() == 0 {
+        return Err(ServiceError::NoContent("Script not found".into()));
+    }
+
+    load_scripts(&pool, &manager).await;
+
+    Ok("Update script success")
 }
 
-/// #### ffplayout Process Control
-///
-/// Control ffplayout process, like:
-/// - start
-/// - stop
-/// - restart
-/// - status
+/// **Delete Script**
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/control/1/process/
-/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
-/// -d '{"command": "start"}'
+/// curl -X DELETE http://127.0.0.1:8787/api/scripts/1/2 -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[post("/control/{id}/process/")]
+#[delete("/scripts/{id}/{script}")]
 #[protect(
-    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    any("Role::GlobalAdmin", "Role::ChannelAdmin"),
     ty = "Role",
-    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+    expr = "(user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ConfigEdit)"
 )]
-pub async fn process_control(
-    id: web::Path<i32>,
-    proc: web::Json<Process>,
+async fn delete_script(
+    pool: web::Data<Pool<Sqlite>>,
+    path: web::Path<(i32, i32)>,
     controllers: web::Data<Mutex<ChannelController>>,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
 ) -> Result<impl Responder, ServiceError> {
-    let manager = controllers.lock().unwrap().get(*id).unwrap();
-    manager.list_init.store(true, Ordering::SeqCst);
-
-    if manager.is_processing.load(Ordering::SeqCst) {
-        return Err(ServiceError::Conflict(
-            "A command is already being processed, please wait".to_string(),
-        ));
-    }
-
-    manager.is_processing.store(true, Ordering::SeqCst);
-
-    match proc.into_inner().command {
-        ProcessCtl::Status => {
-            manager.is_processing.store(false, Ordering::SeqCst);
-
-            if manager.is_alive.load(Ordering::SeqCst) {
-                return Ok(web::Json("active"));
-            }
-            return Ok(web::Json("not running"));
-        }
-        ProcessCtl::Start => {
-            if !manager.is_alive.load(Ordering::SeqCst) {
-                manager.channel.lock().unwrap().active = true;
-                manager.async_start().await;
-            }
-        }
-        ProcessCtl::Stop => {
-            manager.channel.lock().unwrap().active = false;
-            manager.async_stop().await?;
-        }
-        ProcessCtl::Restart => {
-            manager.async_stop().await?;
-            tokio::time::sleep(tokio::time::Duration::from_millis(1500)).await;
-
-            if !manager.is_alive.load(Ordering::SeqCst) {
-                manager.async_start().await;
-            }
-        }
-    }
+    let (id, script_id) = path.into_inner();
+    let manager = controllers.lock().unwrap().get(id).unwrap();
 
-    manager.is_processing.store(false, Ordering::SeqCst);
+    handles::delete_script(&pool, id, script_id).await?;
+    load_scripts(&pool, &manager).await;
 
-    Ok(web::Json("Success"))
+    Ok("Delete script success")
 }
 
-/// #### ffplayout Playlist Operations
+/// #### Ad Breaks
 ///
-/// **Get playlist**
+/// Commercial breaks, the break markers in the playlists are filled with spots from the pool folder.
+///
+/// **Get Ad Breaks**
 ///
 /// ```BASH
-/// curl -X GET http://127.0.0.1:8787/api/playlist/1?date=2022-06-20
-/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// curl -X GET http://127.0.0.1:8787/api/breaks/1 -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[get("/playlist/{id}")]
+#[get("/breaks/{id}")]
 #[protect(
     any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
     ty = "Role",
     expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
 )]
-pub async fn get_playlist(
+async fn get_ad_breaks(
+    pool: web::Data<Pool<Sqlite>>,
     id: web::Path<i32>,
-    obj: web::Query<DateObj>,
-    controllers: web::Data<Mutex<ChannelController>>,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
 ) -> Result<impl Responder, ServiceError> {
-    let manager = controllers.lock().unwrap().get(*id).unwrap();
-    let config = manager.config.lock().unwrap().clone();
+    let breaks = handles::select_ad_breaks(&pool, *id).await?;
 
-    match read_playlist(&config, obj.date.clone()).await {
-        Ok(playlist) => Ok(web::Json(playlist)),
-        Err(e) => Err(e),
-    }
+    Ok(web::Json(breaks))
 }
 
-/// **Save playlist**
+/// **Add Ad Break**
+///
+/// The spots folder is relative to the channel storage.
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/playlist/1/
-/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
-/// --data "{<JSON playlist data>}"
+/// curl -X POST http://127.0.0.1:8787/api/breaks/1/ -H 'Content-Type: application/json' \
+/// -d '{ "name": "prime", "duration": 180, "spots": "commercials/prime", "max_plays": 4, "pad": true }' \
+/// -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[post("/playlist/{id}/")]
+#[post("/breaks/{id}/")]
 #[protect(
     any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
     ty = "Role",
-    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::PlaylistEdit)"
 )]
-pub async fn save_playlist(
+async fn add_ad_break(
+    pool: web::Data<Pool<Sqlite>>,
     id: web::Path<i32>,
-    data: web::Json<JsonPlaylist>,
+    data: web::Json<AdBreak>,
     controllers: web::Data<Mutex<ChannelController>>,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
 ) -> Result<impl Responder, ServiceError> {
     let manager = controllers.lock().unwrap().get(*id).unwrap();
-    let config = manager.config.lock().unwrap().clone();
+    let mut ad_break = data.into_inner();
 
-    match write_playlist(&config, data.into_inner()).await {
-        Ok(res) => Ok(web::Json(res)),
-        Err(e) => Err(e),
+    if ad_break.name.trim().is_empty() || ad_break.duration <= 0.0 {
+        return Err(ServiceError::BadRequest(
+            "Name and a positive duration are needed".into(),
+        ));
     }
+
+    ad_break.channel_id = *id;
+
+    let ad_break = handles::insert_ad_break(&pool, &ad_break).await?;
+    load_ad_breaks(&pool, &manager).await;
+
+    Ok(web::Json(ad_break))
 }
 
-/// **Generate Playlist**
-///
-/// A new playlist will be generated and response.
+/// **Update Ad Break**
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/playlist/1/generate/2022-06-20
-/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
-/// /// --data '{ "paths": [<list of paths>] }' # <- data is optional
-/// ```
-///
-/// Or with template:
-/// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/playlist/1/generate/2023-00-05
-/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
-/// --data '{"template": {"sources": [\
-///            {"start": "00:00:00", "duration": "10:00:00", "shuffle": true, "paths": ["path/1", "path/2"]}, \
-///            {"start": "10:00:00", "duration": "14:00:00", "shuffle": false, "paths": ["path/3", "path/4"]}]}}'
+/// curl -X PUT http://127.0.0.1:8787/api/breaks/1/2 -H 'Content-Type: application/json' \
+/// -d '{ "name": "prime", "duration": 240, "spots": "commercials/prime", "max_plays": 0, "pad": false }' \
+/// -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[post("/playlist/{id}/generate/{date}")]
+#[put("/breaks/{id}/{break}")]
 #[protect(
     any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
     ty = "Role",
-    expr = "user.channels.contains(&params.0) || role.has_authority(&Role::GlobalAdmin)"
+    expr = "(user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::PlaylistEdit)"
 )]
-pub async fn gen_playlist(
-    params: web::Path<(i32, String)>,
-    data: Option<web::Json<PathsObj>>,
+async fn update_ad_break(
+    pool: web::Data<Pool<Sqlite>>,
+    path: web::Path<(i32, i32)>,
+    data: web::Json<AdBreak>,
     controllers: web::Data<Mutex<ChannelController>>,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
 ) -> Result<impl Responder, ServiceError> {
-    let manager = controllers.lock().unwrap().get(params.0).unwrap();
-    manager.config.lock().unwrap().general.generate = Some(vec![params.1.clone()]);
-    let storage = manager.config.lock().unwrap().channel.storage.clone();
-
-    if let Some(obj) = data {
-        if let Some(paths) = &obj.paths {
-            let mut path_list = vec![];
+    let (id, break_id) = path.into_inner();
+    let manager = controllers.lock().unwrap().get(id).unwrap();
+    let mut ad_break = data.into_inner();
 
-            for path in paths {
-                let (p, _, _) = norm_abs_path(&storage, path)?;
+    if ad_break.name.trim().is_empty() || ad_break.duration <= 0.0 {
+        return Err(ServiceError::BadRequest(
+            "Name and a positive duration are needed".into(),
+        ));
+    }
 
-                path_list.push(p);
-            }
+    ad_break.id = break_id;
+    ad_break.channel_id = id;
 
-            manager.config.lock().unwrap().storage.paths = path_list;
-        }
+    let result = handles::update_ad_break(&pool, &ad_break).await?;
 
-        manager
-            .config
-            .lock()
-            .unwrap()
-            .general
-            .template
-            .clone_from(&obj.template);
+    if result.rows_affected() == 0 {
+        return Err(ServiceError::NoContent("Ad break not found".into()));
     }
 
-    match generate_playlist(manager) {
-        Ok(playlist) => Ok(web::Json(playlist)),
-        Err(e) => Err(e),
-    }
+    load_ad_breaks(&pool, &manager).await;
+
+    Ok("Update ad break success")
 }
 
-/// **Delete Playlist**
+/// **Delete Ad Break**
 ///
 /// ```BASH
-/// curl -X DELETE http://127.0.0.1:8787/api/playlist/1/2022-06-20
-/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// curl -X DELETE http://127.0.0.1:8787/api/breaks/1/2 -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[delete("/playlist/{id}/{date}")]
+#[delete("/breaks/{id}/{break}")]
 #[protect(
     any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
     ty = "Role",
-    expr = "user.channels.contains(&params.0) || role.has_authority(&Role::GlobalAdmin)"
+    expr = "(user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::PlaylistEdit)"
 )]
-pub async fn del_playlist(
-    params: web::Path<(i32, String)>,
+async fn delete_ad_break(
+    pool: web::Data<Pool<Sqlite>>,
+    path: web::Path<(i32, i32)>,
     controllers: web::Data<Mutex<ChannelController>>,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
 ) -> Result<impl Responder, ServiceError> {
-    let manager = controllers.lock().unwrap().get(params.0).unwrap();
-    let config = manager.config.lock().unwrap().clone();
+    let (id, break_id) = path.into_inner();
+    let manager = controllers.lock().unwrap().get(id).unwrap();
 
-    match delete_playlist(&config, &params.1).await {
-        Ok(m) => Ok(web::Json(m)),
-        Err(e) => Err(e),
-    }
+    handles::delete_ad_break(&pool, id, break_id).await?;
+    load_ad_breaks(&pool, &manager).await;
+
+    Ok("Delete ad break success")
 }
 
-/// ### Log file
+/// **Get Spot Plays**
 ///
-/// **Read Log File**
+/// How often the spots played on one day, for the rotation.
 ///
 /// ```BASH
-/// curl -X GET http://127.0.0.1:8787/api/log/1?date=2022-06-20
-/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// curl -X GET http://127.0.0.1:8787/api/breaks/1/plays/2024-11-04 -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[get("/log/{id}")]
+#[get("/breaks/{id}/plays/{date}")]
 #[protect(
     any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
     ty = "Role",
-    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+    expr = "user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)"
 )]
-pub async fn get_log(
-    id: web::Path<i32>,
-    log: web::Query<DateObj>,
+async fn get_spot_plays(
+    pool: web::Data<Pool<Sqlite>>,
+    path: web::Path<(i32, String)>,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
 ) -> Result<impl Responder, ServiceError> {
-    read_log_file(&id, &log.date).await
+    let (id, date) = path.into_inner();
+    let plays = handles::select_spot_plays(&pool, id, &date).await?;
+
+    Ok(web::Json(plays))
 }
 
-/// ### File Operations
+/// #### Regional Outputs
 ///
-/// **Get File/Folder List**
+/// Regions share the schedule of the channel, but replace break windows with content from their sub-playlists.
+///
+/// **Get Regions**
+///
+/// Returns the regions with the status of their outputs.
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/file/1/browse/ -H 'Content-Type: application/json'
-/// -d '{ "source": "/" }' -H 'Authorization: Bearer <TOKEN>'
+/// curl -X GET http://127.0.0.1:8787/api/regions/1 -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[post("/file/{id}/browse/")]
+#[get("/regions/{id}")]
 #[protect(
     any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
     ty = "Role",
     expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
 )]
-pub async fn file_browser(
+async fn get_regions(
+    pool: web::Data<Pool<Sqlite>>,
     id: web::Path<i32>,
-    data: web::Json<PathObject>,
-    controllers: web::Data<Mutex<ChannelController>>,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
 ) -> Result<impl Responder, ServiceError> {
-    let manager = controllers.lock().unwrap().get(*id).unwrap();
-    let channel = manager.channel.lock().unwrap().clone();
-    let config = manager.config.lock().unwrap().clone();
+    let regions: Vec<RegionInfo> = handles::select_regions(&pool, *id)
+        .await?
+        .into_iter()
+        .map(|region| RegionInfo {
+            status: region_status(region.id),
+            region,
+        })
+        .collect();
 
-    match browser(&config, &channel, &data.into_inner()).await {
-        Ok(obj) => Ok(web::Json(obj)),
-        Err(e) => Err(e),
-    }
+    Ok(web::Json(regions))
 }
 
-/// **Create Folder**
+/// **Add Region**
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/file/1/create-folder/ -H 'Content-Type: application/json'
-/// -d '{"source": "<FOLDER PATH>"}' -H 'Authorization: Bearer <TOKEN>'
+/// curl -X POST http://127.0.0.1:8787/api/regions/1/ -H 'Content-Type: application/json' \
+/// -d '{ "name": "north", "output_param": "-c:v libx264 -c:a aac -f flv rtmp://127.0.0.1/live/north", "enable": true }' \
+/// -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[post("/file/{id}/create-folder/")]
+#[post("/regions/{id}/")]
 #[protect(
-    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    any("Role::GlobalAdmin", "Role::ChannelAdmin"),
     ty = "Role",
-    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ConfigEdit)"
 )]
-pub async fn add_dir(
+async fn add_region(
+    pool: web::Data<Pool<Sqlite>>,
     id: web::Path<i32>,
-    data: web::Json<PathObject>,
+    data: web::Json<Region>,
     controllers: web::Data<Mutex<ChannelController>>,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
-) -> Result<HttpResponse, ServiceError> {
+) -> Result<impl Responder, ServiceError> {
     let manager = controllers.lock().unwrap().get(*id).unwrap();
-    let config = manager.config.lock().unwrap().clone();
+    let mut region = data.into_inner();
 
-    create_directory(&config, &data.into_inner()).await
+    if region.name.trim().is_empty() || region.output_param.trim().is_empty() {
+        return Err(ServiceError::BadRequest(
+            "Name and output parameters are needed".into(),
+        ));
+    }
+
+    region.channel_id = *id;
+
+    let region = handles::insert_region(&pool, &region).await?;
+    load_regions(&pool, &manager).await;
+
+    Ok(web::Json(region))
 }
 
-/// **Rename File**
+/// **Update Region**
+///
+/// The regional outputs start again with the next clip.
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/file/1/rename/ -H 'Content-Type: application/json'
-/// -d '{"source": "<SOURCE>", "target": "<TARGET>"}' -H 'Authorization: Bearer <TOKEN>'
+/// curl -X PUT http://127.0.0.1:8787/api/regions/1/2 -H 'Content-Type: application/json' \
+/// -d '{ "name": "north", "output_param": "-c:v libx264 -c:a aac -f flv rtmp://127.0.0.1/live/north", "enable": false }' \
+/// -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[post("/file/{id}/rename/")]
+#[put("/regions/{id}/{region}")]
 #[protect(
-    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    any("Role::GlobalAdmin", "Role::ChannelAdmin"),
     ty = "Role",
-    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+    expr = "(user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ConfigEdit)"
 )]
-pub async fn move_rename(
-    id: web::Path<i32>,
-    data: web::Json<MoveObject>,
+async fn update_region(
+    pool: web::Data<Pool<Sqlite>>,
+    path: web::Path<(i32, i32)>,
+    data: web::Json<Region>,
     controllers: web::Data<Mutex<ChannelController>>,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
 ) -> Result<impl Responder, ServiceError> {
-    let manager = controllers.lock().unwrap().get(*id).unwrap();
-    let config = manager.config.lock().unwrap().clone();
+    let (id, region_id) = path.into_inner();
+    let manager = controllers.lock().unwrap().get(id).unwrap();
+    let mut region = data.into_inner();
 
-    match rename_file(&config, &data.into_inner()).await {
-        Ok(obj) => Ok(web::Json(obj)),
-        Err(e) => Err(e),
+    if region.name.trim().is_empty() || region.output_param.trim().is_empty() {
+        return Err(ServiceError::BadRequest(
+            "Name and output parameters are needed".into(),
+        ));
+    }
+
+    region.id = region_id;
+    region.channel_id = id;
+
+    let result = handles::update_region(&pool, &region).await?;
+
+    if result.rows_affected() == 0 {
+        return Err(ServiceError::NoContent("Region not found".into()));
     }
+
+    load_regions(&pool, &manager).await;
+
+    Ok("Update region success")
 }
 
-/// **Remove File/Folder**
+/// **Delete Region**
 ///
 /// ```BASH
-/// curl -X POST http://127.0.0.1:8787/api/file/1/remove/ -H 'Content-Type: application/json'
-/// -d '{"source": "<SOURCE>"}' -H 'Authorization: Bearer <TOKEN>'
+/// curl -X DELETE http://127.0.0.1:8787/api/regions/1/2 -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[post("/file/{id}/remove/")]
+#[delete("/regions/{id}/{region}")]
 #[protect(
-    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    any("Role::GlobalAdmin", "Role::ChannelAdmin"),
     ty = "Role",
-    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+    expr = "(user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ConfigEdit)"
 )]
-pub async fn remove(
-    id: web::Path<i32>,
-    data: web::Json<PathObject>,
+async fn delete_region(
+    pool: web::Data<Pool<Sqlite>>,
+    path: web::Path<(i32, i32)>,
     controllers: web::Data<Mutex<ChannelController>>,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
 ) -> Result<impl Responder, ServiceError> {
-    let manager = controllers.lock().unwrap().get(*id).unwrap();
-    let config = manager.config.lock().unwrap().clone();
-    let recursive = data.recursive;
+    let (id, region_id) = path.into_inner();
+    let manager = controllers.lock().unwrap().get(id).unwrap();
 
-    match remove_file_or_folder(&config, &data.into_inner().source, recursive).await {
-        Ok(obj) => Ok(web::Json(obj)),
-        Err(e) => Err(e),
-    }
+    handles::delete_region(&pool, id, region_id).await?;
+    load_regions(&pool, &manager).await;
+
+    Ok("Delete region success")
 }
 
-/// **Upload File**
+/// Region of the channel, or not found.
+async fn channel_region(
+    pool: &Pool<Sqlite>,
+    id: i32,
+    region_id: i32,
+) -> Result<Region, ServiceError> {
+    handles::select_regions(pool, id)
+        .await?
+        .into_iter()
+        .find(|r| r.id == region_id)
+        .ok_or_else(|| ServiceError::NoContent("Region not found".into()))
+}
+
+/// **Get Region Playlist**
+///
+/// Sub-playlist of a region, every item has the break which it replaces.
 ///
 /// ```BASH
-/// curl -X PUT http://127.0.0.1:8787/api/file/1/upload/ -H 'Authorization: Bearer <TOKEN>'
-/// -F "file=@file.mp4"
+/// curl -X GET 'http://127.0.0.1:8787/api/regions/1/2/playlist?date=2024-11-04' \
+/// -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[allow(clippy::too_many_arguments)]
-#[put("/file/{id}/upload/")]
+#[get("/regions/{id}/{region}/playlist")]
 #[protect(
     any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
     ty = "Role",
-    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+    expr = "user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)"
 )]
-async fn save_file(
-    id: web::Path<i32>,
-    req: HttpRequest,
-    payload: Multipart,
-    obj: web::Query<FileObj>,
+async fn get_region_playlist(
+    pool: web::Data<Pool<Sqlite>>,
+    path: web::Path<(i32, i32)>,
+    obj: web::Query<DateObj>,
     controllers: web::Data<Mutex<ChannelController>>,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
-) -> Result<HttpResponse, ServiceError> {
-    let manager = controllers.lock().unwrap().get(*id).unwrap();
+) -> Result<impl Responder, ServiceError> {
+    let (id, region_id) = path.into_inner();
+    let manager = controllers.lock().unwrap().get(id).unwrap();
     let config = manager.config.lock().unwrap().clone();
 
-    let size: u64 = req
-        .headers()
-        .get("content-length")
-        .and_then(|cl| cl.to_str().ok())
-        .and_then(|cls| cls.parse().ok())
-        .unwrap_or(0);
+    valid_date(&obj.date)?;
+    channel_region(&pool, id, region_id).await?;
 
-    upload(&config, size, payload, &obj.path, false).await
+    let playlist = read_region_playlist(&config, region_id, &obj.date)?;
+
+    Ok(web::Json(playlist))
 }
 
-/// **Get File**
-///
-/// Can be used for preview video files
+/// **Save Region Playlist**
 ///
 /// ```BASH
-/// curl -X GET http://127.0.0.1:8787/file/1/path/to/file.mp4
+/// curl -X POST http://127.0.0.1:8787/api/regions/1/2/playlist -H 'Content-Type: application/json' \
+/// -d '{ "channel": "north", "date": "2024-11-04", "program": [{ "in": 0, "out": 30, "duration": 30,
+/// "source": "/regional/north/promo.mp4", "break": "prime" }] }' -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[get("/file/{id}/{filename:.*}")]
-async fn get_file(
-    req: HttpRequest,
+#[post("/regions/{id}/{region}/playlist")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::PlaylistEdit)"
+)]
+async fn save_region_playlist(
+    pool: web::Data<Pool<Sqlite>>,
+    path: web::Path<(i32, i32)>,
+    data: web::Json<JsonPlaylist>,
     controllers: web::Data<Mutex<ChannelController>>,
-) -> Result<actix_files::NamedFile, ServiceError> {
-    let id: i32 = req.match_info().query("id").parse()?;
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, region_id) = path.into_inner();
     let manager = controllers.lock().unwrap().get(id).unwrap();
-    let config = manager.config.lock().unwrap();
-    let storage = config.channel.storage.clone();
-    let file_path = req.match_info().query("filename");
-    let (path, _, _) = norm_abs_path(&storage, file_path)?;
-    let file = actix_files::NamedFile::open(path)?;
+    let config = manager.config.lock().unwrap().clone();
+    let playlist = data.into_inner();
 
-    Ok(file
-        .use_last_modified(true)
-        .set_content_disposition(ContentDisposition {
-            disposition: DispositionType::Attachment,
-            parameters: vec![],
-        }))
+    valid_date(&playlist.date)?;
+    channel_region(&pool, id, region_id).await?;
+    write_region_playlist(&config, region_id, playlist)?;
+
+    Ok("Save region playlist success")
 }
 
-/// **Get Public**
+/// #### Output Targets
 ///
-/// Can be used for HLS Playlist and other static files in public folder
+/// Destinations of the stream output, every target with its own stream key and login.
+/// In stream mode they replace the destination at the end of the output parameters.
 ///
-/// ```BASH
-/// curl -X GET http://127.0.0.1:8787/1/live/stream.m3u8
-/// ```
-#[get("/{id}/{public:live|preview|public}/{file_stem:.*}")]
-async fn get_public(
-    path: web::Path<(i32, String, String)>,
-    controllers: web::Data<Mutex<ChannelController>>,
-) -> Result<actix_files::NamedFile, ServiceError> {
-    let (id, public, file_stem) = path.into_inner();
-
-    let absolute_path = if file_stem.ends_with(".ts")
-        || file_stem.ends_with(".m3u8")
-        || file_stem.ends_with(".vtt")
-    {
-        let manager = controllers.lock().unwrap().get(id).unwrap();
-        let config = manager.config.lock().unwrap();
-        config.channel.public.join(public)
-    } else {
-        public_path()
-    }
-    .clean();
-
-    let path = absolute_path.join(file_stem.as_str());
-    let file = actix_files::NamedFile::open(path)?;
-
-    Ok(file
-        .use_last_modified(true)
-        .set_content_disposition(ContentDisposition {
-            disposition: DispositionType::Attachment,
-            parameters: vec![],
-        }))
-}
-
-/// **Import playlist**
+/// **Get Output Targets**
 ///
-/// Import text/m3u file and convert it to a playlist
-/// lines with leading "#" will be ignore
+/// Stream keys and passwords are masked.
 ///
 /// ```BASH
-/// curl -X PUT http://127.0.0.1:8787/api/file/1/import/ -H 'Authorization: Bearer <TOKEN>'
-/// -F "file=@list.m3u"
+/// curl -X GET http://127.0.0.1:8787/api/outputs/1 -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[allow(clippy::too_many_arguments)]
-#[put("/file/{id}/import/")]
+#[get("/outputs/{id}")]
 #[protect(
     any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
     ty = "Role",
     expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
 )]
-async fn import_playlist(
+async fn get_output_targets(
+    pool: web::Data<Pool<Sqlite>>,
     id: web::Path<i32>,
-    req: HttpRequest,
-    payload: Multipart,
-    obj: web::Query<ImportObj>,
-    controllers: web::Data<Mutex<ChannelController>>,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
-) -> Result<HttpResponse, ServiceError> {
-    let manager = controllers.lock().unwrap().get(*id).unwrap();
-    let channel_name = manager.channel.lock().unwrap().name.clone();
-    let config = manager.config.lock().unwrap().clone();
-    let file = obj.file.file_name().unwrap_or_default();
-    let path = env::temp_dir().join(file);
-    let path_clone = path.clone();
-    let size: u64 = req
-        .headers()
-        .get("content-length")
-        .and_then(|cl| cl.to_str().ok())
-        .and_then(|cls| cls.parse().ok())
-        .unwrap_or(0);
+) -> Result<impl Responder, ServiceError> {
+    let targets: Vec<OutputTarget> = handles::select_output_targets(&pool, *id)
+        .await?
+        .iter()
+        .map(masked)
+        .collect();
 
-    upload(&config, size, payload, &path, true).await?;
+    Ok(web::Json(targets))
+}
 
-    let response =
-        web::block(move || import_file(&config, &obj.date, Some(channel_name), &path_clone))
-            .await??;
+/// Apply the changed targets to the running channel, the encoder restarts with the next clip.
+async fn reload_targets(
+    pool: &Pool<Sqlite>,
+    controllers: &Mutex<ChannelController>,
+    id: i32,
+) -> Result<(), ServiceError> {
+    let manager = controllers
+        .lock()
+        .unwrap()
+        .get(id)
+        .ok_or_else(|| ServiceError::BadRequest(format!("Channel ({id}) not exists!")))?;
+    let new_config = get_config(pool, id).await?;
 
-    fs::remove_file(path).await?;
+    manager.update_config(new_config);
 
-    Ok(HttpResponse::Ok().body(response))
+    Ok(())
 }
 
-/// **Program info**
-///
-/// Get program infos about given date, or current day
+/// **Test Output Target**
 ///
-/// Examples:
-///
-/// * get program from current day
-/// ```BASH
-/// curl -X GET http://127.0.0.1:8787/api/program/1/ -H 'Authorization: Bearer <TOKEN>'
-/// ```
+/// Test the connection to a target, without saving it. For TCP protocols, like RTMP,
+/// a connection is opened, for SRT, UDP and RIST only the host is resolved.
 ///
-/// * get a program range between two dates
 /// ```BASH
-/// curl -X GET http://127.0.0.1:8787/api/program/1/?start_after=2022-11-13T12:00:00&start_before=2022-11-20T11:59:59 \
+/// curl -X POST http://127.0.0.1:8787/api/outputs/1/test -H 'Content-Type: application/json' \
+/// -d '{ "name": "youtube", "url": "rtmp://a.rtmp.youtube.com/live2" }' \
 /// -H 'Authorization: Bearer <TOKEN>'
 /// ```
+#[post("/outputs/{id}/test")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ConfigEdit)"
+)]
+async fn test_output_target(
+    id: web::Path<i32>,
+    data: web::Json<OutputTarget>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    check_target(&data)?;
+    test_connect(&data).await?;
+
+    Ok("Connection success")
+}
+
+/// **Add Output Target**
+///
+/// Enabled targets are tested before they are saved.
 ///
-/// * get program from give day
 /// ```BASH
-/// curl -X GET http://127.0.0.1:8787/api/program/1/?start_after=2022-11-13T10:00:00 \
+/// curl -X POST http://127.0.0.1:8787/api/outputs/1/ -H 'Content-Type: application/json' \
+/// -d '{ "name": "youtube", "url": "rtmp://a.rtmp.youtube.com/live2", "stream_key": "abcd-1234", "enable": true }' \
 /// -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[get("/program/{id}/")]
+#[post("/outputs/{id}/")]
 #[protect(
-    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    any("Role::GlobalAdmin", "Role::ChannelAdmin"),
     ty = "Role",
-    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ConfigEdit)"
 )]
-async fn get_program(
+async fn add_output_target(
+    pool: web::Data<Pool<Sqlite>>,
     id: web::Path<i32>,
-    obj: web::Query<ProgramObj>,
+    data: web::Json<OutputTarget>,
     controllers: web::Data<Mutex<ChannelController>>,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
 ) -> Result<impl Responder, ServiceError> {
-    let manager = controllers.lock().unwrap().get(*id).unwrap();
-    let config = manager.config.lock().unwrap().clone();
-    let id = config.general.channel_id;
-    let start_sec = config.playlist.start_sec.unwrap();
-    let mut days = 0;
-    let mut program = vec![];
-    let after = obj.start_after;
-    let mut before = obj.start_before;
+    let mut target = data.into_inner();
 
-    if after > before {
-        before = chrono::Local
-            .with_ymd_and_hms(after.year(), after.month(), after.day(), 23, 59, 59)
+    check_target(&target)?;
+
+    if target.enable {
+        test_connect(&target).await?;
+    }
+
+    target.channel_id = *id;
+
+    let target = handles::insert_output_target(&pool, &target).await?;
+    reload_targets(&pool, &controllers, *id).await?;
+
+    Ok(web::Json(masked(&target)))
+}
+
+/// **Update Output Target**
+///
+/// Masked stream keys and passwords are kept. Enabled targets are tested before they are saved.
+///
+/// ```BASH
+/// curl -X PUT http://127.0.0.1:8787/api/outputs/1/2 -H 'Content-Type: application/json' \
+/// -d '{ "name": "youtube", "url": "rtmp://b.rtmp.youtube.com/live2", "stream_key": "********", "enable": true }' \
+/// -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[put("/outputs/{id}/{target}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin"),
+    ty = "Role",
+    expr = "(user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ConfigEdit)"
+)]
+async fn update_output_target(
+    pool: web::Data<Pool<Sqlite>>,
+    path: web::Path<(i32, i32)>,
+    data: web::Json<OutputTarget>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, target_id) = path.into_inner();
+    let stored = handles::select_output_target(&pool, id, target_id)
+        .await
+        .map_err(|_| ServiceError::NoContent("Output target not found".into()))?;
+    let mut target = data.into_inner();
+
+    keep_secrets(&mut target, &stored);
+    check_target(&target)?;
+
+    if target.enable {
+        test_connect(&target).await?;
+    }
+
+    target.id = target_id;
+    target.channel_id = id;
+
+    handles::update_output_target(&pool, &target).await?;
+    reload_targets(&pool, &controllers, id).await?;
+
+    Ok("Update output target success")
+}
+
+#[derive(Debug, Deserialize)]
+struct TargetState {
+    enable: bool,
+}
+
+/// **Enable/Disable Output Target**
+///
+/// Switch a target on or off, the running encoder changes its destinations with the next clip.
+///
+/// ```BASH
+/// curl -X PUT http://127.0.0.1:8787/api/outputs/1/2/enable -H 'Content-Type: application/json' \
+/// -d '{ "enable": false }' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[put("/outputs/{id}/{target}/enable")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ProcessControl)"
+)]
+async fn enable_output_target(
+    pool: web::Data<Pool<Sqlite>>,
+    path: web::Path<(i32, i32)>,
+    data: web::Json<TargetState>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, target_id) = path.into_inner();
+    let result = handles::enable_output_target(&pool, id, target_id, data.enable).await?;
+
+    if result.rows_affected() == 0 {
+        return Err(ServiceError::NoContent("Output target not found".into()));
+    }
+
+    reload_targets(&pool, &controllers, id).await?;
+
+    Ok(if data.enable {
+        "Output target enabled"
+    } else {
+        "Output target disabled"
+    })
+}
+
+/// **Delete Output Target**
+///
+/// ```BASH
+/// curl -X DELETE http://127.0.0.1:8787/api/outputs/1/2 -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[delete("/outputs/{id}/{target}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin"),
+    ty = "Role",
+    expr = "(user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ConfigEdit)"
+)]
+async fn delete_output_target(
+    pool: web::Data<Pool<Sqlite>>,
+    path: web::Path<(i32, i32)>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, target_id) = path.into_inner();
+    let result = handles::delete_output_target(&pool, id, target_id).await?;
+
+    if result.rows_affected() == 0 {
+        return Err(ServiceError::NoContent("Output target not found".into()));
+    }
+
+    reload_targets(&pool, &controllers, id).await?;
+
+    Ok("Delete output target success")
+}
+
+/// ### ffplayout controlling
+///
+/// here we communicate with the engine for:
+/// - jump to last or next clip
+/// - reset playlist state
+/// - get infos about current, next, last clip
+/// - send text to the engine, for overlaying it (as lower third etc.)
+///
+/// **Send Text to ffplayout**
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/control/1/text/ \
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>' \
+/// -d '{"text": "Hello from ffplayout", "x": "(w-text_w)/2", "y": "(h-text_h)/2", fontsize": "24", "line_spacing": "4", "fontcolor": "#ffffff", "box": "1", "boxcolor": "#000000", "boxborderw": "4", "alpha": "1.0"}'
+/// ```
+#[post("/control/{id}/text/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::TextSend)"
+)]
+pub async fn send_text_message(
+    id: web::Path<i32>,
+    data: web::Json<TextFilter>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+
+    match send_message(manager, data.into_inner()).await {
+        Ok(res) => Ok(web::Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// **Send Text Preset to ffplayout**
+///
+/// Send a stored text preset, the optional data overrides single fields from the preset.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/control/1/text/preset/3 \
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>' \
+/// -d '{"text": "Breaking News"}' # <- data is optional
+/// ```
+#[post("/control/{id}/text/preset/{preset_id}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&params.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::TextSend)"
+)]
+pub async fn send_text_preset(
+    pool: web::Data<Pool<Sqlite>>,
+    params: web::Path<(i32, i32)>,
+    data: Option<web::Json<TextFilter>>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, preset_id) = params.into_inner();
+    let preset = handles::select_preset(&pool, id, preset_id)
+        .await
+        .map_err(|_| ServiceError::BadRequest(format!("Preset {preset_id} not found!")))?;
+    let overrides = data.map(web::Json::into_inner).unwrap_or_default();
+    let manager = controllers.lock().unwrap().get(id).unwrap();
+
+    match send_message(manager, TextFilter::from_preset(preset, overrides)).await {
+        Ok(res) => Ok(web::Json(res)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// **Image Overlay**
+///
+/// Show an image from the storage, or a HTML page, as overlay. Position can be a ffmpeg expression,
+/// with `duration` the overlay hides by itself. `{"show": false}` hides it.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/control/1/overlay/ \
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>' \
+/// -d '{"source": "banner/breaking.png", "x": "(W-w)/2", "y": "H-h-60", "duration": 30}'
+/// ```
+#[post("/control/{id}/overlay/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::TextSend)"
+)]
+pub async fn send_overlay(
+    id: web::Path<i32>,
+    data: web::Json<OverlayParams>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let state = web::block(move || set_overlay(&manager, data.into_inner())).await??;
+
+    Ok(web::Json(state))
+}
+
+/// Current overlay.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/control/1/overlay/ -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/control/{id}/overlay/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_overlay(
+    id: web::Path<i32>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let state = manager.overlay.lock().unwrap().clone();
+
+    Ok(web::Json(state))
+}
+
+/// **Control Playout**
+///
+/// - next
+/// - back
+/// - reset
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/control/1/playout/ -H 'Content-Type: application/json'
+/// -d '{ "command": "reset" }' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/control/{id}/playout/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ProcessControl)"
+)]
+pub async fn control_playout(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    control: web::Json<ControlParams>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+
+    if manager.is_processing.load(Ordering::SeqCst) {
+        return Err(ServiceError::Conflict(
+            "A command is already being processed, please wait".to_string(),
+        ));
+    }
+
+    manager.is_processing.store(true, Ordering::SeqCst);
+
+    let resp = match control_state(&pool, &manager, &control.control).await {
+        Ok(res) => Ok(web::Json(res)),
+        Err(e) => Err(e.into()),
+    };
+
+    manager.is_processing.store(false, Ordering::SeqCst);
+
+    resp
+}
+
+/// **Switch Input Source**
+///
+/// - auto: live ingest preempts playlist (default)
+/// - playlist: play playlist/folder, ignore live ingest
+/// - live: play the connected live ingest
+/// - external: play a external input url
+///
+/// Transition can be `cut` or `fade`, with fade length in seconds.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/control/1/source/ -H 'Content-Type: application/json'
+/// -d '{ "source": "external", "url": "srt://127.0.0.1:9000", "transition": "fade", "duration": 1.0 }'
+/// -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/control/{id}/source/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ProcessControl)"
+)]
+pub async fn control_source(
+    id: web::Path<i32>,
+    params: web::Json<SwitchParams>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+
+    if manager.is_processing.load(Ordering::SeqCst) {
+        return Err(ServiceError::Conflict(
+            "A command is already being processed, please wait".to_string(),
+        ));
+    }
+
+    manager.is_processing.store(true, Ordering::SeqCst);
+
+    let resp = web::block(move || {
+        let resp = switch_source(&manager, params.into_inner());
+        manager.is_processing.store(false, Ordering::SeqCst);
+
+        resp
+    })
+    .await?;
+
+    Ok(web::Json(resp?))
+}
+
+/// **Emergency Override**
+///
+/// Play a live input, a slate with text or a looped clip instead of the schedule.
+/// It returns to the schedule with `"source": "release"`, or with `"until": "next"`
+/// by itself at the end of the running item.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/control/1/override/ -H 'Content-Type: application/json'
+/// -d '{ "source": "slate", "path": "slates/breaking.png", "text": "Breaking News", "until": "release" }'
+/// -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/control/{id}/override/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ProcessControl)"
+)]
+pub async fn control_override(
+    id: web::Path<i32>,
+    params: web::Json<OverrideParams>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+
+    if manager.is_processing.load(Ordering::SeqCst) {
+        return Err(ServiceError::Conflict(
+            "A command is already being processed, please wait".to_string(),
+        ));
+    }
+
+    manager.is_processing.store(true, Ordering::SeqCst);
+
+    let resp = web::block(move || {
+        let resp = set_override(&manager, params.into_inner());
+        manager.is_processing.store(false, Ordering::SeqCst);
+
+        resp
+    })
+    .await?;
+
+    Ok(web::Json(resp?))
+}
+
+/// **SCTE-35 Splice**
+///
+/// Start a ad break now, or end the running one with `"cue": "in"`.
+/// Markers are written to the HLS playlists, when SCTE-35 is enabled in the output config.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/control/1/scte35/ -H 'Content-Type: application/json'
+/// -d '{ "cue": "out", "duration": 120 }' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/control/{id}/scte35/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ProcessControl)"
+)]
+pub async fn control_scte35(
+    id: web::Path<i32>,
+    params: web::Json<Scte35Params>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+
+    Ok(web::Json(splice_cue(&manager, &params)?))
+}
+
+/// **Get current Clip**
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/control/1/media/current
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+///     {
+///       "media": {
+///         "category": "",
+///         "duration": 154.2,
+///         "out": 154.2,
+///         "in": 0.0,
+///         "source": "/opt/tv-media/clip.mp4"
+///       },
+///       "index": 39,
+///       "ingest": false,
+///       "dead_air": {
+///         "active": false,
+///         "kind": null,
+///         "source": "",
+///         "since": null
+///       },
+///       "input": {
+///         "source": "auto",
+///         "since": null
+///       },
+///       "mode": "playlist",
+///       "played": 67.808
+///     }
+/// ```
+#[get("/control/{id}/media/current")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn media_current(
+    id: web::Path<i32>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let media_map = get_data_map(&manager);
+
+    Ok(web::Json(media_map))
+}
+
+/// **Playout Clock**
+///
+/// Shows system time and playout time, the state of the reference clock and
+/// the delta between playlist and playout clock at the start of the current clip.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/control/1/clock
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/control/{id}/clock")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_clock(
+    id: web::Path<i32>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers
+        .lock()
+        .unwrap()
+        .get(*id)
+        .ok_or_else(|| ServiceError::BadRequest(format!("Channel ({id}) not exists!")))?;
+    let stop_threshold = manager.config.lock().unwrap().general.stop_threshold;
+    let playlist_delta = *manager.playlist_delta.lock().unwrap();
+    let in_sync =
+        playlist_delta.is_none_or(|delta| stop_threshold <= 0.0 || delta.abs() <= stop_threshold);
+
+    Ok(web::Json(serde_json::json!({
+        "system_time": Local::now().to_rfc3339(),
+        "playout_time": time_now().to_rfc3339(),
+        "reference": clock_status(),
+        "playlist_delta": playlist_delta,
+        "stop_threshold": stop_threshold,
+        "in_sync": in_sync,
+    })))
+}
+
+/// **ffmpeg Events**
+///
+/// Counters of the warnings and errors from the ffmpeg log, like decode errors,
+/// buffer underruns, disconnects and timestamp problems, with the last events.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/control/1/events
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/control/{id}/events")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_ffmpeg_events(
+    id: web::Path<i32>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers
+        .lock()
+        .unwrap()
+        .get(*id)
+        .ok_or_else(|| ServiceError::BadRequest(format!("Channel ({id}) not exists!")))?;
+    let events = manager.ffmpeg_events.lock().unwrap().clone();
+
+    Ok(web::Json(events))
+}
+
+/// **Stream Statistics**
+///
+/// Health of the ingest and the outputs: current bitrate (kbit/s) and fps, dropped frames,
+/// reconnects and the seconds since the last packet. The encoders are named `output` and `backup`,
+/// regional outputs `region_<name>`.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/control/1/streams/
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/control/{id}/streams/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_stream_stats(
+    id: web::Path<i32>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers
+        .lock()
+        .unwrap()
+        .get(*id)
+        .ok_or_else(|| ServiceError::BadRequest(format!("Channel ({id}) not exists!")))?;
+
+    Ok(web::Json(stream_stats(&manager)))
+}
+
+/// #### ffplayout Process Control
+///
+/// Control ffplayout process, like:
+/// - start
+/// - stop
+/// - restart
+/// - status
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/control/1/process/
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// -d '{"command": "start"}'
+/// ```
+#[post("/control/{id}/process/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ProcessControl)"
+)]
+pub async fn process_control(
+    id: web::Path<i32>,
+    proc: web::Json<Process>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    manager.list_init.store(true, Ordering::SeqCst);
+
+    if manager.is_processing.load(Ordering::SeqCst) {
+        return Err(ServiceError::Conflict(
+            "A command is already being processed, please wait".to_string(),
+        ));
+    }
+
+    manager.is_processing.store(true, Ordering::SeqCst);
+
+    match proc.into_inner().command {
+        ProcessCtl::Status => {
+            manager.is_processing.store(false, Ordering::SeqCst);
+
+            if manager.is_alive.load(Ordering::SeqCst) {
+                return Ok(web::Json("active"));
+            }
+            return Ok(web::Json("not running"));
+        }
+        ProcessCtl::Start => {
+            if !manager.is_alive.load(Ordering::SeqCst) {
+                if let Err(e) = assign_gpu(*id, manager.config.lock().unwrap().channel.limits.gpu) {
+                    manager.is_processing.store(false, Ordering::SeqCst);
+
+                    return Err(e.into());
+                }
+
+                manager.channel.lock().unwrap().active = true;
+                manager.async_start().await;
+            }
+        }
+        ProcessCtl::Stop => {
+            manager.channel.lock().unwrap().active = false;
+            manager.async_stop().await?;
+        }
+        ProcessCtl::Restart => {
+            manager.async_stop().await?;
+            tokio::time::sleep(tokio::time::Duration::from_millis(1500)).await;
+
+            if !manager.is_alive.load(Ordering::SeqCst) {
+                if let Err(e) = assign_gpu(*id, manager.config.lock().unwrap().channel.limits.gpu) {
+                    manager.is_processing.store(false, Ordering::SeqCst);
+
+                    return Err(e.into());
+                }
+
+                manager.async_start().await;
+            }
+        }
+    }
+
+    manager.is_processing.store(false, Ordering::SeqCst);
+
+    Ok(web::Json("Success"))
+}
+
+/// #### ffplayout Playlist Operations
+///
+/// **Get playlist**
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/playlist/1?date=2022-06-20
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/playlist/{id}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_playlist(
+    id: web::Path<i32>,
+    obj: web::Query<DateObj>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+
+    match read_playlist(&config, obj.date.clone()).await {
+        Ok(playlist) => Ok(web::Json(playlist)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// **Save playlist**
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/playlist/1/
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// --data "{<JSON playlist data>}"
+/// ```
+#[allow(clippy::too_many_arguments)]
+#[post("/playlist/{id}/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::PlaylistEdit)"
+)]
+pub async fn save_playlist(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    data: web::Json<JsonPlaylist>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    broadcaster: web::Data<Broadcaster>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+    let date = data.date.clone();
+    let previous = read_playlist(&config, date.clone()).await.ok();
+
+    let res = write_playlist(&config, data.into_inner()).await?;
+
+    if let Some(playlist) = previous {
+        if let Err(e) = save_version(&pool, &config, &playlist, user.id).await {
+            error!(target: Target::file_mail(), channel = *id; "Save playlist version failed: {e}");
+        }
+    }
+
+    let event = playlist_event(date, PlaylistAction::Saved);
+    broadcaster.notify(*id, event).await;
+
+    Ok(web::Json(res))
+}
+
+/// **Edit Playlist Items**
+///
+/// Change items of a stored playlist, without sending the whole playlist.
+/// The operations run in the given order, the previous playlist is saved as version.
+///
+/// - `shift`: move the start of all items (from index `from`), positive seconds insert a filler gap, negative cut the items
+/// - `replace`: replace a source in all items
+/// - `delete`: delete all items from a category
+/// - `insert`: insert an item before `index`
+///
+/// ```BASH
+/// curl -X PATCH http://127.0.0.1:8787/api/playlist/1/items/
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// --data '{"date": "2022-06-20", "operations": [{"op": "delete", "category": "advertisement"}, {"op": "shift", "seconds": 30}]}'
+/// ```
+#[allow(clippy::too_many_arguments)]
+#[patch("/playlist/{id}/items/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::PlaylistEdit)"
+)]
+pub async fn edit_playlist_items(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    data: web::Json<ItemsObj>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    broadcaster: web::Data<Broadcaster>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    valid_date(&data.date)?;
+
+    let manager = controllers
+        .lock()
+        .unwrap()
+        .get(*id)
+        .ok_or_else(|| ServiceError::BadRequest(format!("Channel ({id}) not exists!")))?;
+    let config = manager.config.lock().unwrap().clone();
+    let result = edit_items(&pool, &config, &data.date, &data.operations, user.id).await?;
+
+    let event = playlist_event(data.date.clone(), PlaylistAction::Saved);
+    broadcaster.notify(*id, event).await;
+
+    Ok(web::Json(result))
+}
+
+/// **Generate Playlist**
+///
+/// A new playlist will be generated and response.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/playlist/1/generate/2022-06-20
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// /// --data '{ "paths": [<list of paths>] }' # <- data is optional
+/// ```
+///
+/// Or with template:
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/playlist/1/generate/2023-00-05
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// --data '{"template": {"sources": [\
+///            {"start": "00:00:00", "duration": "10:00:00", "shuffle": true, "paths": ["path/1", "path/2"]}, \
+///            {"start": "10:00:00", "duration": "14:00:00", "shuffle": false, "paths": ["path/3", "path/4"]}]}}'
+/// ```
+#[post("/playlist/{id}/generate/{date}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&params.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::PlaylistEdit)"
+)]
+pub async fn gen_playlist(
+    params: web::Path<(i32, String)>,
+    data: Option<web::Json<PathsObj>>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    broadcaster: web::Data<Broadcaster>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(params.0).unwrap();
+    manager.config.lock().unwrap().general.generate = Some(vec![params.1.clone()]);
+    let storage = manager.config.lock().unwrap().channel.storage.clone();
+
+    if let Some(obj) = data {
+        if let Some(paths) = &obj.paths {
+            let mut path_list = vec![];
+
+            for path in paths {
+                let (p, _, _) = norm_abs_path(&storage, path)?;
+
+                path_list.push(p);
+            }
+
+            manager.config.lock().unwrap().storage.paths = path_list;
+        }
+
+        manager
+            .config
+            .lock()
+            .unwrap()
+            .general
+            .template
+            .clone_from(&obj.template);
+    }
+
+    let playlist = generate_playlist(manager)?;
+
+    let event = playlist_event(params.1.clone(), PlaylistAction::Generated);
+    broadcaster.notify(params.0, event).await;
+
+    Ok(web::Json(playlist))
+}
+
+/// **Delete Playlist**
+///
+/// ```BASH
+/// curl -X DELETE http://127.0.0.1:8787/api/playlist/1/2022-06-20
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[delete("/playlist/{id}/{date}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&params.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::PlaylistEdit)"
+)]
+pub async fn del_playlist(
+    pool: web::Data<Pool<Sqlite>>,
+    params: web::Path<(i32, String)>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    broadcaster: web::Data<Broadcaster>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(params.0).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+
+    if let Ok(playlist) = read_playlist(&config, params.1.clone()).await {
+        save_version(&pool, &config, &playlist, user.id).await?;
+    }
+
+    let res = delete_playlist(&config, &params.1).await?;
+
+    let event = playlist_event(params.1.clone(), PlaylistAction::Deleted);
+    broadcaster.notify(params.0, event).await;
+
+    Ok(web::Json(res))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CopyObj {
+    source: String,
+    target: String,
+    /// Target channel, default is the same channel.
+    #[serde(default)]
+    channel: Option<i32>,
+}
+
+/// **Copy Playlist**
+///
+/// Copy playlist to another date and/or channel.
+/// Sources from the channel storage are rewritten, when the target channel has a different storage.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/playlist/1/copy/ -H 'Content-Type: application/json'
+/// -d '{"source": "2022-06-20", "target": "2022-06-21", "channel": 2}' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[allow(clippy::too_many_arguments)]
+#[post("/playlist/{id}/copy/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::PlaylistEdit)"
+)]
+pub async fn copy_playlist_to(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    data: web::Json<CopyObj>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    broadcaster: web::Data<Broadcaster>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    valid_date(&data.source)?;
+    valid_date(&data.target)?;
+
+    let target_id = data.channel.unwrap_or(*id);
+
+    if !user.channels.contains(&target_id) && !role.has_authority(&Role::GlobalAdmin) {
+        return Err(ServiceError::Forbidden(
+            "No permission for the target channel!".into(),
+        ));
+    }
+
+    if target_id == *id && data.source == data.target {
+        return Err(ServiceError::BadRequest(
+            "Source and target are the same!".into(),
+        ));
+    }
+
+    let (manager, target) = {
+        let ctl = controllers.lock().unwrap();
+
+        (ctl.get(*id).unwrap(), ctl.get(target_id))
+    };
+    let target = target
+        .ok_or_else(|| ServiceError::BadRequest(format!("Channel {target_id} not exists!")))?;
+    let config = manager.config.lock().unwrap().clone();
+    let target_config = target.config.lock().unwrap().clone();
+    let target_name = target.channel.lock().unwrap().name.clone();
+
+    let playlist = read_playlist(&config, data.source.clone()).await?;
+    let copy = copy_playlist(
+        &playlist,
+        &target_name,
+        &data.target,
+        &config.channel.storage,
+        &target_config.channel.storage,
+    );
+    let previous = read_playlist(&target_config, data.target.clone())
+        .await
+        .ok();
+
+    let res = write_playlist(&target_config, copy).await?;
+
+    if let Some(p) = previous {
+        if let Err(e) = save_version(&pool, &target_config, &p, user.id).await {
+            error!(target: Target::file_mail(), channel = target_id; "Save playlist version failed: {e}");
+        }
+    }
+
+    let event = playlist_event(data.target.clone(), PlaylistAction::Saved);
+    broadcaster.notify(target_id, event).await;
+
+    Ok(web::Json(res))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DiffObj {
+    from: i32,
+    /// Version id, or empty for the current playlist.
+    #[serde(default)]
+    to: Option<i32>,
+}
+
+fn valid_date(date: &str) -> Result<(), ServiceError> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map(|_| ())
+        .map_err(|_| ServiceError::BadRequest("Date must be in format: YYYY-MM-DD".into()))
+}
+
+fn playlist_event(date: String, action: PlaylistAction) -> ChannelEvent {
+    ChannelEvent::Playlist { date, action }
+}
+
+/// **Playlist Versions**
+///
+/// Every save and delete stores the previous playlist as version, newest first.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/playlist/1/2022-06-20/versions
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/playlist/{id}/{date}/versions")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&params.0) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_playlist_versions(
+    pool: web::Data<Pool<Sqlite>>,
+    params: web::Path<(i32, String)>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    valid_date(&params.1)?;
+
+    let versions = handles::select_playlist_versions(&pool, params.0, &params.1).await?;
+
+    Ok(web::Json(versions))
+}
+
+/// **Playlist Version Diff**
+///
+/// Compare two versions item by item, without `to` the version is compared with the current playlist.
+///
+/// ```BASH
+/// curl -X GET 'http://127.0.0.1:8787/api/playlist/1/2022-06-20/versions/diff?from=4&to=7'
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/playlist/{id}/{date}/versions/diff")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&params.0) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn diff_playlist_versions(
+    pool: web::Data<Pool<Sqlite>>,
+    params: web::Path<(i32, String)>,
+    obj: web::Query<DiffObj>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    valid_date(&params.1)?;
+
+    let manager = controllers.lock().unwrap().get(params.0).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+    let old = read_version(&pool, &config, obj.from).await?;
+    let new = match obj.to {
+        Some(v) => read_version(&pool, &config, v).await?,
+        None => read_playlist(&config, params.1.clone())
+            .await
+            .unwrap_or_else(|_| JsonPlaylist {
+                program: vec![],
+                ..old.clone()
+            }),
+    };
+
+    if old.date != params.1 || new.date != params.1 {
+        return Err(ServiceError::BadRequest(
+            "Versions are not from the given date!".into(),
+        ));
+    }
+
+    Ok(web::Json(diff_playlists(&old.program, &new.program)))
+}
+
+/// **Restore Playlist Version**
+///
+/// The current playlist is stored as new version, so a restore can be undone.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/playlist/1/2022-06-20/versions/4/restore
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/playlist/{id}/{date}/versions/{version}/restore")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&params.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::PlaylistEdit)"
+)]
+pub async fn restore_playlist_version(
+    pool: web::Data<Pool<Sqlite>>,
+    params: web::Path<(i32, String, i32)>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    broadcaster: web::Data<Broadcaster>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    valid_date(&params.1)?;
+
+    let manager = controllers.lock().unwrap().get(params.0).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+    let playlist = read_version(&pool, &config, params.2).await?;
+
+    if playlist.date != params.1 {
+        return Err(ServiceError::BadRequest(
+            "Version is not from the given date!".into(),
+        ));
+    }
+
+    let previous = read_playlist(&config, params.1.clone()).await.ok();
+
+    write_playlist(&config, playlist).await?;
+
+    if let Some(p) = previous {
+        save_version(&pool, &config, &p, user.id).await?;
+    }
+
+    let event = playlist_event(params.1.clone(), PlaylistAction::Saved);
+    broadcaster.notify(params.0, event).await;
+
+    Ok(web::Json(format!(
+        "Restore playlist from {} to version {} success!",
+        params.1, params.2
+    )))
+}
+
+/// **Simulate Playlist**
+///
+/// Dry run of the playlist from `date`, without ffmpeg. Returns the timeline with the wall clock
+/// start of every item, with filler for missing sources, trimming and padding at the end of the day.
+/// With a JSON playlist in the body, the unsaved playlist is simulated.
+///
+/// ```BASH
+/// curl -X POST 'http://127.0.0.1:8787/api/playlist/1/simulate?date=2022-06-20'
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/playlist/{id}/simulate")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn simulate_playlist(
+    id: web::Path<i32>,
+    obj: web::Query<DateObj>,
+    data: Option<web::Json<JsonPlaylist>>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    valid_date(&obj.date)?;
+
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+    let playlist = match data {
+        Some(d) => d.into_inner(),
+        None => read_playlist(&config, obj.date.clone()).await?,
+    };
+
+    Ok(web::Json(simulate(&config, &obj.date, playlist)?))
+}
+
+/// **Apply Trim Suggestions**
+///
+/// Set in/out from the trim suggestions to all items, which play the whole file.
+/// The previous playlist is stored as version.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/playlist/1/2022-06-20/trim
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/playlist/{id}/{date}/trim")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&params.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::PlaylistEdit)"
+)]
+pub async fn trim_playlist(
+    pool: web::Data<Pool<Sqlite>>,
+    params: web::Path<(i32, String)>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    broadcaster: web::Data<Broadcaster>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    valid_date(&params.1)?;
+
+    let manager = controllers.lock().unwrap().get(params.0).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+    let previous = read_playlist(&config, params.1.clone()).await?;
+    let suggestions: HashMap<String, TrimSuggestion> =
+        handles::select_trim_suggestions(&pool, params.0, "")
+            .await?
+            .into_iter()
+            .map(|t| (t.source.clone(), t))
+            .collect();
+    let mut playlist = previous.clone();
+    let count = apply_trim(&mut playlist, &suggestions);
+
+    if count > 0 {
+        write_playlist(&config, playlist).await?;
+        save_version(&pool, &config, &previous, user.id).await?;
+
+        let event = playlist_event(params.1.clone(), PlaylistAction::Saved);
+        broadcaster.notify(params.0, event).await;
+    }
+
+    Ok(web::Json(format!(
+        "Trim {count} items from playlist {}",
+        params.1
+    )))
+}
+
+/// **Repair Playlist**
+///
+/// Check the playlist length against the day length and fill a gap or cut an overlap.
+/// Without `"save": true` the repaired playlist is only returned with the diff, for confirmation.
+///
+/// - `gap`: `insert` a filler item, `stretch` the last filler item or `none`
+/// - `overlap`: `trim` the tail at the end of the day or `none`
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/playlist/1/2022-06-20/repair
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// --data '{"gap": "stretch", "overlap": "trim", "save": false}'
+/// ```
+#[allow(clippy::too_many_arguments)]
+#[post("/playlist/{id}/{date}/repair")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&params.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::PlaylistEdit)"
+)]
+pub async fn repair_playlist_length(
+    pool: web::Data<Pool<Sqlite>>,
+    params: web::Path<(i32, String)>,
+    data: Option<web::Json<RepairParams>>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    broadcaster: web::Data<Broadcaster>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    valid_date(&params.1)?;
+
+    let manager = controllers.lock().unwrap().get(params.0).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+    let repair = data.map(web::Json::into_inner).unwrap_or_default();
+    let result = repair_playlist(&pool, &config, &params.1, &repair, user.id).await?;
+
+    if repair.save {
+        let event = playlist_event(params.1.clone(), PlaylistAction::Saved);
+        broadcaster.notify(params.0, event).await;
+    }
+
+    Ok(web::Json(result))
+}
+
+/// ### Log file
+///
+/// **Read Log File**
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/log/1?date=2022-06-20
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/log/{id}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_log(
+    id: web::Path<i32>,
+    log: web::Query<DateObj>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    Ok(read_log_file(&id, &log.date).await?)
+}
+
+/// ### File Operations
+///
+/// **Get File/Folder List**
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/file/1/browse/ -H 'Content-Type: application/json'
+/// -d '{ "source": "/" }' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/file/{id}/browse/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn file_browser(
+    id: web::Path<i32>,
+    data: web::Json<PathObject>,
+    list: ListQuery,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let channel = manager.channel.lock().unwrap().clone();
+    let config = manager.config.lock().unwrap().clone();
+
+    match browser(&config, &channel, &data.into_inner(), &list).await {
+        Ok(obj) => Ok(web::Json(obj)),
+        Err(e) => Err(e),
+    }
+}
+
+/// **Create Folder**
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/file/1/create-folder/ -H 'Content-Type: application/json'
+/// -d '{"source": "<FOLDER PATH>"}' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/file/{id}/create-folder/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::FileEdit)"
+)]
+pub async fn add_dir(
+    id: web::Path<i32>,
+    data: web::Json<PathObject>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<HttpResponse, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+
+    create_directory(&config, &data.into_inner()).await
+}
+
+/// **Rename File**
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/file/1/rename/ -H 'Content-Type: application/json'
+/// -d '{"source": "<SOURCE>", "target": "<TARGET>"}' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/file/{id}/rename/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::FileEdit)"
+)]
+pub async fn move_rename(
+    id: web::Path<i32>,
+    data: web::Json<MoveObject>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+
+    match rename_file(&config, &data.into_inner()).await {
+        Ok(obj) => Ok(web::Json(obj)),
+        Err(e) => Err(e),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RemoveObj {
+    #[serde(default)]
+    permanent: bool,
+}
+
+/// **Remove File/Folder**
+///
+/// When the trash is enabled, the source is moved to the trash, with `permanent=true` it gets deleted directly.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/file/1/remove/ -H 'Content-Type: application/json'
+/// -d '{"source": "<SOURCE>"}' -H 'Authorization: Bearer <TOKEN>'
+///
+/// curl -X DELETE 'http://127.0.0.1:8787/api/file/1/remove/?permanent=true' -H 'Content-Type: application/json'
+/// -d '{"source": "<SOURCE>"}' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[route("/file/{id}/remove/", method = "POST", method = "DELETE")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::FileDelete)"
+)]
+pub async fn remove(
+    id: web::Path<i32>,
+    data: web::Json<PathObject>,
+    query: web::Query<RemoveObj>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+    let recursive = data.recursive;
+
+    match remove_file_or_folder(
+        &config,
+        &data.into_inner().source,
+        recursive,
+        query.permanent,
+    )
+    .await
+    {
+        Ok(obj) => Ok(web::Json(obj)),
+        Err(e) => Err(e),
+    }
+}
+
+/// **Chapters**
+///
+/// Split a long file by its chapter metadata into playlist items,
+/// files without chapters are returned as one item.
+///
+/// ```BASH
+/// curl -X GET 'http://127.0.0.1:8787/api/file/1/chapters/?path=/recordings/show.mkv'
+/// -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/file/{id}/chapters/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_chapters(
+    id: web::Path<i32>,
+    obj: web::Query<ChapterObj>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let storage = manager.config.lock().unwrap().channel.storage.clone();
+    let (path, _, _) = norm_abs_path(&storage, &obj.path)?;
+
+    if !path.is_file() {
+        return Err(ServiceError::BadRequest("File not exists!".into()));
+    }
+
+    let items =
+        web::block(move || split_by_chapters(Media::new(0, &path.to_string_lossy(), true))).await?;
+
+    Ok(web::Json(items))
+}
+
+/// **Trim Suggestions**
+///
+/// Start the analysis for a folder or file, it runs in background.
+/// Leading and trailing black or silence are suggested as in/out points.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/file/1/trim/analyze/ -H 'Content-Type: application/json'
+/// -d '{ "source": "/archive" }' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/file/{id}/trim/analyze/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::FileEdit)"
+)]
+pub async fn analyze_trim(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    data: web::Json<PathObject>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+    let (path, _, _) = norm_abs_path(&config.channel.storage, &data.source)?;
+
+    if !path.exists() {
+        return Err(ServiceError::BadRequest("Path not exists!".into()));
+    }
+
+    let count = start_analysis(pool.get_ref().clone(), &config, &path)?;
+
+    Ok(web::Json(format!(
+        "Trim analysis started for {count} files"
+    )))
+}
+
+/// List the trim suggestions from all analyzed files in a folder:
+///
+/// ```BASH
+/// curl -X GET 'http://127.0.0.1:8787/api/file/1/trim/?path=/archive'
+/// -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/file/{id}/trim/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_trim_suggestions(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    obj: web::Query<TrimObj>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let storage = manager.config.lock().unwrap().channel.storage.clone();
+    let (path, _, _) = norm_abs_path(&storage, &obj.path)?;
+    let suggestions = handles::select_trim_suggestions(&pool, *id, &path.to_string_lossy()).await?;
+
+    Ok(web::Json(suggestions))
+}
+
+/// Trim a file with frame accurate in/out points, as seconds or timecode (`HH:MM:SS.mmm`, `HH:MM:SS:FF`).
+/// The points are snapped to the frames of the file.
+///
+/// With mode `virtual` the points are stored like a trim suggestion and returned,
+/// with mode `copy` a trimmed copy in the house format is queued and the transcoding job is returned.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/file/1/trim/ -H 'Content-Type: application/json'
+/// -d '{ "source": "/clips/clip.mp4", "in": "00:00:02:12", "out": 85.4, "mode": "copy" }'
+/// -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/file/{id}/trim/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::FileEdit)"
+)]
+pub async fn trim_file(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    data: web::Json<TrimClipObj>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+    let (path, _, _) = norm_abs_path(&config.channel.storage, &data.source)?;
+
+    if !path.is_file() {
+        return Err(ServiceError::BadRequest("File not exists!".into()));
+    }
+
+    let source = path.to_string_lossy().to_string();
+    let (duration, fps) = web::block(move || media_timing(&source)).await??;
+    let (seek, out) = trim_points(&data.seek, &data.out, duration, fps)?;
+
+    if data.mode == TrimMode::Copy {
+        let target = trim_target(&config, &path, data.target.as_deref());
+        let job = transcode::submit_trim(&pool, &config, &path, &target, seek, out).await?;
+
+        return Ok(web::Json(serde_json::to_value(job)?));
+    }
+
+    let trim = TrimSuggestion {
+        channel_id: *id,
+        source: path.to_string_lossy().to_string(),
+        seek,
+        out,
+        duration,
+        updated: time_now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        ..Default::default()
+    };
+
+    handles::upsert_trim_suggestion(&pool, &trim).await?;
+
+    Ok(web::Json(serde_json::to_value(trim)?))
+}
+
+/// Low resolution MP4 preview from the trimmed part, to check the points before scheduling.
+/// Parts longer then ten seconds show the first and the last five seconds.
+///
+/// ```BASH
+/// curl -X GET 'http://127.0.0.1:8787/api/file/1/trim/preview?path=/clips/clip.mp4&in=00:00:02:12&out=85.4'
+/// -H 'Authorization: Bearer <TOKEN>' --output preview.mp4
+/// ```
+#[get("/file/{id}/trim/preview")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_trim_preview(
+    id: web::Path<i32>,
+    obj: web::Query<TrimPreviewObj>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<actix_files::NamedFile, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+    let (path, _, _) = norm_abs_path(&config.channel.storage, &obj.path)?;
+
+    if !path.is_file() {
+        return Err(ServiceError::BadRequest("File not exists!".into()));
+    }
+
+    let video = web::block(move || {
+        let (duration, fps) = media_timing(&path.to_string_lossy())?;
+        let (seek, out) = trim_points(&obj.seek, &obj.out, duration, fps)?;
+
+        trim_preview(&config, &path, seek, out)
+    })
+    .await??;
+
+    Ok(actix_files::NamedFile::open(video)?)
+}
+
+/// **Thumbnails**
+///
+/// JPEG thumbnail from a media file, or from a HLS playlist in the public folder.
+/// With `t` the frame from this second is used, with `sprite=true` the storyboard sprite is returned.
+/// Images are cached, HLS thumbnails are renewed after a few seconds.
+///
+/// ```BASH
+/// curl -X GET 'http://127.0.0.1:8787/api/file/1/thumbnail?path=/clips/clip.mp4&t=12.5'
+/// -H 'Authorization: Bearer <TOKEN>' --output thumb.jpg
+/// ```
+#[get("/file/{id}/thumbnail")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_thumbnail(
+    id: web::Path<i32>,
+    obj: web::Query<ThumbnailObj>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<actix_files::NamedFile, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+    let root = if obj.path.ends_with(".m3u8") {
+        &config.channel.public
+    } else {
+        &config.channel.storage
+    };
+    let (path, _, _) = norm_abs_path(root, &obj.path)?;
+
+    if !path.is_file() {
+        return Err(ServiceError::BadRequest("File not exists!".into()));
+    }
+
+    let image = web::block(move || {
+        if obj.sprite {
+            storyboard(&config, &path).map(|(sprite, _)| sprite)
+        } else {
+            thumbnail(&config, &path, obj.t)
+        }
+    })
+    .await??;
+
+    Ok(actix_files::NamedFile::open(image)?.use_last_modified(true))
+}
+
+/// Storyboard layout for the scrubbing preview, the sprite is created when it not exists.
+/// The frame from second `n * interval` is on tile `n`, from left to right and top to bottom.
+///
+/// ```BASH
+/// curl -X GET 'http://127.0.0.1:8787/api/file/1/storyboard?path=/clips/clip.mp4'
+/// -H 'Authorization: Bearer <TOKEN>'
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// {
+///     "sprite": "/api/file/1/thumbnail?path=%2Fclips%2Fclip.mp4&sprite=true",
+///     "interval": 6.0,
+///     "count": 100,
+///     "columns": 10,
+///     "rows": 10,
+///     "width": 160,
+///     "height": 90
+/// }
+/// ```
+#[get("/file/{id}/storyboard")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_storyboard(
+    id: web::Path<i32>,
+    obj: web::Query<ThumbnailObj>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+    let (path, _, _) = norm_abs_path(&config.channel.storage, &obj.path)?;
+
+    if !path.is_file() {
+        return Err(ServiceError::BadRequest("File not exists!".into()));
+    }
+
+    let (_, layout) = web::block(move || storyboard(&config, &path)).await??;
+    let query: String = url::form_urlencoded::byte_serialize(obj.path.as_bytes()).collect();
+    let mut data = serde_json::to_value(layout)?;
+
+    data["sprite"] =
+        serde_json::json!(format!("/api/file/{id}/thumbnail?path={query}&sprite=true"));
+
+    Ok(web::Json(data))
+}
+
+/// Create thumbnails and storyboards for a folder or file in background.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/file/1/thumbnail/generate/ -H 'Content-Type: application/json'
+/// -d '{ "source": "/clips" }' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/file/{id}/thumbnail/generate/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::FileEdit)"
+)]
+pub async fn generate_thumbnails(
+    id: web::Path<i32>,
+    data: web::Json<PathObject>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+    let (path, _, _) = norm_abs_path(&config.channel.storage, &data.source)?;
+
+    if !path.exists() {
+        return Err(ServiceError::BadRequest("Path not exists!".into()));
+    }
+
+    let count = start_thumbnails(&config, &path)?;
+
+    Ok(web::Json(format!("Thumbnails started for {count} files")))
+}
+
+/// **Watch Folder**
+///
+/// State of the watch folder auto ingest, with the last ingested files.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/watch/1 -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/watch/{id}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_watch_status(
+    id: web::Path<i32>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let status = manager.watch_status.lock().unwrap().clone();
+
+    Ok(web::Json(status))
+}
+
+/// **Pull Ingest**
+///
+/// State of the FTP/SFTP pull ingest, with the last imported files.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/pull/1 -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/pull/{id}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_pull_status(
+    id: web::Path<i32>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let status = manager.pull_status.lock().unwrap().clone();
+
+    Ok(web::Json(status))
+}
+
+/// Run the pull ingest now, the response has the results from this run.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/pull/1 -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/pull/{id}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::FileEdit) && user.can(Permission::PlaylistEdit)"
+)]
+pub async fn run_pull(
+    id: web::Path<i32>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let settings = manager.config.lock().unwrap().storage.pull_ingest.clone();
+
+    if settings.url.is_empty() {
+        return Err(ServiceError::BadRequest("No pull ingest url".into()));
+    }
+
+    let results = pull_ingest::run(manager).await?;
+
+    Ok(web::Json(results))
+}
+
+/// **Transcoding**
+///
+/// Queue jobs, which transcode a file, or all media files from a folder, to the house format of the channel.
+/// The results are used as proxies by the player.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/transcode/1/ -H 'Content-Type: application/json'
+/// -d '{ "source": "/clips" }' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/transcode/{id}/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::FileEdit)"
+)]
+pub async fn add_transcode_jobs(
+    id: web::Path<i32>,
+    data: web::Json<PathObject>,
+    pool: web::Data<Pool<Sqlite>>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+    let (path, _, _) = norm_abs_path(&config.channel.storage, &data.source)?;
+
+    if !path.exists() {
+        return Err(ServiceError::BadRequest("Path not exists!".into()));
+    }
+
+    let jobs = transcode::submit(&pool, &config, &path).await?;
+
+    Ok(web::Json(jobs))
+}
+
+/// List transcoding jobs, newest first. Optional only jobs with a status:
+/// `queued`, `running`, `done`, `failed` or `canceled`.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/transcode/1?status=running -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/transcode/{id}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_transcode_jobs(
+    id: web::Path<i32>,
+    obj: web::Query<TranscodeObj>,
+    list: ListQuery,
+    pool: web::Data<Pool<Sqlite>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let jobs = handles::select_transcode_jobs(&pool, *id, obj.status.as_deref()).await?;
+
+    Ok(web::Json(list.response(jobs)?))
+}
+
+/// State and progress from one job.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/transcode/1/5 -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/transcode/{id}/{job}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_transcode_job(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<Pool<Sqlite>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, job_id) = path.into_inner();
+
+    match handles::select_transcode_job(&pool, id, job_id).await {
+        Ok(job) => Ok(web::Json(job)),
+        Err(_) => Err(ServiceError::NoContent("Job not exists".into())),
+    }
+}
+
+/// Cancel a queued or running job. Finished jobs are removed from the list.
+///
+/// ```BASH
+/// curl -X DELETE http://127.0.0.1:8787/api/transcode/1/5 -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[delete("/transcode/{id}/{job}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::FileEdit)"
+)]
+pub async fn cancel_transcode_job(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<Pool<Sqlite>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, job_id) = path.into_inner();
+    let message = transcode::cancel(&pool, id, job_id).await?;
+
+    Ok(web::Json(message))
+}
+
+/// **Downloads**
+///
+/// Queue a download of a remote url with yt-dlp into the storage. Without `format` the format
+/// matches the house format of the channel. With `playlist_date` the file is added to the end
+/// of the playlist from this date.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/download/1/ -H 'Content-Type: application/json' \
+/// -d '{ "url": "https://www.youtube.com/watch?v=<ID>", "folder": "/downloads", "playlist_date": "2024-05-01" }' \
+/// -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/download/{id}/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::FileEdit)"
+)]
+pub async fn add_download_job(
+    id: web::Path<i32>,
+    data: web::Json<DownloadJob>,
+    pool: web::Data<Pool<Sqlite>>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let job = data.into_inner();
+
+    if job.playlist_date.is_some() && !user.can(Permission::PlaylistEdit) {
+        return Err(ServiceError::Forbidden(
+            "No permission to edit playlists!".into(),
+        ));
+    }
+
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+    let job = download::submit(&pool, &config, job).await?;
+
+    Ok(web::Json(job))
+}
+
+/// List downloads, newest first. Optional only downloads with a status:
+/// `queued`, `running`, `done`, `failed` or `canceled`.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/download/1?status=running -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/download/{id}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_download_jobs(
+    id: web::Path<i32>,
+    obj: web::Query<TranscodeObj>,
+    list: ListQuery,
+    pool: web::Data<Pool<Sqlite>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let jobs = handles::select_download_jobs(&pool, *id, obj.status.as_deref()).await?;
+
+    Ok(web::Json(list.response(jobs)?))
+}
+
+/// State and progress from one download.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/download/1/5 -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/download/{id}/{job}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_download_job(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<Pool<Sqlite>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, job_id) = path.into_inner();
+
+    match handles::select_download_job(&pool, id, job_id).await {
+        Ok(job) => Ok(web::Json(job)),
+        Err(_) => Err(ServiceError::NoContent("Download not exists".into())),
+    }
+}
+
+/// Cancel a queued or running download. Finished downloads are removed from the list.
+///
+/// ```BASH
+/// curl -X DELETE http://127.0.0.1:8787/api/download/1/5 -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[delete("/download/{id}/{job}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::FileEdit)"
+)]
+pub async fn cancel_download_job(
+    path: web::Path<(i32, i32)>,
+    pool: web::Data<Pool<Sqlite>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, job_id) = path.into_inner();
+    let message = download::cancel(&pool, id, job_id).await?;
+
+    Ok(web::Json(message))
+}
+
+/// **Trash**
+///
+/// List items in trash:
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/file/1/trash/ -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/file/{id}/trash/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_trash(
+    id: web::Path<i32>,
+    list: ListQuery,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+
+    Ok(web::Json(list.response(list_trash(&config).await?)?))
+}
+
+/// Restore item from trash to its original path:
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/file/1/trash/<ITEM ID>/restore -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/file/{id}/trash/{item}/restore")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::FileEdit)"
+)]
+pub async fn restore_trash_item(
+    path: web::Path<(i32, String)>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, item) = path.into_inner();
+    let manager = controllers.lock().unwrap().get(id).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+
+    Ok(web::Json(restore_trash(&config, &item).await?))
+}
+
+/// Delete one item, or all items, permanently from trash:
+///
+/// ```BASH
+/// curl -X DELETE http://127.0.0.1:8787/api/file/1/trash/<ITEM ID> -H 'Authorization: Bearer <TOKEN>'
+///
+/// curl -X DELETE http://127.0.0.1:8787/api/file/1/trash/ -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[delete("/file/{id}/trash/{item:.*}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin"),
+    ty = "Role",
+    expr = "(user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::FileDelete)"
+)]
+pub async fn purge_trash_items(
+    path: web::Path<(i32, String)>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, item) = path.into_inner();
+    let manager = controllers.lock().unwrap().get(id).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+    let item = Some(item.trim_matches('/')).filter(|i| !i.is_empty());
+
+    purge_trash(&config, item).await?;
+
+    Ok(web::Json("Purge success"))
+}
+
+/// **Upload File**
+///
+/// ```BASH
+/// curl -X PUT http://127.0.0.1:8787/api/file/1/upload/ -H 'Authorization: Bearer <TOKEN>'
+/// -F "file=@file.mp4"
+/// ```
+#[allow(clippy::too_many_arguments)]
+#[put("/file/{id}/upload/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::FileEdit)"
+)]
+async fn save_file(
+    id: web::Path<i32>,
+    req: HttpRequest,
+    payload: Multipart,
+    obj: web::Query<FileObj>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<HttpResponse, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+
+    let size: u64 = req
+        .headers()
+        .get("content-length")
+        .and_then(|cl| cl.to_str().ok())
+        .and_then(|cls| cls.parse().ok())
+        .unwrap_or(0);
+
+    upload(&config, size, payload, &obj.path, false).await
+}
+
+/// **Get File**
+///
+/// Can be used for preview video files. Range requests are supported, so the browser can seek,
+/// and `ETag` / `Last-Modified` for conditional requests. With `download=true` the file is sent
+/// as attachment, also when the storage setting `file_inline` is on.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/file/1/path/to/file.mp4 -H 'Range: bytes=0-1023'
+/// ```
+#[get("/file/{id}/{filename:.*}")]
+async fn get_file(
+    req: HttpRequest,
+    query: web::Query<FileQuery>,
+    controllers: web::Data<Mutex<ChannelController>>,
+) -> Result<impl Responder, ServiceError> {
+    let id: i32 = req.match_info().query("id").parse()?;
+    let manager = controllers.lock().unwrap().get(id).unwrap();
+    let config = manager.config.lock().unwrap();
+    let storage = config.channel.storage.clone();
+    let file_path = req.match_info().query("filename");
+    let (path, _, _) = norm_abs_path(&storage, file_path)?;
+    let mut file = actix_files::NamedFile::open(&path)?;
+
+    if let Some(mime) = media_mime(&path).and_then(|m| m.parse().ok()) {
+        file = file.set_content_type(mime);
+    }
+
+    let disposition = if config.storage.file_inline && !query.download {
+        DispositionType::Inline
+    } else {
+        DispositionType::Attachment
+    };
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut response = file
+        .use_etag(true)
+        .use_last_modified(true)
+        .set_content_disposition(ContentDisposition {
+            disposition,
+            parameters: vec![DispositionParam::Filename(name)],
+        })
+        .customize();
+
+    for header in &config.channel.headers {
+        response = response.insert_header(header.clone());
+    }
+
+    Ok(response)
+}
+
+/// **Get Public**
+///
+/// Can be used for HLS Playlist, DASH manifest and other static files in public folder.
+/// Custom headers from the channel settings (`public_headers`) are added to the response.
+/// The ingest preview is at `/{id}/preview/ingest.m3u8`, the delayed output in `/{id}/delayed/`.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/1/live/stream.m3u8
+/// ```
+#[get("/{id}/{public:live|preview|delayed|public}/{file_stem:.*}")]
+async fn get_public(
+    path: web::Path<(i32, String, String)>,
+    controllers: web::Data<Mutex<ChannelController>>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, public, file_stem) = path.into_inner();
+    let mut headers = vec![];
+
+    let absolute_path = if file_stem.ends_with(".ts")
+        || file_stem.ends_with(".m3u8")
+        || file_stem.ends_with(".vtt")
+        || file_stem.ends_with(".mpd")
+        || file_stem.ends_with(".m4s")
+    {
+        let manager = controllers.lock().unwrap().get(id).unwrap();
+        let config = manager.config.lock().unwrap();
+        headers.clone_from(&config.channel.headers);
+        config.channel.public.join(public)
+    } else {
+        public_path()
+    }
+    .clean();
+
+    let path = absolute_path.join(file_stem.as_str());
+    let file = actix_files::NamedFile::open(path)?;
+
+    let mut response = file
+        .use_last_modified(true)
+        .set_content_disposition(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![],
+        })
+        .customize();
+
+    for header in headers {
+        response = response.insert_header(header);
+    }
+
+    Ok(response)
+}
+
+/// Escape text for the embed html page.
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Get channel and manager, when the channel allows embedding.
+async fn embed_channel(
+    pool: &Pool<Sqlite>,
+    controllers: &Mutex<ChannelController>,
+    id: i32,
+) -> Result<(Channel, ChannelManager), ServiceError> {
+    let channel = handles::select_channel(pool, &id).await?;
+
+    if channel.public_embed != Some(true) {
+        return Err(ServiceError::Forbidden(format!(
+            "Channel {id} is not public embeddable!"
+        )));
+    }
+
+    let manager = controllers
+        .lock()
+        .unwrap()
+        .get(id)
+        .ok_or_else(|| format!("Channel {id} not found!"))?;
+
+    Ok((channel, manager))
+}
+
+/// **Embed Player**
+///
+/// Minimal HTML5 player for the channel HLS output, to use in an iframe.
+/// No authentication is needed, but the channel must have `public_embed` enabled.
+///
+/// ```HTML
+/// <iframe src="http://127.0.0.1:8787/1/embed" width="640" height="360" allowfullscreen></iframe>
+/// ```
+#[get("/{id}/embed")]
+async fn get_embed(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    controllers: web::Data<Mutex<ChannelController>>,
+) -> Result<impl Responder, ServiceError> {
+    let (channel, manager) = embed_channel(&pool, &controllers, *id).await?;
+    let has_logo = {
+        let config = manager.config.lock().unwrap();
+        config.processing.add_logo && Path::new(&config.processing.logo_path).is_file()
+    };
+    let logo = if has_logo {
+        format!(r#"<img src="/{}/embed/logo" alt="">"#, channel.id)
+    } else {
+        String::new()
+    };
+    // JSON string is a valid js string, only closing tags need to be escaped
+    let src = serde_json::to_string(&channel.preview_url)
+        .unwrap_or_default()
+        .replace("</", "<\\/");
+
+    let html = include_str!("embed.html")
+        .replace("{{name}}", &html_escape(&channel.name))
+        .replace("{{id}}", &channel.id.to_string())
+        .replace("{{logo}}", &logo)
+        .replace("{{src}}", &src);
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(html))
+}
+
+/// **Embed Now Playing**
+///
+/// Current and next clip, descriptions can contain markdown.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/1/embed/now
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// {
+///     "name": "Channel 1",
+///     "title": "Some Title",
+///     "description": "Some **markdown** text",
+///     "artwork": "/file/1/artwork/some.jpg",
+///     "next": { "title": "Next Title", "description": null, "artwork": null }
+/// }
+/// ```
+#[get("/{id}/embed/now")]
+async fn get_embed_now(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    controllers: web::Data<Mutex<ChannelController>>,
+) -> Result<impl Responder, ServiceError> {
+    let (channel, manager) = embed_channel(&pool, &controllers, *id).await?;
+    let current = manager.current_media.lock().unwrap().clone();
+    let next = current
+        .as_ref()
+        .and_then(|m| m.index)
+        .and_then(|i| manager.current_list.lock().unwrap().get(i + 1).cloned());
+
+    let mut data = current
+        .map(|m| embed_item(*id, &m))
+        .unwrap_or_else(|| serde_json::json!({ "title": "" }));
+
+    data["name"] = serde_json::json!(channel.name);
+    data["next"] = next.map_or(serde_json::Value::Null, |m| embed_item(*id, &m));
+
+    Ok(web::Json(data))
+}
+
+/// Public infos from a clip.
+fn embed_item(id: i32, media: &Media) -> serde_json::Value {
+    let title = media.title.clone().unwrap_or_else(|| {
+        Path::new(&media.source)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default()
+    });
+
+    serde_json::json!({
+        "title": title,
+        "description": media.description,
+        "artwork": media.artwork.as_ref().map(|a| artwork_url(id, a)),
+    })
+}
+
+/// **Embed Logo**
+///
+/// Channel logo for the embed player.
+#[get("/{id}/embed/logo")]
+async fn get_embed_logo(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    controllers: web::Data<Mutex<ChannelController>>,
+) -> Result<actix_files::NamedFile, ServiceError> {
+    let (_, manager) = embed_channel(&pool, &controllers, *id).await?;
+    let logo_path = manager.config.lock().unwrap().processing.logo_path.clone();
+    let file = actix_files::NamedFile::open(logo_path)?;
+
+    Ok(file.use_last_modified(true))
+}
+
+/// **Now Playing**
+///
+/// Current and next item with elapsed and remaining time in seconds, for website widgets.
+/// The data comes from a cache, which the player updates when a clip starts.
+/// No login is needed, but the channel must have `public_nowplaying` enabled. With a `nowplaying_token`
+/// in the channel settings, the token must be given as `?token=` or as bearer token.
+/// Custom headers from the channel settings (`public_headers`) are added to the response.
+///
+/// ```BASH
+/// curl -X GET 'http://127.0.0.1:8787/api/nowplaying/1?token=<TOKEN>'
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// {
+///     "current": {
+///         "title": "Some Title",
+///         "category": "",
+///         "description": null,
+///         "artwork": "/file/1/artwork/some.jpg",
+///         "start": "2024-06-13T14:00:00.000+02:00",
+///         "duration": 1800.0
+///     },
+///     "next": { "title": "Next Title", "category": "news", "description": null, "artwork": null, "start": "2024-06-13T14:30:00.000+02:00", "duration": 600.0 },
+///     "elapsed": 123.4,
+///     "remaining": 1676.6
+/// }
+/// ```
+#[get("/api/nowplaying/{id}")]
+async fn get_nowplaying(
+    id: web::Path<i32>,
+    req: HttpRequest,
+    obj: web::Query<TokenObj>,
+    controllers: web::Data<Mutex<ChannelController>>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers
+        .lock()
+        .unwrap()
+        .get(*id)
+        .ok_or_else(|| format!("Channel {id} not found!"))?;
+    let headers = {
+        let config = manager.config.lock().unwrap();
+        let token = obj.token.as_deref().or_else(|| {
+            req.headers()
+                .get("Authorization")
+                .and_then(|h| h.to_str().ok())
+                .and_then(|h| h.trim().strip_prefix("Bearer "))
+        });
+
+        check_access(
+            config.channel.public_nowplaying,
+            &config.channel.nowplaying_token,
+            token,
+        )?;
+
+        config.channel.headers.clone()
+    };
+    let mut data = now_next(&manager);
+
+    for item in [&mut data.current, &mut data.next].into_iter().flatten() {
+        item.artwork = item.artwork.as_ref().map(|a| artwork_url(*id, a));
+    }
+
+    let mut response = HttpResponse::Ok();
+
+    for header in headers {
+        response.insert_header(header);
+    }
+
+    Ok(response.json(data))
+}
+
+/// **Import playlist**
+///
+/// Import text/m3u file and convert it to a playlist
+/// lines with leading "#" will be ignore.
+/// With `chapters=true` files are split by their chapters into multiple items.
+///
+/// ```BASH
+/// curl -X PUT 'http://127.0.0.1:8787/api/file/1/import/?file=list.m3u&date=2024-10-30&chapters=true'
+/// -H 'Authorization: Bearer <TOKEN>' -F "file=@list.m3u"
+/// ```
+#[allow(clippy::too_many_arguments)]
+#[put("/file/{id}/import/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::PlaylistEdit)"
+)]
+async fn import_playlist(
+    id: web::Path<i32>,
+    req: HttpRequest,
+    payload: Multipart,
+    obj: web::Query<ImportObj>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    broadcaster: web::Data<Broadcaster>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<HttpResponse, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let channel_name = manager.channel.lock().unwrap().name.clone();
+    let config = manager.config.lock().unwrap().clone();
+    let file = obj.file.file_name().unwrap_or_default();
+    let path = env::temp_dir().join(file);
+    let path_clone = path.clone();
+    let size: u64 = req
+        .headers()
+        .get("content-length")
+        .and_then(|cl| cl.to_str().ok())
+        .and_then(|cls| cls.parse().ok())
+        .unwrap_or(0);
+
+    upload(&config, size, payload, &path, true).await?;
+
+    let date = obj.date.clone();
+    let response = web::block(move || {
+        import_file(
+            &config,
+            &obj.date,
+            Some(channel_name),
+            &path_clone,
+            obj.chapters,
+        )
+    })
+    .await??;
+
+    fs::remove_file(path).await?;
+
+    let event = playlist_event(date, PlaylistAction::Saved);
+    broadcaster.notify(*id, event).await;
+
+    Ok(HttpResponse::Ok().body(response))
+}
+
+/// **Program info**
+///
+/// Get program infos about given date, or current day.
+/// With air hours, items which start off air are not listed.
+///
+/// Examples:
+///
+/// * get program from current day
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/program/1/ -H 'Authorization: Bearer <TOKEN>'
+/// ```
+///
+/// * get a program range between two dates
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/program/1/?start_after=2022-11-13T12:00:00&start_before=2022-11-20T11:59:59 \
+/// -H 'Authorization: Bearer <TOKEN>'
+/// ```
+///
+/// * get program from give day
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/program/1/?start_after=2022-11-13T10:00:00 \
+/// -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/program/{id}/")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+async fn get_program(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    obj: web::Query<ProgramObj>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+    let program: Vec<ProgramItem> = program_list(&pool, &config, &obj)
+        .await
+        .into_iter()
+        .map(|(_, _, item)| item)
+        .collect();
+
+    Ok(web::Json(program))
+}
+
+/// **Program as XMLTV**
+///
+/// Same range as the program info, but in XMLTV format. Markdown in descriptions is converted to plain text.
+/// Items with a parental rating get a `<rating>` in the rating system of the channel.
+/// Like in the program info, items outside of the air hours are left out.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/program/1/xmltv?start_after=2022-11-13T10:00:00 \
+/// -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[allow(clippy::too_many_arguments)]
+#[get("/program/{id}/xmltv")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+async fn get_program_xmltv(
+    req: HttpRequest,
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    obj: web::Query<ProgramObj>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+    let name = manager.channel.lock().unwrap().name.clone();
+    let host = {
+        let conn = req.connection_info();
+
+        format!("{}://{}", conn.scheme(), conn.host())
+    };
+    let channel_id = format!("ffplayout-{id}");
+    let time_fmt = "%Y%m%d%H%M%S %z";
+
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE tv SYSTEM \"xmltv.dtd\">\n<tv generator-info-name=\"ffplayout\">\n  <channel id=\"{channel_id}\">\n    <display-name>{}</display-name>\n  </channel>\n",
+        html_escape(&name)
+    );
+
+    for (start, stop, item) in program_list(&pool, &config, &obj).await {
+        let title = item.title.clone().unwrap_or_else(|| {
+            Path::new(&item.source)
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+
+        xml.push_str(&format!(
+            "  <programme start=\"{}\" stop=\"{}\" channel=\"{channel_id}\">\n    <title>{}</title>\n",
+            start.format(time_fmt),
+            stop.format(time_fmt),
+            html_escape(&title)
+        ));
+
+        if let Some(desc) = item.description.as_ref().filter(|d| !d.is_empty()) {
+            xml.push_str(&format!(
+                "    <desc>{}</desc>\n",
+                html_escape(&markdown_to_text(desc))
+            ));
+        }
+
+        if !item.category.is_empty() {
+            xml.push_str(&format!(
+                "    <category>{}</category>\n",
+                html_escape(&item.category)
+            ));
+        }
+
+        if let Some(artwork) = &item.artwork {
+            let src = if artwork.starts_with('/') {
+                format!("{host}{artwork}")
+            } else {
+                artwork.clone()
+            };
+
+            xml.push_str(&format!("    <icon src=\"{}\" />\n", html_escape(&src)));
+        }
+
+        if let Some(rating) = &item.rating {
+            let system = &config.output.rating.system;
+
+            if system.is_empty() {
+                xml.push_str("    <rating>\n");
+            } else {
+                xml.push_str(&format!(
+                    "    <rating system=\"{}\">\n",
+                    html_escape(system)
+                ));
+            }
+
+            xml.push_str(&format!(
+                "      <value>{}</value>\n    </rating>\n",
+                html_escape(rating)
+            ));
+        }
+
+        xml.push_str("  </programme>\n");
+    }
+
+    xml.push_str("</tv>\n");
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/xml; charset=utf-8")
+        .body(xml))
+}
+
+/// Public url for a artwork file in the channel storage, full urls are kept.
+fn artwork_url(id: i32, artwork: &str) -> String {
+    if artwork.contains("://") {
+        return artwork.to_string();
+    }
+
+    format!("/file/{id}/{}", artwork.trim_start_matches('/'))
+}
+
+/// Read all program items in the given time range, with start and end time.
+/// Items without artwork get one from the artwork lookup, when it is configured.
+/// Program items with start and stop time, only the items which are on air.
+pub async fn program_list(
+    pool: &Pool<Sqlite>,
+    config: &PlayoutConfig,
+    obj: &ProgramObj,
+) -> Vec<(DateTime<Local>, DateTime<Local>, ProgramItem)> {
+    let id = config.general.channel_id;
+    let start_sec = config.playlist.start_sec.unwrap();
+    let mut days = 0;
+    let mut program = vec![];
+    let after = obj.start_after;
+    let mut before = obj.start_before;
+
+    if after > before {
+        before = chrono::Local
+            .with_ymd_and_hms(after.year(), after.month(), after.day(), 23, 59, 59)
             .unwrap()
             .naive_local();
     }
 
-    if start_sec > time_to_sec(&after.format("%H:%M:%S").to_string()) {
-        days = 1;
+    if start_sec > time_to_sec(&after.format("%H:%M:%S").to_string()) {
+        days = 1;
+    }
+
+    let date_range = get_date_range(
+        id,
+        &vec_strings![
+            (after - TimeDelta::try_days(days).unwrap_or_default()).format("%Y-%m-%d"),
+            "-",
+            before.format("%Y-%m-%d")
+        ],
+    );
+
+    for date in date_range {
+        let mut naive = NaiveDateTime::parse_from_str(
+            &format!("{date} {}", sec_to_time(start_sec)),
+            "%Y-%m-%d %H:%M:%S%.3f",
+        )
+        .unwrap();
+
+        let playlist = match read_playlist(config, date.clone()).await {
+            Ok(p) => p,
+            Err(e) => {
+                error!("Error in Playlist from {date}: {e}");
+                continue;
+            }
+        };
+
+        for item in playlist.program {
+            let start: DateTime<Local> = Local.from_local_datetime(&naive).unwrap();
+            let length = TimeDelta::try_milliseconds(((item.out - item.seek) * 1000.0) as i64)
+                .unwrap_or_default();
+
+            let source = match Regex::new(&config.text.regex)
+                .ok()
+                .and_then(|r| r.captures(&item.source))
+            {
+                Some(t) => t[1].to_string(),
+                None => item.source,
+            };
+            let lookup_title = item.title.clone().unwrap_or_else(|| {
+                Path::new(&source)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            });
+
+            let mut p_item = ProgramItem {
+                source,
+                start: start.format("%Y-%m-%d %H:%M:%S%.3f%:z").to_string(),
+                title: item.title,
+                r#in: item.seek,
+                out: item.out,
+                duration: item.duration,
+                category: item.category,
+                description: item.description,
+                enable_description: item.enable_description,
+                artwork: item.artwork.map(|a| artwork_url(id, &a)),
+                rating: config.output.rating.map(item.rating.as_deref()),
+            };
+
+            // items outside of the air hours are not broadcast
+            let on_air = config
+                .general
+                .air_hours
+                .on_air(time_to_sec(&start.format("%H:%M:%S").to_string()));
+
+            if naive >= after && naive <= before && on_air {
+                if p_item.artwork.is_none() {
+                    p_item.artwork = lookup_artwork(pool, config, &lookup_title)
+                        .await
+                        .map(|a| artwork_url(id, &a));
+                }
+
+                program.push((start, start + length, p_item));
+            }
+
+            naive += length;
+        }
+    }
+
+    program
+}
+
+/// ### System Statistics
+///
+/// Get statistics about CPU, Ram, Disk, etc. usage. `decklink` lists the installed Decklink cards.
+/// `gpus` lists the NVIDIA GPUs, with the channels which run on them.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/system/1
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/system/{id}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_system_stat(
+    id: web::Path<i32>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers.lock().unwrap().get(*id).unwrap();
+    let config = manager.config.lock().unwrap().clone();
+
+    let stat = web::block(move || system::stat(&config)).await?;
+
+    Ok(web::Json(stat))
+}
+
+/// **ffmpeg Capabilities**
+///
+/// Probe the installed ffmpeg for version, libraries, encoders, muxers and filters, and check the
+/// channel config against them. `problems` lists what is missing, like `Encoder libx265 not available`.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/system/1/ffmpeg
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/system/{id}/ffmpeg")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_ffmpeg_caps(
+    id: web::Path<i32>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers
+        .lock()
+        .unwrap()
+        .get(*id)
+        .ok_or_else(|| format!("Channel {id} not found!"))?;
+    let config = manager.config.lock().unwrap().clone();
+
+    let ffmpeg = web::block(probe_ffmpeg)
+        .await?
+        .map_err(ServiceError::ServiceUnavailable)?;
+    let problems = check_config(&config, &ffmpeg);
+
+    Ok(web::Json(FfmpegReport { ffmpeg, problems }))
+}
+
+/// **Health Details**
+///
+/// The checks from `/health/ready` with their messages, the disk paths and the state of every channel.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/system/health -H 'Authorization: Bearer <TOKEN>'
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// {
+///     "status": "ok",
+///     "checks": [
+///         { "name": "database", "ok": true, "message": "Connected" },
+///         { "name": "ffmpeg", "ok": true, "message": "ffmpeg version 7.1" },
+///         { "name": "disk:/var/lib/ffplayout/tv-media", "ok": true, "message": "52428800000 bytes free" }
+///     ],
+///     "channels": [
+///         { "id": 1, "name": "Channel 1", "active": true, "running": true, "off_air": false, "maintenance": false, "ok": true }
+///     ]
+/// }
+/// ```
+#[get("/system/health")]
+#[protect("Role::GlobalAdmin", ty = "Role")]
+pub async fn get_health_details(
+    pool: web::Data<Pool<Sqlite>>,
+    controllers: web::Data<Mutex<ChannelController>>,
+) -> Result<impl Responder, ServiceError> {
+    Ok(web::Json(readiness(&pool, &controllers).await))
+}
+
+/// ### Shutdown
+///
+/// Stops the engine like SIGTERM: new requests get a 503, running uploads can finish,
+/// the channels write their state and ffmpeg gets time to close the outputs.
+/// Returns the count of uploads, which are still running.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/system/shutdown -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/system/shutdown")]
+#[protect("Role::GlobalAdmin", ty = "Role")]
+pub async fn shutdown_engine(user: web::ReqData<UserMeta>) -> Result<impl Responder, ServiceError> {
+    request_shutdown(&format!("API, user id {}", user.id));
+
+    Ok(web::Json(shutdown_status()))
+}
+
+/// ### Backup
+///
+/// Tar archive with the database, the channel settings and the text presets.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/system/backup -H 'Authorization: Bearer <TOKEN>' -o ffplayout_backup.tar
+/// ```
+#[get("/system/backup")]
+#[protect("Role::GlobalAdmin", ty = "Role")]
+pub async fn get_backup(pool: web::Data<Pool<Sqlite>>) -> Result<impl Responder, ServiceError> {
+    let archive = create_backup(&pool).await?;
+    let filename = format!(
+        "ffplayout_backup_{}.tar",
+        time_now().format("%Y-%m-%d_%H-%M-%S")
+    );
+
+    // streamed from the temporary file
+    Ok(actix_files::NamedFile::from_file(archive, &filename)?
+        .use_etag(false)
+        .use_last_modified(false)
+        .set_content_disposition(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(filename)],
+        }))
+}
+
+/// **Restore**
+///
+/// Restore a backup from `/system/backup`. Backups from a newer version are rejected, older ones are migrated.
+/// The database replaces the running one with the next start, so the server shuts down after the restore
+/// and must be started again, for example by systemd.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/system/restore -H 'Content-Type: application/x-tar' \
+/// -H 'Authorization: Bearer <TOKEN>' --data-binary @ffplayout_backup.tar
+/// ```
+#[post("/system/restore")]
+#[protect("Role::GlobalAdmin", ty = "Role")]
+pub async fn restore_system(
+    mut payload: web::Payload,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let mut archive = vec![];
+
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|e| ServiceError::BadRequest(e.to_string()))?;
+
+        if archive.len() + chunk.len() > MAX_BACKUP_SIZE {
+            return Err(ServiceError::BadRequest(format!(
+                "Backup is larger than {} MiB",
+                MAX_BACKUP_SIZE / 1024 / 1024
+            )));
+        }
+
+        archive.extend_from_slice(&chunk);
     }
 
-    let date_range = get_date_range(
-        id,
-        &vec_strings![
-            (after - TimeDelta::try_days(days).unwrap_or_default()).format("%Y-%m-%d"),
-            "-",
-            before.format("%Y-%m-%d")
-        ],
+    let manifest = restore_backup(&archive).await?;
+
+    info!(
+        "Backup from <yellow>{}</> (version {}) restored by user id {}",
+        manifest.created, manifest.version, user.id
     );
 
-    for date in date_range {
-        let mut naive = NaiveDateTime::parse_from_str(
-            &format!("{date} {}", sec_to_time(start_sec)),
-            "%Y-%m-%d %H:%M:%S%.3f",
+    request_shutdown("restore");
+
+    Ok(web::Json(manifest))
+}
+
+/// ### Cache Statistics
+///
+/// Hits and misses of the pre-cache and the object storage cache, copied clips and cache size.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/system/1/cache
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/system/{id}/cache")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_cache_stat(
+    id: web::Path<i32>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    Ok(web::Json(pre_cache::cache_stats(*id)))
+}
+
+/// ### Failover Status
+///
+/// Role from this instance and health of the other instance, when failover is configured.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/failover -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/failover")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role"
+)]
+pub async fn get_failover_status() -> Result<impl Responder, ServiceError> {
+    Ok(web::Json(failover::failover_status()))
+}
+
+/// ### Cluster Nodes
+///
+/// Playout nodes, which run channels for this instance. Control and status calls of their
+/// channels are proxied to the node.
+///
+/// **Get Nodes**
+///
+/// Nodes with health and channels.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/nodes -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/nodes")]
+#[protect("Role::GlobalAdmin", ty = "Role")]
+async fn get_nodes(pool: web::Data<Pool<Sqlite>>) -> Result<impl Responder, ServiceError> {
+    Ok(web::Json(cluster::node_status(&pool).await?))
+}
+
+/// **Add Node**
+///
+/// The node needs the same channels, storage and secret like this instance.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/nodes/ -H 'Content-Type: application/json' \
+/// -d '{ "name": "node-1", "url": "http://10.0.0.2:8787" }' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/nodes/")]
+#[protect("Role::GlobalAdmin", ty = "Role")]
+async fn add_node(
+    pool: web::Data<Pool<Sqlite>>,
+    data: web::Json<Node>,
+) -> Result<impl Responder, ServiceError> {
+    let mut node = data.into_inner();
+
+    if node.name.trim().is_empty() {
+        return Err(ServiceError::BadRequest("Node name is needed".into()));
+    }
+
+    node.url = cluster::node_url(&node.url)?;
+
+    let node = handles::insert_node(&pool, &node).await?;
+    cluster::check_node(&node).await;
+
+    Ok(web::Json(node))
+}
+
+/// **Update Node**
+///
+/// A drained node gets no new channels, its channels move to the other nodes.
+///
+/// ```BASH
+/// curl -X PUT http://127.0.0.1:8787/api/nodes/1 -H 'Content-Type: application/json' \
+/// -d '{ "name": "node-1", "url": "http://10.0.0.2:8787", "drained": true }' \
+/// -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[put("/nodes/{node}")]
+#[protect("Role::GlobalAdmin", ty = "Role")]
+async fn update_node(
+    pool: web::Data<Pool<Sqlite>>,
+    node_id: web::Path<i32>,
+    data: web::Json<Node>,
+) -> Result<impl Responder, ServiceError> {
+    let mut node = data.into_inner();
+
+    if node.name.trim().is_empty() {
+        return Err(ServiceError::BadRequest("Node name is needed".into()));
+    }
+
+    node.id = *node_id;
+    node.url = cluster::node_url(&node.url)?;
+
+    if handles::update_node(&pool, &node).await?.rows_affected() == 0 {
+        return Err(ServiceError::NoContent("Node not found".into()));
+    }
+
+    cluster::load_routes(&pool).await?;
+
+    Ok("Update node success")
+}
+
+/// **Delete Node**
+///
+/// Only nodes without channels can be deleted, drain the node first.
+///
+/// ```BASH
+/// curl -X DELETE http://127.0.0.1:8787/api/nodes/1 -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[delete("/nodes/{node}")]
+#[protect("Role::GlobalAdmin", ty = "Role")]
+async fn remove_node(
+    pool: web::Data<Pool<Sqlite>>,
+    node_id: web::Path<i32>,
+) -> Result<impl Responder, ServiceError> {
+    let assigned = handles::select_node_channels(&pool).await?;
+
+    if assigned.iter().any(|a| a.node_id == *node_id) {
+        return Err(ServiceError::Conflict(
+            "Node has channels, drain it first".into(),
+        ));
+    }
+
+    handles::delete_node(&pool, *node_id).await?;
+    cluster::forget_node(*node_id);
+
+    Ok("Delete node success")
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct NodeTarget {
+    node_id: Option<i32>,
+}
+
+/// **Schedule Channel**
+///
+/// Put a channel on a node, without `node_id` the node with the fewest channels is taken.
+/// An active channel stops where it runs and starts on the node.
+///
+/// ```BASH
+/// curl -X PUT http://127.0.0.1:8787/api/nodes/channel/1 -H 'Content-Type: application/json' \
+/// -d '{ "node_id": 2 }' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[put("/nodes/channel/{id}")]
+#[protect("Role::GlobalAdmin", ty = "Role")]
+async fn schedule_channel(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    data: web::Json<NodeTarget>,
+    controllers: web::Data<Mutex<ChannelController>>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers
+        .lock()
+        .unwrap()
+        .get(*id)
+        .ok_or_else(|| ServiceError::NoContent("Channel not found".into()))?;
+    let nodes = handles::select_nodes(&pool).await?;
+    let assigned = handles::select_node_channels(&pool).await?;
+    let node_id = match data.node_id {
+        Some(node_id) => node_id,
+        None => cluster::pick_node(
+            &nodes,
+            &cluster::node_load(&assigned),
+            &cluster::online_nodes(),
+            None,
         )
-        .unwrap();
+        .ok_or_else(|| ServiceError::Conflict("No node is online".into()))?,
+    };
+    let node = nodes
+        .iter()
+        .find(|n| n.id == node_id && !n.drained)
+        .ok_or_else(|| ServiceError::BadRequest("Node not exists or is drained".into()))?;
 
-        let playlist = match read_playlist(&config, date.clone()).await {
-            Ok(p) => p,
-            Err(e) => {
-                error!("Error in Playlist from {date}: {e}");
-                continue;
-            }
+    cluster::move_channel(&pool, &manager, Some(node)).await?;
+
+    Ok(web::Json(node.clone()))
+}
+
+/// **Release Channel**
+///
+/// The channel runs again on this instance.
+///
+/// ```BASH
+/// curl -X DELETE http://127.0.0.1:8787/api/nodes/channel/1 -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[delete("/nodes/channel/{id}")]
+#[protect("Role::GlobalAdmin", ty = "Role")]
+async fn release_channel(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    controllers: web::Data<Mutex<ChannelController>>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers
+        .lock()
+        .unwrap()
+        .get(*id)
+        .ok_or_else(|| ServiceError::NoContent("Channel not found".into()))?;
+
+    cluster::move_channel(&pool, &manager, None).await?;
+
+    Ok("Channel runs on the API tier")
+}
+
+/// ### Audit Log
+///
+/// Get all state changing API calls for a channel, optional in a time range.
+/// Global admins and channel admins get all entries, users only their own.
+///
+/// ```BASH
+/// curl -X GET 'http://127.0.0.1:8787/api/audit/1?from=2024-10-01&to=2024-10-31T23:59:59' \
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/audit/{id}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn get_audit_log(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    obj: web::Query<AuditObj>,
+    list: ListQuery,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let user_id =
+        if role.has_authority(&Role::GlobalAdmin) || role.has_authority(&Role::ChannelAdmin) {
+            None
+        } else {
+            Some(user.id)
         };
+    let (from, to) = obj.range();
+    let entries = handles::select_audit(&pool, *id, user_id, &from, &to).await?;
 
-        for item in playlist.program {
-            let start: DateTime<Local> = Local.from_local_datetime(&naive).unwrap();
+    Ok(web::Json(list.response(entries)?))
+}
 
-            let source = match Regex::new(&config.text.regex)
-                .ok()
-                .and_then(|r| r.captures(&item.source))
-            {
-                Some(t) => t[1].to_string(),
-                None => item.source,
-            };
+/// **Audit Log without Channel**
+///
+/// Get the entries without channel, like from user and channel management. Only for global admins.
+///
+/// ```BASH
+/// curl -X GET 'http://127.0.0.1:8787/api/audit?from=2024-10-01&to=2024-10-31T23:59:59' \
+/// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/audit")]
+#[protect("Role::GlobalAdmin", ty = "Role")]
+pub async fn get_global_audit_log(
+    pool: web::Data<Pool<Sqlite>>,
+    obj: web::Query<AuditObj>,
+    list: ListQuery,
+) -> Result<impl Responder, ServiceError> {
+    let (from, to) = obj.range();
+    let entries = handles::select_audit_global(&pool, &from, &to).await?;
 
-            let p_item = ProgramItem {
-                source,
-                start: start.format("%Y-%m-%d %H:%M:%S%.3f%:z").to_string(),
-                title: item.title,
-                r#in: item.seek,
-                out: item.out,
-                duration: item.duration,
-                category: item.category,
-                description: item.description,
-                enable_description: item.enable_description,
-            };
+    Ok(web::Json(list.response(entries)?))
+}
 
-            if naive >= after && naive <= before {
-                program.push(p_item);
-            }
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct HandoverObj {
+    #[serde(default)]
+    date: Option<String>,
+}
 
-            naive += TimeDelta::try_milliseconds(((item.out - item.seek) * 1000.0) as i64)
-                .unwrap_or_default();
+/// **Shift Handover Log**
+///
+/// Operators write notes for the next shift. Without a given timestamp the current time is used,
+/// without a event the currently playing clip is stored as as-run reference.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/handover/1 -H 'Content-Type: application/json' \
+/// -d '{ "note": "Audio dropouts on ingest, provider is informed." }' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/handover/{id}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+pub async fn add_handover_note(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    data: web::Json<HandoverNote>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let mut note = data.into_inner();
+
+    if note.note.trim().is_empty() {
+        return Err(ServiceError::BadRequest("Note is empty!".into()));
+    }
+
+    note.timestamp = note.timestamp.replace('T', " ");
+
+    if note.timestamp.is_empty() {
+        note.timestamp = time_now().format("%Y-%m-%d %H:%M:%S").to_string();
+    } else if NaiveDateTime::parse_from_str(&note.timestamp, "%Y-%m-%d %H:%M:%S").is_err() {
+        return Err(ServiceError::BadRequest(
+            "Timestamp must be in format: YYYY-MM-DD HH:MM:SS".into(),
+        ));
+    }
+
+    if note.event.is_empty() {
+        let manager = controllers.lock().unwrap().get(*id);
+
+        if let Some(media) = manager.and_then(|m| m.current_media.lock().unwrap().clone()) {
+            let title = media.title.clone().unwrap_or(media.source.clone());
+
+            note.event = match media.begin {
+                Some(begin) => format!("{} {title}", sec_to_time(begin)),
+                None => title,
+            };
         }
     }
 
-    Ok(web::Json(program))
+    note.channel_id = *id;
+    note.user_id = user.id;
+
+    handles::insert_handover(&pool, note).await?;
+
+    Ok("Add handover note success")
 }
 
-/// ### System Statistics
+/// **Get Shift Handover Log**
 ///
-/// Get statistics about CPU, Ram, Disk, etc. usage.
+/// Get the notes from one day, default is today.
 ///
 /// ```BASH
-/// curl -X GET http://127.0.0.1:8787/api/system/1
+/// curl -X GET 'http://127.0.0.1:8787/api/handover/1?date=2024-10-27' \
 /// -H 'Content-Type: application/json' -H 'Authorization: Bearer <TOKEN>'
 /// ```
-#[get("/system/{id}")]
+#[get("/handover/{id}")]
 #[protect(
     any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
     ty = "Role",
     expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
 )]
-pub async fn get_system_stat(
+pub async fn get_handover_notes(
+    pool: web::Data<Pool<Sqlite>>,
     id: web::Path<i32>,
-    controllers: web::Data<Mutex<ChannelController>>,
+    obj: web::Query<HandoverObj>,
+    list: ListQuery,
     role: AuthDetails<Role>,
     user: web::ReqData<UserMeta>,
 ) -> Result<impl Responder, ServiceError> {
-    let manager = controllers.lock().unwrap().get(*id).unwrap();
-    let config = manager.config.lock().unwrap().clone();
+    let date = obj
+        .date
+        .clone()
+        .unwrap_or_else(|| time_now().format("%Y-%m-%d").to_string());
+
+    if NaiveDate::parse_from_str(&date, "%Y-%m-%d").is_err() {
+        return Err(ServiceError::BadRequest(
+            "Date must be in format: YYYY-MM-DD".into(),
+        ));
+    }
 
-    let stat = web::block(move || system::stat(&config)).await?;
+    let notes = handles::select_handover(
+        &pool,
+        *id,
+        &format!("{date} 00:00:00"),
+        &format!("{date} 23:59:59"),
+    )
+    .await?;
 
-    Ok(web::Json(stat))
+    Ok(web::Json(list.response(notes)?))
 }
 
-pub mod ytbot {
-    use super::*;
-    use super::livestream::extract_rtmp_stream_details; // IMPORTANTE: para usar a função que extrai o rtmp_details
-
-    static YTBOT_PROCESSES: Lazy<AsyncMutex<HashMap<i32, Arc<AsyncMutex<Child>>>>> = Lazy::new(|| AsyncMutex::new(HashMap::new()));
+/// #### Companions
+///
+/// Helper processes of a channel, like bots, relays or a pipe into the ingest. Arguments can use
+/// channel variables, like `{channel_id}`, `{channel_name}`, `{storage}` or `{ingest_url}`.
+/// Only global admins can add or change them, because the command runs on the server.
+///
+/// **Get Companions**
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/companions/1 -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/companions/{id}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+async fn get_companions(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let companions = handles::select_companions(&pool, *id).await?;
 
-    #[derive(Error, Debug)]
-    enum YtbotError {
-        #[error("Erro ao verificar o status do ytbot: {0}")]
-        StatusError(String),
-    }
+    Ok(web::Json(companions))
+}
 
-    async fn get_ytbot_path() -> Option<String> {
-        if let Ok(path) = env::var("YTBOT_PATH") {
-            if metadata(&path).await.is_ok() {
-                return Some(path);
-            }
-        }
+/// **Add Companion**
+///
+/// Restart policy is `never`, `on_failure` or `always`, `max_restarts` 0 is unlimited.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/companions/1 -H 'Content-Type: application/json' \
+/// -d '{ "name": "relay", "command": "/usr/bin/ffmpeg", "args": "-i {ingest_url} -c copy -f flv rtmp://example.org/live/{channel_id}", "restart": "on_failure", "max_restarts": 5, "autostart": true }' \
+/// -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/companions/{id}")]
+#[protect("Role::GlobalAdmin", ty = "Role")]
+async fn add_companion(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    data: web::Json<Companion>,
+) -> Result<impl Responder, ServiceError> {
+    let mut companion = data.into_inner();
 
-        let paths = ["/usr/local/bin/ytbot.sh", "/usr/local/bin/ytbot.py"];
+    companion::check_companion(&companion)?;
+    companion.channel_id = *id;
 
-        for path in &paths {
-            if metadata(path).await.is_ok() {
-                return Some(path.to_string());
-            }
-        }
-        None
-    }
+    handles::insert_companion(&pool, &companion).await?;
 
-    /// Verifica se o processo `ytbot` está ativo para um determinado canal.
-    async fn is_ytbot_active(channel_id: i32) -> Result<bool, YtbotError> {
-        let mut processes = YTBOT_PROCESSES.lock().await;
+    Ok("Add companion success")
+}
 
-        // Removemos do mapa primeiro
-        if let Some(ytbot_process) = processes.remove(&channel_id) {
-            let mut ytbot_child = ytbot_process.lock().await;
+/// **Update Companion**
+///
+/// A running process keeps its settings until the next start.
+///
+/// ```BASH
+/// curl -X PUT http://127.0.0.1:8787/api/companions/1/2 -H 'Content-Type: application/json' \
+/// -d '{ "name": "relay", "command": "/usr/bin/ffmpeg", "args": "<ARGS>", "restart": "always" }' \
+/// -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[put("/companions/{id}/{companion}")]
+#[protect("Role::GlobalAdmin", ty = "Role")]
+async fn update_companion(
+    pool: web::Data<Pool<Sqlite>>,
+    path: web::Path<(i32, i32)>,
+    data: web::Json<Companion>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, companion_id) = path.into_inner();
+    let mut companion = data.into_inner();
 
-            match ytbot_child.try_wait() {
-                Ok(Some(_status)) => {
-                    // O processo terminou, não reinserimos no mapa
-                    Ok(false)
-                }
-                Ok(None) => {
-                    // O processo ainda está ativo
-                    // Precisamos reinserir o processo no mapa
-                    drop(ytbot_child); // Solta o guard antes de reinserir
+    companion::check_companion(&companion)?;
+    companion.id = companion_id;
+    companion.channel_id = id;
 
-                    // Reinserir o mesmo processo no mapa
-                    processes.insert(channel_id, ytbot_process);
+    let result = handles::update_companion(&pool, &companion).await?;
 
-                    Ok(true)
-                }
-                Err(e) => Err(YtbotError::StatusError(e.to_string())),
-            }
-        } else {
-            Ok(false) // Nenhum processo registrado para esse canal
-        }
+    if result.rows_affected() == 0 {
+        return Err(ServiceError::NoContent("Companion not found".into()));
     }
 
-    #[derive(Debug, Serialize, Deserialize)]
-    #[serde(rename_all = "snake_case")]
-    pub enum ServiceStatus {
-        Active,
-        Inactive,
-    }
-
-    #[derive(Debug, Serialize, Deserialize)]
-    pub struct ServiceStatusResponse {
-        pub status: ServiceStatus,
-    }
-
-    #[get("/status/{id}")]
-    #[protect(
-        any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
-        ty = "Role"
-    )]
-    pub async fn ytbot_service_status(
-        id: web::Path<i32>,
-        _role: AuthDetails<Role>,
-        _user: web::ReqData<UserMeta>,
-        controllers: web::Data<Mutex<ChannelController>>, // Adicionado como parâmetro
-    ) -> impl Responder {
-        let channel_id = *id;
-        let channel_name = match get_channel_name(channel_id, controllers.clone()).await {
-            Ok(name) => name,
-            Err(_) => return HttpResponse::InternalServerError().json("Erro ao acessar o canal"),
-        };
+    Ok("Update companion success")
+}
 
-        match is_ytbot_active(channel_id).await {
-            Ok(active) => {
-                let status = if active {
-                    ServiceStatus::Active
-                } else {
-                    ServiceStatus::Inactive
-                };
-                let response = ServiceStatusResponse { status };
-                HttpResponse::Ok().json(response)
-            }
-            Err(e) => {
-                error!(
-                    "Erro ao verificar o status do ytbot para o canal {}: {}",
-                    channel_name, e
-                );
-                HttpResponse::InternalServerError().json(format!(
-                    "Erro ao verificar o status do ytbot para o canal {}",
-                    channel_name
-                ))
-            }
-        }
-    }
+/// **Delete Companion**
+///
+/// A running process is stopped.
+///
+/// ```BASH
+/// curl -X DELETE http://127.0.0.1:8787/api/companions/1/2 -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[delete("/companions/{id}/{companion}")]
+#[protect("Role::GlobalAdmin", ty = "Role")]
+async fn delete_companion(
+    pool: web::Data<Pool<Sqlite>>,
+    path: web::Path<(i32, i32)>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, companion_id) = path.into_inner();
+    let name = handles::select_companions(&pool, id)
+        .await?
+        .into_iter()
+        .find(|c| c.id == companion_id)
+        .map(|c| c.name)
+        .ok_or_else(|| ServiceError::NoContent("Companion not found".into()))?;
 
-    #[derive(Debug, Deserialize, Serialize, Clone)]
-    #[serde(rename_all = "snake_case")]
-    pub enum ServiceAction {
-        Start,
-        Stop,
-    }
-
-    #[derive(Debug, Deserialize, Serialize, Clone)]
-    pub struct ServiceControlParams {
-        pub action: ServiceAction,
-    }
-
-    #[post("/control/{id}")]
-    #[protect(
-        any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
-        ty = "Role"
-    )]
-    pub async fn ytbot_control(
-        id: web::Path<i32>,
-        req: web::Json<ServiceControlParams>,
-        controllers: web::Data<Mutex<ChannelController>>, // Adicionado como parâmetro
-        _role: AuthDetails<Role>,
-        _user: web::ReqData<UserMeta>,
-    ) -> impl Responder {
-        let action = req.action.clone();
-        let channel_id = *id;
-        let channel_name = match get_channel_name(channel_id, controllers.clone()).await {
-            Ok(name) => name,
-            Err(_) => return HttpResponse::InternalServerError().json("Erro ao acessar o canal"),
-        };
+    let _ = companion::stop(id, &name);
+    handles::delete_companion(&pool, id, companion_id).await?;
 
-        match action {
-            ServiceAction::Start => {
-                let mut processes = YTBOT_PROCESSES.lock().await;
-                if processes.contains_key(&channel_id) {
-                    info!("O ytbot já está em execução para o canal {}", channel_name);
-                    return HttpResponse::BadRequest().json(format!(
-                        "O ytbot já está em execução para o canal {}",
-                        channel_name
-                    ));
-                }
+    Ok("Delete companion success")
+}
 
-                let ytbot_path = match get_ytbot_path().await {
-                    Some(path) => path,
-                    None => {
-                        warn!("Nenhum executável do ytbot encontrado");
-                        return HttpResponse::InternalServerError()
-                            .json("Executável do ytbot não encontrado");
-                    }
-                };
+#[derive(Debug, Default, Deserialize)]
+pub struct CompanionStart {
+    /// Own variables for the arguments, like `{url}`.
+    #[serde(default)]
+    vars: HashMap<String, String>,
+}
 
-                // Extraímos o rtmp_details via função Rust já existente
-                let rtmp_details = match extract_rtmp_stream_details(channel_id, controllers.clone()).await {
-                    Ok(details) => details,
-                    Err(e) => {
-                        error!("Erro ao extrair detalhes RTMP: {}", e);
-                        return HttpResponse::InternalServerError().json(format!(
-                            "Erro ao extrair detalhes RTMP para o canal {}",
-                            channel_name
-                        ));
-                    }
-                };
+/// **Start Companion**
+///
+/// `ytbot` and `livestream` are built in, when the channel has no companion with this name.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/companions/1/livestream/start -H 'Content-Type: application/json' \
+/// -d '{ "vars": { "url": "https://www.youtube.com/watch?v=<ID>" } }' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/companions/{id}/{name}/start")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ProcessControl)"
+)]
+async fn start_companion(
+    pool: web::Data<Pool<Sqlite>>,
+    path: web::Path<(i32, String)>,
+    data: Option<web::Json<CompanionStart>>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, name) = path.into_inner();
+    let manager = controllers
+        .lock()
+        .unwrap()
+        .get(id)
+        .ok_or_else(|| ServiceError::BadRequest(format!("Channel ({id}) not exists!")))?;
+    let item = companion::find(&pool, id, &name).await?;
+    let vars = data.map(|d| d.into_inner().vars).unwrap_or_default();
 
-                // Montamos os argumentos para o ytbot com os parâmetros solicitados
-                let args = vec![
-                    format!("--monitor_channel={}", channel_id),
-                    format!("--channel_name={}", channel_name),
-                    format!("--rtmp_details={}", rtmp_details),
-                ];
-
-                let child = match Command::new(&ytbot_path)
-                    .args(&args)
-                    .stdout(Stdio::piped())
-                    .stderr(Stdio::piped())
-                    .spawn()
-                {
-                    Ok(proc) => proc,
-                    Err(e) => {
-                        error!(
-                            "Erro ao iniciar o ytbot para o canal {}: {}",
-                            channel_name, e
-                        );
-                        return HttpResponse::InternalServerError().json(format!(
-                            "Erro ao iniciar o ytbot para o canal {}",
-                            channel_name
-                        ));
-                    }
-                };
+    companion::start(&manager, item, vars)?;
 
-                let child = Arc::new(AsyncMutex::new(child));
-
-                let stdout = {
-                    let mut process_lock = child.lock().await;
-                    match process_lock.stdout.take() {
-                        Some(stdout) => stdout,
-                        None => {
-                            error!(
-                                "Falha ao obter o stdout do ytbot para o canal {}",
-                                channel_name
-                            );
-                            let _ = process_lock.kill().await;
-                            return HttpResponse::InternalServerError().json(format!(
-                                "Falha ao iniciar o ytbot para o canal {}",
-                                channel_name
-                            ));
-                        }
-                    }
-                };
+    Ok("Start companion success")
+}
 
-                let stderr = {
-                    let mut process_lock = child.lock().await;
-                    match process_lock.stderr.take() {
-                        Some(stderr) => stderr,
-                        None => {
-                            error!(
-                                "Falha ao obter o stderr do ytbot para o canal {}",
-                                channel_name
-                            );
-                            let _ = process_lock.kill().await;
-                            return HttpResponse::InternalServerError().json(format!(
-                                "Falha ao iniciar o ytbot para o canal {}",
-                                channel_name
-                            ));
-                        }
-                    }
-                };
+/// **Stop Companion**
+///
+/// The process group gets SIGTERM and is killed after 5 seconds.
+///
+/// ```BASH
+/// curl -X POST http://127.0.0.1:8787/api/companions/1/relay/stop -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[post("/companions/{id}/{name}/stop")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ProcessControl)"
+)]
+async fn stop_companion(
+    path: web::Path<(i32, String)>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, name) = path.into_inner();
 
-                tokio::spawn(async move {
-                    let reader = BufReader::new(stdout);
-                    let mut lines = reader.lines();
-                    while let Ok(Some(line)) = lines.next_line().await {
-                        debug!("ytbot stdout: {}", line);
-                    }
-                });
-
-                tokio::spawn(async move {
-                    let reader = BufReader::new(stderr);
-                    let mut lines = reader.lines();
-                    while let Ok(Some(line)) = lines.next_line().await {
-                        debug!("ytbot stderr: {}", line);
-                    }
-                });
-
-                processes.insert(channel_id, child);
-                info!(
-                    "Processo do ytbot iniciado com sucesso para canal {}",
-                    channel_name
-                );
-                HttpResponse::Ok().json(format!(
-                    "ytbot iniciado com sucesso para o canal {}",
-                    channel_name
-                ))
-            }
-            ServiceAction::Stop => {
-                let mut processes = YTBOT_PROCESSES.lock().await;
-                if let Some(child) = processes.remove(&channel_id) {
-                    async fn kill_and_wait_with_timeout(child: Arc<AsyncMutex<Child>>) -> Result<(), String> {
-                        let mut child = child.lock().await;
-                        child.kill().await.map_err(|e| e.to_string())?;
-                        match timeout(Duration::from_secs(5), child.wait()).await {
-                            Ok(Ok(_)) => Ok(()),
-                            Ok(Err(e)) => Err(e.to_string()),
-                            Err(_) => Err("Timeout ao encerrar o processo".to_string()),
-                        }
-                    }
-
-                    match kill_and_wait_with_timeout(child).await {
-                        Ok(()) => {
-                            info!(
-                                "Processo do ytbot interrompido com sucesso para canal {}",
-                                channel_name
-                            );
-                            HttpResponse::Ok().json(format!(
-                                "ytbot interrompido com sucesso para o canal {}",
-                                channel_name
-                            ))
-                        }
-                        Err(e) => {
-                            error!(
-                                "Erro ao interromper o ytbot para canal {}: {}",
-                                channel_name, e
-                            );
-                            HttpResponse::InternalServerError().json(format!(
-                                "Erro ao interromper o ytbot para o canal {}",
-                                channel_name
-                            ))
-                        }
-                    }
-                } else {
-                    info!(
-                        "Nenhum processo do ytbot em execução para o canal {}",
-                        channel_name
-                    );
-                    HttpResponse::BadRequest().json(format!(
-                        "Nenhum processo do ytbot em execução para o canal {}",
-                        channel_name
-                    ))
-                }
-            }
-        }
-    }
+    companion::stop(id, &name)?;
 
-    async fn get_channel_name(
-        channel_id: i32,
-        controllers: web::Data<Mutex<ChannelController>>
-    ) -> Result<String, String> {
-        let controller = match controllers.lock() {
-            Ok(ctrl) => ctrl,
-            Err(_) => return Err("Erro interno ao obter o controller".to_string()),
-        };
+    Ok("Stop companion success")
+}
 
-        let manager = match controller.get(channel_id) {
-            Some(mgr) => mgr,
-            None => return Err(format!("Canal ({}) não existe!", channel_id)),
-        };
+/// **Companion Status**
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/companions/1/relay/status -H 'Authorization: Bearer <TOKEN>'
+/// ```
+///
+/// **Response:**
+///
+/// ```JSON
+/// {
+///     "name": "relay", "running": true, "pid": 4711, "started": "2024-05-01 10:00:00", "restarts": 0,
+///     "exit_code": null, "restarting": false, "gave_up": false, "bitrate": 4520.3,
+///     "last_data": "2024-05-01 10:12:40"
+/// }
+/// ```
+#[get("/companions/{id}/{name}/status")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)"
+)]
+async fn companion_status(
+    path: web::Path<(i32, String)>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, name) = path.into_inner();
 
-        let channel_name = match manager.channel.lock() {
-            Ok(ch) => ch.name.clone(),
-            Err(_) => return Err("Erro ao acessar o canal".to_string()),
-        };
+    Ok(web::Json(companion::status(id, &name)))
+}
 
-        Ok(channel_name)
-    }
+/// **Companion Log**
+///
+/// Last lines from stdout and stderr.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/companions/1/relay/log -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/companions/{id}/{name}/log")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&path.0) || role.has_authority(&Role::GlobalAdmin)"
+)]
+async fn companion_log(
+    path: web::Path<(i32, String)>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let (id, name) = path.into_inner();
 
-    // Expondo as rotas para uso externo
-    pub fn ytbot_routes() -> Scope {
-        web::scope("/ytbot")
-            .service(ytbot_service_status)
-            .service(ytbot_control)
-    }
+    Ok(web::Json(companion::logs(id, &name)))
 }
 
-// Módulo livestream
-pub mod livestream {
-    use super::*;
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceAction {
+    Start,
+    Stop,
+}
 
-    #[derive(Error, Debug)]
-    enum LivestreamError {
-        #[error("Erro ao verificar o status do ffmpeg: {0}")]
-        StatusError(String),
-    }
-    
-    // Aqui definimos um mapa global de canal_id -> (streamlink_process, ffmpeg_process)
-    static STREAM_PROCESSES: Lazy<AsyncMutex<HashMap<i32, (Arc<AsyncMutex<Child>>, Arc<AsyncMutex<Child>>)>>>
-        = Lazy::new(|| AsyncMutex::new(HashMap::new()));
-    
-    async fn get_ffmpeg_path() -> Option<String> {
-        if let Ok(path) = env::var("FFMPEG_PATH") {
-            if metadata(&path).await.is_ok() {
-                return Some(path);
-            }
-        }
-    
-        let paths = ["/usr/bin/ffmpeg", "/usr/local/bin/ffmpeg"];
-    
-        for path in &paths {
-            if metadata(path).await.is_ok() {
-                return Some(path.to_string());
-            }
-        }
-        None
-    }
-    
-    /// Verifica se o processo `ffmpeg` do livestream está ativo para um determinado canal.
-    async fn is_ffmpeg_livestream_active(channel_id: i32) -> Result<bool, LivestreamError> {
-        let mut processes = STREAM_PROCESSES.lock().await;
-    
-        // Removemos do mapa primeiro
-        if let Some((streamlink_process, ffmpeg_process)) = processes.remove(&channel_id) {
-            let mut ffmpeg_child = ffmpeg_process.lock().await;
-    
-            match ffmpeg_child.try_wait() {
-                Ok(Some(_status)) => {
-                    // O processo terminou, não reinserimos no mapa
-                    Ok(false)
-                }
-                Ok(None) => {
-                    // O processo ainda está ativo
-                    // Precisamos reinserir o par no mapa
-                    drop(ffmpeg_child); // Solta o guard antes de reinserir
-    
-                    // Reinserir o mesmo tuple (streamlink_process, ffmpeg_process)
-                    processes.insert(channel_id, (streamlink_process, ffmpeg_process));
-    
-                    Ok(true)
-                }
-                Err(e) => Err(LivestreamError::StatusError(e.to_string())),
-            }
-        } else {
-            Ok(false) // Nenhum processo registrado para esse canal
-        }
-    }
-    
-
-    #[derive(Debug, Serialize, Deserialize)]
-    #[serde(rename_all = "snake_case")]
-    pub enum ServiceStatus {
-        Active,
-        Inactive,
-    }
-
-    #[derive(Debug, Serialize, Deserialize)]
-    pub struct ServiceStatusResponse {
-        pub status: ServiceStatus,
-    }
-
-    #[get("/ffmpeg/status/{id}")]
-    #[protect(
-        any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
-        ty = "Role"
-    )]
-    pub async fn livestream_ffmpeg_status(
-        id: web::Path<i32>,
-        _role: AuthDetails<Role>,
-        _user: web::ReqData<UserMeta>,
-        controllers: web::Data<Mutex<ChannelController>>, // Adicionado como parâmetro
-    ) -> impl Responder {
-        let channel_id = *id;
-        let channel_name = match get_channel_name(channel_id, controllers.clone()).await {
-            Ok(name) => name,
-            Err(_) => return HttpResponse::InternalServerError().json("Erro ao acessar o canal"),
-        };
+#[derive(Debug, Deserialize)]
+pub struct ServiceControlParams {
+    pub action: ServiceAction,
+    #[serde(default)]
+    pub url: Option<String>,
+}
 
-        match is_ffmpeg_livestream_active(channel_id).await {
-            Ok(active) => {
-                let status = if active {
-                    ServiceStatus::Active
-                } else {
-                    ServiceStatus::Inactive
-                };
-                let response = ServiceStatusResponse {
-                    status,
-                };
-                HttpResponse::Ok().json(response)
-            }
-            Err(e) => {
-                error!("Erro ao verificar o status do ffmpeg para o canal {}: {}", channel_name, e);
-                HttpResponse::InternalServerError().json(format!("Erro ao verificar o status do ffmpeg para o canal {}", channel_name))
-            }
-        }
-    }
+/// Status in the format of the old ytbot and livestream endpoints, with the relay health.
+fn service_status(id: i32, name: &str) -> HttpResponse {
+    let state = companion::status(id, name);
+    let status = if state.running || state.restarting {
+        "active"
+    } else {
+        "inactive"
+    };
 
-    async fn get_streamlink_path() -> Option<String> {
-        // Verifica se a variável de ambiente STREAMLINK_PATH está definida e se o caminho é válido
-        if let Ok(path) = env::var("STREAMLINK_PATH") {
-            if fs::metadata(&path).await.is_ok() {
-                return Some(path);
-            }
-        }
-    
-        // Tenta encontrar o streamlink no diretório de instalação padrão do usuário
-        if let Some(home_dir) = home_dir() {
-            let default_path = home_dir.join("livebot/venv/bin/streamlink");
-            if fs::metadata(&default_path).await.is_ok() {
-                return Some(default_path.to_string_lossy().to_string());
-            }
-        }
-    
-        None
-    }
-    
-    pub async fn extract_rtmp_stream_details(
-        id: i32,
-        controllers: web::Data<Mutex<ChannelController>>
-    ) -> Result<String, ServiceError> {
-        let controller = controllers.lock().map_err(|_| ServiceError::InternalServerError)?;
-    
-        let manager = controller
-            .get(id)
-            .ok_or(ServiceError::BadRequest(format!("Canal ({id}) não existe!")))?;
-    
-        let config = manager.config.lock().map_err(|_| ServiceError::InternalServerError)?;
-        let input_param = &config.ingest.input_param;
-    
-        let re = Regex::new(r":(\d{1,5})(\S*)").map_err(|_| ServiceError::InternalServerError)?;
-    
-        if let Some(caps) = re.captures(input_param) {
-            if let Some(port_str) = caps.get(1) {
-                let port_str = port_str.as_str();
-                if let Ok(port) = port_str.parse::<u16>() {
-                    let stream_key = caps.get(2).map(|m| m.as_str()).unwrap_or("");
-                    return Ok(format!(":{}{}", port, stream_key));
-                }
+    HttpResponse::Ok().json(serde_json::json!({
+        "status": status,
+        "restarts": state.restarts,
+        "gave_up": state.gave_up,
+        "bitrate": state.bitrate,
+        "last_data": state.last_data,
+    }))
+}
+
+async fn service_control(
+    pool: &Pool<Sqlite>,
+    controllers: &Mutex<ChannelController>,
+    id: i32,
+    name: &str,
+    params: ServiceControlParams,
+) -> Result<&'static str, ServiceError> {
+    match params.action {
+        ServiceAction::Start => {
+            let manager =
+                controllers.lock().unwrap().get(id).ok_or_else(|| {
+                    ServiceError::BadRequest(format!("Channel ({id}) not exists!"))
+                })?;
+            let item = companion::find(pool, id, name).await?;
+            let mut vars = HashMap::new();
+
+            if let Some(url) = params.url {
+                url::Url::parse(&url).map_err(|e| ServiceError::BadRequest(e.to_string()))?;
+                vars.insert("url".to_string(), url);
             }
+
+            companion::start(&manager, item, vars)?;
+
+            Ok("Start success")
         }
-    
-        Err(ServiceError::BadRequest("Nenhuma porta válida encontrada".to_string()))
-    }
-    
-    #[derive(Debug, Deserialize, Serialize, Clone)]
-    #[serde(rename_all = "snake_case")]
-    pub enum StreamAction {
-        Start,
-        Stop,
-    }
-
-    #[derive(Debug, Deserialize)]
-    pub struct StreamParams {
-        pub action: StreamAction,
-        pub url: Option<String>,
-    }
-    
-    #[post("/control/{id}")]
-    #[protect(
-        any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
-        ty = "Role"
-    )]
-    pub async fn livestream_control(
-        id: web::Path<i32>,
-        req: web::Json<StreamParams>,
-        controllers: web::Data<Mutex<ChannelController>>, // Adicionado como parâmetro
-        _role: AuthDetails<Role>,
-        _user: web::ReqData<UserMeta>,
-    ) -> impl Responder {
-        let action = req.action.clone();
-        let channel_id = *id;
-        let channel_name = match get_channel_name(channel_id, controllers.clone()).await {
-            Ok(name) => name,
-            Err(_) => return HttpResponse::InternalServerError().json("Erro ao acessar o canal"),
-        };
-    
-        match action {
-            StreamAction::Start => {
-                let mut processes = STREAM_PROCESSES.lock().await;
-                if processes.contains_key(&channel_id) {
-                    info!("Stream já está em execução para o canal {}", channel_name);
-                    return HttpResponse::BadRequest().json(format!("Stream já está em execução para o canal {}", channel_name));
-                }
-    
-                let url = match &req.url {
-                    Some(u) => u,
-                    None => {
-                        info!("URL não fornecida");
-                        return HttpResponse::BadRequest().json("URL não fornecida");
-                    }
-                };
-    
-                if let Ok(parsed_url) = Url::parse(url) {
-                    // Verifica o caminho do executável do streamlink
-                    let streamlink_path = match get_streamlink_path().await {
-                        Some(path) => path,
-                        None => {
-                            error!("Executável do streamlink não encontrado");
-                            return HttpResponse::InternalServerError()
-                                .json("Executável do streamlink não encontrado");
-                        }
-                    };
-    
-                    let ffmpeg_path = match get_ffmpeg_path().await {
-                        Some(path) => path,
-                        None => {
-                            error!("Executável do ffmpeg não encontrado");
-                            return HttpResponse::InternalServerError()
-                                .json("Executável do ffmpeg não encontrado");
-                        }
-                    };
-    
-                    // Define os argumentos do streamlink
-                    let streamlink_args = vec![
-                        "--hls-live-edge",
-                        "6",
-                        "--ringbuffer-size",
-                        "128M",
-                        "-4",
-                        "--stream-sorting-excludes",
-                        ">720p",
-                        "--default-stream",
-                        "best",
-                        "--url",
-                        parsed_url.as_str(),
-                        "-o",
-                        "-",
-                    ];
-    
-                    // Inicia o processo do streamlink
-                    let streamlink_process = match Command::new(&streamlink_path)
-                        .args(&streamlink_args)
-                        .stdout(Stdio::piped())
-                        .stderr(Stdio::piped())
-                        .stdin(Stdio::null())
-                        .spawn()
-                    {
-                        Ok(process) => process,
-                        Err(e) => {
-                            error!("Erro ao iniciar o streamlink: {}", e);
-                            return HttpResponse::InternalServerError()
-                                .json("Erro ao iniciar o streaming");
-                        }
-                    };
-    
-                    let streamlink_process = Arc::new(AsyncMutex::new(streamlink_process));
-    
-                    let mut streamlink_stdout = {
-                        let mut process_lock = streamlink_process.lock().await;
-                        match process_lock.stdout.take() {
-                            Some(stdout) => stdout,
-                            None => {
-                                error!("Falha ao obter o stdout do streamlink");
-                                let _ = process_lock.kill().await;
-                                return HttpResponse::InternalServerError()
-                                    .json("Erro ao iniciar o streaming");
-                            }
-                        }
-                    };
-    
-                    let streamlink_stderr = {
-                        let mut process_lock = streamlink_process.lock().await;
-                        match process_lock.stderr.take() {
-                            Some(stderr) => stderr,
-                            None => {
-                                error!("Falha ao obter o stderr do streamlink");
-                                let _ = process_lock.kill().await;
-                                return HttpResponse::InternalServerError()
-                                    .json("Erro ao iniciar o streaming");
-                            }
-                        }
-                    };
-    
-                    let rtmp_details = match extract_rtmp_stream_details(channel_id, controllers.clone()).await {
-                        Ok(details) => details,
-                        Err(e) => {
-                            error!("Erro ao extrair detalhes RTMP: {}", e);
-                            let mut process_lock = streamlink_process.lock().await;
-                            let _ = process_lock.kill().await;
-                            return HttpResponse::InternalServerError().json("Erro ao extrair detalhes RTMP");
-                        }
-                    };
-    
-                    let ffmpeg_url = format!("rtmp://127.0.0.1{}", rtmp_details);
-    
-                    let ffmpeg_args = [
-                        "-re",
-                        "-hide_banner",
-                        "-nostats",
-                        "-v",
-                        "level+error",
-                        "-i",
-                        "pipe:0",
-                        "-vcodec",
-                        "copy",
-                        "-acodec",
-                        "copy",
-                        "-f",
-                        "flv",
-                        &ffmpeg_url,
-                    ];
-    
-                    let ffmpeg_process = match Command::new(&ffmpeg_path)
-                        .args(&ffmpeg_args)
-                        .stdin(Stdio::piped())
-                        .stdout(Stdio::piped())
-                        .stderr(Stdio::piped())
-                        .spawn()
-                    {
-                        Ok(process) => process,
-                        Err(e) => {
-                            error!("Erro ao iniciar o ffmpeg: {}", e);
-                            let mut process_lock = streamlink_process.lock().await;
-                            let _ = process_lock.kill().await;
-                            return HttpResponse::InternalServerError()
-                                .json("Erro ao iniciar o streaming");
-                        }
-                    };
-    
-                    let ffmpeg_process = Arc::new(AsyncMutex::new(ffmpeg_process));
-    
-                    let mut ffmpeg_stdin = {
-                        let mut process_lock = ffmpeg_process.lock().await;
-                        match process_lock.stdin.take() {
-                            Some(stdin) => stdin,
-                            None => {
-                                error!("Falha ao obter o stdin do ffmpeg");
-                                let mut streamlink_process_lock = streamlink_process.lock().await;
-                                let _ = streamlink_process_lock.kill().await;
-                                let _ = process_lock.kill().await;
-                                return HttpResponse::InternalServerError()
-                                    .json("Erro ao iniciar o streaming");
-                            }
-                        }
-                    };
-    
-                    let ffmpeg_stdout = {
-                        let mut process_lock = ffmpeg_process.lock().await;
-                        match process_lock.stdout.take() {
-                            Some(stdout) => stdout,
-                            None => {
-                                error!("Falha ao obter o stdout do ffmpeg");
-                                let mut streamlink_process_lock = streamlink_process.lock().await;
-                                let _ = streamlink_process_lock.kill().await;
-                                let _ = process_lock.kill().await;
-                                return HttpResponse::InternalServerError()
-                                    .json("Erro ao iniciar o streaming");
-                            }
-                        }
-                    };
-    
-                    let ffmpeg_stderr = {
-                        let mut process_lock = ffmpeg_process.lock().await;
-                        match process_lock.stderr.take() {
-                            Some(stderr) => stderr,
-                            None => {
-                                error!("Falha ao obter o stderr do ffmpeg");
-                                let mut streamlink_process_lock = streamlink_process.lock().await;
-                                let _ = streamlink_process_lock.kill().await;
-                                let _ = process_lock.kill().await;
-                                return HttpResponse::InternalServerError()
-                                    .json("Erro ao iniciar o streaming");
-                            }
-                        }
-                    };
-    
-                    let streamlink_process_clone = Arc::clone(&streamlink_process);
-                    let ffmpeg_process_clone = Arc::clone(&ffmpeg_process);
-    
-                    let copy_task = tokio::spawn(async move {
-                        if let Err(e) = tokio::io::copy(&mut streamlink_stdout, &mut ffmpeg_stdin).await {
-                            error!("Erro ao copiar dados do streamlink para o ffmpeg: {}", e);
-                            HttpResponse::InternalServerError().json("Erro ao copiar dados do streamlink para o ffmpeg");
-                            let mut streamlink_process = streamlink_process_clone.lock().await;
-                            let mut ffmpeg_process = ffmpeg_process_clone.lock().await;
-                            let _ = streamlink_process.kill().await;
-                            let _ = ffmpeg_process.kill().await;
-                        }
-                    });
-    
-                    tokio::spawn(async move {
-                        if let Err(e) = copy_task.await {
-                            error!("Erro na tarefa de cópia: {}", e);
-                        }
-                    });
-    
-                    tokio::spawn(async move {
-                        let reader = BufReader::new(streamlink_stderr);
-                        let mut lines = reader.lines();
-    
-                        while let Ok(Some(line)) = lines.next_line().await {
-                            debug!("streamlink: {}", line);
-                        }
-                    });
-    
-                    tokio::spawn(async move {
-                        let reader = BufReader::new(ffmpeg_stdout);
-                        let mut lines = reader.lines();
-    
-                        while let Ok(Some(line)) = lines.next_line().await {
-                            debug!("ffmpeg stdout: {}", line);
-                        }
-                    });
-    
-                    tokio::spawn(async move {
-                        let reader = BufReader::new(ffmpeg_stderr);
-                        let mut lines = reader.lines();
-    
-                        while let Ok(Some(line)) = lines.next_line().await {
-                            debug!("ffmpeg stderr: {}", line);
-                        }
-                    });
-    
-                    // Armazena ambos os processos no mapa
-                    processes.insert(channel_id, (streamlink_process, ffmpeg_process));
-                    drop(processes);
-    
-                    info!("Stream iniciado para canal {}", channel_name);
-                    HttpResponse::Ok().json(format!("Stream iniciado para canal {}", channel_name))
-                } else {
-                    info!("URL inválida");
-                    HttpResponse::BadRequest().json("URL inválida")
-                }
-            }
-            StreamAction::Stop => {
-                let mut processes = STREAM_PROCESSES.lock().await;
-                if let Some((streamlink_child, ffmpeg_child)) = processes.remove(&channel_id) {
-                    async fn kill_and_wait_with_timeout(child: Arc<AsyncMutex<Child>>) -> Result<(), String> {
-                        let mut child = child.lock().await;
-                        child.kill().await.map_err(|e| e.to_string())?;
-                        match timeout(Duration::from_secs(5), child.wait()).await {
-                            Ok(Ok(_)) => Ok(()),
-                            Ok(Err(e)) => Err(e.to_string()),
-                            Err(_) => Err("Timeout ao encerrar o processo".to_string()),
-                        }
-                    }
-    
-                    let streamlink_result = kill_and_wait_with_timeout(streamlink_child).await;
-                    let ffmpeg_result = kill_and_wait_with_timeout(ffmpeg_child).await;
-    
-                    match (streamlink_result, ffmpeg_result) {
-                        (Ok(()), Ok(())) => {
-                            info!("Stream Encerrado para o canal {}", channel_name);
-                            HttpResponse::Ok().json(format!("Stream Encerrado para o canal {}", channel_name))
-                        }
-                        (Err(e1), Err(e2)) => {
-                            error!(
-                                "Erro ao parar streaming do canal {}: streamlink: {}, ffmpeg: {}",
-                                channel_name, e1, e2
-                            );
-                            HttpResponse::InternalServerError().json(format!("Erro ao parar streaming do canal {}",
-                                channel_name))
-                        }
-                        (Err(e), _) | (_, Err(e)) => {
-                            error!("Erro ao parar um dos processos do streaming do canal {}: {}", channel_name, e);
-                            HttpResponse::InternalServerError().json(format!("Erro ao parar um dos processos do streaming do canal {}", channel_name))
-                        }
-                    }
-                } else {
-                    info!("Nenhum stream está em execução para o canal {}", channel_name);
-                    HttpResponse::BadRequest().json(format!("Nenhum stream está em execução para o canal {}", channel_name))
-                }
-            }
+        ServiceAction::Stop => {
+            companion::stop(id, name)?;
+
+            Ok("Stop success")
         }
     }
+}
 
-    async fn get_channel_name(channel_id: i32, controllers: web::Data<Mutex<ChannelController>>) -> Result<String, String> {
-        let controller = match controllers.lock() {
-            Ok(ctrl) => ctrl,
-            Err(_) => return Err("Erro interno ao obter o controller".to_string()),
-        };
+/// **YouTube Bot**
+///
+/// Shortcuts for the companion `ytbot`.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/ytbot/status/1 -H 'Authorization: Bearer <TOKEN>'
+/// curl -X POST http://127.0.0.1:8787/api/ytbot/control/1 -H 'Content-Type: application/json' \
+/// -d '{ "action": "start" }' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/ytbot/status/{id}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+async fn ytbot_status(
+    id: web::Path<i32>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> impl Responder {
+    service_status(*id, "ytbot")
+}
 
-        let manager = match controller.get(channel_id) {
-            Some(mgr) => mgr,
-            None => return Err(format!("Canal ({}) não existe!", channel_id)),
-        };
+#[post("/ytbot/control/{id}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ProcessControl)"
+)]
+async fn ytbot_control(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    data: web::Json<ServiceControlParams>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    service_control(&pool, &controllers, *id, "ytbot", data.into_inner()).await
+}
 
-        let channel_name = match manager.channel.lock() {
-            Ok(ch) => ch.name.clone(),
-            Err(_) => return Err("Erro ao acessar o canal".to_string()),
-        };
+/// **Live Stream**
+///
+/// Shortcuts for the companion `livestream`, which pulls the url with streamlink into the ingest.
+///
+/// ```BASH
+/// curl -X GET http://127.0.0.1:8787/api/livestream/ffmpeg/status/1 -H 'Authorization: Bearer <TOKEN>'
+/// curl -X POST http://127.0.0.1:8787/api/livestream/control/1 -H 'Content-Type: application/json' \
+/// -d '{ "action": "start", "url": "https://www.youtube.com/watch?v=<ID>" }' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/livestream/ffmpeg/status/{id}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+async fn livestream_status(
+    id: web::Path<i32>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> impl Responder {
+    service_status(*id, "livestream")
+}
 
-        Ok(channel_name)
-    }
+#[post("/livestream/control/{id}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "(user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)) && user.can(Permission::ProcessControl)"
+)]
+async fn livestream_control(
+    pool: web::Data<Pool<Sqlite>>,
+    id: web::Path<i32>,
+    data: web::Json<ServiceControlParams>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let params = data.into_inner();
 
-    // Expondo as rotas para uso externo
-    pub fn livestream_routes() -> Scope {
-        web::scope("/livestream")
-            .service(livestream_ffmpeg_status)
-            .service(livestream_control)
+    if matches!(params.action, ServiceAction::Start) && params.url.is_none() {
+        return Err(ServiceError::BadRequest("URL is needed".into()));
     }
-}
 
-// Reexporte as funções para facilitar o acesso
-pub use livestream::livestream_routes;
-pub use ytbot::ytbot_routes;
+    service_control(&pool, &controllers, *id, "livestream", params).await
+}
@@ -0,0 +1,167 @@
+use std::{
+    str::FromStr,
+    sync::{atomic::Ordering, Mutex},
+    time::Duration,
+};
+
+use actix_web::{get, rt, web, Error, HttpRequest, HttpResponse};
+use actix_ws::Message;
+use futures_util::StreamExt;
+use log::Level;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::player::{
+    controller::ChannelController,
+    utils::{audio_meter::audio_levels, get_data_map, stream_stats::stream_stats},
+};
+use crate::sse::{check_uuid, SseAuthState};
+use crate::utils::{errors::ServiceError, logging::subscribe_lines, maintenance::in_maintenance};
+
+#[derive(Debug, Deserialize)]
+struct StatusParams {
+    uuid: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LogParams {
+    uuid: String,
+    #[serde(default)]
+    level: Option<String>,
+}
+
+/// **Live Log**
+///
+/// Stream new log lines from a channel over a WebSocket. `level` is the lowest level
+/// (`error`, `warn`, `info`, `debug` or `trace`), which is sent, default is `info`.
+/// The UUID comes from `/api/generate-uuid`.
+///
+/// ```BASH
+/// websocat 'ws://127.0.0.1:8787/ws/log/1?level=warn&uuid=f2f8c29b-712a-48c5-8919-b535d3a05a3a'
+/// ```
+#[get("/log/{id}")]
+async fn log_socket(
+    req: HttpRequest,
+    body: web::Payload,
+    data: web::Data<SseAuthState>,
+    id: web::Path<i32>,
+    params: web::Query<LogParams>,
+) -> Result<HttpResponse, Error> {
+    check_uuid(&mut *data.uuids.lock().await, &params.uuid)?;
+
+    let channel = *id;
+    let level = match params.level.as_deref() {
+        Some(level) => Level::from_str(level)
+            .map_err(|_| ServiceError::BadRequest(format!("Unknown log level: {level}")))?,
+        None => Level::Info,
+    };
+
+    let (response, mut session, mut messages) = actix_ws::handle(&req, body)?;
+    let mut lines = subscribe_lines();
+
+    rt::spawn(async move {
+        loop {
+            tokio::select! {
+                line = lines.recv() => match line {
+                    Ok(line) => {
+                        if line.channel == channel
+                            && line.level <= level
+                            && session.text(line.line).await.is_err()
+                        {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(count)) => {
+                        if session.text(format!("... {count} lines skipped")).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                msg = messages.next() => match msg {
+                    Some(Ok(Message::Ping(bytes))) if session.pong(&bytes).await.is_err() => break,
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                    _ => {}
+                },
+            }
+        }
+
+        let _ = session.close(None).await;
+    });
+
+    Ok(response)
+}
+
+/// **Status Feed**
+///
+/// Send the status of a channel every second over a WebSocket: if it is running and in maintenance,
+/// the current clip, the stats from the ingest and the outputs, like on `/api/control/{id}/streams/`,
+/// and the audio levels. With the audio meter enabled, the levels are sent also in between,
+/// five times per second, as `{"audio": {...}}`. Levels are `null`, when no audio is measured.
+/// The UUID comes from `/api/generate-uuid`.
+///
+/// ```BASH
+/// websocat 'ws://127.0.0.1:8787/ws/status/1?uuid=f2f8c29b-712a-48c5-8919-b535d3a05a3a'
+/// ```
+#[get("/status/{id}")]
+async fn status_socket(
+    req: HttpRequest,
+    body: web::Payload,
+    data: web::Data<SseAuthState>,
+    id: web::Path<i32>,
+    params: web::Query<StatusParams>,
+    controllers: web::Data<Mutex<ChannelController>>,
+) -> Result<HttpResponse, Error> {
+    check_uuid(&mut *data.uuids.lock().await, &params.uuid)?;
+
+    let manager = controllers
+        .lock()
+        .unwrap()
+        .get(*id)
+        .ok_or_else(|| ServiceError::BadRequest(format!("Channel ({id}) not exists!")))?;
+
+    let (response, mut session, mut messages) = actix_ws::handle(&req, body)?;
+    let mut interval = rt::time::interval(Duration::from_secs(1));
+    let mut meter = rt::time::interval(Duration::from_millis(200));
+    let channel_id = *id;
+
+    rt::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    let running = manager.is_alive.load(Ordering::SeqCst);
+                    let status = json!({
+                        "running": running,
+                        "maintenance": in_maintenance(channel_id),
+                        "media": running.then(|| get_data_map(&manager)),
+                        "streams": stream_stats(&manager),
+                        "audio": audio_levels(&manager),
+                    });
+
+                    if session.text(status.to_string()).await.is_err() {
+                        break;
+                    }
+                }
+                _ = meter.tick() => {
+                    if let Some(levels) = audio_levels(&manager) {
+                        let status = json!({ "audio": levels });
+
+                        if session.text(status.to_string()).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+                msg = messages.next() => match msg {
+                    Some(Ok(Message::Ping(bytes))) if session.pong(&bytes).await.is_err() => break,
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                    _ => {}
+                },
+            }
+        }
+
+        let _ = session.close(None).await;
+    });
+
+    Ok(response)
+}
@@ -7,8 +7,12 @@ use std::{
     thread,
 };
 
-use actix_web::{middleware::Logger, web, App, HttpServer};
+use actix_web::{
+    middleware::{from_fn, Logger},
+    web, App, HttpServer,
+};
 use actix_web_httpauth::middleware::HttpAuthentication;
+use clap::Parser;
 
 #[cfg(any(debug_assertions, not(feature = "embed_frontend")))]
 use actix_files::Files;
@@ -19,21 +23,32 @@ use actix_web_static_files::ResourceFiles;
 use log::*;
 
 use ffplayout::{
-    api::routes::*,
+    api::{audit::audit_log, graphql_routes, proxy::cluster_proxy, routes::*, ws::*},
     db::{db_drop, db_pool, handles, init_globales},
     player::{
         controller::{ChannelController, ChannelManager},
+        output::regions::load_regions,
         utils::{get_date, is_remote, json_validate::validate_playlist, JsonPlaylist},
     },
     sse::{broadcast::Broadcaster, routes::*, SseAuthState},
     utils::{
-        args_parse::run_args,
+        ad_breaks::load_ad_breaks,
+        args_parse::{run_args, Args},
+        clock_reference::{start_reference_clock, ClockReference},
+        cluster::{channel_node, cluster_monitor, load_routes},
+        companion,
         config::get_config,
+        failover::init_failover,
+        listener::{activated_sockets, remove_stale_socket, ActivatedSocket, Listen},
         logging::{init_logging, MailQueue},
         playlist::generate_playlist,
-        time_machine::set_mock_time,
+        scripting::load_scripts,
+        shutdown::{set_server, shutdown_channels, shutdown_guard, signal_listener},
+        startup::{load_channels, start_channels},
+        time_machine::set_simulation,
+        tls::{init_tls, server_config, tls_worker, TlsMode},
     },
-    validator, ARGS,
+    set_args, validator, ARGS,
 };
 
 #[cfg(any(debug_assertions, not(feature = "embed_frontend")))]
@@ -52,6 +67,8 @@ fn thread_counter() -> usize {
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    set_args(Args::parse()).map_err(|_| io::Error::other("Arguments are already set"))?;
+
     let mail_queues = Arc::new(Mutex::new(vec![]));
 
     let pool = db_pool().await.map_err(io::Error::other)?;
@@ -60,65 +77,66 @@ async fn main() -> std::io::Result<()> {
         exit(c);
     }
 
-    set_mock_time(&ARGS.fake_time);
+    set_simulation(&ARGS.fake_time, ARGS.time_speed);
 
     init_globales(&pool)
         .await
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        .map_err(|e| io::Error::other(e.to_string()))?;
     init_logging(mail_queues.clone())?;
 
+    // fake and simulated time have priority over the reference clock
+    if let Some(reference) = ARGS
+        .clock_reference
+        .as_ref()
+        .filter(|_| ARGS.fake_time.is_none() && ARGS.time_speed.is_none())
+    {
+        match reference.parse::<ClockReference>() {
+            Ok(reference) => start_reference_clock(reference, !ARGS.clock_monitor_only),
+            Err(e) => {
+                eprintln!("Error: {e}");
+                exit(1);
+            }
+        }
+    }
+
     let channel_controllers = Arc::new(Mutex::new(ChannelController::new()));
 
     if let Some(conn) = &ARGS.listen {
-        let channels = handles::select_related_channels(&pool, None)
-            .await
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        let listen = Listen::parse(conn)?;
 
-        for channel in &channels {
-            let config = get_config(&pool, channel.id)
-                .await
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
-            let manager = ChannelManager::new(Some(pool.clone()), channel.clone(), config.clone());
-            let m_queue = Arc::new(Mutex::new(MailQueue::new(channel.id, config.mail)));
+        init_failover(
+            ARGS.failover_role,
+            ARGS.failover_peer.clone(),
+            ARGS.failover_token.clone(),
+            ARGS.failover_timeout,
+        );
 
-            channel_controllers
-                .lock()
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?
-                .add(manager.clone());
+        load_routes(&pool)
+            .await
+            .map_err(|e| io::Error::other(e.to_string()))?;
 
-            if let Ok(mut mqs) = mail_queues.lock() {
-                mqs.push(m_queue.clone());
-            }
+        load_channels(&pool, &channel_controllers, &mail_queues)
+            .await
+            .map_err(|e| io::Error::other(e.to_string()))?;
 
-            if channel.active {
-                manager.async_start().await;
-            }
-        }
+        start_channels(&pool, &channel_controllers, |id| channel_node(id).is_some());
+        cluster_monitor(pool.clone(), channel_controllers.clone());
 
-        let ip_port = conn.split(':').collect::<Vec<&str>>();
-        let addr = ip_port[0];
-        let port = ip_port
-            .get(1)
-            .and_then(|p| p.parse::<u16>().ok())
-            .ok_or_else(|| {
-                io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    "<ADRESSE>:<PORT> needed! For example: 127.0.0.1:8787",
-                )
-            })?;
         let controllers = web::Data::from(channel_controllers.clone());
         let auth_state = web::Data::new(SseAuthState {
             uuids: tokio::sync::Mutex::new(HashSet::new()),
         });
         let broadcast_data = Broadcaster::create();
         let thread_count = thread_counter();
+        let tls_mode = TlsMode::from_args(&ARGS)?;
+        let scheme = if tls_mode.is_some() { "https" } else { "http" };
 
-        info!("Running ffplayout API, listen on http://{conn}");
+        info!("Running ffplayout API, listen on {scheme}://{listen}");
         
         let db_clone = pool.clone();
 
         // no 'allow origin' here, give it to the reverse proxy
-        HttpServer::new(move || {
+        let server = HttpServer::new(move || {
             let queues = mail_queues.clone();
 
             let auth = HttpAuthentication::bearer(validator);
@@ -135,22 +153,74 @@ async fn main() -> std::io::Result<()> {
                 .app_data(web::Data::from(Arc::clone(&broadcast_data)))
                 .wrap(logger)
                 .service(login)
+                .service(reset_request)
+                .service(reset_password)
+                .service(health_live)
+                .service(health_ready)
+                .service(ingest_auth)
+                .service(whip_publish)
+                .service(whip_update)
+                .service(whip_stop)
+                .service(failover_heartbeat)
+                .service(failover_config)
+                .service(get_nowplaying)
                 .service(
                     web::scope("/api")
+                        .wrap(from_fn(cluster_proxy))
+                        .wrap(from_fn(shutdown_guard))
+                        .wrap(from_fn(audit_log))
                         .wrap(auth)
                         .service(add_user)
                         .service(get_user)
                         .service(get_by_name)
                         .service(get_users)
                         .service(remove_user)
+                        .service(get_permissions)
+                        .service(update_permissions)
+                        .service(get_password_policy)
+                        .service(update_password_policy)
                         .service(get_advanced_config)
                         .service(update_advanced_config)
                         .service(get_playout_config)
                         .service(update_playout_config)
+                        .service(export_playout_config)
+                        .service(import_playout_config)
+                        .service(rotate_stream_keys)
+                        .service(get_airtime)
+                        .service(update_airtime)
                         .service(add_preset)
                         .service(get_presets)
                         .service(update_preset)
                         .service(delete_preset)
+                        .service(get_scripts)
+                        .service(add_script)
+                        .service(update_script)
+                        .service(delete_script)
+                        .service(get_companions)
+                        .service(add_companion)
+                        .service(update_companion)
+                        .service(delete_companion)
+                        .service(start_companion)
+                        .service(stop_companion)
+                        .service(companion_status)
+                        .service(companion_log)
+                        .service(get_ad_breaks)
+                        .service(add_ad_break)
+                        .service(update_ad_break)
+                        .service(delete_ad_break)
+                        .service(get_spot_plays)
+                        .service(get_regions)
+                        .service(add_region)
+                        .service(update_region)
+                        .service(delete_region)
+                        .service(get_region_playlist)
+                        .service(save_region_playlist)
+                        .service(get_output_targets)
+                        .service(test_output_target)
+                        .service(add_output_target)
+                        .service(update_output_target)
+                        .service(enable_output_target)
+                        .service(delete_output_target)
                         .service(get_channel)
                         .service(get_all_channels)
                         .service(patch_channel)
@@ -158,32 +228,97 @@ async fn main() -> std::io::Result<()> {
                         .service(remove_channel)
                         .service(update_user)
                         .service(send_text_message)
+                        .service(send_text_preset)
+                        .service(send_overlay)
+                        .service(get_overlay)
                         .service(control_playout)
+                        .service(control_source)
+                        .service(control_override)
+                        .service(control_scte35)
                         .service(media_current)
+                        .service(get_clock)
+                        .service(get_ffmpeg_events)
+                        .service(get_stream_stats)
                         .service(process_control)
                         .service(get_playlist)
                         .service(save_playlist)
+                        .service(edit_playlist_items)
                         .service(gen_playlist)
                         .service(del_playlist)
+                        .service(get_playlist_versions)
+                        .service(diff_playlist_versions)
+                        .service(restore_playlist_version)
+                        .service(simulate_playlist)
+                        .service(trim_playlist)
+                        .service(repair_playlist_length)
+                        .service(copy_playlist_to)
                         .service(get_log)
                         .service(file_browser)
                         .service(add_dir)
                         .service(move_rename)
                         .service(remove)
+                        .service(get_trash)
+                        .service(restore_trash_item)
+                        .service(purge_trash_items)
                         .service(save_file)
                         .service(import_playlist)
+                        .service(get_chapters)
+                        .service(analyze_trim)
+                        .service(get_trim_suggestions)
+                        .service(trim_file)
+                        .service(get_trim_preview)
+                        .service(get_thumbnail)
+                        .service(get_storyboard)
+                        .service(generate_thumbnails)
+                        .service(get_watch_status)
+                        .service(get_pull_status)
+                        .service(run_pull)
+                        .service(add_transcode_jobs)
+                        .service(get_transcode_jobs)
+                        .service(get_transcode_job)
+                        .service(cancel_transcode_job)
+                        .service(add_download_job)
+                        .service(get_download_jobs)
+                        .service(get_download_job)
+                        .service(cancel_download_job)
                         .service(get_program)
+                        .service(get_program_xmltv)
+                        .service(get_backup)
+                        .service(restore_system)
+                        .service(get_health_details)
                         .service(get_system_stat)
+                        .service(get_ffmpeg_caps)
+                        .service(shutdown_engine)
+                        .service(get_cache_stat)
+                        .service(get_failover_status)
+                        .service(get_nodes)
+                        .service(add_node)
+                        .service(update_node)
+                        .service(remove_node)
+                        .service(schedule_channel)
+                        .service(release_channel)
+                        .service(get_audit_log)
+                        .service(get_global_audit_log)
+                        .service(add_handover_note)
+                        .service(get_handover_notes)
                         .service(generate_uuid)
-                        .service(livestream_routes())
-                        .service(ytbot_routes()),
+                        .service(channel_events)
+                        .configure(graphql_routes)
+                        .service(ytbot_status)
+                        .service(ytbot_control)
+                        .service(livestream_status)
+                        .service(livestream_control),
                 )
                 .service(
                     web::scope("/data")
                         .service(validate_uuid)
                         .service(event_stream),
                 )
+                .service(web::scope("/ws").service(log_socket).service(status_socket))
                 .service(get_file)
+                .service(get_embed)
+                .service(get_embed_now)
+                .service(get_embed_logo)
                 .service(get_public);
 
             #[cfg(all(not(debug_assertions), feature = "embed_frontend"))]
@@ -201,11 +336,68 @@ async fn main() -> std::io::Result<()> {
             }
 
             web_app
-        })
-        .bind((addr, port))?
-        .workers(thread_count)
-        .run()
-        .await?;
+        });
+
+        let tls = match tls_mode {
+            Some(mode) => {
+                let resolver = init_tls(&mode)?;
+                let tls_config = server_config(resolver.clone())?;
+
+                tokio::spawn(tls_worker(mode, resolver));
+
+                Some(tls_config)
+            }
+            None => None,
+        };
+
+        let server = match (listen, tls) {
+            (Listen::Tcp(addr, port), Some(tls_config)) => {
+                server.bind_rustls_0_23((addr, port), tls_config)?
+            }
+            (Listen::Tcp(addr, port), None) => server.bind((addr, port))?,
+            (Listen::Unix(_), Some(_)) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "HTTPS is only possible on TCP, not on a unix socket",
+                ));
+            }
+            (Listen::Unix(path), None) => {
+                remove_stale_socket(&path)?;
+                server.bind_uds(path)?
+            }
+            (Listen::Systemd, tls) => {
+                let mut server = server;
+
+                for socket in activated_sockets()? {
+                    server = match (socket, &tls) {
+                        (ActivatedSocket::Tcp(l), Some(tls_config)) => {
+                            server.listen_rustls_0_23(l, tls_config.clone())?
+                        }
+                        (ActivatedSocket::Tcp(l), None) => server.listen(l)?,
+                        (ActivatedSocket::Unix(l), None) => server.listen_uds(l)?,
+                        (ActivatedSocket::Unix(_), Some(_)) => {
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidInput,
+                                "HTTPS is only possible on TCP, not on a unix socket",
+                            ));
+                        }
+                    };
+                }
+
+                server
+            }
+        };
+
+        let server = server
+            .workers(thread_count)
+            .shutdown_timeout(ARGS.shutdown_timeout.unwrap_or(60))
+            .disable_signals()
+            .run();
+
+        set_server(server.handle());
+        tokio::spawn(signal_listener());
+
+        server.await?;
     } else if ARGS.drop_db {
         db_drop().await;
     } else {
@@ -216,14 +408,18 @@ async fn main() -> std::io::Result<()> {
                 Ok(c) => c,
                 Err(e) => {
                     eprint!("No config found, channel may not exists!\nOriginal error message: ");
-                    return Err(io::Error::new(io::ErrorKind::Other, e.to_string()));
+                    return Err(io::Error::other(e.to_string()));
                 }
             };
             let channel = handles::select_channel(&pool, channel_id)
                 .await
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+                .map_err(|e| io::Error::other(e.to_string()))?;
             let manager = ChannelManager::new(Some(pool.clone()), channel.clone(), config.clone());
 
+            load_scripts(&pool, &manager).await;
+            load_ad_breaks(&pool, &manager).await;
+            load_regions(&pool, &manager).await;
+
             if ARGS.foreground {
                 if ARGS.channel.is_none() {
                     error!(
@@ -235,7 +431,7 @@ async fn main() -> std::io::Result<()> {
 
                 channel_controllers
                     .lock()
-                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?
+                    .map_err(|e| io::Error::other(e.to_string()))?
                     .add(manager.clone());
 
                 if let Ok(mut mqs) = mail_queues.lock() {
@@ -282,10 +478,8 @@ async fn main() -> std::io::Result<()> {
         }
     }
 
-    for channel_ctl in &channel_controllers.lock().unwrap().channels {
-        channel_ctl.channel.lock().unwrap().active = false;
-        channel_ctl.stop_all();
-    }
+    shutdown_channels(&pool, &channel_controllers).await;
+    companion::stop_all().await;
 
     pool.close().await;
 
@@ -1,5 +1,8 @@
 use std::{
-    sync::{atomic::Ordering, Arc},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     time::Duration,
 };
 
@@ -14,21 +17,27 @@ use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
 
 use crate::player::{controller::ChannelManager, utils::get_data_map};
-use crate::utils::system;
+use crate::sse::events::{is_current_date, ChannelEvent};
+use crate::utils::{system, time_machine::time_now};
 
 #[derive(Debug, Clone)]
 struct Client {
     manager: ChannelManager,
     endpoint: String,
     sender: mpsc::Sender<sse::Event>,
+    /// Last process state, which the client got.
+    running: Arc<AtomicBool>,
 }
 
 impl Client {
     fn new(manager: ChannelManager, endpoint: String, sender: mpsc::Sender<sse::Event>) -> Self {
+        let running = Arc::new(AtomicBool::new(manager.is_alive.load(Ordering::SeqCst)));
+
         Self {
             manager,
             endpoint,
             sender,
+            running,
         }
     }
 }
@@ -70,6 +79,7 @@ impl Broadcaster {
 
                 this.broadcast_playout().await;
                 this.broadcast_system().await;
+                this.broadcast_process().await;
 
                 counter = (counter + 1) % 61;
             }
@@ -152,4 +162,45 @@ impl Broadcaster {
             }
         }
     }
+
+    /// Sends the process state to event clients, when the channel started or stopped.
+    async fn broadcast_process(&self) {
+        let clients = self.inner.lock().clients.clone();
+
+        for client in clients.iter().filter(|client| client.endpoint == "events") {
+            let running = client.manager.is_alive.load(Ordering::SeqCst);
+
+            if client.running.swap(running, Ordering::SeqCst) != running {
+                let _ = client
+                    .sender
+                    .send(ChannelEvent::Process { running }.into())
+                    .await;
+            }
+        }
+    }
+
+    /// Sends the event to the event clients from the channel.
+    /// Playlist events are only for today, tomorrow and the playing playlist.
+    pub async fn notify(&self, channel_id: i32, event: ChannelEvent) {
+        let clients = self.inner.lock().clients.clone();
+        let today = time_now().date_naive();
+
+        for client in clients.iter().filter(|client| client.endpoint == "events") {
+            let id = client.manager.channel.lock().unwrap().id;
+
+            if id != channel_id {
+                continue;
+            }
+
+            if let ChannelEvent::Playlist { date, .. } = &event {
+                let playing = client.manager.current_date.lock().unwrap().clone();
+
+                if !is_current_date(date, &playing, today) {
+                    continue;
+                }
+            }
+
+            let _ = client.sender.send(event.clone().into()).await;
+        }
+    }
 }
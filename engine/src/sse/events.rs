@@ -0,0 +1,75 @@
+/*
+Channel events for the dashboards.
+
+`/api/events/{id}` is a Server-Sent Events stream, so all open dashboards of a channel see
+changes from other users, without polling:
+
+- `playlist`: the playlist from today or tomorrow is saved, generated or deleted
+- `config`: the playout or advanced config is changed
+- `process`: the channel starts or stops
+
+The event name is also in the data, like: `{"event":"playlist","date":"2024-10-30","action":"saved"}`
+
+*/
+
+use actix_web_lab::sse;
+use chrono::{NaiveDate, TimeDelta};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaylistAction {
+    Saved,
+    Generated,
+    Deleted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigKind {
+    Playout,
+    Advanced,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum ChannelEvent {
+    Playlist {
+        date: String,
+        action: PlaylistAction,
+    },
+    Config {
+        kind: ConfigKind,
+    },
+    Process {
+        running: bool,
+    },
+}
+
+impl ChannelEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Playlist { .. } => "playlist",
+            Self::Config { .. } => "config",
+            Self::Process { .. } => "process",
+        }
+    }
+}
+
+impl From<ChannelEvent> for sse::Event {
+    fn from(event: ChannelEvent) -> Self {
+        sse::Data::new(serde_json::to_string(&event).unwrap_or_default())
+            .event(event.name())
+            .into()
+    }
+}
+
+/// Playlist date is from today or tomorrow, or it is the playlist which is playing now.
+/// Before the day start, the playing playlist is still the one from yesterday.
+pub fn is_current_date(date: &str, playing: &str, today: NaiveDate) -> bool {
+    let tomorrow = today + TimeDelta::try_days(1).unwrap();
+
+    date == playing
+        || date == today.format("%Y-%m-%d").to_string()
+        || date == tomorrow.format("%Y-%m-%d").to_string()
+}
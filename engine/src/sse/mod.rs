@@ -9,6 +9,7 @@ use uuid::Uuid;
 use crate::utils::errors::ServiceError;
 
 pub mod broadcast;
+pub mod events;
 pub mod routes;
 
 #[derive(Debug, Eq, Hash, PartialEq, Clone, Copy)]
@@ -1,11 +1,11 @@
 use std::sync::Mutex;
 
 use actix_web::{get, post, web, Responder};
-use actix_web_grants::proc_macro::protect;
+use actix_web_grants::{authorities::AuthDetails, proc_macro::protect};
 use serde::{Deserialize, Serialize};
 
 use super::{check_uuid, prune_uuids, SseAuthState, UuidData};
-use crate::db::models::Role;
+use crate::db::models::{Role, UserMeta};
 use crate::player::controller::ChannelController;
 use crate::sse::broadcast::Broadcaster;
 use crate::utils::errors::ServiceError;
@@ -86,3 +86,32 @@ async fn event_stream(
         .new_client(manager.clone(), user.endpoint.clone())
         .await)
 }
+
+/// **Channel events**
+///
+/// Stream with playlist, config and process events from the channel.
+///
+/// ```BASH
+/// curl -N -X GET 'http://127.0.0.1:8787/api/events/1' -H 'Authorization: Bearer <TOKEN>'
+/// ```
+#[get("/events/{id}")]
+#[protect(
+    any("Role::GlobalAdmin", "Role::ChannelAdmin", "Role::User"),
+    ty = "Role",
+    expr = "user.channels.contains(&*id) || role.has_authority(&Role::GlobalAdmin)"
+)]
+async fn channel_events(
+    broadcaster: web::Data<Broadcaster>,
+    id: web::Path<i32>,
+    controllers: web::Data<Mutex<ChannelController>>,
+    role: AuthDetails<Role>,
+    user: web::ReqData<UserMeta>,
+) -> Result<impl Responder, ServiceError> {
+    let manager = controllers
+        .lock()
+        .unwrap()
+        .get(*id)
+        .ok_or_else(|| ServiceError::BadRequest(format!("Channel ({id}) not exists!")))?;
+
+    Ok(broadcaster.new_client(manager, "events".to_string()).await)
+}
@@ -0,0 +1,413 @@
+/*
+Channel scheduling over several playout nodes, for installations with many channels.
+
+One instance is the API tier, the frontend and the users talk only to it. The playout nodes are
+more ffplayout instances with the same channels, the same storage and the same secret,
+so the tokens from the API tier are valid on the nodes too:
+
+- channels without node run on the API tier itself
+- a channel goes to the node with the fewest channels, or to a chosen node
+- control and status calls of a channel (`/api/control/{id}/...`, `/api/system/{id}`
+  and `/api/log/{id}`) are proxied to its node
+- the API tier checks the nodes every 5 seconds, the channels of a node which is drained
+  or not reachable for 30 seconds move to the other nodes and start there
+
+*/
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{atomic::Ordering, Arc, LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use actix_web::web;
+use log::*;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::json;
+use sqlx::{Pool, Sqlite};
+use tokio::time::interval;
+use url::Url;
+
+use crate::api::auth::{create_jwt, Claims};
+use crate::db::{
+    handles,
+    models::{Node, NodeChannel, Permission, Role},
+};
+use crate::player::controller::{ChannelController, ChannelManager};
+use crate::utils::{
+    errors::ServiceError, logging::Target, maintenance::in_maintenance, time_machine::time_now,
+};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time a node can fail, until its channels move.
+pub const FAIL_AFTER: Duration = Duration::from_secs(30);
+
+// Channel id with the address of its node
+static ROUTES: LazyLock<Mutex<HashMap<i32, String>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+static HEALTH: LazyLock<Mutex<HashMap<i32, NodeHealth>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Last check of a node.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct NodeHealth {
+    pub online: bool,
+    pub last_seen: Option<String>,
+    pub latency_ms: Option<u64>,
+    pub error: Option<String>,
+    #[serde(skip)]
+    pub failed_since: Option<Instant>,
+}
+
+/// Node with health and channels, for the status API.
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeStatus {
+    #[serde(flatten)]
+    pub node: Node,
+    #[serde(flatten)]
+    pub health: NodeHealth,
+    pub channels: Vec<i32>,
+}
+
+/// Check the address of a node, it is stored without the trailing slash.
+pub fn node_url(url: &str) -> Result<String, ServiceError> {
+    let url = url.trim().trim_end_matches('/');
+
+    match Url::parse(url) {
+        Ok(u) if matches!(u.scheme(), "http" | "https") && u.host().is_some() => {
+            Ok(url.to_string())
+        }
+        _ => Err(ServiceError::BadRequest(format!(
+            "Node address {url} is not a http(s) url"
+        ))),
+    }
+}
+
+/// Channel from a control or status path, these calls go to the node of the channel.
+pub fn proxy_channel(path: &str) -> Option<i32> {
+    let re = Regex::new(r"^/api/(control|system|log)/(\d+)(/|$)").unwrap();
+
+    re.captures(path).and_then(|c| c[2].parse().ok())
+}
+
+/// Channels per node.
+pub fn node_load(assigned: &[NodeChannel]) -> HashMap<i32, usize> {
+    let mut load = HashMap::new();
+
+    for a in assigned {
+        *load.entry(a.node_id).or_default() += 1;
+    }
+
+    load
+}
+
+/// Node for a channel: online, not drained and with the fewest channels.
+/// On the same load the older node wins.
+pub fn pick_node(
+    nodes: &[Node],
+    load: &HashMap<i32, usize>,
+    online: &HashSet<i32>,
+    exclude: Option<i32>,
+) -> Option<i32> {
+    nodes
+        .iter()
+        .filter(|n| !n.drained && online.contains(&n.id) && Some(n.id) != exclude)
+        .min_by_key(|n| (load.get(&n.id).copied().unwrap_or_default(), n.id))
+        .map(|n| n.id)
+}
+
+/// Channels of a node, which has to move: the node is drained or failed too long.
+pub fn needs_move(node: &Node, health: Option<&NodeHealth>) -> bool {
+    node.drained
+        || health
+            .and_then(|h| h.failed_since)
+            .is_some_and(|f| f.elapsed() >= FAIL_AFTER)
+}
+
+/// Address of the node, which runs the channel.
+pub fn channel_node(id: i32) -> Option<String> {
+    ROUTES.lock().unwrap().get(&id).cloned()
+}
+
+/// Forget the health of a removed node.
+pub fn forget_node(id: i32) {
+    HEALTH.lock().unwrap().remove(&id);
+}
+
+pub fn online_nodes() -> HashSet<i32> {
+    HEALTH
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(_, h)| h.online)
+        .map(|(id, _)| *id)
+        .collect()
+}
+
+/// Load the channels of the nodes, for the proxy.
+pub async fn load_routes(pool: &Pool<Sqlite>) -> Result<(), ServiceError> {
+    let nodes = handles::select_nodes(pool).await?;
+    let assigned = handles::select_node_channels(pool).await?;
+
+    *ROUTES.lock().unwrap() = assigned
+        .iter()
+        .filter_map(|a| {
+            nodes
+                .iter()
+                .find(|n| n.id == a.node_id)
+                .map(|n| (a.channel_id, n.url.clone()))
+        })
+        .collect();
+
+    Ok(())
+}
+
+pub async fn node_status(pool: &Pool<Sqlite>) -> Result<Vec<NodeStatus>, ServiceError> {
+    let nodes = handles::select_nodes(pool).await?;
+    let assigned = handles::select_node_channels(pool).await?;
+    let health = HEALTH.lock().unwrap().clone();
+
+    Ok(nodes
+        .into_iter()
+        .map(|node| NodeStatus {
+            health: health.get(&node.id).cloned().unwrap_or_default(),
+            channels: assigned
+                .iter()
+                .filter(|a| a.node_id == node.id)
+                .map(|a| a.channel_id)
+                .collect(),
+            node,
+        })
+        .collect())
+}
+
+fn node_error(e: reqwest::Error) -> ServiceError {
+    ServiceError::ServiceUnavailable(format!("Playout node: {}", e.without_url()))
+}
+
+fn client() -> Result<reqwest::Client, ServiceError> {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(node_error)
+}
+
+/// Ask the node for its health and keep the result.
+pub async fn check_node(node: &Node) {
+    let start = Instant::now();
+    let result = match client() {
+        Ok(client) => client
+            .get(format!("{}/health/live", node.url))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| e.without_url().to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+
+    let mut health = HEALTH.lock().unwrap();
+    let entry = health.entry(node.id).or_default();
+
+    match result {
+        Ok(_) => {
+            entry.online = true;
+            entry.last_seen = Some(time_now().format("%Y-%m-%d %H:%M:%S").to_string());
+            entry.latency_ms = Some(start.elapsed().as_millis() as u64);
+            entry.error = None;
+            entry.failed_since = None;
+        }
+        Err(e) => {
+            entry.online = false;
+            entry.error = Some(e);
+            entry.failed_since.get_or_insert_with(Instant::now);
+        }
+    }
+}
+
+/// Send a process command to the channel on its node.
+/// The API tier signs in with a token from the shared secret.
+pub async fn node_command(url: &str, id: i32, command: &str) -> Result<(), ServiceError> {
+    let claims = Claims::new(
+        0,
+        vec![],
+        "cluster".to_string(),
+        Role::GlobalAdmin,
+        Permission::ALL.to_vec(),
+    );
+    let token = create_jwt(claims).await?;
+
+    client()?
+        .post(format!("{url}/api/control/{id}/process/"))
+        .bearer_auth(token)
+        .json(&json!({ "command": command }))
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(node_error)?;
+
+    Ok(())
+}
+
+/// Forward a request to a node, returns status, content type and body from the node.
+pub async fn forward(
+    url: &str,
+    method: &str,
+    path: &str,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+) -> Result<(u16, String, Vec<u8>), ServiceError> {
+    let method = reqwest::Method::from_bytes(method.as_bytes())
+        .map_err(|e| ServiceError::BadRequest(e.to_string()))?;
+    let mut request = client()?.request(method, format!("{url}{path}")).body(body);
+
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+
+    let response = request.send().await.map_err(node_error)?;
+    let status = response.status().as_u16();
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|c| c.to_str().ok())
+        .unwrap_or("application/json")
+        .to_string();
+    let body = response.bytes().await.map_err(node_error)?;
+
+    Ok((status, content_type, body.to_vec()))
+}
+
+/// Put a channel on a node, or back on the API tier without node.
+/// The channel stops where it ran and starts on the new place, when it is active and not in maintenance.
+pub async fn move_channel(
+    pool: &Pool<Sqlite>,
+    manager: &ChannelManager,
+    target: Option<&Node>,
+) -> Result<(), ServiceError> {
+    let id = manager.channel.lock().unwrap().id;
+    let active = handles::select_channel(pool, &id).await?.active;
+
+    match channel_node(id) {
+        Some(url) => {
+            // a failed node is not reachable, the channel runs there only when it comes back
+            if let Err(e) = node_command(&url, id, "stop").await {
+                warn!(target: Target::file_mail(), channel = id; "Unable to stop channel on node {url}: {e}");
+            }
+        }
+        None if manager.is_alive.load(Ordering::SeqCst) => {
+            let manager = manager.clone();
+            manager.channel.lock().unwrap().active = false;
+
+            web::block(move || manager.stop_all())
+                .await
+                .map_err(|e| ServiceError::Conflict(e.to_string()))?;
+        }
+        None => {}
+    }
+
+    match target {
+        Some(node) => handles::upsert_node_channel(pool, id, node.id).await?,
+        None => handles::delete_node_channel(pool, id).await?,
+    };
+
+    load_routes(pool).await?;
+
+    if active && !in_maintenance(id) {
+        match target {
+            Some(node) => node_command(&node.url, id, "start").await?,
+            None => {
+                manager.channel.lock().unwrap().active = true;
+                manager.async_start().await;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Move the channels from drained and failed nodes to the other nodes.
+async fn rebalance(
+    pool: &Pool<Sqlite>,
+    controllers: &Arc<Mutex<ChannelController>>,
+    nodes: &[Node],
+    stuck: &mut HashSet<i32>,
+) -> Result<(), ServiceError> {
+    let assigned = handles::select_node_channels(pool).await?;
+    let health = HEALTH.lock().unwrap().clone();
+    let online = online_nodes();
+    let mut load = node_load(&assigned);
+
+    for a in &assigned {
+        let Some(node) = nodes.iter().find(|n| n.id == a.node_id) else {
+            continue;
+        };
+
+        if !needs_move(node, health.get(&node.id)) {
+            stuck.remove(&a.channel_id);
+            continue;
+        }
+
+        let Some(target) = pick_node(nodes, &load, &online, Some(node.id))
+            .and_then(|t| nodes.iter().find(|n| n.id == t))
+        else {
+            if stuck.insert(a.channel_id) {
+                error!(target: Target::file_mail(), channel = a.channel_id; "Node <b>{}</b> is down or drained, no other node can take the channel", node.name);
+            }
+
+            continue;
+        };
+
+        let Some(manager) = controllers.lock().unwrap().get(a.channel_id) else {
+            continue;
+        };
+
+        match move_channel(pool, &manager, Some(target)).await {
+            Ok(_) => {
+                stuck.remove(&a.channel_id);
+                *load.entry(node.id).or_default() -= 1;
+                *load.entry(target.id).or_default() += 1;
+
+                warn!(target: Target::file_mail(), channel = a.channel_id; "Channel moved from node <b>{}</b> to <b>{}</b>", node.name, target.name);
+            }
+            Err(e) => {
+                error!(target: Target::file_mail(), channel = a.channel_id; "Unable to move channel to node <b>{}</b>: {e}", target.name);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check the nodes and move the channels of drained and failed nodes.
+pub fn cluster_monitor(pool: Pool<Sqlite>, controllers: Arc<Mutex<ChannelController>>) {
+    actix_web::rt::spawn(async move {
+        let mut interval = interval(CHECK_INTERVAL);
+        let mut stuck = HashSet::new();
+
+        loop {
+            interval.tick().await;
+
+            let nodes = match handles::select_nodes(&pool).await {
+                Ok(nodes) => nodes,
+                Err(e) => {
+                    error!("Cluster: {e}");
+                    continue;
+                }
+            };
+
+            if nodes.is_empty() {
+                continue;
+            }
+
+            for node in &nodes {
+                check_node(node).await;
+            }
+
+            if let Err(e) = rebalance(&pool, &controllers, &nodes, &mut stuck).await {
+                error!("Cluster: {e}");
+            }
+        }
+    });
+}
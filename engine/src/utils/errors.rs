@@ -1,9 +1,13 @@
-use std::io;
-
 use actix_web::{error::ResponseError, Error, HttpResponse};
 use derive_more::Display;
-use ffprobe::FfProbeError;
 
+pub use ffplayout_core::utils::errors::ProcessError;
+
+use ffplayout_core::utils::errors::ServiceError as CoreError;
+
+/// Error of the API, with the HTTP response for it.
+///
+/// Errors from the player core convert into it with the same variant.
 #[derive(Debug, Display)]
 pub enum ServiceError {
     #[display("Internal Server Error")]
@@ -26,6 +30,9 @@ pub enum ServiceError {
 
     #[display("ServiceUnavailable: {_0}")]
     ServiceUnavailable(String),
+
+    #[display("InsufficientStorage: {_0}")]
+    InsufficientStorage(String),
 }
 
 // impl ResponseError trait allows to convert our errors into http responses with appropriate data
@@ -43,6 +50,24 @@ impl ResponseError for ServiceError {
             Self::ServiceUnavailable(ref message) => {
                 HttpResponse::ServiceUnavailable().json(message)
             }
+            Self::InsufficientStorage(ref message) => {
+                HttpResponse::InsufficientStorage().json(message)
+            }
+        }
+    }
+}
+
+impl From<CoreError> for ServiceError {
+    fn from(err: CoreError) -> Self {
+        match err {
+            CoreError::InternalServerError => Self::InternalServerError,
+            CoreError::BadRequest(message) => Self::BadRequest(message),
+            CoreError::Conflict(message) => Self::Conflict(message),
+            CoreError::Forbidden(message) => Self::Forbidden(message),
+            CoreError::Unauthorized(message) => Self::Unauthorized(message),
+            CoreError::NoContent(message) => Self::NoContent(message),
+            CoreError::ServiceUnavailable(message) => Self::ServiceUnavailable(message),
+            CoreError::InsufficientStorage(message) => Self::InsufficientStorage(message),
         }
     }
 }
@@ -130,73 +155,3 @@ impl From<&str> for ServiceError {
         Self::BadRequest(err.to_string())
     }
 }
-
-#[derive(Debug, Display)]
-pub enum ProcessError {
-    #[display("Failed to spawn ffmpeg/ffprobe. {}", _0)]
-    CommandSpawn(io::Error),
-    #[display("{}", _0)]
-    Custom(String),
-    #[display("IO error: {}", _0)]
-    IO(io::Error),
-    #[display("{}", _0)]
-    Ffprobe(FfProbeError),
-    #[display("Regex compile error {}", _0)]
-    Regex(String),
-    #[display("Thread error {}", _0)]
-    Thread(String),
-}
-
-impl From<std::io::Error> for ProcessError {
-    fn from(err: std::io::Error) -> Self {
-        Self::IO(err)
-    }
-}
-
-impl From<FfProbeError> for ProcessError {
-    fn from(err: FfProbeError) -> Self {
-        Self::Ffprobe(err)
-    }
-}
-
-impl From<lettre::address::AddressError> for ProcessError {
-    fn from(err: lettre::address::AddressError) -> Self {
-        Self::Custom(err.to_string())
-    }
-}
-
-impl From<lettre::transport::smtp::Error> for ProcessError {
-    fn from(err: lettre::transport::smtp::Error) -> Self {
-        Self::Custom(err.to_string())
-    }
-}
-
-impl From<lettre::error::Error> for ProcessError {
-    fn from(err: lettre::error::Error) -> Self {
-        Self::Custom(err.to_string())
-    }
-}
-
-impl<T> From<std::sync::PoisonError<T>> for ProcessError {
-    fn from(err: std::sync::PoisonError<T>) -> Self {
-        Self::Custom(err.to_string())
-    }
-}
-
-impl From<regex::Error> for ProcessError {
-    fn from(err: regex::Error) -> Self {
-        Self::Regex(err.to_string())
-    }
-}
-
-impl From<serde_json::Error> for ProcessError {
-    fn from(err: serde_json::Error) -> Self {
-        Self::Custom(err.to_string())
-    }
-}
-
-impl From<Box<dyn std::any::Any + std::marker::Send>> for ProcessError {
-    fn from(err: Box<dyn std::any::Any + std::marker::Send>) -> Self {
-        Self::Thread(format!("{err:?}"))
-    }
-}
@@ -8,15 +8,23 @@ use actix_web::{web, HttpResponse};
 use futures_util::TryStreamExt as _;
 use lexical_sort::{natural_lexical_cmp, PathSort};
 use rand::{distributions::Alphanumeric, Rng};
-use relative_path::RelativePath;
 use serde::{Deserialize, Serialize};
 use tokio::fs;
 
 use log::*;
 
+pub use ffplayout_core::utils::files::*;
+
+use crate::api::pagination::ListQuery;
 use crate::db::models::Channel;
 use crate::player::utils::{file_extension, MediaProbe};
-use crate::utils::{config::PlayoutConfig, errors::ServiceError};
+use crate::utils::{
+    config::PlayoutConfig,
+    errors::ServiceError,
+    object_store::{self, probe_source, push},
+    shutdown::UploadGuard,
+    trash::move_to_trash,
+};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct PathObject {
@@ -25,6 +33,9 @@ pub struct PathObject {
     parent_folders: Option<Vec<String>>,
     folders: Option<Vec<String>>,
     files: Option<Vec<VideoFile>>,
+    /// Count of all matching files, when the list is paged.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    total: Option<usize>,
     #[serde(default)]
     pub folders_only: bool,
     #[serde(default)]
@@ -39,6 +50,7 @@ impl PathObject {
             parent_folders: Some(vec![]),
             folders: Some(vec![]),
             files: Some(vec![]),
+            total: None,
             folders_only: false,
             recursive: false,
         }
@@ -57,46 +69,58 @@ pub struct VideoFile {
     duration: f64,
 }
 
-/// Normalize absolut path
-///
-/// This function takes care, that it is not possible to break out from root_path.
-pub fn norm_abs_path(
-    root_path: &Path,
-    input_path: &str,
-) -> Result<(PathBuf, String, String), ServiceError> {
-    let path_relative = RelativePath::new(&root_path.to_string_lossy())
-        .normalize()
-        .to_string()
-        .replace("../", "");
-    let path_suffix = root_path
-        .file_name()
-        .unwrap_or_default()
-        .to_string_lossy()
-        .to_string();
-    let mut source_relative = RelativePath::new(input_path)
-        .normalize()
-        .to_string()
-        .replace("../", "");
-
-    if input_path.starts_with(&*root_path.to_string_lossy())
-        || source_relative.starts_with(&path_relative)
-    {
-        source_relative = source_relative
-            .strip_prefix(&path_relative)
-            .and_then(|s| s.strip_prefix('/'))
-            .unwrap_or_default()
-            .to_string();
-    } else {
-        source_relative = source_relative
-            .strip_prefix(&path_suffix)
-            .and_then(|s| s.strip_prefix('/'))
-            .unwrap_or(&source_relative)
-            .to_string();
+/// MIME type for media previews. The guess from actix files does not know all containers,
+/// or uses types which browsers do not play, like `video/vnd.dlna.mpeg-tts` for `.ts`.
+pub fn media_mime(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+
+    let mime = match ext.as_str() {
+        "mp4" | "m4v" => "video/mp4",
+        "mov" => "video/quicktime",
+        "mkv" => "video/x-matroska",
+        "webm" => "video/webm",
+        "ts" | "m2ts" | "mts" => "video/mp2t",
+        "mpg" | "mpeg" => "video/mpeg",
+        "avi" => "video/x-msvideo",
+        "flv" => "video/x-flv",
+        "ogv" => "video/ogg",
+        "mxf" => "application/mxf",
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "aac" => "audio/aac",
+        "wav" => "audio/wav",
+        "flac" => "audio/flac",
+        "ogg" | "oga" => "audio/ogg",
+        "opus" => "audio/opus",
+        "vtt" => "text/vtt",
+        "srt" => "application/x-subrip",
+        _ => return None,
+    };
+
+    Some(mime)
+}
+
+/// Folders and files in a folder, with `true` for folders.
+async fn folder_entries(
+    config: &PlayoutConfig,
+    path: &Path,
+) -> Result<Vec<(PathBuf, bool)>, ServiceError> {
+    if config.storage.object_store.enable {
+        return Ok(object_store::folder_entries(config, path).await?);
     }
 
-    let path = &root_path.join(&source_relative);
+    let mut entries = vec![];
+    let mut children = fs::read_dir(path).await?;
+
+    while let Some(child) = children.next_entry().await? {
+        let meta = child.metadata().await?;
 
-    Ok((path.clone(), path_suffix, source_relative))
+        if meta.is_dir() || meta.is_file() {
+            entries.push((child.path(), meta.is_dir()));
+        }
+    }
+
+    Ok(entries)
 }
 
 /// File Browser
@@ -108,6 +132,7 @@ pub async fn browser(
     config: &PlayoutConfig,
     channel: &Channel,
     path_obj: &PathObject,
+    list: &ListQuery,
 ) -> Result<PathObject, ServiceError> {
     let mut channel_extensions = channel
         .extra_extensions
@@ -130,18 +155,9 @@ pub async fn browser(
     obj.folders_only = path_obj.folders_only;
 
     if path != parent_path && !path_obj.folders_only {
-        let mut parents = fs::read_dir(&parent_path).await?;
-
-        while let Some(child) = parents.next_entry().await? {
-            if child.metadata().await?.is_dir() {
-                parent_folders.push(
-                    child
-                        .path()
-                        .file_name()
-                        .unwrap()
-                        .to_string_lossy()
-                        .to_string(),
-                );
+        for (child, is_dir) in folder_entries(config, parent_path).await? {
+            if is_dir {
+                parent_folders.push(child.file_name().unwrap().to_string_lossy().to_string());
             }
         }
 
@@ -150,32 +166,31 @@ pub async fn browser(
         obj.parent_folders = Some(parent_folders);
     }
 
-    let mut paths_obj = fs::read_dir(path).await?;
-
     let mut files = vec![];
     let mut folders = vec![];
 
-    while let Some(child) = paths_obj.next_entry().await? {
-        let f_meta = child.metadata().await?;
-
+    for (child, is_dir) in folder_entries(config, &path).await? {
         // ignore hidden files/folders on unix
-        if child.path().to_string_lossy().to_string().contains("/.") {
+        if child.to_string_lossy().to_string().contains("/.") {
             continue;
         }
 
-        if f_meta.is_dir() {
-            folders.push(
-                child
-                    .path()
-                    .file_name()
-                    .unwrap()
-                    .to_string_lossy()
-                    .to_string(),
-            );
-        } else if f_meta.is_file() && !path_obj.folders_only {
-            if let Some(ext) = file_extension(&child.path()) {
+        let name = child
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        if !list.matches_text(&name) {
+            continue;
+        }
+
+        if is_dir {
+            folders.push(name);
+        } else if !path_obj.folders_only {
+            if let Some(ext) = file_extension(&child) {
                 if extensions.contains(&ext.to_string().to_lowercase()) {
-                    files.push(child.path());
+                    files.push(child);
                 }
             }
         }
@@ -185,8 +200,33 @@ pub async fn browser(
     files.path_sort(natural_lexical_cmp);
     let mut media_files = vec![];
 
+    let sort = list.sort_field();
+    let by_duration = matches!(sort, Some(("duration", _)));
+
+    if let Some((field, desc)) = sort {
+        if !["name", "duration"].contains(&field) {
+            return Err(ServiceError::BadRequest(format!(
+                "Unknown sort field: {field}"
+            )));
+        }
+
+        if desc {
+            folders.reverse();
+            files.reverse();
+        }
+    }
+
+    if !list.is_empty() {
+        obj.total = Some(files.len());
+
+        // only the files from the page are probed, unless they are sorted by duration
+        if !by_duration {
+            files = files.drain(list.range(files.len())).collect();
+        }
+    }
+
     for file in files {
-        match MediaProbe::new(file.to_string_lossy().as_ref()) {
+        match MediaProbe::new(&probe_source(config, &file)) {
             Ok(probe) => {
                 let mut duration = 0.0;
 
@@ -204,6 +244,16 @@ pub async fn browser(
         };
     }
 
+    if by_duration {
+        media_files.sort_by(|a, b| a.duration.total_cmp(&b.duration));
+
+        if matches!(sort, Some((_, true))) {
+            media_files.reverse();
+        }
+
+        media_files = media_files.drain(list.range(media_files.len())).collect();
+    }
+
     obj.folders = Some(folders);
     obj.files = Some(media_files);
 
@@ -216,10 +266,16 @@ pub async fn create_directory(
 ) -> Result<HttpResponse, ServiceError> {
     let (path, _, _) = norm_abs_path(&config.channel.storage, &path_obj.source)?;
 
+    check_quota(config, 1).await?;
+
     if let Err(e) = fs::create_dir_all(&path).await {
         return Err(ServiceError::BadRequest(e.to_string()));
     }
 
+    if config.storage.object_store.enable {
+        object_store::create_folder(config, &path).await?;
+    }
+
     info!(
         "create folder: <b><magenta>{}</></b>",
         path.to_string_lossy()
@@ -283,6 +339,12 @@ pub async fn rename_file(
     config: &PlayoutConfig,
     move_object: &MoveObject,
 ) -> Result<MoveObject, ServiceError> {
+    if config.storage.object_store.enable {
+        return Err(ServiceError::BadRequest(
+            "Moving files is not supported with object storage!".into(),
+        ));
+    }
+
     let (source_path, _, _) = norm_abs_path(&config.channel.storage, &move_object.source)?;
     let (mut target_path, _, _) = norm_abs_path(&config.channel.storage, &move_object.target)?;
 
@@ -312,17 +374,38 @@ pub async fn rename_file(
     Err(ServiceError::InternalServerError)
 }
 
+/// Remove file or folder, when the trash is enabled it will be moved there.
 pub async fn remove_file_or_folder(
     config: &PlayoutConfig,
     source_path: &str,
     recursive: bool,
+    permanent: bool,
 ) -> Result<(), ServiceError> {
-    let (source, _, _) = norm_abs_path(&config.channel.storage, source_path)?;
+    let (source, _, relative) = norm_abs_path(&config.channel.storage, source_path)?;
+
+    if config.storage.object_store.enable {
+        return Ok(object_store::remove(config, &source, recursive).await?);
+    }
 
     if !source.exists() {
         return Err(ServiceError::BadRequest("Source does not exists!".into()));
     }
 
+    if !permanent && config.storage.trash_retention > 0 {
+        if source.is_dir()
+            && !recursive
+            && fs::read_dir(&source).await?.next_entry().await?.is_some()
+        {
+            return Err(ServiceError::BadRequest(
+                "Delete folder failed! (Folder must be empty)".into(),
+            ));
+        }
+
+        move_to_trash(config, &source, &relative).await?;
+
+        return Ok(());
+    }
+
     if source.is_dir() {
         let res = if recursive {
             fs::remove_dir_all(source).await
@@ -357,6 +440,11 @@ pub async fn remove_file_or_folder(
 async fn valid_path(config: &PlayoutConfig, path: &str) -> Result<PathBuf, ServiceError> {
     let (test_path, _, _) = norm_abs_path(&config.channel.storage, path)?;
 
+    // with object storage the folder is only in the bucket, until something is cached
+    if config.storage.object_store.enable {
+        fs::create_dir_all(&test_path).await?;
+    }
+
     if !test_path.is_dir() {
         return Err(ServiceError::BadRequest("Target folder not exists!".into()));
     }
@@ -366,11 +454,26 @@ async fn valid_path(config: &PlayoutConfig, path: &str) -> Result<PathBuf, Servi
 
 pub async fn upload(
     config: &PlayoutConfig,
-    _size: u64,
+    size: u64,
     mut payload: Multipart,
     path: &Path,
     abs_path: bool,
 ) -> Result<HttpResponse, ServiceError> {
+    // a shutdown waits for the running uploads
+    let _guard = UploadGuard::new();
+    // absolute paths are temporary files outside the storage, they count not to the quota
+    let quota = if abs_path {
+        0
+    } else {
+        config.channel.storage_quota
+    };
+    // content length is a bit larger then the file, but good enough for a first check
+    let mut used = if abs_path {
+        0
+    } else {
+        check_quota(config, size).await?
+    };
+
     while let Some(mut field) = payload.try_next().await? {
         let content_disposition = field.content_disposition().ok_or("No content")?;
         debug!("{content_disposition}");
@@ -406,6 +509,17 @@ pub async fn upload(
         loop {
             match field.try_next().await {
                 Ok(Some(chunk)) => {
+                    used += chunk.len() as u64;
+
+                    if quota > 0 && used > quota {
+                        drop(f);
+                        tokio::fs::remove_file(filepath).await?;
+
+                        return Err(ServiceError::InsufficientStorage(format!(
+                            "Storage quota of {quota} bytes exceeded"
+                        )));
+                    }
+
                     f = web::block(move || f.write_all(&chunk).map(|_| f)).await??;
                 }
 
@@ -422,6 +536,14 @@ pub async fn upload(
                 }
             }
         }
+
+        if !abs_path && config.storage.object_store.enable {
+            if let Err(e) = push(config, &filepath).await {
+                tokio::fs::remove_file(filepath).await?;
+
+                return Err(e.into());
+            }
+        }
     }
 
     Ok(HttpResponse::Ok().into())
@@ -0,0 +1,177 @@
+/*
+Address for the API server, from `-l`.
+
+- `127.0.0.1:8787`, `[::1]:8787`, `localhost:8787`: TCP
+- `unix:/run/ffplayout/api.sock` or an absolute path: unix domain socket, for a local proxy
+- `systemd`: sockets from systemd socket activation (`LISTEN_FDS`)
+
+With socket activation systemd opens the socket and starts ffplayout on the first connection,
+so the port can be below 1024 without privileges and connections wait while ffplayout restarts.
+
+*/
+
+use std::{
+    env, fmt, fs, io,
+    net::TcpListener,
+    os::{
+        fd::{FromRawFd, RawFd},
+        unix::{fs::FileTypeExt, net::UnixListener},
+    },
+    path::{Path, PathBuf},
+};
+
+use nix::{
+    fcntl::{fcntl, FcntlArg, FdFlag},
+    sys::socket::{getsockname, AddressFamily, SockaddrLike, SockaddrStorage},
+};
+
+/// First file descriptor from systemd.
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Listen {
+    Tcp(String, u16),
+    Unix(PathBuf),
+    Systemd,
+}
+
+impl Listen {
+    pub fn parse(value: &str) -> io::Result<Self> {
+        let value = value.trim();
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidInput, msg.to_string());
+
+        if value == "systemd" {
+            return Ok(Self::Systemd);
+        }
+
+        if let Some(path) = value.strip_prefix("unix:") {
+            if path.is_empty() {
+                return Err(invalid(
+                    "Socket path is empty, like: unix:/run/ffplayout/api.sock",
+                ));
+            }
+
+            return Ok(Self::Unix(PathBuf::from(path)));
+        }
+
+        if value.starts_with('/') {
+            return Ok(Self::Unix(PathBuf::from(value)));
+        }
+
+        let Some((host, port)) = value.rsplit_once(':') else {
+            return Err(invalid(
+                "<ADRESSE>:<PORT> needed! For example: 127.0.0.1:8787",
+            ));
+        };
+
+        let host = match host.strip_prefix('[') {
+            Some(h) => h
+                .strip_suffix(']')
+                .ok_or_else(|| invalid("IPv6 address needs brackets, like: [::1]:8787"))?,
+            None if host.contains(':') => {
+                return Err(invalid("IPv6 address needs brackets, like: [::1]:8787"));
+            }
+            None => host,
+        };
+
+        if host.is_empty() {
+            return Err(invalid("Address is empty! For example: 127.0.0.1:8787"));
+        }
+
+        let port = port
+            .parse::<u16>()
+            .map_err(|_| invalid(&format!("Invalid port: {port}")))?;
+
+        Ok(Self::Tcp(host.to_string(), port))
+    }
+}
+
+impl fmt::Display for Listen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(host, port) if host.contains(':') => write!(f, "[{host}]:{port}"),
+            Self::Tcp(host, port) => write!(f, "{host}:{port}"),
+            Self::Unix(path) => write!(f, "unix:{}", path.display()),
+            Self::Systemd => write!(f, "systemd"),
+        }
+    }
+}
+
+/// Socket from systemd socket activation.
+#[derive(Debug)]
+pub enum ActivatedSocket {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+/// Count of sockets from systemd, when they are for this process.
+pub fn listen_fds(pid: Option<&str>, fds: Option<&str>, own_pid: u32) -> usize {
+    match (pid.and_then(|p| p.parse::<u32>().ok()), fds) {
+        (Some(p), Some(n)) if p == own_pid => n.parse().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// Take the sockets from systemd. The variables are removed, so ffmpeg processes do not see them.
+pub fn activated_sockets() -> io::Result<Vec<ActivatedSocket>> {
+    let count = listen_fds(
+        env::var("LISTEN_PID").ok().as_deref(),
+        env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    );
+
+    env::remove_var("LISTEN_PID");
+    env::remove_var("LISTEN_FDS");
+    env::remove_var("LISTEN_FDNAMES");
+
+    if count == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "No sockets from systemd, start ffplayout with a .socket unit",
+        ));
+    }
+
+    let mut sockets = vec![];
+
+    for fd in SD_LISTEN_FDS_START..SD_LISTEN_FDS_START + count as RawFd {
+        fcntl(fd, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC))?;
+
+        let family = getsockname::<SockaddrStorage>(fd)?.family();
+
+        // systemd gives the sockets to this process, nothing else owns them
+        let socket = match family {
+            Some(AddressFamily::Unix) => {
+                let listener = unsafe { UnixListener::from_raw_fd(fd) };
+                listener.set_nonblocking(true)?;
+                ActivatedSocket::Unix(listener)
+            }
+            Some(AddressFamily::Inet | AddressFamily::Inet6) => {
+                let listener = unsafe { TcpListener::from_raw_fd(fd) };
+                listener.set_nonblocking(true)?;
+                ActivatedSocket::Tcp(listener)
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Socket {fd} from systemd is not TCP or unix"),
+                ));
+            }
+        };
+
+        sockets.push(socket);
+    }
+
+    Ok(sockets)
+}
+
+/// Remove an old socket file from a previous run, other files stay.
+pub fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => fs::remove_file(path),
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} exists and is not a socket", path.display()),
+        )),
+        Err(_) => Ok(()),
+    }
+}
@@ -0,0 +1,73 @@
+/*
+Graceful shutdown of the web server.
+
+The shutdown state, the upload counter and the stop of the channels are in the player core,
+here the server gets stopped and the API answers with 503, while the shutdown runs.
+
+*/
+
+use std::sync::OnceLock;
+
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServerHandle, ServiceRequest, ServiceResponse},
+    middleware::Next,
+    Error, ResponseError,
+};
+use log::*;
+use tokio::signal::unix::{signal, SignalKind};
+
+pub use ffplayout_core::utils::shutdown::*;
+
+use crate::utils::errors::ServiceError;
+
+static SERVER: OnceLock<ServerHandle> = OnceLock::new();
+
+/// Handle of the web server, for stopping it from the API.
+pub fn set_server(handle: ServerHandle) {
+    let _ = SERVER.set(handle);
+}
+
+/// Start the shutdown, the server stops after the requests in flight.
+pub fn request_shutdown(reason: &str) {
+    if !start_shutdown(reason) {
+        return;
+    }
+
+    if let Some(handle) = SERVER.get().cloned() {
+        tokio::spawn(async move { handle.stop(true).await });
+    }
+}
+
+/// Wait for SIGTERM or SIGINT and start the shutdown.
+pub async fn signal_listener() {
+    let mut term = match signal(SignalKind::terminate()) {
+        Ok(term) => term,
+        Err(e) => {
+            error!("Unable to listen for SIGTERM: {e}");
+            return;
+        }
+    };
+
+    tokio::select! {
+        _ = term.recv() => request_shutdown("SIGTERM"),
+        _ = tokio::signal::ctrl_c() => request_shutdown("SIGINT"),
+    }
+}
+
+/// Shutdown middleware
+///
+/// After the shutdown started, new API requests get a 503.
+pub async fn shutdown_guard(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    if is_shutting_down() {
+        let response =
+            ServiceError::ServiceUnavailable("Server shuts down".into()).error_response();
+
+        return Ok(req.into_response(response));
+    }
+
+    Ok(next.call(req).await?.map_into_boxed_body())
+}
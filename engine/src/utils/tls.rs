@@ -0,0 +1,408 @@
+/*
+HTTPS in the API server, for deployments without a reverse proxy.
+
+With `--tls-cert` and `--tls-key` the server reads the PEM files and loads them again, when they
+change on disk, so a renewal from an external tool needs no restart.
+
+With `--acme-domain` the certificate comes from Let's Encrypt (or another ACME server from
+`--acme-url`). The challenge is TLS-ALPN-01, so the server needs to be reachable on port 443
+for the domains, no extra HTTP port is needed. Account, certificate and key are stored in
+`--acme-dir` (default: `acme` next to the database) and the certificate is renewed
+`RENEW_BEFORE` days before it expires.
+
+*/
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use instant_acme::{
+    Account, AccountCredentials, AuthorizationStatus, ChallengeType, Identifier, LetsEncrypt,
+    NewAccount, NewOrder, OrderStatus,
+};
+use log::*;
+use rcgen::{CertificateParams, CustomExtension, DistinguishedName, KeyPair};
+use rustls::{
+    crypto::ring::{default_provider, sign::any_supported_type},
+    pki_types::{CertificateDer, PrivateKeyDer},
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+    ServerConfig,
+};
+use tokio::time::{interval, sleep};
+
+use crate::db::DB_PATH;
+use crate::utils::args_parse::Args;
+
+/// ALPN protocol of the TLS-ALPN-01 challenge.
+const ACME_TLS_ALPN: &[u8] = b"acme-tls/1";
+
+/// Let's Encrypt certificates are valid for 90 days.
+const CERT_LIFETIME: u64 = 90;
+
+/// Days before the expiration, when the certificate is renewed.
+pub const RENEW_BEFORE: u64 = 30;
+
+/// Settings from the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TlsMode {
+    Files {
+        cert: PathBuf,
+        key: PathBuf,
+    },
+    Acme {
+        domains: Vec<String>,
+        email: Option<String>,
+        url: String,
+        dir: PathBuf,
+    },
+}
+
+impl TlsMode {
+    /// TLS settings from the arguments, `None` for plain HTTP.
+    pub fn from_args(args: &Args) -> io::Result<Option<Self>> {
+        if let Some(domains) = args.acme_domain.clone().filter(|d| !d.is_empty()) {
+            let dir = match &args.acme_dir {
+                Some(dir) => dir.clone(),
+                None => DB_PATH
+                    .as_ref()
+                    .map_err(|e| io::Error::other(e.to_string()))?
+                    .parent()
+                    .unwrap_or(Path::new("."))
+                    .join("acme"),
+            };
+            let url = args.acme_url.clone().unwrap_or_else(|| {
+                if args.acme_staging {
+                    LetsEncrypt::Staging.url().to_string()
+                } else {
+                    LetsEncrypt::Production.url().to_string()
+                }
+            });
+
+            return Ok(Some(Self::Acme {
+                domains,
+                email: args.acme_email.clone(),
+                url,
+                dir,
+            }));
+        }
+
+        match (&args.tls_cert, &args.tls_key) {
+            (Some(cert), Some(key)) => Ok(Some(Self::Files {
+                cert: cert.clone(),
+                key: key.clone(),
+            })),
+            (None, None) => Ok(None),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "HTTPS needs --tls-cert and --tls-key",
+            )),
+        }
+    }
+
+    fn cert_files(&self) -> (PathBuf, PathBuf) {
+        match self {
+            Self::Files { cert, key } => (cert.clone(), key.clone()),
+            Self::Acme { dir, .. } => (dir.join("cert.pem"), dir.join("key.pem")),
+        }
+    }
+}
+
+/// Certificate for the TLS handshakes, which can be replaced while the server runs.
+#[derive(Debug, Default)]
+pub struct CertResolver {
+    cert: RwLock<Option<Arc<CertifiedKey>>>,
+    /// Challenge certificates by domain, while an ACME order runs.
+    challenges: RwLock<HashMap<String, Arc<CertifiedKey>>>,
+}
+
+impl CertResolver {
+    pub fn set_cert(&self, cert: CertifiedKey) {
+        *self.cert.write().unwrap() = Some(Arc::new(cert));
+    }
+
+    pub fn has_cert(&self) -> bool {
+        self.cert.read().unwrap().is_some()
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        let is_challenge = hello
+            .alpn()
+            .is_some_and(|mut protocols| protocols.any(|p| p == ACME_TLS_ALPN));
+
+        if is_challenge {
+            let domain = hello.server_name()?;
+
+            return self.challenges.read().unwrap().get(domain).cloned();
+        }
+
+        self.cert.read().unwrap().clone()
+    }
+}
+
+/// Certificate chain and key from PEM data.
+pub fn parse_pem(cert: &[u8], key: &[u8]) -> io::Result<CertifiedKey> {
+    let chain = rustls_pemfile::certs(&mut &cert[..]).collect::<Result<Vec<_>, _>>()?;
+
+    if chain.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "No certificate in PEM data",
+        ));
+    }
+
+    let key = rustls_pemfile::private_key(&mut &key[..])?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "No private key in PEM data"))?;
+
+    certified_key(chain, key)
+}
+
+fn certified_key(
+    chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+) -> io::Result<CertifiedKey> {
+    let key = any_supported_type(&key).map_err(io::Error::other)?;
+
+    Ok(CertifiedKey::new(chain, key))
+}
+
+fn load_files(cert: &Path, key: &Path) -> io::Result<CertifiedKey> {
+    parse_pem(&fs::read(cert)?, &fs::read(key)?)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// The certificate from `modified` needs a renewal.
+pub fn needs_renewal(modified: Option<SystemTime>, now: SystemTime) -> bool {
+    let max_age = Duration::from_secs((CERT_LIFETIME - RENEW_BEFORE) * 86400);
+
+    match modified {
+        Some(m) => now.duration_since(m).is_ok_and(|age| age >= max_age),
+        None => true,
+    }
+}
+
+/// Server config for actix, with the resolver for the certificates.
+pub fn server_config(resolver: Arc<CertResolver>) -> io::Result<ServerConfig> {
+    let mut config = ServerConfig::builder_with_provider(Arc::new(default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(io::Error::other)?
+        .with_no_client_auth()
+        .with_cert_resolver(resolver);
+
+    // actix adds h2 and http/1.1
+    config.alpn_protocols.push(ACME_TLS_ALPN.to_vec());
+
+    Ok(config)
+}
+
+/// Load the certificate, which exists already, so the server can start.
+pub fn init_tls(mode: &TlsMode) -> io::Result<Arc<CertResolver>> {
+    let resolver = Arc::new(CertResolver::default());
+    let (cert, key) = mode.cert_files();
+
+    match load_files(&cert, &key) {
+        Ok(c) => resolver.set_cert(c),
+        Err(e) => {
+            if let TlsMode::Files { .. } = mode {
+                return Err(io::Error::new(
+                    e.kind(),
+                    format!("Unable to load {}: {e}", cert.display()),
+                ));
+            }
+
+            info!("No ACME certificate yet, order one");
+        }
+    }
+
+    Ok(resolver)
+}
+
+/// Reload changed certificate files and renew ACME certificates, in the background.
+pub async fn tls_worker(mode: TlsMode, resolver: Arc<CertResolver>) {
+    let (cert, key) = mode.cert_files();
+    let mut last_modified = modified(&cert);
+    let mut timer = interval(Duration::from_secs(3600));
+
+    loop {
+        timer.tick().await;
+
+        if let TlsMode::Acme {
+            domains,
+            email,
+            url,
+            dir,
+        } = &mode
+        {
+            if needs_renewal(modified(&cert), SystemTime::now()) || !resolver.has_cert() {
+                info!(
+                    "Order certificate for: <b><magenta>{}</></b>",
+                    domains.join(", ")
+                );
+
+                if let Err(e) = order_cert(&resolver, domains, email.as_deref(), url, dir).await {
+                    error!("ACME order failed: {e}");
+                }
+            }
+        }
+
+        let current = modified(&cert);
+
+        if current != last_modified {
+            match load_files(&cert, &key) {
+                Ok(c) => {
+                    resolver.set_cert(c);
+                    last_modified = current;
+
+                    info!(
+                        "Load new TLS certificate: <b><magenta>{}</></b>",
+                        cert.display()
+                    );
+                }
+                Err(e) => error!("Unable to load TLS certificate: {e}"),
+            }
+        }
+    }
+}
+
+async fn acme_account(dir: &Path, email: Option<&str>, url: &str) -> io::Result<Account> {
+    let path = dir.join("account.json");
+
+    if let Ok(data) = fs::read(&path) {
+        let credentials: AccountCredentials = serde_json::from_slice(&data)?;
+
+        return Account::from_credentials(credentials)
+            .await
+            .map_err(io::Error::other);
+    }
+
+    let contact = email.map(|e| format!("mailto:{e}"));
+    let contact: Vec<&str> = contact.iter().map(String::as_str).collect();
+    let (account, credentials) = Account::create(
+        &NewAccount {
+            contact: &contact,
+            terms_of_service_agreed: true,
+            only_return_existing: false,
+        },
+        url,
+        None,
+    )
+    .await
+    .map_err(io::Error::other)?;
+
+    fs::write(&path, serde_json::to_vec_pretty(&credentials)?)?;
+
+    Ok(account)
+}
+
+/// Order a certificate with the TLS-ALPN-01 challenge and store it in the ACME directory.
+async fn order_cert(
+    resolver: &CertResolver,
+    domains: &[String],
+    email: Option<&str>,
+    url: &str,
+    dir: &Path,
+) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let account = acme_account(dir, email, url).await?;
+    let identifiers: Vec<Identifier> = domains.iter().map(|d| Identifier::Dns(d.clone())).collect();
+    let mut order = account
+        .new_order(&NewOrder {
+            identifiers: &identifiers,
+        })
+        .await
+        .map_err(io::Error::other)?;
+    let authorizations = order.authorizations().await.map_err(io::Error::other)?;
+
+    for authz in &authorizations {
+        if authz.status != AuthorizationStatus::Pending {
+            continue;
+        }
+
+        let challenge = authz
+            .challenges
+            .iter()
+            .find(|c| c.r#type == ChallengeType::TlsAlpn01)
+            .ok_or_else(|| io::Error::other("ACME server offers no TLS-ALPN-01 challenge"))?;
+        let Identifier::Dns(domain) = &authz.identifier;
+        let digest = order.key_authorization(challenge).digest();
+
+        let mut params = CertificateParams::new(vec![domain.clone()]).map_err(io::Error::other)?;
+        params.custom_extensions = vec![CustomExtension::new_acme_identifier(digest.as_ref())];
+        let key_pair = KeyPair::generate().map_err(io::Error::other)?;
+        let cert = params.self_signed(&key_pair).map_err(io::Error::other)?;
+        let challenge_cert = certified_key(
+            vec![cert.der().clone()],
+            PrivateKeyDer::try_from(key_pair.serialize_der()).map_err(io::Error::other)?,
+        )?;
+
+        resolver
+            .challenges
+            .write()
+            .unwrap()
+            .insert(domain.clone(), Arc::new(challenge_cert));
+
+        order
+            .set_challenge_ready(&challenge.url)
+            .await
+            .map_err(io::Error::other)?;
+    }
+
+    let mut status = OrderStatus::Pending;
+
+    for _ in 0..30 {
+        sleep(Duration::from_secs(2)).await;
+
+        status = order.refresh().await.map_err(io::Error::other)?.status;
+
+        if status != OrderStatus::Pending {
+            break;
+        }
+    }
+
+    resolver.challenges.write().unwrap().clear();
+
+    if status != OrderStatus::Ready {
+        return Err(io::Error::other(format!(
+            "Order is not ready, status: {status:?}"
+        )));
+    }
+
+    let mut params = CertificateParams::new(domains.to_vec()).map_err(io::Error::other)?;
+    params.distinguished_name = DistinguishedName::new();
+    let key_pair = KeyPair::generate().map_err(io::Error::other)?;
+    let csr = params
+        .serialize_request(&key_pair)
+        .map_err(io::Error::other)?;
+
+    order.finalize(csr.der()).await.map_err(io::Error::other)?;
+
+    let chain = loop {
+        match order.certificate().await.map_err(io::Error::other)? {
+            Some(chain) => break chain,
+            None => sleep(Duration::from_secs(1)).await,
+        }
+    };
+    let key = key_pair.serialize_pem();
+
+    resolver.set_cert(parse_pem(chain.as_bytes(), key.as_bytes())?);
+
+    fs::write(dir.join("key.pem"), key)?;
+    fs::write(dir.join("cert.pem"), chain)?;
+
+    info!(
+        "New certificate stored in: <b><magenta>{}</></b>",
+        dir.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,68 @@
+export const channelEvents = () => {
+    const authStore = useAuth()
+    let controller: AbortController | null = null
+
+    /*
+    Read the Server-Sent Events from /api/events, with fetch, because EventSource can not send the auth header.
+    The connection starts again after a break.
+    */
+    async function connect(id: number, onEvent: (event: ChannelEvent) => void) {
+        close()
+
+        const abort = new AbortController()
+        controller = abort
+
+        while (!abort.signal.aborted) {
+            try {
+                const response = await fetch(`/api/events/${id}`, {
+                    headers: authStore.authHeader,
+                    signal: abort.signal,
+                })
+
+                if (!response.ok || !response.body) {
+                    throw new Error(`Status ${response.status}`)
+                }
+
+                const reader = response.body.pipeThrough(new TextDecoderStream()).getReader()
+                let buffer = ''
+
+                while (true) {
+                    const { value, done } = await reader.read()
+
+                    if (done) {
+                        break
+                    }
+
+                    buffer += value
+                    const blocks = buffer.split('\n\n')
+                    buffer = blocks.pop() ?? ''
+
+                    for (const block of blocks) {
+                        const data = block
+                            .split('\n')
+                            .filter((line) => line.startsWith('data:'))
+                            .map((line) => line.slice(5).trim())
+                            .join('')
+
+                        if (data.startsWith('{')) {
+                            onEvent(JSON.parse(data))
+                        }
+                    }
+                }
+            } catch {
+                // aborted or connection lost
+            }
+
+            if (!abort.signal.aborted) {
+                await new Promise((resolve) => setTimeout(resolve, 5000))
+            }
+        }
+    }
+
+    function close() {
+        controller?.abort()
+        controller = null
+    }
+
+    return { connect, close }
+}
@@ -223,6 +223,18 @@ export const playlistOperations = () => {
                     delete item.custom_filter
                 }
 
+                if (!item.transition) {
+                    delete item.transition
+                }
+
+                if (!item.aspect_policy) {
+                    delete item.aspect_policy
+                }
+
+                if (!item.channel_map) {
+                    delete item.channel_map
+                }
+
                 if (!item.title) {
                     delete item.title
                 }
@@ -235,6 +247,10 @@ export const playlistOperations = () => {
                     delete item.enable_description
                 }
 
+                if (!item.artwork) {
+                    delete item.artwork
+                }
+
                 if (
                     begin + (item.out - item.in) >
                     configStore.playout.playlist.startInSec + configStore.playout.playlist.lengthInSec
@@ -5,6 +5,11 @@ export default {
     socketDisconnected: 'Message stream disconnected',
     alert: {
         wrongLogin: 'Incorrect login data!',
+        resetSent: 'When the user exists, a mail with a reset link was sent.',
+        resetFailed: 'Password reset failed!',
+        passwordMismatch: 'Passwords do not match!',
+        passwordChanged: 'Password changed, you can login now.',
+        passwordExpired: 'Your password has expired or must be changed, set a new one in your user profile.',
     },
     button: {
         login: 'Login',
@@ -20,6 +25,9 @@ export default {
         livebot: 'Livebot',
         configure: 'Configure',
         logout: 'Logout',
+        forgotPassword: 'Forgot password?',
+        sendLink: 'Send Link',
+        setPassword: 'Set Password',
     },
     error: {
         notFound: 'Page not found',
@@ -28,6 +36,9 @@ export default {
     input: {
         username: 'Username',
         password: 'Password',
+        usernameOrMail: 'Username or mail',
+        newPassword: 'New password',
+        confirmPassword: 'Confirm password',
     },
     system: {
         cpu: 'CPU',
@@ -44,10 +55,19 @@ export default {
         device: 'Device',
         size: 'Size',
         used: 'Used',
+        quota: 'Quota',
+        quotaUsed: 'Quota Used',
+        decklink: 'Decklink Cards',
+        gpu: 'GPU',
+        encoders: 'Encoders',
+        channels: 'Channels',
     },
     control: {
         noClip: 'No clip is playing',
         ingest: 'Live Ingest',
+        ingestPreview: 'Ingest Preview',
+        programPreview: 'Program Preview',
+        maintenance: 'Maintenance mode: no alerts and no automatic starts',
         start: 'Start Playout Service',
         last: 'Jump to last Clip',
         stop: 'Stop Playout Service',
@@ -68,6 +88,8 @@ export default {
         edit: 'Edit',
         description: 'Description',
         enable_description: "Enable description",
+        descriptionMarkdown: 'Markdown is supported.',
+        artwork: 'Artwork (path in storage)',
         delete: 'Delete',
         copy: 'Copy Playlist',
         loop: 'Loop Clips in Playlist',
@@ -82,6 +104,9 @@ export default {
         addEdit: 'Add/Edit Source',
         audio: 'Audio',
         customFilter: 'Custom Filter',
+        aspectPolicy: 'Aspect Policy',
+        aspectDefault: 'from config',
+        channelMap: 'Channel Map',
         deleteFrom: 'Delete program from',
         deleteSuccess: 'Playlist deleted...',
         generateProgram: 'Generate Program',
@@ -92,6 +117,10 @@ export default {
         shift: 'Shift',
         all: 'All',
         addBlock: 'Add time block',
+        maxSeriesRun: 'Same series',
+        maxSeriesRunHelp: 'Clips from the same series folder in a row, 0 is unlimited',
+        minRepeatHours: 'Repeat after',
+        minRepeatHoursHelp: 'Hours until the same clip plays again, also across days',
         infinitInfo: 'Playout runs in infinite mode. No time based information is possible.',
         generateDone: 'Generate Playlist done...',
         dateYesterday: 'Current time is before the playlist start time!',
@@ -148,6 +177,7 @@ export default {
     log: {
         download: 'Download log file',
         reload: 'Reload',
+        live: 'Live log',
     },
     advanced: {
         title: 'Advanced Configuration',
@@ -167,6 +197,16 @@ export default {
         name: 'Name',
         previewUrl: 'Preview URL',
         extensions: 'Extra Extensions',
+        publicHeaders: "Custom headers for public files (one 'Name: value' per line)",
+        publicEmbed: 'Public embed player',
+        publicNowPlaying: 'Public now playing API (/api/nowplaying/ID)',
+        nowPlayingToken: 'Token for the now playing API (empty for open access)',
+        maintenance: 'Maintenance mode (no alerts and no automatic starts)',
+        startPriority: 'Start priority at boot (higher starts first)',
+        cpuAffinity: 'CPU affinity of the ffmpeg processes (empty for all CPUs)',
+        niceLevel: 'Nice level of the ffmpeg processes (-20 to 19)',
+        memoryLimit: 'Memory limit of the ffmpeg processes in GB (0 for no limit)',
+        gpu: "GPU for hardware encoders: empty for none, 'auto' or the GPU index",
         save: 'Save',
         delete: 'Delete',
         updateChannelSuccess: 'Update channel config success!',
@@ -190,40 +230,124 @@ export default {
         help: 'Help',
         generalHelp: 'Sometimes it can happen that a file is corrupt but still playable. This can produce a streaming error for all following files. The only solution in this case is to stop ffplayout and start it again.',
         stopThreshold: 'The threshold stops ffplayout if it is asynchronous in time above this value. A number below 3 can cause unexpected errors.',
+        airHours:
+            'Broadcast hours of the channel, like 06:00 to 24:00. Outside of them the engine plays a slate (image or video, empty uses the dead-air fallback), or switches the channel off and on again.',
+        scripting:
+            'Run the Lua scripts of this channel, when a playlist is generated, the next clip is prepared or a gap needs a filler.',
         mailHelp: `Send error messages to an email address, such as missing clips, missing or invalid playlist format, etc.. Leave the recipient blank if you don't need this.`,
         mailInterval: 'The interval refers to the number of seconds until a new email is sent; the value must be in increments of 10 and not lower then 30 seconds.',
+        notifiers: 'Notifications',
+        notifiersHelp: 'Send error messages to Telegram, Discord or Slack. Telegram needs a bot token and a chat ID, Discord and Slack a webhook URL. Each service has its own level.',
+        notifierAdd: 'Add Service',
+        repeatLimit: 'Seconds until an identical message is sent again, 0 sends every message.',
         logHelp: 'Adjust logging behavior.',
         logDetect: 'Logs an error message if the audio line is silent for 15 seconds during the validation process.',
         logIgnore: 'Ignore strings that contain matched lines; the format is a semicolon-separated list.',
+        logLevel: 'Log level of this channel, changes apply without restart.',
+        logLevelServer: 'server level',
+        logRetention:
+            'Days to keep the log files (0 uses the server setting), size in MB for an additional rotation (0 rotates only daily) and gzip compression of rotated files. Compressed files can not be opened in the log viewer.',
         processingHelp: 'Default processing for all clips ensures uniqueness.',
         processingLogoPath: 'The logo is used only if the path exists; the path is relative to the storage folder.',
         processingLogoScale: `Leave logo scale blank if no scaling is needed. The format is 'width:height', for example: '100:-1' for proportional scaling.`,
         processingLogoPosition: `Position is specified in the format 'x:y'`,
+        processingLogos:
+            'List of additional logos as JSON. Fields: path, position, scale, opacity, days (1-7, monday is 1), start, end (HH:MM:SS) and ingest (only during live ingest). GIF, APNG and image sequences like logo_%03d.png are supported.',
+        processingLogosInvalid: 'Scheduled logos are not valid JSON!',
         processingAudioTracks: 'Specify how many audio tracks should be processed.',
         processingAudioIndex: 'Which audio line to use, -1 for all.',
-        processingAudioChannels: 'Set the audio channel count, if audio has more channels than stereo.',
+        processingAudioMap:
+            'List of output audio tracks as JSON, like main and audio description. Fields: language (ISO 639 code of the source track), name (label in the HLS master playlist) and source (track index, when no track has the language). Overrides audio tracks and audio track index.',
+        processingAudioMapInvalid: 'Audio track map is not valid JSON!',
+        processingAudioChannels: 'Channel layout of the output. Sources with another layout are converted, missing audio tracks are filled with silence in this layout.',
+        processingAudioDownmix:
+            "Downmix of 5.1 sources for stereo output: 'auto' uses the ffmpeg default, 'itu' mixes center and surround at -3 dB (ITU-R BS.775), 'front' uses only front and center. A playlist item can set its own channel map.",
         processingCustomFilter: 'Add custom filters to the processing. The filter outputs must end with [c_v_out] for video filters and [c_a_out] for audio filters.',
-        processingVTTEnable: 'VTT can only be used in HLS mode and only if there are *.vtt files with the same name as the video file.',
+        processingVTTEnable: 'VTT can only be used in HLS mode. Subtitles come from the playlist item, from *.vtt or *.srt files with the same name as the video file, or from the first embedded subtitle track.',
         processingVTTDummy: 'A placeholder is needed if there is no vtt file.',
+        processingPassthrough:
+            'Copy clips in the house format without encoding, all other clips are encoded to the house format. Logo, text, transitions and custom filters turn it off.',
+        processingSubtitleBurn: 'Burn the subtitles into the video, instead of passing them through as WebVTT. Works in all output modes.',
+        processingTransition:
+            "Transition between clips: 'fade' fades to black and silence, 'audio' fades only the sound, 'crossfade' blends the end of a clip with the begin of the next one. Length in frames, can be overridden per playlist item.",
+        processingAspectPolicy:
+            "Clips with another aspect ratio: 'pad' adds black bars, 'crop' cuts the picture, 'stretch' distorts it to the channel size, 'blur' shows it over a blurred and zoomed copy. Can be overridden per playlist item.",
+        processingTransitionAudio: 'Length of the audio fade in frames, 0 uses the video length.',
+        processingDeadAir:
+            'Seconds of frozen picture or silence, until the current clip is replaced by the fallback. An alert is sent by mail. 0 disables the detection.',
+        processingDeadAirFallback:
+            'Image, video or stream url (for example a backup SRT feed), relative to storage or absolute. Empty plays filler.',
+        processingDeadAirAudio: 'Optional audio file, which plays together with a fallback image.',
+        processingAudioMeter:
+            'Measure the loudness (EBU R128) and the peak levels of the first audio track, from the clips and the live ingest on air. The levels are in the status WebSocket, for VU meters. Applies with the next clip.',
+        houseFormat:
+            'Format of transcoded files and proxies, picture size and frame rate are from the processing settings. The loudness is normalized to the LUFS value, 0 disables the normalization.',
         ingestHelp: `Run a server for an ingest stream. This stream will override the normal streaming until it is finished. There is only a very simple authentication mechanism, which checks if the stream name is correct.`,
         ingestCustomFilter: 'Apply a custom filter to the Ingest stream in the same way as in the Processing section.',
+        ingestRotateKeys: 'Rotate Stream Keys',
+        ingestRotateKeysSuccess: 'New stream keys are generated. Old ingest key is still valid for a short time.',
+        ingestAuth: 'One stream key per line. The key from the ingest url is always valid. The RTMP server in front of the ingest (nginx-rtmp or SRS) calls /ingest/ID/auth on publish, addresses outside the list are rejected; an empty list allows all.',
+        ingestPreview: 'Low latency HLS preview of the incoming stream at /ID/preview/ingest.m3u8, also when the ingest is not on air. It can be shown in the player on the control page.',
+        ingestWhip: 'WHIP endpoint of the WebRTC gateway (MediaMTX or SRS), which pushes the stream to the ingest url. Contributors publish to /ID/whip with the stream key as bearer token.',
         playlistHelp: 'Playlist handling.',
         playlistDayStart: 'At what time the playlist should start; leave it blank if the playlist should always start at the beginning.',
         playlistLength: 'Target length of the playlist; when it is blank, the real length will not be considered.',
         playlistInfinit: 'Loop a single playlist file infinitely.',
+        playlistCheck: 'Minutes before the day rollover, in which the playlist from the next day is checked. When it is missing or invalid, an alert is sent. 0 disables the check.',
+        playlistFallback: 'Fallback for a missing playlist: none plays filler, copy uses the playlist from the current day, generate creates a playlist from storage or template.',
         storageHelp: 'Storage settings, locations are relative to channel storage.',
         storageFiller: 'Use filler to play in place of a missing file or to fill the remaining time to reach a total of 24 hours. It can be a file or folder, with relative path, and will loop when necessary.',
+        storageFillerRepeat: 'How often one filler clip can play per day. When all clips reached the limit, the least played are used again. 0 is unlimited.',
+        storageFillerFit: 'Prefer filler clips which fit completely into the gap, instead of cutting them.',
+        storageFillerExclude: 'Sub folders of the filler folder, which are not used as filler. Separated by semicolon.',
         storageExtension: 'Specify which files to search and use.',
         storageShuffle: 'Pick files randomly (in folder mode and playlist generation).',
+        storageArtwork: 'Poster lookup for the program by clip title. First in the artwork folder (relative to storage), then with the API. The API must return JSON, the field is a JSON pointer to the image url.',
+        storageTrash: 'Days until removed files are deleted from the trash. 0 deletes files directly.',
+        storageInline: 'Show media files in the browser, with seeking in the preview. Otherwise they are downloaded.',
+        watchFolder:
+            'New files in the watch folder are checked and moved to the target folder in the storage. Optional they are transcoded to the house format and added to the end of the current playlist. Invalid files are moved to ".failed".',
+        objectStore:
+            'Media in S3 compatible object storage. The browser lists the bucket, uploads are sent to it and upcoming clips are downloaded to the channel storage before they air. Downloads are removed, when the cache is larger than its size (0 is unlimited).',
+        pullIngest:
+            'Pull playlists and media from a FTP or SFTP server. New media files are saved in the target folder, then playlists are imported: JSON playlists with their date, M3U, TXT and CSV files with the date in the file name (YYYY-MM-DD). Imported playlists replace the existing ones. After each run with changes a report is sent to the mail recipient and the notifiers.',
+        preCache: 'Copy the clips of the next minutes from slow storage, like NFS or SMB mounts, to a fast local folder. Clips play from the copy, when it is ready. The least used copies are removed, when the cache is larger than its size (0 is unlimited).',
         textHelp: 'Overlay text in combination with libzmq for remote text manipulation.',
         textFont: 'Relative path to channel storage.',
         textFromFile: 'Extraction of text from a filename.',
         textStyle: 'Define the drawtext parameters, such as position, color, etc. Posting text over the API will override this.',
         textRegex: 'Format file names to extract a title from them.',
+        textWidgets:
+            'Clock, countdown to the next program and a ticker with text from an API endpoint (plain text or JSON list) or a RSS feed. The countdown skips clips with the given categories. Style are drawtext parameters, like position, font size and color.',
+        textOverlay:
+            'Images from the storage or HTML pages as overlay, which are shown and hidden over the control API. HTML pages are rendered with a headless Chromium compatible browser.',
         taskHelp: 'Run an external program with a given media object. The media object is in JSON format and contains all the information about the current clip. The external program can be a script or a binary, but it should only run for a short time.',
         taskPath: 'Path to executable.',
         outputHelp: `The final playout encoding, set the settings according to your needs. Use 'stream' mode and adjust the 'Output Parameter' when you want to stream to an RTMP/RTSP/SRT/... server.
         In production, don't serve HLS playlists with ffplayout; use Nginx or another web server!`,
+        outputBackup:
+            'Output parameters for a backup encoder. It runs parallel to the main encoder and takes over, when the main encoder fails. Leave empty to disable.',
+        outputRadio:
+            'Audio only web radio to an Icecast or SHOUTcast server. It replaces the output parameters and decodes the clips without video. The title of the running clip is sent as now playing. Audio HLS writes also radio/radio.m3u8 to the public folder.',
+        outputDecklink:
+            'SDI output to a Blackmagic Decklink card. It replaces the output parameters and sets the processing resolution and frame rate to the format. Audio Map is a comma separated list with the source of each SDI channel as track.channel, like 0.0, 0.1, 1.0, 1.1; an empty entry is silence. Timing Offset is the genlock offset in pixels. The system page lists the installed cards.',
+        outputScte35: 'Write SCTE-35 markers for ad breaks (playlist item "ad_break") to the HLS playlists.',
+        outputItemMetadata:
+            'Write title and category of the playing item as timed metadata (EXT-X-DATERANGE) to the HLS playlists, for players and analytics.',
+        outputRating:
+            'Write the parental rating of the running item (playlist item "rating") to the HLS playlists. Fields as JSON: system (like MPAA or FSK), default (for items without rating) and mapping (item rating to channel rating). The program and XMLTV export use the same mapping.',
+        outputRatingInvalid: 'Parental rating is not valid JSON!',
+        outputBroadcast:
+            "For broadcast chains: 'tff' or 'bff' makes interlaced output, with more than 30 fps two frames become one interlaced frame (50p to 1080i25), otherwise the frames are only flagged. BT.709 converts and flags the colors in limited range, the legalizer clips luma to 16-235 and chroma to 16-240. Not in decklink mode the encoder flags are added to the output parameters.",
+        outputOffline:
+            'Write a slate into the HLS output, while the channel is stopped, so viewers see that it is offline instead of a frozen picture. Image and audio are relative to the storage, without image the slate is black, without audio silent. The slate stops, when the channel starts again.',
+        outputPreview:
+            'Encode a low bitrate copy of the program for monitoring, at /ID/preview/program.m3u8. It gets the same stream as the channel encoder, overlays from the encoder, like live text, are not in it. With a gateway url (RTMP or RTSP), like from MediaMTX, the preview is pushed there too, for WebRTC. The preview drops data when it is too slow and never holds up the output. Changes apply with the next clip. It can be shown in the player on the control page.',
+        outputAlarms:
+            'Send an alarm to the log and the mail notifiers, when the program output has silence or black picture longer than the seconds, 0 disables the check. After an alarm, the next alarm of the same kind waits for the cooldown and is only logged until then. Changes apply with the next clip.',
+        outputDelayed:
+            'Play the HLS output again after the delay, like a +1h channel. The delayed stream is in the delayed folder of the public folder, with output parameters it is pushed to a server too. The buffer is on disk and starts empty with the channel.',
+        outputS3: 'Upload HLS segments and playlists to S3 compatible storage. Expired segments are deleted from the bucket.',
         outputParam: 'HLS segment and playlist paths are relative.',
         restartTile: 'Restart Playout',
         restartText: 'Restart ffplayout to apply changes?',
@@ -234,6 +358,8 @@ export default {
         publicPath: 'Public (HLS) Path',
         playlistPath: 'Playlist Path',
         storagePath: 'Storage Path',
+        storageQuota: 'Storage Quota (GB, 0 = unlimited)',
+        storageUsed: 'Used',
         sharedStorage: 'ffplayout runs inside a container, use the same storage root for all channels!',
     },
     user: {
@@ -247,6 +373,7 @@ export default {
         confirmPass: 'Confirm Password',
         save: 'Save',
         admin: 'Admin',
+        permissions: 'Permissions (empty: defaults from the role)',
         deleteNotPossible: 'Delete current user not possible!',
         deleteSuccess: 'Delete user done!',
         deleteError: 'Delete user error',
@@ -255,5 +382,16 @@ export default {
         mismatch: 'Password mismatch!',
         updateSuccess: 'Update user profile success!',
         updateFailed: 'Update user profile failed!',
+        mustChange: 'Change password at next login',
+        policy: 'Password Policy',
+        policyHelp: 'Rules for new passwords. History: count of last passwords which can not be reused. Max age: days until a password expires, 0 for never.',
+        minLength: 'Min length',
+        history: 'History',
+        maxAge: 'Max age',
+        uppercase: 'Uppercase letter',
+        lowercase: 'Lowercase letter',
+        digit: 'Digit',
+        symbol: 'Symbol',
+        policySaved: 'Password policy saved',
     },
 }
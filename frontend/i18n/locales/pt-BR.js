@@ -5,6 +5,11 @@ export default {
     socketDisconnected: 'Aviso! stream desconectado.',
     alert: {
         wrongLogin: 'Dados incorretos!',
+        resetSent: 'Se o usuário existir, um e-mail com o link de redefinição foi enviado.',
+        resetFailed: 'Falha ao redefinir a senha!',
+        passwordMismatch: 'As senhas não coincidem!',
+        passwordChanged: 'Senha alterada, agora você pode entrar.',
+        passwordExpired: 'Sua senha expirou ou precisa ser alterada, defina uma nova no seu perfil de usuário.',
     },
     button: {
         login: 'Logar',
@@ -20,6 +25,9 @@ export default {
         livebot: 'Livebot',
         configure: 'Configurar',
         logout: 'Sair',
+        forgotPassword: 'Esqueceu a senha?',
+        sendLink: 'Enviar Link',
+        setPassword: 'Definir Senha',
     },
     error: {
         notFound: 'Página não encontrada',
@@ -28,6 +36,9 @@ export default {
     input: {
         username: 'Usuário',
         password: 'Senha',
+        usernameOrMail: 'Usuário ou e-mail',
+        newPassword: 'Nova senha',
+        confirmPassword: 'Confirmar senha',
     },
     system: {
         cpu: 'CPU',
@@ -44,10 +55,19 @@ export default {
         device: 'Dispositivo',
         size: 'Tamanho total',
         used: 'Disponível',
+        quota: 'Cota',
+        quotaUsed: 'Cota usada',
+        decklink: 'Placas Decklink',
+        gpu: 'GPU',
+        encoders: 'Encoders',
+        channels: 'Canais',
     },
     control: {
         noClip: 'Nenhum clipe está sendo reproduzido',
         ingest: 'Ingestão ao Vivo',
+        ingestPreview: 'Prévia da ingestão',
+        maintenance: 'Modo de manutenção: sem alertas e sem inícios automáticos',
+        programPreview: 'Prévia do programa',
         start: 'Iniciar Serviço do Playout',
         last: 'Ir para o último Clipe',
         stop: 'Parar Serviço do Playout',
@@ -68,6 +88,8 @@ export default {
         edit: 'Editar',
         description: 'Descrição',
         enable_description: "Habilitar descrição",
+        descriptionMarkdown: 'Markdown é suportado.',
+        artwork: 'Arte (caminho no armazenamento)',
         delete: 'Deletar',
         copy: 'Copiar playlist',
         loop: 'Repetir Clipes na Playlist',
@@ -82,6 +104,9 @@ export default {
         addEdit: 'Adicionar/Editar Fonte',
         audio: 'Áudio',
         customFilter: 'Filtro Personalizado',
+        aspectPolicy: 'Política de Aspecto',
+        aspectDefault: 'da configuração',
+        channelMap: 'Mapa de Canais',
         deleteFrom: 'Excluir programação de',
         deleteSuccess: 'Lista de reprodução excluída...',
         generateProgram: 'Gerar Programação',
@@ -92,6 +117,10 @@ export default {
         shift: 'Diferença horária',
         all: 'Todos',
         addBlock: 'Adicionar bloco de tempo',
+        maxSeriesRun: 'Mesma série',
+        maxSeriesRunHelp: 'Clipes da mesma pasta de série em sequência, 0 é ilimitado',
+        minRepeatHours: 'Repetir após',
+        minRepeatHoursHelp: 'Horas até o mesmo clipe tocar novamente, também entre dias',
         infinitInfo: 'O playout é executado no modo infinito. Nenhuma informação baseada em tempo é possível',
         generateDone: 'Gerar lista de reprodução concluída...',
         dateYesterday: 'A hora atual é anterior à hora de início da lista de reprodução!',
@@ -148,6 +177,7 @@ export default {
     log: {
         download: 'Baixar arquivo de registro',
         reload: 'Recarregar',
+        live: 'Registro ao vivo',
     },
     advanced: {
         title: 'Configurações avançadas',
@@ -167,6 +197,16 @@ export default {
         name: 'Nome',
         previewUrl: 'URL de Visualização',
         extensions: 'Extensões Extras',
+        publicHeaders: "Cabeçalhos personalizados para arquivos públicos (um 'Nome: valor' por linha)",
+        publicEmbed: 'Player público incorporável',
+        publicNowPlaying: 'API pública de "tocando agora" (/api/nowplaying/ID)',
+        nowPlayingToken: 'Token para a API de "tocando agora" (vazio para acesso livre)',
+        maintenance: 'Modo de manutenção (sem alertas e sem inícios automáticos)',
+        startPriority: 'Prioridade de início no boot (maior inicia primeiro)',
+        cpuAffinity: 'Afinidade de CPU dos processos ffmpeg (vazio para todas as CPUs)',
+        niceLevel: 'Nível nice dos processos ffmpeg (-20 a 19)',
+        memoryLimit: 'Limite de memória dos processos ffmpeg em GB (0 para sem limite)',
+        gpu: "GPU para encoders de hardware: vazio para nenhuma, 'auto' ou o índice da GPU",
         save: 'Salvar',
         delete: 'Deletar',
         updateChannelSuccess: 'Atualização da configuração do canal bem-sucedida!',
@@ -190,39 +230,123 @@ export default {
         help: 'Ajuda',
         generalHelp: 'Às vezes pode acontecer de um arquivo estar corrompido, mas ainda ser reproduzível. Isso pode causar um erro de streaming para todos os arquivos seguintes. A única solução nesse caso é parar o ffplayout e reiniciá-lo.',
         stopThreshold: 'O limite para o ffplayout se ele estiver fora de sincronia acima deste valor. Um número abaixo de 3 pode causar erros inesperados.',
+        airHours:
+            'Horário de transmissão do canal, como 06:00 até 24:00. Fora dele o engine exibe uma tela (imagem ou vídeo, vazio usa o fallback de dead-air), ou desliga o canal e liga novamente.',
+        scripting:
+            'Executa os scripts Lua deste canal, quando uma playlist é gerada, o próximo clipe é preparado ou uma lacuna precisa de um filler.',
         mailHelp: `Envie mensagens de erro para um endereço de e-mail, como clipes ausentes, formato de playlist ausente ou inválido, etc. Deixe o destinatário em branco se não precisar disso.`,
         mailInterval: 'O intervalo se refere ao número de segundos até o envio de um novo e-mail; o valor deve ser em incrementos de 10 e não inferior a 30 segundos.',
+        notifiers: 'Notificações',
+        notifiersHelp: 'Envie mensagens de erro para Telegram, Discord ou Slack. O Telegram precisa de um token de bot e um ID de chat, Discord e Slack uma URL de webhook. Cada serviço tem seu próprio nível.',
+        notifierAdd: 'Adicionar Serviço',
+        repeatLimit: 'Segundos até que uma mensagem idêntica seja enviada novamente, 0 envia todas as mensagens.',
         logHelp: 'Ajuste o comportamento de log.',
         logDetect: 'Registra uma mensagem de erro se a linha de áudio estiver em silêncio por 15 segundos durante o processo de validação.',
         logIgnore: 'Ignorar strings que contenham linhas correspondentes; o formato é uma lista separada por ponto e vírgula.',
+        logLevel: 'Nível de log deste canal, as alterações são aplicadas sem reiniciar.',
+        logLevelServer: 'nível do servidor',
+        logRetention:
+            'Dias para manter os arquivos de log (0 usa a configuração do servidor), tamanho em MB para uma rotação adicional (0 rotaciona apenas diariamente) e compressão gzip dos arquivos rotacionados. Arquivos comprimidos não podem ser abertos no visualizador de logs.',
         processingHelp: 'O processamento padrão para todos os clipes garante a exclusividade.',
         processingLogoPath: 'O logotipo só é usado se o caminho existir; o caminho é relativo à pasta de armazenamento.',
         processingLogoScale: `Deixe a escala do logotipo em branco se não for necessário escalonamento. O formato é 'largura:altura', por exemplo: '100:-1' para escalonamento proporcional.`,
         processingLogoPosition: `A posição é especificada no formato 'x:y'.`,
+        processingLogos:
+            'Lista de logos adicionais em JSON. Campos: path, position, scale, opacity, days (1-7, segunda é 1), start, end (HH:MM:SS) e ingest (somente durante ingest ao vivo). GIF, APNG e sequências de imagens como logo_%03d.png são suportados.',
+        processingLogosInvalid: 'Os logos agendados não são um JSON válido!',
         processingAudioTracks: 'Especifique quantas faixas de áudio devem ser processadas.',
         processingAudioIndex: 'Qual linha de áudio usar, -1 para todas.',
-        processingAudioChannels: 'Defina a contagem de canais de áudio, se o áudio tiver mais canais do que estéreo.',
+        processingAudioMap:
+            'Lista das faixas de áudio da saída em JSON, como principal e audiodescrição. Campos: language (código ISO 639 da faixa de origem), name (rótulo na playlist master HLS) e source (índice da faixa, quando nenhuma faixa tem o idioma). Substitui faixas de áudio e índice da faixa de áudio.',
+        processingAudioMapInvalid: 'O mapa de faixas de áudio não é um JSON válido!',
+        processingAudioChannels: 'Layout de canais da saída. Fontes com outro layout são convertidas, faixas de áudio ausentes são preenchidas com silêncio neste layout.',
+        processingAudioDownmix:
+            "Downmix de fontes 5.1 para saída estéreo: 'auto' usa o padrão do ffmpeg, 'itu' mistura centro e surround a -3 dB (ITU-R BS.775), 'front' usa apenas frente e centro. Um item da playlist pode definir seu próprio mapa de canais.",
         processingCustomFilter: 'Adicione filtros personalizados ao processamento. As saídas de filtro devem terminar com [c_v_out] para filtros de vídeo e [c_a_out] para filtros de áudio.',
-        processingVTTEnable: 'VTT só pode ser usado no modo HLS e apenas se houver arquivos *.vtt com o mesmo nome do arquivo de vídeo.',
+        processingVTTEnable: 'VTT só pode ser usado no modo HLS. As legendas vêm do item da playlist, de arquivos *.vtt ou *.srt com o mesmo nome do arquivo de vídeo, ou da primeira faixa de legenda embutida.',
         processingVTTDummy: 'Um espaço reservado é necessário se não houver arquivo vtt.',
+        processingPassthrough:
+            'Copia clipes no formato da casa sem codificação, todos os outros clipes são codificados no formato da casa. Logo, texto, transições e filtros personalizados o desativam.',
+        processingSubtitleBurn: 'Grava as legendas no vídeo, em vez de repassá-las como WebVTT. Funciona em todos os modos de saída.',
+        processingTransition:
+            "Transição entre clipes: 'fade' escurece até o preto e silêncio, 'audio' suaviza apenas o som, 'crossfade' mistura o fim de um clipe com o início do próximo. Duração em quadros, pode ser sobrescrita por item da playlist.",
+        processingAspectPolicy:
+            "Clipes com outra proporção: 'pad' adiciona barras pretas, 'crop' corta a imagem, 'stretch' distorce para o tamanho do canal, 'blur' mostra sobre uma cópia desfocada e ampliada. Pode ser sobrescrita por item da playlist.",
+        processingTransitionAudio: 'Duração do fade de áudio em quadros, 0 usa a duração do vídeo.',
+        processingDeadAir:
+            'Segundos de imagem congelada ou silêncio, até o clipe atual ser substituído pela alternativa. Um alerta é enviado por e-mail. 0 desativa a detecção.',
+        processingDeadAirFallback:
+            'Imagem, vídeo ou url de stream (por exemplo um feed SRT de backup), relativo ao armazenamento ou absoluto. Vazio reproduz o preenchimento.',
+        processingDeadAirAudio: 'Arquivo de áudio opcional, que toca junto com uma imagem alternativa.',
+        processingAudioMeter:
+            'Mede a loudness (EBU R128) e os níveis de pico da primeira faixa de áudio, dos clipes e da ingestão ao vivo no ar. Os níveis estão no WebSocket de status, para medidores VU. Vale a partir do próximo clipe.',
+        houseFormat:
+            'Formato dos arquivos transcodificados e proxies, tamanho da imagem e taxa de quadros vêm das configurações de processamento. A intensidade sonora é normalizada para o valor LUFS, 0 desativa a normalização.',
         ingestHelp: `Execute um servidor para um fluxo de ingestão. Este fluxo substituirá o streaming normal até que termine. Há apenas um mecanismo de autenticação simples que verifica se o nome do fluxo está correto.`,
         ingestCustomFilter: 'Aplique um filtro personalizado ao fluxo de ingestão da mesma forma que na seção de Processamento.',
+        ingestRotateKeys: 'Renovar chaves de transmissão',
+        ingestRotateKeysSuccess: 'Novas chaves de transmissão foram geradas. A chave de ingestão antiga ainda é válida por pouco tempo.',
+        ingestAuth: 'Uma chave de transmissão por linha. A chave da url de ingestão é sempre válida. O servidor RTMP na frente da ingestão (nginx-rtmp ou SRS) chama /ingest/ID/auth ao publicar, endereços fora da lista são rejeitados; uma lista vazia permite todos.',
+        ingestPreview: 'Prévia HLS de baixa latência do fluxo recebido em /ID/preview/ingest.m3u8, também quando a ingestão não está no ar. Ela pode ser exibida no player da página de controle.',
+        ingestWhip: 'Endpoint WHIP do gateway WebRTC (MediaMTX ou SRS), que envia o fluxo para a url de ingestão. Os colaboradores publicam em /ID/whip com a chave de transmissão como token bearer.',
         playlistHelp: 'Gerenciamento de playlist.',
         playlistDayStart: 'A que horas a playlist deve começar; deixe em branco se a playlist sempre começar do início.',
         playlistLength: 'Duração alvo da playlist; quando estiver em branco, o comprimento real não será considerado.',
         playlistInfinit: 'Reproduza infinitamente um único arquivo de playlist.',
+        playlistCheck: 'Minutos antes da virada do dia, em que a playlist do dia seguinte é verificada. Se estiver ausente ou inválida, um alerta é enviado. 0 desativa a verificação.',
+        playlistFallback: 'Alternativa para uma playlist ausente: none reproduz o preenchimento, copy usa a playlist do dia atual, generate cria uma playlist a partir do armazenamento ou template.',
         storageHelp: 'Configurações de armazenamento, os locais são relativos ao armazenamento do canal.',
         storageFiller: 'Use um preenchimento para reproduzir no lugar de um arquivo ausente ou preencher o tempo restante para alcançar um total de 24 horas. Pode ser um arquivo ou uma pasta com caminho relativo, e será repetido quando necessário.',
+        storageFillerRepeat: 'Quantas vezes um clipe de preenchimento pode tocar por dia. Quando todos os clipes atingirem o limite, os menos tocados são usados novamente. 0 é ilimitado.',
+        storageFillerFit: 'Preferir clipes de preenchimento que cabem completamente no intervalo, em vez de cortá-los.',
+        storageFillerExclude: 'Subpastas da pasta de preenchimento que não são usadas como preenchimento. Separadas por ponto e vírgula.',
         storageExtension: 'Especifique quais arquivos procurar e usar.',
         storageShuffle: 'Escolha arquivos aleatoriamente (no modo de pasta e geração de playlist).',
+        storageArtwork: 'Busca de pôsteres para a programação pelo título do clipe. Primeiro na pasta de artes (relativa ao armazenamento), depois pela API. A API deve retornar JSON, o campo é um JSON pointer para a url da imagem.',
+        storageTrash: 'Dias até os arquivos removidos serem apagados da lixeira. 0 apaga os arquivos diretamente.',
+        storageInline: 'Mostra arquivos de mídia no navegador, com busca na pré-visualização. Caso contrário, são baixados.',
+        watchFolder:
+            'Novos arquivos na pasta monitorada são verificados e movidos para a pasta de destino no armazenamento. Opcionalmente são transcodificados para o formato padrão do canal e adicionados ao final da playlist atual. Arquivos inválidos são movidos para ".failed".',
+        objectStore:
+            'Mídias em armazenamento de objetos compatível com S3. O navegador lista o bucket, os uploads são enviados para ele e os próximos clipes são baixados para o armazenamento do canal antes de irem ao ar. Os downloads são removidos quando o cache passa do seu tamanho (0 é ilimitado).',
+        pullIngest:
+            'Baixa playlists e mídias de um servidor FTP ou SFTP. Novos arquivos de mídia são salvos na pasta de destino, depois as playlists são importadas: playlists JSON com sua data, arquivos M3U, TXT e CSV com a data no nome do arquivo (AAAA-MM-DD). Playlists importadas substituem as existentes. Após cada execução com alterações, um relatório é enviado ao destinatário de e-mail e aos notificadores.',
+        preCache: 'Copia os clipes dos próximos minutos de um armazenamento lento, como montagens NFS ou SMB, para uma pasta local rápida. Os clipes são reproduzidos da cópia, quando ela está pronta. As cópias menos usadas são removidas, quando o cache é maior que o seu tamanho (0 é ilimitado).',
         textHelp: 'Sobrepor texto em combinação com libzmq para manipulação remota de texto.',
         textFont: 'Caminho relativo ao armazenamento do canal.',
         textFromFile: 'Extração de texto a partir de um nome de arquivo.',
         textStyle: 'Defina os parâmetros drawtext, como posição, cor, etc. Postar texto pela API substituirá isso.',
         textRegex: 'Formate nomes de arquivos para extrair um título deles.',
+        textWidgets:
+            'Relógio, contagem regressiva para o próximo programa e um letreiro com texto de um endpoint de API (texto simples ou lista JSON) ou de um feed RSS. A contagem regressiva ignora clipes com as categorias informadas. Estilo são parâmetros do drawtext, como posição, tamanho da fonte e cor.',
+        textOverlay:
+            'Imagens do armazenamento ou páginas HTML como sobreposição, que são exibidas e ocultadas pela API de controle. Páginas HTML são renderizadas com um navegador compatível com Chromium em modo headless.',
         taskHelp: 'Execute um programa externo com um objeto de mídia fornecido. O objeto de mídia está em formato JSON e contém todas as informações sobre o clipe atual. O programa externo pode ser um script ou binário, mas deve ser executado apenas por um curto período de tempo.',
         taskPath: 'Caminho para o executável.',
         outputHelp: `A codificação final do playout, ajuste as configurações de acordo com suas necessidades. Use o modo 'stream' e ajuste o 'Parâmetro de Saída' quando quiser fazer streaming para um servidor RTMP/RTSP/SRT/... No ambiente de produção, não sirva playlists HLS com ffplayout; use Nginx ou outro servidor web!`,
+        outputBackup:
+            'Parâmetros de saída para um encoder reserva. Ele roda em paralelo ao encoder principal e assume quando o principal falha. Deixe vazio para desativar.',
+        outputRadio:
+            'Web rádio somente com áudio para um servidor Icecast ou SHOUTcast. Substitui os parâmetros de saída e decodifica os clipes sem vídeo. O título do clipe atual é enviado como "tocando agora". HLS de áudio também grava radio/radio.m3u8 na pasta pública.',
+        outputDecklink:
+            'Saída SDI para uma placa Blackmagic Decklink. Substitui os parâmetros de saída e define a resolução e a taxa de quadros do processamento conforme o formato. Audio Map é uma lista separada por vírgulas com a origem de cada canal SDI como faixa.canal, por exemplo 0.0, 0.1, 1.0, 1.1; uma entrada vazia é silêncio. Timing Offset é o deslocamento de genlock em pixels. A página do sistema lista as placas instaladas.',
+        outputScte35: 'Grava marcadores SCTE-35 para intervalos comerciais (item de playlist "ad_break") nas playlists HLS.',
+        outputItemMetadata:
+            'Grava título e categoria do item em execução como metadados temporizados (EXT-X-DATERANGE) nas playlists HLS, para players e análises.',
+        outputRating:
+            'Grava a classificação indicativa do item em execução (item de playlist "rating") nas playlists HLS. Campos em JSON: system (como MPAA ou ClassInd), default (para itens sem classificação) e mapping (classificação do item para a classificação do canal). A programação e o export XMLTV usam o mesmo mapeamento.',
+        outputRatingInvalid: 'A classificação indicativa não é um JSON válido!',
+        outputBroadcast:
+            "Para cadeias de broadcast: 'tff' ou 'bff' gera saída entrelaçada, com mais de 30 fps dois quadros viram um quadro entrelaçado (50p para 1080i25), senão os quadros são apenas sinalizados. BT.709 converte e sinaliza as cores em faixa limitada, o legalizador limita luma a 16-235 e croma a 16-240. Fora do modo decklink as flags do encoder são adicionadas aos parâmetros de saída.",
+        outputOffline:
+            'Escreve um slate na saída HLS enquanto o canal está parado, para que os espectadores vejam que ele está offline em vez de uma imagem congelada. Imagem e áudio são relativos ao armazenamento, sem imagem o slate é preto, sem áudio fica em silêncio. O slate para quando o canal inicia novamente.',
+        outputPreview:
+            'Codifica uma cópia de baixa taxa de bits do programa para monitoramento, em /ID/preview/program.m3u8. Ela recebe o mesmo stream que o encoder do canal, overlays do encoder, como texto ao vivo, não estão nela. Com uma url de gateway (RTMP ou RTSP), como do MediaMTX, o preview também é enviado para lá, para WebRTC. O preview descarta dados quando está lento demais e nunca atrasa a saída. Alterações valem a partir do próximo clipe. Ela pode ser exibida no player da página de controle.',
+        outputAlarms:
+            'Envia um alarme para o log e os notificadores de e-mail quando a saída do programa tem silêncio ou imagem preta por mais tempo que os segundos, 0 desativa a verificação. Após um alarme, o próximo alarme do mesmo tipo espera o tempo de espera e até lá é apenas registrado no log. Alterações valem a partir do próximo clipe.',
+        outputDelayed:
+            'Reproduz a saída HLS novamente após o atraso, como um canal +1h. O stream atrasado fica na pasta delayed da pasta pública, com parâmetros de saída ele também é enviado para um servidor. O buffer fica no disco e começa vazio com o canal.',
+        outputS3: 'Envia segmentos e playlists HLS para um armazenamento compatível com S3. Segmentos expirados são removidos do bucket.',
         outputParam: 'Os caminhos dos segmentos e playlists HLS são relativos.',
         restartTile: 'Reiniciar Playout',
         restartText: 'Reiniciar o ffplayout para aplicar as alterações?',
@@ -233,6 +357,8 @@ export default {
         publicPath: 'Public (HLS) Path',
         playlistPath: 'Playlist Path',
         storagePath: 'Storage Path',
+        storageQuota: 'Cota de armazenamento (GB, 0 = ilimitado)',
+        storageUsed: 'Usado',
         sharedStorage: 'O ffplayout é executado dentro de um contêiner; use a mesma raiz de armazenamento para todos os canais!',
     },
     user: {
@@ -246,6 +372,7 @@ export default {
         confirmPass: 'Confirmar Senha',
         save: 'Salvar',
         admin: 'Administrador',
+        permissions: 'Permissões (vazio: padrão da função)',
         deleteNotPossible: 'Excluir o usuário atual não é possível!',
         deleteSuccess: 'Usuário deletado com sucesso!',
         deleteError: 'Erro ao deletar usuário',
@@ -254,5 +381,16 @@ export default {
         mismatch: 'Senhas não coincidem!',
         updateSuccess: 'Atualização do perfil do usuário bem-sucedida! ',
         updateFailed: 'Atualização do perfil do usuário falhou!',
+        mustChange: 'Alterar senha no próximo login',
+        policy: 'Política de Senha',
+        policyHelp: 'Regras para novas senhas. Histórico: quantidade das últimas senhas que não podem ser reutilizadas. Idade máxima: dias até a senha expirar, 0 para nunca.',
+        minLength: 'Tamanho mínimo',
+        history: 'Histórico',
+        maxAge: 'Idade máxima',
+        uppercase: 'Letra maiúscula',
+        lowercase: 'Letra minúscula',
+        digit: 'Dígito',
+        symbol: 'Símbolo',
+        policySaved: 'Política de senha salva',
     },
 }
@@ -10,6 +10,7 @@ export default defineNuxtConfig({
             '/api': { target: 'http://127.0.0.1:8787/api' },
             '/auth': { target: 'http://127.0.0.1:8787/auth' },
             '/data': { target: 'http://127.0.0.1:8787/data' },
+            '/ws': { target: 'ws://127.0.0.1:8787/ws', ws: true },
             '/live': { target: 'http://127.0.0.1:8787/live' },
             '/1/live': { target: 'http://127.0.0.1:8787/1/live' },
             '/file': { target: 'http://127.0.0.1:8787/file' },
@@ -8,10 +8,14 @@ export const useAuth = defineStore('auth', {
         authHeader: {},
         channelID: 0,
         role: '',
+        permissions: [] as string[],
+        mustChangePassword: false,
         uuid: null as null | string,
     }),
 
-    getters: {},
+    getters: {
+        can: (state) => (permission: string) => state.permissions.includes(permission),
+    },
     actions: {
         updateToken(token: string) {
             const cookie = useCookie('token', {
@@ -53,6 +57,8 @@ export const useAuth = defineStore('auth', {
                     this.isLogin = true
                     this.channelID = decodedToken.channel
                     this.role = decodedToken.role
+                    this.permissions = decodedToken.permissions ?? []
+                    this.mustChangePassword = response.user?.must_change_password ?? false
                 })
                 .catch((e) => {
                     code = e.status
@@ -87,6 +93,7 @@ export const useAuth = defineStore('auth', {
                 const expireToken = decodedToken.exp
                 this.channelID = decodedToken.channel
                 this.role = decodedToken.role
+                this.permissions = decodedToken.permissions ?? []
 
                 if (expireToken && this.jwtToken && expireToken - timestamp > 15) {
                     this.isLogin = true
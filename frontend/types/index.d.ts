@@ -9,6 +9,7 @@ declare global {
         id: number
         channel: number
         role: string
+        permissions?: string[]
     }
 
     interface PlaylistExt extends Ply {
@@ -44,6 +45,18 @@ declare global {
         playlists: string
         storage: string
         uts_offset?: number
+        public_embed?: boolean
+        public_headers?: string
+        public_nowplaying?: boolean
+        nowplaying_token?: string
+        maintenance?: boolean
+        start_priority?: number
+        cpu_affinity?: string
+        nice?: number
+        memory_limit?: number
+        gpu?: string
+        storage_quota?: number
+        storage_used?: number
     }
 
     interface User {
@@ -55,6 +68,26 @@ declare global {
         admin?: boolean
         channel_ids?: number[]
         role_id?: number
+        permissions?: string[]
+        must_change_password?: boolean
+    }
+
+    interface ChannelEvent {
+        event: 'playlist' | 'config' | 'process'
+        date?: string
+        action?: 'saved' | 'generated' | 'deleted'
+        kind?: 'playout' | 'advanced'
+        running?: boolean
+    }
+
+    interface PasswordPolicy {
+        min_length: number
+        uppercase: boolean
+        lowercase: boolean
+        digit: boolean
+        symbol: boolean
+        history: number
+        max_age: number
     }
 
     interface Crumb {
@@ -87,7 +120,16 @@ declare global {
         category?: string
         description?: string | null
         enable_description?: boolean | null
+        artwork?: string | null
+        rating?: string | null
+        subtitle?: string | null
+        audio_tracks?: number[] | null
         custom_filter?: string
+        aspect_policy?: string | null
+        channel_map?: string | null
+        transition?: { kind?: string | null; frames?: number | null; audio_frames?: number | null } | null
+        ad_break?: number | null
+        break?: string | null
         overtime?: boolean
     }
 
@@ -125,10 +167,17 @@ declare global {
         duration: string
         shuffle: boolean
         paths: string[]
+        quotas?: { [key: string]: number }
+    }
+
+    interface ScheduleRules {
+        max_series_run: number
+        min_repeat_hours: number
     }
 
     interface Template {
         sources: TemplateItem[]
+        rules: ScheduleRules
     }
 
     interface BodyObject {
@@ -141,9 +190,19 @@ declare global {
         load: { one: number; five: number; fifteen: number }
         memory: { total: number; used: number; free: number }
         network?: { name: string; current_in: number; current_out: number; total_in: number; total_out: number }
-        storage?: { path: string; total: number; used: number }
+        storage?: { path: string; total: number; used: number; quota: number; quota_used: number }
         swap: { total: number; used: number; free: number }
         system: { name?: string; kernel?: string; version?: string; ffp_version?: string }
+        decklink?: string[]
+        gpus?: {
+            index: number
+            name: string
+            memory_total: number
+            memory_used: number
+            usage: number
+            encoder_sessions: number
+            channels: number[]
+        }[]
     }
 
     interface PlayoutStatus {
@@ -156,5 +215,7 @@ declare global {
         title?: string
         description?: string
         enable_description?: boolean
+        artwork?: string
+        rating?: string
     }
 }
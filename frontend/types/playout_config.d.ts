@@ -1,18 +1,281 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type General = { stop_threshold: number, };
+/**
+ * Broadcast hours of the channel.
+ *
+ * - `enable`: the engine enforces the hours
+ * - `start`/`end`: on air window in `HH:MM`, like `06:00` to `24:00`, it can go over midnight (`18:00` to `02:00`)
+ * - `off_air`: `slate` plays the slate outside the hours, `off` stops the channel and starts it again at `start`
+ * - `slate`: image or video, relative paths are in the channel storage, empty means the dead-air fallback
+ */
+export type AirHours = { enable: boolean, start: string, end: string, off_air: OffAir, slate: string, };
 
-export type Ingest = { enable: boolean, input_param: string, custom_filter: string, };
+/**
+ * Handling of clips with another aspect ratio than the channel.
+ */
+export type AspectPolicy = "pad" | "crop" | "stretch" | "blur";
 
-export type Logging = { ffmpeg_level: string, ingest_level: string, detect_silence: boolean, ignore_lines: Array<string>, };
+/**
+ * Audio track of the output.
+ *
+ * - `language`: ISO 639 code, like `eng`; the source track with this language is mapped
+ * - `name`: label in the HLS master playlist, like `English` or `Audio Description`
+ * - `source`: source track, when no track has the language; -1 plays silence then
+ */
+export type AudioTrack = { language: string, name: string, source: number, };
+
+/**
+ * Output for traditional broadcast chains.
+ *
+ * - `field_order`: interlaced output with `tff` or `bff`; with a frame rate above 30 two frames
+ * become one interlaced frame, like 50p to 1080i25, otherwise the frames are only flagged
+ * - `bt709`: convert and flag the colors as BT.709, limited range
+ * - `legalizer`: clip luma to 16-235 and chroma to 16-240
+ */
+export type Broadcast = { field_order: FieldOrder, bt709: boolean, legalizer: boolean, };
 
-export type Mail = { show: boolean, subject: string, recipient: string, mail_level: string, interval: bigint, };
+/**
+ * Blackmagic Decklink SDI output, which replaces the output parameters in decklink mode.
+ *
+ * - `device`: name of the card, like `DeckLink Duo (1)`, the system endpoint lists the installed cards
+ * - `format`: video format, like `1080i50`, `1080p25`, `720p59.94` or `576i50`
+ * - `ten_bit`: 10 bit video (v210) instead of 8 bit (uyvy422)
+ * - `audio_channels`: 2, 8 or 16 embedded audio channels
+ * - `audio_map`: source of each SDI channel as `track.channel`, like `1.0`; empty plays silence,
+ * without a map the channels from the tracks follow each other
+ * - `timing_offset`: genlock timing offset in pixels
+ * - `duplex_mode`: `unset`, `half` or `full`
+ * - `link`: `unset`, `single`, `dual` or `quad` link SDI
+ * - `preroll`: seconds, which are buffered before the playback starts
+ */
+export type Decklink = { device: string, format: string, ten_bit: boolean, audio_channels: number, audio_map: Array<string>, timing_offset: number | null, duplex_mode: string, link: string, preroll: number, };
 
-export type Output = { mode: OutputMode, output_param: string, };
+/**
+ * Delayed copy of the HLS output, for a time shifted channel like +1h.
+ *
+ * - `delay`: seconds from the live output to the delayed output
+ * - `output_param`: ffmpeg output parameters, which push the delayed stream to a server;
+ * without parameters the delayed stream is only written as HLS to the `delayed` folder
+ */
+export type DelayedOutput = { enable: boolean, delay: number, output_param: string, };
 
-export type OutputMode = "desktop" | "hls" | "null" | "stream";
+/**
+ * Downmix of sources with more channels than the output.
+ */
+export type Downmix = "auto" | "itu" | "front";
 
-export type Playlist = { day_start: string, length: string, infinit: boolean, };
+/**
+ * Field order of the output.
+ */
+export type FieldOrder = "progressive" | "tff" | "bff";
+
+export type General = { stop_threshold: number, 
+/**
+ * Broadcast hours, the engine plays a slate or stops the channel outside of them.
+ */
+air_hours: AirHours, 
+/**
+ * Run the Lua scripts of the channel, at playlist generation and when the next clip is prepared.
+ */
+scripting: boolean, };
+
+/**
+ * Target format for transcoded files, picture size and frame rate are from the processing config.
+ *
+ * - `video_codec`, `preset`, `crf`: ffmpeg video encoder and its quality
+ * - `audio_codec`, `audio_bitrate`: ffmpeg audio encoder
+ * - `loudness`: integrated loudness in LUFS, with `true_peak` in dBTP, 0 disables the normalization
+ */
+export type HouseFormat = { video_codec: string, preset: string, crf: number, audio_codec: string, audio_bitrate: string, loudness: number, true_peak: number, };
+
+/**
+ * Image and HTML overlay, which is shown and hidden over the control API.
+ *
+ * - `browser`: Chromium compatible browser, which renders HTML pages headless to images
+ */
+export type ImageOverlay = { enable: boolean, browser: string, };
+
+export type Ingest = { enable: boolean, input_param: string, custom_filter: string, 
+/**
+ * Publisher authentication over the callback endpoint.
+ */
+auth: IngestAuth, 
+/**
+ * Low latency preview of the ingest stream.
+ */
+preview: IngestPreview, 
+/**
+ * WebRTC publishing over WHIP.
+ */
+whip: IngestWhip, };
+
+/**
+ * Publisher authentication for an RTMP server in front of the ingest, like nginx-rtmp or SRS.
+ *
+ * - `enable`: the server asks `/ingest/{id}/auth` before it accepts a stream
+ * - `keys`: stream keys of the publishers, the key from the ingest url is always valid
+ * - `allow`: IP addresses or networks (`10.0.0.0/8`), which can publish, empty allows all
+ */
+export type IngestAuth = { enable: boolean, keys: Array<string>, allow: Array<string>, };
+
+/**
+ * Preview of the incoming ingest stream, also before it goes on air.
+ *
+ * - `enable`: the ingest writes a low latency HLS stream to `/{id}/preview/ingest.m3u8`
+ * - `height`: height of the preview video, the width follows the aspect ratio
+ * - `bitrate`: video bitrate in kbit/s
+ */
+export type IngestPreview = { enable: boolean, height: bigint, bitrate: bigint, };
+
+/**
+ * WHIP ingest over a WebRTC gateway, like MediaMTX or SRS, which pushes the stream to the ingest.
+ *
+ * - `enable`: clients can publish to `/{id}/whip`, with the stream key as bearer token
+ * - `gateway`: WHIP endpoint of the gateway, like `http://127.0.0.1:8889/live/whip`
+ */
+export type IngestWhip = { enable: boolean, gateway: string, };
+
+/**
+ * Retention of the log files from one channel.
+ *
+ * - `days`: days to keep, `0` uses `--log-backup-count`
+ * - `max_size`: rotate the file also at this size in MB, `0` rotates only daily
+ * - `compress`: compress the rotated files with gzip
+ */
+export type LogRetention = { days: number, max_size: number, compress: boolean, };
+
+export type Logging = { ffmpeg_level: string, ingest_level: string, detect_silence: boolean, ignore_lines: Array<string>, 
+/**
+ * Log level of the channel: `debug`, `info`, `warn` or `error`, empty uses the server level.
+ */
+level: string, retention: LogRetention, };
+
+export type LogoRule = { path: string, position: string, scale: string, opacity: number, days: Array<number>, start: string, end: string, ingest: boolean, };
+
+export type Mail = { show: boolean, subject: string, recipient: string, mail_level: string, interval: bigint, 
+/**
+ * Chat services, which get alerts like the mail recipient.
+ */
+notifiers: Array<Notifier>, 
+/**
+ * Seconds until an identical message is sent again.
+ */
+repeat_limit: bigint, };
+
+/**
+ * What the channel does outside of its broadcast hours.
+ */
+/**
+ * Alert target in a chat service, with its own level.
+ *
+ * - `url`: webhook url for Discord and Slack
+ * - `token`, `chat_id`: bot token and chat id for Telegram
+ * - `level`: lowest level which is sent, `INFO`, `WARNING` or `ERROR`
+ */
+export type Notifier = { kind: NotifierKind, enable: boolean, url: string, token: string, chat_id: string, level: string, };
+
+export type NotifierKind = "telegram" | "discord" | "slack";
+
+/**
+ * Media in S3 compatible object storage, the channel storage folder is the local cache.
+ *
+ * - `endpoint`, `region`, `bucket`, `access_key`, `secret_key`: S3 connection, path style
+ * - `prefix`: key prefix for the storage root in the bucket
+ * - `cache_size`: size of the local cache in MB, 0 is unlimited
+ * - `prefetch`: seconds of upcoming clips, which are downloaded before they air
+ */
+export type ObjectStore = { enable: boolean, endpoint: string, region: string, bucket: string, prefix: string, access_key: string, secret_key: string, cache_size: bigint, prefetch: number, };
+
+export type OffAir = "slate" | "off";
+
+/**
+ * Slate in the HLS output, while the channel is stopped.
+ *
+ * - `image`: image for the slate, relative paths are in the channel storage, empty is black
+ * - `audio`: audio file, which plays in a loop, empty is silence
+ * - `text`: text in the center of the slate, like "Channel offline"
+ */
+export type OfflineSlate = { enable: boolean, image: string, audio: string, text: string, };
+
+export type Output = { mode: OutputMode, output_param: string, s3_enable: boolean, s3_endpoint: string, s3_region: string, s3_bucket: string, s3_prefix: string, s3_access_key: string, s3_secret_key: string, s3_concurrency: number, 
+/**
+ * Write SCTE-35 markers for ad breaks to the HLS playlists.
+ */
+scte35: boolean, 
+/**
+ * Write title and category of the playing item to the HLS playlists.
+ */
+item_metadata: boolean, 
+/**
+ * Parental rating signaling for HLS and the program export.
+ */
+rating: ParentalRating, 
+/**
+ * Output parameters for a backup encoder, which takes over when the main encoder fails.
+ */
+backup_param: string, 
+/**
+ * Web radio output, which replaces the output parameters in stream mode.
+ */
+radio: Radio, 
+/**
+ * SDI output in decklink mode.
+ */
+decklink: Decklink, 
+/**
+ * Time shifted copy of the HLS output.
+ */
+delayed: DelayedOutput, 
+/**
+ * Interlacing and broadcast legal colors.
+ */
+broadcast: Broadcast, 
+/**
+ * Slate in the HLS output, while the channel is stopped.
+ */
+offline: OfflineSlate, 
+/**
+ * Low bitrate preview of the program output.
+ */
+preview: ProgramPreview, 
+/**
+ * Silence and black picture alarms.
+ */
+alarms: OutputAlarms, };
+
+/**
+ * Alarms for silence and black picture in the program output.
+ *
+ * - `silence`: seconds of silence until the alarm, 0 disables it
+ * - `silence_level`: noise level in dB, under which the audio counts as silence
+ * - `black`: seconds of black picture until the alarm, 0 disables it
+ * - `black_level`: luminance from 0.0 to 1.0, under which a pixel counts as black
+ * - `cooldown`: seconds after an alarm, in which the next alarm of the same kind is only logged
+ */
+export type OutputAlarms = { silence: number, silence_level: number, black: number, black_level: number, cooldown: bigint, };
+
+export type OutputMode = "dash" | "decklink" | "desktop" | "hls" | "null" | "stream";
+
+/**
+ * Parental rating signaling.
+ *
+ * - `enable`: write the rating of the running item to the HLS playlists
+ * - `system`: rating system, like `MPAA`, `VCHIP` or `FSK`
+ * - `default`: rating for items without own rating, empty means no rating
+ * - `mapping`: item ratings to ratings of the channel system, like `{ "PG-13": "12" }`
+ */
+export type ParentalRating = { enable: boolean, system: string, default: string, mapping: { [key in string]?: string }, };
+
+export type Playlist = { day_start: string, length: string, infinit: boolean, 
+/**
+ * Minutes before the day rollover, in which the next playlist is checked. 0 disables the check.
+ */
+check_before: bigint, fallback: PlaylistFallback, };
+
+/**
+ * What happens, when the playlist from the next day is missing shortly before the day rollover.
+ */
+export type PlaylistFallback = "none" | "copy" | "generate";
 
 /**
  * Channel Config
@@ -21,12 +284,192 @@ export type Playlist = { day_start: string, length: string, infinit: boolean, };
  */
 export type PlayoutConfig = { general: General, mail: Mail, logging: Logging, processing: Processing, ingest: Ingest, playlist: Playlist, storage: Storage, text: Text, task: Task, output: Output, };
 
+/**
+ * Local copies of the upcoming clips, from slow or remote storage like NFS or SMB mounts.
+ *
+ * - `path`: fast local folder for the copies, each channel gets a sub folder
+ * - `size`: size of the cache in MB, 0 is unlimited
+ * - `look_ahead`: seconds of upcoming clips, which are copied before they air
+ */
+export type PreCache = { enable: boolean, path: string, size: bigint, look_ahead: number, };
+
 export type ProcessMode = "folder" | "playlist";
 
-export type Processing = { mode: ProcessMode, audio_only: boolean, copy_audio: boolean, copy_video: boolean, width: bigint, height: bigint, aspect: number, fps: number, add_logo: boolean, logo: string, logo_scale: string, logo_opacity: number, logo_position: string, audio_tracks: number, audio_track_index: number, audio_channels: number, volume: number, custom_filter: string, vtt_enable: boolean, vtt_dummy: string | null, };
+export type Processing = { mode: ProcessMode, audio_only: boolean, copy_audio: boolean, copy_video: boolean, 
+/**
+ * Copy clips in the house format, encode only the other clips.
+ */
+passthrough: boolean, width: bigint, height: bigint, aspect: number, 
+/**
+ * Clips with another aspect, can be overridden per playlist item.
+ */
+aspect_policy: AspectPolicy, fps: number, add_logo: boolean, logo: string, logo_scale: string, logo_opacity: number, logo_position: string, audio_tracks: number, audio_track_index: number, audio_channels: number, 
+/**
+ * Downmix of 5.1 sources for stereo output, can be overridden per playlist item with a channel map.
+ */
+audio_downmix: Downmix, volume: number, custom_filter: string, vtt_enable: boolean, vtt_dummy: string | null, 
+/**
+ * Burn subtitles into the video, instead of passing them through as WebVTT.
+ */
+subtitle_burn: boolean, transition: TransitionKind, transition_frames: number, 
+/**
+ * Length of the audio fade, 0 uses the video length.
+ */
+transition_audio_frames: number, logos: Array<LogoRule>, 
+/**
+ * Audio tracks of the output, like main and audio description. Empty maps the first `audio_tracks`.
+ */
+audio_track_map: Array<AudioTrack>, 
+/**
+ * Seconds of frozen picture or silence, until the dead-air fallback starts. 0 disables the detection.
+ */
+dead_air_duration: number, 
+/**
+ * Fallback source: image, video or stream url. Empty plays filler.
+ */
+dead_air_fallback: string, 
+/**
+ * Optional audio for a fallback image.
+ */
+dead_air_audio: string, 
+/**
+ * Measure loudness and peak levels of the first audio track, for the VU meters.
+ */
+audio_meter: boolean, 
+/**
+ * Format of transcoded files and proxies.
+ */
+house_format: HouseFormat, };
+
+/**
+ * Pull playlists and media from a FTP or SFTP server, like from a traffic system.
+ *
+ * - `url`: `ftp://`, `ftps://` or `sftp://` url of the base folder
+ * - `username`, `password`: login, `key_file` is a private key for SFTP
+ * - `playlist_folder`, `media_folder`: folders below the url, empty to skip
+ * - `target`: folder in the storage, where the media files are saved
+ * - `interval`: minutes between two runs
+ * - `delete`: delete files on the server after they are imported
+ * - `notify`: send a report to the mail recipient and the notifiers after each run with changes
+ */
+/**
+ * Low bitrate preview of the program output, for monitoring in the web UI.
+ *
+ * - `height`: video height, the width follows the aspect
+ * - `bitrate`: video bitrate in kbit/s
+ * - `gateway`: RTMP or RTSP url of a WebRTC gateway, like MediaMTX, which gets the preview too
+ */
+export type ProgramPreview = { enable: boolean, height: bigint, bitrate: bigint, gateway: string, };
+
+export type PullIngest = { enable: boolean, url: string, username: string, password: string, key_file: string, playlist_folder: string, media_folder: string, target: string, interval: bigint, delete: boolean, notify: boolean, };
 
-export type Storage = { filler: string, extensions: Array<string>, shuffle: boolean, shared_storage: boolean, };
+/**
+ * Web radio, an audio only channel which streams to an Icecast or SHOUTcast server.
+ *
+ * - `server`: `icecast` or `shoutcast`
+ * - `host`: address of the server, like `radio.example.org:8000`
+ * - `mount`: mount point, SHOUTcast uses the stream id instead, like `/1`
+ * - `username`/`password`: source login, which also updates the metadata
+ * - `name`: name of the station
+ * - `codec`: `mp3` or `aac`, with `bitrate` in kbit/s
+ * - `metadata`: now playing text, with the placeholders `{title}`, `{category}` and `{file}`
+ * - `hls`: audio HLS variant in `radio/radio.m3u8` from the public folder
+ */
+export type Radio = { enable: boolean, server: string, host: string, mount: string, username: string, password: string, name: string, codec: string, bitrate: bigint, metadata: string, hls: boolean, };
+
+export type Storage = { filler: string, 
+/**
+ * How often one filler clip can play per day, 0 is unlimited.
+ */
+filler_max_repeat: number, 
+/**
+ * Prefer filler clips which fit completely into the gap.
+ */
+filler_fit: boolean, 
+/**
+ * Sub folders of the filler folder, which are not used. Separated by semicolon.
+ */
+filler_exclude: string, extensions: Array<string>, shuffle: boolean, shared_storage: boolean, 
+/**
+ * Folder with poster images, relative to storage. Files are matched by the clip title.
+ */
+artwork_path: string, 
+/**
+ * Url for artwork lookup, `{title}` is replaced by the clip title.
+ */
+artwork_api: string, 
+/**
+ * JSON pointer to the image url in the API response.
+ */
+artwork_field: string, 
+/**
+ * Days until removed files are deleted from trash, 0 disables the trash.
+ */
+trash_retention: number, 
+/**
+ * Files from the storage are shown in the browser, not downloaded.
+ */
+file_inline: boolean, 
+/**
+ * Folder, from which new files are ingested into the storage.
+ */
+watch_folder: WatchFolder, 
+/**
+ * S3 compatible storage for the media files.
+ */
+object_store: ObjectStore, 
+/**
+ * FTP or SFTP server, from which playlists and media are pulled.
+ */
+pull_ingest: PullIngest, 
+/**
+ * Local copies of the upcoming clips from slow storage.
+ */
+pre_cache: PreCache, };
 
 export type Task = { enable: boolean, path: string, };
 
-export type Text = { add_text: boolean, font: string, text_from_filename: boolean, style: string, regex: string, };
+export type Text = { add_text: boolean, font: string, text_from_filename: boolean, style: string, regex: string, 
+/**
+ * Clock, countdown and ticker overlays.
+ */
+widgets: Widgets, 
+/**
+ * Image and HTML overlay from the control API.
+ */
+overlay: ImageOverlay, };
+
+export type Transition = { kind: TransitionKind | null, frames: number | null, 
+/**
+ * Length of the audio fade, when it differs from the video fade.
+ */
+audio_frames: number | null, };
+
+export type TransitionKind = "none" | "fade" | "audio" | "crossfade";
+
+/**
+ * Auto ingest of files, which are dropped into a folder.
+ *
+ * - `path`: drop folder, absolute or relative to the channel storage
+ * - `target`: folder in the storage, where the files are moved to
+ * - `transcode`: transcode to the house format of the channel
+ * - `append`: add the files to the end of the current playlist
+ */
+export type WatchFolder = { enable: boolean, path: string, target: string, transcode: boolean, append: boolean, };
+
+/**
+ * Dynamic text overlays, which are rendered with drawtext from text files.
+ *
+ * - `clock`: current time in `clock_format` (strftime)
+ * - `countdown`: time until the next program, `countdown_text` has the placeholders
+ * `{title}` and `{time}`; clips with a category from `countdown_skip` (separated by semicolon)
+ * are no program, like commercials
+ * - `ticker`: crawl with the text from `ticker_url`, an API endpoint with plain text or
+ * a JSON list, or a RSS/Atom feed; it is loaded again every `ticker_interval` seconds
+ * - `*_style`: drawtext parameters, like position, size and color
+ */
+export type Widgets = { clock: boolean, clock_format: string, clock_style: string, countdown: boolean, countdown_text: string, countdown_skip: string, countdown_style: string, ticker: boolean, ticker_url: string, ticker_interval: bigint, ticker_separator: string, 
+/**
+ * Speed in pixel per second.
+ */
+ticker_speed: number, ticker_style: string, };
@@ -0,0 +1,14 @@
+CREATE TABLE
+    audit_log (
+        id INTEGER PRIMARY KEY,
+        channel_id INTEGER,
+        user_id INTEGER NOT NULL,
+        ip TEXT NOT NULL DEFAULT "",
+        method TEXT NOT NULL,
+        route TEXT NOT NULL,
+        payload TEXT NOT NULL DEFAULT "",
+        status INTEGER NOT NULL DEFAULT 0,
+        timestamp TEXT NOT NULL
+    );
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_channel_time ON audit_log (channel_id, timestamp);
@@ -0,0 +1,2 @@
+ALTER TABLE channels
+    ADD public_embed INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,5 @@
+ALTER TABLE configurations
+    ADD processing_transition TEXT NOT NULL DEFAULT "none";
+
+ALTER TABLE configurations
+    ADD processing_transition_frames INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE channels
+    ADD public_headers TEXT NOT NULL DEFAULT "";
@@ -0,0 +1,23 @@
+ALTER TABLE configurations
+    ADD output_s3_enable INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE configurations
+    ADD output_s3_endpoint TEXT NOT NULL DEFAULT "";
+
+ALTER TABLE configurations
+    ADD output_s3_region TEXT NOT NULL DEFAULT "";
+
+ALTER TABLE configurations
+    ADD output_s3_bucket TEXT NOT NULL DEFAULT "";
+
+ALTER TABLE configurations
+    ADD output_s3_prefix TEXT NOT NULL DEFAULT "";
+
+ALTER TABLE configurations
+    ADD output_s3_access_key TEXT NOT NULL DEFAULT "";
+
+ALTER TABLE configurations
+    ADD output_s3_secret_key TEXT NOT NULL DEFAULT "";
+
+ALTER TABLE configurations
+    ADD output_s3_concurrency INTEGER NOT NULL DEFAULT 4;
@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD processing_logos TEXT NOT NULL DEFAULT "[]";
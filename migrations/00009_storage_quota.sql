@@ -0,0 +1,2 @@
+ALTER TABLE channels
+    ADD storage_quota INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,19 @@
+ALTER TABLE configurations
+    ADD storage_artwork_path TEXT NOT NULL DEFAULT "";
+
+ALTER TABLE configurations
+    ADD storage_artwork_api TEXT NOT NULL DEFAULT "";
+
+ALTER TABLE configurations
+    ADD storage_artwork_field TEXT NOT NULL DEFAULT "/poster";
+
+CREATE TABLE
+    artwork_cache (
+        id INTEGER PRIMARY KEY,
+        channel_id INTEGER NOT NULL,
+        title TEXT NOT NULL,
+        url TEXT NOT NULL DEFAULT "",
+        updated TEXT NOT NULL,
+        FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE CASCADE ON DELETE CASCADE,
+        UNIQUE (channel_id, title)
+    );
@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD storage_trash_retention INTEGER NOT NULL DEFAULT 30;
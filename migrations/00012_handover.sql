@@ -0,0 +1,12 @@
+CREATE TABLE
+    handover_notes (
+        id INTEGER PRIMARY KEY,
+        channel_id INTEGER NOT NULL,
+        user_id INTEGER NOT NULL,
+        note TEXT NOT NULL,
+        event TEXT NOT NULL DEFAULT "",
+        timestamp TEXT NOT NULL,
+        FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE CASCADE ON DELETE CASCADE
+    );
+
+CREATE INDEX IF NOT EXISTS idx_handover_notes_channel_time ON handover_notes (channel_id, timestamp);
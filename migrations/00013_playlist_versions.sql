@@ -0,0 +1,12 @@
+CREATE TABLE
+    playlist_versions (
+        id INTEGER PRIMARY KEY,
+        channel_id INTEGER NOT NULL,
+        date TEXT NOT NULL,
+        user_id INTEGER NOT NULL DEFAULT 0,
+        playlist TEXT NOT NULL,
+        timestamp TEXT NOT NULL,
+        FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE CASCADE ON DELETE CASCADE
+    );
+
+CREATE INDEX IF NOT EXISTS idx_playlist_versions_channel_date ON playlist_versions (channel_id, date);
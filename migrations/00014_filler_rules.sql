@@ -0,0 +1,8 @@
+ALTER TABLE configurations
+    ADD storage_filler_max_repeat INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE configurations
+    ADD storage_filler_fit INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE configurations
+    ADD storage_filler_exclude TEXT NOT NULL DEFAULT "";
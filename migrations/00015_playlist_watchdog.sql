@@ -0,0 +1,5 @@
+ALTER TABLE configurations
+    ADD playlist_check_before INTEGER NOT NULL DEFAULT 60;
+
+ALTER TABLE configurations
+    ADD playlist_fallback TEXT NOT NULL DEFAULT "none";
@@ -0,0 +1,8 @@
+ALTER TABLE configurations
+    ADD processing_dead_air_duration REAL NOT NULL DEFAULT 0.0;
+
+ALTER TABLE configurations
+    ADD processing_dead_air_fallback TEXT NOT NULL DEFAULT "";
+
+ALTER TABLE configurations
+    ADD processing_dead_air_audio TEXT NOT NULL DEFAULT "";
@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD processing_transition_audio_frames INTEGER NOT NULL DEFAULT 0;
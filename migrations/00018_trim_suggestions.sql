@@ -0,0 +1,12 @@
+CREATE TABLE
+    trim_suggestions (
+        id INTEGER PRIMARY KEY,
+        channel_id INTEGER NOT NULL,
+        source TEXT NOT NULL,
+        seek REAL NOT NULL DEFAULT 0,
+        out REAL NOT NULL DEFAULT 0,
+        duration REAL NOT NULL DEFAULT 0,
+        updated TEXT NOT NULL,
+        FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE CASCADE ON DELETE CASCADE,
+        UNIQUE (channel_id, source)
+    );
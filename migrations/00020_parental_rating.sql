@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD output_rating TEXT NOT NULL DEFAULT "{}";
@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD processing_subtitle_burn INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD output_backup_param TEXT NOT NULL DEFAULT "";
@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD general_air_hours TEXT NOT NULL DEFAULT "{}";
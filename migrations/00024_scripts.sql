@@ -0,0 +1,14 @@
+CREATE TABLE
+    scripts (
+        id INTEGER PRIMARY KEY,
+        channel_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        code TEXT NOT NULL,
+        enabled INTEGER NOT NULL DEFAULT 1,
+        updated TEXT NOT NULL,
+        FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE CASCADE ON DELETE CASCADE,
+        UNIQUE (channel_id, name)
+    );
+
+ALTER TABLE configurations
+    ADD general_scripting INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD storage_watch_folder TEXT NOT NULL DEFAULT "{}";
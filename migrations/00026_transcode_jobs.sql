@@ -0,0 +1,16 @@
+CREATE TABLE
+    transcode_jobs (
+        id INTEGER PRIMARY KEY,
+        channel_id INTEGER NOT NULL,
+        source TEXT NOT NULL,
+        target TEXT NOT NULL,
+        status TEXT NOT NULL DEFAULT "queued",
+        progress REAL NOT NULL DEFAULT 0,
+        message TEXT NOT NULL DEFAULT "",
+        created TEXT NOT NULL,
+        updated TEXT NOT NULL,
+        FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE CASCADE ON DELETE CASCADE
+    );
+
+ALTER TABLE configurations
+    ADD processing_house_format TEXT NOT NULL DEFAULT "{}";
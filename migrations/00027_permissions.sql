@@ -0,0 +1,37 @@
+CREATE TABLE
+    role_permissions (
+        id INTEGER PRIMARY KEY,
+        role_id INTEGER NOT NULL,
+        permission TEXT NOT NULL,
+        FOREIGN KEY (role_id) REFERENCES roles (id) ON UPDATE CASCADE ON DELETE CASCADE,
+        UNIQUE (role_id, permission)
+    );
+
+INSERT INTO
+    role_permissions (role_id, permission)
+VALUES
+    (1, 'channel.edit'),
+    (1, 'config.edit'),
+    (1, 'preset.edit'),
+    (1, 'text.send'),
+    (1, 'process.control'),
+    (1, 'playlist.edit'),
+    (1, 'file.edit'),
+    (1, 'file.delete'),
+    (2, 'channel.edit'),
+    (2, 'config.edit'),
+    (2, 'preset.edit'),
+    (2, 'text.send'),
+    (2, 'process.control'),
+    (2, 'playlist.edit'),
+    (2, 'file.edit'),
+    (2, 'file.delete'),
+    (3, 'preset.edit'),
+    (3, 'text.send'),
+    (3, 'process.control'),
+    (3, 'playlist.edit'),
+    (3, 'file.edit'),
+    (3, 'file.delete');
+
+ALTER TABLE user
+    ADD permissions TEXT;
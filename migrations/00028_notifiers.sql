@@ -0,0 +1,5 @@
+ALTER TABLE configurations
+    ADD mail_notifiers TEXT NOT NULL DEFAULT "[]";
+
+ALTER TABLE configurations
+    ADD mail_repeat_limit INTEGER NOT NULL DEFAULT 3600;
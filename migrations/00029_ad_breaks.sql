@@ -0,0 +1,23 @@
+CREATE TABLE
+    ad_breaks (
+        id INTEGER PRIMARY KEY,
+        channel_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        duration REAL NOT NULL DEFAULT 120,
+        spots TEXT NOT NULL DEFAULT "",
+        max_plays INTEGER NOT NULL DEFAULT 0,
+        pad INTEGER NOT NULL DEFAULT 1,
+        FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE CASCADE ON DELETE CASCADE,
+        UNIQUE (channel_id, name)
+    );
+
+CREATE TABLE
+    spot_plays (
+        id INTEGER PRIMARY KEY,
+        channel_id INTEGER NOT NULL,
+        source TEXT NOT NULL,
+        date TEXT NOT NULL,
+        plays INTEGER NOT NULL DEFAULT 0,
+        FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE CASCADE ON DELETE CASCADE,
+        UNIQUE (channel_id, source, date)
+    );
@@ -0,0 +1,5 @@
+ALTER TABLE transcode_jobs
+    ADD seek REAL NOT NULL DEFAULT 0;
+
+ALTER TABLE transcode_jobs
+    ADD out REAL NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD processing_passthrough INTEGER NOT NULL DEFAULT 0;
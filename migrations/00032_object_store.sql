@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD storage_object_store TEXT NOT NULL DEFAULT "{}";
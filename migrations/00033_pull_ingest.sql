@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD storage_pull_ingest TEXT NOT NULL DEFAULT "{}";
@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD text_widgets TEXT NOT NULL DEFAULT "{}";
@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD text_overlay TEXT NOT NULL DEFAULT "{}";
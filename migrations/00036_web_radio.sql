@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD output_radio TEXT NOT NULL DEFAULT "{}";
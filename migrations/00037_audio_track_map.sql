@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD processing_audio_track_map TEXT NOT NULL DEFAULT "[]";
@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD output_decklink TEXT NOT NULL DEFAULT "{}";
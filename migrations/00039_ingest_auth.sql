@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD ingest_auth TEXT NOT NULL DEFAULT "{}";
@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD ingest_preview TEXT NOT NULL DEFAULT "{}";
@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD ingest_whip TEXT NOT NULL DEFAULT "{}";
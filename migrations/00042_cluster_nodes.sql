@@ -0,0 +1,16 @@
+CREATE TABLE
+    nodes (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        url TEXT NOT NULL,
+        drained INTEGER NOT NULL DEFAULT 0,
+        UNIQUE (name)
+    );
+
+CREATE TABLE
+    node_channels (
+        channel_id INTEGER PRIMARY KEY,
+        node_id INTEGER NOT NULL,
+        FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE CASCADE ON DELETE CASCADE,
+        FOREIGN KEY (node_id) REFERENCES nodes (id) ON UPDATE CASCADE ON DELETE CASCADE
+    );
@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD storage_pre_cache TEXT NOT NULL DEFAULT "{}";
@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD output_delayed TEXT NOT NULL DEFAULT "{}";
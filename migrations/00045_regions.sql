@@ -0,0 +1,10 @@
+CREATE TABLE
+    regions (
+        id INTEGER PRIMARY KEY,
+        channel_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        output_param TEXT NOT NULL,
+        enable INTEGER NOT NULL DEFAULT 1,
+        FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE CASCADE ON DELETE CASCADE,
+        UNIQUE (channel_id, name)
+    );
@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD processing_aspect_policy TEXT NOT NULL DEFAULT "pad";
@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD output_broadcast TEXT NOT NULL DEFAULT "{}";
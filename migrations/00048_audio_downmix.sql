@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD processing_audio_downmix TEXT NOT NULL DEFAULT "auto";
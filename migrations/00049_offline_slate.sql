@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD output_offline TEXT NOT NULL DEFAULT "{}";
@@ -0,0 +1,5 @@
+ALTER TABLE configurations
+    ADD logging_level TEXT NOT NULL DEFAULT "";
+
+ALTER TABLE configurations
+    ADD logging_retention TEXT NOT NULL DEFAULT "{}";
@@ -0,0 +1,8 @@
+CREATE TABLE
+    password_resets (
+        id INTEGER PRIMARY KEY,
+        user_id INTEGER NOT NULL,
+        token_hash TEXT NOT NULL,
+        expires INTEGER NOT NULL,
+        FOREIGN KEY (user_id) REFERENCES user (id) ON UPDATE CASCADE ON DELETE CASCADE
+    );
@@ -0,0 +1,18 @@
+ALTER TABLE global
+    ADD password_policy TEXT NOT NULL DEFAULT "{}";
+
+ALTER TABLE user
+    ADD must_change INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE user
+    ADD password_changed INTEGER NOT NULL DEFAULT 0;
+
+UPDATE user SET password_changed = strftime('%s', 'now');
+
+CREATE TABLE
+    password_history (
+        id INTEGER PRIMARY KEY,
+        user_id INTEGER NOT NULL,
+        password TEXT NOT NULL,
+        FOREIGN KEY (user_id) REFERENCES user (id) ON UPDATE CASCADE ON DELETE CASCADE
+    );
@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD storage_file_inline INTEGER NOT NULL DEFAULT 1;
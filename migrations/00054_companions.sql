@@ -0,0 +1,13 @@
+CREATE TABLE
+    companions (
+        id INTEGER PRIMARY KEY,
+        channel_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        command TEXT NOT NULL,
+        args TEXT NOT NULL DEFAULT "",
+        restart TEXT NOT NULL DEFAULT "never",
+        max_restarts INTEGER NOT NULL DEFAULT 5,
+        autostart INTEGER NOT NULL DEFAULT 0,
+        FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE CASCADE ON DELETE CASCADE,
+        UNIQUE (channel_id, name)
+    );
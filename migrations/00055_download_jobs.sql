@@ -0,0 +1,16 @@
+CREATE TABLE
+    download_jobs (
+        id INTEGER PRIMARY KEY,
+        channel_id INTEGER NOT NULL,
+        url TEXT NOT NULL,
+        folder TEXT NOT NULL DEFAULT "",
+        format TEXT NOT NULL DEFAULT "",
+        playlist_date TEXT,
+        target TEXT NOT NULL DEFAULT "",
+        status TEXT NOT NULL DEFAULT "queued",
+        progress REAL NOT NULL DEFAULT 0,
+        message TEXT NOT NULL DEFAULT "",
+        created TEXT NOT NULL,
+        updated TEXT NOT NULL,
+        FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE CASCADE ON DELETE CASCADE
+    );
@@ -0,0 +1,14 @@
+CREATE TABLE
+    output_targets (
+        id INTEGER PRIMARY KEY,
+        channel_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        url TEXT NOT NULL,
+        format TEXT NOT NULL DEFAULT "",
+        stream_key TEXT NOT NULL DEFAULT "",
+        username TEXT NOT NULL DEFAULT "",
+        password TEXT NOT NULL DEFAULT "",
+        enable INTEGER NOT NULL DEFAULT 1,
+        FOREIGN KEY (channel_id) REFERENCES channels (id) ON UPDATE CASCADE ON DELETE CASCADE,
+        UNIQUE (channel_id, name)
+    );
@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD output_preview TEXT NOT NULL DEFAULT "{}";
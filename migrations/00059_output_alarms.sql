@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD output_alarms TEXT NOT NULL DEFAULT "{}";
@@ -0,0 +1,2 @@
+ALTER TABLE configurations
+    ADD output_item_metadata INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,5 @@
+ALTER TABLE channels
+    ADD public_nowplaying INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE channels
+    ADD nowplaying_token TEXT NOT NULL DEFAULT "";
@@ -0,0 +1,2 @@
+ALTER TABLE channels
+    ADD maintenance INTEGER NOT NULL DEFAULT 0;
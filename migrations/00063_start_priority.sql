@@ -0,0 +1,2 @@
+ALTER TABLE channels
+    ADD start_priority INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,8 @@
+ALTER TABLE channels
+    ADD cpu_affinity TEXT NOT NULL DEFAULT "";
+
+ALTER TABLE channels
+    ADD nice INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE channels
+    ADD memory_limit INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE channels
+    ADD gpu TEXT NOT NULL DEFAULT "";
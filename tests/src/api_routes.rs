@@ -1,14 +1,33 @@
-use actix_web::{get, web, App, Error, HttpResponse, Responder};
-// use actix_web_httpauth::extractors::bearer::BearerAuth;
+use actix_web::{
+    get, http::header, middleware::from_fn, post, test, web, App, Error, HttpResponse, Responder,
+};
+use actix_web_httpauth::middleware::HttpAuthentication;
 
 use serde_json::json;
 use sqlx::{sqlite::SqlitePoolOptions, Pool, Sqlite};
 
-use ffplayout::api::routes::login;
-use ffplayout::db::{handles, init_globales, models::User};
-use ffplayout::player::controller::ChannelManager;
-use ffplayout::utils::config::PlayoutConfig;
-// use ffplayout::validator;
+use std::sync::Mutex;
+
+use ffplayout::api::{
+    audit::audit_log,
+    auth::{create_jwt, decode_jwt, Claims},
+    pagination::ListQuery,
+    routes::*,
+};
+use ffplayout::db::{
+    handles, init_globales,
+    models::{Permission, Role, User},
+    GLOBAL_SETTINGS,
+};
+use ffplayout::player::controller::{ChannelController, ChannelManager};
+use ffplayout::utils::{
+    backup::tar_read,
+    config::PlayoutConfig,
+    errors::ServiceError,
+    password_reset::{reset_token, token_hash},
+    time_machine::time_now,
+};
+use ffplayout::validator;
 
 async fn prepare_config() -> (PlayoutConfig, ChannelManager, Pool<Sqlite>) {
     let pool = SqlitePoolOptions::new()
@@ -34,11 +53,16 @@ async fn prepare_config() -> (PlayoutConfig, ChannelManager, Pool<Sqlite>) {
         password: "admin".to_string(),
         role_id: Some(1),
         channel_ids: Some(vec![1]),
+        permissions: None,
         token: None,
+        ..Default::default()
     };
 
     handles::insert_user(&pool, user.clone()).await.unwrap();
 
+    // the settings are global for the process, the first test sets them
+    let _ = init_globales(&pool).await;
+
     let config = PlayoutConfig::new(&pool, 1).await.unwrap();
     let channel = handles::select_channel(&pool, &1).await.unwrap();
     let manager = ChannelManager::new(Some(pool.clone()), channel, config.clone());
@@ -46,6 +70,24 @@ async fn prepare_config() -> (PlayoutConfig, ChannelManager, Pool<Sqlite>) {
     (config, manager, pool)
 }
 
+fn controllers(manager: ChannelManager) -> web::Data<Mutex<ChannelController>> {
+    let mut controller = ChannelController::new();
+    controller.add(manager);
+
+    web::Data::new(Mutex::new(controller))
+}
+
+/// Bearer token for a user from channel 1.
+async fn token(role: Role, permissions: &[Permission]) -> String {
+    let claims = Claims::new(1, vec![1], "admin".into(), role, permissions.to_vec());
+
+    format!("Bearer {}", create_jwt(claims).await.unwrap())
+}
+
+fn request(req: test::TestRequest, token: &str) -> test::TestRequest {
+    req.insert_header((header::AUTHORIZATION, token))
+}
+
 #[get("/")]
 async fn get_handler() -> Result<impl Responder, Error> {
     Ok(HttpResponse::Ok())
@@ -53,10 +95,10 @@ async fn get_handler() -> Result<impl Responder, Error> {
 
 #[actix_rt::test]
 async fn test_get() {
-    let srv = actix_test::start(|| App::new().service(get_handler));
+    let app = test::init_service(App::new().service(get_handler)).await;
 
-    let req = srv.get("/");
-    let res = req.send().await.unwrap();
+    let req = test::TestRequest::get().uri("/").to_request();
+    let res = test::call_service(&app, req).await;
 
     assert!(res.status().is_success());
 }
@@ -65,28 +107,762 @@ async fn test_get() {
 async fn test_login() {
     let (_, _, pool) = prepare_config().await;
 
-    init_globales(&pool).await.unwrap();
+    let app = test::init_service(App::new().app_data(web::Data::new(pool)).service(login)).await;
+
+    for (username, password, status) in [
+        ("admin", "admin", 200),
+        ("admin", "1234", 403),
+        ("aaa", "1234", 400),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/auth/login/")
+            .set_json(json!({"username": username, "password": password}))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+
+        assert_eq!(res.status().as_u16(), status);
+    }
+}
+
+#[actix_rt::test]
+async fn test_login_permissions() {
+    let (_, _, pool) = prepare_config().await;
+
+    let operator = User {
+        id: 0,
+        mail: Some("operator@mail.com".to_string()),
+        username: "operator".to_string(),
+        password: "operator".to_string(),
+        role_id: Some(3),
+        channel_ids: Some(vec![1]),
+        permissions: Some(vec![Permission::PlaylistEdit]),
+        token: None,
+        ..Default::default()
+    };
+
+    handles::insert_user(&pool, operator).await.unwrap();
+    handles::update_role_permissions(&pool, 2, &[Permission::ConfigEdit])
+        .await
+        .unwrap();
+
+    let matrix = handles::select_role_permissions(&pool).await.unwrap();
+
+    assert_eq!(matrix[1].permissions, vec![Permission::ConfigEdit]);
+    assert!(matrix[2].permissions.contains(&Permission::ProcessControl));
+
+    let app = test::init_service(App::new().app_data(web::Data::new(pool)).service(login)).await;
+
+    for (name, permissions) in [
+        ("admin", Permission::ALL.to_vec()),
+        ("operator", vec![Permission::PlaylistEdit]),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/auth/login/")
+            .set_json(json!({"username": name, "password": name}))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let claims = decode_jwt(body["user"]["token"].as_str().unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(claims.permissions, permissions);
+    }
+}
+
+#[post("/api/channel/{id}")]
+async fn post_handler(_id: web::Path<i32>, _data: web::Json<serde_json::Value>) -> impl Responder {
+    HttpResponse::Ok()
+}
+
+#[post("/api/user/{id}")]
+async fn post_user_handler(
+    _id: web::Path<i32>,
+    _data: web::Json<serde_json::Value>,
+) -> impl Responder {
+    HttpResponse::Ok()
+}
+
+#[actix_rt::test]
+async fn test_audit_log() {
+    let (_, _, pool) = prepare_config().await;
+    let db_pool = pool.clone();
+
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(db_pool))
+            .wrap(from_fn(audit_log))
+            .service(post_handler)
+            .service(post_user_handler),
+    )
+    .await;
 
-    let srv = actix_test::start(move || {
-        let db_pool = web::Data::new(pool.clone());
-        App::new().app_data(db_pool).service(login)
+    let payload = json!({
+        "name": "Channel 1",
+        "password": "hunter2",
+        "outputs": [{"s3_secret_key": "s3-secret", "Stream_Key": "live-key"}],
+        "mail": {"sender_pass": "mail-pass", "api_credentials": {"user": "cred-user"}}
     });
+    let req = test::TestRequest::post()
+        .uri("/api/channel/1")
+        .set_json(&payload)
+        .to_request();
+    let res = test::call_service(&app, req).await;
 
-    let payload = json!({"username": "admin", "password": "admin"});
+    assert!(res.status().is_success());
 
-    let res = srv.post("/auth/login/").send_json(&payload).await.unwrap();
+    let entries = handles::select_audit(&pool, 1, None, "", "9999-12-31 23:59:59")
+        .await
+        .unwrap();
+
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].method, "POST");
+    assert_eq!(entries[0].route, "/api/channel/1");
+    assert!(entries[0].payload.contains("Channel 1"));
+
+    for secret in ["hunter2", "s3-secret", "live-key", "mail-pass", "cred-user"] {
+        assert!(!entries[0].payload.contains(secret));
+    }
+
+    let req = test::TestRequest::post()
+        .uri("/api/user/2")
+        .set_json(json!({"username": "user"}))
+        .to_request();
+    let res = test::call_service(&app, req).await;
 
     assert!(res.status().is_success());
 
-    let payload = json!({"username": "admin", "password": "1234"});
+    let entries = handles::select_audit_global(&pool, "", "9999-12-31 23:59:59")
+        .await
+        .unwrap();
+
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].route, "/api/user/2");
+}
+
+#[actix_rt::test]
+async fn test_channel_keeps_public_embed() {
+    let (_, _, pool) = prepare_config().await;
+    let mut channel = handles::select_channel(&pool, &1).await.unwrap();
+
+    channel.public_embed = Some(true);
+    handles::update_channel(&pool, 1, channel.clone())
+        .await
+        .unwrap();
+
+    // a patch without the field keeps the stored value
+    channel.public_embed = None;
+    channel.name = "Channel 1 renamed".to_string();
+    handles::update_channel(&pool, 1, channel).await.unwrap();
+
+    let channel = handles::select_channel(&pool, &1).await.unwrap();
+
+    assert_eq!(channel.name, "Channel 1 renamed");
+    assert_eq!(channel.public_embed, Some(true));
+}
+
+#[actix_rt::test]
+async fn test_health() {
+    let (_, manager, pool) = prepare_config().await;
+
+    manager.channel.lock().unwrap().active = true;
+
+    let controllers = controllers(manager);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool))
+            .app_data(controllers.clone())
+            .service(health_live)
+            .service(health_ready),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/health/live").to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert!(res.status().is_success());
+
+    // active channel without running engine
+    let req = test::TestRequest::get().uri("/health/ready").to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert_eq!(res.status().as_u16(), 503);
+
+    let body: serde_json::Value = test::read_body_json(res).await;
+
+    assert_eq!(body["status"], "fail");
+    assert_eq!(body["checks"][0]["name"], "database");
+    assert_eq!(body["checks"][0]["ok"], true);
+    assert_eq!(body["checks"][0].get("message"), None);
+    assert_eq!(body.get("channels"), None);
+
+    let channels = body["checks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|c| c["name"] == "channels")
+        .unwrap();
+
+    assert_eq!(channels["ok"], false);
+
+    controllers.lock().unwrap().channels[0]
+        .channel
+        .lock()
+        .unwrap()
+        .active = false;
+
+    let req = test::TestRequest::get().uri("/health/ready").to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let channels = body["checks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|c| c["name"] == "channels")
+        .unwrap();
+
+    assert_eq!(channels["ok"], true);
+}
+
+#[get("/api/list")]
+async fn list_handler(list: ListQuery) -> Result<impl Responder, ServiceError> {
+    let items: Vec<serde_json::Value> = (1..=7)
+        .map(|i| json!({"id": i, "name": format!("clip-{}", 8 - i)}))
+        .collect();
+
+    Ok(web::Json(list.response(items)?))
+}
+
+#[actix_rt::test]
+async fn test_list_query() {
+    let app = test::init_service(App::new().service(list_handler)).await;
+    let list = |query: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/api/list{query}"))
+            .to_request()
+    };
+
+    // without parameters the plain list
+    let body: serde_json::Value = test::call_and_read_body_json(&app, list("")).await;
+
+    assert_eq!(body.as_array().unwrap().len(), 7);
+
+    let body: serde_json::Value =
+        test::call_and_read_body_json(&app, list("?page=3&per_page=3")).await;
+
+    assert_eq!(body["total"], 7);
+    assert_eq!(body["page"], 3);
+    assert_eq!(body["items"], json!([{"id": 7, "name": "clip-1"}]));
+
+    let body: serde_json::Value =
+        test::call_and_read_body_json(&app, list("?sort=name&per_page=2")).await;
+
+    assert_eq!(body["items"][0]["id"], 7);
+    assert_eq!(body["items"][1]["id"], 6);
+
+    let body: serde_json::Value =
+        test::call_and_read_body_json(&app, list("?sort=-id&filter=name:clip-")).await;
+
+    assert_eq!(body["total"], 7);
+    assert_eq!(body["items"][0]["id"], 7);
+
+    let body: serde_json::Value = test::call_and_read_body_json(&app, list("?filter=CLIP-3")).await;
+
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["items"][0]["id"], 5);
+
+    for query in ["page=0", "per_page=5000", "page=abc", "sort=size"] {
+        let res = test::call_service(&app, list(&format!("?{query}"))).await;
+
+        assert_eq!(res.status().as_u16(), 400);
+    }
+}
+
+#[actix_rt::test]
+async fn test_output_target_routes() {
+    let (_, manager, pool) = prepare_config().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool))
+            .app_data(controllers(manager))
+            .service(
+                web::scope("/api")
+                    .wrap(HttpAuthentication::bearer(validator))
+                    .service(get_output_targets)
+                    .service(add_output_target)
+                    .service(update_output_target)
+                    .service(enable_output_target)
+                    .service(delete_output_target),
+            ),
+    )
+    .await;
+    let admin = token(Role::ChannelAdmin, &Permission::ALL).await;
+    let operator = token(Role::User, &[Permission::ProcessControl]).await;
+    let target = json!({
+        "name": "Backup",
+        "url": "rtmp://127.0.0.1/live",
+        "stream_key": "live-key",
+        "enable": false
+    });
+
+    // users can not add targets, channel admins need the config permission
+    for auth in [
+        &operator,
+        &token(Role::ChannelAdmin, &[Permission::ProcessControl]).await,
+    ] {
+        let req = request(test::TestRequest::post().uri("/api/outputs/1/"), auth)
+            .set_json(&target)
+            .to_request();
+
+        assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+    }
+
+    let req = request(test::TestRequest::post().uri("/api/outputs/1/"), &admin)
+        .set_json(&target)
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let id = body["id"].as_i64().unwrap();
+
+    assert_ne!(body["stream_key"], "live-key");
 
-    let res = srv.post("/auth/login/").send_json(&payload).await.unwrap();
+    let req = request(test::TestRequest::get().uri("/api/outputs/1"), &operator).to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(body.as_array().unwrap().len(), 1);
+    assert_ne!(body[0]["stream_key"], "live-key");
+
+    // other channel
+    let req = request(test::TestRequest::get().uri("/api/outputs/2"), &operator).to_request();
+
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+
+    let req = request(
+        test::TestRequest::put().uri(&format!("/api/outputs/1/{id}/enable")),
+        &operator,
+    )
+    .set_json(json!({"enable": false}))
+    .to_request();
+
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+
+    let req = request(
+        test::TestRequest::put().uri(&format!("/api/outputs/1/{id}")),
+        &operator,
+    )
+    .set_json(&target)
+    .to_request();
+
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+
+    let req = request(
+        test::TestRequest::delete().uri(&format!("/api/outputs/1/{id}")),
+        &operator,
+    )
+    .to_request();
+
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+
+    for status in [200, 204] {
+        let req = request(
+            test::TestRequest::delete().uri(&format!("/api/outputs/1/{id}")),
+            &admin,
+        )
+        .to_request();
+
+        assert_eq!(
+            test::call_service(&app, req).await.status().as_u16(),
+            status
+        );
+    }
+}
+
+#[actix_rt::test]
+async fn test_region_routes() {
+    let (_, manager, pool) = prepare_config().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool))
+            .app_data(controllers(manager))
+            .service(
+                web::scope("/api")
+                    .wrap(HttpAuthentication::bearer(validator))
+                    .service(get_regions)
+                    .service(add_region)
+                    .service(update_region)
+                    .service(delete_region),
+            ),
+    )
+    .await;
+    let admin = token(Role::ChannelAdmin, &[Permission::ConfigEdit]).await;
+    let viewer = token(Role::ChannelAdmin, &[]).await;
+    let region = json!({"name": "North", "output_param": "-f null -"});
+
+    let req = request(test::TestRequest::post().uri("/api/regions/1/"), &viewer)
+        .set_json(&region)
+        .to_request();
+
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+
+    let req = request(test::TestRequest::post().uri("/api/regions/1/"), &admin)
+        .set_json(json!({"name": "North", "output_param": ""}))
+        .to_request();
+
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 400);
+
+    let req = request(test::TestRequest::post().uri("/api/regions/1/"), &admin)
+        .set_json(&region)
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let id = body["id"].as_i64().unwrap();
+
+    let req = request(test::TestRequest::get().uri("/api/regions/1"), &viewer).to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(body.as_array().unwrap().len(), 1);
+
+    for (auth, path, status) in [(&viewer, id, 403), (&admin, id, 200), (&admin, id + 1, 204)] {
+        let req = request(
+            test::TestRequest::put().uri(&format!("/api/regions/1/{path}")),
+            auth,
+        )
+        .set_json(&region)
+        .to_request();
+
+        assert_eq!(
+            test::call_service(&app, req).await.status().as_u16(),
+            status
+        );
+    }
+
+    for (auth, status) in [(&viewer, 403), (&admin, 200)] {
+        let req = request(
+            test::TestRequest::delete().uri(&format!("/api/regions/1/{id}")),
+            auth,
+        )
+        .to_request();
+
+        assert_eq!(
+            test::call_service(&app, req).await.status().as_u16(),
+            status
+        );
+    }
+}
+
+#[actix_rt::test]
+async fn test_script_routes() {
+    let (_, manager, pool) = prepare_config().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool))
+            .app_data(controllers(manager))
+            .service(
+                web::scope("/api")
+                    .wrap(HttpAuthentication::bearer(validator))
+                    .service(get_scripts)
+                    .service(add_script)
+                    .service(update_script)
+                    .service(delete_script),
+            ),
+    )
+    .await;
+    let admin = token(Role::ChannelAdmin, &[Permission::ConfigEdit]).await;
+    let viewer = token(Role::ChannelAdmin, &[]).await;
+    let user = token(Role::User, &Permission::ALL).await;
+
+    // only admins see the scripts
+    let req = request(test::TestRequest::get().uri("/api/scripts/1"), &user).to_request();
+
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+
+    for (auth, code, status) in [
+        (&viewer, "return {}", 403),
+        (&admin, "return io.open('/etc/passwd')", 400),
+        (&admin, "return {}", 200),
+    ] {
+        let req = request(test::TestRequest::post().uri("/api/scripts/1/"), auth)
+            .set_json(json!({"name": "hooks", "code": code}))
+            .to_request();
+
+        assert_eq!(
+            test::call_service(&app, req).await.status().as_u16(),
+            status
+        );
+    }
+
+    let req = request(test::TestRequest::get().uri("/api/scripts/1"), &viewer).to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let id = body[0]["id"].as_i64().unwrap();
+
+    assert_eq!(body[0]["name"], "hooks");
+
+    for (auth, path, status) in [(&viewer, id, 403), (&admin, id, 200), (&admin, id + 1, 204)] {
+        let req = request(
+            test::TestRequest::put().uri(&format!("/api/scripts/1/{path}")),
+            auth,
+        )
+        .set_json(json!({"name": "hooks", "code": "return {}", "enabled": false}))
+        .to_request();
+
+        assert_eq!(
+            test::call_service(&app, req).await.status().as_u16(),
+            status
+        );
+    }
+
+    let req = request(
+        test::TestRequest::delete().uri(&format!("/api/scripts/1/{id}")),
+        &admin,
+    )
+    .to_request();
+
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
+}
+
+#[actix_rt::test]
+async fn test_companion_routes() {
+    let (_, manager, pool) = prepare_config().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool))
+            .app_data(controllers(manager))
+            .service(
+                web::scope("/api")
+                    .wrap(HttpAuthentication::bearer(validator))
+                    .service(get_companions)
+                    .service(add_companion)
+                    .service(delete_companion)
+                    .service(start_companion)
+                    .service(companion_status),
+            ),
+    )
+    .await;
+    let global = token(Role::GlobalAdmin, &Permission::ALL).await;
+    let admin = token(Role::ChannelAdmin, &Permission::ALL).await;
+    let user = token(Role::User, &[]).await;
+    let companion = json!({"name": "relay", "command": "/bin/true", "restart": "never"});
+
+    // companions run commands, so only global admins manage them
+    for (auth, data, status) in [
+        (&admin, &companion, 403),
+        (
+            &global,
+            &json!({"name": "Relay", "command": "/bin/true"}),
+            400,
+        ),
+        (&global, &companion, 200),
+    ] {
+        let req = request(test::TestRequest::post().uri("/api/companions/1"), auth)
+            .set_json(data)
+            .to_request();
+
+        assert_eq!(
+            test::call_service(&app, req).await.status().as_u16(),
+            status
+        );
+    }
+
+    let req = request(test::TestRequest::get().uri("/api/companions/1"), &user).to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let id = body[0]["id"].as_i64().unwrap();
+
+    assert_eq!(body[0]["name"], "relay");
+
+    let req = request(test::TestRequest::get().uri("/api/companions/2"), &user).to_request();
+
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+
+    let req = request(
+        test::TestRequest::get().uri("/api/companions/1/relay/status"),
+        &user,
+    )
+    .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(body["name"], "relay");
+
+    // starting needs the process permission
+    let req = request(
+        test::TestRequest::post().uri("/api/companions/1/relay/start"),
+        &user,
+    )
+    .to_request();
+
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+
+    for (auth, status) in [(&admin, 403), (&global, 200), (&global, 204)] {
+        let req = request(
+            test::TestRequest::delete().uri(&format!("/api/companions/1/{id}")),
+            auth,
+        )
+        .to_request();
+
+        assert_eq!(
+            test::call_service(&app, req).await.status().as_u16(),
+            status
+        );
+    }
+}
+
+#[actix_rt::test]
+async fn test_backup_routes() {
+    prepare_config().await;
+
+    // VACUUM INTO copies an in-memory database only into memory, so the backup needs a file
+    let dir = std::env::temp_dir().join("ffplayout_backup_routes");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let pool = SqlitePoolOptions::new()
+        .connect(&format!(
+            "sqlite://{}?mode=rwc",
+            dir.join("ffplayout.db").display()
+        ))
+        .await
+        .unwrap();
+    handles::db_migrate(&pool).await.unwrap();
+
+    let app = test::init_service(
+        App::new().app_data(web::Data::new(pool)).service(
+            web::scope("/api")
+                .wrap(HttpAuthentication::bearer(validator))
+                .service(get_backup)
+                .service(restore_system),
+        ),
+    )
+    .await;
+    let global = token(Role::GlobalAdmin, &Permission::ALL).await;
+    let admin = token(Role::ChannelAdmin, &Permission::ALL).await;
+
+    let req = request(test::TestRequest::get().uri("/api/system/backup"), &admin).to_request();
+
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+
+    let req = request(test::TestRequest::get().uri("/api/system/backup"), &global).to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert_eq!(res.status().as_u16(), 200);
+
+    let archive = test::read_body(res).await;
+    let files = tar_read(&archive).unwrap();
+
+    for name in ["manifest.json", "ffplayout.db", "channels/1/config.json"] {
+        assert!(files.iter().any(|(n, _)| n == name), "{name} missing");
+    }
+
+    // the restore is not tested with a valid archive, it stops the engine
+    for (auth, status) in [(&admin, 403), (&global, 400)] {
+        let req = request(test::TestRequest::post().uri("/api/system/restore"), auth)
+            .insert_header((header::CONTENT_TYPE, "application/x-tar"))
+            .set_payload("no archive")
+            .to_request();
+
+        assert_eq!(
+            test::call_service(&app, req).await.status().as_u16(),
+            status
+        );
+    }
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[actix_rt::test]
+async fn test_nowplaying() {
+    let (_, manager, _) = prepare_config().await;
+    let controllers = controllers(manager);
+    let app = test::init_service(
+        App::new()
+            .app_data(controllers.clone())
+            .service(get_nowplaying),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/nowplaying/1")
+        .to_request();
+
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 403);
+
+    {
+        let manager = controllers.lock().unwrap().get(1).unwrap();
+        let mut config = manager.config.lock().unwrap();
+
+        config.channel.public_nowplaying = true;
+        config.channel.nowplaying_token = "widget".to_string();
+    }
+
+    for (uri, auth, status) in [
+        ("/api/nowplaying/1", "", 401),
+        ("/api/nowplaying/1?token=other", "", 401),
+        ("/api/nowplaying/1?token=widget", "", 200),
+        ("/api/nowplaying/1", "Bearer widget", 200),
+    ] {
+        let mut req = test::TestRequest::get().uri(uri);
+
+        if !auth.is_empty() {
+            req = request(req, auth);
+        }
+
+        let res = test::call_service(&app, req.to_request()).await;
+
+        assert_eq!(res.status().as_u16(), status);
+
+        if status == 200 {
+            let body: serde_json::Value = test::read_body_json(res).await;
+
+            // the channel is not running
+            assert_eq!(body["current"], serde_json::Value::Null);
+        }
+    }
+}
+
+#[actix_rt::test]
+async fn test_password_reset() {
+    let (_, _, pool) = prepare_config().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .service(login)
+            .service(reset_request)
+            .service(reset_password),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/auth/reset-request/")
+        .set_json(json!({"username": "admin"}))
+        .to_request();
+
+    // no mail server in the settings
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 503);
+
+    let user = handles::select_login(&pool, "admin").await.unwrap();
+    let secret = GLOBAL_SETTINGS.get().unwrap().secret.clone().unwrap();
+    let expires = time_now().timestamp() + 3600;
+    let reset = reset_token(&secret, user.id, expires);
+
+    handles::insert_password_reset(&pool, user.id, &token_hash(&reset), expires)
+        .await
+        .unwrap();
 
-    assert_eq!(res.status().as_u16(), 403);
+    // the token works only once
+    for (reset, status) in [
+        ("invalid.token", 403),
+        (reset.as_str(), 200),
+        (reset.as_str(), 403),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/auth/reset/")
+            .set_json(json!({"token": reset, "password": "Reset-Passw0rd!"}))
+            .to_request();
 
-    let payload = json!({"username": "aaa", "password": "1234"});
+        assert_eq!(
+            test::call_service(&app, req).await.status().as_u16(),
+            status
+        );
+    }
 
-    let res = srv.post("/auth/login/").send_json(&payload).await.unwrap();
+    let req = test::TestRequest::post()
+        .uri("/auth/login/")
+        .set_json(json!({"username": "admin", "password": "Reset-Passw0rd!"}))
+        .to_request();
 
-    assert_eq!(res.status().as_u16(), 400);
+    assert_eq!(test::call_service(&app, req).await.status().as_u16(), 200);
 }
@@ -0,0 +1,470 @@
+/*
+Fake ffmpeg, ffprobe, ffplay and streamlink for tests without a real ffmpeg.
+
+The tool is chosen by the name of the binary, the test harness links this binary under all names
+into a temporary folder and puts it in front of PATH. The fake does not decode anything:
+
+- decoders, which write to stdout, send dummy packets in real time for the clip length
+- encoders, which read from stdin, consume everything until the input is closed
+- jobs, which write files (thumbnails, transcoding), write a fake media file right away
+- the ingest server waits until the trigger file exists, then sends packets for its seconds
+- ffprobe describes fake media files from their header, other files get default values
+
+Fake media files are text files, which start with `ffplayout-fake-media`, followed by `key=value`
+lines: `duration`, `width`, `height`, `fps`, `video`, `audio` and `fail`.
+
+Environment:
+
+- `FFPLAYOUT_FAKE_LOG`: every call is appended to this file, one line per call
+- `FFPLAYOUT_FAKE_INGEST`: trigger file for the ingest server, it contains the seconds to send
+- `FFPLAYOUT_FAKE_SPEED`: play faster than real time
+- `FFPLAYOUT_FAKE_DURATION`: length of files, which are no fake media, default 2 seconds
+
+*/
+
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::{self, Read, Write},
+    path::Path,
+    process::exit,
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+const HEADER: &str = "ffplayout-fake-media";
+const PACKET: usize = 188;
+/// Time between packets from a decoder.
+const TICK: Duration = Duration::from_millis(40);
+/// Maximal time, which the ingest server waits for the trigger.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+const FILTERS: [&str; 34] = [
+    "acrossfade",
+    "afade",
+    "aevalsrc",
+    "amix",
+    "anull",
+    "anullsrc",
+    "apad",
+    "aresample",
+    "asplit",
+    "blackdetect",
+    "color",
+    "drawtext",
+    "ebur128",
+    "fade",
+    "format",
+    "fps",
+    "freezedetect",
+    "loudnorm",
+    "null",
+    "overlay",
+    "pad",
+    "scale",
+    "setdar",
+    "setsar",
+    "silencedetect",
+    "sine",
+    "split",
+    "subtitles",
+    "testsrc2",
+    "tile",
+    "tpad",
+    "volume",
+    "xfade",
+    "zmq",
+];
+
+#[derive(Debug, Clone)]
+struct FakeMedia {
+    duration: f64,
+    width: i64,
+    height: i64,
+    fps: f64,
+    video: bool,
+    audio: bool,
+    fail: bool,
+}
+
+impl FakeMedia {
+    fn new(duration: f64) -> Self {
+        Self {
+            duration,
+            width: 1024,
+            height: 576,
+            fps: 25.0,
+            video: true,
+            audio: true,
+            fail: false,
+        }
+    }
+
+    /// Fake media from a file, real files get the default values.
+    fn read(path: &str) -> Option<Self> {
+        if !Path::new(path).is_file() {
+            return None;
+        }
+
+        let mut media = Self::new(default_duration());
+
+        if is_image(path) {
+            media.duration = 0.04;
+            media.audio = false;
+        }
+
+        let Ok(content) = fs::read_to_string(path) else {
+            return Some(media);
+        };
+
+        if !content.starts_with(HEADER) {
+            return Some(media);
+        }
+
+        let values: HashMap<&str, &str> = content
+            .lines()
+            .skip(1)
+            .filter_map(|l| l.split_once('='))
+            .map(|(k, v)| (k.trim(), v.trim()))
+            .collect();
+        let flag = |key: &str, default: bool| values.get(key).map_or(default, |v| *v == "1");
+
+        media.duration = values
+            .get("duration")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(media.duration);
+        media.width = values
+            .get("width")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(media.width);
+        media.height = values
+            .get("height")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(media.height);
+        media.fps = values
+            .get("fps")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(media.fps);
+        media.video = flag("video", media.video);
+        media.audio = flag("audio", media.audio);
+        media.fail = flag("fail", false);
+
+        Some(media)
+    }
+
+    fn write(&self, path: &str) -> io::Result<()> {
+        fs::write(
+            path,
+            format!(
+                "{HEADER}\nduration={}\nwidth={}\nheight={}\nfps={}\nvideo={}\naudio={}\n",
+                self.duration,
+                self.width,
+                self.height,
+                self.fps,
+                u8::from(self.video),
+                u8::from(self.audio)
+            ),
+        )
+    }
+}
+
+fn default_duration() -> f64 {
+    env::var("FFPLAYOUT_FAKE_DURATION")
+        .ok()
+        .and_then(|d| d.parse().ok())
+        .unwrap_or(2.0)
+}
+
+fn speed() -> f64 {
+    env::var("FFPLAYOUT_FAKE_SPEED")
+        .ok()
+        .and_then(|s| s.parse::<f64>().ok())
+        .filter(|s| *s > 0.0)
+        .unwrap_or(1.0)
+}
+
+fn is_image(path: &str) -> bool {
+    let path = path.to_lowercase();
+
+    [".png", ".jpg", ".jpeg", ".bmp", ".gif", ".webp"]
+        .iter()
+        .any(|e| path.ends_with(e))
+}
+
+fn log_call(tool: &str, args: &[String]) {
+    if let Ok(log) = env::var("FFPLAYOUT_FAKE_LOG") {
+        if let Ok(mut file) = fs::OpenOptions::new().create(true).append(true).open(log) {
+            let _ = writeln!(file, "{tool}\u{1f}{}", args.join("\u{1f}"));
+        }
+    }
+}
+
+fn value_after<'a>(args: &'a [String], key: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == key)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+fn inputs(args: &[String]) -> Vec<&str> {
+    args.windows(2)
+        .filter(|w| w[0] == "-i")
+        .map(|w| w[1].as_str())
+        .collect()
+}
+
+/// Length from lavfi sources, like `color=c=black:d=30`.
+fn lavfi_duration(input: &str) -> Option<f64> {
+    input
+        .split([':', ','])
+        .filter_map(|p| p.split_once('='))
+        .find(|(k, _)| *k == "d" || *k == "duration")
+        .and_then(|(_, v)| v.parse().ok())
+}
+
+/// Length of the output, from `-t`, `-to` or the first input.
+fn output_duration(args: &[String]) -> f64 {
+    if let Some(t) = value_after(args, "-t").and_then(|t| t.parse().ok()) {
+        return t;
+    }
+
+    let seek: f64 = value_after(args, "-ss")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_default();
+
+    if let Some(to) = value_after(args, "-to").and_then(|t| t.parse::<f64>().ok()) {
+        return (to - seek).max(0.0);
+    }
+
+    let input = inputs(args).first().copied().unwrap_or_default();
+    let length = lavfi_duration(input)
+        .or_else(|| FakeMedia::read(input).map(|m| m.duration))
+        .unwrap_or_else(default_duration);
+
+    (length - seek).max(0.0)
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{message}");
+    exit(1);
+}
+
+/// Send dummy transport stream packets in real time.
+fn produce(seconds: f64) {
+    let mut packet = [0_u8; PACKET];
+    packet[0] = 0x47;
+    let chunk = packet.repeat(20);
+    let end = Duration::from_secs_f64(seconds / speed());
+    let start = Instant::now();
+    let mut stdout = io::stdout().lock();
+
+    while start.elapsed() < end {
+        if stdout
+            .write_all(&chunk)
+            .and_then(|_| stdout.flush())
+            .is_err()
+        {
+            // reader is gone
+            exit(0);
+        }
+
+        sleep(TICK.min(end.saturating_sub(start.elapsed())));
+    }
+}
+
+/// Read stdin until it is closed, written files get the data.
+fn consume(output: Option<&str>) {
+    let mut buffer = [0_u8; 65536];
+    let mut stdin = io::stdin().lock();
+    let mut file = output.and_then(|o| fs::File::create(o).ok());
+
+    while let Ok(len) = stdin.read(&mut buffer) {
+        if len == 0 {
+            break;
+        }
+
+        if let Some(f) = file.as_mut() {
+            let _ = f.write_all(&buffer[..len]);
+        }
+    }
+}
+
+fn ingest_server() {
+    let Ok(trigger) = env::var("FFPLAYOUT_FAKE_INGEST") else {
+        sleep(IDLE_TIMEOUT);
+        return;
+    };
+    let start = Instant::now();
+
+    while start.elapsed() < IDLE_TIMEOUT {
+        if let Ok(content) = fs::read_to_string(&trigger) {
+            let _ = fs::remove_file(&trigger);
+            let seconds = content.trim().parse().unwrap_or(2.0);
+
+            produce(seconds);
+
+            return;
+        }
+
+        sleep(Duration::from_millis(100));
+    }
+}
+
+fn print_filters() {
+    eprintln!("ffmpeg version 7.1-fake Copyright (c) 2000-2024 the FFmpeg developers");
+    eprintln!(
+        "  configuration: --enable-gpl --enable-version3 --enable-libx264 --enable-libzmq --enable-libfreetype --enable-libsrt"
+    );
+    println!("Filters:");
+
+    for filter in FILTERS {
+        println!(" ... {filter:<16} N->N       Fake filter.");
+    }
+}
+
+fn ffmpeg(args: &[String]) {
+    if args.is_empty() {
+        fail("Hyper fast Audio and Video encoder");
+    }
+
+    if args.iter().any(|a| a == "-version") {
+        println!("ffmpeg version 7.1-fake Copyright (c) 2000-2024 the FFmpeg developers");
+        return;
+    }
+
+    if args.iter().any(|a| a == "-filters") {
+        print_filters();
+        return;
+    }
+
+    if args.windows(2).any(|w| w[0] == "-listen" && w[1] == "1") {
+        ingest_server();
+        return;
+    }
+
+    let inputs = inputs(args);
+    let output = args.last().map(String::as_str).unwrap_or_default();
+    let to_stdout = output == "-" || output == "pipe:1";
+    let null_format = value_after(args, "-f") == Some("null") && to_stdout;
+
+    for input in &inputs {
+        if let Some(media) = FakeMedia::read(input) {
+            if media.fail {
+                fail(&format!(
+                    "{input}: Invalid data found when processing input"
+                ));
+            }
+        } else if !(*input == "-"
+            || input.starts_with("pipe:")
+            || input.contains("://")
+            || lavfi_duration(input).is_some()
+            || value_after(args, "-f") == Some("lavfi"))
+        {
+            fail(&format!("{input}: No such file or directory"));
+        }
+    }
+
+    if inputs.iter().any(|i| *i == "-" || *i == "pipe:0") {
+        let file = (!to_stdout && !output.contains("://")).then_some(output);
+
+        consume(file);
+    } else if to_stdout && !null_format {
+        produce(output_duration(args));
+    } else {
+        let duration = output_duration(args);
+
+        if !to_stdout && !output.contains("://") {
+            let mut media = inputs
+                .first()
+                .and_then(|i| FakeMedia::read(i))
+                .unwrap_or_else(|| FakeMedia::new(duration));
+            media.duration = duration;
+            media.video = media.video && !args.iter().any(|a| a == "-vn");
+
+            if let Err(e) = media.write(output) {
+                fail(&format!("{output}: {e}"));
+            }
+        }
+
+        if value_after(args, "-progress") == Some("pipe:1") {
+            println!("out_time_us={}", (duration * 1_000_000.0) as i64);
+            println!("progress=end");
+        }
+    }
+}
+
+fn stream_json(media: &FakeMedia) -> String {
+    let disposition = r#""disposition": {"default": 1, "dub": 0, "original": 0, "comment": 0, "lyrics": 0, "karaoke": 0, "forced": 0, "hearing_impaired": 0, "visual_impaired": 0, "clean_effects": 0, "attached_pic": 0, "timed_thumbnails": 0, "non_diegetic": 0, "captions": 0, "descriptions": 0, "metadata": 0, "dependent": 0, "still_image": 0}"#;
+    let mut streams = vec![];
+    let fps = format!("{}/1", media.fps.round() as i64);
+
+    if media.video {
+        streams.push(format!(
+            r#"{{"index": {}, "codec_name": "h264", "codec_long_name": "H.264 / AVC / MPEG-4 AVC / MPEG-4 part 10", "profile": "High", "codec_type": "video", "codec_tag_string": "avc1", "codec_tag": "0x31637661", "width": {}, "height": {}, "coded_width": {}, "coded_height": {}, "closed_captions": 0, "film_grain": 0, "has_b_frames": 2, "sample_aspect_ratio": "1:1", "display_aspect_ratio": "16:9", "pix_fmt": "yuv420p", "level": 40, "color_range": "tv", "chroma_location": "left", "field_order": "progressive", "refs": 1, "is_avc": "true", "nal_length_size": "4", "id": "0x1", "r_frame_rate": "{fps}", "avg_frame_rate": "{fps}", "time_base": "1/12800", "start_pts": 0, "start_time": "0.000000", "duration_ts": {}, "duration": "{:.6}", "bit_rate": "2000000", "bits_per_raw_sample": "8", "nb_frames": "{}", "extradata_size": 48, {disposition}}}"#,
+            streams.len(),
+            media.width,
+            media.height,
+            media.width,
+            media.height,
+            (media.duration * 12800.0) as i64,
+            media.duration,
+            (media.duration * media.fps) as i64,
+        ));
+    }
+
+    if media.audio {
+        streams.push(format!(
+            r#"{{"index": {}, "codec_name": "aac", "codec_long_name": "AAC (Advanced Audio Coding)", "profile": "LC", "codec_type": "audio", "codec_tag_string": "mp4a", "codec_tag": "0x6134706d", "id": "0x2", "sample_fmt": "fltp", "sample_rate": "48000", "channels": 2, "channel_layout": "stereo", "bits_per_sample": 0, "r_frame_rate": "0/0", "avg_frame_rate": "0/0", "time_base": "1/48000", "start_pts": 0, "start_time": "0.000000", "duration_ts": {}, "duration": "{:.6}", "bit_rate": "128000", "nb_frames": "{}", "extradata_size": 2, {disposition}}}"#,
+            streams.len(),
+            (media.duration * 48000.0) as i64,
+            media.duration,
+            (media.duration * 48000.0 / 1024.0) as i64,
+        ));
+    }
+
+    format!("[{}]", streams.join(", "))
+}
+
+fn ffprobe(args: &[String]) {
+    let input = args.last().map(String::as_str).unwrap_or_default();
+    let Some(media) = FakeMedia::read(input) else {
+        fail(&format!("{input}: No such file or directory"));
+    };
+
+    if media.fail {
+        fail(&format!(
+            "{input}: Invalid data found when processing input"
+        ));
+    }
+
+    let streams = stream_json(&media);
+    let count = usize::from(media.video) + usize::from(media.audio);
+    let size = fs::metadata(input).map(|m| m.len()).unwrap_or_default();
+    let name = input.replace('\\', "\\\\").replace('"', "\\\"");
+
+    println!(
+        r#"{{"streams": {streams}, "format": {{"filename": "{name}", "nb_streams": {count}, "nb_programs": 0, "format_name": "mov,mp4,m4a,3gp,3g2,mj2", "format_long_name": "QuickTime / MOV", "start_time": "0.000000", "duration": "{:.6}", "size": "{size}", "bit_rate": "2128000", "probe_score": 100, "tags": {{"encoder": "Lavf61.7.100"}}}}}}"#,
+        media.duration
+    );
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let tool = env::args()
+        .next()
+        .and_then(|a| {
+            Path::new(&a)
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+        })
+        .unwrap_or_default();
+
+    log_call(&tool, &args);
+
+    match tool.as_str() {
+        "ffprobe" => ffprobe(&args),
+        "ffplay" => consume(None),
+        "streamlink" => produce(default_duration()),
+        _ => ffmpeg(&args),
+    }
+}
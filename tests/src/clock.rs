@@ -0,0 +1,66 @@
+//! The tests in this binary replace the global clock, so they run apart from the other tests,
+//! which read the time while they run.
+use std::{
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::{prelude::*, TimeDelta};
+use serial_test::serial;
+
+use ffplayout::player::utils::get_date;
+use ffplayout::utils::time_machine::{
+    clock_sleep, reset_clock, set_clock, time_now, ManualClock, SimulatedClock,
+};
+
+#[test]
+#[serial]
+fn manual_clock_midnight() {
+    let clock = Arc::new(ManualClock::new(
+        Local.with_ymd_and_hms(2022, 5, 20, 23, 59, 58).unwrap(),
+    ));
+    set_clock(clock.clone());
+
+    assert_eq!(get_date(false, 0.0, true), "2022-05-21");
+    assert_eq!(get_date(true, 21600.0, false), "2022-05-20");
+
+    clock.advance(TimeDelta::seconds(4));
+
+    assert_eq!(time_now().format("%H:%M:%S").to_string(), "00:00:02");
+    assert_eq!(get_date(true, 21600.0, false), "2022-05-20");
+    assert_eq!(get_date(true, 0.0, false), "2022-05-21");
+
+    reset_clock();
+}
+
+#[test]
+#[serial]
+fn simulated_clock_speed() {
+    let start = Local.with_ymd_and_hms(2022, 5, 20, 12, 0, 0).unwrap();
+    set_clock(SimulatedClock::new(start, 3600.0));
+
+    thread::sleep(Duration::from_millis(100));
+
+    // 100ms real time are around 6 minutes in simulation
+    assert!(time_now() - start >= TimeDelta::minutes(5));
+
+    reset_clock();
+}
+
+#[test]
+#[serial]
+fn simulated_clock_sleep() {
+    let start = Local.with_ymd_and_hms(2022, 5, 20, 12, 0, 0).unwrap();
+    set_clock(SimulatedClock::new(start, 600.0));
+
+    let instant = Instant::now();
+
+    // one minute in simulation are 100ms real time
+    clock_sleep(Duration::from_secs(60));
+
+    assert!(instant.elapsed() < Duration::from_secs(1));
+    assert!(time_now() - start >= TimeDelta::seconds(60));
+
+    reset_clock();
+}
@@ -6,11 +6,15 @@ use tokio::runtime::Runtime;
 use ffplayout::db::handles;
 use ffplayout::player::{
     controller::{ChannelManager, ProcessUnit::*},
-    input::playlist::gen_source,
+    filter::crossfade_duration,
+    input::{
+        dead_air::{parse_dead_air, DeadAirKind},
+        playlist::gen_source,
+    },
     utils::prepare_output_cmd,
-    utils::Media,
+    utils::{Crossfade, Media},
 };
-use ffplayout::utils::config::{OutputMode::*, PlayoutConfig};
+use ffplayout::utils::config::{OutputMode::*, PlayoutConfig, Transition, TransitionKind};
 use ffplayout::vec_strings;
 
 async fn prepare_config() -> (PlayoutConfig, ChannelManager) {
@@ -70,6 +74,195 @@ fn video_audio_input() {
     assert_eq!(media.filter.unwrap().map(), test_filter_map);
 }
 
+#[test]
+fn video_audio_transition_input() {
+    let (mut config, manager) = get_config();
+
+    config.output.mode = Stream;
+    config.processing.add_logo = false;
+    config.processing.transition = TransitionKind::Fade;
+    config.processing.transition_frames = 25;
+
+    let media_obj = Media::new(0, "./assets/media_mix/with_audio.mp4", true);
+    let media = gen_source(&config, media_obj, &manager, 1);
+    let fade_out = media.out - media.seek - 1.0;
+
+    let test_filter_cmd = vec_strings![
+        "-filter_complex",
+        format!("[0:v:0]scale=1024:576,fade=in:st=0:d=1,fade=out:st={fade_out}:d=1[vout0];[0:a:0]anull,afade=in:st=0:d=1,afade=out:st={fade_out}:d=1[aout0]")
+    ];
+
+    assert_eq!(media.filter.unwrap().cmd(), test_filter_cmd);
+}
+
+#[test]
+fn video_audio_dead_air_input() {
+    let (mut config, manager) = get_config();
+
+    config.output.mode = Stream;
+    config.processing.add_logo = false;
+    config.processing.dead_air_duration = 5.0;
+
+    let media_obj = Media::new(0, "./assets/media_mix/with_audio.mp4", true);
+    let media = gen_source(&config, media_obj, &manager, 1);
+
+    let test_filter_cmd = vec_strings![
+        "-filter_complex",
+        "[0:v:0]scale=1024:576,freezedetect=d=5[vout0];[0:a:0]anull,silencedetect=n=-60dB:d=5[aout0]"
+    ];
+
+    assert_eq!(media.filter.unwrap().cmd(), test_filter_cmd);
+
+    assert_eq!(
+        parse_dead_air("[freezedetect @ 0x5581e0c0] [info] lavfi.freezedetect.freeze_start: 12.48"),
+        Some((DeadAirKind::Freeze, 12.48))
+    );
+    assert_eq!(
+        parse_dead_air("[silencedetect @ 0x5581e0c0] [info] silence_start: 3"),
+        Some((DeadAirKind::Silence, 3.0))
+    );
+    assert_eq!(
+        parse_dead_air("[silencedetect @ 0x5581e0c0] [info] silence_end: 9 | silence_duration: 6"),
+        None
+    );
+}
+
+#[test]
+fn video_audio_switch_fade_input() {
+    let (mut config, manager) = get_config();
+
+    config.output.mode = Stream;
+    config.processing.add_logo = false;
+
+    let mut media_obj = Media::new(0, "./assets/media_mix/with_audio.mp4", true);
+    media_obj.fade_in = Some(1.5);
+    let media = gen_source(&config, media_obj, &manager, 1);
+
+    let test_filter_cmd = vec_strings![
+        "-filter_complex",
+        "[0:v:0]scale=1024:576,fade=in:st=0:d=1.5[vout0];[0:a:0]anull,afade=in:st=0:d=1.5[aout0]"
+    ];
+
+    assert_eq!(media.filter.unwrap().cmd(), test_filter_cmd);
+}
+
+#[test]
+fn video_audio_subtitle_burn_input() {
+    let (mut config, manager) = get_config();
+
+    config.output.mode = Stream;
+    config.processing.add_logo = false;
+    config.processing.subtitle_burn = true;
+    let subtitle = fs::canonicalize("./assets/media_mix/subtitle.vtt").unwrap();
+
+    let mut media_obj = Media::new(0, "./assets/media_mix/with_audio.mp4", true);
+    media_obj.subtitle = Some(subtitle.to_string_lossy().to_string());
+    let media = gen_source(&config, media_obj, &manager, 1);
+
+    let test_filter_cmd = vec_strings![
+        "-filter_complex",
+        format!(
+            "[0:v:0]scale=1024:576,subtitles=filename={}[vout0];[0:a:0]anull[aout0]",
+            subtitle.to_string_lossy()
+        )
+    ];
+
+    assert_eq!(media.filter.unwrap().cmd(), test_filter_cmd);
+}
+
+#[test]
+fn video_audio_transition_override_input() {
+    let (mut config, manager) = get_config();
+
+    config.output.mode = Stream;
+    config.processing.add_logo = false;
+    config.processing.transition = TransitionKind::Fade;
+    config.processing.transition_frames = 25;
+
+    let mut media_obj = Media::new(0, "./assets/media_mix/with_audio.mp4", true);
+    media_obj.transition = Some(Transition {
+        kind: Some(TransitionKind::Audio),
+        frames: Some(50),
+        audio_frames: None,
+    });
+    let media = gen_source(&config, media_obj, &manager, 1);
+    let fade_out = media.out - media.seek - 2.0;
+
+    let test_filter_cmd = vec_strings![
+        "-filter_complex",
+        format!("[0:v:0]scale=1024:576[vout0];[0:a:0]anull,afade=in:st=0:d=2,afade=out:st={fade_out}:d=2[aout0]")
+    ];
+
+    assert_eq!(media.filter.unwrap().cmd(), test_filter_cmd);
+}
+
+#[test]
+fn video_audio_transition_audio_frames_input() {
+    let (mut config, manager) = get_config();
+
+    config.output.mode = Stream;
+    config.processing.add_logo = false;
+    config.processing.transition = TransitionKind::Fade;
+    config.processing.transition_frames = 25;
+    config.processing.transition_audio_frames = 50;
+
+    let media_obj = Media::new(0, "./assets/media_mix/with_audio.mp4", true);
+    let media = gen_source(&config, media_obj, &manager, 1);
+    let length = media.out - media.seek;
+
+    let test_filter_cmd = vec_strings![
+        "-filter_complex",
+        format!("[0:v:0]scale=1024:576,fade=in:st=0:d=1,fade=out:st={}:d=1[vout0];[0:a:0]anull,afade=in:st=0:d=2,afade=out:st={}:d=2[aout0]", length - 1.0, length - 2.0)
+    ];
+
+    assert_eq!(media.filter.unwrap().cmd(), test_filter_cmd);
+}
+
+#[test]
+fn video_audio_crossfade_input() {
+    let (mut config, manager) = get_config();
+
+    config.output.mode = Stream;
+    config.processing.add_logo = false;
+    config.processing.transition = TransitionKind::Crossfade;
+    config.processing.transition_frames = 25;
+
+    let mut media_obj = Media::new(0, "./assets/media_mix/with_audio.mp4", true);
+    let next = Media::new(1, "./assets/media_mix/with_audio.mp4", true);
+    let duration = crossfade_duration(&media_obj, &next, &config);
+
+    assert_eq!(duration, Some(1.0));
+
+    media_obj.crossfade = Some(Crossfade {
+        source: next.source,
+        seek: 0.0,
+        duration: 1.0,
+        has_audio: true,
+        input: 0,
+    });
+
+    let media = gen_source(&config, media_obj, &manager, 1);
+    let offset = media.out - media.seek - 1.0;
+
+    let test_filter_cmd = vec_strings![
+        "-filter_complex",
+        format!("[0:v:0]scale=1024:576,setsar=1,fps=25[xf_v_in];[1:v:0]scale=1024:576:force_original_aspect_ratio=decrease,pad=1024:576:(ow-iw)/2:(oh-ih)/2,setsar=1,fps=25[xf_v_next];[xf_v_in][xf_v_next]xfade=transition=fade:duration=1:offset={offset}[vout0];[0:a:0]anull,anull[xf_a_in];[xf_a_in][1:a:0]acrossfade=d=1[aout0]")
+    ];
+
+    assert_eq!(
+        media.cmd,
+        Some(vec_strings![
+            "-i",
+            "./assets/media_mix/with_audio.mp4",
+            "-t",
+            "1",
+            "-i",
+            "./assets/media_mix/with_audio.mp4"
+        ])
+    );
+    assert_eq!(media.filter.unwrap().cmd(), test_filter_cmd);
+}
+
 #[test]
 fn video_audio_custom_filter1_input() {
     let (mut config, manager) = get_config();
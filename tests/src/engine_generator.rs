@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     fs,
     path::{Path, PathBuf},
 };
@@ -8,11 +9,17 @@ use sqlx::sqlite::SqlitePoolOptions;
 use tokio::runtime::Runtime;
 
 use ffplayout::db::handles;
-use ffplayout::player::{controller::ChannelManager, utils::*};
+use ffplayout::player::{
+    controller::ChannelManager,
+    utils::{
+        filler::{FillerHistory, FillerRules},
+        *,
+    },
+};
 use ffplayout::utils::config::ProcessMode::Playlist;
 use ffplayout::utils::playlist::generate_playlist;
 use ffplayout::utils::{
-    config::{PlayoutConfig, Source, Template},
+    config::{PlayoutConfig, ScheduleRules, Source, Template},
     generator::*,
 };
 
@@ -90,11 +97,115 @@ fn test_filler_list() {
 
     config.storage.filler = "assets/".into();
 
-    let f_list = filler_list(&config, 2440.0);
+    let f_list = filler_list(&config, 2440.0, &mut FillerHistory::default());
 
     assert_eq!(sum_durations(&f_list), 2440.0);
 }
 
+#[test]
+fn test_filler_rules() {
+    let clip = |src: &str, category: &str, duration: f64| {
+        let mut media = Media::new(0, src, false);
+        media.category = category.to_string();
+        media.duration = duration;
+        media.out = duration;
+        media
+    };
+    let fillers = vec![
+        clip("a.mp4", "", 60.0),
+        clip("promo.mp4", "promos", 30.0),
+        clip("b.mp4", "", 20.0),
+    ];
+    let rules = FillerRules {
+        max_repeat: 1,
+        fit_duration: true,
+        exclude: vec!["promos".to_string()],
+    };
+    let mut history = FillerHistory::new("2024-10-27");
+
+    // a is too long for the gap, promos are excluded
+    assert_eq!(history.select(&fillers, &rules, 0, 25.0), Some(2));
+    assert_eq!(history.select(&fillers, &rules, 0, 100.0), Some(0));
+    // all reached the limit, don't repeat the last clip
+    assert_eq!(history.select(&fillers, &rules, 0, 100.0), Some(2));
+    assert_eq!(history.plays("b.mp4"), 2);
+    assert_eq!(history.plays("promo.mp4"), 0);
+
+    history.set_date("2024-10-28");
+
+    assert_eq!(history.plays("b.mp4"), 0);
+}
+
+#[test]
+fn test_schedule_rules() {
+    let clip = |src: &str, category: &str| {
+        let mut media = Media::new(0, src, false);
+        media.category = category.to_string();
+        media.duration = 600.0;
+        media.out = 600.0;
+        media
+    };
+    let clips = vec![
+        clip("/tv/series/show_a/ep1.mp4", "series"),
+        clip("/tv/series/show_a/ep2.mp4", "series"),
+        clip("/tv/series/show_a/ep3.mp4", "series"),
+        clip("/tv/series/show_b/ep1.mp4", "series"),
+        clip("/tv/music/clip1.mp4", "music"),
+        clip("/tv/music/clip2.mp4", "music"),
+    ];
+    let rules = ScheduleRules {
+        max_series_run: 2,
+        min_repeat_hours: 1.0,
+    };
+    let mut history = ScheduleHistory::default();
+
+    history.set_date("2024-11-04");
+
+    let list = ruled_list(
+        &clips[..4],
+        2400.0,
+        false,
+        &rules,
+        &HashMap::new(),
+        &mut history,
+    );
+    let sources: Vec<&str> = list.iter().map(|m| m.source.as_str()).collect();
+
+    // the third episode from show_a has to wait
+    assert_eq!(
+        sources,
+        vec![
+            "/tv/series/show_a/ep1.mp4",
+            "/tv/series/show_a/ep2.mp4",
+            "/tv/series/show_b/ep1.mp4",
+            "/tv/series/show_a/ep3.mp4",
+        ]
+    );
+    assert_eq!(series_name(&clips[0]), "show_a");
+
+    let quotas = HashMap::from([("music".to_string(), 0.25)]);
+    let list = ruled_list(&clips[4..], 2400.0, false, &rules, &quotas, &mut history);
+
+    assert_eq!(sum_durations(&list), 600.0);
+
+    let rules = ScheduleRules {
+        max_series_run: 0,
+        min_repeat_hours: 24.0,
+    };
+
+    history.set_date("2024-11-05");
+
+    // all clips played less then 24 hours ago
+    assert!(ruled_list(&clips, 2400.0, true, &rules, &HashMap::new(), &mut history).is_empty());
+
+    history.set_date("2024-11-06");
+
+    assert_eq!(
+        ruled_list(&clips, 3600.0, true, &rules, &HashMap::new(), &mut history).len(),
+        6
+    );
+}
+
 #[test]
 #[ignore]
 fn test_generate_playlist_from_folder() {
@@ -137,14 +248,17 @@ fn test_generate_playlist_from_template() {
                 duration: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
                 shuffle: false,
                 paths: vec![PathBuf::from("assets/")],
+                quotas: HashMap::new(),
             },
             Source {
                 start: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
                 duration: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
                 shuffle: true,
                 paths: vec![PathBuf::from("assets/")],
+                quotas: HashMap::new(),
             },
         ],
+        rules: ScheduleRules::default(),
     });
     config.processing.mode = Playlist;
     config.storage.filler = "assets/".into();
@@ -0,0 +1,137 @@
+use std::{sync::atomic::Ordering, time::Duration};
+
+use serial_test::serial;
+use tokio::runtime::Runtime;
+
+use ffplayout::player::utils::MediaProbe;
+use ffplayout::utils::control::control_state;
+use ffplayout_testing::{
+    enable_ingest, fake_media, fake_media_with, start_player, stop_player, test_channel, wait_for,
+    FakeTools,
+};
+
+fn fake_tools() -> FakeTools {
+    FakeTools::install(env!("CARGO_BIN_EXE_fake_ffmpeg")).unwrap()
+}
+
+#[test]
+#[serial]
+fn fake_probe() {
+    let tools = fake_tools();
+    let clip = tools.dir().join("clip.mp4");
+    let audio = tools.dir().join("audio.mp3");
+    let broken = tools.dir().join("broken.mp4");
+
+    fake_media(&clip, 12.5).unwrap();
+    fake_media_with(&audio, &[("duration", "3"), ("video", "0")]).unwrap();
+    fake_media_with(&broken, &[("fail", "1")]).unwrap();
+
+    let probe = MediaProbe::new(&clip.to_string_lossy()).unwrap();
+
+    assert_eq!(probe.format.duration, Some("12.500000".to_string()));
+    assert_eq!(probe.video_streams[0].width, Some(1024));
+    assert_eq!(probe.audio_streams.len(), 1);
+
+    let probe = MediaProbe::new(&audio.to_string_lossy()).unwrap();
+
+    assert!(probe.video_streams.is_empty());
+    assert_eq!(probe.audio_streams.len(), 1);
+
+    assert!(MediaProbe::new(&broken.to_string_lossy()).is_err());
+    assert!(MediaProbe::new("/not/existing/clip.mp4").is_err());
+}
+
+#[test]
+#[serial]
+fn clip_transitions() {
+    let tools = fake_tools();
+    let storage = tools.dir().join("storage");
+
+    for i in 1..=3 {
+        fake_media(&storage.join(format!("clip_{i}.mp4")), 1.5).unwrap();
+    }
+
+    let (_, manager) = Runtime::new().unwrap().block_on(test_channel(&storage));
+    let player = start_player(&manager);
+    let played = wait_for(Duration::from_secs(15), || {
+        tools.decoder_inputs().len() >= 3
+    });
+
+    stop_player(&manager, player);
+
+    assert!(played);
+
+    let inputs = tools.decoder_inputs();
+
+    assert!(inputs[0].ends_with("clip_1.mp4"));
+    assert!(inputs[1].ends_with("clip_2.mp4"));
+    assert!(inputs[2].ends_with("clip_3.mp4"));
+    assert!(tools
+        .calls()
+        .iter()
+        .any(|c| c.tool == "ffmpeg" && c.input() == Some("pipe:0")));
+}
+
+#[test]
+#[serial]
+fn control_next() {
+    let tools = fake_tools();
+    let storage = tools.dir().join("storage");
+    let rt = Runtime::new().unwrap();
+
+    fake_media(&storage.join("clip_1.mp4"), 60.0).unwrap();
+    fake_media(&storage.join("clip_2.mp4"), 60.0).unwrap();
+
+    let (pool, manager) = rt.block_on(test_channel(&storage));
+    let player = start_player(&manager);
+    let started = wait_for(Duration::from_secs(5), || {
+        !tools.decoder_inputs().is_empty()
+    });
+    let result = rt.block_on(control_state(&pool, &manager, "next"));
+    let switched = wait_for(Duration::from_secs(5), || tools.decoder_inputs().len() >= 2);
+
+    stop_player(&manager, player);
+
+    assert!(started);
+    assert!(result.is_ok());
+    assert!(switched);
+    assert!(tools.decoder_inputs()[1].ends_with("clip_2.mp4"));
+}
+
+#[test]
+#[serial]
+fn ingest_preemption() {
+    let tools = fake_tools();
+    let storage = tools.dir().join("storage");
+
+    fake_media(&storage.join("clip_1.mp4"), 60.0).unwrap();
+    fake_media(&storage.join("clip_2.mp4"), 60.0).unwrap();
+
+    let (_, manager) = Runtime::new().unwrap().block_on(test_channel(&storage));
+
+    enable_ingest(&manager);
+
+    let player = start_player(&manager);
+    let started = wait_for(Duration::from_secs(5), || {
+        !tools.decoder_inputs().is_empty()
+    });
+
+    tools.start_ingest(2.0).unwrap();
+
+    let live = wait_for(Duration::from_secs(5), || {
+        manager.ingest_is_running.load(Ordering::SeqCst)
+    });
+    let back = wait_for(Duration::from_secs(10), || {
+        !manager.ingest_is_running.load(Ordering::SeqCst) && tools.decoder_inputs().len() >= 2
+    });
+
+    stop_player(&manager, player);
+
+    assert!(started);
+    assert!(live);
+    assert!(back);
+    assert!(tools
+        .calls()
+        .iter()
+        .any(|c| c.args.windows(2).any(|w| w[0] == "-listen" && w[1] == "1")));
+}
@@ -9,7 +9,14 @@ use tokio::runtime::Runtime;
 
 use ffplayout::db::handles;
 use ffplayout::player::output::player;
-use ffplayout::player::{controller::ChannelManager, input::playlist::gen_source, utils::Media};
+use ffplayout::player::{
+    controller::ChannelManager,
+    input::{
+        playlist::gen_source,
+        watchdog::{check_playlist, rollover_in},
+    },
+    utils::{JsonPlaylist, Media},
+};
 use ffplayout::utils::config::OutputMode::Null;
 use ffplayout::utils::config::{PlayoutConfig, ProcessMode::Playlist};
 use ffplayout::utils::time_machine::set_mock_time;
@@ -349,3 +356,46 @@ fn playlist_change_at_six() {
 
     assert_eq!(playlist_date, "2023-02-09");
 }
+
+#[test]
+#[serial]
+fn playlist_watchdog_check() {
+    let (mut config, _) = get_config();
+    let dir = std::env::temp_dir().join("ffplayout_watchdog");
+    let clip = dir.join("clip.mp4");
+    let path = dir.join("2024-10-28.json");
+
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(&clip, b"").unwrap();
+
+    config.playlist.length_sec = Some(3600.0);
+
+    assert_eq!(rollover_in(82800.0, 0.0), 3600.0);
+    assert_eq!(rollover_in(18000.0, 21600.0), 3600.0);
+    assert_eq!(check_playlist(&config, &path).len(), 1);
+
+    let mut playlist = JsonPlaylist::new("2024-10-28".to_string(), 0.0);
+    let mut media = Media::new(0, &clip.to_string_lossy(), false);
+    media.duration = 3600.0;
+    media.out = 3600.0;
+    playlist.program = vec![media, Media::new(1, "/not/existing.mp4", false)];
+
+    std::fs::write(&path, serde_json::to_string(&playlist).unwrap()).unwrap();
+
+    let problems = check_playlist(&config, &path);
+
+    assert_eq!(problems.len(), 1);
+    assert!(problems[0].contains("/not/existing.mp4"));
+
+    playlist.program.truncate(1);
+    playlist.program[0].out = 1800.0;
+
+    std::fs::write(&path, serde_json::to_string(&playlist).unwrap()).unwrap();
+
+    let problems = check_playlist(&config, &path);
+
+    assert_eq!(problems.len(), 1);
+    assert!(problems[0].contains("too short"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
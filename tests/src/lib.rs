@@ -0,0 +1,333 @@
+//! Test support for ffplayout, to run the full channel lifecycle without ffmpeg.
+//!
+//! [`FakeTools`] puts the `fake_ffmpeg` binary from this package as `ffmpeg`, `ffprobe`, `ffplay`
+//! and `streamlink` in front of `PATH`. The fake plays fake media files in real time, records every call
+//! and simulates a live ingest on request. Integration tests get the binary with
+//! `env!("CARGO_BIN_EXE_fake_ffmpeg")`, packagers can use the same helpers to check their builds:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use ffplayout_testing::*;
+//!
+//! let tools = FakeTools::install("target/debug/fake_ffmpeg").unwrap();
+//! let storage = tools.dir().join("storage");
+//!
+//! fake_media(&storage.join("clip_1.mp4"), 1.5).unwrap();
+//! fake_media(&storage.join("clip_2.mp4"), 1.5).unwrap();
+//!
+//! let (_pool, manager) = tokio::runtime::Runtime::new()
+//!     .unwrap()
+//!     .block_on(test_channel(&storage));
+//! let player = start_player(&manager);
+//!
+//! assert!(wait_for(Duration::from_secs(10), || tools.decoder_inputs().len() >= 2));
+//!
+//! stop_player(&manager, player);
+//! ```
+//!
+//! The tools change the environment of the whole process, so tests which use them must not run in parallel.
+
+use std::{
+    env,
+    ffi::OsString,
+    fs, io,
+    net::TcpListener,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread::{self, sleep, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use sqlx::{sqlite::SqlitePoolOptions, Pool, Sqlite};
+
+use ffplayout::{
+    db::handles,
+    player::{controller::ChannelManager, output::player},
+    utils::config::{OutputMode::Null, PlayoutConfig, ProcessMode::Folder},
+    vec_strings,
+};
+
+/// Header from fake media files, see the `fake_ffmpeg` binary.
+pub const FAKE_MEDIA: &str = "ffplayout-fake-media";
+
+const TOOLS: [&str; 4] = ["ffmpeg", "ffprobe", "ffplay", "streamlink"];
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// One call of a fake tool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Call {
+    pub tool: String,
+    pub args: Vec<String>,
+}
+
+impl Call {
+    fn value_after(&self, key: &str) -> Option<&str> {
+        self.args
+            .iter()
+            .position(|a| a == key)
+            .and_then(|i| self.args.get(i + 1))
+            .map(String::as_str)
+    }
+
+    /// First input.
+    pub fn input(&self) -> Option<&str> {
+        self.value_after("-i")
+    }
+
+    /// ffmpeg instance, which reads a clip and writes it to the encoder.
+    pub fn is_decoder(&self) -> bool {
+        self.tool == "ffmpeg"
+            && self.args.last().is_some_and(|a| a == "-")
+            && self.value_after("-f") != Some("null")
+            && !self.args.iter().any(|a| a == "-listen")
+            && !matches!(self.input(), Some("pipe:0" | "-"))
+    }
+}
+
+/// Fake ffmpeg tools in a temporary folder, which is in front of `PATH` until they are dropped.
+#[derive(Debug)]
+pub struct FakeTools {
+    dir: PathBuf,
+    path: Option<OsString>,
+}
+
+impl FakeTools {
+    /// Link the fake binary under the tool names and use them.
+    pub fn install(binary: impl AsRef<Path>) -> io::Result<Self> {
+        let binary = fs::canonicalize(binary)?;
+        let dir = env::temp_dir().join(format!(
+            "ffplayout_fake_{}_{}",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+        let bin = dir.join("bin");
+
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&bin)?;
+
+        for tool in TOOLS {
+            let target = bin.join(format!("{tool}{}", env::consts::EXE_SUFFIX));
+
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(&binary, &target)?;
+
+            #[cfg(not(unix))]
+            fs::copy(&binary, &target)?;
+        }
+
+        let path = env::var_os("PATH");
+        let mut paths = vec![bin];
+        paths.extend(path.iter().flat_map(env::split_paths));
+
+        env::set_var(
+            "PATH",
+            env::join_paths(paths).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?,
+        );
+        env::set_var("FFPLAYOUT_FAKE_LOG", dir.join("calls.log"));
+        env::set_var("FFPLAYOUT_FAKE_INGEST", dir.join("ingest"));
+        env::remove_var("FFPLAYOUT_FAKE_SPEED");
+
+        Ok(Self { dir, path })
+    }
+
+    /// Temporary folder, for media and other test files. It is removed with the tools.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Play the clips faster than real time.
+    pub fn speed(&self, factor: f64) {
+        env::set_var("FFPLAYOUT_FAKE_SPEED", factor.to_string());
+    }
+
+    /// All calls until now, the oldest first.
+    pub fn calls(&self) -> Vec<Call> {
+        fs::read_to_string(self.dir.join("calls.log"))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.split('\u{1f}').map(String::from);
+                let tool = parts.next()?;
+
+                Some(Call {
+                    tool,
+                    args: parts.filter(|a| !a.is_empty()).collect(),
+                })
+            })
+            .collect()
+    }
+
+    /// Inputs of all decoders, so the clips in the order they were played.
+    pub fn decoder_inputs(&self) -> Vec<String> {
+        self.calls()
+            .iter()
+            .filter(|c| c.is_decoder())
+            .filter_map(|c| c.input().map(String::from))
+            .collect()
+    }
+
+    /// Let the ingest server receive a stream for the given seconds.
+    pub fn start_ingest(&self, seconds: f64) -> io::Result<()> {
+        fs::write(self.dir.join("ingest"), seconds.to_string())
+    }
+}
+
+impl Drop for FakeTools {
+    fn drop(&mut self) {
+        match &self.path {
+            Some(path) => env::set_var("PATH", path),
+            None => env::remove_var("PATH"),
+        }
+
+        for var in [
+            "FFPLAYOUT_FAKE_LOG",
+            "FFPLAYOUT_FAKE_INGEST",
+            "FFPLAYOUT_FAKE_SPEED",
+        ] {
+            env::remove_var(var);
+        }
+
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Write a fake media file with audio and video, the folder is created when it not exists.
+pub fn fake_media(path: &Path, duration: f64) -> io::Result<()> {
+    fake_media_with(path, &[("duration", &duration.to_string())])
+}
+
+/// Write a fake media file with custom values: `duration`, `width`, `height`, `fps`,
+/// `video`, `audio` and `fail` (`1` or `0`).
+pub fn fake_media_with(path: &Path, values: &[(&str, &str)]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let lines: Vec<String> = values.iter().map(|(k, v)| format!("{k}={v}")).collect();
+
+    fs::write(path, format!("{FAKE_MEDIA}\n{}\n", lines.join("\n")))
+}
+
+/// Local address with a free port, for an ingest server.
+pub fn free_address() -> String {
+    TcpListener::bind("127.0.0.1:0")
+        .and_then(|l| l.local_addr())
+        .map(|a| format!("{a}"))
+        .unwrap_or_else(|_| "127.0.0.1:1936".to_string())
+}
+
+/// Channel with an in-memory database, which plays the storage folder to the null output.
+pub async fn test_channel(storage: &Path) -> (Pool<Sqlite>, ChannelManager) {
+    let root = storage.parent().unwrap_or(storage).to_path_buf();
+    let public = root.join("public");
+    let playlists = root.join("playlists");
+    let logs = root.join("logs");
+
+    for dir in [storage, &public, &playlists, &logs] {
+        fs::create_dir_all(dir).unwrap();
+    }
+
+    let pool = SqlitePoolOptions::new()
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    handles::db_migrate(&pool).await.unwrap();
+
+    sqlx::query("UPDATE global SET public = $1, logs = $2, playlists = $3, storage = $4")
+        .bind(public.to_string_lossy())
+        .bind(logs.to_string_lossy())
+        .bind(playlists.to_string_lossy())
+        .bind(storage.to_string_lossy())
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE channels SET public = $1, playlists = $2, storage = $3")
+        .bind(public.to_string_lossy())
+        .bind(playlists.to_string_lossy())
+        .bind(storage.to_string_lossy())
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE configurations SET processing_width = 1024, processing_height = 576")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let mut config = PlayoutConfig::new(&pool, 1).await.unwrap();
+    let channel = handles::select_channel(&pool, &1).await.unwrap();
+
+    config.general.skip_validation = true;
+    config.mail.recipient = String::new();
+    config.processing.mode = Folder;
+    config.ingest.enable = false;
+    config.text.add_text = false;
+    config.channel.storage = storage.to_path_buf();
+    config.channel.public = public;
+    config.channel.playlists = playlists;
+    config.channel.logs = logs;
+    config.storage.filler = String::new();
+    config.storage.shuffle = false;
+    config.output.mode = Null;
+    config.output.output_count = 1;
+    config.output.output_filter = None;
+    config.output.output_cmd = Some(vec_strings!["-f", "null", "-"]);
+
+    let manager = ChannelManager::new(Some(pool.clone()), channel, config);
+
+    (pool, manager)
+}
+
+/// Enable the ingest server of the channel, with a free local port.
+pub fn enable_ingest(manager: &ChannelManager) {
+    let mut config = manager.config.lock().unwrap().clone();
+
+    config.ingest.enable = true;
+    config.ingest.input_cmd = Some(vec_strings![
+        "-f",
+        "live_flv",
+        "-listen",
+        "1",
+        "-i",
+        format!("rtmp://{}/live/test", free_address())
+    ]);
+
+    manager.update_config(config);
+}
+
+/// Run the player in a thread.
+pub fn start_player(manager: &ChannelManager) -> JoinHandle<()> {
+    let manager = manager.clone();
+
+    manager.channel.lock().unwrap().active = true;
+
+    thread::spawn(move || {
+        if let Err(e) = player(manager) {
+            eprintln!("Player: {e}");
+        }
+    })
+}
+
+/// Stop the channel and wait until the player is finished.
+pub fn stop_player(manager: &ChannelManager, player: JoinHandle<()>) {
+    manager.channel.lock().unwrap().active = false;
+    manager.stop_all();
+
+    let _ = player.join();
+}
+
+/// Check the condition until it is true, returns false after the timeout.
+pub fn wait_for(timeout: Duration, mut condition: impl FnMut() -> bool) -> bool {
+    let start = Instant::now();
+
+    while start.elapsed() < timeout {
+        if condition() {
+            return true;
+        }
+
+        sleep(Duration::from_millis(50));
+    }
+
+    condition()
+}
@@ -1,15 +1,56 @@
 use sqlx::sqlite::SqlitePoolOptions;
 use tokio::runtime::Runtime;
 
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+};
+
 use chrono::prelude::*;
 use serial_test::serial;
 
-use ffplayout::db::handles;
-use ffplayout::player::{controller::ChannelManager, utils::*};
+use ffplayout::db::{
+    handles,
+    models::{HandoverNote, Node, NodeChannel},
+};
+use ffplayout::player::{
+    controller::ChannelManager,
+    filter::v_drawtext::widget_nodes,
+    output::{
+        output_changed,
+        regions::{break_window, region_playlist_path, window_items, window_length},
+    },
+    utils::{
+        audio_tracks::{audio_sources, master_labels},
+        passthrough::{codec_name, format_mismatch, global_filters, ClipFormat},
+        *,
+    },
+};
+use ffplayout::sse::events::{is_current_date, ChannelEvent, PlaylistAction};
 use ffplayout::utils::{
-    config::{PlayoutConfig, ProcessMode::Playlist},
+    cluster::{needs_move, node_load, node_url, pick_node, proxy_channel, NodeHealth},
+    config::{AudioTrack, PlayoutConfig, ProcessMode::Playlist},
+    config_file::{config_diff, export_config, parse_config, unknown_keys, ConfigDocument},
+    download::{check_url, download_args, parse_progress as download_progress},
+    failover::{
+        backup_output, check_token, config_revision, failover_status, init_failover, is_standby,
+        FailoverRole,
+    },
+    files::{check_quota, dir_size, media_mime, remove_file_or_folder, storage_size},
+    listener::{listen_fds, Listen},
+    object_store::{object_key, CacheEntry, CacheIndex},
+    pre_cache::{cache_path, cache_stats, cached_source, is_fresh},
+    report::report_text,
+    simulate::{simulate, SimulatedKind},
     time_machine::{set_mock_time, time_now},
+    tls::{needs_renewal, parse_pem, RENEW_BEFORE},
+    transcode::{house_args, parse_progress as transcode_progress, preferred_source, proxy_path},
+    trash::{list_trash, purge_trash, restore_trash},
+    widgets::{clock_text, countdown_text, format_remaining, next_program, text_file, ticker_text},
 };
+use ffplayout::vec_strings;
+use ffplayout_core::ServiceError;
 
 async fn prepare_config() -> (PlayoutConfig, ChannelManager) {
     let pool = SqlitePoolOptions::new()
@@ -94,3 +135,913 @@ fn test_delta() {
 
     assert!(delta < 2.0);
 }
+
+#[test]
+#[serial]
+fn object_store_cache() {
+    let (mut config, _) = get_config();
+    config.channel.storage = PathBuf::from("/var/lib/ffplayout/tv-media");
+    config.storage.object_store.prefix = "/media/".to_string();
+
+    let clip = config.channel.storage.join("shows/a.mp4");
+
+    assert_eq!(
+        object_key(&config, &clip),
+        Some("media/shows/a.mp4".to_string())
+    );
+    assert_eq!(object_key(&config, Path::new("/opt/a.mp4")), None);
+
+    config.storage.object_store.prefix = String::new();
+
+    assert_eq!(object_key(&config, &clip), Some("shows/a.mp4".to_string()));
+
+    let mut index = CacheIndex::default();
+
+    for (name, size, used) in [("a.mp4", 300, 30), ("b.mp4", 200, 10), ("c.mp4", 100, 20)] {
+        index
+            .files
+            .insert(PathBuf::from(name), CacheEntry { size, used });
+    }
+
+    let keep = HashSet::from([PathBuf::from("b.mp4")]);
+
+    assert_eq!(index.size(), 600);
+    assert_eq!(index.expired(600, &keep), Vec::<PathBuf>::new());
+    assert_eq!(index.expired(500, &keep), vec![PathBuf::from("c.mp4")]);
+    assert_eq!(
+        index.expired(100, &keep),
+        vec![PathBuf::from("c.mp4"), PathBuf::from("a.mp4")]
+    );
+}
+
+#[test]
+fn region_break_windows() {
+    let (config, _) = get_config();
+    let item = |out: f64, window: Option<&str>, marker: Option<&str>| {
+        let mut media = Media::new(0, "/media/clip.mp4", false);
+        media.out = out;
+        media.duration = out;
+        media.window = window.map(str::to_string);
+        media.break_name = marker.map(str::to_string);
+        media
+    };
+    let list = vec![
+        item(600.0, None, None),
+        item(30.0, Some("prime"), None),
+        item(20.0, Some("prime"), None),
+        item(10.0, Some("prime"), None),
+        item(120.0, None, Some("late")),
+        item(600.0, None, None),
+    ];
+
+    assert_eq!(break_window(&list[1]), Some("prime".to_string()));
+    assert_eq!(break_window(&list[4]), Some("late".to_string()));
+    assert_eq!(window_length(&list, 1), 60.0);
+    assert_eq!(window_length(&list, 2), 30.0);
+    assert_eq!(window_length(&list, 4), 120.0);
+    assert_eq!(window_length(&list, 0), 0.0);
+
+    let mut playlist = JsonPlaylist::new("2024-11-04".into(), 0.0);
+    playlist.program = vec![
+        item(25.0, None, Some("prime")),
+        item(40.0, None, Some("late")),
+        item(25.0, None, Some("prime")),
+        item(25.0, None, Some("prime")),
+    ];
+
+    let items = window_items(&playlist, "prime", 60.0);
+
+    assert_eq!(items.len(), 3);
+    assert_eq!(items[2].out, 10.0);
+    assert!(items.iter().all(|i| i.break_name.is_none()));
+    assert_eq!(window_items(&playlist, "late", 30.0)[0].out, 30.0);
+    assert!(window_items(&playlist, "news", 60.0).is_empty());
+    assert_eq!(
+        region_playlist_path(&config, 2, "2024-11-04"),
+        config
+            .channel
+            .playlists
+            .join("regions/2/2024/11/2024-11-04.json")
+    );
+}
+
+#[test]
+fn config_export_import() {
+    let (config, _) = get_config();
+    let text = export_config(&config).unwrap();
+    let mut doc = parse_config(&text).unwrap();
+    let current = serde_json::to_value(ConfigDocument::new(&config)).unwrap();
+
+    // comes from the channel, not from the config
+    doc.playout.storage.shared_storage = config.storage.shared_storage;
+
+    assert!(config_diff(&current, &serde_json::to_value(&doc).unwrap()).is_empty());
+    assert!(parse_config(&format!("{text}\n[playout.extra]\nkey = 1\n")).is_err());
+    assert!(parse_config("[playout]\n").is_err());
+
+    let old = serde_json::json!({"a": {"b": 1, "c": [1, 2]}, "d": "x"});
+    let new = serde_json::json!({"a": {"b": 2, "c": [1, 2], "e": true}});
+    let changes = config_diff(&old, &new);
+
+    assert_eq!(changes.len(), 3);
+    assert_eq!(changes[0].key, "a.b");
+    assert_eq!(changes[0].new, Some(serde_json::json!(2)));
+    assert_eq!(changes[1].key, "a.e");
+    assert_eq!(changes[1].old, None);
+    assert_eq!(changes[2].key, "d");
+    assert_eq!(changes[2].new, None);
+    assert_eq!(unknown_keys(&new, &old), vec!["a.e".to_string()]);
+}
+
+#[test]
+fn simulate_timeline() {
+    let (mut config, _) = get_config();
+    let dir = std::env::temp_dir().join("ffplayout_simulate");
+    let clip = dir.join("clip.mp4");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(&clip, "clip").unwrap();
+
+    config.playlist.start_sec = Some(21600.0);
+    config.playlist.length = "01:00:00".into();
+    config.playlist.length_sec = Some(3600.0);
+    config.playlist.infinit = false;
+    config.processing.transition_frames = 0;
+
+    let item = |src: &str, out: f64| {
+        let mut media = Media::new(0, src, false);
+        media.out = out;
+        media.duration = out;
+        media
+    };
+    let clip = clip.to_string_lossy();
+    let mut playlist = JsonPlaylist::new("2024-03-04".into(), 21600.0);
+    playlist.program = vec![
+        item(&clip, 1200.0),
+        item("/missing/b.mp4", 1200.0),
+        item(&clip, 0.5),
+        item(&clip, 1500.0),
+        item(&clip, 600.0),
+    ];
+
+    let sim = simulate(&config, "2024-03-04", playlist.clone()).unwrap();
+    let kinds: Vec<SimulatedKind> = sim.items.iter().map(|i| i.kind).collect();
+
+    assert_eq!(
+        kinds,
+        vec![
+            SimulatedKind::Clip,
+            SimulatedKind::Missing,
+            SimulatedKind::Skipped,
+            SimulatedKind::Clip,
+            SimulatedKind::Skipped
+        ]
+    );
+    assert!(sim.items[0].start.starts_with("2024-03-04 06:00:00.000"));
+    assert!(sim.items[1].start.starts_with("2024-03-04 06:20:00.000"));
+    assert!(sim.items[3].trimmed);
+    assert_eq!(sim.items[3].out, 1200.0);
+    assert!(sim.items[3].end.starts_with("2024-03-04 07:00:00.000"));
+    assert_eq!(sim.length, 3600.0);
+
+    // too short, filler pads to the end of the day
+    playlist.program.truncate(2);
+
+    let sim = simulate(&config, "2024-03-04", playlist).unwrap();
+    let last = sim.items.last().unwrap();
+
+    assert_eq!(last.kind, SimulatedKind::Filler);
+    assert_eq!(last.length, 1200.0);
+    assert!(last.start.starts_with("2024-03-04 06:40:00.000"));
+    assert_eq!(sim.warnings.len(), 2);
+
+    assert!(simulate(&config, "2024-13-04", JsonPlaylist::new("".into(), 0.0)).is_err());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+#[serial]
+fn pre_cache_source() {
+    let (mut config, _) = get_config();
+    let dir = std::env::temp_dir().join("ffplayout_pre_cache");
+    let source = dir.join("nfs/shows/a.mp4");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(source.parent().unwrap()).unwrap();
+    std::fs::write(&source, "clip").unwrap();
+
+    config.storage.pre_cache.enable = true;
+    config.storage.pre_cache.path = dir.join("cache").to_string_lossy().to_string();
+
+    let id = config.general.channel_id;
+    let cache = dir.join("cache").join(id.to_string());
+    let copy = cache_path(&cache, &source);
+    let stats = cache_stats(id);
+
+    assert!(copy.starts_with(&cache));
+    assert!(copy.ends_with("nfs/shows/a.mp4"));
+
+    // not copied yet, plays from the source
+    assert_eq!(
+        cached_source(&config, &source.to_string_lossy()),
+        source.to_string_lossy()
+    );
+
+    std::fs::create_dir_all(copy.parent().unwrap()).unwrap();
+    std::fs::copy(&source, &copy).unwrap();
+
+    assert!(is_fresh(&source, &copy));
+    assert_eq!(
+        cached_source(&config, &source.to_string_lossy()),
+        copy.to_string_lossy()
+    );
+
+    // the source changed, the copy is old
+    std::fs::write(&source, "new clip").unwrap();
+
+    assert!(!is_fresh(&source, &copy));
+    assert_eq!(cache_stats(id).hits, stats.hits + 1);
+    assert_eq!(cache_stats(id).misses, stats.misses + 1);
+
+    // urls and missing files are not counted
+    assert_eq!(
+        cached_source(&config, "https://example.org/live.m3u8"),
+        "https://example.org/live.m3u8"
+    );
+    assert_eq!(cache_stats(id).misses, stats.misses + 1);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+#[serial]
+fn overlay_widgets() {
+    assert_eq!(format_remaining(65.2), "01:06");
+    assert_eq!(format_remaining(-3.0), "00:00");
+    assert_eq!(format_remaining(3725.0), "1:02:05");
+
+    let mut list = vec![];
+
+    for (begin, title, category) in [
+        (3600.0, "Show", ""),
+        (5400.0, "Spot", "advertisement"),
+        (5430.0, "News", ""),
+    ] {
+        let mut item = Media::new(list.len(), "", false);
+        item.begin = Some(begin);
+        item.title = Some(title.to_string());
+        item.category = category.to_string();
+
+        list.push(item);
+    }
+
+    let skip = vec!["Advertisement".to_string()];
+
+    assert_eq!(
+        next_program(&list, 0, 5000.0, 0.0, &skip),
+        Some((430.0, "News".to_string()))
+    );
+    assert_eq!(
+        next_program(&list, 0, 5000.0, 0.0, &[]),
+        Some((400.0, "Spot".to_string()))
+    );
+    assert_eq!(next_program(&list, 2, 5000.0, 0.0, &skip), None);
+    // after midnight the next program is at the begin of the day
+    assert_eq!(
+        next_program(&list, 0, 86000.0, 0.0, &skip),
+        Some((5830.0, "News".to_string()))
+    );
+    assert_eq!(
+        countdown_text("Next: {title} in {time}", 430.0, "News"),
+        "Next: News in 07:10"
+    );
+
+    let rss = r#"<?xml version="1.0"?><rss><channel><title>Feed</title>
+        <item><title>Rain &amp; wind</title></item>
+        <item><title><![CDATA[Road   closed]]></title></item>
+        </channel></rss>"#;
+
+    assert_eq!(ticker_text(rss, " +++ "), "Rain & wind +++ Road closed");
+    assert_eq!(
+        ticker_text(r#"["one", {"text": "two"}, {"title": "three"}, 4]"#, " | "),
+        "one | two | three"
+    );
+    assert_eq!(ticker_text(r#"{"items": ["a", "b"]}"#, "-"), "a-b");
+    assert_eq!(ticker_text("first\n\n second \n", " "), "first second");
+
+    let time = Local.with_ymd_and_hms(2024, 10, 27, 8, 5, 9).unwrap();
+
+    assert_eq!(clock_text(&time, "%H:%M"), "08:05");
+    assert_eq!(clock_text(&time, "%Q"), "08:05:09");
+
+    let (mut config, _) = get_config();
+    config.text.font_path = String::new();
+    config.text.widgets.clock = true;
+    config.text.widgets.clock_style = "x=10:y=10".to_string();
+    config.text.widgets.ticker = true;
+    config.text.widgets.ticker_speed = 100.0;
+    config.text.widgets.ticker_style = String::new();
+
+    let clock_file = text_file(config.general.channel_id, "clock");
+    let ticker_file = text_file(config.general.channel_id, "ticker");
+
+    // ticker without url is off
+    assert_eq!(
+        widget_nodes(&config),
+        vec![format!(
+            "drawtext=textfile='{}':reload=1:expansion=none:x=10:y=10",
+            clock_file.display()
+        )]
+    );
+
+    config.text.widgets.ticker_url = "https://example.org/news.rss".to_string();
+
+    assert_eq!(
+        widget_nodes(&config)[1],
+        format!(
+            "drawtext=textfile='{}':reload=1:expansion=none:x='w-mod(t*100,w+tw)'",
+            ticker_file.display()
+        )
+    );
+}
+
+#[test]
+fn multi_audio_tracks() {
+    let mut config = PlayoutConfig::default();
+    config.processing.audio_tracks = 2;
+    config.processing.audio_track_index = -1;
+
+    let mut node = Media::new(0, "./assets/media_mix/no_audio.mp4", false);
+
+    assert_eq!(audio_sources(&config, &node), [(0, None), (1, None)]);
+
+    node.audio_tracks = Some(vec![1, -1]);
+
+    assert_eq!(audio_sources(&config, &node), [(0, None), (1, None)]);
+
+    node.audio = "./assets/media_mix/audio.mp3".to_string();
+
+    assert_eq!(audio_sources(&config, &node), [(0, Some(0)), (1, Some(1))]);
+
+    config.processing.audio_track_index = 1;
+
+    assert_eq!(audio_sources(&config, &node), [(1, Some(1))]);
+
+    let tracks = vec![
+        AudioTrack {
+            language: "eng".to_string(),
+            name: "English".to_string(),
+            source: 0,
+        },
+        AudioTrack {
+            language: "eng".to_string(),
+            name: "Audio \"Description\"".to_string(),
+            source: 1,
+        },
+    ];
+    let master = "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"group_audio\",NAME=\"audio_0\",DEFAULT=YES,URI=\"stream_0.m3u8\"\n#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"group_audio\",NAME=\"audio_1\",DEFAULT=NO,URI=\"stream_1.m3u8\"\n#EXT-X-STREAM-INF:BANDWIDTH=1400000,AUDIO=\"group_audio\"\nstream_2.m3u8\n";
+    let labeled = master_labels(master, &tracks);
+
+    assert!(labeled.contains(
+        "GROUP-ID=\"group_audio\",NAME=\"English\",DEFAULT=YES,URI=\"stream_0.m3u8\",LANGUAGE=\"eng\""
+    ));
+    assert!(labeled.contains("NAME=\"Audio Description\",DEFAULT=NO"));
+    assert!(labeled.ends_with("AUDIO=\"group_audio\"\nstream_2.m3u8\n"));
+    assert_eq!(master_labels(&labeled, &tracks), labeled);
+    assert_eq!(master_labels(master, &[]), master);
+}
+
+#[test]
+#[serial]
+fn failover_roles() {
+    let timeout = std::time::Duration::from_secs(5);
+
+    // backup waits, as long as the primary answers
+    assert!(!backup_output(
+        false,
+        std::time::Duration::from_secs(1),
+        true,
+        timeout
+    ));
+    // primary silent for the timeout
+    assert!(backup_output(false, timeout, false, timeout));
+    // backup stays active, until the primary outputs again
+    assert!(backup_output(
+        true,
+        std::time::Duration::ZERO,
+        false,
+        timeout
+    ));
+    assert!(!backup_output(
+        true,
+        std::time::Duration::ZERO,
+        true,
+        timeout
+    ));
+
+    assert_eq!("backup".parse::<FailoverRole>(), Ok(FailoverRole::Backup));
+    assert!("master".parse::<FailoverRole>().is_err());
+
+    init_failover(
+        Some(FailoverRole::Backup),
+        Some("http://10.0.0.1:8787/".to_string()),
+        Some("secret".to_string()),
+        Some(1),
+    );
+
+    let status = failover_status();
+
+    assert!(is_standby());
+    assert_eq!(status.peer.url, "http://10.0.0.1:8787");
+    assert_eq!(status.timeout, 2);
+    assert!(check_token(Some("Bearer secret")).is_ok());
+    assert!(check_token(Some("Bearer wrong")).is_err());
+    assert!(check_token(None).is_err());
+
+    // without token no failover
+    init_failover(
+        Some(FailoverRole::Backup),
+        Some("http://10.0.0.1:8787".to_string()),
+        None,
+        None,
+    );
+
+    assert!(!is_standby());
+    assert!(check_token(Some("Bearer secret")).is_err());
+
+    let (config, _) = get_config();
+
+    assert_eq!(config_revision(&config), config_revision(&config.clone()));
+}
+
+#[test]
+#[serial]
+fn storage_quota_check() {
+    let (mut config, _) = get_config();
+    let storage = std::env::temp_dir().join("ffplayout_quota");
+
+    std::fs::create_dir_all(storage.join("sub")).unwrap();
+    std::fs::write(storage.join("sub/file.bin"), vec![0u8; 100]).unwrap();
+
+    config.channel.storage = storage.clone();
+    config.channel.storage_quota = 150;
+
+    let rt = Runtime::new().unwrap();
+
+    assert_eq!(dir_size(&storage), 100);
+    assert_eq!(rt.block_on(check_quota(&config, 10)).unwrap(), 100);
+
+    // the trash is not counted
+    std::fs::create_dir_all(storage.join(".trash/item")).unwrap();
+    std::fs::write(storage.join(".trash/item/old.bin"), vec![0u8; 100]).unwrap();
+
+    assert_eq!(storage_size(&storage), 100);
+    assert_eq!(rt.block_on(check_quota(&config, 10)).unwrap(), 100);
+    assert!(matches!(
+        rt.block_on(check_quota(&config, 100)),
+        Err(ServiceError::InsufficientStorage(_))
+    ));
+
+    config.channel.storage_quota = 0;
+
+    assert!(rt.block_on(check_quota(&config, 100)).is_ok());
+
+    std::fs::remove_dir_all(storage).unwrap();
+}
+
+#[test]
+#[serial]
+fn trash_remove_restore() {
+    let (mut config, _) = get_config();
+    let storage = std::env::temp_dir().join("ffplayout_trash");
+
+    std::fs::create_dir_all(storage.join("folder")).unwrap();
+    std::fs::write(storage.join("folder/clip.mp4"), vec![0u8; 10]).unwrap();
+
+    config.channel.storage = storage.clone();
+    config.storage.trash_retention = 30;
+
+    let rt = Runtime::new().unwrap();
+
+    rt.block_on(remove_file_or_folder(
+        &config,
+        "folder/clip.mp4",
+        false,
+        false,
+    ))
+    .unwrap();
+    assert!(!storage.join("folder/clip.mp4").exists());
+
+    let items = rt.block_on(list_trash(&config)).unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].source, "folder/clip.mp4");
+    assert_eq!(items[0].size, 10);
+
+    rt.block_on(restore_trash(&config, &items[0].id)).unwrap();
+    assert!(storage.join("folder/clip.mp4").is_file());
+    assert!(rt.block_on(list_trash(&config)).unwrap().is_empty());
+
+    rt.block_on(remove_file_or_folder(&config, "folder", true, false))
+        .unwrap();
+    assert_eq!(rt.block_on(list_trash(&config)).unwrap().len(), 1);
+
+    rt.block_on(purge_trash(&config, None)).unwrap();
+    assert!(rt.block_on(list_trash(&config)).unwrap().is_empty());
+
+    std::fs::write(storage.join("other.mp4"), b"").unwrap();
+    rt.block_on(remove_file_or_folder(&config, "other.mp4", false, true))
+        .unwrap();
+    assert!(rt.block_on(list_trash(&config)).unwrap().is_empty());
+
+    std::fs::remove_dir_all(storage).unwrap();
+}
+
+#[test]
+fn handover_notes_report() {
+    let rt = Runtime::new().unwrap();
+    let pool = rt.block_on(async {
+        let pool = SqlitePoolOptions::new()
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        handles::db_migrate(&pool).await.unwrap();
+
+        pool
+    });
+
+    for (timestamp, note) in [
+        ("2024-10-27 22:10:00", "Ingest dropouts, provider informed."),
+        ("2024-10-27 06:05:00", "Morning show started late."),
+        ("2024-10-28 06:00:00", "Next day."),
+    ] {
+        let entry = HandoverNote {
+            channel_id: 1,
+            user_id: 1,
+            note: note.to_string(),
+            event: "22:00:00 News".to_string(),
+            timestamp: timestamp.to_string(),
+            ..Default::default()
+        };
+
+        rt.block_on(handles::insert_handover(&pool, entry)).unwrap();
+    }
+
+    let notes = rt
+        .block_on(handles::select_handover(
+            &pool,
+            1,
+            "2024-10-27 00:00:00",
+            "2024-10-27 23:59:59",
+        ))
+        .unwrap();
+
+    assert_eq!(notes.len(), 2);
+    assert_eq!(notes[0].note, "Morning show started late.");
+
+    let date = NaiveDate::from_ymd_opt(2024, 10, 27).unwrap();
+    let text = report_text("Channel 1", &date, &notes);
+
+    assert!(text.starts_with("Daily report for Channel 1, 2024-10-27"));
+    assert!(text.contains("[22:10:00]"));
+    assert!(text.contains("on air: 22:00:00 News"));
+    assert!(report_text("Channel 1", &date, &[]).contains("- none -"));
+}
+
+#[test]
+#[serial]
+fn output_reload() {
+    let (config, manager) = get_config();
+    let mut new_config = config.clone();
+
+    assert!(!output_changed(&config, &new_config));
+
+    new_config.output.output_cmd = Some(vec_strings!["-f", "null", "-"]);
+    assert!(output_changed(&config, &new_config));
+
+    // only a running channel gets reloaded
+    manager.update_config(new_config.clone());
+    assert!(!manager.reload_output.load(Ordering::SeqCst));
+
+    manager.is_alive.store(true, Ordering::SeqCst);
+    new_config.text.style = "fontsize=32".to_string();
+    manager.update_config(new_config);
+    assert!(manager.reload_output.load(Ordering::SeqCst));
+
+    manager.is_alive.store(false, Ordering::SeqCst);
+}
+
+#[test]
+#[serial]
+fn transcode_proxies() {
+    let (mut config, _) = get_config();
+    let dir = std::env::temp_dir().join("ffplayout_proxies");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("clips")).unwrap();
+    std::fs::create_dir_all(dir.join(".proxies/clips")).unwrap();
+
+    config.channel.storage = dir.clone();
+
+    let source = dir.join("clips/news.mov");
+    let proxy = dir.join(".proxies/clips/news.mov.mp4");
+    let src = source.to_string_lossy().to_string();
+
+    assert_eq!(proxy_path(&config, &source), Some(proxy.clone()));
+    assert_eq!(proxy_path(&config, &proxy), None);
+    assert_eq!(proxy_path(&config, Path::new("/other/news.mov")), None);
+
+    std::fs::write(&source, "").unwrap();
+    assert_eq!(preferred_source(&config, &src), None);
+
+    std::fs::write(&proxy, "").unwrap();
+    assert_eq!(
+        preferred_source(&config, &src),
+        Some(proxy.to_string_lossy().to_string())
+    );
+
+    // replaced source plays directly
+    std::fs::File::options()
+        .write(true)
+        .open(&source)
+        .unwrap()
+        .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(10))
+        .unwrap();
+    assert_eq!(preferred_source(&config, &src), None);
+
+    assert_eq!(transcode_progress("out_time_us=30000000", 60.0), Some(50.0));
+    assert_eq!(transcode_progress("out_time_us=30000000", 0.0), None);
+    assert_eq!(transcode_progress("progress=continue", 60.0), None);
+
+    assert!(house_args(&config).contains(&"loudnorm=I=-23:TP=-1:LRA=11".to_string()));
+
+    config.processing.house_format.loudness = 0.0;
+    assert!(!house_args(&config).contains(&"-af".to_string()));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+#[serial]
+fn passthrough_house_format() {
+    let (mut config, _) = get_config();
+    config.processing.passthrough = true;
+    config.processing.audio_channels = 2;
+
+    assert_eq!(codec_name("libx264"), "h264");
+    assert_eq!(codec_name("h264_nvenc"), "h264");
+    assert_eq!(codec_name("libx265"), "hevc");
+    assert_eq!(codec_name("aac"), "aac");
+    assert_eq!(codec_name("libopus"), "opus");
+
+    let format = ClipFormat {
+        video_codec: Some("h264".to_string()),
+        width: config.processing.width,
+        height: config.processing.height,
+        fps: config.processing.fps,
+        pix_fmt: "yuv420p".to_string(),
+        audio_codec: Some("aac".to_string()),
+        sample_rate: 48000,
+        channels: 2,
+    };
+
+    assert!(format_mismatch(&config, &format).is_empty());
+
+    let other = ClipFormat {
+        video_codec: Some("prores".to_string()),
+        width: 1920,
+        height: 1080,
+        sample_rate: 44100,
+        ..format.clone()
+    };
+    let mismatch = format_mismatch(&config, &other);
+
+    assert!(mismatch.contains(&"video codec prores".to_string()));
+    assert!(mismatch.contains(&"size 1920x1080".to_string()));
+    assert!(mismatch.contains(&"sample rate 44100".to_string()));
+
+    let silent = ClipFormat {
+        audio_codec: None,
+        ..format.clone()
+    };
+
+    assert_eq!(
+        format_mismatch(&config, &silent),
+        vec!["no audio".to_string()]
+    );
+
+    // radio mode checks only the audio
+    let radio = ClipFormat {
+        video_codec: None,
+        ..format
+    };
+
+    config.processing.audio_only = true;
+    assert!(format_mismatch(&config, &radio).is_empty());
+
+    config.processing.add_logo = false;
+    config.processing.logos.clear();
+    config.processing.custom_filter.clear();
+    config.processing.volume = 1.0;
+    config.processing.audio_tracks = 1;
+    config.processing.subtitle_burn = false;
+    config.processing.transition = Default::default();
+    config.text.add_text = false;
+    assert!(!global_filters(&config));
+
+    config.processing.add_logo = true;
+    assert!(global_filters(&config));
+}
+
+#[test]
+fn cluster_scheduling() {
+    let node = |id: i32, drained: bool| Node {
+        id,
+        name: format!("node-{id}"),
+        url: format!("http://10.0.0.{id}:8787"),
+        drained,
+    };
+    let nodes = vec![node(1, false), node(2, false), node(3, true)];
+    let assigned = vec![
+        NodeChannel {
+            channel_id: 1,
+            node_id: 1,
+        },
+        NodeChannel {
+            channel_id: 2,
+            node_id: 1,
+        },
+        NodeChannel {
+            channel_id: 3,
+            node_id: 2,
+        },
+    ];
+    let load = node_load(&assigned);
+    let online = HashSet::from([1, 2, 3]);
+
+    assert_eq!(load.get(&1), Some(&2));
+    // fewest channels, drained nodes get none
+    assert_eq!(pick_node(&nodes, &load, &online, None), Some(2));
+    assert_eq!(pick_node(&nodes, &load, &online, Some(2)), Some(1));
+    assert_eq!(pick_node(&nodes, &load, &HashSet::from([3]), None), None);
+    // same load, the older node wins
+    assert_eq!(pick_node(&nodes, &HashMap::new(), &online, None), Some(1));
+
+    assert!(needs_move(&nodes[2], None));
+    assert!(!needs_move(&nodes[0], None));
+
+    let failed = NodeHealth {
+        failed_since: std::time::Instant::now().checked_sub(std::time::Duration::from_secs(31)),
+        ..Default::default()
+    };
+
+    assert!(needs_move(&nodes[0], Some(&failed)));
+    assert!(!needs_move(
+        &nodes[0],
+        Some(&NodeHealth {
+            failed_since: Some(std::time::Instant::now()),
+            ..Default::default()
+        })
+    ));
+
+    assert_eq!(proxy_channel("/api/control/12/process/"), Some(12));
+    assert_eq!(proxy_channel("/api/system/3"), Some(3));
+    assert_eq!(proxy_channel("/api/log/1"), Some(1));
+    assert_eq!(proxy_channel("/api/playlist/1"), None);
+    assert_eq!(proxy_channel("/api/control/1x"), None);
+
+    assert_eq!(
+        node_url(" http://10.0.0.2:8787/ ").unwrap(),
+        "http://10.0.0.2:8787"
+    );
+    assert!(node_url("10.0.0.2:8787").is_err());
+}
+
+#[test]
+fn channel_event_dates() {
+    let today = NaiveDate::from_ymd_opt(2024, 10, 30).unwrap();
+
+    assert!(is_current_date("2024-10-30", "2024-10-30", today));
+    assert!(is_current_date("2024-10-31", "2024-10-30", today));
+    assert!(is_current_date("2024-10-29", "2024-10-29", today));
+    assert!(!is_current_date("2024-10-29", "2024-10-30", today));
+    assert!(!is_current_date("2024-11-01", "2024-10-30", today));
+
+    let event = ChannelEvent::Playlist {
+        date: "2024-10-30".to_string(),
+        action: PlaylistAction::Generated,
+    };
+
+    assert_eq!(event.name(), "playlist");
+    assert_eq!(
+        serde_json::to_string(&event).unwrap(),
+        r#"{"event":"playlist","date":"2024-10-30","action":"generated"}"#
+    );
+    assert_eq!(
+        serde_json::to_string(&ChannelEvent::Process { running: true }).unwrap(),
+        r#"{"event":"process","running":true}"#
+    );
+}
+
+#[test]
+fn tls_renewal() {
+    let now = std::time::SystemTime::now();
+    let day = std::time::Duration::from_secs(86400);
+
+    assert!(needs_renewal(None, now));
+    assert!(!needs_renewal(Some(now - day), now));
+    assert!(!needs_renewal(Some(now - day * 59), now));
+    assert!(needs_renewal(Some(now - day * 60), now));
+    assert_eq!(RENEW_BEFORE, 30);
+    assert!(!needs_renewal(Some(now + day), now));
+
+    assert!(parse_pem(b"", b"").is_err());
+    assert!(parse_pem(b"no pem data", b"no pem data").is_err());
+}
+
+#[test]
+fn listen_address() {
+    assert_eq!(
+        Listen::parse("127.0.0.1:8787").unwrap(),
+        Listen::Tcp("127.0.0.1".to_string(), 8787)
+    );
+    assert_eq!(
+        Listen::parse("[::1]:8787").unwrap(),
+        Listen::Tcp("::1".to_string(), 8787)
+    );
+    assert_eq!(
+        Listen::parse("unix:/run/ffplayout/api.sock").unwrap(),
+        Listen::Unix(PathBuf::from("/run/ffplayout/api.sock"))
+    );
+    assert_eq!(
+        Listen::parse("/run/ffplayout/api.sock").unwrap(),
+        Listen::Unix(PathBuf::from("/run/ffplayout/api.sock"))
+    );
+    assert_eq!(Listen::parse("systemd").unwrap(), Listen::Systemd);
+    assert_eq!(
+        Listen::parse("[::1]:8787").unwrap().to_string(),
+        "[::1]:8787"
+    );
+
+    for invalid in [
+        "",
+        "8787",
+        "127.0.0.1",
+        ":8787",
+        "127.0.0.1:",
+        "127.0.0.1:99999",
+        "::1:8787",
+        "[::1:8787",
+        "unix:",
+    ] {
+        assert!(Listen::parse(invalid).is_err(), "{invalid}");
+    }
+
+    assert_eq!(listen_fds(Some("42"), Some("2"), 42), 2);
+    assert_eq!(listen_fds(Some("42"), Some("2"), 43), 0);
+    assert_eq!(listen_fds(None, Some("2"), 42), 0);
+    assert_eq!(listen_fds(Some("42"), None, 42), 0);
+}
+
+#[test]
+fn preview_mime_types() {
+    assert_eq!(media_mime(Path::new("clip.mp4")), Some("video/mp4"));
+    assert_eq!(
+        media_mime(Path::new("/storage/Clip.MKV")),
+        Some("video/x-matroska")
+    );
+    assert_eq!(media_mime(Path::new("segment.ts")), Some("video/mp2t"));
+    assert_eq!(media_mime(Path::new("song.mp3")), Some("audio/mpeg"));
+    assert_eq!(media_mime(Path::new("notes.txt")), None);
+    assert_eq!(media_mime(Path::new("no_extension")), None);
+}
+
+#[test]
+fn ytdlp_downloads() {
+    let (mut config, _) = get_config();
+    let dir = Path::new("/storage/downloads");
+
+    config.processing.height = 720;
+    config.processing.fps = 25.0;
+
+    let args = download_args(&config, "", dir);
+
+    assert!(args.contains(&"res:720,fps:25".to_string()));
+    assert!(args.contains(&"/storage/downloads".to_string()));
+    assert_eq!(args.last().map(String::as_str), Some("mp4"));
+
+    let args = download_args(&config, "bv*[height<=1080]+ba", dir);
+
+    assert!(args.contains(&"bv*[height<=1080]+ba".to_string()));
+    assert!(!args.contains(&"-S".to_string()));
+
+    config.processing.audio_only = true;
+    assert!(download_args(&config, "", dir).contains(&"-x".to_string()));
+
+    assert_eq!(download_progress("[progress]  42.3%"), Some(42.3));
+    assert_eq!(download_progress("[progress] 100.0%"), Some(100.0));
+    assert_eq!(download_progress("[progress]    N/A"), None);
+    assert_eq!(download_progress("/storage/downloads/clip [abc].mp4"), None);
+
+    assert!(check_url("https://www.youtube.com/watch?v=abc").is_ok());
+    assert!(check_url("http://example.org/clip.mp4").is_ok());
+    assert!(check_url("file:///etc/passwd").is_err());
+    assert!(check_url("--exec rm").is_err());
+}